@@ -8,10 +8,40 @@ use crate::{
 use anyhow::{anyhow, Result};
 use std::result::Result as StdResult;
 
-/// Max size of a single GRPC message (4 MB)
+/// Max size of a single GRPC message (4 MB), used unless a larger size is negotiated
 pub const GRPC_MAX_SIZE_BYTES: usize = 1024 * 1024 * 4;
 
-const GRPC_EFFECTIVE_MAX_SIZE: usize = GRPC_MAX_SIZE_BYTES - 1024; // Minus one KB
+/// The smallest max message size either side may negotiate (64 KB)
+pub const GRPC_MIN_SIZE_BYTES: usize = 1024 * 64;
+
+/// Room left in each message for the fields which aren't chunked (one KB)
+const GRPC_MESSAGE_OVERHEAD: usize = 1024;
+
+const GRPC_EFFECTIVE_MAX_SIZE: usize = GRPC_MAX_SIZE_BYTES - GRPC_MESSAGE_OVERHEAD;
+
+/// The most messages of the negotiated max size a single query may be chunked into
+pub const MAX_CHUNKS_PER_QUERY: usize = 256;
+
+/// The largest query payload either side reassembles, given the negotiated max message size.
+///
+/// Both sides derive it from the same negotiated size, so a sender can tell which queries the
+/// receiver will refuse.
+pub fn max_payload_size(max_message_size: usize) -> usize {
+	max_message_size.saturating_mul(MAX_CHUNKS_PER_QUERY)
+}
+
+/// Agree on the max size of a query protocol message, given our own max size and the max size
+/// the other side sent during the `SetConfiguration` handshake.
+///
+/// The other side sends zero if it predates negotiation, in which case it can only be assumed to
+/// accept messages of the default size.
+pub fn negotiate_max_size(ours: usize, theirs: u64) -> usize {
+	let theirs = match theirs {
+		0 => GRPC_MAX_SIZE_BYTES,
+		theirs => usize::try_from(theirs).unwrap_or(usize::MAX),
+	};
+	ours.min(theirs).max(GRPC_MIN_SIZE_BYTES)
+}
 
 #[derive(Clone, Debug)]
 enum DrainedString {
@@ -52,55 +82,73 @@ fn drain_at_most_n_bytes(mut buf: String, max: usize) -> DrainedString {
 /// true => all chunkable fields have been consumed
 /// false => there is still data to consume
 fn all_chunkable_data_consumed(msg: &PluginQuery) -> bool {
-	msg.key.is_empty() && msg.output.is_empty() && msg.concern.is_empty()
+	msg.key.is_empty() && msg.output.is_empty() && msg.concern.is_empty() && msg.fragment.is_empty()
 }
 
-pub fn chunk_with_size(msg: PluginQuery, max_est_size: usize) -> Result<Vec<PluginQuery>> {
-	// in_progress_state - the state the PluginQuery is in for all queries in the resulting Vec,
-	// EXCEPT the last one
-	//
-	// completion_state - the state the PluginQuery is in if it is the last chunked message
-	let (in_progress_state, completion_state) = match msg.state() {
-		// if the message gets chunked, then it must either be a reply or submission that is in process
-		QueryState::Unspecified => return Err(anyhow!("msg in Unspecified query state")),
-		QueryState::SubmitInProgress | QueryState::SubmitComplete => {
-			(QueryState::SubmitInProgress, QueryState::SubmitComplete)
-		}
-		QueryState::ReplyInProgress | QueryState::ReplyComplete => {
-			(QueryState::ReplyInProgress, QueryState::ReplyComplete)
-		}
-	};
+/// Splits a `PluginQuery` into chunks small enough to send, one chunk at a time.
+///
+/// Chunks are only produced as they're taken, so a sender which sends each chunk before taking the
+/// next one (e.g. over a bounded channel) never holds more than the remainder of the original
+/// message and the chunks in flight, rather than a second copy of the whole message.
+#[derive(Debug)]
+pub struct QueryChunks {
+	base: PluginQuery,
+	max_est_size: usize,
+	/// the state the PluginQuery is in for all chunks EXCEPT the last one
+	in_progress_state: QueryState,
+	/// the state the PluginQuery is in if it is the last chunk
+	completion_state: QueryState,
+	done: bool,
+}
 
-	let mut out: Vec<PluginQuery> = vec![];
-	let mut base: PluginQuery = msg;
+impl QueryChunks {
+	pub fn new(msg: PluginQuery, max_est_size: usize) -> Result<QueryChunks> {
+		let (in_progress_state, completion_state) = match msg.state() {
+			// if the message gets chunked, then it must either be a reply or submission that is in process
+			QueryState::Unspecified => return Err(anyhow!("msg in Unspecified query state")),
+			QueryState::SubmitInProgress | QueryState::SubmitComplete => {
+				(QueryState::SubmitInProgress, QueryState::SubmitComplete)
+			}
+			QueryState::ReplyInProgress | QueryState::ReplyComplete => {
+				(QueryState::ReplyInProgress, QueryState::ReplyComplete)
+			}
+		};
 
-	// Track whether we did anything on each iteration to avoid infinite loop
-	let mut made_progress = true;
-	while !all_chunkable_data_consumed(&base) {
-		if !made_progress {
-			return Err(anyhow!("Message could not be chunked"));
-		}
-		made_progress = false;
+		Ok(QueryChunks {
+			base: msg,
+			max_est_size,
+			in_progress_state,
+			completion_state,
+			done: false,
+		})
+	}
 
-		// For this loop, we want to take at most MAX_SIZE bytes because that's
+	fn next_chunk(&mut self) -> Result<PluginQuery> {
+		// For this chunk, we want to take at most `max_est_size` bytes because that's
 		// all that can fit in a PluginQuery
-		let mut remaining = max_est_size;
+		let mut remaining = self.max_est_size;
+		let mut made_progress = false;
 		let mut chunked_query = PluginQuery {
-			id: base.id,
-			state: in_progress_state as i32,
-			publisher_name: base.publisher_name.clone(),
-			plugin_name: base.plugin_name.clone(),
-			query_name: base.query_name.clone(),
+			id: self.base.id,
+			state: self.in_progress_state as i32,
+			publisher_name: self.base.publisher_name.clone(),
+			plugin_name: self.base.plugin_name.clone(),
+			query_name: self.base.query_name.clone(),
 			key: vec![],
 			output: vec![],
 			concern: vec![],
 			split: false,
+			fragment: vec![],
+			error: String::new(),
+			progress: None,
+			cancel: false,
 		};
 
 		for (source, sink) in [
-			(&mut base.key, &mut chunked_query.key),
-			(&mut base.output, &mut chunked_query.output),
-			(&mut base.concern, &mut chunked_query.concern),
+			(&mut self.base.key, &mut chunked_query.key),
+			(&mut self.base.output, &mut chunked_query.output),
+			(&mut self.base.concern, &mut chunked_query.concern),
+			(&mut self.base.fragment, &mut chunked_query.fragment),
 		] {
 			let split_occurred = drain_vec_string(source, sink, &mut remaining, &mut made_progress);
 			if split_occurred {
@@ -111,14 +159,36 @@ pub fn chunk_with_size(msg: PluginQuery, max_est_size: usize) -> Result<Vec<Plug
 				break;
 			}
 		}
-		out.push(chunked_query);
+
+		// Check whether we did anything, to avoid looping forever
+		if !made_progress {
+			return Err(anyhow!("Message could not be chunked"));
+		}
+
+		// ensure the last chunk is set to the appropriate Complete state
+		if all_chunkable_data_consumed(&self.base) {
+			chunked_query.state = self.completion_state as i32;
+		}
+		Ok(chunked_query)
 	}
+}
+
+impl Iterator for QueryChunks {
+	type Item = Result<PluginQuery>;
 
-	// ensure the last message in the chunked messages is set to the appropriate Complete state
-	if let Some(last) = out.last_mut() {
-		last.state = completion_state as i32;
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done || all_chunkable_data_consumed(&self.base) {
+			return None;
+		}
+		let chunk = self.next_chunk();
+		// stop after an error, since no more progress can be made
+		self.done = chunk.is_err();
+		Some(chunk)
 	}
-	Ok(out)
+}
+
+pub fn chunk_with_size(msg: PluginQuery, max_est_size: usize) -> Result<Vec<PluginQuery>> {
+	QueryChunks::new(msg, max_est_size)?.collect()
 }
 
 pub fn chunk(msg: PluginQuery) -> Result<Vec<PluginQuery>> {
@@ -129,6 +199,15 @@ pub fn prepare(msg: Query) -> Result<Vec<PluginQuery>> {
 	chunk(msg.try_into()?)
 }
 
+/// Like `prepare`, but chunks to fit a negotiated max message size, producing each chunk as it's
+/// taken.
+pub fn prepare_with_max_size(msg: Query, max_message_size: usize) -> Result<QueryChunks> {
+	let max_est_size = max_message_size
+		.saturating_sub(GRPC_MESSAGE_OVERHEAD)
+		.max(1);
+	QueryChunks::new(msg.try_into()?, max_est_size)
+}
+
 /// Drain as much from a `Vec<String>` as possible
 ///
 /// `true` -> a `PartialString` was written to sink, indicating `split = true` for this message and no
@@ -182,21 +261,26 @@ fn in_progress_state(state: &QueryState) -> bool {
 	)
 }
 
-/// represents the 3 fields in a `PluginQuery` that hold `Vec<String>` data
+/// represents the 4 fields in a `PluginQuery` that hold `Vec<String>` data
 #[derive(Debug)]
 enum QueryVecField {
 	Key,
 	Output,
 	Concern,
+	Fragment,
 }
 
 /// determines which field in `PluginQuery` is the "latest" one with data
 ///
 /// checks for data in reverse order:
-/// 1. concern
-/// 2. output
-/// 3. key
+/// 1. fragment
+/// 2. concern
+/// 3. output
+/// 4. key
 fn last_field_to_have_content(query: &PluginQuery) -> QueryVecField {
+	if !query.fragment.is_empty() {
+		return QueryVecField::Fragment;
+	}
 	if !query.concern.is_empty() {
 		return QueryVecField::Concern;
 	}
@@ -206,38 +290,87 @@ fn last_field_to_have_content(query: &PluginQuery) -> QueryVecField {
 	QueryVecField::Key
 }
 
-#[derive(Default)]
+/// The size of the chunkable data in a message
+fn chunked_size(query: &PluginQuery) -> usize {
+	[&query.key, &query.output, &query.concern, &query.fragment]
+		.into_iter()
+		.flatten()
+		.map(String::len)
+		.sum()
+}
+
+/// Reassembles chunked messages into a query, refusing queries larger than the max payload size
 pub struct QuerySynthesizer {
 	raw: Option<PluginQuery>,
+	/// The size of the payload reassembled so far
+	size: usize,
+	max_size: usize,
+}
+
+impl Default for QuerySynthesizer {
+	fn default() -> Self {
+		QuerySynthesizer::with_max_message_size(GRPC_MAX_SIZE_BYTES)
+	}
 }
 
 impl QuerySynthesizer {
+	/// Make a synthesizer for queries chunked into messages of at most the negotiated size
+	pub fn with_max_message_size(max_message_size: usize) -> Self {
+		QuerySynthesizer {
+			raw: None,
+			size: 0,
+			max_size: max_payload_size(max_message_size),
+		}
+	}
+
+	/// Count a message toward the payload size, erroring out before a query larger than the max
+	/// size is held in memory
+	fn count(&mut self, chunk: &PluginQuery) -> StdResult<(), Error> {
+		self.size = self.size.saturating_add(chunked_size(chunk));
+		if self.size > self.max_size {
+			return Err(Error::QueryTooLarge {
+				id: chunk.id as usize,
+				max: self.max_size,
+			});
+		}
+		Ok(())
+	}
+
 	pub fn add<I>(&mut self, mut chunks: I) -> StdResult<Option<Query>, Error>
 	where
 		I: Iterator<Item = PluginQuery>,
 	{
 		if self.raw.is_none() {
-			self.raw = match chunks.next() {
-				Some(x) => Some(x),
+			let first = match chunks.next() {
+				Some(x) => x,
 				None => {
 					return Ok(None);
 				}
 			};
+			self.count(&first)?;
+			self.raw = Some(first);
 		}
-		let raw = self.raw.as_mut().unwrap(); // We know its `Some`, was set above
-		let initial_state: QueryState = raw
+		let initial_state: QueryState = self
+			.raw
+			.as_ref()
+			.unwrap() // We know its `Some`, was set above
 			.state
 			.try_into()
 			.map_err(|_| Error::UnspecifiedQueryState)?;
+		// A query which failed is answered in the unspecified state, with why it failed
+		if let Some(raw) = &self.raw {
+			if initial_state == QueryState::Unspecified && !raw.error.is_empty() {
+				return Err(Error::QueryFailed(raw.error.clone()));
+			}
+		}
 		// holds state of current chunk
 		let mut current_state: QueryState = initial_state;
 
 		// holds whether the last message was split, if it was then it holds the "latest" field
 		// with data that should have the first element of the next message appended to it
-		let mut last_message_split: Option<QueryVecField> = if raw.split {
-			Some(last_field_to_have_content(raw))
-		} else {
-			None
+		let mut last_message_split: Option<QueryVecField> = match &self.raw {
+			Some(raw) if raw.split => Some(last_field_to_have_content(raw)),
+			_ => None,
 		};
 
 		// If response is the first of a set of chunks, handle
@@ -253,6 +386,8 @@ impl QuerySynthesizer {
 				};
 
 				// By now we have our "next" message
+				self.count(&next)?;
+				let raw = self.raw.as_mut().unwrap();
 				current_state = next
 					.state
 					.try_into()
@@ -319,11 +454,18 @@ impl QuerySynthesizer {
 										.unwrap()
 										.push_str(next.concern.remove(0).as_str());
 								}
+								QueryVecField::Fragment => {
+									raw.fragment
+										.last_mut()
+										.unwrap()
+										.push_str(next.fragment.remove(0).as_str());
+								}
 							}
 						}
 						raw.key.extend(next.key);
 						raw.output.extend(next.output);
 						raw.concern.extend(next.concern);
+						raw.fragment.extend(next.fragment);
 
 						// save off whether or not the message that was just processed was split
 						last_message_split = next_message_split;
@@ -334,10 +476,11 @@ impl QuerySynthesizer {
 			// Sanity check - after we've left this loop, there should be no left over message
 			if chunks.next().is_some() {
 				return Err(Error::MoreAfterQueryComplete {
-					id: raw.id as usize,
+					id: self.raw.as_ref().unwrap().id as usize,
 				});
 			}
 		}
+		self.size = 0;
 		self.raw.take().unwrap().try_into().map(Some)
 	}
 }
@@ -346,6 +489,7 @@ impl QuerySynthesizer {
 mod test {
 
 	use super::*;
+	use crate::types::QueryDirection;
 
 	#[test]
 	fn test_bounded_char_draining() {
@@ -479,6 +623,13 @@ mod test {
 					"< 10#2".to_owned(),
 				],
 				split: false,
+				// This fragment will be split across several chunks after the concerns
+				fragment: vec![
+					r#"{"body":"MIT","format":"markdown","title":"Licenses"}"#.to_owned()
+				],
+				error: String::new(),
+				progress: None,
+				cancel: false,
 			};
 			let res = match chunk_with_size(orig_query.clone(), 10) {
 				Ok(r) => r,
@@ -503,4 +654,200 @@ mod test {
 			assert_eq!(orig_query, synthesized_plugin_query);
 		}
 	}
+
+	#[test]
+	fn test_over_limit_query_is_rejected() {
+		let query = PluginQuery {
+			id: 7,
+			state: QueryState::ReplyComplete as i32,
+			publisher_name: "".to_owned(),
+			plugin_name: "".to_owned(),
+			query_name: "".to_owned(),
+			key: vec![],
+			output: vec![serde_json::to_string(&"x".repeat(GRPC_MIN_SIZE_BYTES * 2)).unwrap()],
+			concern: vec![],
+			split: false,
+			fragment: vec![],
+			error: String::new(),
+			progress: None,
+			cancel: false,
+		};
+		let max_message_size = GRPC_MIN_SIZE_BYTES / MAX_CHUNKS_PER_QUERY;
+		let chunks = chunk_with_size(query.clone(), max_message_size).unwrap();
+		assert!(chunks.len() > MAX_CHUNKS_PER_QUERY);
+
+		let mut synth = QuerySynthesizer::with_max_message_size(max_message_size);
+		match synth.add(chunks.into_iter()) {
+			Err(Error::QueryTooLarge { id: 7, max }) => {
+				assert_eq!(max, max_payload_size(max_message_size))
+			}
+			other => panic!(
+				"expected the query to be too large, got {:?}",
+				other.map(|_| ())
+			),
+		}
+
+		// The same query fits when more is negotiated
+		let chunks = chunk_with_size(query.clone(), GRPC_MIN_SIZE_BYTES).unwrap();
+		let mut synth = QuerySynthesizer::with_max_message_size(GRPC_MIN_SIZE_BYTES);
+		let synthesized: PluginQuery = synth
+			.add(chunks.into_iter())
+			.unwrap()
+			.unwrap()
+			.try_into()
+			.unwrap();
+		assert_eq!(synthesized, query);
+	}
+
+	#[test]
+	fn test_negotiate_max_size() {
+		// A peer which doesn't negotiate only accepts the default size
+		assert_eq!(
+			negotiate_max_size(GRPC_MAX_SIZE_BYTES * 4, 0),
+			GRPC_MAX_SIZE_BYTES
+		);
+		assert_eq!(
+			negotiate_max_size(GRPC_MIN_SIZE_BYTES * 2, 0),
+			GRPC_MIN_SIZE_BYTES * 2
+		);
+		// Otherwise the smaller size wins, down to the minimum
+		assert_eq!(
+			negotiate_max_size(GRPC_MAX_SIZE_BYTES * 4, (GRPC_MAX_SIZE_BYTES * 2) as u64),
+			GRPC_MAX_SIZE_BYTES * 2
+		);
+		assert_eq!(
+			negotiate_max_size(GRPC_MAX_SIZE_BYTES, 1),
+			GRPC_MIN_SIZE_BYTES
+		);
+	}
+
+	#[test]
+	fn test_chunks_are_produced_lazily() {
+		let query = Query {
+			id: 3,
+			direction: QueryDirection::Response,
+			publisher: "mitre".to_owned(),
+			plugin: "git".to_owned(),
+			query: "commit_diffs".to_owned(),
+			key: vec![],
+			output: vec![serde_json::json!("x".repeat(10 * 1024))],
+			concerns: vec![],
+			fragments: vec![],
+		};
+		let mut chunks = prepare_with_max_size(query, 1024 + 4096).unwrap();
+
+		// Each chunk fits in the max size, and only the last one completes the reply
+		let first = chunks.next().unwrap().unwrap();
+		assert_eq!(first.state(), QueryState::ReplyInProgress);
+		assert!(first.split);
+		assert_eq!(first.output[0].len(), 4096);
+
+		let rest = chunks.collect::<Result<Vec<_>>>().unwrap();
+		assert_eq!(rest.len(), 2);
+		assert_eq!(rest.last().unwrap().state(), QueryState::ReplyComplete);
+
+		let mut synth = QuerySynthesizer::default();
+		let synthesized = synth
+			.add(std::iter::once(first).chain(rest))
+			.unwrap()
+			.unwrap();
+		assert_eq!(
+			synthesized.output[0],
+			serde_json::json!("x".repeat(10 * 1024))
+		);
+	}
+
+	#[test]
+	fn test_failed_query_reports_error() {
+		let failed = PluginQuery {
+			id: 0,
+			state: QueryState::Unspecified as i32,
+			publisher_name: "mitre".to_owned(),
+			plugin_name: "github".to_owned(),
+			query_name: "".to_owned(),
+			key: vec![],
+			output: vec![],
+			concern: vec![],
+			split: false,
+			fragment: vec![],
+			error: "provider schema drift".to_owned(),
+			progress: None,
+			cancel: false,
+		};
+
+		let mut synth = QuerySynthesizer::default();
+		match synth.add(std::iter::once(failed)) {
+			Err(Error::QueryFailed(message)) => assert_eq!(message, "provider schema drift"),
+			other => panic!("expected the query to fail, got {other:?}"),
+		}
+	}
+
+	/// Check chunking against the vectors shared with the SDKs for other languages, which must
+	/// split messages exactly as Hipcheck core does
+	#[test]
+	fn test_chunking_conformance_vectors() {
+		use serde_json::Value;
+
+		fn strings(value: &Value) -> Vec<String> {
+			value
+				.as_array()
+				.unwrap()
+				.iter()
+				.map(|s| s.as_str().unwrap().to_owned())
+				.collect()
+		}
+
+		fn state(value: &Value) -> QueryState {
+			QueryState::from_str_name(value.as_str().unwrap()).unwrap()
+		}
+
+		let vectors: Value =
+			serde_json::from_str(include_str!("../tests/conformance/chunks.json")).unwrap();
+
+		for case in vectors["cases"].as_array().unwrap() {
+			let name = case["name"].as_str().unwrap();
+			let query = &case["query"];
+			let msg = PluginQuery {
+				id: 1,
+				state: state(&query["state"]) as i32,
+				publisher_name: "pub".to_owned(),
+				plugin_name: "plugin".to_owned(),
+				query_name: "q".to_owned(),
+				key: strings(&query["key"]),
+				output: strings(&query["output"]),
+				concern: strings(&query["concern"]),
+				split: false,
+				fragment: strings(&query["fragment"]),
+				error: String::new(),
+				progress: None,
+				cancel: false,
+			};
+			let max_size = case["max_size"].as_u64().unwrap() as usize;
+			let res = chunk_with_size(msg, max_size);
+
+			if case["error"] == Value::Bool(true) {
+				assert!(res.is_err(), "{name}: expected chunking to fail");
+				continue;
+			}
+			let chunks = res.unwrap_or_else(|e| panic!("{name}: chunking failed: {e}"));
+			let expected = case["chunks"].as_array().unwrap();
+			assert_eq!(
+				chunks.len(),
+				expected.len(),
+				"{name}: wrong number of chunks"
+			);
+			for (chunk, expected) in chunks.iter().zip(expected) {
+				assert_eq!(chunk.state(), state(&expected["state"]), "{name}");
+				assert_eq!(chunk.key, strings(&expected["key"]), "{name}");
+				assert_eq!(chunk.output, strings(&expected["output"]), "{name}");
+				assert_eq!(chunk.concern, strings(&expected["concern"]), "{name}");
+				assert_eq!(chunk.fragment, strings(&expected["fragment"]), "{name}");
+				assert_eq!(
+					chunk.split,
+					expected["split"] == Value::Bool(true),
+					"{name}"
+				);
+			}
+		}
+	}
 }
@@ -32,4 +32,18 @@ pub enum Error {
 
 	#[error("invalid JSON in query output")]
 	InvalidJsonInQueryOutput(#[source] serde_json::Error),
+
+	#[error("invalid JSON in query report fragment")]
+	InvalidJsonInReportFragment(#[source] serde_json::Error),
+
+	#[error("invalid JSON in query concern")]
+	InvalidJsonInConcern(#[source] serde_json::Error),
+
+	/// The remote sent a query larger than the max payload size for the negotiated message size
+	#[error("query '{id}' is larger than the max of {max} bytes")]
+	QueryTooLarge { id: usize, max: usize },
+
+	/// The remote failed to answer the query, and said why
+	#[error("{0}")]
+	QueryFailed(String),
 }
@@ -6,6 +6,10 @@ use std::{result::Result as StdResult, str::FromStr};
 
 pub mod chunk;
 pub mod error;
+pub mod plugin_cache;
+pub mod plugin_log;
+pub mod plugin_scratch;
+pub mod plugin_secret;
 pub mod types;
 
 pub mod proto {
@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The cache directory Hipcheck core gives each plugin to keep data across runs.
+//!
+//! When Hipcheck core spawns a plugin, it passes the path of a directory
+//! dedicated to that version of the plugin in the [`CACHE_DIR_ENV_VAR`]
+//! environment variable. Unlike the scratch directory, core keeps it between
+//! runs, so plugins can store what's expensive to compute there, like the
+//! responses of API calls.
+
+use std::path::PathBuf;
+
+/// The environment variable core uses to tell a plugin where its cache directory is.
+pub const CACHE_DIR_ENV_VAR: &str = "HC_PLUGIN_CACHE_DIR";
+
+/// Get the cache directory core gave this plugin, if any.
+pub fn cache_dir_from_env() -> Option<PathBuf> {
+	std::env::var_os(CACHE_DIR_ENV_VAR)
+		.filter(|dir| !dir.is_empty())
+		.map(PathBuf::from)
+}
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The protocol by which plugins forward their log messages to Hipcheck core.
+//!
+//! When Hipcheck core spawns a plugin, it sets the [`LOG_LEVEL_ENV_VAR`]
+//! environment variable to the maximum level core itself is logging at. Plugins
+//! write each log record they want core to see as a single line on their
+//! standard error, formatted with [`format_line`]. Core reads the plugin's
+//! standard error, parses those lines with [`parse_line`], and re-emits them
+//! through its own logger tagged with the plugin's name.
+
+use log::{Level, LevelFilter};
+use std::str::FromStr;
+
+/// The environment variable core uses to tell a plugin what level to log at.
+pub const LOG_LEVEL_ENV_VAR: &str = "HC_PLUGIN_LOG_LEVEL";
+
+/// The prefix marking a line of plugin standard error as a log record.
+const LINE_PREFIX: &str = "hc-log";
+
+/// The separator between fields of a log record line.
+const SEPARATOR: char = '\t';
+
+/// A log record forwarded from a plugin, as parsed from one line of output.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LogLine<'l> {
+	/// The level the plugin logged the record at.
+	pub level: Level,
+	/// The target the plugin logged the record with.
+	pub target: &'l str,
+	/// The message of the record.
+	pub message: &'l str,
+}
+
+/// Get the level filter core asked a plugin to log at.
+///
+/// Defaults to `Error` if the variable is unset or unparseable, which matches
+/// core's own default when `HC_LOG` is unset.
+pub fn level_from_env() -> LevelFilter {
+	std::env::var(LOG_LEVEL_ENV_VAR)
+		.ok()
+		.and_then(|s| LevelFilter::from_str(&s).ok())
+		.unwrap_or(LevelFilter::Error)
+}
+
+/// Format a log record as a single line to be parsed by core.
+///
+/// Newlines in the message are escaped so the record stays on one line.
+pub fn format_line(level: Level, target: &str, message: &str) -> String {
+	format!(
+		"{LINE_PREFIX}{SEPARATOR}{level}{SEPARATOR}{target}{SEPARATOR}{}",
+		message.replace('\n', "\\n")
+	)
+}
+
+/// Parse a line of plugin output as a log record, if it is one.
+pub fn parse_line(line: &str) -> Option<LogLine<'_>> {
+	let mut parts = line.splitn(4, SEPARATOR);
+
+	if parts.next()? != LINE_PREFIX {
+		return None;
+	}
+
+	let level = Level::from_str(parts.next()?).ok()?;
+	let target = parts.next()?;
+	let message = parts.next()?;
+
+	Some(LogLine {
+		level,
+		target,
+		message,
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn round_trip() {
+		let line = format_line(Level::Debug, "git::local", "found 3 commits");
+		let parsed = parse_line(&line).unwrap();
+		assert_eq!(
+			parsed,
+			LogLine {
+				level: Level::Debug,
+				target: "git::local",
+				message: "found 3 commits",
+			}
+		);
+	}
+
+	#[test]
+	fn message_stays_on_one_line() {
+		let line = format_line(Level::Warn, "t", "a\nb\tc");
+		assert_eq!(line.lines().count(), 1);
+		assert_eq!(parse_line(&line).unwrap().message, "a\\nb\tc");
+	}
+
+	#[test]
+	fn non_log_lines_are_ignored() {
+		assert_eq!(parse_line("thread 'main' panicked at src/main.rs"), None);
+		assert_eq!(parse_line("hc-log\tNOTALEVEL\tt\tm"), None);
+	}
+}
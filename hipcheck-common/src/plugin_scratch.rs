@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The scratch directory Hipcheck core gives each plugin for temporary files.
+//!
+//! When Hipcheck core spawns a plugin, it creates a directory dedicated to that
+//! plugin for the current run and passes its path in the
+//! [`SCRATCH_DIR_ENV_VAR`] environment variable. Core deletes the directory
+//! when the run ends, so plugins should write any temporary files there rather
+//! than elsewhere on the system.
+
+use std::path::PathBuf;
+
+/// The environment variable core uses to tell a plugin where its scratch directory is.
+pub const SCRATCH_DIR_ENV_VAR: &str = "HC_PLUGIN_SCRATCH_DIR";
+
+/// Get the scratch directory core gave this plugin, if any.
+pub fn scratch_dir_from_env() -> Option<PathBuf> {
+	std::env::var_os(SCRATCH_DIR_ENV_VAR)
+		.filter(|dir| !dir.is_empty())
+		.map(PathBuf::from)
+}
@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The secrets Hipcheck core gives plugins, like API tokens.
+//!
+//! Users list secrets in their exec config, along with where core should read
+//! each one from and which plugins may have it. When core spawns a plugin, it
+//! passes each secret the plugin may have in an environment variable named
+//! by [`secret_env_var`], so every plugin finds its tokens the same way.
+
+/// The prefix of the environment variables core passes secrets to plugins in.
+pub const SECRET_ENV_VAR_PREFIX: &str = "HC_SECRET_";
+
+/// Get the environment variable core passes the secret with this name in.
+///
+/// The name is upper-cased, and anything other than a letter or digit becomes
+/// an underscore, so the secret "github-token" is in `HC_SECRET_GITHUB_TOKEN`.
+pub fn secret_env_var(name: &str) -> String {
+	let name = name
+		.chars()
+		.map(|c| {
+			if c.is_ascii_alphanumeric() {
+				c.to_ascii_uppercase()
+			} else {
+				'_'
+			}
+		})
+		.collect::<String>();
+	format!("{SECRET_ENV_VAR_PREFIX}{name}")
+}
+
+/// Get a secret core gave this plugin, if any.
+pub fn secret_from_env(name: &str) -> Option<String> {
+	std::env::var(secret_env_var(name))
+		.ok()
+		.filter(|secret| !secret.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn secret_names_become_env_vars() {
+		assert_eq!(secret_env_var("github-token"), "HC_SECRET_GITHUB_TOKEN");
+		assert_eq!(secret_env_var("npm.token"), "HC_SECRET_NPM_TOKEN");
+		assert_eq!(secret_env_var("API_KEY2"), "HC_SECRET_API_KEY2");
+	}
+}
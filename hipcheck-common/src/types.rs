@@ -14,7 +14,9 @@ pub struct Query {
 	pub query: String,
 	pub key: Vec<serde_json::Value>,
 	pub output: Vec<serde_json::Value>,
-	pub concerns: Vec<String>,
+	/// Concerns raised by the query, each a JSON object with at least a "message".
+	pub concerns: Vec<serde_json::Value>,
+	pub fragments: Vec<serde_json::Value>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -65,6 +67,23 @@ impl TryFrom<PluginQuery> for Query {
 			outputs.push(value);
 		}
 
+		// Plugins built with older SDKs send concerns as plain text instead of JSON
+		let concerns = value
+			.concern
+			.into_iter()
+			.map(|x| match serde_json::from_str(x.as_str()) {
+				Ok(concern @ serde_json::Value::Object(_)) => concern,
+				_ => serde_json::Value::String(x),
+			})
+			.collect();
+
+		let mut fragments = Vec::with_capacity(value.fragment.len());
+		for x in value.fragment.into_iter() {
+			let value =
+				serde_json::from_str(x.as_str()).map_err(Error::InvalidJsonInReportFragment)?;
+			fragments.push(value);
+		}
+
 		Ok(Query {
 			id: value.id as usize,
 			direction,
@@ -73,7 +92,8 @@ impl TryFrom<PluginQuery> for Query {
 			query: value.query_name,
 			key: keys,
 			output: outputs,
-			concerns: value.concern,
+			concerns,
+			fragments,
 		})
 	}
 }
@@ -96,6 +116,20 @@ impl TryFrom<Query> for PluginQuery {
 				serde_json::to_string(&output).map_err(Error::InvalidJsonInQueryKey)?;
 			outputs.push(json_formatted_output);
 		}
+		let mut concerns = vec![];
+		for concern in value.concerns {
+			let formatted_concern = match concern {
+				serde_json::Value::String(text) => text,
+				concern => serde_json::to_string(&concern).map_err(Error::InvalidJsonInConcern)?,
+			};
+			concerns.push(formatted_concern);
+		}
+		let mut fragments = vec![];
+		for fragment in value.fragments {
+			let json_formatted_fragment =
+				serde_json::to_string(&fragment).map_err(Error::InvalidJsonInReportFragment)?;
+			fragments.push(json_formatted_fragment);
+		}
 
 		Ok(PluginQuery {
 			id: value.id as i32,
@@ -105,8 +139,12 @@ impl TryFrom<Query> for PluginQuery {
 			query_name: value.query,
 			key: keys,
 			output: outputs,
-			concern: value.concerns,
+			concern: concerns,
 			split: false,
+			fragment: fragments,
+			error: String::new(),
+			progress: None,
+			cancel: false,
 		})
 	}
 }
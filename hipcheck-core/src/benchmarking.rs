@@ -36,6 +36,8 @@ impl Drop for PrintTime {
 	}
 }
 
+#[doc(hidden)]
+#[macro_export]
 macro_rules! print_scope_time {
 	() => {
 		$crate::benchmarking::PrintTime::new(format!(
@@ -58,4 +60,4 @@ macro_rules! print_scope_time {
 }
 
 // Make `print_scope_time` available through `benchmarking` module
-pub(super) use print_scope_time;
+pub use crate::print_scope_time;
@@ -0,0 +1,8 @@
+// SPDX-License-Identifier: Apache-2.0
+
+pub mod plugin;
+pub mod plugin_data;
+pub mod remote;
+pub mod repo;
+pub mod results;
+pub mod scratch;
@@ -1,6 +1,11 @@
 // SPDX-License-Identifier: Apache-2.0
 #![allow(unused)]
-use crate::{cache::repo, error::Result, plugin::PluginId, StdResult};
+use crate::{
+	cache::repo,
+	error::Result,
+	plugin::{PluginId, PluginName, PluginPublisher, PluginVersion},
+	StdResult,
+};
 use pathbuf::pathbuf;
 use std::{
 	borrow::Borrow,
@@ -259,6 +264,53 @@ impl HcPluginCache {
 	fn display(&self, to_show: Vec<&PluginCacheEntry>) {
 		println!("{}", Table::new(to_show));
 	}
+
+	/// Find the newest installed version of a plugin, or the given version if
+	/// it's installed.
+	pub fn find(&self, publisher: &str, name: &str, version: Option<&str>) -> Option<PluginId> {
+		self.entries
+			.iter()
+			.filter(|e| e.publisher == publisher && e.name == name)
+			.filter(|e| version.map_or(true, |v| e.version == v))
+			.max_by_key(|e| Version::parse(&e.version).ok())
+			.map(|e| {
+				PluginId::new(
+					PluginPublisher(e.publisher.clone()),
+					PluginName(e.name.clone()),
+					PluginVersion(e.version.clone()),
+				)
+			})
+	}
+
+	/// Removes a plugin from the cache, returning the versions that were removed. If no version
+	/// is given, every cached version of the plugin is removed.
+	pub fn remove(
+		&self,
+		publisher: &str,
+		name: &str,
+		version: Option<&str>,
+	) -> Result<Vec<String>> {
+		let mut removed = Vec::new();
+		for entry in self
+			.entries
+			.iter()
+			.filter(|e| e.publisher == publisher && e.name == name)
+			.filter(|e| version.map_or(true, |v| e.version == v))
+		{
+			std::fs::remove_dir_all(pathbuf![&self.path, publisher, name, &entry.version])?;
+			removed.push(entry.version.clone());
+		}
+
+		// clean up the name and publisher directories once they're empty
+		let name_dir = pathbuf![&self.path, publisher, name];
+		for dir in [name_dir.as_path(), name_dir.parent().unwrap()] {
+			if dir.is_dir() && std::fs::read_dir(dir)?.next().is_none() {
+				std::fs::remove_dir(dir)?;
+			}
+		}
+
+		Ok(removed)
+	}
 }
 
 #[cfg(test)]
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Cache directories plugins may use to keep data across runs.
+//!
+//! Plugin data is stored with the format
+//! `<path_to_cache>/plugin_data/<publisher>/<plugin_name>/<version>`, so a new
+//! version of a plugin never reads what an older one stored. Plugins built with
+//! the Rust SDK use it through `PluginEngine::cache`, which further scopes
+//! entries by the HEAD commit of the target.
+
+use crate::{error::Result, plugin::PluginId, util::fs::create_dir_all};
+use pathbuf::pathbuf;
+use std::path::{Path, PathBuf};
+
+/// The directories plugins keep data in between runs.
+#[derive(Debug)]
+pub struct HcPluginDataCache {
+	path: PathBuf,
+}
+
+impl HcPluginDataCache {
+	pub fn new(path: &Path) -> Self {
+		HcPluginDataCache {
+			path: pathbuf![path, "plugin_data"],
+		}
+	}
+
+	/// Create the data directory for a version of a plugin.
+	pub fn plugin_data_dir(&self, plugin_id: &PluginId) -> Result<PathBuf> {
+		let dir = self
+			.path
+			.join(plugin_id.publisher().as_ref())
+			.join(plugin_id.name().as_ref())
+			.join(plugin_id.version().as_ref());
+		create_dir_all(&dir)?;
+		Ok(dir)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::plugin::{PluginName, PluginPublisher, PluginVersion};
+
+	#[test]
+	fn data_dirs_are_scoped_by_version() {
+		let home = tempfile::tempdir().unwrap();
+		let data = HcPluginDataCache::new(home.path());
+		let plugin_id = |version: &str| {
+			PluginId::new(
+				PluginPublisher("mitre".to_owned()),
+				PluginName("git".to_owned()),
+				PluginVersion(version.to_owned()),
+			)
+		};
+
+		let old = data.plugin_data_dir(&plugin_id("0.1.0")).unwrap();
+		let new = data.plugin_data_dir(&plugin_id("0.2.0")).unwrap();
+		assert!(old.ends_with("plugin_data/mitre/git/0.1.0"));
+		assert!(old.is_dir());
+		assert_ne!(old, new);
+	}
+}
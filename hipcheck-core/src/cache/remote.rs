@@ -0,0 +1,211 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Local copies of files which plugins are configured with by URL.
+//!
+//! Options like `orgs-file` or `typo-file` may be given as an HTTPS URL in the
+//! policy file, so an organization can keep one copy of the data every machine
+//! uses. Each file is stored with the format
+//! `<path_to_cache>/remote-files/<url_hash>/<content_hash>/<file_name>`, next
+//! to a `meta.json` holding the ETag the server gave for it. Every run asks the
+//! server whether the file changed since, so changes are picked up without
+//! downloading the file again when it hasn't.
+
+use crate::{
+	error::{Context as _, Result},
+	hc_error,
+	util::{fs::create_dir_all, http::agent::agent, network::is_offline},
+};
+use pathbuf::pathbuf;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+	fs,
+	io::Read as _,
+	path::{Path, PathBuf},
+};
+use url::Url;
+
+/// The on-disk cache of files plugins are configured with by URL.
+#[derive(Debug)]
+pub struct HcRemoteFileCache {
+	/// The directory holding every downloaded file.
+	root: PathBuf,
+}
+
+/// What's known about the cached copy of a file.
+#[derive(Debug, Serialize, Deserialize)]
+struct RemoteFileMeta {
+	url: String,
+	/// The ETag the server gave for the file, if any.
+	etag: Option<String>,
+	/// The path of the copy, relative to the URL's directory.
+	file: PathBuf,
+}
+
+impl HcRemoteFileCache {
+	pub fn new(path: &Path) -> Self {
+		HcRemoteFileCache {
+			root: pathbuf![path, "remote-files"],
+		}
+	}
+
+	/// Replace the HTTPS URLs given for a plugin's `*-file` options with the
+	/// paths of local copies, downloading them if they changed.
+	pub fn localize_config(&self, config: &mut Value) -> Result<()> {
+		localize_with(config, |url| self.fetch(url))
+	}
+
+	/// Get a local copy of a file, downloading it if it isn't cached or the
+	/// server says it changed.
+	///
+	/// If the server can't be reached, the cached copy is used with a warning.
+	pub fn fetch(&self, url: &Url) -> Result<PathBuf> {
+		let dir = pathbuf![
+			&self.root,
+			blake3::hash(url.as_str().as_bytes()).to_hex().as_str()
+		];
+		let meta_path = pathbuf![&dir, "meta.json"];
+		let cached = fs::read_to_string(&meta_path)
+			.ok()
+			.and_then(|contents| serde_json::from_str::<RemoteFileMeta>(&contents).ok())
+			.filter(|meta| dir.join(&meta.file).is_file());
+
+		if is_offline() {
+			return cached.map(|meta| dir.join(meta.file)).ok_or_else(|| {
+				hc_error!(
+					"'{}' hasn't been downloaded before, which needs network access, which is disabled by --offline",
+					url
+				)
+			});
+		}
+
+		let mut request = agent().get(url.as_str());
+		if let Some(etag) = cached.as_ref().and_then(|meta| meta.etag.as_deref()) {
+			request = request.set("If-None-Match", etag);
+		}
+
+		let response = match request.call() {
+			Ok(response) => response,
+			Err(e) => {
+				let Some(meta) = cached else {
+					return Err(hc_error!("failed to download '{}': {}", url, e));
+				};
+				tracing::warn!(
+					"failed to check '{}' for changes, so using the copy downloaded before: {}",
+					url,
+					e
+				);
+				return Ok(dir.join(meta.file));
+			}
+		};
+
+		// Only a cached copy's ETag is sent, so there's always a copy when nothing changed
+		if let (304, Some(meta)) = (response.status(), &cached) {
+			tracing::debug!("'{}' hasn't changed since it was downloaded", url);
+			return Ok(dir.join(&meta.file));
+		}
+
+		let etag = response.header("etag").map(ToOwned::to_owned);
+		let mut contents = Vec::new();
+		response
+			.into_reader()
+			.read_to_end(&mut contents)
+			.with_context(|| format!("failed to download '{}'", url))?;
+
+		// The copy's path changes with its contents, so results cached for
+		// plugins configured with an older copy aren't reused.
+		let file_name = url
+			.path_segments()
+			.and_then(|mut segments| segments.next_back())
+			.filter(|name| !name.is_empty())
+			.unwrap_or("file");
+		let file = pathbuf![blake3::hash(&contents).to_hex().as_str(), file_name];
+		let path = dir.join(&file);
+		create_dir_all(path.parent().expect("the copy is always in a directory"))?;
+		fs::write(&path, &contents)
+			.with_context(|| format!("failed to write '{}'", path.display()))?;
+
+		let meta = RemoteFileMeta {
+			url: url.to_string(),
+			etag,
+			file: file.clone(),
+		};
+		fs::write(&meta_path, serde_json::to_string(&meta)?)
+			.with_context(|| format!("failed to write '{}'", meta_path.display()))?;
+
+		// Remove the copy this one replaces
+		if let Some(old) = cached.filter(|old| old.file != file) {
+			if let Some(old_dir) = dir.join(&old.file).parent() {
+				let _ = fs::remove_dir_all(old_dir);
+			}
+		}
+
+		tracing::debug!("downloaded '{}' to '{}'", url, path.display());
+		Ok(path)
+	}
+}
+
+/// Replace the HTTPS URLs given for `*-file` options in a plugin's config
+/// with the paths `fetch` gives for them.
+fn localize_with<F>(config: &mut Value, mut fetch: F) -> Result<()>
+where
+	F: FnMut(&Url) -> Result<PathBuf>,
+{
+	let Some(options) = config.as_object_mut() else {
+		return Ok(());
+	};
+
+	for (name, value) in options.iter_mut() {
+		if !name.ends_with("-file") {
+			continue;
+		}
+		let Some(url) = value
+			.as_str()
+			.filter(|value| value.starts_with("https://"))
+			.and_then(|value| Url::parse(value).ok())
+		else {
+			continue;
+		};
+
+		let path = fetch(&url).with_context(|| format!("failed to get '{}' for {}", url, name))?;
+		*value = Value::String(path.to_string_lossy().into_owned());
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn only_https_file_options_are_localized() {
+		let mut config = json!({
+			"orgs-file": "https://example.com/data/orgs.kdl",
+			"typo-file": "./config/Typos.toml",
+			"binary-file": "http://example.com/Binary.toml",
+			"homepage": "https://example.com",
+			"count-threshold": 3,
+		});
+
+		let mut fetched = Vec::new();
+		localize_with(&mut config, |url| {
+			fetched.push(url.to_string());
+			Ok(PathBuf::from("/cache/remote-files/orgs.kdl"))
+		})
+		.unwrap();
+
+		assert_eq!(fetched, vec!["https://example.com/data/orgs.kdl"]);
+		assert_eq!(
+			config,
+			json!({
+				"orgs-file": "/cache/remote-files/orgs.kdl",
+				"typo-file": "./config/Typos.toml",
+				"binary-file": "http://example.com/Binary.toml",
+				"homepage": "https://example.com",
+				"count-threshold": 3,
+			})
+		);
+	}
+}
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{error::Result, hc_error};
+use crate::{error::Result, hc_error, source::clone_dir_name, util::fs::FileLock};
 use dialoguer::Confirm;
 use git2::Repository;
 use pathbuf::pathbuf;
@@ -12,7 +12,7 @@ use std::{
 	fs,
 	path::{Path, PathBuf},
 	result::Result as StdResult,
-	time::SystemTime,
+	time::{Duration, SystemTime},
 };
 use tabled::{Table, Tabled};
 use walkdir::{DirEntry, WalkDir};
@@ -43,6 +43,45 @@ pub struct RepoCacheListScope {
 	pub n: Option<usize>,
 }
 
+/// Limits on the repository cache, past which `prune` deletes entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoCacheLimits {
+	/// The most space, in bytes, the cached repositories may take up together.
+	pub max_size: Option<u64>,
+	/// How long a repository may go unused before it's deleted.
+	pub max_age: Option<Duration>,
+}
+
+impl RepoCacheLimits {
+	pub fn is_empty(&self) -> bool {
+		self.max_size.is_none() && self.max_age.is_none()
+	}
+}
+
+/// Parse an age like "90d", in hours (h), days (d), or weeks (w).
+pub fn parse_age(s: &str) -> Result<Duration> {
+	let s = s.trim();
+	let split = s
+		.find(|c: char| !c.is_ascii_digit())
+		.ok_or_else(|| hc_error!("age '{}' needs a unit of h, d, or w", s))?;
+	let (count, unit) = s.split_at(split);
+	let count: u64 = count
+		.parse()
+		.map_err(|_| hc_error!("age '{}' doesn't start with a whole number", s))?;
+	let hours = match unit.trim().to_lowercase().as_str() {
+		"h" => 1,
+		"d" => 24,
+		"w" => 24 * 7,
+		other => {
+			return Err(hc_error!(
+				"unknown age unit '{}', expected h, d, or w",
+				other
+			))
+		}
+	};
+	Ok(Duration::from_secs(count.saturating_mul(hours * 60 * 60)))
+}
+
 #[derive(Debug, Clone, Tabled)]
 struct RepoCacheEntry {
 	pub name: String,
@@ -65,33 +104,43 @@ impl RepoCacheEntry {
 			.to_string()
 	}
 	fn display_modified(&self) -> String {
-		let Ok(dur) = self.modified.duration_since(SystemTime::UNIX_EPOCH) else {
-			return "<DISPLAY_ERROR>".to_owned();
-		};
-		let Some(dt) = chrono::DateTime::<chrono::offset::Utc>::from_timestamp(
-			dur.as_secs() as i64,
-			dur.subsec_nanos(),
-		) else {
-			return "<DISPLAY_ERROR>".to_owned();
-		};
-		let chars = dt.to_rfc2822().chars().collect::<Vec<char>>();
-		// Remove unnecessary " +0000" from end of rfc datetime str
-		chars[..chars.len() - 6].iter().collect()
+		display_modified(self.modified)
 	}
 	fn display_size(&self) -> String {
-		static ONE_KB: f64 = 1000.0;
-		static ONE_MB: f64 = ONE_KB * 1000.0;
-		static ONE_GB: f64 = ONE_MB * 1000.0;
-		let e_size = self.size as f64;
-		if e_size > ONE_GB {
-			format!("{:.2} GB", e_size / ONE_GB)
-		} else if e_size > ONE_MB {
-			format!("{:.2} MB", e_size / ONE_MB)
-		} else if e_size > ONE_KB {
-			format!("{:.2} KB", e_size / ONE_KB)
-		} else {
-			format!("{:.0} B", e_size)
-		}
+		display_size(self.size)
+	}
+}
+
+/// Display a modification time for a cache listing.
+pub(crate) fn display_modified(modified: SystemTime) -> String {
+	let Ok(dur) = modified.duration_since(SystemTime::UNIX_EPOCH) else {
+		return "<DISPLAY_ERROR>".to_owned();
+	};
+	let Some(dt) = chrono::DateTime::<chrono::offset::Utc>::from_timestamp(
+		dur.as_secs() as i64,
+		dur.subsec_nanos(),
+	) else {
+		return "<DISPLAY_ERROR>".to_owned();
+	};
+	let chars = dt.to_rfc2822().chars().collect::<Vec<char>>();
+	// Remove unnecessary " +0000" from end of rfc datetime str
+	chars[..chars.len() - 6].iter().collect()
+}
+
+/// Display a size in bytes for a cache listing.
+pub(crate) fn display_size(size: usize) -> String {
+	static ONE_KB: f64 = 1000.0;
+	static ONE_MB: f64 = ONE_KB * 1000.0;
+	static ONE_GB: f64 = ONE_MB * 1000.0;
+	let e_size = size as f64;
+	if e_size > ONE_GB {
+		format!("{:.2} GB", e_size / ONE_GB)
+	} else if e_size > ONE_MB {
+		format!("{:.2} MB", e_size / ONE_MB)
+	} else if e_size > ONE_KB {
+		format!("{:.2} KB", e_size / ONE_KB)
+	} else {
+		format!("{:.0} B", e_size)
 	}
 }
 
@@ -133,6 +182,85 @@ pub fn get_last_modified_or_now(path: &Path) -> SystemTime {
 	try_get_last_modified(path).unwrap_or(SystemTime::now())
 }
 
+/// Get when a cached repo was last used, which is when it was last fetched or
+/// checked out, since those don't always change the repo dir itself.
+fn get_last_used(path: &Path) -> SystemTime {
+	[".git/FETCH_HEAD", ".git/HEAD", ".git/index"]
+		.iter()
+		.filter_map(|file| try_get_last_modified(&pathbuf![path, file]).ok())
+		.fold(get_last_modified_or_now(path), SystemTime::max)
+}
+
+/// Split cache entries into those to keep and those to delete to get under
+/// the limits, deleting any unused for longer than the max age and then the
+/// least recently used until the rest fit in the max size.
+fn select_for_pruning(
+	mut entries: Vec<RepoCacheEntry>,
+	limits: &RepoCacheLimits,
+	now: SystemTime,
+) -> (Vec<RepoCacheEntry>, Vec<RepoCacheEntry>) {
+	HcRepoCache::sort(&mut entries, RepoCacheSort::Oldest, false);
+	let mut size: u64 = entries.iter().map(|e| e.size as u64).sum();
+	entries.into_iter().partition(|e| {
+		let expired = limits.max_age.is_some_and(|max_age| {
+			now.duration_since(e.modified)
+				.is_ok_and(|age| age > max_age)
+		});
+		let too_big = limits.max_size.is_some_and(|max_size| size > max_size);
+		if expired || too_big {
+			size -= e.size as u64;
+			false
+		} else {
+			true
+		}
+	})
+}
+
+/// Rename any directories in the clones cache which were created before clone
+/// directory names were escaped, so they're reused instead of cloned again.
+///
+/// GitHub clones are left alone, since they're still named for their owner
+/// and repository as they are.
+pub fn migrate_clone_dirs(root: &Path) -> Result<()> {
+	let clones_path = pathbuf![root, "clones"];
+	for group in ["unknown", "local"] {
+		let group_path = pathbuf![&clones_path, group];
+		if group_path.is_dir() {
+			migrate_dir_names(&group_path)?;
+		}
+	}
+	Ok(())
+}
+
+fn migrate_dir_names(dir: &Path) -> Result<()> {
+	for entry in fs::read_dir(dir)? {
+		let entry = entry?;
+		if !entry.file_type()?.is_dir() {
+			continue;
+		}
+		let path = entry.path();
+		let raw = entry.file_name().to_string_lossy().into_owned();
+		let name = clone_dir_name(&raw);
+		if name != raw {
+			let new_path = pathbuf![dir, &name];
+			if new_path.exists() {
+				// The repo was already cloned again under its new name, so the
+				// old clone is stale.
+				tracing::debug!("removing stale clone {}", path.display());
+				fs::remove_dir_all(&path)?;
+				continue;
+			}
+			tracing::debug!(
+				"migrating clone {} to {}",
+				path.display(),
+				new_path.display()
+			);
+			fs::rename(&path, &new_path)?;
+		}
+	}
+	Ok(())
+}
+
 /// Starting from a given cache dir, finds and iterates over git repos as "CacheEntry" structs
 struct HcRepoCacheIterator {
 	root: PathBuf,
@@ -171,7 +299,7 @@ impl HcRepoCacheIterator {
 			.as_str()
 			.unwrap()
 			.to_owned();
-		let modified = get_last_modified_or_now(path);
+		let modified = get_last_used(path);
 		let cache_subdir = pathbuf![path.strip_prefix(self.root.as_path()).unwrap()];
 		let mut parent = cache_subdir.clone();
 		parent.pop();
@@ -287,6 +415,40 @@ impl HcRepoCache {
 		};
 		// At this point self.entries is empty and we have separated the set of
 		// entries to delete
+		self.delete_entries(to_keep, to_del, force);
+		Ok(())
+	}
+	/// Delete the least recently used cache entries until the cache is within
+	/// the limits
+	pub fn prune(&mut self, limits: &RepoCacheLimits, force: bool) -> Result<()> {
+		let entries = self.entries.drain(0..).collect();
+		let (to_keep, to_del) = select_for_pruning(entries, limits, SystemTime::now());
+		self.delete_entries(to_keep, to_del, force);
+		Ok(())
+	}
+	/// Delete the least recently used cache entries until the cache is within
+	/// the limits, without asking, as is done before each check.
+	///
+	/// Entries another Hipcheck run is cloning or updating are left alone.
+	pub fn prune_unused(&mut self, limits: &RepoCacheLimits) {
+		let entries = self.entries.drain(0..).collect();
+		let (to_keep, to_del) = select_for_pruning(entries, limits, SystemTime::now());
+		for entry in to_del {
+			if let Err(e) = self.internal_delete(&entry) {
+				tracing::debug!("not pruning cached repo '{}': {}", entry.name, e);
+				self.entries.push(entry);
+			}
+		}
+		self.entries.extend(to_keep);
+	}
+	/// Internal helper which deletes entries once the user confirms, putting
+	/// them back in self.entries otherwise
+	fn delete_entries(
+		&mut self,
+		to_keep: Vec<RepoCacheEntry>,
+		to_del: Vec<RepoCacheEntry>,
+		force: bool,
+	) {
 		if !to_del.is_empty() {
 			if !force {
 				// Ask user for confirmation
@@ -300,11 +462,12 @@ impl HcRepoCache {
 					// Cleanup by returning entries to storage
 					self.entries.extend(to_del);
 					self.entries.extend(to_keep);
-					return Ok(());
+					return;
 				}
 			}
 			// Delete entries, returning failures back to the self.entries list
 			for entry in to_del {
+				tracing::debug!("deleting cached repo '{}'", entry.name);
 				if let Err(e) = self.internal_delete(&entry) {
 					println!("Failed to delete entry '{}': {e}", entry.name);
 					self.entries.push(entry)
@@ -314,7 +477,6 @@ impl HcRepoCache {
 		self.entries.extend(to_keep);
 		// self.entries contains `to_keep` plus any entries that were unsuccessfully
 		// deleted
-		Ok(())
 	}
 	/// List cache entries
 	pub fn list(&mut self, scope: RepoCacheListScope, filter: Option<String>) -> Result<()> {
@@ -354,6 +516,10 @@ impl HcRepoCache {
 
 		let parent_path = pathbuf![self.path.as_path(), entry.parent.as_path()];
 		let path = pathbuf![&parent_path, &entry.name];
+		// Don't delete a clone out from under another Hipcheck run using it
+		let Some(_lock) = FileLock::try_acquire(&path)? else {
+			return Err(hc_error!("it's in use by another Hipcheck run"));
+		};
 		std::fs::remove_dir_all(path)?;
 
 		// Clear owner/org dir if deleting this entry from 'github' made it empty
@@ -387,12 +553,128 @@ impl Drop for HcRepoCache {
 		let data = match serde_json::to_string(&hc_disk_cache) {
 			Ok(d) => d,
 			Err(e) => {
-				log::debug!("Failed to jsonify Hipcheck cache info: {e}");
+				tracing::debug!("Failed to jsonify Hipcheck cache info: {e}");
 				return;
 			}
 		};
 		if let Err(e) = fs::write(file_path, data) {
-			log::debug!("Failed to write Hipcheck cache info: {e}");
+			tracing::debug!("Failed to write Hipcheck cache info: {e}");
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::build_unknown_remote_clone_dir;
+	use url::Url;
+
+	const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+	fn entry(name: &str, size: usize, days_unused: u32, now: SystemTime) -> RepoCacheEntry {
+		RepoCacheEntry {
+			name: name.to_owned(),
+			parent: PathBuf::from("github/mitre"),
+			commit: "abc1234".to_owned(),
+			size,
+			modified: now - DAY * days_unused,
 		}
 	}
+
+	fn names(entries: &[RepoCacheEntry]) -> Vec<&str> {
+		entries.iter().map(|e| e.name.as_str()).collect()
+	}
+
+	#[test]
+	fn parse_ages() {
+		assert_eq!(parse_age("90d").unwrap(), DAY * 90);
+		assert_eq!(parse_age("12h").unwrap(), DAY / 2);
+		assert_eq!(parse_age("2w").unwrap(), DAY * 14);
+		assert!(parse_age("90").is_err());
+		assert!(parse_age("d").is_err());
+		assert!(parse_age("3mo").is_err());
+	}
+
+	#[test]
+	fn pruning_drops_expired_then_least_recently_used() {
+		let now = SystemTime::now();
+		let entries = vec![
+			entry("new", 40, 1, now),
+			entry("stale", 10, 100, now),
+			entry("old", 30, 30, now),
+			entry("recent", 30, 5, now),
+		];
+		let limits = RepoCacheLimits {
+			max_size: Some(75),
+			max_age: Some(DAY * 90),
+		};
+
+		let (keep, delete) = select_for_pruning(entries, &limits, now);
+
+		assert_eq!(names(&delete), vec!["stale", "old"]);
+		assert_eq!(names(&keep), vec!["recent", "new"]);
+	}
+
+	#[test]
+	fn pruning_within_limits_keeps_everything() {
+		let now = SystemTime::now();
+		let entries = vec![entry("a", 10, 3, now), entry("b", 10, 2, now)];
+		let limits = RepoCacheLimits {
+			max_size: Some(20),
+			max_age: None,
+		};
+
+		let (keep, delete) = select_for_pruning(entries, &limits, now);
+
+		assert!(delete.is_empty());
+		assert_eq!(keep.len(), 2);
+	}
+
+	#[test]
+	fn pruning_skips_clones_in_use() {
+		let now = SystemTime::now();
+		let dir = tempfile::tempdir().unwrap();
+		let clones = pathbuf![dir.path(), "clones"];
+		for name in ["in-use", "unused"] {
+			fs::create_dir_all(pathbuf![&clones, "github", "mitre", name]).unwrap();
+		}
+		let mut cache = HcRepoCache {
+			path: clones.clone(),
+			entries: vec![entry("in-use", 10, 100, now), entry("unused", 10, 100, now)],
+		};
+		let limits = RepoCacheLimits {
+			max_size: None,
+			max_age: Some(DAY * 90),
+		};
+
+		let _lock = FileLock::acquire(pathbuf![&clones, "github", "mitre", "in-use"]).unwrap();
+		cache.prune_unused(&limits);
+
+		assert_eq!(names(&cache.entries), vec!["in-use"]);
+		assert!(pathbuf![&clones, "github", "mitre", "in-use"].exists());
+		assert!(!pathbuf![&clones, "github", "mitre", "unused"].exists());
+	}
+
+	#[test]
+	fn migrated_clones_are_found_again() {
+		let dir = tempfile::tempdir().unwrap();
+		let clones = pathbuf![dir.path(), "clones"];
+		let url = Url::parse(&format!("https://example.com/{}/repo", "a".repeat(80))).unwrap();
+		let old_name = format!("example.com__{}__repo", "a".repeat(80));
+		fs::create_dir_all(pathbuf![&clones, "unknown", &old_name]).unwrap();
+		let github = pathbuf![&clones, "github", "mitre", "b".repeat(80)];
+		fs::create_dir_all(&github).unwrap();
+
+		migrate_clone_dirs(dir.path()).unwrap();
+
+		let new_name = build_unknown_remote_clone_dir(&url).unwrap();
+		assert_ne!(new_name, old_name);
+		assert!(pathbuf![&clones, "unknown", &new_name].is_dir());
+		assert!(!pathbuf![&clones, "unknown", &old_name].exists());
+		assert!(github.is_dir());
+
+		// Migrating again leaves the clones where they are.
+		migrate_clone_dirs(dir.path()).unwrap();
+		assert!(pathbuf![&clones, "unknown", &new_name].is_dir());
+	}
 }
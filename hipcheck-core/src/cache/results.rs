@@ -0,0 +1,620 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Results of plugin queries, kept across runs of Hipcheck.
+//!
+//! Results are stored with the format
+//! `<path_to_cache>/plugin-results/<publisher>/<plugin_name>/<version>/<hash>.json`,
+//! where `<hash>` covers the query name, the query key, the plugin's config,
+//! and the HEAD commit of the target repository. Running Hipcheck again
+//! against the same commit with the same policy can then reuse the results
+//! of expensive queries instead of asking the plugins again.
+//!
+//! Results of plugins which use the network depend on more than those inputs,
+//! like the state of a GitHub repository, so they're only reused for
+//! [`NETWORK_RESULT_MAX_AGE`].
+//!
+//! The HEAD commit analyzed for each repository is also recorded, under
+//! `<path_to_cache>/plugin-results/heads/`. When the repository is checked
+//! again at a newer commit, plugins can ask for their results from the
+//! previous run and only work out what changed since then.
+
+use crate::{
+	cache::repo::{display_modified, display_size},
+	error::{Context as _, Result},
+	hc_error,
+	plugin::QueryResult,
+	util::fs::create_dir_all,
+};
+use dialoguer::Confirm;
+use pathbuf::pathbuf;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+	collections::HashMap,
+	fs,
+	path::{Path, PathBuf},
+	process,
+	sync::RwLock,
+	time::{Duration, SystemTime},
+};
+use tabled::{Table, Tabled};
+use walkdir::WalkDir;
+
+/// How long results of plugins which use the network are reused.
+pub const NETWORK_RESULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The on-disk cache of plugin query results.
+#[derive(Debug)]
+pub struct HcResultCache {
+	/// The directory holding the results of every plugin.
+	root: PathBuf,
+	/// The version and config of each running plugin, by `<publisher>/<name>`.
+	plugins: HashMap<String, PluginScope>,
+	/// The HEAD commit of the target, once it's been resolved.
+	head: RwLock<Option<String>>,
+	/// The HEAD commit analyzed by the previous run against the target, if
+	/// it was a different commit.
+	previous: RwLock<Option<String>>,
+}
+
+/// The HEAD commits analyzed in runs against a repository.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RecordedHeads {
+	/// The HEAD commit of the latest run.
+	head: String,
+	/// The HEAD commit of the run before that one which analyzed a different commit.
+	#[serde(default)]
+	previous: Option<String>,
+}
+
+/// What a plugin's results depend on, besides the query itself.
+#[derive(Debug)]
+struct PluginScope {
+	version: String,
+	config: Value,
+	/// How long the plugin's results are reused, if they ever go stale.
+	max_age: Option<Duration>,
+}
+
+/// A cached result, along with the inputs that produced it.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedResult {
+	query: String,
+	key: Value,
+	head: String,
+	value: Vec<Value>,
+	/// Concerns as plugins send them; older caches have them as plain text.
+	concerns: Vec<Value>,
+	#[serde(default)]
+	fragments: Vec<Value>,
+}
+
+impl HcResultCache {
+	pub fn new(path: &Path) -> Self {
+		HcResultCache {
+			root: results_root(path),
+			plugins: HashMap::new(),
+			head: RwLock::new(None),
+			previous: RwLock::new(None),
+		}
+	}
+
+	/// Cache the results of a plugin, given as `<publisher>/<name>`.
+	pub fn add_plugin(&mut self, plugin_name: &str, version: &str, config: &Value) {
+		self.plugins.insert(
+			plugin_name.to_owned(),
+			PluginScope {
+				version: version.to_owned(),
+				config: config.clone(),
+				max_age: None,
+			},
+		);
+	}
+
+	/// Only reuse the results of a plugin, given as `<publisher>/<name>`, for
+	/// `max_age` after they were stored.
+	pub fn set_max_age(&mut self, plugin_name: &str, max_age: Duration) {
+		if let Some(scope) = self.plugins.get_mut(plugin_name) {
+			scope.max_age = Some(max_age);
+		}
+	}
+
+	/// Set the HEAD commit of the target, whose repository is at `repo`.
+	///
+	/// Nothing is read from or written to the cache until this is set, so
+	/// queries made while resolving the target are always run. The HEAD is
+	/// recorded for the repository, and the one analyzed before it, if any,
+	/// is remembered so plugins can build on their results from that run.
+	pub fn set_head(&self, repo: &Path, head: &str) {
+		let mut current = self.head.write().unwrap();
+		if current.is_some() {
+			tracing::warn!("plugin result cache HEAD was already set");
+			return;
+		}
+		*current = Some(head.to_owned());
+
+		match self.record_head(repo, head) {
+			Ok(previous) => *self.previous.write().unwrap() = previous,
+			Err(e) => tracing::warn!(error = %e, "failed to record analyzed HEAD"),
+		}
+	}
+
+	/// Forget the HEAD commit of the target, so the cache can be used for
+	/// another target once its HEAD is set.
+	pub fn clear_head(&self) {
+		*self.head.write().unwrap() = None;
+		*self.previous.write().unwrap() = None;
+	}
+
+	/// Get the HEAD commit analyzed by the previous run against the target,
+	/// if it was a different commit.
+	pub fn previous_head(&self) -> Option<String> {
+		self.previous.read().unwrap().clone()
+	}
+
+	/// Get the value of a query from the previous run against the target, if
+	/// it was cached. The key is the one the query was made with then.
+	pub fn previous_value(&self, plugin_name: &str, query: &str, key: &Value) -> Option<Value> {
+		let previous = self.previous_head()?;
+		let path = self.entry_path_at(plugin_name, query, key, &previous)?;
+		if self.is_stale(plugin_name, &path) {
+			return None;
+		}
+		let contents = fs::read_to_string(path).ok()?;
+		let mut cached = serde_json::from_str::<CachedResult>(&contents).ok()?;
+		tracing::debug!(
+			"using result of {}/{} from {}",
+			plugin_name,
+			query,
+			previous
+		);
+		cached.value.pop()
+	}
+
+	/// Record the HEAD commit of a run against a repository, returning the
+	/// different commit analyzed before it, if there was one.
+	fn record_head(&self, repo: &Path, head: &str) -> Result<Option<String>> {
+		let hash = blake3::hash(repo.to_string_lossy().as_bytes());
+		let path = pathbuf![&self.root, "heads", &format!("{}.json", hash.to_hex())];
+
+		let recorded = fs::read_to_string(&path)
+			.ok()
+			.and_then(|contents| serde_json::from_str::<RecordedHeads>(&contents).ok());
+		// Running against the same commit again keeps what came before it
+		let previous = match recorded {
+			Some(recorded) if recorded.head == head => recorded.previous,
+			Some(recorded) => Some(recorded.head),
+			None => None,
+		};
+
+		let heads = RecordedHeads {
+			head: head.to_owned(),
+			previous: previous.clone(),
+		};
+		write_atomically(&path, &serde_json::to_string(&heads)?)?;
+
+		Ok(previous)
+	}
+
+	/// Get the cached result of a query, if there is one.
+	pub fn get(&self, plugin_name: &str, query: &str, key: &Value) -> Option<QueryResult> {
+		let path = self.entry_path(plugin_name, query, key)?;
+
+		if !path.exists() || self.is_stale(plugin_name, &path) {
+			return None;
+		}
+
+		let cached = fs::read_to_string(&path)
+			.map_err(|e| hc_error!("{}", e))
+			.and_then(|contents| {
+				serde_json::from_str::<CachedResult>(&contents).map_err(|e| hc_error!("{}", e))
+			});
+
+		match cached {
+			Ok(cached) => {
+				tracing::debug!("using cached result of {}/{}", plugin_name, query);
+				Some(QueryResult {
+					value: cached.value,
+					concerns: cached.concerns,
+					fragments: cached.fragments,
+				})
+			}
+			Err(e) => {
+				tracing::warn!(
+					"ignoring unreadable cached plugin result '{}': {}",
+					path.display(),
+					e
+				);
+				None
+			}
+		}
+	}
+
+	/// Store the result of a query.
+	pub fn put(
+		&self,
+		plugin_name: &str,
+		query: &str,
+		key: &Value,
+		result: &QueryResult,
+	) -> Result<()> {
+		let Some(path) = self.entry_path(plugin_name, query, key) else {
+			return Ok(());
+		};
+
+		let cached = CachedResult {
+			query: query.to_owned(),
+			key: key.clone(),
+			head: self.head.read().unwrap().clone().unwrap_or_default(),
+			value: result.value.clone(),
+			concerns: result.concerns.clone(),
+			fragments: result.fragments.clone(),
+		};
+		let contents = serde_json::to_string(&cached)?;
+
+		write_atomically(&path, &contents)
+	}
+
+	/// Whether the result at `path` is too old to be reused.
+	fn is_stale(&self, plugin_name: &str, path: &Path) -> bool {
+		let Some(max_age) = self.plugins.get(plugin_name).and_then(|s| s.max_age) else {
+			return false;
+		};
+		let age = fs::metadata(path)
+			.and_then(|metadata| metadata.modified())
+			.map(|modified| modified.elapsed().unwrap_or_default());
+		match age {
+			Ok(age) if age < max_age => false,
+			Ok(_) => {
+				tracing::debug!("ignoring stale cached result '{}'", path.display());
+				true
+			}
+			Err(_) => true,
+		}
+	}
+
+	/// Get the path for the result of a query, if results for it are cached.
+	fn entry_path(&self, plugin_name: &str, query: &str, key: &Value) -> Option<PathBuf> {
+		let head = self.head.read().unwrap().clone()?;
+		self.entry_path_at(plugin_name, query, key, &head)
+	}
+
+	/// Get the path for the result of a query against some HEAD commit.
+	fn entry_path_at(
+		&self,
+		plugin_name: &str,
+		query: &str,
+		key: &Value,
+		head: &str,
+	) -> Option<PathBuf> {
+		let scope = self.plugins.get(plugin_name)?;
+
+		let inputs = json!({
+			"query": query,
+			"key": key,
+			"config": scope.config,
+			"head": head,
+		});
+		let hash = blake3::hash(inputs.to_string().as_bytes());
+
+		let dir = plugin_name
+			.split('/')
+			.fold(self.root.clone(), |dir, part| dir.join(part));
+
+		Some(pathbuf![
+			&dir,
+			&scope.version,
+			&format!("{}.json", hash.to_hex())
+		])
+	}
+
+	/// List the cached results for each plugin version.
+	pub fn list(path: &Path, plugin: Option<&str>) -> Result<()> {
+		let entries = ResultCacheEntry::collect(&results_root(path), plugin)?;
+
+		if entries.is_empty() {
+			println!("no cached plugin results");
+		} else {
+			println!("{}", Table::new(entries));
+		}
+
+		Ok(())
+	}
+
+	/// Delete the cached results for one plugin, or for every plugin.
+	pub fn purge(path: &Path, plugin: Option<&str>, force: bool) -> Result<()> {
+		let root = results_root(path);
+		let entries = ResultCacheEntry::collect(&root, plugin)?;
+
+		if entries.is_empty() {
+			println!("no cached plugin results");
+			return Ok(());
+		}
+
+		if !force {
+			println!("You will delete the following entries:");
+			println!("{}", Table::new(&entries));
+			let conf = Confirm::new()
+				.with_prompt("Are you sure you want to delete?")
+				.interact()
+				.unwrap();
+			if !conf {
+				return Ok(());
+			}
+		}
+
+		let dir = match plugin {
+			Some(plugin) => plugin.split('/').fold(root, |dir, part| dir.join(part)),
+			None => root,
+		};
+		fs::remove_dir_all(&dir)
+			.with_context(|| format!("failed to delete '{}'", dir.display()))?;
+
+		Ok(())
+	}
+}
+
+/// The cached results for a single version of a plugin.
+#[derive(Debug, Clone, Tabled)]
+struct ResultCacheEntry {
+	pub plugin: String,
+	pub version: String,
+	pub results: usize,
+	#[tabled(display_with("Self::display_size", self))]
+	pub size: usize,
+	#[tabled(display_with("Self::display_modified", self))]
+	pub modified: SystemTime,
+}
+
+impl ResultCacheEntry {
+	fn display_size(&self) -> String {
+		display_size(self.size)
+	}
+
+	fn display_modified(&self) -> String {
+		display_modified(self.modified)
+	}
+
+	/// Gather the entries under the results directory, optionally for just one
+	/// plugin, given as `<publisher>/<name>`.
+	fn collect(root: &Path, plugin: Option<&str>) -> Result<Vec<ResultCacheEntry>> {
+		let mut entries = HashMap::<(String, String), ResultCacheEntry>::new();
+
+		if !root.exists() {
+			return Ok(Vec::new());
+		}
+
+		for file in WalkDir::new(root).min_depth(4).max_depth(4) {
+			let file = file?;
+			let is_result = file.file_type().is_file()
+				&& file.path().extension().is_some_and(|ext| ext == "json");
+			if !is_result {
+				continue;
+			}
+
+			let Ok(relative) = file.path().strip_prefix(root) else {
+				continue;
+			};
+			let parts = relative
+				.iter()
+				.map(|part| part.to_string_lossy().into_owned())
+				.collect::<Vec<_>>();
+			let [publisher, name, version, _] = parts.as_slice() else {
+				continue;
+			};
+
+			let plugin_name = format!("{}/{}", publisher, name);
+			if plugin.is_some_and(|plugin| plugin != plugin_name) {
+				continue;
+			}
+
+			let metadata = file.metadata()?;
+			let modified = metadata.modified()?;
+
+			let entry = entries
+				.entry((plugin_name.clone(), version.clone()))
+				.or_insert_with(|| ResultCacheEntry {
+					plugin: plugin_name,
+					version: version.clone(),
+					results: 0,
+					size: 0,
+					modified,
+				});
+			entry.results += 1;
+			entry.size += metadata.len() as usize;
+			entry.modified = entry.modified.max(modified);
+		}
+
+		let mut entries = entries.into_values().collect::<Vec<_>>();
+		entries.sort_by(|a, b| (&a.plugin, &a.version).cmp(&(&b.plugin, &b.version)));
+		Ok(entries)
+	}
+}
+
+/// The directory holding the cached results of every plugin.
+fn results_root(path: &Path) -> PathBuf {
+	pathbuf![path, "plugin-results"]
+}
+
+/// Write to a file unique to this run, then move it into place, so a
+/// concurrent run never reads a partially-written file.
+fn write_atomically(path: &Path, contents: &str) -> Result<()> {
+	let dir = path
+		.parent()
+		.expect("cached files are always in a directory");
+	create_dir_all(dir)?;
+	let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+	let tmp = dir.join(format!(".{}.{}.tmp", file_name, process::id()));
+	fs::write(&tmp, contents).with_context(|| format!("failed to write '{}'", tmp.display()))?;
+	fs::rename(&tmp, path).with_context(|| format!("failed to write '{}'", path.display()))?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn result() -> QueryResult {
+		QueryResult {
+			value: vec![json!(42)],
+			concerns: vec![json!("a concern"), json!({ "message": "another concern" })],
+			fragments: vec![],
+		}
+	}
+
+	#[test]
+	fn results_are_cached_once_head_is_set() {
+		let tmp = tempfile::tempdir().unwrap();
+		let mut cache = HcResultCache::new(tmp.path());
+		cache.add_plugin("mitre/git", "0.1.0", &json!({}));
+		let key = json!({"path": "/tmp/repo"});
+
+		cache
+			.put("mitre/git", "commit_diffs", &key, &result())
+			.unwrap();
+		assert_eq!(cache.get("mitre/git", "commit_diffs", &key), None);
+
+		cache.set_head(Path::new("/tmp/repo"), "abc123");
+		cache
+			.put("mitre/git", "commit_diffs", &key, &result())
+			.unwrap();
+		assert_eq!(cache.get("mitre/git", "commit_diffs", &key), Some(result()));
+
+		let entries = ResultCacheEntry::collect(&results_root(tmp.path()), None).unwrap();
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].plugin, "mitre/git");
+		assert_eq!(entries[0].version, "0.1.0");
+		assert_eq!(entries[0].results, 1);
+	}
+
+	#[test]
+	fn results_depend_on_head_and_config() {
+		let tmp = tempfile::tempdir().unwrap();
+		let key = json!({"path": "/tmp/repo"});
+
+		let mut cache = HcResultCache::new(tmp.path());
+		cache.add_plugin("mitre/git", "0.1.0", &json!({}));
+		cache.set_head(Path::new("/tmp/repo"), "abc123");
+		cache
+			.put("mitre/git", "commit_diffs", &key, &result())
+			.unwrap();
+
+		let mut other_head = HcResultCache::new(tmp.path());
+		other_head.add_plugin("mitre/git", "0.1.0", &json!({}));
+		other_head.set_head(Path::new("/tmp/repo"), "def456");
+		assert_eq!(other_head.get("mitre/git", "commit_diffs", &key), None);
+
+		let mut other_config = HcResultCache::new(tmp.path());
+		other_config.add_plugin("mitre/git", "0.1.0", &json!({"limit": 10}));
+		other_config.set_head(Path::new("/tmp/repo"), "abc123");
+		assert_eq!(other_config.get("mitre/git", "commit_diffs", &key), None);
+	}
+
+	#[test]
+	fn cleared_head_can_be_set_again() {
+		let tmp = tempfile::tempdir().unwrap();
+		let key = json!({"path": "/tmp/repo"});
+
+		let mut cache = HcResultCache::new(tmp.path());
+		cache.add_plugin("mitre/git", "0.1.0", &json!({}));
+		cache.set_head(Path::new("/tmp/repo"), "abc123");
+		cache
+			.put("mitre/git", "commit_diffs", &key, &result())
+			.unwrap();
+
+		cache.clear_head();
+		assert_eq!(cache.get("mitre/git", "commit_diffs", &key), None);
+
+		cache.set_head(Path::new("/tmp/repo"), "def456");
+		assert_eq!(cache.get("mitre/git", "commit_diffs", &key), None);
+
+		cache.clear_head();
+		cache.set_head(Path::new("/tmp/repo"), "abc123");
+		assert_eq!(cache.get("mitre/git", "commit_diffs", &key), Some(result()));
+	}
+
+	#[test]
+	fn network_results_go_stale() {
+		let tmp = tempfile::tempdir().unwrap();
+		let key = json!({"owner": "mitre", "repo": "hipcheck"});
+
+		let mut cache = HcResultCache::new(tmp.path());
+		cache.add_plugin("mitre/github", "0.1.0", &json!({}));
+		cache.set_max_age("mitre/github", NETWORK_RESULT_MAX_AGE);
+		cache.set_head(Path::new("/tmp/repo"), "abc123");
+		cache
+			.put("mitre/github", "has_fuzz", &key, &result())
+			.unwrap();
+		assert_eq!(cache.get("mitre/github", "has_fuzz", &key), Some(result()));
+
+		let mut later = HcResultCache::new(tmp.path());
+		later.add_plugin("mitre/github", "0.1.0", &json!({}));
+		later.set_max_age("mitre/github", Duration::ZERO);
+		later.set_head(Path::new("/tmp/repo"), "abc123");
+		assert_eq!(later.get("mitre/github", "has_fuzz", &key), None);
+
+		// Storing the result again makes it fresh
+		later.set_max_age("mitre/github", NETWORK_RESULT_MAX_AGE);
+		later
+			.put("mitre/github", "has_fuzz", &key, &result())
+			.unwrap();
+		assert_eq!(later.get("mitre/github", "has_fuzz", &key), Some(result()));
+	}
+
+	#[test]
+	fn purge_deletes_one_plugin() {
+		let tmp = tempfile::tempdir().unwrap();
+		let key = json!(null);
+
+		let mut cache = HcResultCache::new(tmp.path());
+		cache.add_plugin("mitre/git", "0.1.0", &json!({}));
+		cache.add_plugin("mitre/activity", "0.1.0", &json!({}));
+		cache.set_head(Path::new("/tmp/repo"), "abc123");
+		cache.put("mitre/git", "", &key, &result()).unwrap();
+		cache.put("mitre/activity", "", &key, &result()).unwrap();
+
+		HcResultCache::purge(tmp.path(), Some("mitre/git"), true).unwrap();
+
+		let entries = ResultCacheEntry::collect(&results_root(tmp.path()), None).unwrap();
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].plugin, "mitre/activity");
+	}
+
+	#[test]
+	fn previous_head_is_remembered() {
+		let tmp = tempfile::tempdir().unwrap();
+		let repo = Path::new("/tmp/repo");
+		let key = |head: &str| json!({"path": "/tmp/repo", "git_ref": head});
+
+		let mut cache = HcResultCache::new(tmp.path());
+		cache.add_plugin("mitre/git", "0.1.0", &json!({}));
+		cache.set_head(repo, "abc123");
+		assert_eq!(cache.previous_head(), None);
+		cache
+			.put("mitre/git", "commit_diffs", &key("abc123"), &result())
+			.unwrap();
+
+		let mut newer = HcResultCache::new(tmp.path());
+		newer.add_plugin("mitre/git", "0.1.0", &json!({}));
+		newer.set_head(repo, "def456");
+		assert_eq!(newer.previous_head().as_deref(), Some("abc123"));
+		assert_eq!(
+			newer.previous_value("mitre/git", "commit_diffs", &key("abc123")),
+			Some(json!(42))
+		);
+		assert_eq!(
+			newer.previous_value("mitre/git", "commit_diffs", &key("def456")),
+			None
+		);
+
+		// Checking the same commit again still builds on the one before it
+		newer.clear_head();
+		newer.set_head(repo, "def456");
+		assert_eq!(newer.previous_head().as_deref(), Some("abc123"));
+
+		// Other repositories have their own history
+		newer.clear_head();
+		newer.set_head(Path::new("/tmp/other"), "def456");
+		assert_eq!(newer.previous_head(), None);
+	}
+}
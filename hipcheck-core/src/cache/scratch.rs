@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Scratch directories plugins may use for temporary files during a run.
+//!
+//! Scratch directories are stored with the format
+//! `<path_to_cache>/scratch/<run>/<publisher>/<plugin_name>`, where `<run>` is
+//! the process ID of the Hipcheck run which created them.
+
+use crate::{error::Result, util::fs::create_dir_all};
+use pathbuf::pathbuf;
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	process,
+};
+
+/// The scratch directories for the plugins of a single run of Hipcheck.
+///
+/// The directories for the whole run are deleted when this is dropped.
+#[derive(Debug)]
+pub struct HcScratchCache {
+	/// The directory holding every plugin's scratch directory for this run.
+	run_dir: PathBuf,
+}
+
+impl HcScratchCache {
+	pub fn new(path: &Path) -> Self {
+		HcScratchCache {
+			run_dir: scratch_root(path).join(process::id().to_string()),
+		}
+	}
+
+	/// Create the scratch directory for a plugin, given as `<publisher>/<name>`.
+	pub fn plugin_scratch_dir(&self, plugin_name: &str) -> Result<PathBuf> {
+		let dir = plugin_name
+			.split('/')
+			.fold(self.run_dir.clone(), |dir, part| dir.join(part));
+		create_dir_all(&dir)?;
+		Ok(dir)
+	}
+
+	/// Delete scratch directories left behind by runs which didn't exit cleanly.
+	///
+	/// This must not be called while another Hipcheck run is in progress, as
+	/// it would delete that run's scratch directories too.
+	pub fn delete_leftovers(path: &Path) -> Result<()> {
+		let root = scratch_root(path);
+
+		if root.exists() {
+			tracing::debug!("deleting leftover plugin scratch directories");
+			fs::remove_dir_all(&root)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl Drop for HcScratchCache {
+	fn drop(&mut self) {
+		if self.run_dir.exists() {
+			if let Err(e) = fs::remove_dir_all(&self.run_dir) {
+				tracing::warn!(
+					"failed to delete plugin scratch directory '{}': {}",
+					self.run_dir.display(),
+					e
+				);
+			}
+		}
+	}
+}
+
+/// The directory holding the scratch directories of every run.
+fn scratch_root(path: &Path) -> PathBuf {
+	pathbuf![path, "scratch"]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn scratch_dirs_are_deleted_on_drop() {
+		let home = tempfile::tempdir().unwrap();
+		let scratch = HcScratchCache::new(home.path());
+
+		let dir = scratch.plugin_scratch_dir("mitre/git").unwrap();
+		assert!(dir.ends_with("mitre/git"));
+		assert!(dir.is_dir());
+
+		drop(scratch);
+		assert!(!dir.exists());
+	}
+
+	#[test]
+	fn leftovers_are_deleted() {
+		let home = tempfile::tempdir().unwrap();
+		let scratch = HcScratchCache::new(home.path());
+		let dir = scratch.plugin_scratch_dir("mitre/git").unwrap();
+		// Simulate a run which exited without cleaning up.
+		std::mem::forget(scratch);
+
+		HcScratchCache::delete_leftovers(home.path()).unwrap();
+		assert!(!dir.exists());
+	}
+}
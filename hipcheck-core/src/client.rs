@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Running Hipcheck from other Rust programs.
+//!
+//! [`Client`] runs the same analyses as `hc check`, but takes its target and
+//! policy as values and hands back the [`Report`] instead of printing it.
+
+use crate::{
+	error::{Context as _, Result},
+	exec::ExecConfig,
+	init::init_library,
+	policy::PolicyFile,
+	report::{
+		report_builder::{build_report, Report},
+		Format,
+	},
+	score::score_results,
+	session::{resolver::TargetRequest, Session},
+	shell::{spinner_phase::SpinnerPhase, Shell},
+	source::{
+		git::{complete_clone, is_partial_clone},
+		SourceQuery as _,
+	},
+	target::TargetSeed,
+	util::network::is_offline,
+};
+use std::{path::PathBuf, time::Duration};
+
+/// Runs Hipcheck's analyses in-process.
+///
+/// Plugins, clones of target repositories, and cached plugin results are kept
+/// in the client's home directory, the same as the cache directory `hc` uses,
+/// so a client and `hc` can share one.
+#[derive(Debug, Clone)]
+pub struct Client {
+	/// Where plugins, clones, and cached results are kept.
+	home: PathBuf,
+	/// How plugins are run.
+	exec: ExecConfig,
+	/// Whether to report every concern, instead of summarizing.
+	all_concerns: bool,
+}
+
+impl Client {
+	/// Make a client which keeps its plugins, clones, and cached results under
+	/// `home`, and runs plugins with the default exec config.
+	pub fn new(home: impl Into<PathBuf>) -> Result<Client> {
+		init_library();
+
+		Ok(Client {
+			home: home.into(),
+			exec: ExecConfig::default()?,
+			all_concerns: false,
+		})
+	}
+
+	/// Run plugins with the given exec config instead of the default.
+	pub fn with_exec_config(mut self, exec: ExecConfig) -> Client {
+		self.exec = exec;
+		self
+	}
+
+	/// Report every concern, instead of only the first ones of each analysis.
+	pub fn with_all_concerns(mut self, all_concerns: bool) -> Client {
+		self.all_concerns = all_concerns;
+		self
+	}
+
+	/// Analyze a target against a policy.
+	///
+	/// The plugins the policy uses are started for each call, and shut down
+	/// before it returns.
+	pub fn analyze(&self, target: TargetSeed, policy: PolicyFile) -> Result<Report> {
+		let target = TargetRequest::Seed(target);
+
+		analyze_complete(
+			|| {
+				Session::with_policy(
+					&target,
+					policy.clone(),
+					self.exec.clone(),
+					self.home.clone(),
+					Format::Json,
+				)
+			},
+			self.all_concerns,
+		)
+	}
+}
+
+/// Start a session and analyze its target.
+///
+/// Analyses may fail for lack of history in a shallow or partial clone, so if
+/// any do, the clone is completed and a new session analyzes it once more.
+pub fn analyze_complete<F>(start: F, all_concerns: bool) -> Result<Report>
+where
+	F: Fn() -> Result<Session>,
+{
+	let session = start()?;
+	let report = analyze(&session, all_concerns)?;
+
+	let local = session.local();
+	if !report.has_errored_analyses() || !is_partial_clone(&local) || is_offline() {
+		return Ok(report);
+	}
+
+	Shell::eprintln(
+		"warning: some analyses failed on a shallow or partial clone; fetching the full history and analyzing again",
+	);
+	drop(session);
+	complete_clone(&local)?;
+
+	let session = start()?;
+	analyze(&session, all_concerns)
+}
+
+/// Run the analyses for a session's target and build its report.
+pub fn analyze(session: &Session, all_concerns: bool) -> Result<Report> {
+	// Run analyses against a repo and score the results (score calls analyses that call metrics).
+	let phase = SpinnerPhase::start("analyzing and scoring results");
+
+	// Enable steady ticking on the spinner, since we currently don't increment it manually.
+	phase.enable_steady_tick(Duration::from_millis(250));
+
+	let scoring = score_results(&phase, session)?;
+
+	phase.finish_successful();
+
+	// Build the final report.
+	let report =
+		build_report(session, &scoring, all_concerns).context("failed to build final report")?;
+
+	Ok(report)
+}
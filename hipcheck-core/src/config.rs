@@ -473,6 +473,9 @@ pub trait ConfigSource: salsa::Database {
 pub trait RiskConfigQuery: ConfigSource {
 	/// Returns the risk policy expr
 	fn risk_policy(&self) -> Result<Rc<Expr>>;
+
+	/// Returns the "use with conditions" policy expr, if one is configured
+	fn conditions_policy(&self) -> Result<Option<Rc<Expr>>>;
 }
 
 pub static DEFAULT_QUERY: &str = "";
@@ -595,6 +598,22 @@ impl AnalysisTree {
 		)
 	}
 
+	/// Get each analysis with its share of the risk score, which is the product
+	/// of its normalized weight and the weights of every category above it.
+	pub fn get_analysis_weights(&self) -> Vec<(Analysis, f64)> {
+		visit_leaves(
+			self.root,
+			&self.tree,
+			|n| n.get_weight().into_inner(),
+			|scope, n| match n {
+				AnalysisTreeNode::Analysis { analysis, .. } => {
+					(analysis.0.clone(), scope.iter().product())
+				}
+				AnalysisTreeNode::Category { .. } => unreachable!(),
+			},
+		)
+	}
+
 	pub fn node_is_category(&self, id: NodeId) -> Result<bool> {
 		let node_ref = self.tree.get(id).ok_or(hc_error!("node not in tree"))?;
 		Ok(matches!(node_ref.get(), AnalysisTreeNode::Category { .. }))
@@ -837,3 +856,20 @@ fn risk_policy(db: &dyn RiskConfigQuery) -> Result<Rc<Expr>> {
 
 	Ok(Rc::new(expr))
 }
+
+fn conditions_policy(db: &dyn RiskConfigQuery) -> Result<Option<Rc<Expr>>> {
+	let policy = db.policy();
+	let Some(conditions_policy) = &policy.analyze.conditions_policy else {
+		return Ok(None);
+	};
+	let expr_str = conditions_policy.0.as_str();
+	let expr = std_parse(expr_str).map_err(|e| {
+		hc_error!(
+			"Malformed use-with-conditions policy expression '{}': {}",
+			expr_str,
+			e
+		)
+	})?;
+
+	Ok(Some(Rc::new(expr)))
+}
@@ -0,0 +1,911 @@
+// SPDX-License-Identifier: Apache-2.0
+
+pub use crate::plugin::{HcPluginCore, PluginExecutor, PluginWithConfig};
+use crate::{
+	cache::{
+		plugin::HcPluginCache,
+		plugin_data::HcPluginDataCache,
+		remote::HcRemoteFileCache,
+		results::{HcResultCache, NETWORK_RESULT_MAX_AGE},
+		scratch::HcScratchCache,
+	},
+	exec::{PluginLimits, PluginMirrors, PluginSecrets, PluginTrust, QueryTimeouts},
+	hc_error,
+	plugin::{
+		get_current_arch, get_plugin_key, retrieve_plugins, ActivePlugin, Plugin, PluginManifest,
+		PluginResponse, QueryResult,
+	},
+	policy::PolicyFile,
+	policy_exprs::Expr,
+	util::network::is_offline,
+	Error, Result,
+};
+use futures::{
+	future::{self, BoxFuture, FutureExt, Shared},
+	stream::{self, StreamExt},
+};
+use serde_json::Value;
+use std::{
+	collections::{hash_map::Entry, HashMap, HashSet},
+	future::Future,
+	ops::Not,
+	result::Result as StdResult,
+	sync::{Arc, LazyLock, Mutex, Weak},
+	time::Duration,
+};
+use tokio::{
+	runtime::{Handle, Runtime},
+	time::{timeout_at, Instant},
+};
+use tracing::Instrument as _;
+
+// Salsa doesn't natively support async functions, so our `query()` function that interacts
+// with plugins (which use async) hands the query to the session's `QueryDispatcher` and blocks
+// on the underlying runtime until it's answered.
+
+static RUNTIME: LazyLock<Runtime> = LazyLock::new(|| Runtime::new().unwrap());
+
+/// The span log events about a query happen in, naming the plugin and query.
+///
+/// It's at the error level so that it's enabled whenever any events are logged.
+fn query_span(publisher: &str, plugin: &str, query: &str) -> tracing::Span {
+	tracing::error_span!("query", publisher, plugin, query)
+}
+
+#[salsa::query_group(HcEngineStorage)]
+pub trait HcEngine: salsa::Database {
+	#[salsa::input]
+	fn core(&self) -> Arc<HcPluginCore>;
+
+	/// Runs the session's queries to plugins, each distinct query only once.
+	#[salsa::input]
+	fn dispatcher(&self) -> Arc<QueryDispatcher>;
+
+	fn default_policy_expr(&self, publisher: String, plugin: String) -> Result<Option<Expr>>;
+
+	fn default_query_explanation(
+		&self,
+		publisher: String,
+		plugin: String,
+	) -> Result<Option<String>>;
+
+	fn query(
+		&self,
+		publisher: String,
+		plugin: String,
+		query: String,
+		key: Value,
+	) -> Result<QueryResult>;
+}
+
+fn default_policy_expr(
+	db: &dyn HcEngine,
+	publisher: String,
+	plugin: String,
+) -> Result<Option<Expr>> {
+	let core = db.core();
+	let key = get_plugin_key(publisher.as_str(), plugin.as_str());
+	let Some(p_handle) = core.plugins.get(&key) else {
+		return Err(hc_error!("No such plugin {}", key));
+	};
+	Ok(p_handle.get_default_policy_expr().cloned())
+}
+
+fn default_query_explanation(
+	db: &dyn HcEngine,
+	publisher: String,
+	plugin: String,
+) -> Result<Option<String>> {
+	let core = db.core();
+	let key = get_plugin_key(publisher.as_str(), plugin.as_str());
+	let Some(p_handle) = core.plugins.get(&key) else {
+		return Err(hc_error!("Plugin '{}' not found", key,));
+	};
+	Ok(p_handle.get_default_query_explanation().cloned())
+}
+
+fn query(
+	db: &dyn HcEngine,
+	publisher: String,
+	plugin: String,
+	query: String,
+	key: Value,
+) -> Result<QueryResult> {
+	// The dispatcher shares the query with any identical one already running,
+	// such as an analysis' query of a plugin another analysis is also querying
+	db.dispatcher()
+		.query_blocking(publisher, plugin, query, key)
+}
+
+/// Whether a query made by a plugin is one Hipcheck answers itself from the
+/// plugin results cache, rather than passing it on to another plugin.
+fn is_cache_query(publisher: &str, plugin: &str) -> bool {
+	publisher == "hipcheck" && plugin == "cache"
+}
+
+/// Answer a plugin's query about its results from the previous run against
+/// the target, so it can build on them instead of starting over.
+///
+/// `previous_head` gives the HEAD commit the previous run analyzed, and
+/// `previous_result` the value of one of the plugin's own queries in that
+/// run, given as `{ "query": <name>, "key": <key> }`. Either is `null` if
+/// there's nothing to build on.
+fn answer_cache_query(
+	core: &HcPluginCore,
+	caller: &str,
+	query: &str,
+	key: &Value,
+) -> Result<Value> {
+	let Some(results) = core.results() else {
+		return Ok(Value::Null);
+	};
+
+	match query {
+		"previous_head" => Ok(results.previous_head().map_or(Value::Null, Value::String)),
+		"previous_result" => {
+			let (Some(query), Some(key)) = (key["query"].as_str(), key.get("key")) else {
+				return Err(hc_error!(
+					"hipcheck/cache/previous_result must be given a query and key"
+				));
+			};
+			Ok(results
+				.previous_value(caller, query, key)
+				.unwrap_or(Value::Null))
+		}
+		_ => Err(hc_error!("No such query hipcheck/cache/{}", query)),
+	}
+}
+
+/// Await a plugin's response, unless the deadline for the query passes first.
+async fn until<F>(
+	plugin_name: &str,
+	deadline: Option<(Duration, Instant)>,
+	response: F,
+) -> Result<PluginResponse>
+where
+	F: Future<Output = Result<PluginResponse>>,
+{
+	let Some((timeout, deadline)) = deadline else {
+		return response.await;
+	};
+
+	timeout_at(deadline, response).await.map_err(|_| {
+		tracing::warn!(?timeout, "query timed out");
+		Error::timed_out(plugin_name.to_owned(), timeout)
+	})?
+}
+
+/// Tells a plugin to stop working on a query when dropped, unless the plugin
+/// answered it.
+///
+/// Queries are abandoned when they time out, when a query the plugin made
+/// fails, or when nothing is waiting on their result anymore. Without being
+/// told, the plugin would keep working on them, or wait forever for the answer
+/// to a query it made.
+struct CancelOnDrop<'p> {
+	plugin: &'p ActivePlugin,
+	id: usize,
+	answered: bool,
+}
+
+impl<'p> CancelOnDrop<'p> {
+	fn new(plugin: &'p ActivePlugin, id: usize) -> Self {
+		CancelOnDrop {
+			plugin,
+			id,
+			answered: false,
+		}
+	}
+
+	/// Record that the plugin answered the query, so there's nothing to cancel.
+	fn answered(mut self) {
+		self.answered = true;
+	}
+}
+
+impl Drop for CancelOnDrop<'_> {
+	fn drop(&mut self) {
+		if self.answered.not() {
+			self.plugin.cancel_query(self.id);
+		}
+	}
+}
+
+/// A query which may be shared by everything waiting on its result.
+pub type SharedQuery = Shared<BoxFuture<'static, Result<QueryResult>>>;
+
+/// A query to a plugin, by plugin, query name, and key.
+type QueryKey = (String, String, String);
+
+fn query_key(publisher: &str, plugin: &str, query: &str, key: &Value) -> QueryKey {
+	(
+		get_plugin_key(publisher, plugin),
+		query.to_owned(),
+		key.to_string(),
+	)
+}
+
+/// Which queries to plugins are waiting on the answers to which others.
+///
+/// Only queries made by plugins while answering another query are recorded,
+/// since only those can wait on themselves.
+#[derive(Debug, Default)]
+struct WaitsFor {
+	/// The queries each query is waiting on, once for each time it's waiting.
+	edges: HashMap<QueryKey, Vec<QueryKey>>,
+}
+
+impl WaitsFor {
+	/// Record that `waiter` is waiting on `query`, unless `query` is already
+	/// waiting on `waiter`, directly or through other queries. Then the wait
+	/// would never end, and the cycle of queries is returned instead, starting
+	/// and ending with `query`.
+	fn wait(&mut self, waiter: &QueryKey, query: &QueryKey) -> StdResult<(), Vec<QueryKey>> {
+		if let Some(mut cycle) = self.path(query, waiter) {
+			cycle.push(query.clone());
+			return Err(cycle);
+		}
+		self.edges
+			.entry(waiter.clone())
+			.or_default()
+			.push(query.clone());
+		Ok(())
+	}
+
+	/// Record that `waiter` is no longer waiting on `query`.
+	fn done(&mut self, waiter: &QueryKey, query: &QueryKey) {
+		let Entry::Occupied(mut entry) = self.edges.entry(waiter.clone()) else {
+			return;
+		};
+		if let Some(i) = entry.get().iter().position(|waited| waited == query) {
+			entry.get_mut().swap_remove(i);
+		}
+		if entry.get().is_empty() {
+			entry.remove();
+		}
+	}
+
+	/// Find the queries through which `from` is waiting on `to`, if it is.
+	fn path(&self, from: &QueryKey, to: &QueryKey) -> Option<Vec<QueryKey>> {
+		let mut seen = HashSet::new();
+		let mut paths = vec![vec![from.clone()]];
+		while let Some(path) = paths.pop() {
+			let last = path.last()?;
+			if last == to {
+				return Some(path);
+			}
+			if seen.insert(last.clone()).not() {
+				continue;
+			}
+			for next in self.edges.get(last).into_iter().flatten() {
+				let mut path = path.clone();
+				path.push(next.clone());
+				paths.push(path);
+			}
+		}
+		None
+	}
+}
+
+/// Stops recording that one query is waiting on another when dropped, whether
+/// the wait ended with an answer or was abandoned.
+struct Waiting {
+	dispatcher: Weak<QueryDispatcher>,
+	waiter: QueryKey,
+	query: QueryKey,
+}
+
+impl Drop for Waiting {
+	fn drop(&mut self) {
+		if let Some(dispatcher) = self.dispatcher.upgrade() {
+			dispatcher
+				.waits_for
+				.lock()
+				.unwrap()
+				.done(&self.waiter, &self.query);
+		}
+	}
+}
+
+/// Runs queries to plugins concurrently on the engine's runtime.
+///
+/// Salsa answers one query at a time, so analyses queried through `HcEngine`
+/// run one after another even when they don't depend on each other. Queries
+/// made through the dispatcher instead run as tasks, and each distinct query,
+/// including those plugins make to each other, runs only once: everything
+/// making the same query shares its result.
+///
+/// Each session has one dispatcher, which `HcEngine::query` goes through too,
+/// so a query several analyses depend on, like `mitre/git/commit_diffs` of the
+/// target, is answered once for the whole session, whether the analyses ask
+/// for it at the same time or one after another.
+///
+/// Since a query joins an identical one already running, a query which
+/// depends on its own result would wait on itself forever, whether it was
+/// made again while answering itself or started separately by another
+/// analysis. The dispatcher records which queries are waiting on which, and
+/// fails a query made by a plugin instead of joining it if the wait would
+/// close a cycle.
+pub struct QueryDispatcher {
+	core: Arc<HcPluginCore>,
+	/// The queries started so far, by plugin, query name, and key.
+	started: Mutex<HashMap<QueryKey, SharedQuery>>,
+	/// Which running queries are waiting on which others.
+	waits_for: Mutex<WaitsFor>,
+}
+
+impl std::fmt::Debug for QueryDispatcher {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("QueryDispatcher")
+			.field("core", &self.core)
+			.field("started", &self.started.lock().unwrap().len())
+			.finish()
+	}
+}
+
+impl QueryDispatcher {
+	pub fn new(core: Arc<HcPluginCore>) -> Arc<Self> {
+		Arc::new(QueryDispatcher {
+			core,
+			started: Mutex::new(HashMap::new()),
+			waits_for: Mutex::new(WaitsFor::default()),
+		})
+	}
+
+	pub fn core(&self) -> &HcPluginCore {
+		&self.core
+	}
+
+	/// Start a query, or join it if it's already been started.
+	///
+	/// A query which has already finished is joined too, so its result is
+	/// reused rather than asking the plugin again.
+	pub fn query(
+		self: &Arc<Self>,
+		publisher: String,
+		plugin: String,
+		query: String,
+		key: Value,
+	) -> SharedQuery {
+		let query_key = query_key(&publisher, &plugin, &query, &key);
+		self.start_or_join(query_key, publisher, plugin, query, key)
+	}
+
+	/// Start or join a query made by a plugin while answering `waiter`,
+	/// failing it if `waiter` can't be answered until it is.
+	fn query_for(
+		self: &Arc<Self>,
+		waiter: &QueryKey,
+		publisher: String,
+		plugin: String,
+		query: String,
+		key: Value,
+	) -> SharedQuery {
+		let query_key = query_key(&publisher, &plugin, &query, &key);
+
+		if let Err(cycle) = self.waits_for.lock().unwrap().wait(waiter, &query_key) {
+			let cycle = cycle
+				.iter()
+				.map(|(plugin, query, _)| format!("{}/{}", plugin, query))
+				.collect::<Vec<_>>()
+				.join(" -> ");
+			let error = hc_error!(
+				"query {}/{}/{} with key {} depends on its own result: {}",
+				publisher,
+				plugin,
+				query,
+				key,
+				cycle
+			);
+			return future::ready(Err(error)).boxed().shared();
+		}
+
+		let waiting = Waiting {
+			dispatcher: Arc::downgrade(self),
+			waiter: waiter.clone(),
+			query: query_key.clone(),
+		};
+		let answer = self.start_or_join(query_key, publisher, plugin, query, key);
+		async move {
+			let _waiting = waiting;
+			answer.await
+		}
+		.boxed()
+		.shared()
+	}
+
+	fn start_or_join(
+		self: &Arc<Self>,
+		query_key: QueryKey,
+		publisher: String,
+		plugin: String,
+		query: String,
+		key: Value,
+	) -> SharedQuery {
+		let mut started = self.started.lock().unwrap();
+		match started.entry(query_key) {
+			Entry::Occupied(entry) => {
+				tracing::trace!(%publisher, %plugin, %query, "joining query already started");
+				entry.get().clone()
+			}
+			Entry::Vacant(entry) => {
+				let span = query_span(&publisher, &plugin, &query);
+				// The query holds the dispatcher weakly, since the dispatcher
+				// holds the query
+				let query_key = entry.key().clone();
+				entry
+					.insert(
+						dispatch_query(
+							Arc::downgrade(self),
+							Arc::clone(&self.core),
+							query_key,
+							query,
+							key,
+						)
+						.instrument(span)
+						.boxed()
+						.shared(),
+					)
+					.clone()
+			}
+		}
+	}
+
+	/// Run a query, blocking until it completes.
+	pub fn query_blocking(
+		self: &Arc<Self>,
+		publisher: String,
+		plugin: String,
+		query: String,
+		key: Value,
+	) -> Result<QueryResult> {
+		RUNTIME
+			.handle()
+			.block_on(self.query(publisher, plugin, query, key))
+	}
+
+	/// Run a set of independent queries, at most `limit` of them at once, and
+	/// return their results in the order the queries were given.
+	pub fn query_all(
+		self: &Arc<Self>,
+		queries: Vec<(String, String, String, Value)>,
+		limit: usize,
+	) -> Vec<Result<QueryResult>> {
+		let runtime = RUNTIME.handle();
+		let tasks = stream::iter(queries)
+			.map(|(publisher, plugin, query, key)| {
+				runtime.spawn(self.query(publisher, plugin, query, key))
+			})
+			.buffered(limit.max(1))
+			.map(|joined| joined.unwrap_or_else(|e| Err(hc_error!("query task failed: {}", e))))
+			.collect::<Vec<_>>();
+		runtime.block_on(tasks)
+	}
+}
+
+/// Run a query to a plugin, answering the plugin's own queries through the
+/// dispatcher.
+///
+/// Results saved by an earlier run against the same commit are reused, and the
+/// plugin's answer is saved for later runs.
+async fn dispatch_query(
+	dispatcher: Weak<QueryDispatcher>,
+	core: Arc<HcPluginCore>,
+	query_key: QueryKey,
+	query: String,
+	key: Value,
+) -> Result<QueryResult> {
+	let hash_key = &query_key.0;
+	#[cfg(feature = "print-timings")]
+	let _0 = crate::benchmarking::print_scope_time!(format!("{}/{}", hash_key, &query));
+
+	// Results saved by an earlier run against the same commit can be reused
+	if let Some(result) = core
+		.results()
+		.and_then(|results| results.get(hash_key, &query, &key))
+	{
+		return Ok(result);
+	}
+
+	let result =
+		dispatch_plugin_query(&dispatcher, &core, &query_key, query.clone(), key.clone()).await?;
+
+	if let Some(results) = core.results() {
+		if let Err(e) = results.put(hash_key, &query, &key, &result) {
+			tracing::warn!(error = %e, "failed to cache query result");
+		}
+	}
+
+	Ok(result)
+}
+
+async fn dispatch_plugin_query(
+	dispatcher: &Weak<QueryDispatcher>,
+	core: &HcPluginCore,
+	query_key: &QueryKey,
+	query: String,
+	key: Value,
+) -> Result<QueryResult> {
+	let hash_key = query_key.0.as_str();
+	if core.is_offline_skipped(hash_key) {
+		return Err(Error::skipped("offline"));
+	}
+
+	// Find the plugin
+	let Some(p_handle) = core.plugins.get(hash_key) else {
+		return Err(hc_error!("No such plugin {}", hash_key));
+	};
+	// The timeout covers the whole query, including the time spent answering
+	// the plugin's own queries to other plugins.
+	let deadline = core
+		.timeout(hash_key)
+		.map(|timeout| (timeout, Instant::now() + timeout));
+	// Initiate the query. If remote closed or we got our response immediately,
+	// return
+	// If the query is abandoned before the plugin answers, as when it times
+	// out, the plugin is told to stop working on it
+	let id = p_handle.next_query_id();
+	let cancel = CancelOnDrop::new(p_handle, id);
+	tracing::trace!(?key, "sending query");
+	let mut ar = match until(hash_key, deadline, p_handle.query(id, query, key)).await? {
+		PluginResponse::RemoteClosed => {
+			return Err(hc_error!("Plugin channel closed unexpected"));
+		}
+		PluginResponse::Completed(v) => {
+			cancel.answered();
+			return Ok(v);
+		}
+		PluginResponse::AwaitingResult(a) => a,
+	};
+	// Otherwise, the plugin needs more data to continue. Query the dispatcher
+	// for the needed data, and resume our current query by providing the
+	// plugin the answer.
+	loop {
+		tracing::trace!("Awaiting result, now recursing");
+		let answers = if is_cache_query(&ar.publisher, &ar.plugin) {
+			ar.key
+				.iter()
+				.map(|key| answer_cache_query(core, hash_key, &ar.query, key))
+				.collect::<Result<Vec<_>>>()?
+		} else {
+			let Some(dispatcher) = dispatcher.upgrade() else {
+				return Err(hc_error!("session ended before the query was answered"));
+			};
+			// per RFD 0009, each key is queried independently, so the queries
+			// can run concurrently
+			future::try_join_all(ar.key.iter().map(|key| {
+				dispatcher.query_for(
+					query_key,
+					ar.publisher.clone(),
+					ar.plugin.clone(),
+					ar.query.clone(),
+					key.clone(),
+				)
+			}))
+			.await?
+			.into_iter()
+			// since one key is used per query, there will only be one value
+			// returned and the `pop().unwrap()` is safe
+			.map(|mut result| result.value.pop().unwrap())
+			.collect()
+		};
+		tracing::trace!(?answers, "resuming query");
+		ar = match until(hash_key, deadline, p_handle.resume_query(ar, answers)).await? {
+			PluginResponse::RemoteClosed => {
+				return Err(hc_error!("Plugin channel closed unexpected"));
+			}
+			PluginResponse::Completed(v) => {
+				cancel.answered();
+				return Ok(v);
+			}
+			PluginResponse::AwaitingResult(a) => a,
+		};
+	}
+}
+
+#[salsa::database(HcEngineStorage)]
+pub struct HcEngineImpl {
+	// Query storage
+	storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for HcEngineImpl {}
+
+impl salsa::ParallelDatabase for HcEngineImpl {
+	fn snapshot(&self) -> salsa::Snapshot<Self> {
+		salsa::Snapshot::new(HcEngineImpl {
+			storage: self.storage.snapshot(),
+		})
+	}
+}
+
+impl HcEngineImpl {
+	// Really HcEngineImpl and HcPluginCore do the same thing right now, except HcPluginCore
+	// has an async constructor. If we can manipulate salsa to accept async functions, we
+	// could consider merging the two structs. Although maybe its wise to keep HcPluginCore
+	// independent of Salsa.
+	pub fn new(executor: PluginExecutor, plugins: Vec<PluginWithConfig>) -> Result<Self> {
+		let runtime = RUNTIME.handle();
+		tracing::info!("Starting HcPluginCore");
+		let core = runtime.block_on(HcPluginCore::new(executor, plugins))?;
+		let mut engine = HcEngineImpl {
+			storage: Default::default(),
+		};
+		let core = Arc::new(core);
+		engine.set_dispatcher(QueryDispatcher::new(Arc::clone(&core)));
+		engine.set_core(core);
+		Ok(engine)
+	}
+	pub fn runtime() -> &'static Handle {
+		RUNTIME.handle()
+	}
+	// TODO - "run" function that takes analysis heirarchy and target, and queries each
+	// analysis plugin to kick off the execution
+}
+
+pub fn start_plugins(
+	policy_file: &PolicyFile,
+	plugin_cache: &HcPluginCache,
+	remote_files: &HcRemoteFileCache,
+	scratch: HcScratchCache,
+	plugin_data: &HcPluginDataCache,
+	mut results: HcResultCache,
+	mirrors: &PluginMirrors,
+	trust: &PluginTrust,
+	timeouts: &QueryTimeouts,
+	secrets: &PluginSecrets,
+	limits: &PluginLimits,
+	executor: PluginExecutor,
+) -> Result<Arc<HcPluginCore>> {
+	let current_arch = get_current_arch();
+
+	// retrieve, verify and extract all required plugins
+	let required_plugin_names =
+		retrieve_plugins(&policy_file.plugins.0, plugin_cache, mirrors, trust)?;
+
+	let mut plugins = vec![];
+	let mut prerequisites = HashMap::new();
+	let mut network_use = HashMap::new();
+	for plugin_id in required_plugin_names.iter() {
+		let plugin_kdl = plugin_cache.plugin_kdl(plugin_id);
+		let working_dir = plugin_kdl
+			.parent()
+			.expect("The plugin.kdl is always in the plugin cache")
+			.to_owned();
+		let plugin_manifest = PluginManifest::from_file(plugin_kdl)?;
+		let entrypoint = plugin_manifest
+			.get_entrypoint(&current_arch)
+			.ok_or_else(|| {
+				hc_error!(
+					"Could not find {} entrypoint for {}",
+					current_arch,
+					plugin_id
+				)
+			})?;
+
+		let name = plugin_id.to_policy_file_plugin_identifier();
+		let dependencies = plugin_manifest
+			.dependencies
+			.0
+			.iter()
+			.map(|dependency| dependency.as_ref().to_policy_file_plugin_identifier())
+			.collect::<Vec<_>>();
+		network_use.insert(
+			name.clone(),
+			(plugin_manifest.requires_network, dependencies),
+		);
+		if plugin_manifest.prerequisites.0.is_empty().not() {
+			prerequisites.insert(name.clone(), plugin_manifest.prerequisites.0);
+		}
+		let scratch_dir = scratch.plugin_scratch_dir(&name)?;
+		let data_dir = plugin_data.plugin_data_dir(plugin_id)?;
+		let secrets = secrets.for_plugin(&name);
+		let limits = limits.for_plugin(&name);
+
+		let plugin = Plugin {
+			name,
+			working_dir,
+			entrypoint,
+			scratch_dir: Some(scratch_dir),
+			data_dir: Some(data_dir),
+			secrets,
+			limits,
+		};
+
+		// find and serialize config for plugin
+		let config = policy_file
+			.get_config(plugin_id.to_policy_file_plugin_identifier().as_str())
+			.ok_or_else(|| hc_error!("Could not find config for {}", plugin_id))?;
+		let mut config = serde_json::to_value(&config).map_err(|_e| {
+			hc_error!(
+				"Error serializing config for {}",
+				plugin_id.to_policy_file_plugin_identifier()
+			)
+		})?;
+		// Files the plugin is configured with by URL are given to it as local copies
+		remote_files.localize_config(&mut config)?;
+
+		results.add_plugin(
+			&plugin_id.to_policy_file_plugin_identifier(),
+			&plugin_id.version().0,
+			&config,
+		);
+
+		let plugin_with_config = PluginWithConfig(plugin, config);
+		plugins.push(plugin_with_config);
+	}
+
+	let needing_network = plugins_needing_network(&network_use);
+	// What plugins fetch changes without the target changing, so their results
+	// are only reused for a while
+	for name in &needing_network {
+		results.set_max_age(name, NETWORK_RESULT_MAX_AGE);
+	}
+
+	// Offline, analyses can't use plugins which need the network
+	let offline_skipped = if is_offline() {
+		needing_network
+	} else {
+		HashSet::new()
+	};
+
+	let runtime = RUNTIME.handle();
+	let core = runtime
+		.block_on(HcPluginCore::new(executor, plugins))?
+		.with_scratch(scratch)
+		.with_results(results)
+		.with_timeouts(timeouts.clone())
+		.with_prerequisites(prerequisites)
+		.with_offline_skipped(offline_skipped);
+	Ok(Arc::new(core))
+}
+
+/// Find the plugins which use the network, either themselves or through any of
+/// their dependencies.
+///
+/// Plugins are given by `<publisher>/<name>`, with whether their manifest says
+/// they use the network and the names of their dependencies.
+fn plugins_needing_network(plugins: &HashMap<String, (bool, Vec<String>)>) -> HashSet<String> {
+	let mut needing: HashSet<String> = plugins
+		.iter()
+		.filter(|(_, (requires_network, _))| *requires_network)
+		.map(|(name, _)| name.clone())
+		.collect();
+
+	// Add the plugins depending on those until there are no more to add
+	loop {
+		let dependents = plugins
+			.iter()
+			.filter(|(name, (_, dependencies))| {
+				needing.contains(*name).not() && dependencies.iter().any(|d| needing.contains(d))
+			})
+			.map(|(name, _)| name.clone())
+			.collect::<Vec<_>>();
+		if dependents.is_empty() {
+			return needing;
+		}
+		needing.extend(dependents);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::exec::ExecConfig;
+	use serde_json::json;
+
+	/// A dispatcher without any plugins, whose queries are started by hand.
+	fn dispatcher() -> Arc<QueryDispatcher> {
+		let executor = ExecConfig::default()
+			.and_then(|config| config.get_plugin_executor())
+			.unwrap();
+		let core = RUNTIME
+			.block_on(HcPluginCore::new(executor, vec![]))
+			.unwrap();
+		QueryDispatcher::new(Arc::new(core))
+	}
+
+	/// Start a query which never finishes, as if its plugin were still working on it.
+	fn start_running(dispatcher: &QueryDispatcher, plugin: &str, query: &str) -> QueryKey {
+		let query_key = query_key("dummy", plugin, query, &json!(1));
+		dispatcher.started.lock().unwrap().insert(
+			query_key.clone(),
+			future::pending::<Result<QueryResult>>().boxed().shared(),
+		);
+		query_key
+	}
+
+	/// Make a query as the plugin answering `waiter` would.
+	fn query_for(dispatcher: &Arc<QueryDispatcher>, waiter: &QueryKey, name: &str) -> SharedQuery {
+		dispatcher.query_for(
+			waiter,
+			"dummy".to_owned(),
+			name.to_owned(),
+			name.to_owned(),
+			json!(1),
+		)
+	}
+
+	#[test]
+	fn queries_started_separately_cant_wait_on_each_other() {
+		let dispatcher = dispatcher();
+		// Two analyses each start a query
+		let a = start_running(&dispatcher, "a", "a");
+		let b = start_running(&dispatcher, "b", "b");
+
+		// The first query's plugin asks for the second, which is joined
+		let a_waits = query_for(&dispatcher, &a, "b");
+		assert!(a_waits.clone().now_or_never().is_none());
+
+		// The second's asks for the first, which can't finish until it does
+		let error = query_for(&dispatcher, &b, "a")
+			.now_or_never()
+			.expect("a cycle should fail at once")
+			.unwrap_err();
+		assert!(
+			error
+				.to_string()
+				.contains("dummy/a/a -> dummy/b/b -> dummy/a/a"),
+			"unexpected error: {}",
+			error
+		);
+
+		// Once the first stops waiting, the second may wait on it
+		drop(a_waits);
+		assert!(query_for(&dispatcher, &b, "a").now_or_never().is_none());
+	}
+
+	#[test]
+	fn query_cant_wait_on_itself() {
+		let dispatcher = dispatcher();
+		let a = start_running(&dispatcher, "a", "a");
+		let answer = query_for(&dispatcher, &a, "a").now_or_never();
+		assert!(matches!(answer, Some(Err(_))));
+	}
+
+	#[test]
+	fn waits_through_other_queries_are_found() {
+		let key = |name: &str| (format!("dummy/{}", name), name.to_owned(), "1".to_owned());
+		let (a, b, c) = (key("a"), key("b"), key("c"));
+		let mut waits_for = WaitsFor::default();
+
+		assert!(waits_for.wait(&a, &b).is_ok());
+		assert!(waits_for.wait(&b, &c).is_ok());
+		assert_eq!(
+			waits_for.wait(&c, &a),
+			Err(vec![a.clone(), b.clone(), c.clone(), a.clone()])
+		);
+
+		waits_for.done(&b, &c);
+		assert!(waits_for.wait(&c, &a).is_ok());
+	}
+
+	#[test]
+	fn network_use_is_inherited_from_dependencies() {
+		let plugins = HashMap::from([
+			("mitre/github".to_owned(), (true, vec![])),
+			(
+				"mitre/review".to_owned(),
+				(false, vec!["mitre/github".to_owned()]),
+			),
+			(
+				"example/wrapper".to_owned(),
+				(false, vec!["mitre/review".to_owned()]),
+			),
+			("mitre/git".to_owned(), (false, vec![])),
+			(
+				"mitre/activity".to_owned(),
+				(false, vec!["mitre/git".to_owned()]),
+			),
+		]);
+
+		let mut needing = plugins_needing_network(&plugins)
+			.into_iter()
+			.collect::<Vec<_>>();
+		needing.sort();
+		assert_eq!(
+			needing,
+			vec!["example/wrapper", "mitre/github", "mitre/review"]
+		);
+	}
+}
@@ -13,12 +13,15 @@
 mod context;
 
 pub use crate::error::context::Context;
+
+use crate::util::secret::redact;
 use std::{
 	borrow::Cow,
 	error::Error as StdError,
 	fmt,
 	fmt::{Debug, Display},
 	sync::Arc,
+	time::Duration,
 };
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -63,7 +66,7 @@ impl Error {
 	{
 		let message: Cow<'static, str> = context.into();
 
-		log::trace!(
+		tracing::trace!(
 			"adding context to error [context: {}, error: {}]",
 			message,
 			self.head
@@ -81,6 +84,39 @@ impl Error {
 	pub fn chain(&self) -> Chain {
 		Chain::new(self)
 	}
+
+	/// Create a new `Error` saying an analysis doesn't apply to the target.
+	///
+	/// Reports list these separately from errors, so users can tell analyses
+	/// which are broken apart from ones which couldn't run for this target.
+	// Plugins can't yet report a skip reason over the query protocol, so only
+	// core skips analyses for now.
+	pub fn skipped(reason: impl Introspect) -> Self {
+		Error::new(Skipped(reason.into()))
+	}
+
+	/// Get the reason for skipping, if anything in the chain was a skip.
+	pub fn skip_reason(&self) -> Option<&str> {
+		self.chain()
+			.find_map(|node| node.current.downcast_ref::<Skipped>())
+			.map(|skipped| skipped.0.as_ref())
+	}
+
+	/// Create a new `Error` saying a query to a plugin was cancelled because
+	/// it ran for longer than its timeout.
+	pub fn timed_out(plugin: impl Introspect, timeout: Duration) -> Self {
+		Error::new(TimedOut {
+			plugin: plugin.into(),
+			timeout,
+		})
+	}
+
+	/// Get the timeout which was exceeded, if anything in the chain timed out.
+	pub fn timeout(&self) -> Option<Duration> {
+		self.chain()
+			.find_map(|node| node.current.downcast_ref::<TimedOut>())
+			.map(|timed_out| timed_out.timeout)
+	}
 }
 
 /// Allows use of `?` operator on query system entry.
@@ -136,7 +172,7 @@ pub struct ErrorNode {
 
 impl Debug for ErrorNode {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		write!(f, "{}", self.current)?;
+		write!(f, "{}", redacted(&self.current))?;
 
 		if self.next.is_some() {
 			write!(f, "\n\nCaused by: ")?;
@@ -145,16 +181,16 @@ impl Debug for ErrorNode {
 			let mut link = self.next.as_ref();
 
 			while let Some(step) = link {
-				write!(f, "\n{:5}: {}", index, step.current)?;
+				write!(f, "\n{:5}: {}", index, redacted(&step.current))?;
 				link = step.next.as_ref();
 				index += 1;
 			}
 
 			match (index, link) {
 				// Only printed one message.
-				(0, Some(step)) => write!(f, "\n    {}", step.current)?,
+				(0, Some(step)) => write!(f, "\n    {}", redacted(&step.current))?,
 				// Printed more than one.
-				(_, Some(step)) => write!(f, "\n{:5}: {}", index, step.current)?,
+				(_, Some(step)) => write!(f, "\n{:5}: {}", index, redacted(&step.current))?,
 				// Nothing to print.
 				(_, None) => {}
 			}
@@ -166,10 +202,15 @@ impl Debug for ErrorNode {
 
 impl Display for ErrorNode {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		write!(f, "{}", self.current)
+		write!(f, "{}", redacted(&self.current))
 	}
 }
 
+/// Render an error's message with any secrets masked.
+fn redacted(error: &ErrorObj) -> String {
+	redact(&error.to_string()).into_owned()
+}
+
 impl StdError for ErrorNode {
 	fn source(&self) -> Option<&(dyn StdError + 'static)> {
 		self.next
@@ -201,6 +242,46 @@ impl StdError for Message {
 	}
 }
 
+/// A reason an analysis was skipped rather than run.
+#[derive(Debug)]
+struct Skipped(Cow<'static, str>);
+
+impl Display for Skipped {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "skipped: {}", self.0)
+	}
+}
+
+impl StdError for Skipped {
+	fn source(&self) -> Option<&(dyn StdError + 'static)> {
+		None
+	}
+}
+
+/// A plugin query which was cancelled for running too long.
+#[derive(Debug)]
+struct TimedOut {
+	plugin: Cow<'static, str>,
+	timeout: Duration,
+}
+
+impl Display for TimedOut {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"query to {} timed out after {}s",
+			self.plugin,
+			self.timeout.as_secs()
+		)
+	}
+}
+
+impl StdError for TimedOut {
+	fn source(&self) -> Option<&(dyn StdError + 'static)> {
+		None
+	}
+}
+
 pub struct Chain<'e> {
 	current: Option<&'e ErrorNode>,
 }
@@ -219,7 +300,7 @@ impl<'e> Iterator for Chain<'e> {
 	fn next(&mut self) -> Option<Self::Item> {
 		match self.current {
 			Some(node) => {
-				log::trace!("error in chain [error: {}]", node);
+				tracing::trace!("error in chain [error: {}]", node);
 
 				self.current = node.next.as_deref();
 				Some(node)
@@ -278,4 +359,23 @@ mod tests {
 		assert_eq!("second error", iter.next().unwrap().to_string());
 		assert_eq!("first error", iter.next().unwrap().to_string());
 	}
+
+	// Verify that skips are found through added context.
+	#[test]
+	fn skip_reason_through_context() {
+		use super::Error;
+
+		let error = Error::skipped("no remote repository").context("query failed");
+		assert_eq!(Some("no remote repository"), error.skip_reason());
+		assert_eq!(None, hc_error!("broken").skip_reason());
+	}
+
+	// Verify that timeouts are found through added context.
+	#[test]
+	fn timeout_through_context() {
+		let timeout = Duration::from_secs(30);
+		let error = Error::timed_out("mitre/git", timeout).context("query failed");
+		assert_eq!(Some(timeout), error.timeout());
+		assert_eq!(None, hc_error!("broken").timeout());
+	}
 }
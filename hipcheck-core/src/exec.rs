@@ -0,0 +1,1610 @@
+// SPDX-License-Identifier: Apache-2.0
+// reference hipcheck/src/plugin/plugin_manifest.rs
+use crate::{
+	cache::repo::{parse_age, RepoCacheLimits},
+	error::Result,
+	hc_error,
+	plugin::{PluginExecutor, ResourceLimits},
+	policy::policy_file::parse_size,
+	shell::Shell,
+	util::{
+		fs::read_string,
+		kdl::{extract_data, ParseKdlNode},
+		secret::Secret,
+	},
+};
+use hipcheck_common::{
+	chunk::{GRPC_MAX_SIZE_BYTES, GRPC_MIN_SIZE_BYTES},
+	plugin_secret::secret_env_var,
+};
+use kdl::{KdlDocument, KdlEntry, KdlNode, KdlValue};
+use std::{
+	collections::HashMap,
+	env,
+	num::NonZeroUsize,
+	path::{Path, PathBuf},
+	str::FromStr,
+	thread,
+	time::Duration,
+};
+use url::Url;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PluginBackoffInterval {
+	/// size of the downloaded artifact, in bytes
+	pub micros: u64,
+}
+
+impl PluginBackoffInterval {
+	#[cfg(test)]
+	pub fn new(micros: u64) -> Self {
+		Self { micros }
+	}
+}
+
+impl ParseKdlNode for PluginBackoffInterval {
+	fn kdl_key() -> &'static str {
+		"backoff-interval"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let specified_duration = node.entries().first()?;
+		let micros = match specified_duration.value() {
+			// Value should be greater than 0
+			KdlValue::Integer(micros) => {
+				let micros = *micros;
+				if micros.is_positive() {
+					micros as u64
+				} else {
+					return None;
+				}
+			}
+			_ => return None,
+		};
+		Some(PluginBackoffInterval { micros })
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PluginMaxSpawnAttempts {
+	/// the number of spawns to attempt
+	pub attempts: usize,
+}
+
+impl PluginMaxSpawnAttempts {
+	#[cfg(test)]
+	pub fn new(attempts: usize) -> Self {
+		Self { attempts }
+	}
+}
+
+impl ParseKdlNode for PluginMaxSpawnAttempts {
+	fn kdl_key() -> &'static str {
+		"max-spawn-attempts"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let specified_attempts = node.entries().first()?;
+		let attempts = match specified_attempts.value() {
+			// Value should be greater than 0
+			KdlValue::Integer(attempts) => {
+				let attempts = *attempts;
+				if attempts.is_positive() {
+					attempts as usize
+				} else {
+					return None;
+				}
+			}
+			_ => return None,
+		};
+		Some(PluginMaxSpawnAttempts { attempts })
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PluginMaxConnectionAttempts {
+	/// the number of spawns to attempt
+	pub attempts: usize,
+}
+
+impl PluginMaxConnectionAttempts {
+	#[cfg(test)]
+	pub fn new(attempts: usize) -> Self {
+		Self { attempts }
+	}
+}
+
+impl ParseKdlNode for PluginMaxConnectionAttempts {
+	fn kdl_key() -> &'static str {
+		"max-conn-attempts"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let specified_attempts = node.entries().first()?;
+		let attempts = match specified_attempts.value() {
+			// Value should be greater than 0
+			KdlValue::Integer(attempts) => {
+				let attempts = *attempts;
+				if attempts.is_positive() {
+					attempts as usize
+				} else {
+					return None;
+				}
+			}
+			_ => return None,
+		};
+		Some(PluginMaxConnectionAttempts { attempts })
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PluginJitterPercent {
+	/// the number of spawns to attempt
+	pub percent: u8,
+}
+
+impl PluginJitterPercent {
+	#[cfg(test)]
+	pub fn new(percent: u8) -> Self {
+		Self { percent }
+	}
+}
+
+impl ParseKdlNode for PluginJitterPercent {
+	fn kdl_key() -> &'static str {
+		"jitter-percent"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let specified_percentage = node.entries().first()?;
+		let percent = match specified_percentage.value() {
+			// Value should be greater than 0
+			KdlValue::Integer(percent) => {
+				let percent = *percent;
+				if percent.is_positive() {
+					percent as u8
+				} else {
+					return None;
+				}
+			}
+			_ => return None,
+		};
+		Some(PluginJitterPercent { percent })
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PluginMsgBufferSize {
+	/// size of the buffer for the grpc buffer
+	pub size: usize,
+}
+
+impl PluginMsgBufferSize {
+	#[cfg(test)]
+	pub fn new(size: usize) -> Self {
+		Self { size }
+	}
+}
+
+impl ParseKdlNode for PluginMsgBufferSize {
+	fn kdl_key() -> &'static str {
+		"grpc-msg-buffer-size"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let specified_size = node.entries().first()?;
+		let size = match specified_size.value() {
+			// Value should be greater than 0
+			KdlValue::Integer(size) => {
+				let size = *size;
+				if size.is_positive() {
+					size as usize
+				} else {
+					return None;
+				}
+			}
+			_ => return None,
+		};
+		Some(PluginMsgBufferSize { size })
+	}
+}
+
+/// The largest gRPC message core sends or accepts on the query protocol, in bytes.
+///
+/// Each plugin may ask for a smaller size when it's configured, and query messages
+/// are chunked to fit whichever size is smaller.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PluginMaxMessageSize {
+	pub bytes: usize,
+}
+
+impl Default for PluginMaxMessageSize {
+	fn default() -> Self {
+		PluginMaxMessageSize {
+			bytes: GRPC_MAX_SIZE_BYTES,
+		}
+	}
+}
+
+impl ParseKdlNode for PluginMaxMessageSize {
+	fn kdl_key() -> &'static str {
+		"grpc-max-message-size"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		match node.entries().first()?.value() {
+			// Value should be at least the smallest size a plugin may negotiate
+			KdlValue::Integer(bytes) if *bytes >= GRPC_MIN_SIZE_BYTES as i128 => {
+				Some(PluginMaxMessageSize {
+					bytes: usize::try_from(*bytes).ok()?,
+				})
+			}
+			_ => None,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PluginConfig {
+	pub backoff: PluginBackoffInterval,
+	pub max_spawn: PluginMaxSpawnAttempts,
+	pub max_conn: PluginMaxConnectionAttempts,
+	pub jitter: PluginJitterPercent,
+	pub grpc_buffer: PluginMsgBufferSize,
+	pub max_message_size: PluginMaxMessageSize,
+}
+
+impl PluginConfig {
+	#[cfg(test)]
+	pub fn new(
+		backoff: PluginBackoffInterval,
+		max_spawn: PluginMaxSpawnAttempts,
+		max_conn: PluginMaxConnectionAttempts,
+		jitter: PluginJitterPercent,
+		grpc_buffer: PluginMsgBufferSize,
+	) -> Self {
+		Self {
+			backoff,
+			max_spawn,
+			max_conn,
+			jitter,
+			grpc_buffer,
+			max_message_size: PluginMaxMessageSize::default(),
+		}
+	}
+}
+
+impl ParseKdlNode for PluginConfig {
+	fn kdl_key() -> &'static str {
+		"plugin"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let nodes = node.children()?.nodes();
+
+		// extract the configured plugin data from the child
+		let backoff: PluginBackoffInterval = extract_data(nodes)?;
+		let max_spawn: PluginMaxSpawnAttempts = extract_data(nodes)?;
+		let max_conn: PluginMaxConnectionAttempts = extract_data(nodes)?;
+		let jitter: PluginJitterPercent = extract_data(nodes)?;
+		let grpc_buffer: PluginMsgBufferSize = extract_data(nodes)?;
+		// The max message size is optional, but an invalid one is an error
+		let max_message_size = match nodes
+			.iter()
+			.find(|node| node.name().to_string().as_str() == PluginMaxMessageSize::kdl_key())
+		{
+			Some(node) => PluginMaxMessageSize::parse_node(node)?,
+			None => PluginMaxMessageSize::default(),
+		};
+
+		Some(Self {
+			backoff,
+			max_spawn,
+			max_conn,
+			jitter,
+			grpc_buffer,
+			max_message_size,
+		})
+	}
+
+	// add to_kdl(&self) & to_kdl_formatted_string from plugin manifest
+}
+
+/// A rule pointing plugin downloads from one URL prefix at a mirror instead.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PluginMirror {
+	/// The URL prefix to replace, e.g. `https://github.com/`
+	pub from: String,
+	/// The URL prefix to replace it with
+	pub to: String,
+}
+
+impl PluginMirror {
+	#[cfg(test)]
+	pub fn new(from: &str, to: &str) -> Self {
+		Self {
+			from: from.to_owned(),
+			to: to.to_owned(),
+		}
+	}
+
+	/// Rewrite a URL to point at the mirror, if the rule applies to it.
+	fn rewrite(&self, url: &Url) -> Option<Result<Url>> {
+		let rest = url.as_str().strip_prefix(self.from.as_str())?;
+		let mirrored = format!("{}{}", self.to, rest);
+		Some(
+			Url::parse(&mirrored)
+				.map_err(|e| hc_error!("invalid mirror URL '{}': {}", mirrored, e)),
+		)
+	}
+}
+
+impl ParseKdlNode for PluginMirror {
+	fn kdl_key() -> &'static str {
+		"mirror"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let from = node.get("from")?.as_string()?.to_string();
+		let to = node.get("to")?.as_string()?.to_string();
+		Some(PluginMirror { from, to })
+	}
+}
+
+/// Rules for rewriting the URLs plugins are downloaded from.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PluginMirrors(pub Vec<PluginMirror>);
+
+impl PluginMirrors {
+	/// Rewrite a URL using the first rule that applies to it, if any.
+	pub fn rewrite(&self, url: &Url) -> Result<Url> {
+		for mirror in &self.0 {
+			if let Some(mirrored) = mirror.rewrite(url) {
+				let mirrored = mirrored?;
+				tracing::debug!("Using mirror {} for {}", mirrored, url);
+				return Ok(mirrored);
+			}
+		}
+		Ok(url.clone())
+	}
+}
+
+impl ParseKdlNode for PluginMirrors {
+	fn kdl_key() -> &'static str {
+		"mirrors"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let mut mirrors = Vec::new();
+		for child in node.children()?.nodes() {
+			mirrors.push(PluginMirror::parse_node(child)?);
+		}
+		Some(PluginMirrors(mirrors))
+	}
+}
+
+/// The plugin registry index `hc plugin` commands use to find plugins.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PluginRegistry(pub Url);
+
+impl ParseKdlNode for PluginRegistry {
+	fn kdl_key() -> &'static str {
+		"registry"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let raw_url = node.entries().first()?.value().as_string()?;
+		Url::parse(raw_url).ok().map(PluginRegistry)
+	}
+}
+
+/// How long a query to a plugin may take before it's cancelled.
+///
+/// The default applies to every plugin without its own timeout. Each timeout
+/// is in seconds, and without any timeout queries are never cancelled.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct QueryTimeouts {
+	pub default: Option<Duration>,
+	/// Timeouts for individual plugins, by `<publisher>/<name>`.
+	pub plugins: HashMap<String, Duration>,
+}
+
+impl QueryTimeouts {
+	/// Get the timeout for queries to a plugin, given as `<publisher>/<name>`.
+	pub fn for_plugin(&self, plugin_name: &str) -> Option<Duration> {
+		self.plugins.get(plugin_name).copied().or(self.default)
+	}
+}
+
+impl ParseKdlNode for QueryTimeouts {
+	fn kdl_key() -> &'static str {
+		"timeouts"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let mut timeouts = QueryTimeouts::default();
+		for child in node.children()?.nodes() {
+			let entries = child.entries();
+			match child.name().to_string().as_str() {
+				"default" => {
+					let [seconds] = entries else {
+						return None;
+					};
+					timeouts.default = Some(parse_seconds(seconds.value())?);
+				}
+				"plugin" => {
+					let [name, seconds] = entries else {
+						return None;
+					};
+					let name = name.value().as_string()?;
+					name.split_once('/')?;
+					timeouts
+						.plugins
+						.insert(name.to_owned(), parse_seconds(seconds.value())?);
+				}
+				_ => return None,
+			}
+		}
+		Some(timeouts)
+	}
+}
+
+/// Parse a positive number of seconds.
+fn parse_seconds(value: &KdlValue) -> Option<Duration> {
+	match value {
+		KdlValue::Integer(seconds) if seconds.is_positive() => {
+			Some(Duration::from_secs(*seconds as u64))
+		}
+		_ => None,
+	}
+}
+
+/// The limits on the resources plugin processes may use.
+///
+/// The default applies to every plugin without its own limits, and a plugin
+/// takes any limit it doesn't set itself from the default. Limits are only
+/// supported on Unix systems.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PluginLimits {
+	pub default: ResourceLimits,
+	/// Limits for individual plugins, by `<publisher>/<name>`.
+	pub plugins: HashMap<String, ResourceLimits>,
+}
+
+impl PluginLimits {
+	/// Get the limits for a plugin, given as `<publisher>/<name>`.
+	pub fn for_plugin(&self, plugin_name: &str) -> ResourceLimits {
+		self.plugins
+			.get(plugin_name)
+			.cloned()
+			.unwrap_or_default()
+			.or(&self.default)
+	}
+}
+
+impl ParseKdlNode for PluginLimits {
+	fn kdl_key() -> &'static str {
+		"limits"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let mut limits = PluginLimits::default();
+		for child in node.children()?.nodes() {
+			let entries = child.entries();
+			match child.name().to_string().as_str() {
+				"default" => {
+					limits.default = parse_resource_limits(entries)?;
+				}
+				"plugin" => {
+					let [name, props @ ..] = entries else {
+						return None;
+					};
+					if name.name().is_some() {
+						return None;
+					}
+					let name = name.value().as_string()?;
+					name.split_once('/')?;
+					limits
+						.plugins
+						.insert(name.to_owned(), parse_resource_limits(props)?);
+				}
+				_ => return None,
+			}
+		}
+		Some(limits)
+	}
+}
+
+/// Parse the `memory`, `cpu-time`, `open-files`, and `user` properties of
+/// a node into resource limits.
+fn parse_resource_limits(entries: &[KdlEntry]) -> Option<ResourceLimits> {
+	let mut limits = ResourceLimits::default();
+	for entry in entries {
+		let value = entry.value();
+		match entry.name()?.value() {
+			"memory" => limits.memory = Some(parse_size(value.as_string()?).ok()?),
+			"cpu-time" => limits.cpu_time = Some(parse_seconds(value)?),
+			"open-files" => match value {
+				KdlValue::Integer(count) if count.is_positive() => {
+					limits.open_files = Some(*count as u64)
+				}
+				_ => return None,
+			},
+			"user" => match value {
+				KdlValue::String(user) if !user.is_empty() => limits.user = Some(user.clone()),
+				KdlValue::Integer(uid) if !uid.is_negative() => limits.user = Some(uid.to_string()),
+				_ => return None,
+			},
+			_ => return None,
+		}
+	}
+	Some(limits)
+}
+
+/// Keeping plugins running after a check, so later checks can reuse them
+/// instead of starting them again.
+///
+/// Plugins are shut down once no check has used them for the idle timeout,
+/// in seconds.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PluginPool {
+	pub idle_timeout: Duration,
+}
+
+impl ParseKdlNode for PluginPool {
+	fn kdl_key() -> &'static str {
+		"plugin-pool"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let idle_timeout = parse_seconds(node.get("idle-timeout")?)?;
+		Some(PluginPool { idle_timeout })
+	}
+}
+
+// The repo cache is pruned to these limits at the start of each `hc check`
+impl ParseKdlNode for RepoCacheLimits {
+	fn kdl_key() -> &'static str {
+		"repo-cache"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let max_size = match node.get("max-size") {
+			Some(size) => Some(parse_size(size.as_string()?).ok()?),
+			None => None,
+		};
+		let max_age = match node.get("max-age") {
+			Some(age) => Some(parse_age(age.as_string()?).ok()?),
+			None => None,
+		};
+		let limits = RepoCacheLimits { max_size, max_age };
+		if limits.is_empty() {
+			return None;
+		}
+		Some(limits)
+	}
+}
+
+/// How many analyses may query their plugins at once.
+///
+/// Defaults to the number of cores available to Hipcheck.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AnalysisConcurrency {
+	pub limit: usize,
+}
+
+impl Default for AnalysisConcurrency {
+	fn default() -> Self {
+		let limit = thread::available_parallelism()
+			.map(NonZeroUsize::get)
+			.unwrap_or(1);
+		AnalysisConcurrency { limit }
+	}
+}
+
+impl ParseKdlNode for AnalysisConcurrency {
+	fn kdl_key() -> &'static str {
+		"max-concurrent-analyses"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		match node.entries().first()?.value() {
+			// Value should be greater than 0
+			KdlValue::Integer(limit) if limit.is_positive() => Some(AnalysisConcurrency {
+				limit: *limit as usize,
+			}),
+			_ => None,
+		}
+	}
+}
+
+/// Where core reads a secret from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SecretSource {
+	/// An environment variable in core's environment.
+	Env(String),
+	/// A file, whose contents are the secret.
+	File(PathBuf),
+}
+
+/// A secret, like an API token, which core gives to some plugins.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PluginSecret {
+	/// The name plugins look the secret up by.
+	pub name: String,
+	/// Where core reads the secret from.
+	pub source: SecretSource,
+	/// The plugins which may have the secret, by `<publisher>/<name>`.
+	pub plugins: Vec<String>,
+}
+
+impl PluginSecret {
+	/// Read the secret from its source.
+	fn read(&self) -> Result<Secret> {
+		let value = match &self.source {
+			SecretSource::Env(var) => {
+				env::var(var).map_err(|_| hc_error!("environment variable '{}' isn't set", var))?
+			}
+			SecretSource::File(path) => read_string(path)?.trim_end().to_owned(),
+		};
+		if value.is_empty() {
+			return Err(hc_error!("secret is empty"));
+		}
+		Ok(Secret::new(value))
+	}
+}
+
+impl ParseKdlNode for PluginSecret {
+	fn kdl_key() -> &'static str {
+		"secret"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let source = match (node.get("env"), node.get("file")) {
+			(Some(var), None) => SecretSource::Env(var.as_string()?.to_owned()),
+			(None, Some(path)) => SecretSource::File(PathBuf::from(path.as_string()?)),
+			_ => return None,
+		};
+		let mut args = node.entries().iter().filter(|entry| entry.name().is_none());
+		let name = args.next()?.value().as_string()?.to_owned();
+		let mut plugins = Vec::new();
+		for plugin in args {
+			let plugin = plugin.value().as_string()?;
+			plugin.split_once('/')?;
+			plugins.push(plugin.to_owned());
+		}
+		if name.is_empty() || plugins.is_empty() {
+			return None;
+		}
+		Some(PluginSecret {
+			name,
+			source,
+			plugins,
+		})
+	}
+}
+
+/// The secrets core gives to plugins when starting them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PluginSecrets(pub Vec<PluginSecret>);
+
+impl PluginSecrets {
+	/// Read the secrets a plugin, given as `<publisher>/<name>`, may have,
+	/// along with the environment variables to pass them in.
+	///
+	/// A secret which can't be read is left out with a warning, since the
+	/// plugin may still work without it.
+	pub fn for_plugin(&self, plugin_name: &str) -> Vec<(String, Secret)> {
+		self.0
+			.iter()
+			.filter(|secret| secret.plugins.iter().any(|p| p == plugin_name))
+			.filter_map(|secret| match secret.read() {
+				Ok(value) => Some((secret_env_var(&secret.name), value)),
+				Err(e) => {
+					Shell::eprintln(format!(
+						"warning: secret '{}' for {} can't be read: {}",
+						secret.name, plugin_name, e
+					));
+					None
+				}
+			})
+			.collect()
+	}
+}
+
+impl ParseKdlNode for PluginSecrets {
+	fn kdl_key() -> &'static str {
+		"secrets"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let mut secrets = Vec::new();
+		for child in node.children()?.nodes() {
+			secrets.push(PluginSecret::parse_node(child)?);
+		}
+		Some(PluginSecrets(secrets))
+	}
+}
+
+/// A public key trusted to sign the plugins of a publisher.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrustedKey {
+	/// The publisher whose plugins the key may sign, or `*` for any publisher.
+	pub publisher: String,
+	/// A PEM-encoded ECDSA P-256 or Ed25519 public key.
+	pub path: PathBuf,
+}
+
+impl ParseKdlNode for TrustedKey {
+	fn kdl_key() -> &'static str {
+		"key"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let publisher = node
+			.entries()
+			.iter()
+			.find(|entry| entry.name().is_none())?
+			.value()
+			.as_string()?
+			.to_owned();
+		let path = PathBuf::from(node.get("file")?.as_string()?);
+		if publisher.is_empty() {
+			return None;
+		}
+		Some(TrustedKey { publisher, path })
+	}
+}
+
+/// The keys trusted to sign downloaded plugins.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PluginTrust(pub Vec<TrustedKey>);
+
+impl PluginTrust {
+	/// Get the keys trusted to sign a publisher's plugins.
+	pub fn keys_for<'a>(&'a self, publisher: &'a str) -> impl Iterator<Item = &'a TrustedKey> {
+		self.0
+			.iter()
+			.filter(move |key| key.publisher == publisher || key.publisher == "*")
+	}
+}
+
+impl ParseKdlNode for PluginTrust {
+	fn kdl_key() -> &'static str {
+		"trust"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let mut keys = Vec::new();
+		for child in node.children()?.nodes() {
+			keys.push(TrustedKey::parse_node(child)?);
+		}
+		Some(PluginTrust(keys))
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecConfig {
+	pub plugin_data: PluginConfig,
+	pub mirrors: PluginMirrors,
+	pub registry: Option<PluginRegistry>,
+	pub timeouts: QueryTimeouts,
+	pub limits: PluginLimits,
+	pub secrets: PluginSecrets,
+	pub concurrency: AnalysisConcurrency,
+	pub trust: PluginTrust,
+	pub pool: Option<PluginPool>,
+	pub repo_cache: Option<RepoCacheLimits>,
+	// Any new configurable data forms can be added here
+}
+
+impl ExecConfig {
+	pub fn from_file<P>(path: P) -> Result<Self>
+	where
+		P: AsRef<Path>,
+	{
+		Self::from_str(&read_string(path)?)
+	}
+
+	pub fn find_file() -> Result<Self> {
+		// Locate file
+		let mut exec_file = "Exec.kdl";
+		let mut curr_dir = env::current_dir()?;
+		let file = curr_dir.join(exec_file);
+		let file_path = file.as_path();
+		if file_path.exists() {
+			// Parse found file
+			tracing::info!("Using Exec Config at {:?}", file_path);
+			return Self::from_file(file_path);
+		}
+
+		// Walk the directory tree for the file
+		exec_file = ".hipcheck/Exec.kdl";
+		loop {
+			let target_path = curr_dir.join(exec_file);
+			let target_ref = target_path.as_path();
+			if target_ref.exists() {
+				// Parse found file
+				tracing::info!("Using Exec Config at {:?}", target_ref);
+				return Self::from_file(target_ref);
+			}
+			if let Some(parent) = curr_dir.parent() {
+				curr_dir = parent.to_path_buf();
+			} else {
+				// If file not found, use default values
+				tracing::info!("Using a default Exec Config");
+				return Self::default();
+			}
+		}
+	}
+
+	pub fn default() -> Result<Self> {
+		// These are the default values
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}"#;
+		Self::from_str(data)
+	}
+
+	pub fn get_plugin_executor(&self) -> Result<PluginExecutor> {
+		let plugin_data = &self.plugin_data;
+		PluginExecutor::new(
+			/* max_spawn_attempts */ plugin_data.max_spawn.attempts,
+			/* max_conn_attempts */ plugin_data.max_conn.attempts,
+			/* port_range */ 40000..u16::MAX,
+			/* backoff_interval_micros */ plugin_data.backoff.micros,
+			/* jitter_percent */ plugin_data.jitter.percent,
+			/*grpc_buffer*/ plugin_data.grpc_buffer.size,
+			/* max_message_size */ plugin_data.max_message_size.bytes,
+		)
+	}
+}
+
+impl FromStr for ExecConfig {
+	type Err = crate::Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		let document = KdlDocument::from_str(s)
+			.map_err(|e| hc_error!("Error parsing exec config file: {}", e))?;
+		let nodes = document.nodes();
+		let plugin_data: PluginConfig = extract_data(nodes).unwrap();
+		// Mirrors are optional, but an invalid `mirrors` node is an error
+		let mirrors = match nodes
+			.iter()
+			.find(|node| node.name().to_string().as_str() == PluginMirrors::kdl_key())
+		{
+			Some(node) => PluginMirrors::parse_node(node).ok_or_else(|| {
+				hc_error!("Error parsing exec config file: each mirror needs 'from' and 'to' URLs")
+			})?,
+			None => PluginMirrors::default(),
+		};
+		// The registry is optional, but an invalid `registry` node is an error
+		let registry = match nodes
+			.iter()
+			.find(|node| node.name().to_string().as_str() == PluginRegistry::kdl_key())
+		{
+			Some(node) => Some(PluginRegistry::parse_node(node).ok_or_else(|| {
+				hc_error!("Error parsing exec config file: the registry must be a URL")
+			})?),
+			None => None,
+		};
+		// Timeouts are optional, but an invalid `timeouts` node is an error
+		let timeouts = match nodes
+			.iter()
+			.find(|node| node.name().to_string().as_str() == QueryTimeouts::kdl_key())
+		{
+			Some(node) => QueryTimeouts::parse_node(node).ok_or_else(|| {
+				hc_error!(
+					"Error parsing exec config file: timeouts must be 'default <seconds>' or 'plugin \"<publisher>/<name>\" <seconds>'"
+				)
+			})?,
+			None => QueryTimeouts::default(),
+		};
+		// Limits are optional, but an invalid `limits` node is an error
+		let limits = match nodes
+			.iter()
+			.find(|node| node.name().to_string().as_str() == PluginLimits::kdl_key())
+		{
+			Some(node) => PluginLimits::parse_node(node).ok_or_else(|| {
+				hc_error!(
+					"Error parsing exec config file: limits must be 'default' or 'plugin \"<publisher>/<name>\"' with memory=\"<size>\", cpu-time=<seconds>, open-files=<count>, or user=\"<user>\""
+				)
+			})?,
+			None => PluginLimits::default(),
+		};
+		// Secrets are optional, but an invalid `secrets` node is an error
+		let secrets = match nodes
+			.iter()
+			.find(|node| node.name().to_string().as_str() == PluginSecrets::kdl_key())
+		{
+			Some(node) => PluginSecrets::parse_node(node).ok_or_else(|| {
+				hc_error!(
+					"Error parsing exec config file: secrets must be 'secret \"<name>\" \"<publisher>/<name>\"... env=\"<variable>\"' or 'file=\"<path>\"'"
+				)
+			})?,
+			None => PluginSecrets::default(),
+		};
+		// The concurrency limit is optional, but an invalid one is an error
+		let concurrency = match nodes
+			.iter()
+			.find(|node| node.name().to_string().as_str() == AnalysisConcurrency::kdl_key())
+		{
+			Some(node) => AnalysisConcurrency::parse_node(node).ok_or_else(|| {
+				hc_error!(
+					"Error parsing exec config file: max-concurrent-analyses must be a positive integer"
+				)
+			})?,
+			None => AnalysisConcurrency::default(),
+		};
+		// The trust policy is optional, but an invalid `trust` node is an error
+		let trust = match nodes
+			.iter()
+			.find(|node| node.name().to_string().as_str() == PluginTrust::kdl_key())
+		{
+			Some(node) => PluginTrust::parse_node(node).ok_or_else(|| {
+				hc_error!(
+					"Error parsing exec config file: trusted keys must be 'key \"<publisher>\" file=\"<path>\"'"
+				)
+			})?,
+			None => PluginTrust::default(),
+		};
+		// The plugin pool is off unless configured, but an invalid `plugin-pool` node is an error
+		let pool = match nodes
+			.iter()
+			.find(|node| node.name().to_string().as_str() == PluginPool::kdl_key())
+		{
+			Some(node) => Some(PluginPool::parse_node(node).ok_or_else(|| {
+				hc_error!(
+					"Error parsing exec config file: the plugin pool must be 'plugin-pool idle-timeout=<seconds>'"
+				)
+			})?),
+			None => None,
+		};
+		// The repo cache is only pruned if configured, but an invalid `repo-cache` node is an error
+		let repo_cache = match nodes
+			.iter()
+			.find(|node| node.name().to_string().as_str() == RepoCacheLimits::kdl_key())
+		{
+			Some(node) => Some(RepoCacheLimits::parse_node(node).ok_or_else(|| {
+				hc_error!(
+					"Error parsing exec config file: the repo cache limits must be 'repo-cache max-size=\"<size>\" max-age=\"<age>\"', with at least one given"
+				)
+			})?),
+			None => None,
+		};
+		// Future config nodes will be here
+		Ok(Self {
+			plugin_data,
+			mirrors,
+			registry,
+			timeouts,
+			limits,
+			secrets,
+			concurrency,
+			trust,
+			pool,
+			repo_cache,
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::path::PathBuf;
+
+	use super::*;
+	use pathbuf::pathbuf;
+
+	#[test]
+	fn test_parsing_plugin_backoff_interval() {
+		let data = "backoff-interval 100000";
+		let node = KdlNode::from_str(data).unwrap();
+		assert_eq!(
+			PluginBackoffInterval::new(100000),
+			PluginBackoffInterval::parse_node(&node).unwrap()
+		)
+	}
+
+	#[test]
+	fn test_parsing_plugin_max_spawns() {
+		let data = "max-spawn-attempts 3";
+		let node = KdlNode::from_str(data).unwrap();
+		assert_eq!(
+			PluginMaxSpawnAttempts::new(3),
+			PluginMaxSpawnAttempts::parse_node(&node).unwrap()
+		)
+	}
+
+	#[test]
+	fn test_parsing_plugin_max_connections() {
+		let data = "max-conn-attempts 5";
+		let node = KdlNode::from_str(data).unwrap();
+		assert_eq!(
+			PluginMaxConnectionAttempts::new(5),
+			PluginMaxConnectionAttempts::parse_node(&node).unwrap()
+		)
+	}
+
+	#[test]
+	fn test_parsing_plugin_jitter_percent() {
+		let data = "jitter-percent 10";
+		let node = KdlNode::from_str(data).unwrap();
+		assert_eq!(
+			PluginJitterPercent::new(10),
+			PluginJitterPercent::parse_node(&node).unwrap()
+		)
+	}
+
+	#[test]
+	fn test_parsing_plugin_buffer_size() {
+		let data = "grpc-msg-buffer-size 10";
+		let node = KdlNode::from_str(data).unwrap();
+		assert_eq!(
+			PluginMsgBufferSize::new(10),
+			PluginMsgBufferSize::parse_node(&node).unwrap()
+		)
+	}
+
+	#[test]
+	fn test_optional_parsing_plugin_buffer_size() {
+		let data = "jitter-percent 10";
+		let node = KdlNode::from_str(data).unwrap();
+		assert_eq!(None, PluginMsgBufferSize::parse_node(&node))
+	}
+
+	#[test]
+	fn test_parsing_plugin_config() {
+		let data = r#"plugin {
+    backoff-interval 100000
+    max-spawn-attempts 3
+    max-conn-attempts 5
+    jitter-percent 10
+    grpc-msg-buffer-size 10
+}"#;
+		let node = KdlNode::from_str(data).unwrap();
+		let backoff = PluginBackoffInterval::new(100000);
+		let max_spawn = PluginMaxSpawnAttempts::new(3);
+		let max_conn = PluginMaxConnectionAttempts::new(5);
+		let jitter = PluginJitterPercent::new(10);
+		let grpc_buffer = PluginMsgBufferSize::new(10);
+
+		let expected = PluginConfig::new(backoff, max_spawn, max_conn, jitter, grpc_buffer);
+
+		assert_eq!(expected, PluginConfig::parse_node(&node).unwrap())
+	}
+
+	#[test]
+	fn test_parsing_plugin_config_backoff() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}"#;
+		let node = KdlNode::from_str(data).unwrap();
+		let parsed_node = PluginConfig::parse_node(&node).unwrap();
+
+		assert_eq!(parsed_node.backoff.micros, 100000);
+	}
+
+	#[test]
+	fn test_parsing_plugin_config_max_spawn() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}"#;
+		let node = KdlNode::from_str(data).unwrap();
+		let parsed_node = PluginConfig::parse_node(&node).unwrap();
+
+		assert_eq!(parsed_node.max_spawn.attempts, 3);
+	}
+
+	#[test]
+	fn test_parsing_plugin_config_max_conn() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}"#;
+		let node = KdlNode::from_str(data).unwrap();
+		let parsed_node = PluginConfig::parse_node(&node).unwrap();
+
+		assert_ne!(parsed_node.max_conn.attempts, 3);
+	}
+
+	#[test]
+	fn test_parsing_plugin_config_jitter() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}"#;
+		let node = KdlNode::from_str(data).unwrap();
+		let parsed_node = PluginConfig::parse_node(&node).unwrap();
+
+		assert_eq!(parsed_node.jitter.percent, 10);
+	}
+
+	#[test]
+	fn test_parsing_plugin_config_buffer() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}"#;
+		let node = KdlNode::from_str(data).unwrap();
+		let parsed_node = PluginConfig::parse_node(&node).unwrap();
+
+		assert_eq!(parsed_node.grpc_buffer.size, 10);
+	}
+
+	#[test]
+	fn test_parsing_plugin_config_max_message_size() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+			grpc-max-message-size 16777216
+		}"#;
+		let node = KdlNode::from_str(data).unwrap();
+		let parsed_node = PluginConfig::parse_node(&node).unwrap();
+		assert_eq!(parsed_node.max_message_size.bytes, 16777216);
+
+		// Smaller than the smallest size a plugin may negotiate
+		let bad = data.replace("16777216", "1024");
+		let node = KdlNode::from_str(&bad).unwrap();
+		assert!(PluginConfig::parse_node(&node).is_none());
+	}
+
+	#[test]
+	fn test_parsing_exec_config_from_str() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}"#;
+		let exec_config = ExecConfig::from_str(data).unwrap();
+		assert_eq!(exec_config.plugin_data.backoff.micros, 100000);
+		assert_eq!(exec_config.plugin_data.max_spawn.attempts, 3);
+		assert_eq!(exec_config.plugin_data.max_conn.attempts, 5);
+		assert_eq!(exec_config.plugin_data.jitter.percent, 10);
+		assert_eq!(exec_config.plugin_data.grpc_buffer.size, 10);
+	}
+
+	#[test]
+	fn test_parsing_exec_config_mirrors() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}
+		mirrors {
+			mirror from="https://github.com/" to="https://mirror.example.com/github/"
+		}"#;
+		let exec_config = ExecConfig::from_str(data).unwrap();
+		assert_eq!(
+			exec_config.mirrors,
+			PluginMirrors(vec![PluginMirror::new(
+				"https://github.com/",
+				"https://mirror.example.com/github/"
+			)])
+		);
+	}
+
+	#[test]
+	fn test_parsing_exec_config_bad_mirror() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}
+		mirrors {
+			mirror from="https://github.com/"
+		}"#;
+		assert!(ExecConfig::from_str(data).is_err());
+	}
+
+	#[test]
+	fn test_parsing_exec_config_registry() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}
+		registry "https://registry.example.com/index.kdl""#;
+		let exec_config = ExecConfig::from_str(data).unwrap();
+		assert_eq!(
+			exec_config.registry,
+			Some(PluginRegistry(
+				Url::parse("https://registry.example.com/index.kdl").unwrap()
+			))
+		);
+
+		let bad = data.replace("https://registry.example.com/index.kdl", "not a url");
+		assert!(ExecConfig::from_str(&bad).is_err());
+	}
+
+	#[test]
+	fn test_parsing_exec_config_secrets() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}
+		secrets {
+			secret "github-token" "mitre/git" "mitre/review" env="GITHUB_TOKEN"
+			secret "nvd-key" "mitre/vulns" file="/run/secrets/nvd"
+		}"#;
+		let exec_config = ExecConfig::from_str(data).unwrap();
+		assert_eq!(
+			exec_config.secrets,
+			PluginSecrets(vec![
+				PluginSecret {
+					name: "github-token".to_owned(),
+					source: SecretSource::Env("GITHUB_TOKEN".to_owned()),
+					plugins: vec!["mitre/git".to_owned(), "mitre/review".to_owned()],
+				},
+				PluginSecret {
+					name: "nvd-key".to_owned(),
+					source: SecretSource::File(PathBuf::from("/run/secrets/nvd")),
+					plugins: vec!["mitre/vulns".to_owned()],
+				},
+			])
+		);
+	}
+
+	#[test]
+	fn test_parsing_exec_config_bad_secret() {
+		for secret in [
+			r#"secret "github-token" "mitre/git""#,
+			r#"secret "github-token" "git" env="GITHUB_TOKEN""#,
+			r#"secret "github-token" env="GITHUB_TOKEN""#,
+			r#"secret "github-token" "mitre/git" env="GITHUB_TOKEN" file="token""#,
+		] {
+			let data = format!(
+				"plugin {{
+					backoff-interval 100000
+					max-spawn-attempts 3
+					max-conn-attempts 5
+					jitter-percent 10
+					grpc-msg-buffer-size 10
+				}}
+				secrets {{
+					{}
+				}}",
+				secret
+			);
+			assert!(ExecConfig::from_str(&data).is_err(), "{}", secret);
+		}
+	}
+
+	#[test]
+	fn test_parsing_exec_config_timeouts() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}
+		timeouts {
+			default 300
+			plugin "mitre/git" 900
+		}"#;
+		let exec_config = ExecConfig::from_str(data).unwrap();
+		let timeouts = &exec_config.timeouts;
+		assert_eq!(
+			timeouts.for_plugin("mitre/git"),
+			Some(Duration::from_secs(900))
+		);
+		assert_eq!(
+			timeouts.for_plugin("mitre/activity"),
+			Some(Duration::from_secs(300))
+		);
+
+		let bad = data.replace("900", "0");
+		assert!(ExecConfig::from_str(&bad).is_err());
+		let bad = data.replace("\"mitre/git\"", "\"git\"");
+		assert!(ExecConfig::from_str(&bad).is_err());
+	}
+
+	#[test]
+	fn test_parsing_exec_config_limits() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}
+		limits {
+			default memory="2GB" cpu-time=600 user="hipcheck"
+			plugin "mitre/git" memory="4GB" open-files=256
+		}"#;
+		let exec_config = ExecConfig::from_str(data).unwrap();
+		let limits = &exec_config.limits;
+		assert_eq!(
+			limits.for_plugin("mitre/git"),
+			ResourceLimits {
+				memory: Some(4_000_000_000),
+				cpu_time: Some(Duration::from_secs(600)),
+				open_files: Some(256),
+				user: Some("hipcheck".to_owned()),
+			}
+		);
+		assert_eq!(
+			limits.for_plugin("mitre/activity").memory,
+			Some(2_000_000_000)
+		);
+		assert_eq!(limits.for_plugin("mitre/activity").open_files, None);
+
+		let bad = data.replace("open-files=256", "open-files=0");
+		assert!(ExecConfig::from_str(&bad).is_err());
+		let bad = data.replace("cpu-time=600", "cpu-time=\"10m\"");
+		assert!(ExecConfig::from_str(&bad).is_err());
+		let bad = data.replace("open-files=256", "network=false");
+		assert!(ExecConfig::from_str(&bad).is_err());
+
+		let default = ExecConfig::default().unwrap();
+		assert!(default.limits.for_plugin("mitre/git").is_empty());
+	}
+
+	#[test]
+	fn test_parsing_exec_config_concurrency() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}
+		max-concurrent-analyses 4"#;
+		let exec_config = ExecConfig::from_str(data).unwrap();
+		assert_eq!(exec_config.concurrency.limit, 4);
+
+		let bad = data.replace(" 4", " 0");
+		assert!(ExecConfig::from_str(&bad).is_err());
+
+		let default = ExecConfig::default().unwrap();
+		assert!(default.concurrency.limit >= 1);
+	}
+
+	#[test]
+	fn test_parsing_exec_config_plugin_pool() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}
+		plugin-pool idle-timeout=600"#;
+		let exec_config = ExecConfig::from_str(data).unwrap();
+		assert_eq!(
+			exec_config.pool,
+			Some(PluginPool {
+				idle_timeout: Duration::from_secs(600)
+			})
+		);
+
+		let bad = data.replace("600", "0");
+		assert!(ExecConfig::from_str(&bad).is_err());
+		let bad = data.replace("idle-timeout=600", "600");
+		assert!(ExecConfig::from_str(&bad).is_err());
+
+		let default = ExecConfig::default().unwrap();
+		assert_eq!(default.pool, None);
+	}
+
+	#[test]
+	fn test_parsing_exec_config_repo_cache() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}
+		repo-cache max-size="20GB" max-age="90d""#;
+		let exec_config = ExecConfig::from_str(data).unwrap();
+		assert_eq!(
+			exec_config.repo_cache,
+			Some(RepoCacheLimits {
+				max_size: Some(20_000_000_000),
+				max_age: Some(Duration::from_secs(90 * 24 * 60 * 60)),
+			})
+		);
+
+		let only_age = data.replace(r#"max-size="20GB" "#, "");
+		let exec_config = ExecConfig::from_str(&only_age).unwrap();
+		assert_eq!(exec_config.repo_cache.unwrap().max_size, None);
+
+		let bad = data.replace("90d", "90");
+		assert!(ExecConfig::from_str(&bad).is_err());
+		let bad = data.replace(r#"max-size="20GB" max-age="90d""#, "");
+		assert!(ExecConfig::from_str(&bad).is_err());
+
+		let default = ExecConfig::default().unwrap();
+		assert_eq!(default.repo_cache, None);
+	}
+
+	#[test]
+	fn test_parsing_exec_config_trust() {
+		let data = r#"plugin {
+			backoff-interval 100000
+			max-spawn-attempts 3
+			max-conn-attempts 5
+			jitter-percent 10
+			grpc-msg-buffer-size 10
+		}
+		trust {
+			key "mitre" file="/etc/hipcheck/mitre.pub"
+			key "*" file="/etc/hipcheck/mirror.pub"
+		}"#;
+		let exec_config = ExecConfig::from_str(data).unwrap();
+		let paths = |publisher| {
+			exec_config
+				.trust
+				.keys_for(publisher)
+				.map(|key| key.path.clone())
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(
+			paths("mitre"),
+			vec![
+				PathBuf::from("/etc/hipcheck/mitre.pub"),
+				PathBuf::from("/etc/hipcheck/mirror.pub")
+			]
+		);
+		assert_eq!(
+			paths("other"),
+			vec![PathBuf::from("/etc/hipcheck/mirror.pub")]
+		);
+
+		let bad = data.replace("key \"mitre\" file", "key file");
+		assert!(ExecConfig::from_str(&bad).is_err());
+		let bad = data.replace("file=", "path=");
+		assert!(ExecConfig::from_str(&bad).is_err());
+
+		let default = ExecConfig::default().unwrap();
+		assert_eq!(default.trust, PluginTrust::default());
+	}
+
+	#[test]
+	fn test_mirror_rewrite() {
+		let mirrors = PluginMirrors(vec![
+			PluginMirror::new("https://github.com/", "https://mirror.example.com/github/"),
+			PluginMirror::new("https://github.com/mitre/", "https://unused.example.com/"),
+		]);
+
+		let url = Url::parse("https://github.com/mitre/hipcheck/releases/download/x.tar.xz")
+			.unwrap();
+		assert_eq!(
+			mirrors.rewrite(&url).unwrap().as_str(),
+			"https://mirror.example.com/github/mitre/hipcheck/releases/download/x.tar.xz"
+		);
+
+		let other = Url::parse("https://example.org/plugin.kdl").unwrap();
+		assert_eq!(mirrors.rewrite(&other).unwrap(), other);
+	}
+
+	#[test]
+	fn test_read_exec_config_file() {
+		let root = workspace_dir();
+		let path = pathbuf![&root, "config", "Exec.kdl"];
+		let config = ExecConfig::from_file(path);
+		assert!(config.is_ok())
+	}
+
+	// Adapted from https://stackoverflow.com/a/74942075
+	// Licensed CC BY-SA 4.0
+	fn workspace_dir() -> PathBuf {
+		let output = std::process::Command::new(env!("CARGO"))
+			.arg("locate-project")
+			.arg("--workspace")
+			.arg("--message-format=plain")
+			.output()
+			.unwrap()
+			.stdout;
+		let cargo_path = Path::new(std::str::from_utf8(&output).unwrap().trim());
+		cargo_path.parent().unwrap().to_path_buf()
+	}
+
+	#[test]
+	fn test_default_exec_config() {
+		let config = ExecConfig::default().unwrap();
+
+		assert_eq!(config.plugin_data.backoff.micros, 100000);
+		assert_eq!(config.plugin_data.max_spawn.attempts, 3);
+		assert_eq!(config.plugin_data.max_conn.attempts, 5);
+		assert_eq!(config.plugin_data.jitter.percent, 10);
+		assert_eq!(config.plugin_data.grpc_buffer.size, 10);
+		assert_eq!(
+			config.plugin_data.max_message_size.bytes,
+			GRPC_MAX_SIZE_BYTES
+		);
+	}
+}
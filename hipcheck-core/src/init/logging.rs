@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hipcheck's logging, built on `tracing`.
+//!
+//! Log lines are written to stderr, out of the way of the shell's progress bars
+//! and with any secrets masked. Messages from crates which use `log` rather than
+//! `tracing`, including the messages forwarded from plugins, are turned into
+//! `tracing` events so they're filtered and formatted the same way.
+
+use crate::{
+	shell::{color_choice::ColorChoice, Shell},
+	util::secret::{has_secrets, redact},
+};
+use std::{
+	io::{self, IsTerminal as _, Write},
+	sync::OnceLock,
+};
+use tracing_subscriber::{
+	filter::{EnvFilter, LevelFilter},
+	fmt::{self, MakeWriter},
+	layer::{Layered, SubscriberExt as _},
+	reload,
+	util::SubscriberInitExt as _,
+	Layer, Registry,
+};
+
+/// The environment variable which configures what gets logged.
+const LOG_FILTER_ENV_VAR: &str = "HC_LOG";
+
+/// The environment variable which configures whether log lines use color.
+const LOG_STYLE_ENV_VAR: &str = "HC_LOG_STYLE";
+
+/// The format log lines are written in.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+	/// Human-readable log lines
+	#[default]
+	Text,
+	/// One JSON object per line, including the spans the event happened in
+	Json,
+}
+
+type FilteredRegistry = Layered<EnvFilter, Registry>;
+
+type FormatLayer = Box<dyn Layer<FilteredRegistry> + Send + Sync>;
+
+/// Handle to swap out the format layer once the CLI config is loaded.
+static FORMAT_HANDLE: OnceLock<reload::Handle<FormatLayer, FilteredRegistry>> = OnceLock::new();
+
+/// Initialize the global logger, writing human-readable log lines.
+pub fn init() {
+	if !Shell::is_init() {
+		panic!("Initialize the global shell before initializing the logger");
+	}
+
+	let filter = EnvFilter::builder()
+		.with_default_directive(LevelFilter::ERROR.into())
+		.with_env_var(LOG_FILTER_ENV_VAR)
+		.from_env_lossy();
+	let (format, handle) = reload::Layer::new(format_layer(LogFormat::default()));
+
+	tracing_subscriber::registry()
+		.with(filter)
+		.with(format)
+		.try_init()
+		.expect("logging initialization must succeed");
+
+	FORMAT_HANDLE
+		.set(handle)
+		.expect("logging is only initialized once");
+}
+
+/// Set the format log lines are written in from now on.
+pub fn set_log_format(format: LogFormat) {
+	let Some(handle) = FORMAT_HANDLE.get() else {
+		return;
+	};
+
+	if let Err(e) = handle.reload(format_layer(format)) {
+		tracing::error!("failed to change log format: {}", e);
+	}
+}
+
+fn format_layer(format: LogFormat) -> FormatLayer {
+	let layer = fmt::layer().with_writer(ShellWriter);
+
+	match format {
+		LogFormat::Text => layer.with_ansi(use_color()).boxed(),
+		LogFormat::Json => layer
+			.json()
+			.with_current_span(true)
+			.with_span_list(true)
+			.boxed(),
+	}
+}
+
+/// Whether human-readable log lines should use color, per `HC_LOG_STYLE`.
+fn use_color() -> bool {
+	let choice: ColorChoice = std::env::var(LOG_STYLE_ENV_VAR)
+		.ok()
+		.and_then(|style| style.parse().ok())
+		.unwrap_or_default();
+
+	match choice {
+		ColorChoice::Always => true,
+		ColorChoice::Never => false,
+		ColorChoice::Auto => io::stderr().is_terminal(),
+	}
+}
+
+/// Writes log lines to stderr, suspending the shell's progress bars while it does.
+struct ShellWriter;
+
+impl<'a> MakeWriter<'a> for ShellWriter {
+	type Writer = ShellWriter;
+
+	fn make_writer(&'a self) -> Self::Writer {
+		ShellWriter
+	}
+}
+
+impl Write for ShellWriter {
+	// Each log line is written with a single call, so secrets can't be split
+	// across writes.
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		Shell::in_suspend(|| {
+			if has_secrets() {
+				// Mask any secrets which made it into the line.
+				let line = String::from_utf8_lossy(buf);
+				io::stderr().write_all(redact(&line).as_bytes())?;
+			} else {
+				io::stderr().write_all(buf)?;
+			}
+
+			Ok(buf.len())
+		})
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Shell::in_suspend(|| io::stderr().flush())
+	}
+}
@@ -2,11 +2,13 @@
 
 mod git2_log_shim;
 mod git2_rustls_transport;
-mod indicatif_log_bridge;
+mod logging;
 
 use crate::shell::{verbosity::Verbosity, Shell};
-use env_logger::Env;
 use rustls::crypto::{ring, CryptoProvider};
+use std::sync::Once;
+
+pub use logging::{set_log_format, LogFormat};
 
 /// Initialize global state for the program.
 ///
@@ -19,16 +21,30 @@ pub fn init() {
 	init_cryptography();
 }
 
+/// Initialize global state for Hipcheck used as a library.
+///
+/// Unlike [`init`], this is safe to call more than once, leaves logging to the
+/// program using Hipcheck, and keeps Hipcheck from printing anything.
+pub fn init_library() {
+	static INIT: Once = Once::new();
+
+	INIT.call_once(|| {
+		if !Shell::is_init() {
+			init_shell();
+			Shell::set_verbosity(Verbosity::Silent);
+		}
+		init_libgit2();
+		// The program may have installed its own provider already.
+		let _ = CryptoProvider::install_default(ring::default_provider());
+	});
+}
+
 fn init_shell() {
 	Shell::init(Verbosity::Normal);
 }
 
 fn init_logging() {
-	let env = Env::new().filter("HC_LOG").write_style("HC_LOG_STYLE");
-	let logger = env_logger::Builder::from_env(env).build();
-	indicatif_log_bridge::LogWrapper(logger)
-		.try_init()
-		.expect("logging initialization must succeed");
+	logging::init();
 }
 
 fn init_libgit2() {
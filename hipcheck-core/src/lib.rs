@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hipcheck's analysis pipeline, for running Hipcheck from other Rust programs.
+//!
+//! Programs embedding Hipcheck should use a [`Client`], which analyzes a
+//! [`TargetSeed`] against a [`PolicyFile`] and returns a [`Report`]. These,
+//! and the other items exported at the crate root, are this crate's stable
+//! API.
+//!
+//! The modules are public so the `hc` binary can be built on them, but they're
+//! internal to Hipcheck, and may change in any release.
+
+#[cfg(feature = "benchmarking")]
+#[doc(hidden)]
+pub mod benchmarking;
+#[doc(hidden)]
+pub mod cache;
+mod client;
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod engine;
+#[doc(hidden)]
+pub mod error;
+#[doc(hidden)]
+pub mod exec;
+#[doc(hidden)]
+pub mod init;
+#[doc(hidden)]
+pub mod plugin;
+#[doc(hidden)]
+pub mod policy;
+#[doc(hidden)]
+pub mod policy_exprs;
+#[doc(hidden)]
+pub mod report;
+#[doc(hidden)]
+pub mod score;
+#[doc(hidden)]
+pub mod session;
+#[doc(hidden)]
+pub mod shell;
+#[doc(hidden)]
+pub mod source;
+#[doc(hidden)]
+pub mod target;
+#[doc(hidden)]
+pub mod util;
+#[doc(hidden)]
+pub mod version;
+
+pub use client::Client;
+pub use error::{Error, Result};
+pub use exec::ExecConfig;
+pub use policy::PolicyFile;
+pub use report::{RecommendationKind, Report};
+pub use target::{TargetSeed, TargetSeedKind};
+
+#[doc(hidden)]
+pub use client::{analyze, analyze_complete};
+
+use std::result::Result as StdResult;
+
+/// An `f64` that is never `NaN`.
+type F64 = ordered_float::NotNan<f64>;
+
+// Constants for exiting with error codes.
+/// Indicates the program failed.
+const EXIT_FAILURE: i32 = 1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum CheckKind {
+	Npm,
+	Pypi,
+}
+
+impl CheckKind {
+	/// Get the name of the check.
+	const fn name(&self) -> &'static str {
+		match self {
+			CheckKind::Npm => "npm",
+			CheckKind::Pypi => "pypi",
+		}
+	}
+}
@@ -0,0 +1,308 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bundles of plugins, for provisioning the plugin cache of a machine which
+//! can't download them.
+//!
+//! A bundle is a tar archive of every plugin a policy file uses, under
+//! `plugins/<publisher>/<name>/<version>`, along with a `bundle.json` index
+//! listing them. Downloaded plugins are bundled as the archive they were
+//! downloaded as, with its signature, so they're verified against the trust
+//! policy of the machine loading the bundle just as if it had downloaded them.
+//! Plugins without an archive, like local plugins, are bundled as their plugin
+//! cache folder, and are only loaded if unsigned plugins are allowed. Plugins
+//! are downloaded for one arch, so a bundle can only be loaded on machines of
+//! the arch it was made on.
+
+use crate::{
+	cache::plugin::HcPluginCache,
+	error::{Context as _, Result},
+	exec::{PluginMirrors, PluginTrust},
+	hc_error,
+	plugin::{
+		get_current_arch,
+		retrieval::{PluginArchive, ARCHIVE_DIR},
+		retrieve_plugins,
+		signature::unverified,
+		PluginId, PluginName, PluginPublisher, PluginVersion,
+	},
+	policy::policy_file::PolicyPlugin,
+	shell::Shell,
+	util::fs::{create_dir_all, read_string, FileLock},
+};
+use pathbuf::pathbuf;
+use serde::{Deserialize, Serialize};
+use std::{
+	fs::{self, File},
+	path::{Component, Path, PathBuf},
+};
+use tar::{Archive, Builder, Header};
+
+/// The name of the index in a bundle.
+const INDEX_FILE: &str = "bundle.json";
+
+/// The folder in a bundle holding the plugins.
+const PLUGINS_DIR: &str = "plugins";
+
+/// The list of what's in a bundle.
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleIndex {
+	/// The arch the plugins were downloaded for.
+	arch: String,
+	plugins: Vec<BundledPlugin>,
+}
+
+/// A plugin in a bundle.
+#[derive(Debug, Serialize, Deserialize)]
+struct BundledPlugin {
+	publisher: String,
+	name: String,
+	version: String,
+	/// Whether the plugin is bundled as its signed archive, rather than as
+	/// its plugin cache folder.
+	archived: bool,
+}
+
+impl BundledPlugin {
+	fn plugin_id(&self) -> Result<PluginId> {
+		// The names become paths in the plugin cache, so they can't leave it
+		for part in [&self.publisher, &self.name, &self.version] {
+			let mut components = Path::new(part).components();
+			if !matches!(
+				(components.next(), components.next()),
+				(Some(Component::Normal(_)), None)
+			) {
+				return Err(hc_error!(
+					"bundle has a plugin with invalid name '{}'",
+					part
+				));
+			}
+		}
+		Ok(PluginId::new(
+			PluginPublisher(self.publisher.clone()),
+			PluginName(self.name.clone()),
+			PluginVersion(self.version.clone()),
+		))
+	}
+
+	fn dir(&self, root: &Path) -> PathBuf {
+		pathbuf![
+			root,
+			PLUGINS_DIR,
+			&self.publisher,
+			&self.name,
+			&self.version
+		]
+	}
+}
+
+/// Write a bundle of the plugins a policy file uses, and the plugins they
+/// depend on, downloading any which aren't in the plugin cache yet.
+///
+/// Returns the IDs of the plugins in the bundle.
+pub fn create_bundle(
+	policy_plugins: &[PolicyPlugin],
+	plugin_cache: &HcPluginCache,
+	mirrors: &PluginMirrors,
+	trust: &PluginTrust,
+	out: &Path,
+) -> Result<Vec<PluginId>> {
+	let mut plugin_ids = retrieve_plugins(policy_plugins, plugin_cache, mirrors, trust)?
+		.into_iter()
+		.collect::<Vec<_>>();
+	plugin_ids.sort_by_key(|plugin_id| plugin_id.to_string());
+
+	let mut plugins = Vec::new();
+	let mut sources = Vec::new();
+	for plugin_id in &plugin_ids {
+		let dir = plugin_cache.plugin_download_dir(plugin_id);
+		let archive_dir = pathbuf![&dir, ARCHIVE_DIR];
+		let archived = PluginArchive::find(&archive_dir)?.is_some();
+		if !archived {
+			Shell::eprintln(format!(
+				"warning: {} wasn't downloaded, or was downloaded by an older Hipcheck, so it can't be verified and will only load with --allow-unsigned",
+				plugin_id
+			));
+		}
+		plugins.push(BundledPlugin {
+			publisher: plugin_id.publisher().0.clone(),
+			name: plugin_id.name().0.clone(),
+			version: plugin_id.version().0.clone(),
+			archived,
+		});
+		sources.push(if archived { archive_dir } else { dir });
+	}
+	let index = BundleIndex {
+		arch: get_current_arch().to_string(),
+		plugins,
+	};
+	write_bundle(&index, &sources, out)?;
+
+	Ok(plugin_ids)
+}
+
+/// Write a bundle with the given index, taking each plugin's files from the
+/// matching folder in `sources`.
+fn write_bundle(index: &BundleIndex, sources: &[PathBuf], out: &Path) -> Result<()> {
+	let file =
+		File::create(out).with_context(|| format!("failed to create '{}'", out.display()))?;
+	let mut builder = Builder::new(file);
+
+	let index_json = serde_json::to_vec_pretty(index)?;
+	let mut header = Header::new_gnu();
+	header.set_size(index_json.len() as u64);
+	header.set_mode(0o644);
+	header.set_cksum();
+	builder.append_data(&mut header, INDEX_FILE, index_json.as_slice())?;
+
+	for (plugin, source) in index.plugins.iter().zip(sources) {
+		builder.append_dir_all(plugin.dir(Path::new("")), source)?;
+	}
+	builder
+		.into_inner()
+		.with_context(|| format!("failed to write '{}'", out.display()))?;
+	Ok(())
+}
+
+/// Add the plugins in a bundle to the plugin cache, replacing any copies of
+/// the same versions already there.
+///
+/// Every plugin's archive is checked against the keys `trust` has for its
+/// publisher before any are added, as when plugins are downloaded. Returns
+/// the IDs of the plugins added.
+pub fn load_bundle(
+	bundle: &Path,
+	plugin_cache: &HcPluginCache,
+	trust: &PluginTrust,
+) -> Result<Vec<PluginId>> {
+	let unpacked = tempfile::tempdir()?;
+	let file =
+		File::open(bundle).with_context(|| format!("failed to open '{}'", bundle.display()))?;
+	Archive::new(file)
+		.unpack(unpacked.path())
+		.with_context(|| format!("failed to unpack '{}'", bundle.display()))?;
+
+	let index_path = pathbuf![unpacked.path(), INDEX_FILE];
+	let index: BundleIndex = serde_json::from_str(&read_string(&index_path)?)
+		.map_err(|e| hc_error!("'{}' isn't a plugin bundle: {}", bundle.display(), e))?;
+
+	let current_arch = get_current_arch().to_string();
+	if index.arch != current_arch {
+		return Err(hc_error!(
+			"bundle has plugins for arch '{}', but this machine's arch is '{}'",
+			index.arch,
+			current_arch
+		));
+	}
+
+	let mut plugin_ids = Vec::new();
+	let mut archives = Vec::new();
+	for plugin in &index.plugins {
+		let plugin_id = plugin.plugin_id()?;
+		let archive = if plugin.archived {
+			let archive = PluginArchive::find(&plugin.dir(unpacked.path()))?
+				.ok_or_else(|| hc_error!("the bundle is missing the archive of {}", plugin_id))?;
+			archive.verify(&plugin_id, trust)?;
+			Some(archive)
+		} else {
+			unverified(
+				&plugin_id.to_policy_file_plugin_identifier(),
+				"is bundled without its signed archive",
+			)?;
+			None
+		};
+		plugin_ids.push(plugin_id);
+		archives.push(archive);
+	}
+
+	for ((plugin, plugin_id), archive) in index.plugins.iter().zip(&plugin_ids).zip(&archives) {
+		let download_dir = plugin_cache.plugin_download_dir(plugin_id);
+		let _lock = FileLock::acquire(&download_dir)?;
+		if download_dir.exists() {
+			fs::remove_dir_all(&download_dir)?;
+		}
+		create_dir_all(&download_dir)?;
+
+		match archive {
+			Some(archive) => {
+				archive.extract(&download_dir)?;
+				archive.keep(&pathbuf![&download_dir, ARCHIVE_DIR])?;
+			}
+			None => {
+				let options = fs_extra::dir::CopyOptions::new().content_only(true);
+				fs_extra::dir::copy(plugin.dir(unpacked.path()), &download_dir, &options).map_err(
+					|e| hc_error!("failed to add {} to the plugin cache: {}", plugin_id, e),
+				)?;
+			}
+		}
+		if !plugin_cache.plugin_kdl(plugin_id).is_file() {
+			return Err(hc_error!("the bundle has no plugin.kdl for {}", plugin_id));
+		}
+	}
+
+	Ok(plugin_ids)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::plugin::ArchiveFormat;
+
+	fn plugin(publisher: &str, name: &str, version: &str) -> BundledPlugin {
+		BundledPlugin {
+			publisher: publisher.to_owned(),
+			name: name.to_owned(),
+			version: version.to_owned(),
+			archived: true,
+		}
+	}
+
+	#[test]
+	fn bundled_plugin_names_stay_in_the_cache() {
+		assert!(plugin("mitre", "git", "0.1.0").plugin_id().is_ok());
+		assert!(plugin("..", "git", "0.1.0").plugin_id().is_err());
+		assert!(plugin("mitre", "../../bin", "0.1.0").plugin_id().is_err());
+		assert!(plugin("mitre", "git", "").plugin_id().is_err());
+		assert!(plugin("/etc", "git", "0.1.0").plugin_id().is_err());
+	}
+
+	#[test]
+	fn unverified_plugins_are_refused() {
+		let dir = tempfile::tempdir().unwrap();
+		let plugin_dir = pathbuf![dir.path(), "git"];
+		fs::create_dir(&plugin_dir).unwrap();
+		fs::write(pathbuf![&plugin_dir, "plugin.kdl"], "publisher \"mitre\"").unwrap();
+
+		// An unsigned archive of the plugin, kept as it would be when downloaded
+		let archive_path = pathbuf![dir.path(), "git.tar"];
+		let mut archive = Builder::new(File::create(&archive_path).unwrap());
+		archive.append_dir_all("", &plugin_dir).unwrap();
+		archive.into_inner().unwrap();
+		let archive_dir = pathbuf![dir.path(), ARCHIVE_DIR];
+		PluginArchive {
+			path: archive_path,
+			format: ArchiveFormat::Tar,
+			signature: None,
+		}
+		.keep(&archive_dir)
+		.unwrap();
+
+		// Neither the archive nor the bare plugin folder is trusted
+		for (archived, source) in [(true, &archive_dir), (false, &plugin_dir)] {
+			let bundled = BundledPlugin {
+				archived,
+				..plugin("mitre", "git", "0.1.0")
+			};
+			let plugin_id = bundled.plugin_id().unwrap();
+			let index = BundleIndex {
+				arch: get_current_arch().to_string(),
+				plugins: vec![bundled],
+			};
+			let bundle = pathbuf![dir.path(), "bundle.tar"];
+			write_bundle(&index, &[source.clone()], &bundle).unwrap();
+
+			let cache = HcPluginCache::new(&pathbuf![dir.path(), "cache"]);
+			assert!(load_bundle(&bundle, &cache, &PluginTrust::default()).is_err());
+			assert!(!cache.plugin_kdl(&plugin_id).exists());
+		}
+	}
+}
@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Describing what a plugin offers, for tools which help write policy files.
+//!
+//! Describing a plugin starts it with an empty scratch directory and runs only
+//! the startup RPCs which report its queries and defaults. The query protocol
+//! is never started, so the plugin is never given a target to analyze.
+
+use crate::{
+	cache::{plugin::HcPluginCache, scratch::HcScratchCache},
+	error::Result,
+	hc_error,
+	plugin::{
+		get_current_arch, Plugin, PluginExecutor, PluginId, PluginManifest, ResourceLimits, Schema,
+	},
+};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::runtime::Handle;
+
+/// Everything a plugin reports about itself, plus what its manifest says.
+#[derive(Debug, Serialize)]
+pub struct PluginDescription {
+	pub publisher: String,
+	pub name: String,
+	pub version: String,
+	pub license: String,
+	/// The plugins this plugin queries, as `<publisher>/<name>` and version.
+	pub dependencies: Vec<DependencyDescription>,
+	/// Every query the plugin supports, with the default query first.
+	pub queries: Vec<QueryDescription>,
+	/// How the plugin responded to the configuration it was given.
+	pub configuration: ConfigurationDescription,
+	/// The policy expression used if a policy file doesn't provide one.
+	///
+	/// Plugins often base this on their configuration, so it's only known if
+	/// the plugin accepted the configuration.
+	pub default_policy_expression: Option<String>,
+	/// The plugin's explanation of its default query.
+	pub default_query_explanation: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DependencyDescription {
+	pub plugin: String,
+	pub version: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryDescription {
+	/// The name of the query, which is empty for the default query.
+	pub name: String,
+	pub default: bool,
+	/// The JSON Schema of the key the query accepts.
+	pub key_schema: Value,
+	/// The JSON Schema of the output the query returns.
+	pub output_schema: Value,
+}
+
+impl From<Schema> for QueryDescription {
+	fn from(schema: Schema) -> Self {
+		QueryDescription {
+			default: schema.query_name.is_empty(),
+			name: schema.query_name,
+			key_schema: schema.key_schema,
+			output_schema: schema.output_schema,
+		}
+	}
+}
+
+/// The plugin protocol doesn't list the configuration a plugin accepts, so this
+/// reports whether the plugin accepted the configuration it was given, and its
+/// explanation if it didn't, which usually names the missing or invalid keys.
+#[derive(Debug, Serialize)]
+pub struct ConfigurationDescription {
+	/// The configuration given to the plugin.
+	pub given: Value,
+	pub accepted: bool,
+	pub message: Option<String>,
+}
+
+/// Start an installed plugin and ask it to describe itself.
+///
+/// The plugin is stopped before this returns.
+pub fn describe_plugin(
+	runtime: &Handle,
+	plugin_id: &PluginId,
+	plugin_cache: &HcPluginCache,
+	scratch: &HcScratchCache,
+	executor: PluginExecutor,
+	config: Value,
+) -> Result<PluginDescription> {
+	let current_arch = get_current_arch();
+	let plugin_kdl = plugin_cache.plugin_kdl(plugin_id);
+	let manifest = PluginManifest::from_file(&plugin_kdl)?;
+	let entrypoint = manifest.get_entrypoint(&current_arch).ok_or_else(|| {
+		hc_error!(
+			"Could not find {} entrypoint for {}",
+			current_arch,
+			plugin_id
+		)
+	})?;
+
+	let name = plugin_id.to_policy_file_plugin_identifier();
+	let plugin = Plugin {
+		name: name.clone(),
+		working_dir: plugin_cache.plugin_download_dir(plugin_id),
+		entrypoint,
+		scratch_dir: Some(scratch.plugin_scratch_dir(&name)?),
+		data_dir: None,
+		secrets: vec![],
+		limits: ResourceLimits::default(),
+	};
+
+	let dependencies = manifest
+		.dependencies
+		.0
+		.iter()
+		.map(|dependency| DependencyDescription {
+			plugin: dependency.plugin_id.to_policy_file_plugin_identifier(),
+			version: dependency.plugin_id.version().0.clone(),
+		})
+		.collect();
+
+	runtime.block_on(async {
+		let mut ctx = executor.start_plugin(plugin).await?;
+
+		let mut queries: Vec<QueryDescription> = ctx
+			.get_query_schemas()
+			.await?
+			.into_iter()
+			.map(QueryDescription::from)
+			.collect();
+		queries.sort_by(|a, b| (!a.default, &a.name).cmp(&(!b.default, &b.name)));
+
+		let result = ctx.set_configuration(&config).await?;
+		let accepted = result.as_result().is_ok();
+
+		let (default_policy_expression, default_query_explanation) = if accepted {
+			(
+				ctx.get_default_policy_expression().await?,
+				ctx.explain_default_query().await?,
+			)
+		} else {
+			(None, None)
+		};
+
+		Ok::<_, crate::error::Error>(PluginDescription {
+			publisher: manifest.publisher.0.clone(),
+			name: manifest.name.0.clone(),
+			version: manifest.version.0.clone(),
+			license: manifest.license.0.clone(),
+			dependencies,
+			queries,
+			configuration: ConfigurationDescription {
+				given: config,
+				accepted,
+				message: result.message,
+			},
+			default_policy_expression,
+			default_query_explanation,
+		})
+	})
+}
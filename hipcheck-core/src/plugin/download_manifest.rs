@@ -5,7 +5,10 @@ use crate::plugin::arch::KnownArch;
 use crate::{
 	hc_error,
 	plugin::{arch::Arch, PluginVersion},
-	util::kdl::{extract_data, ParseKdlNode},
+	util::{
+		http::download::Checksum,
+		kdl::{extract_data, ParseKdlNode},
+	},
 };
 use kdl::{KdlDocument, KdlNode, KdlValue};
 use std::{fmt::Display, str::FromStr};
@@ -18,6 +21,9 @@ impl ParseKdlNode for url::Url {
 	}
 
 	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
 		let raw_url = node.entries().first()?.value().as_string()?;
 		url::Url::from_str(raw_url).ok()
 	}
@@ -68,6 +74,15 @@ impl HashWithDigest {
 	}
 }
 
+impl From<&HashWithDigest> for Checksum {
+	fn from(hash: &HashWithDigest) -> Self {
+		match hash.hash_algorithm {
+			HashAlgorithm::Sha256 => Checksum::Sha256(hash.digest.clone()),
+			HashAlgorithm::Blake3 => Checksum::Blake3(hash.digest.clone()),
+		}
+	}
+}
+
 impl ParseKdlNode for HashWithDigest {
 	fn kdl_key() -> &'static str {
 		"hash"
@@ -197,6 +212,55 @@ impl ParseKdlNode for Size {
 	}
 }
 
+/// How a plugin archive's signature is packaged
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SignatureFormat {
+	/// a base64-encoded signature of the archive, as made by `cosign sign-blob`
+	#[default]
+	Detached,
+	/// a Sigstore bundle holding the signature of the archive
+	Bundle,
+}
+
+impl TryFrom<&str> for SignatureFormat {
+	type Error = crate::Error;
+
+	fn try_from(value: &str) -> Result<Self, Self::Error> {
+		match value {
+			"detached" => Ok(SignatureFormat::Detached),
+			"bundle" => Ok(SignatureFormat::Bundle),
+			_ => Err(hc_error!("Invalid signature format specified: '{}'", value)),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+	/// where to download the signature from
+	pub url: url::Url,
+	/// how the signature is packaged
+	pub format: SignatureFormat,
+}
+
+impl ParseKdlNode for Signature {
+	fn kdl_key() -> &'static str {
+		"signature"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let url = url::Url::from_str(node.get("url")?.as_string()?).ok()?;
+		// The format is optional, but an unknown format is an error
+		let format = match node.get("format") {
+			Some(format) => SignatureFormat::try_from(format.as_string()?).ok()?,
+			None => SignatureFormat::default(),
+		};
+		Some(Signature { url, format })
+	}
+}
+
 /// Represents one entry in a download manifest file, as spelled out in RFD #0004
 /// Example entry:
 /// ```
@@ -205,6 +269,7 @@ impl ParseKdlNode for Size {
 ///  hash alg="SHA256" digest="b8e111e7817c4a1eb40ed50712d04e15b369546c4748be1aa8893b553f4e756b"
 ///  compress format="tar.xz"
 ///  size bytes=2_869_896
+///  signature url="https://github.com/mitre/hipcheck/releases/download/hipcheck-v3.4.0/hipcheck-aarch64-apple-darwin.tar.xz.sig"
 ///}
 ///```
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -226,6 +291,9 @@ pub struct DownloadManifestEntry {
 	/// Describes the size of the downloaded artifact, used to validate the download was
 	/// successful, makes it more difficult for an attacker to distribute malformed artifacts
 	pub size: Size,
+	/// Where to find the signature of the archive, which is checked against the keys trusted
+	/// for the plugin's publisher before the plugin is run. Not part of RFD #0004.
+	pub signature: Option<Signature>,
 }
 
 impl ParseKdlNode for DownloadManifestEntry {
@@ -251,6 +319,14 @@ impl ParseKdlNode for DownloadManifestEntry {
 		let hash: HashWithDigest = extract_data(nodes)?;
 		let compress: Compress = extract_data(nodes)?;
 		let size: Size = extract_data(nodes)?;
+		// The signature is optional, but an invalid `signature` node is an error
+		let signature = match nodes
+			.iter()
+			.find(|node| node.name().to_string().as_str() == Signature::kdl_key())
+		{
+			Some(node) => Some(Signature::parse_node(node)?),
+			None => None,
+		};
 
 		Some(Self {
 			version,
@@ -259,6 +335,7 @@ impl ParseKdlNode for DownloadManifestEntry {
 			hash,
 			compress,
 			size,
+			signature,
 		})
 	}
 }
@@ -386,6 +463,52 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn test_parsing_signature() {
+		let raw_url = "https://example.com/plugin.tar.xz.sig";
+		let node = KdlNode::from_str(format!(r#"signature url="{}""#, raw_url).as_str()).unwrap();
+		assert_eq!(
+			Signature::parse_node(&node).unwrap(),
+			Signature {
+				url: Url::parse(raw_url).unwrap(),
+				format: SignatureFormat::Detached
+			}
+		);
+
+		let node =
+			KdlNode::from_str(format!(r#"signature url="{}" format="bundle""#, raw_url).as_str())
+				.unwrap();
+		assert_eq!(
+			Signature::parse_node(&node).unwrap().format,
+			SignatureFormat::Bundle
+		);
+
+		// ensure unknown formats do not pass
+		let node =
+			KdlNode::from_str(format!(r#"signature url="{}" format="pgp""#, raw_url).as_str())
+				.unwrap();
+		assert!(Signature::parse_node(&node).is_none());
+	}
+
+	#[test]
+	fn test_parsing_signed_download_manifest_entry() {
+		let contents = r#"plugin version="0.1.0" arch="aarch64-apple-darwin" {
+  signature url="https://example.com/plugin.tar.xz.sig"
+  url "https://example.com/plugin.tar.xz"
+  hash alg="SHA256" digest="b8e111e7817c4a1eb40ed50712d04e15b369546c4748be1aa8893b553f4e756b"
+  compress format="tar.xz"
+  size bytes=2_869_896
+}"#;
+		let entries = DownloadManifest::from_str(contents).unwrap();
+		let entry = entries.iter().next().unwrap();
+		// the signature's URL isn't mistaken for the archive's
+		assert_eq!(entry.url.as_str(), "https://example.com/plugin.tar.xz");
+		assert_eq!(
+			entry.signature.as_ref().unwrap().url.as_str(),
+			"https://example.com/plugin.tar.xz.sig"
+		);
+	}
+
 	#[test]
 	fn test_parsing_single_download_manifest_entry() {
 		let version = "0.1.0";
@@ -424,6 +547,7 @@ mod test {
 			size: Size {
 				bytes: u64::from_str(size.replace("_", "").as_str()).unwrap(),
 			},
+			signature: None,
 		};
 
 		assert_eq!(
@@ -459,7 +583,8 @@ plugin version="0.1.0" arch="x86_64-apple-darwin" {
 				compress: Compress::new(ArchiveFormat::TarXz),
 				size: Size {
 					bytes: 2_869_896
-				}
+				},
+				signature: None,
 			},
 		    entries_iter.next().unwrap()
 		);
@@ -470,7 +595,8 @@ plugin version="0.1.0" arch="x86_64-apple-darwin" {
 				url: Url::parse("https://github.com/mitre/hipcheck/releases/download/hipcheck-v3.4.0/hipcheck-x86_64-apple-darwin.tar.xz").unwrap(),
 				hash: HashWithDigest::new(HashAlgorithm::Sha256, "ddb8c6d26dd9a91e11c99b3bd7ee2b9585aedac6e6df614190f1ba2bfe86dc19".to_owned()),
                 compress: Compress::new(ArchiveFormat::TarXz),
-                size: Size::new(3_183_768)
+                size: Size::new(3_183_768),
+				signature: None,
 			},
 		    entries_iter.next().unwrap()
         );
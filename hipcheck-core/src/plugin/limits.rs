@@ -0,0 +1,221 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Limiting the resources a plugin process may use, and the user it runs as,
+//! so an untrusted plugin can't exhaust the host.
+//!
+//! Limits are set on the plugin's process as it starts, as hard resource
+//! limits the plugin can't raise, and are inherited by anything it runs.
+
+use crate::{error::Result, hc_error};
+use serde::Serialize;
+use std::{path::PathBuf, process::Command, time::Duration};
+
+/// The limits a plugin is started with, each unlimited if not given.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct ResourceLimits {
+	/// The most memory the plugin may map, in bytes.
+	pub memory: Option<u64>,
+	/// The most CPU time the plugin may use, after which it's killed.
+	pub cpu_time: Option<Duration>,
+	/// The most files and sockets the plugin may have open at once.
+	pub open_files: Option<u64>,
+	/// The user the plugin runs as, by name or ID, instead of the user running
+	/// Hipcheck.
+	pub user: Option<String>,
+}
+
+impl ResourceLimits {
+	/// Whether the plugin is started as it would be without limits.
+	pub fn is_empty(&self) -> bool {
+		self == &ResourceLimits::default()
+	}
+
+	/// Take any limits not given here from `defaults`.
+	pub fn or(self, defaults: &ResourceLimits) -> ResourceLimits {
+		ResourceLimits {
+			memory: self.memory.or(defaults.memory),
+			cpu_time: self.cpu_time.or(defaults.cpu_time),
+			open_files: self.open_files.or(defaults.open_files),
+			user: self.user.or_else(|| defaults.user.clone()),
+		}
+	}
+
+	/// The arguments passing the limits on to a pooled plugin's supervisor,
+	/// which applies them to the plugin.
+	pub fn to_args(&self) -> Vec<String> {
+		let mut args = Vec::new();
+		if let Some(memory) = self.memory {
+			args.extend(["--memory".to_owned(), memory.to_string()]);
+		}
+		if let Some(cpu_time) = self.cpu_time {
+			args.extend(["--cpu-time".to_owned(), cpu_time.as_secs().to_string()]);
+		}
+		if let Some(open_files) = self.open_files {
+			args.extend(["--open-files".to_owned(), open_files.to_string()]);
+		}
+		if let Some(user) = &self.user {
+			args.extend(["--user".to_owned(), user.clone()]);
+		}
+		args
+	}
+
+	/// Give the directories the plugin writes to, like its scratch directory,
+	/// to the user it runs as.
+	#[cfg(unix)]
+	pub fn hand_over<'a>(&self, dirs: impl IntoIterator<Item = &'a PathBuf>) -> Result<()> {
+		use crate::error::Context as _;
+
+		let Some(user) = &self.user else {
+			return Ok(());
+		};
+		let (uid, gid) = lookup_user(user)?;
+		for dir in dirs {
+			std::os::unix::fs::chown(dir, Some(uid), Some(gid)).with_context(|| {
+				format!("failed to give '{}' to user '{}'", dir.display(), user)
+			})?;
+		}
+		Ok(())
+	}
+
+	#[cfg(not(unix))]
+	pub fn hand_over<'a>(&self, _dirs: impl IntoIterator<Item = &'a PathBuf>) -> Result<()> {
+		Ok(())
+	}
+
+	/// Start the plugin's command with the limits.
+	#[cfg(unix)]
+	pub fn apply(&self, cmd: &mut Command) -> Result<()> {
+		use std::os::unix::process::CommandExt as _;
+
+		if let Some(user) = &self.user {
+			let (uid, gid) = lookup_user(user)?;
+			cmd.uid(uid).gid(gid);
+		}
+
+		let limits = [
+			(libc::RLIMIT_AS, self.memory),
+			(libc::RLIMIT_CPU, self.cpu_time.map(|time| time.as_secs())),
+			(libc::RLIMIT_NOFILE, self.open_files),
+		]
+		.into_iter()
+		.filter_map(|(resource, limit)| Some((resource, limit? as libc::rlim_t)))
+		.collect::<Vec<_>>();
+		if limits.is_empty() {
+			return Ok(());
+		}
+
+		// SAFETY: the closure runs between fork and exec, so it only makes
+		// async-signal-safe calls and doesn't allocate.
+		unsafe {
+			cmd.pre_exec(move || {
+				for (resource, limit) in &limits {
+					let rlimit = libc::rlimit {
+						rlim_cur: *limit,
+						rlim_max: *limit,
+					};
+					if libc::setrlimit(*resource, &rlimit) != 0 {
+						return Err(std::io::Error::last_os_error());
+					}
+				}
+				Ok(())
+			});
+		}
+		Ok(())
+	}
+
+	#[cfg(not(unix))]
+	pub fn apply(&self, _cmd: &mut Command) -> Result<()> {
+		if self.is_empty() {
+			return Ok(());
+		}
+		Err(hc_error!(
+			"plugin resource limits are only supported on Unix systems"
+		))
+	}
+}
+
+/// Find the user and group IDs of a user, given by name or ID.
+#[cfg(unix)]
+fn lookup_user(user: &str) -> Result<(u32, u32)> {
+	use std::{ffi::CString, mem::MaybeUninit, ptr};
+
+	let name = CString::new(user).map_err(|_| hc_error!("invalid user name '{}'", user))?;
+	let mut passwd = MaybeUninit::<libc::passwd>::uninit();
+	let mut buf = vec![0; 16 * 1024];
+	let mut found = ptr::null_mut();
+
+	// SAFETY: `passwd` and `buf` outlive the call, which only writes within
+	// them, and `found` is only read once the call succeeds.
+	let status = unsafe {
+		match user.parse::<libc::uid_t>() {
+			Ok(uid) => libc::getpwuid_r(
+				uid,
+				passwd.as_mut_ptr(),
+				buf.as_mut_ptr(),
+				buf.len(),
+				&mut found,
+			),
+			Err(_) => libc::getpwnam_r(
+				name.as_ptr(),
+				passwd.as_mut_ptr(),
+				buf.as_mut_ptr(),
+				buf.len(),
+				&mut found,
+			),
+		}
+	};
+	if status != 0 || found.is_null() {
+		return Err(hc_error!("no such user '{}' to run plugins as", user));
+	}
+
+	// SAFETY: the entry was found, so the call filled in `passwd`.
+	let passwd = unsafe { passwd.assume_init() };
+	Ok((passwd.pw_uid, passwd.pw_gid))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+	use super::*;
+	use std::process::Stdio;
+
+	#[test]
+	fn plugin_limits_take_defaults() {
+		let defaults = ResourceLimits {
+			memory: Some(1 << 30),
+			open_files: Some(1024),
+			..Default::default()
+		};
+		let limits = ResourceLimits {
+			open_files: Some(64),
+			user: Some("nobody".to_owned()),
+			..Default::default()
+		}
+		.or(&defaults);
+		assert_eq!(limits.memory, Some(1 << 30));
+		assert_eq!(limits.open_files, Some(64));
+		assert_eq!(limits.user.as_deref(), Some("nobody"));
+	}
+
+	#[test]
+	fn limits_apply_to_the_plugin_process() {
+		let limits = ResourceLimits {
+			open_files: Some(64),
+			cpu_time: Some(Duration::from_secs(30)),
+			..Default::default()
+		};
+		let mut cmd = Command::new("sh");
+		cmd.args(["-c", "ulimit -n; ulimit -t"])
+			.stdout(Stdio::piped());
+		limits.apply(&mut cmd).unwrap();
+
+		let output = cmd.output().unwrap();
+		assert_eq!(String::from_utf8_lossy(&output.stdout), "64\n30\n");
+	}
+
+	#[test]
+	fn users_are_found_by_name_or_id() {
+		assert_eq!(lookup_user("root").unwrap(), (0, 0));
+		assert_eq!(lookup_user("0").unwrap(), (0, 0));
+		assert!(lookup_user("no-such-hipcheck-user").is_err());
+	}
+}
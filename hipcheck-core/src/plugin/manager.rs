@@ -0,0 +1,376 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+	hc_error,
+	plugin::{
+		pool::{PluginPoolDir, PoolEntry, Pooled},
+		try_get_bin_for_entrypoint, HcPluginClient, Plugin, PluginContext, PluginContextWithConfig,
+		PluginWithConfig,
+	},
+	shell::Shell,
+	util::secret::redact,
+	Result,
+};
+use futures::future::join_all;
+use hipcheck_common::{
+	plugin_cache::CACHE_DIR_ENV_VAR,
+	plugin_log::{parse_line, LOG_LEVEL_ENV_VAR},
+	plugin_scratch::SCRATCH_DIR_ENV_VAR,
+	proto::plugin_service_client::PluginServiceClient,
+};
+use rand::Rng;
+use std::{
+	ffi::OsString,
+	io::{BufRead, BufReader},
+	ops::Range,
+	path::Path,
+	process::{ChildStderr, Command, Stdio},
+};
+use tokio::time::{sleep_until, Duration, Instant};
+use tracing::level_filters::LevelFilter;
+
+#[derive(Clone, Debug)]
+pub struct PluginExecutor {
+	max_spawn_attempts: usize,
+	max_conn_attempts: usize,
+	port_range: Range<u16>,
+	backoff_interval: Duration,
+	jitter_percent: u8,
+	grpc_buffer: usize,
+	max_message_size: usize,
+	/// Where plugins are kept running for later runs, if they are.
+	pool: Option<PluginPoolDir>,
+}
+impl PluginExecutor {
+	pub fn new(
+		max_spawn_attempts: usize,
+		max_conn_attempts: usize,
+		port_range: Range<u16>,
+		backoff_interval_micros: u64,
+		jitter_percent: u8,
+		grpc_buffer: usize,
+		max_message_size: usize,
+	) -> Result<Self> {
+		if jitter_percent > 100 {
+			return Err(hc_error!(
+				"jitter_percent must be <= 100, got {}",
+				jitter_percent
+			));
+		}
+
+		let backoff_interval = Duration::from_micros(backoff_interval_micros);
+
+		Ok(PluginExecutor {
+			max_spawn_attempts,
+			max_conn_attempts,
+			port_range,
+			backoff_interval,
+			jitter_percent,
+			grpc_buffer,
+			max_message_size,
+			pool: None,
+		})
+	}
+
+	/// Keep the plugins started with their configs running after this run,
+	/// and reuse any left running by earlier runs.
+	pub fn with_pool(mut self, pool: Option<PluginPoolDir>) -> Self {
+		self.pool = pool;
+		self
+	}
+
+	fn get_available_port(&self) -> Result<u16> {
+		for _i in self.port_range.start..self.port_range.end {
+			// @Todo - either TcpListener::bind returns Ok even if port is bound
+			// or we have a race condition. For now just have OS assign a port
+			// if std::net::TcpListener::bind(format!("127.0.0.1:{i}")).is_ok() {
+			// 	return Ok(i);
+			// }
+			if let Ok(addr) = std::net::TcpListener::bind("127.0.0.1:0") {
+				if let Ok(local_addr) = addr.local_addr() {
+					return Ok(local_addr.port());
+				}
+			}
+		}
+
+		Err(hc_error!("Failed to find available port"))
+	}
+
+	pub async fn start_plugins(&self, plugins: Vec<Plugin>) -> Result<Vec<PluginContext>> {
+		join_all(plugins.into_iter().map(|p| self.start_plugin(p)))
+			.await
+			.into_iter()
+			.collect()
+	}
+
+	/// Start plugins along with their configs, reusing pooled plugins already
+	/// running with the same configs.
+	pub async fn start_configured_plugins(
+		&self,
+		plugins: Vec<PluginWithConfig>,
+	) -> Result<Vec<PluginContextWithConfig>> {
+		join_all(plugins.into_iter().map(|plugin| async move {
+			let (plugin, config) = plugin.into();
+			let ctx = match &self.pool {
+				Some(pool) => {
+					let entry = pool.entry(&plugin, &config, self.max_message_size);
+					match self.connect_pooled(&plugin, &entry).await {
+						Some(ctx) => ctx,
+						None => self.spawn_plugin(plugin, Some(entry)).await?,
+					}
+				}
+				None => self.start_plugin(plugin).await?,
+			};
+			Ok(PluginContextWithConfig(ctx, config))
+		}))
+		.await
+		.into_iter()
+		.collect()
+	}
+
+	/// Connect to a pooled plugin left running by an earlier run, if there is one.
+	async fn connect_pooled(&self, plugin: &Plugin, entry: &PoolEntry) -> Option<PluginContext> {
+		let record = entry.running()?;
+
+		let Ok(grpc) =
+			PluginServiceClient::connect(format!("http://127.0.0.1:{}", record.port)).await
+		else {
+			// The plugin stopped without its record being removed.
+			entry.forget();
+			return None;
+		};
+
+		tracing::debug!(plugin = %plugin.name, port = record.port, "reusing pooled plugin");
+		Some(PluginContext {
+			plugin: plugin.clone(),
+			port: record.port,
+			grpc: grpc
+				.max_decoding_message_size(self.max_message_size)
+				.max_encoding_message_size(self.max_message_size),
+			proc: None,
+			grpc_query_buffer_size: self.grpc_buffer,
+			max_message_size: self.max_message_size,
+			pool: Some(Pooled::Leased {
+				record,
+				lease: entry.reuse(),
+			}),
+		})
+	}
+
+	pub async fn start_plugin(&self, plugin: Plugin) -> Result<PluginContext> {
+		self.spawn_plugin(plugin, None).await
+	}
+
+	/// Start a plugin, under a supervisor if it's to be pooled.
+	async fn spawn_plugin(&self, plugin: Plugin, pool: Option<PoolEntry>) -> Result<PluginContext> {
+		// Plugin startup design has inherent TOCTOU flaws since we tell the plugin
+		// which port we expect it to bind to. We can try to ensure the port we pass
+		// on the cmdline is not already in use, but it is still possible for that
+		// port to become unavailable between our check and the plugin's bind attempt.
+		// Hence the need for subsequent attempts if we get unlucky
+
+		// `entrypoint` is a string that represents a CLI invocation which may contain
+		// arguments, so we have to split off only the first token
+		let (opt_bin_path_str, args) = try_get_bin_for_entrypoint(&plugin.entrypoint);
+		let Some(bin_path_str) = opt_bin_path_str else {
+			return Err(hc_error!(
+				"Unable to get bin path for plugin entrypoint '{}'",
+				&plugin.entrypoint
+			));
+		};
+
+		let Ok(canon_working_dir) = plugin.working_dir.canonicalize() else {
+			return Err(hc_error!(
+				"Failed to canonicalize plugin working dir: {:?}",
+				&plugin.working_dir
+			));
+		};
+
+		// Entrypoints are often "<BIN_NAME>" which can overlap with existing binaries on the
+		// system like "git", "npm", so we must search for <BIN_NAME> from within the plugin
+		// cache subfolder. First, grab the existing path.
+		let Some(mut which_os_paths) =
+			std::env::var_os("PATH").map(|s| std::env::split_paths(&s).collect::<Vec<_>>())
+		else {
+			return Err(hc_error!("Unable to get system PATH var"));
+		};
+
+		// Add canonicalized plugin cache dir to end of PATH for plugin exec
+		let mut cmd_os_paths = which_os_paths.clone();
+		cmd_os_paths.push(canon_working_dir.clone());
+		let cmd_path = std::env::join_paths(cmd_os_paths).unwrap();
+
+		// Add canonicalized plugin cache dir to front of PATH for bin-searching
+		which_os_paths.insert(0, canon_working_dir.clone());
+		let which_path = std::env::join_paths(which_os_paths).unwrap();
+
+		// Find the binary_str using temp PATH
+		let Ok(canon_bin_path) = which::which_in::<&str, &OsString, &Path>(
+			bin_path_str,
+			Some(&which_path),
+			canon_working_dir.as_ref(),
+		) else {
+			return Err(hc_error!(
+				"Failed to find binary '{}' for plugin",
+				bin_path_str
+			));
+		};
+
+		tracing::debug!(plugin = %plugin.name, path = ?canon_bin_path, "starting plugin");
+
+		let mut spawn_attempts: usize = 0;
+		while spawn_attempts < self.max_spawn_attempts {
+			let mut spawn_args = args.clone();
+
+			// Find free port for process. Don't retry if we fail since this means all
+			// ports in the desired range are already bound
+			let port = self.get_available_port()?;
+			let port_str = port.to_string();
+			spawn_args.push("--port");
+			spawn_args.push(port_str.as_str());
+
+			// Spawn plugin process
+			tracing::debug!(entrypoint = %plugin.entrypoint, port, "spawning plugin");
+			let (mut cmd, scratch_dir) = match &pool {
+				Some(entry) => {
+					let (cmd, scratch_dir) = entry.supervised_command(
+						&canon_bin_path,
+						&spawn_args,
+						port,
+						&plugin.limits,
+					)?;
+					(cmd, Some(scratch_dir))
+				}
+				None => {
+					let mut cmd = Command::new(&canon_bin_path);
+					cmd.args(&spawn_args)
+						// @Temporary - directly forward stdout from plugin to shell
+						.stdout(std::io::stdout())
+						.stderr(Stdio::piped());
+					(cmd, plugin.scratch_dir.clone())
+				}
+			};
+			cmd.env("PATH", &cmd_path)
+				// Tell the plugin to log at the same level as core.
+				.env(LOG_LEVEL_ENV_VAR, LevelFilter::current().to_string());
+			// Tell the plugin where to put its temporary files.
+			if let Some(scratch_dir) = &scratch_dir {
+				cmd.env(SCRATCH_DIR_ENV_VAR, scratch_dir);
+			}
+			// Tell the plugin where to keep data between runs.
+			if let Some(data_dir) = &plugin.data_dir {
+				cmd.env(CACHE_DIR_ENV_VAR, data_dir);
+			}
+			// Give the plugin the secrets it was configured with.
+			for (var, secret) in &plugin.secrets {
+				cmd.env(var, secret.expose());
+			}
+			// Let the user the plugin runs as write to its directories, and
+			// limit what it may use. A pooled plugin's supervisor starts the
+			// plugin with its limits, so they don't apply to the supervisor.
+			plugin
+				.limits
+				.hand_over(scratch_dir.iter().chain(&plugin.data_dir))?;
+			if pool.is_none() {
+				plugin.limits.apply(&mut cmd)?;
+			}
+			let Ok(mut proc) = cmd.spawn() else {
+				spawn_attempts += 1;
+				continue;
+			};
+			if let Some(stderr) = proc.stderr.take() {
+				forward_plugin_stderr(plugin.name.clone(), stderr);
+			}
+			// Attempt to connect to the plugin's gRPC server up to N times, using
+			// linear backoff with a percentage jitter.
+			let mut conn_attempts = 0;
+			let mut opt_grpc: Option<HcPluginClient> = None;
+			while conn_attempts < self.max_conn_attempts {
+				// Jitter could be positive or negative, so mult by 2 to cover both sides
+				let jitter: i32 = rand::thread_rng().gen_range(0..(2 * self.jitter_percent)) as i32;
+				// Then subtract by self.jitter_percent to center around 0, and add to 100%
+				let jitter_percent = 1.0 + ((jitter - (self.jitter_percent as i32)) as f64 / 100.0);
+				// Once we are confident this math works, we can remove this
+				if !(0.0..=2.0).contains(&jitter_percent) {
+					panic!("Math error! We should have better guardrails around PluginExecutor field values.");
+				}
+				// sleep_duration = (backoff * conn_attempts) * (1.0 +/- jitter_percent)
+				let sleep_duration: Duration = self
+					.backoff_interval
+					.saturating_mul(conn_attempts as u32)
+					.mul_f64(jitter_percent);
+				sleep_until(Instant::now() + sleep_duration).await;
+				if let Ok(grpc) =
+					PluginServiceClient::connect(format!("http://127.0.0.1:{port_str}")).await
+				{
+					// Accept messages up to the largest size we may negotiate
+					opt_grpc = Some(
+						grpc.max_decoding_message_size(self.max_message_size)
+							.max_encoding_message_size(self.max_message_size),
+					);
+					break;
+				} else {
+					conn_attempts += 1;
+				}
+			}
+			// If opt_grpc is None, we did not manage to connect to the plugin. Kill it
+			// and try again
+			let Some(grpc) = opt_grpc else {
+				// A pooled plugin's supervisor shuts it down, since it's never recorded
+				if pool.is_none() {
+					if let Err(e) = proc.kill() {
+						println!("Failed to kill child process for plugin: {e}");
+					}
+				}
+				spawn_attempts += 1;
+				continue;
+			};
+			// We now have an open gRPC connection to our plugin process. A pooled
+			// plugin is left to its supervisor, so it isn't killed with this run.
+			return Ok(PluginContext {
+				plugin: plugin.clone(),
+				port,
+				grpc,
+				proc: pool.is_none().then_some(proc),
+				grpc_query_buffer_size: self.grpc_buffer,
+				max_message_size: self.max_message_size,
+				pool: pool.map(Pooled::Started),
+			});
+		}
+		Err(hc_error!(
+			"Reached max spawn attempts for plugin {}",
+			plugin.name
+		))
+	}
+}
+
+/// Re-emit a plugin's standard error through core, tagged with the plugin's name.
+///
+/// Lines in the plugin log format are passed to core's logger with the target
+/// `plugin::<name>`, so they're filtered by `HC_LOG` like any other log message.
+/// Anything else (like a panic message) is printed to standard error as-is.
+fn forward_plugin_stderr(name: String, stderr: ChildStderr) {
+	let target = format!("plugin::{name}");
+
+	std::thread::spawn(move || {
+		let (publisher, plugin) = name.split_once('/').unwrap_or(("", &name));
+		let _span = tracing::error_span!("plugin", publisher, plugin).entered();
+
+		for line in BufReader::new(stderr).lines() {
+			let Ok(line) = line else {
+				break;
+			};
+
+			match parse_line(&line) {
+				Some(record) => log::log!(
+					target: &target,
+					record.level,
+					"{}: {}",
+					record.target,
+					record.message
+				),
+				None => Shell::eprintln(format!("[{name}] {}", redact(&line))),
+			}
+		}
+	});
+}
@@ -0,0 +1,276 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod arch;
+mod bundle;
+pub mod describe;
+mod download_manifest;
+mod limits;
+mod manager;
+mod plugin_id;
+mod plugin_manifest;
+mod pool;
+mod progress;
+mod registry;
+mod retrieval;
+mod signature;
+mod types;
+
+pub use crate::plugin::{get_plugin_key, manager::*, plugin_id::PluginId, types::*};
+use crate::policy_exprs::Expr;
+use crate::{
+	cache::{results::HcResultCache, scratch::HcScratchCache},
+	error::Result,
+	exec::QueryTimeouts,
+	hc_error,
+};
+pub use arch::{get_current_arch, try_set_arch, Arch};
+pub use bundle::{create_bundle, load_bundle};
+pub use download_manifest::{ArchiveFormat, DownloadManifest, HashAlgorithm, HashWithDigest};
+use hipcheck_common::types::{Query, QueryDirection};
+pub use limits::ResourceLimits;
+pub use plugin_manifest::{
+	try_get_bin_for_entrypoint, PluginManifest, PluginName, PluginPublisher, PluginVersion,
+	Prerequisite,
+};
+pub use pool::{set_pool_supervisor, supervise, PluginPoolDir, Supervised};
+pub use progress::QueryProgress;
+pub use registry::{RegistryEntry, RegistryIndex};
+pub use retrieval::{install_plugin, retrieve_plugins};
+use serde_json::Value;
+pub use signature::set_allow_unsigned;
+use std::{
+	collections::{HashMap, HashSet},
+	ops::Not,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+pub async fn initialize_plugins(
+	plugins: Vec<PluginContextWithConfig>,
+	progress: &Arc<QueryProgress>,
+) -> Result<Vec<PluginTransport>> {
+	let mut set = tokio::task::JoinSet::new();
+
+	for (p, c) in plugins
+		.into_iter()
+		.map(Into::<(PluginContext, Value)>::into)
+	{
+		set.spawn(p.initialize(c, Arc::clone(progress)));
+	}
+
+	let mut inited: Vec<PluginTransport> = vec![];
+	let mut failures: Vec<_> = vec![];
+
+	while let Some(res) = set.join_next().await {
+		// @Todo - what is the cleanup if the tokio func fails?
+		let init_res = res?;
+
+		// Instead of immediately erroring, we need to finish
+		// initializing all plugins so they shut down properly
+		match init_res {
+			Ok(pt) => inited.push(pt),
+			Err(e) => failures.push(e),
+		};
+	}
+
+	if failures.is_empty().not() {
+		let mut err = "Failures occurred during plugin initialization:".to_owned();
+		for x in failures {
+			err += "\n";
+			err += x.to_string().as_str();
+		}
+		Err(hc_error!("{}", err))
+	} else {
+		Ok(inited)
+	}
+}
+
+#[derive(Debug)]
+pub struct ActivePlugin {
+	next_id: AtomicUsize,
+	channel: PluginTransport,
+}
+
+impl ActivePlugin {
+	pub fn new(channel: PluginTransport) -> Self {
+		ActivePlugin {
+			next_id: AtomicUsize::new(1),
+			channel,
+		}
+	}
+
+	pub fn get_default_policy_expr(&self) -> Option<&Expr> {
+		self.channel.opt_default_policy_expr.as_ref()
+	}
+
+	pub fn get_default_query_explanation(&self) -> Option<&String> {
+		self.channel.opt_explain_default_query.as_ref()
+	}
+
+	pub fn get_schema(&self, query: &str) -> Option<&Schema> {
+		self.channel.schemas.get(query)
+	}
+
+	/// Get the ID to start a new query with.
+	pub fn next_query_id(&self) -> usize {
+		// even IDs reserved for plugin-originated queries, so skip to next odd ID
+		self.next_id.fetch_add(2, Ordering::Relaxed)
+	}
+
+	pub async fn query(&self, id: usize, name: String, key: Value) -> Result<PluginResponse> {
+		// TODO: remove this unwrap
+		let (publisher, plugin) = self.channel.name().split_once('/').unwrap();
+
+		// @Todo - check name+key valid for schema
+		let query = Query {
+			id,
+			direction: QueryDirection::Request,
+			publisher: publisher.to_owned(),
+			plugin: plugin.to_owned(),
+			query: name,
+			key: vec![key],
+			output: vec![],
+			concerns: vec![],
+			fragments: vec![],
+		};
+
+		Ok(self.channel.query(query).await?.into())
+	}
+
+	pub async fn resume_query(
+		&self,
+		state: AwaitingResult,
+		output: Vec<Value>,
+	) -> Result<PluginResponse> {
+		let query = Query {
+			id: state.id,
+			direction: QueryDirection::Response,
+			publisher: state.publisher,
+			plugin: state.plugin,
+			query: state.query,
+			key: vec![],
+			output,
+			concerns: vec![],
+			fragments: vec![],
+		};
+
+		tracing::trace!("Resuming query");
+
+		Ok(self.channel.query(query).await?.into())
+	}
+
+	/// Tell the plugin to stop working on a query, since its answer is no
+	/// longer needed.
+	pub fn cancel_query(&self, id: usize) {
+		self.channel.cancel(id as i32);
+	}
+}
+
+#[derive(Debug)]
+pub struct HcPluginCore {
+	pub plugins: HashMap<String, ActivePlugin>,
+	/// The plugins' scratch directories, which are deleted once the plugins have shut down.
+	scratch: Option<HcScratchCache>,
+	/// The results of queries saved from earlier runs, if they're being cached.
+	results: Option<HcResultCache>,
+	/// How long queries to each plugin may run before they're cancelled.
+	timeouts: QueryTimeouts,
+	/// The queries each plugin declared its analyses depend on.
+	prerequisites: HashMap<String, Vec<Prerequisite>>,
+	/// The progress plugins have reported on their queries.
+	progress: Arc<QueryProgress>,
+	/// The plugins whose queries are skipped, since they need the network and
+	/// Hipcheck is offline.
+	offline_skipped: HashSet<String>,
+}
+
+impl HcPluginCore {
+	// When this object is returned, the plugins are all connected but the
+	// initialization protocol over the gRPC still needs to be completed
+	pub async fn new(executor: PluginExecutor, plugins: Vec<PluginWithConfig>) -> Result<Self> {
+		// Start plugins async, keeping each with its config
+		let mapped_ctxs = executor.start_configured_plugins(plugins).await?;
+
+		// Use configs to initialize corresponding plugin
+		let progress = Arc::new(QueryProgress::default());
+		let plugins = HashMap::<String, ActivePlugin>::from_iter(
+			initialize_plugins(mapped_ctxs, &progress)
+				.await?
+				.into_iter()
+				.map(|p| (p.name().to_owned(), ActivePlugin::new(p))),
+		);
+
+		// Now we have a set of started and initialized plugins to interact with
+		Ok(HcPluginCore {
+			plugins,
+			scratch: None,
+			results: None,
+			timeouts: QueryTimeouts::default(),
+			prerequisites: HashMap::new(),
+			progress,
+			offline_skipped: HashSet::new(),
+		})
+	}
+
+	/// Take ownership of the plugins' scratch directories, so they live as long as the plugins.
+	pub fn with_scratch(mut self, scratch: HcScratchCache) -> Self {
+		self.scratch = Some(scratch);
+		self
+	}
+
+	/// Reuse and save the results of plugin queries across runs.
+	pub fn with_results(mut self, results: HcResultCache) -> Self {
+		self.results = Some(results);
+		self
+	}
+
+	/// Get the cache of plugin query results, if there is one.
+	pub fn results(&self) -> Option<&HcResultCache> {
+		self.results.as_ref()
+	}
+
+	/// Cancel queries to plugins which run for longer than their timeout.
+	pub fn with_timeouts(mut self, timeouts: QueryTimeouts) -> Self {
+		self.timeouts = timeouts;
+		self
+	}
+
+	/// Get the timeout for queries to a plugin, given as `<publisher>/<name>`.
+	pub fn timeout(&self, plugin_name: &str) -> Option<Duration> {
+		self.timeouts.for_plugin(plugin_name)
+	}
+
+	/// Record the queries each plugin's analyses depend on, keyed by `<publisher>/<name>`.
+	pub fn with_prerequisites(mut self, prerequisites: HashMap<String, Vec<Prerequisite>>) -> Self {
+		self.prerequisites = prerequisites;
+		self
+	}
+
+	/// Get the queries a plugin's analyses depend on, given as `<publisher>/<name>`.
+	pub fn prerequisites(&self, plugin_name: &str) -> &[Prerequisite] {
+		self.prerequisites
+			.get(plugin_name)
+			.map(Vec::as_slice)
+			.unwrap_or_default()
+	}
+
+	/// Get the progress plugins have reported on their queries.
+	pub fn progress(&self) -> &QueryProgress {
+		&self.progress
+	}
+
+	/// Skip queries to plugins which need the network, keyed by `<publisher>/<name>`.
+	pub fn with_offline_skipped(mut self, plugins: HashSet<String>) -> Self {
+		self.offline_skipped = plugins;
+		self
+	}
+
+	/// Check if queries to a plugin, given as `<publisher>/<name>`, are skipped
+	/// because it needs the network.
+	pub fn is_offline_skipped(&self, plugin_name: &str) -> bool {
+		self.offline_skipped.contains(plugin_name)
+	}
+}
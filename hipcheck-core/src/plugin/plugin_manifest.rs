@@ -96,7 +96,7 @@ impl ParseKdlNode for Entrypoints {
 				.to_string();
 
 			if let Err(_e) = entrypoints.insert(arch.clone(), entrypoint) {
-				log::error!("Duplicate entrypoint detected for [{}]", arch);
+				tracing::error!("Duplicate entrypoint detected for [{}]", arch);
 				return None;
 			}
 		}
@@ -223,6 +223,127 @@ impl ParseKdlNode for PluginDependencyList {
 	}
 }
 
+/// An expensive query to another plugin which a plugin's analyses depend on.
+///
+/// When several analyses share a prerequisite, Hipcheck runs it once before any
+/// of them, so its cost isn't charged to whichever analysis asks for it first.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Prerequisite {
+	pub publisher: String,
+	pub plugin: String,
+	pub query: String,
+	/// A JSON pointer to the part of the target used as the query's key.
+	pub key: String,
+}
+
+impl Prerequisite {
+	#[cfg(test)]
+	pub fn new(publisher: &str, plugin: &str, query: &str, key: &str) -> Self {
+		Self {
+			publisher: publisher.to_owned(),
+			plugin: plugin.to_owned(),
+			query: query.to_owned(),
+			key: key.to_owned(),
+		}
+	}
+}
+
+impl ParseKdlNode for Prerequisite {
+	fn kdl_key() -> &'static str {
+		"query"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+
+		// The query is the first positional entry, of the format `<publisher>/<name>/<query>`
+		let mut parts = node.entries().first()?.value().as_string()?.splitn(3, '/');
+		let publisher = parts.next()?.to_owned();
+		let plugin = parts.next()?.to_owned();
+		let query = parts.next()?.to_owned();
+		// The key defaults to the whole target
+		let key = match node.get("key") {
+			Some(key) => key.as_string()?.to_owned(),
+			None => String::new(),
+		};
+
+		Some(Self {
+			publisher,
+			plugin,
+			query,
+			key,
+		})
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct PrerequisiteList(pub Vec<Prerequisite>);
+
+impl PrerequisiteList {
+	pub fn new() -> Self {
+		Self(Vec::new())
+	}
+
+	pub fn push(&mut self, prerequisite: Prerequisite) {
+		self.0.push(prerequisite);
+	}
+}
+
+impl ToKdlNode for PrerequisiteList {
+	fn to_kdl_node(&self) -> KdlNode {
+		let mut prerequisite_parent = KdlNode::new("prerequisites");
+		let mut prerequisite_children = KdlDocument::new();
+		let prerequisite_children_nodes = prerequisite_children.nodes_mut();
+		for prerequisite in self.0.iter() {
+			let mut entry = KdlNode::new("query");
+			entry.insert(
+				0,
+				format!(
+					"{}/{}/{}",
+					prerequisite.publisher, prerequisite.plugin, prerequisite.query
+				),
+			);
+			if prerequisite.key.is_empty().not() {
+				entry.insert("key", prerequisite.key.as_str());
+			}
+			prerequisite_children_nodes.push(entry);
+		}
+		prerequisite_parent.set_children(prerequisite_children);
+		prerequisite_parent
+	}
+}
+
+impl ParseKdlNode for PrerequisiteList {
+	fn kdl_key() -> &'static str {
+		"prerequisites"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+
+		let mut prerequisites = Self::new();
+
+		for node in node.children()?.nodes() {
+			match Prerequisite::parse_node(node) {
+				Some(prerequisite) => prerequisites.push(prerequisite),
+				None => tracing::warn!(
+					"Ignoring malformed prerequisite '{}'",
+					node.to_string().trim()
+				),
+			}
+		}
+
+		Some(prerequisites)
+	}
+}
+
+/// The node a plugin manifest has if the plugin uses the network.
+const REQUIRES_NETWORK: &str = "requires-network";
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PluginManifest {
 	pub publisher: PluginPublisher,
@@ -231,6 +352,9 @@ pub struct PluginManifest {
 	pub license: License,
 	pub entrypoints: Entrypoints,
 	pub dependencies: PluginDependencyList,
+	pub prerequisites: PrerequisiteList,
+	/// Whether the plugin's queries use the network, so it can't be used offline.
+	pub requires_network: bool,
 }
 
 impl PluginManifest {
@@ -306,6 +430,12 @@ impl PluginManifest {
 			self.entrypoints.to_kdl_node(),
 			self.dependencies.to_kdl_node(),
 		]);
+		if self.prerequisites.0.is_empty().not() {
+			document.nodes_mut().push(self.prerequisites.to_kdl_node());
+		}
+		if self.requires_network {
+			document.nodes_mut().push(KdlNode::new(REQUIRES_NETWORK));
+		}
 		document
 	}
 
@@ -335,6 +465,12 @@ impl FromStr for PluginManifest {
 			extract_data(nodes).ok_or_else(|| hc_error!("Could not parse 'entrypoint'"))?;
 		// Not a required field
 		let dependencies: PluginDependencyList = extract_data(nodes).unwrap_or_default();
+		// Not a required field
+		let prerequisites: PrerequisiteList = extract_data(nodes).unwrap_or_default();
+		// Not a required field, and has no value
+		let requires_network = nodes
+			.iter()
+			.any(|node| node.name().value() == REQUIRES_NETWORK);
 
 		Ok(Self {
 			publisher,
@@ -343,6 +479,8 @@ impl FromStr for PluginManifest {
 			license,
 			entrypoints,
 			dependencies,
+			prerequisites,
+			requires_network,
 		})
 	}
 }
@@ -525,6 +663,20 @@ mod test {
 		assert_eq!(PluginDependencyList::parse_node(&node).unwrap(), expected);
 	}
 
+	#[test]
+	fn test_parsing_prerequisite_list() {
+		let prerequisites = r#"prerequisites {
+  query "mitre/git/commit_diffs" key="/local"
+  query "mitre/git/contributors"
+  query "malformed"
+}"#;
+		let node = KdlNode::from_str(prerequisites).unwrap();
+		let mut expected = PrerequisiteList::new();
+		expected.push(Prerequisite::new("mitre", "git", "commit_diffs", "/local"));
+		expected.push(Prerequisite::new("mitre", "git", "contributors", ""));
+		assert_eq!(PrerequisiteList::parse_node(&node).unwrap(), expected);
+	}
+
 	#[test]
 	fn test_parsing_entire_plugin_manifest_file() {
 		let file_contents = r#"publisher "mitre"
@@ -540,6 +692,10 @@ entrypoint {
 
 dependencies {
   plugin "mitre/git" version="0.1.0" manifest="https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-git.kdl"
+}
+
+prerequisites {
+  query "mitre/git/commit_diffs" key="/local"
 }"#;
 		let plugin_manifest = PluginManifest::from_str(file_contents).unwrap();
 
@@ -584,6 +740,9 @@ dependencies {
 			)),
 		));
 
+		let mut prerequisites = PrerequisiteList::new();
+		prerequisites.push(Prerequisite::new("mitre", "git", "commit_diffs", "/local"));
+
 		let expected_manifest = PluginManifest {
 			publisher: PluginPublisher::new("mitre".to_owned()),
 			name: PluginName::new("affiliation".to_owned()),
@@ -591,10 +750,30 @@ dependencies {
 			license: License::new("Apache-2.0".to_owned()),
 			entrypoints,
 			dependencies,
+			prerequisites,
+			requires_network: false,
 		};
 		assert_eq!(plugin_manifest, expected_manifest);
 	}
 
+	#[test]
+	fn test_parsing_requires_network() {
+		let file_contents = r#"publisher "mitre"
+name "github"
+version "0.1.0"
+license "Apache-2.0"
+entrypoint {
+  on arch="x86_64-unknown-linux-gnu" "github"
+}
+requires-network"#;
+		let plugin_manifest = PluginManifest::from_str(file_contents).unwrap();
+		assert!(plugin_manifest.requires_network);
+
+		let without = file_contents.replace("requires-network", "");
+		let plugin_manifest = PluginManifest::from_str(&without).unwrap();
+		assert!(!plugin_manifest.requires_network);
+	}
+
 	#[test]
 	fn test_to_kdl() {
 		let mut entrypoints = Entrypoints::new();
@@ -633,6 +812,10 @@ dependencies {
 			Some(ManifestLocation::Local("./plugins/git/plugin.kdl".into())),
 		));
 
+		let mut prerequisites = PrerequisiteList::new();
+		prerequisites.push(Prerequisite::new("mitre", "git", "commit_diffs", "/local"));
+		prerequisites.push(Prerequisite::new("mitre", "git", "contributors", ""));
+
 		let plugin_manifest = PluginManifest {
 			publisher: PluginPublisher::new("mitre".to_owned()),
 			name: PluginName::new("activity".to_owned()),
@@ -640,6 +823,8 @@ dependencies {
 			license: License::new("Apache-2.0".to_owned()),
 			entrypoints,
 			dependencies,
+			prerequisites,
+			requires_network: true,
 		};
 
 		let plugin_manifest_string = plugin_manifest.to_kdl_formatted_string();
@@ -0,0 +1,437 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Keeping plugins running between checks, so later checks can reuse them
+//! instead of paying for plugin startup again.
+//!
+//! Each pooled plugin is started under a supervisor, a separate `hc` process
+//! which outlives the check that started it. Once the plugin is configured,
+//! the check writes a record of its port to the pool directory, where later
+//! checks starting the same plugin with the same config find it. Checks using
+//! a pooled plugin keep its record's modification time current, and the
+//! supervisor shuts the plugin down once no check has for the idle timeout.
+//!
+//! Plugins only accept their config once, so pooled plugins are keyed by
+//! everything they were started with, and a reused plugin isn't sent its
+//! config again.
+
+use crate::{
+	error::{Context as _, Result},
+	hc_error,
+	plugin::{Plugin, ResourceLimits},
+	util::fs::create_dir_all,
+};
+use pathbuf::pathbuf;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+	ffi::OsString,
+	fs::{self, File},
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
+	sync::{
+		mpsc::{self, RecvTimeoutError, Sender},
+		OnceLock,
+	},
+	thread,
+	time::{Duration, Instant, SystemTime},
+};
+
+/// The `hc` executable which runs the supervisors of pooled plugins.
+static SUPERVISOR: OnceLock<PathBuf> = OnceLock::new();
+
+/// How long a supervisor waits for its plugin's record to be written before
+/// deciding the plugin failed to start and shutting it down.
+const STARTUP_GRACE: Duration = Duration::from_secs(60);
+
+/// Set the `hc` executable which supervises pooled plugins.
+///
+/// Plugins are only pooled once this is set, since a program embedding
+/// Hipcheck can't supervise them itself.
+pub fn set_pool_supervisor(exe: PathBuf) {
+	let _ = SUPERVISOR.set(exe);
+}
+
+/// Where pooled plugins are recorded, and how long they're kept idle.
+#[derive(Clone, Debug)]
+pub struct PluginPoolDir {
+	/// The directory holding the records of pooled plugins.
+	dir: PathBuf,
+	/// How long a plugin is kept running without being used.
+	idle_timeout: Duration,
+	/// The `hc` executable which supervises pooled plugins.
+	supervisor: PathBuf,
+}
+
+impl PluginPoolDir {
+	/// Get the plugin pool in a Hipcheck cache directory, if pooled plugins
+	/// can be supervised.
+	pub fn new(home: &Path, idle_timeout: Duration) -> Option<PluginPoolDir> {
+		let Some(supervisor) = SUPERVISOR.get() else {
+			tracing::debug!("no plugin pool supervisor is set; plugins won't be kept running");
+			return None;
+		};
+
+		Some(PluginPoolDir {
+			dir: pool_root(home),
+			idle_timeout,
+			supervisor: supervisor.clone(),
+		})
+	}
+
+	/// Get the entry for a plugin started with a config.
+	///
+	/// `max_message_size` is the largest message core offers the plugin, which
+	/// like its config is only sent once.
+	pub fn entry(&self, plugin: &Plugin, config: &Value, max_message_size: usize) -> PoolEntry {
+		let secrets = plugin
+			.secrets
+			.iter()
+			.map(|(var, secret)| (var.as_str(), secret.expose()))
+			.collect::<Vec<_>>();
+		let inputs = json!({
+			"name": plugin.name,
+			"working_dir": plugin.working_dir,
+			"entrypoint": plugin.entrypoint,
+			"config": config,
+			"secrets": secrets,
+			"limits": plugin.limits,
+			"max_message_size": max_message_size,
+		});
+		let hash = blake3::hash(inputs.to_string().as_bytes());
+		let key = format!("{}-{}", plugin.name.replace('/', "-"), &hash.to_hex()[..16]);
+
+		PoolEntry {
+			record: pathbuf![&self.dir, &format!("{key}.json")],
+			key,
+			pool: self.clone(),
+		}
+	}
+}
+
+/// A plugin's place in the pool, whether or not it's running.
+#[derive(Clone, Debug)]
+pub struct PoolEntry {
+	/// Identifies the plugin, along with everything it was started with.
+	key: String,
+	/// The record of the running plugin.
+	record: PathBuf,
+	pool: PluginPoolDir,
+}
+
+/// The record of a configured, pooled plugin.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PoolRecord {
+	/// The port the plugin is listening on.
+	pub port: u16,
+	/// The largest message size negotiated with the plugin, in bytes.
+	pub max_message_size: usize,
+}
+
+impl PoolEntry {
+	/// Get the record of the plugin, if it's running.
+	pub fn running(&self) -> Option<PoolRecord> {
+		let record = read_record(&self.record)?;
+		// Keep the plugin from being shut down while it's connected to.
+		touch(&self.record);
+		Some(record)
+	}
+
+	/// Forget a plugin which couldn't be connected to, so it's started again.
+	pub fn forget(&self) {
+		let _ = fs::remove_file(&self.record);
+	}
+
+	/// Make the command starting the plugin under a supervisor, instead of
+	/// the plugin's own command, along with the directory the plugin may use
+	/// for temporary files. The supervisor starts the plugin with its limits.
+	pub fn supervised_command(
+		&self,
+		bin: &Path,
+		args: &[&str],
+		port: u16,
+		limits: &ResourceLimits,
+	) -> Result<(Command, PathBuf)> {
+		let instance_dir = pathbuf![&self.pool.dir, &format!("{}-{}", self.key, port)];
+		let scratch_dir = pathbuf![&instance_dir, "scratch"];
+		create_dir_all(&scratch_dir)?;
+
+		// The plugin outlives this run, so it can't write to its output.
+		let log_path = pathbuf![&instance_dir, "plugin.log"];
+		let log = File::create(&log_path)
+			.with_context(|| format!("failed to create '{}'", log_path.display()))?;
+
+		let mut cmd = Command::new(&self.pool.supervisor);
+		cmd.arg("plugin-pool")
+			.arg("--record")
+			.arg(&self.record)
+			.arg("--dir")
+			.arg(&instance_dir)
+			.arg("--port")
+			.arg(port.to_string())
+			.arg("--idle-timeout")
+			.arg(self.pool.idle_timeout.as_secs().to_string())
+			.args(limits.to_args())
+			.arg("--")
+			.arg(bin)
+			.args(args)
+			.stdin(Stdio::null())
+			.stdout(log.try_clone()?)
+			.stderr(log);
+
+		// Keep the supervisor running if this run is interrupted.
+		#[cfg(unix)]
+		{
+			use std::os::unix::process::CommandExt as _;
+			cmd.process_group(0);
+		}
+		#[cfg(windows)]
+		{
+			use std::os::windows::process::CommandExt as _;
+			const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+			cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
+		}
+
+		Ok((cmd, scratch_dir))
+	}
+
+	/// Record a plugin once it's configured, so later runs can reuse it, and
+	/// keep it running for as long as the returned lease is held.
+	pub fn lease(&self, record: PoolRecord) -> Result<PoolLease> {
+		create_dir_all(&self.pool.dir)?;
+		let contents = serde_json::to_string(&record)?;
+		let tmp = self.record.with_extension(format!("{}.tmp", record.port));
+		fs::write(&tmp, contents)
+			.with_context(|| format!("failed to write '{}'", tmp.display()))?;
+		fs::rename(&tmp, &self.record)
+			.with_context(|| format!("failed to write '{}'", self.record.display()))?;
+
+		Ok(self.reuse())
+	}
+
+	/// Keep a running plugin running for as long as the returned lease is held.
+	pub fn reuse(&self) -> PoolLease {
+		let (stop, stopped) = mpsc::channel::<()>();
+		let record = self.record.clone();
+		let interval = self.pool.idle_timeout / 3;
+
+		thread::spawn(move || loop {
+			touch(&record);
+			match stopped.recv_timeout(interval) {
+				Err(RecvTimeoutError::Timeout) => continue,
+				_ => break,
+			}
+		});
+
+		PoolLease {
+			record: self.record.clone(),
+			_stop: stop,
+		}
+	}
+}
+
+/// A pooled plugin, as used by this run.
+#[derive(Debug)]
+pub enum Pooled {
+	/// Started by this run, and recorded once it's configured.
+	Started(PoolEntry),
+	/// Configured and recorded, by this run or an earlier one.
+	Leased {
+		record: PoolRecord,
+		lease: PoolLease,
+	},
+}
+
+/// Keeps a pooled plugin running while it's used.
+///
+/// Once dropped, the plugin's idle timeout starts.
+#[derive(Debug)]
+pub struct PoolLease {
+	record: PathBuf,
+	/// Stops the thread keeping the record current when dropped.
+	_stop: Sender<()>,
+}
+
+impl Drop for PoolLease {
+	fn drop(&mut self) {
+		touch(&self.record);
+	}
+}
+
+/// What a supervisor runs, and how it knows when to stop.
+#[derive(Debug)]
+pub struct Supervised {
+	/// The record of the plugin, once it's configured.
+	pub record: PathBuf,
+	/// The directory holding the plugin's log and temporary files, deleted
+	/// once it's shut down.
+	pub dir: PathBuf,
+	/// The port the plugin listens on.
+	pub port: u16,
+	/// How long the plugin is kept running without being used.
+	pub idle_timeout: Duration,
+	/// The plugin's command.
+	pub command: Vec<OsString>,
+	/// The limits the plugin is started with.
+	pub limits: ResourceLimits,
+}
+
+/// Run a pooled plugin until it's idle for too long, it stops on its own, or
+/// another plugin takes its place in the pool.
+pub fn supervise(supervised: Supervised) -> Result<()> {
+	let (bin, args) = supervised
+		.command
+		.split_first()
+		.ok_or_else(|| hc_error!("no plugin command to supervise"))?;
+
+	// The plugin inherits the environment and output the supervisor was
+	// started with.
+	let mut command = Command::new(bin);
+	command.args(args);
+	supervised.limits.apply(&mut command)?;
+	let mut plugin = command
+		.spawn()
+		.with_context(|| format!("failed to start plugin '{}'", bin.to_string_lossy()))?;
+
+	let started = Instant::now();
+	let poll = (supervised.idle_timeout / 4).clamp(Duration::from_secs(1), Duration::from_secs(10));
+
+	loop {
+		thread::sleep(poll);
+
+		if plugin.try_wait()?.is_some() {
+			tracing::info!("pooled plugin stopped");
+			remove_record_if_ours(&supervised);
+			break;
+		}
+
+		let Some(record) = read_record(&supervised.record) else {
+			if started.elapsed() > STARTUP_GRACE {
+				tracing::info!("pooled plugin was never recorded; shutting it down");
+				break;
+			}
+			continue;
+		};
+
+		if record.port != supervised.port {
+			tracing::info!("pooled plugin was replaced; shutting it down");
+			break;
+		}
+
+		if idle_for(&supervised.record) > supervised.idle_timeout {
+			tracing::info!("pooled plugin is idle; shutting it down");
+			remove_record_if_ours(&supervised);
+			break;
+		}
+	}
+
+	let _ = plugin.kill();
+	let _ = plugin.wait();
+	let _ = fs::remove_dir_all(&supervised.dir);
+
+	Ok(())
+}
+
+/// Remove the record of a supervised plugin, unless another has replaced it.
+fn remove_record_if_ours(supervised: &Supervised) {
+	if read_record(&supervised.record).is_some_and(|record| record.port == supervised.port) {
+		let _ = fs::remove_file(&supervised.record);
+	}
+}
+
+fn read_record(path: &Path) -> Option<PoolRecord> {
+	let contents = fs::read_to_string(path).ok()?;
+	serde_json::from_str(&contents).ok()
+}
+
+/// Mark a pooled plugin as just used.
+fn touch(record: &Path) {
+	let touched = File::options()
+		.append(true)
+		.open(record)
+		.and_then(|file| file.set_modified(SystemTime::now()));
+
+	if let Err(e) = touched {
+		tracing::debug!("failed to touch '{}': {}", record.display(), e);
+	}
+}
+
+/// How long it's been since a pooled plugin was used.
+fn idle_for(record: &Path) -> Duration {
+	fs::metadata(record)
+		.and_then(|metadata| metadata.modified())
+		.ok()
+		.and_then(|modified| modified.elapsed().ok())
+		.unwrap_or_default()
+}
+
+/// The directory holding the records of pooled plugins.
+fn pool_root(path: &Path) -> PathBuf {
+	pathbuf![path, "plugin-pool"]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pool(home: &Path) -> PluginPoolDir {
+		PluginPoolDir {
+			dir: pool_root(home),
+			idle_timeout: Duration::from_secs(60),
+			supervisor: PathBuf::from("hc"),
+		}
+	}
+
+	fn plugin() -> Plugin {
+		Plugin {
+			name: "mitre/git".to_owned(),
+			working_dir: PathBuf::from("/plugins/mitre/git/0.1.0"),
+			entrypoint: "git-plugin".to_owned(),
+			scratch_dir: None,
+			data_dir: None,
+			secrets: vec![],
+			limits: ResourceLimits::default(),
+		}
+	}
+
+	#[test]
+	fn entries_depend_on_config() {
+		let home = tempfile::tempdir().unwrap();
+		let pool = pool(home.path());
+
+		let entry = pool.entry(&plugin(), &json!({}), 1024);
+		assert!(entry.key.starts_with("mitre-git-"));
+		assert_eq!(entry.key, pool.entry(&plugin(), &json!({}), 1024).key);
+		assert_ne!(
+			entry.key,
+			pool.entry(&plugin(), &json!({"limit": 10}), 1024).key
+		);
+		assert_ne!(entry.key, pool.entry(&plugin(), &json!({}), 2048).key);
+
+		let limited = Plugin {
+			limits: ResourceLimits {
+				open_files: Some(64),
+				..Default::default()
+			},
+			..plugin()
+		};
+		assert_ne!(entry.key, pool.entry(&limited, &json!({}), 1024).key);
+	}
+
+	#[test]
+	fn leased_plugins_are_running() {
+		let home = tempfile::tempdir().unwrap();
+		let entry = pool(home.path()).entry(&plugin(), &json!({}), 1024);
+		assert_eq!(entry.running(), None);
+
+		let record = PoolRecord {
+			port: 40123,
+			max_message_size: 1024,
+		};
+		let lease = entry.lease(record).unwrap();
+		assert_eq!(entry.running(), Some(record));
+		drop(lease);
+
+		entry.forget();
+		assert_eq!(entry.running(), None);
+	}
+}
@@ -0,0 +1,104 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Progress plugins report on the queries they're answering.
+
+use crate::shell::spinner_phase::SpinnerPhase;
+use std::{collections::HashMap, sync::Mutex};
+
+/// How far along the queries plugins have reported progress on are.
+///
+/// Only queries whose plugins report progress are tracked, so the overall
+/// progress covers those queries and not every query in the run.
+#[derive(Debug, Default)]
+pub struct QueryProgress {
+	state: Mutex<ProgressState>,
+}
+
+#[derive(Debug, Default)]
+struct ProgressState {
+	/// The progress of each query as `(completed, total)`, by plugin and query ID.
+	queries: HashMap<(String, i32), (u64, u64)>,
+	/// The phase to show the overall progress in, if any.
+	phase: Option<SpinnerPhase>,
+}
+
+impl ProgressState {
+	/// The average of the queries' percentages done, if any query reported progress.
+	fn percent(&self) -> Option<u64> {
+		if self.queries.is_empty() {
+			return None;
+		}
+		let sum: f64 = self
+			.queries
+			.values()
+			.map(|(completed, total)| *completed as f64 / *total as f64)
+			.sum();
+		Some((100.0 * sum / self.queries.len() as f64).floor() as u64)
+	}
+
+	fn show(&self) {
+		if let (Some(phase), Some(percent)) = (&self.phase, self.percent()) {
+			phase.update_percent(percent);
+		}
+	}
+}
+
+impl QueryProgress {
+	/// Show the overall progress in a phase from now on.
+	pub fn show_in(&self, phase: SpinnerPhase) {
+		let mut state = self.state.lock().unwrap();
+		state.phase = Some(phase);
+		state.show();
+	}
+
+	/// Record the progress a plugin reported on one of its queries.
+	pub fn update(&self, plugin: &str, id: i32, completed: u64, total: u64) {
+		if total == 0 {
+			return;
+		}
+		let mut state = self.state.lock().unwrap();
+		state
+			.queries
+			.insert((plugin.to_owned(), id), (completed.min(total), total));
+		state.show();
+	}
+
+	/// Record that a query is done, if its plugin was reporting progress on it.
+	pub fn finish(&self, plugin: &str, id: i32) {
+		let mut state = self.state.lock().unwrap();
+		if let Some((completed, total)) = state.queries.get_mut(&(plugin.to_owned(), id)) {
+			*completed = *total;
+			state.show();
+		}
+	}
+
+	#[cfg(test)]
+	fn percent(&self) -> Option<u64> {
+		self.state.lock().unwrap().percent()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_query_progress() {
+		let progress = QueryProgress::default();
+		assert_eq!(progress.percent(), None);
+
+		// Queries which didn't report progress aren't tracked
+		progress.finish("mitre/git", 1);
+		assert_eq!(progress.percent(), None);
+
+		progress.update("mitre/git", 1, 50, 200);
+		assert_eq!(progress.percent(), Some(25));
+
+		// Each query counts the same, however much work it has
+		progress.update("mitre/churn", 1, 5, 10);
+		assert_eq!(progress.percent(), Some(37));
+
+		progress.finish("mitre/git", 1);
+		assert_eq!(progress.percent(), Some(75));
+	}
+}
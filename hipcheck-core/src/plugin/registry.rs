@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Plugin registry indexes, which list the plugins `hc plugin install` can install.
+//!
+//! An index is a KDL file with one node per published version of a plugin:
+//!
+//! ```kdl
+//! plugin "mitre/activity" version="0.5.0" manifest="https://github.com/mitre/hipcheck/releases/download/activity-v0.5.0/activity.kdl" {
+//!   description "Checks when the last commit to a repository was made"
+//! }
+//! ```
+//!
+//! The `manifest` is the URL of the plugin's download manifest, just as it
+//! would be given in a policy file.
+
+use crate::{
+	error::{Context as _, Result},
+	exec::PluginMirrors,
+	hc_error,
+	plugin::{PluginId, PluginVersion},
+	policy::policy_file::PolicyPluginName,
+	util::http::download::Download,
+};
+use kdl::{KdlDocument, KdlNode};
+use std::str::FromStr;
+use tabled::Tabled;
+use url::Url;
+
+/// One published version of a plugin in a registry index.
+#[derive(Debug, Clone, PartialEq, Eq, Tabled)]
+pub struct RegistryEntry {
+	/// The plugin's name, as `<publisher>/<name>`.
+	pub plugin: String,
+	pub version: String,
+	pub description: String,
+	/// The URL of the plugin's download manifest.
+	#[tabled(skip)]
+	pub manifest: Url,
+}
+
+impl RegistryEntry {
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != "plugin" {
+			return None;
+		}
+
+		let plugin = node.entries().first()?.value().as_string()?.to_string();
+		let version = node.get("version")?.as_string()?.to_string();
+		let manifest = Url::parse(node.get("manifest")?.as_string()?).ok()?;
+		let description = match node.children() {
+			Some(children) => match children.get("description") {
+				Some(child) => child.entries().first()?.value().as_string()?.to_string(),
+				None => String::new(),
+			},
+			None => String::new(),
+		};
+
+		Some(RegistryEntry {
+			plugin,
+			version,
+			description,
+			manifest,
+		})
+	}
+
+	/// Get the ID the plugin will have in the plugin cache.
+	pub fn plugin_id(&self) -> Result<PluginId> {
+		let name = PolicyPluginName::new(&self.plugin)?;
+		Ok(PluginId::new(
+			name.publisher,
+			name.name,
+			PluginVersion(self.version.clone()),
+		))
+	}
+}
+
+/// The plugins listed by a plugin registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryIndex {
+	pub entries: Vec<RegistryEntry>,
+}
+
+impl RegistryIndex {
+	/// Fetch the registry index at `url`, which may be a `file://` URL.
+	///
+	/// Other URLs are rewritten according to `mirrors` before being fetched.
+	pub fn fetch(url: &Url, mirrors: &PluginMirrors) -> Result<RegistryIndex> {
+		if url.scheme() == "file" {
+			let path = url
+				.to_file_path()
+				.map_err(|_| hc_error!("invalid registry index path {}", url))?;
+			let contents = std::fs::read_to_string(&path).map_err(|e| {
+				hc_error!("Error [{}] reading registry index {}", e, path.display())
+			})?;
+			return RegistryIndex::from_str(&contents);
+		}
+
+		let url = mirrors.rewrite(url)?;
+		let contents = Download::new(&url)
+			.to_bytes()
+			.context("failed to retrieve registry index")?;
+		RegistryIndex::from_str(&String::from_utf8_lossy(&contents))
+	}
+
+	/// Find the plugins whose name or description contains `query`, ignoring case.
+	pub fn search(&self, query: &str) -> Vec<&RegistryEntry> {
+		let query = query.to_lowercase();
+		self.entries
+			.iter()
+			.filter(|e| {
+				e.plugin.to_lowercase().contains(&query)
+					|| e.description.to_lowercase().contains(&query)
+			})
+			.collect()
+	}
+
+	/// Find a version of a plugin, or its newest version if `version` is `None`.
+	pub fn find(&self, plugin: &str, version: Option<&str>) -> Result<&RegistryEntry> {
+		let mut versions = self
+			.entries
+			.iter()
+			.filter(|e| e.plugin == plugin)
+			.peekable();
+		if versions.peek().is_none() {
+			return Err(hc_error!("plugin {} is not in the registry", plugin));
+		}
+
+		match version {
+			Some(version) => versions.find(|e| e.version == version).ok_or_else(|| {
+				hc_error!(
+					"version {} of plugin {} is not in the registry",
+					version,
+					plugin
+				)
+			}),
+			None => versions
+				.filter_map(|e| Some((semver::Version::parse(&e.version).ok()?, e)))
+				.max_by(|(a, _), (b, _)| a.cmp(b))
+				.map(|(_, e)| e)
+				.ok_or_else(|| {
+					hc_error!("plugin {} has no valid versions in the registry", plugin)
+				}),
+		}
+	}
+}
+
+impl FromStr for RegistryIndex {
+	type Err = crate::Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		let document = KdlDocument::from_str(s)
+			.map_err(|e| hc_error!("Error parsing registry index: {}", e))?;
+		let mut entries = vec![];
+		for node in document.nodes() {
+			if let Some(entry) = RegistryEntry::parse_node(node) {
+				entries.push(entry);
+			} else {
+				return Err(hc_error!("Error parsing registry index entry: {}", node));
+			}
+		}
+		Ok(Self { entries })
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const INDEX: &str = r#"
+plugin "mitre/activity" version="0.4.0" manifest="https://example.com/activity-0.4.0.kdl"
+plugin "mitre/activity" version="0.5.0" manifest="https://example.com/activity-0.5.0.kdl" {
+  description "Checks when the last commit was made"
+}
+plugin "mitre/binary" version="0.1.0" manifest="https://example.com/binary.kdl" {
+  description "Finds binary files"
+}
+"#;
+
+	#[test]
+	fn test_parsing_registry_index() {
+		let index = RegistryIndex::from_str(INDEX).unwrap();
+		assert_eq!(index.entries.len(), 3);
+		assert_eq!(
+			index.entries[1],
+			RegistryEntry {
+				plugin: "mitre/activity".to_owned(),
+				version: "0.5.0".to_owned(),
+				description: "Checks when the last commit was made".to_owned(),
+				manifest: Url::parse("https://example.com/activity-0.5.0.kdl").unwrap(),
+			}
+		);
+		assert_eq!(index.entries[0].description, "");
+	}
+
+	#[test]
+	fn test_invalid_registry_entry() {
+		let index = r#"plugin "mitre/activity" version="0.4.0""#;
+		assert!(RegistryIndex::from_str(index).is_err());
+	}
+
+	#[test]
+	fn test_search() {
+		let index = RegistryIndex::from_str(INDEX).unwrap();
+		let found: Vec<_> = index
+			.search("BINARY")
+			.into_iter()
+			.map(|e| e.plugin.as_str())
+			.collect();
+		assert_eq!(found, vec!["mitre/binary"]);
+		assert_eq!(index.search("commit").len(), 1);
+		assert_eq!(index.search("").len(), 3);
+	}
+
+	#[test]
+	fn test_find() {
+		let index = RegistryIndex::from_str(INDEX).unwrap();
+		assert_eq!(index.find("mitre/activity", None).unwrap().version, "0.5.0");
+		assert_eq!(
+			index.find("mitre/activity", Some("0.4.0")).unwrap().version,
+			"0.4.0"
+		);
+		assert!(index.find("mitre/activity", Some("0.3.0")).is_err());
+		assert!(index.find("mitre/typo", None).is_err());
+	}
+}
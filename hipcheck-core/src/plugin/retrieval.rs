@@ -3,21 +3,29 @@
 use crate::{
 	cache::plugin::HcPluginCache,
 	error::{Context, Error},
+	exec::{PluginMirrors, PluginTrust},
 	hc_error,
 	plugin::{
-		download_manifest::DownloadManifestEntry, get_current_arch, try_get_bin_for_entrypoint,
-		ArchiveFormat, DownloadManifest, HashAlgorithm, HashWithDigest, PluginId, PluginManifest,
+		download_manifest::DownloadManifestEntry,
+		get_current_arch,
+		signature::{download_signature, unverified, verify_plugin_archive},
+		try_get_bin_for_entrypoint, ArchiveFormat, DownloadManifest, HashWithDigest, PluginId,
+		PluginManifest,
 	},
 	policy::policy_file::{ManifestLocation, PolicyPlugin},
-	util::{fs::file_sha256, http::agent::agent},
+	util::{
+		fs::{create_dir_all, file_sha256, read_string, FileLock},
+		http::download::{progress_phase, Checksum, Download},
+		network::is_offline,
+	},
 };
+use base64::{prelude::BASE64_STANDARD, Engine};
 use flate2::read::GzDecoder;
 use fs_extra::{dir::remove, file::write_all};
 use pathbuf::pathbuf;
 use std::{
 	collections::HashSet,
-	fs::{read_dir, rename, DirEntry, File},
-	io::{Read, Write},
+	fs::{self, read_dir, rename, DirEntry, File},
 	path::{Path, PathBuf},
 	str::FromStr,
 };
@@ -25,10 +33,106 @@ use tar::Archive;
 use url::Url;
 use xz2::read::XzDecoder;
 
+/// The folder in a downloaded plugin's cache folder keeping the archive it was
+/// extracted from, so it can be verified again when it's bundled.
+pub const ARCHIVE_DIR: &str = ".archive";
+
+/// The file next to a kept archive naming its format.
+const ARCHIVE_FORMAT_FILE: &str = "format";
+
+/// The file next to a kept archive holding its signature, base64-encoded.
+const ARCHIVE_SIGNATURE_FILE: &str = "signature";
+
+/// A plugin's original archive, kept with the signature it was verified with.
+#[derive(Debug)]
+pub struct PluginArchive {
+	pub path: PathBuf,
+	pub format: ArchiveFormat,
+	/// The raw signature of the archive, if it was signed.
+	pub signature: Option<Vec<u8>>,
+}
+
+impl PluginArchive {
+	/// Find the archive kept in `dir`, if there is one.
+	pub fn find(dir: &Path) -> Result<Option<PluginArchive>, Error> {
+		let format_path = pathbuf![dir, ARCHIVE_FORMAT_FILE];
+		if !format_path.is_file() {
+			return Ok(None);
+		}
+		let format = ArchiveFormat::try_from(read_string(&format_path)?.trim())?;
+
+		let signature_path = pathbuf![dir, ARCHIVE_SIGNATURE_FILE];
+		let signature = match signature_path.is_file() {
+			true => Some(BASE64_STANDARD.decode(read_string(&signature_path)?.trim())?),
+			false => None,
+		};
+
+		let mut archives = Vec::new();
+		for entry in read_dir(dir)? {
+			let entry = entry?;
+			let name = entry.file_name();
+			if name != ARCHIVE_FORMAT_FILE && name != ARCHIVE_SIGNATURE_FILE {
+				archives.push(entry.path());
+			}
+		}
+		let [path] = archives.as_slice() else {
+			return Err(hc_error!(
+				"expected one plugin archive in '{}', found {}",
+				dir.display(),
+				archives.len()
+			));
+		};
+
+		Ok(Some(PluginArchive {
+			path: path.clone(),
+			format,
+			signature,
+		}))
+	}
+
+	/// Copy the archive and its signature into `dir`, replacing anything kept
+	/// there before.
+	pub fn keep(&self, dir: &Path) -> Result<(), Error> {
+		if dir.exists() {
+			fs::remove_dir_all(dir)?;
+		}
+		create_dir_all(dir)?;
+
+		let name = self
+			.path
+			.file_name()
+			.ok_or_else(|| hc_error!("no file name in '{}'", self.path.display()))?;
+		fs::copy(&self.path, pathbuf![dir, name])?;
+		fs::write(pathbuf![dir, ARCHIVE_FORMAT_FILE], self.format.to_string())?;
+		if let Some(signature) = &self.signature {
+			fs::write(
+				pathbuf![dir, ARCHIVE_SIGNATURE_FILE],
+				BASE64_STANDARD.encode(signature),
+			)?;
+		}
+		Ok(())
+	}
+
+	/// Check the archive was signed by a key trusted for the plugin's publisher.
+	pub fn verify(&self, plugin_id: &PluginId, trust: &PluginTrust) -> Result<(), Error> {
+		verify_plugin_archive(plugin_id, &self.path, self.signature.as_deref(), trust)
+	}
+
+	/// Extract the plugin in the archive into `dir`, leaving the archive in place.
+	pub fn extract(&self, dir: &Path) -> Result<(), Error> {
+		extract_plugin(&self.path, dir, self.format)
+	}
+}
+
 /// determine all of the plugins that need to be run and locate download them, if they do not exist
+///
+/// Download URLs are rewritten according to `mirrors` before being fetched, and
+/// downloaded plugins must be signed by a key in `trust`.
 pub fn retrieve_plugins(
 	policy_plugins: &[PolicyPlugin],
 	plugin_cache: &HcPluginCache,
+	mirrors: &PluginMirrors,
+	trust: &PluginTrust,
 ) -> Result<HashSet<PluginId>, Error> {
 	#[cfg(feature = "print-timings")]
 	let _0 = crate::benchmarking::print_scope_time!("retrieve plugins");
@@ -40,23 +144,49 @@ pub fn retrieve_plugins(
 			policy_plugin.get_plugin_id(),
 			&policy_plugin.manifest,
 			plugin_cache,
+			mirrors,
+			trust,
 			&mut required_plugins,
 		)?;
 	}
 	Ok(required_plugins)
 }
 
+/// Download a plugin and its dependencies from the network into the plugin cache.
+///
+/// Returns the IDs of the plugin and all of its dependencies.
+pub fn install_plugin(
+	plugin_id: PluginId,
+	manifest_url: Url,
+	plugin_cache: &HcPluginCache,
+	mirrors: &PluginMirrors,
+	trust: &PluginTrust,
+) -> Result<HashSet<PluginId>, Error> {
+	let mut installed = HashSet::new();
+	retrieve_plugin(
+		plugin_id,
+		&Some(ManifestLocation::Url(manifest_url)),
+		plugin_cache,
+		mirrors,
+		trust,
+		&mut installed,
+	)?;
+	Ok(installed)
+}
+
 fn retrieve_plugin(
 	plugin_id: PluginId,
 	manifest_location: &Option<ManifestLocation>,
 	plugin_cache: &HcPluginCache,
+	mirrors: &PluginMirrors,
+	trust: &PluginTrust,
 	required_plugins: &mut HashSet<PluginId>,
 ) -> Result<(), Error> {
 	if required_plugins.contains(&plugin_id) {
 		return Ok(());
 	}
 
-	log::debug!(
+	tracing::debug!(
 		"Retrieving Plugin ID {} from {:?}",
 		plugin_id,
 		manifest_location
@@ -64,17 +194,30 @@ fn retrieve_plugin(
 
 	let plugin_manifest = match manifest_location {
 		Some(ManifestLocation::Url(plugin_url)) => {
-			retrieve_plugin_from_network(plugin_id.clone(), plugin_url, plugin_cache, false)?
+			retrieve_plugin_from_network(
+				plugin_id.clone(),
+				plugin_url,
+				plugin_cache,
+				mirrors,
+				trust,
+				false,
+			)?
 		}
 		Some(ManifestLocation::Local(plugin_manifest_path)) => {
 			retrieve_local_plugin(plugin_id.clone(), plugin_manifest_path, plugin_cache)?
 		}
 		None => {
-			// in the future, this could attempt to reach a known package registry
-			return Err(hc_error!(
-				"No manifest specified for {}",
-				plugin_id.to_policy_file_plugin_identifier()
-			));
+			// Plugins installed with `hc plugin install` don't need a manifest
+			let installed_manifest = plugin_cache.plugin_kdl(&plugin_id);
+			if !installed_manifest.is_file() {
+				return Err(hc_error!(
+					"No manifest specified for {}, and it hasn't been installed with `hc plugin install`",
+					plugin_id.to_policy_file_plugin_identifier()
+				));
+			}
+			tracing::debug!("Using installed plugin {}", &plugin_id);
+			verify_cached_plugin(&plugin_id, plugin_cache, trust)?;
+			PluginManifest::from_file(installed_manifest)?
 		}
 	};
 	required_plugins.insert(plugin_id);
@@ -83,6 +226,8 @@ fn retrieve_plugin(
 			dependency.as_ref().clone(),
 			&dependency.manifest,
 			plugin_cache,
+			mirrors,
+			trust,
 			required_plugins,
 		)?;
 	}
@@ -93,21 +238,34 @@ fn retrieve_plugin_from_network(
 	plugin_id: PluginId,
 	plugin_url: &Url,
 	plugin_cache: &HcPluginCache,
+	mirrors: &PluginMirrors,
+	trust: &PluginTrust,
 	force: bool,
 ) -> Result<PluginManifest, Error> {
+	// Hold the lock while checking the cache, so a plugin another Hipcheck run
+	// is downloading is reused once it's done instead of downloaded twice.
+	let _lock = FileLock::acquire(plugin_cache.plugin_download_dir(&plugin_id))?;
+
 	// Use existing cache entry if not force
 	let target_manifest = plugin_cache.plugin_kdl(&plugin_id);
 	if target_manifest.is_file() && !force {
-		log::debug!("Using existing entry in cache for {}", &plugin_id);
+		tracing::debug!("Using existing entry in cache for {}", &plugin_id);
+		verify_cached_plugin(&plugin_id, plugin_cache, trust)?;
 		return PluginManifest::from_file(target_manifest);
 	}
+	if is_offline() {
+		return Err(hc_error!(
+			"plugin {} isn't in the plugin cache, and can't be downloaded with --offline",
+			plugin_id.to_policy_file_plugin_identifier()
+		));
+	}
 
 	let current_arch = get_current_arch();
 	let version = plugin_id.version();
-	let download_manifest = retrieve_download_manifest(plugin_url)?;
+	let download_manifest = retrieve_download_manifest(&mirrors.rewrite(plugin_url)?)?;
 	for entry in &download_manifest.entries {
 		if entry.arch == current_arch && version == &entry.version {
-			return download_and_unpack_plugin(entry, plugin_id, plugin_cache);
+			return download_and_unpack_plugin(entry, plugin_id, plugin_cache, mirrors, trust);
 		}
 	}
 	Err(hc_error!(
@@ -117,6 +275,26 @@ fn retrieve_plugin_from_network(
 	))
 }
 
+/// Check that a downloaded plugin in the cache was extracted from an archive
+/// signed by a key trusted for its publisher.
+///
+/// This runs every time the plugin is loaded, so a kept archive which was
+/// changed, or a key which is no longer trusted, stops the plugin running.
+fn verify_cached_plugin(
+	plugin_id: &PluginId,
+	plugin_cache: &HcPluginCache,
+	trust: &PluginTrust,
+) -> Result<(), Error> {
+	let archive_dir = pathbuf![&plugin_cache.plugin_download_dir(plugin_id), ARCHIVE_DIR];
+	match PluginArchive::find(&archive_dir)? {
+		Some(archive) => archive.verify(plugin_id, trust),
+		None => unverified(
+			&plugin_id.to_policy_file_plugin_identifier(),
+			"was cached without its archive, so its signature can't be checked",
+		),
+	}
+}
+
 /// retrieves a plugin from the local filesystem by copying its `plugin.kdl` and `entrypoint` binary to the plugin_cache
 fn retrieve_local_plugin(
 	plugin_id: PluginId,
@@ -124,6 +302,7 @@ fn retrieve_local_plugin(
 	plugin_cache: &HcPluginCache,
 ) -> Result<PluginManifest, Error> {
 	let download_dir = plugin_cache.plugin_download_dir(&plugin_id);
+	let _lock = FileLock::acquire(&download_dir)?;
 	std::fs::create_dir_all(&download_dir).map_err(|e| {
 		hc_error!(
 			"Error [{}] creating download directory {}",
@@ -187,24 +366,27 @@ fn retrieve_local_plugin(
 /// This function does the following:
 /// 1. Download specified plugin for the current arch
 /// 1. Verify its size and hash
+/// 1. Verify its signature against the trusted keys
 /// 1. Extract plugin into plugin-specific folder
+/// 1. Keep the archive and its signature, so they can be bundled
 /// 1. Finds `plugin.kdl` inside plugin-specific folder and parses it
 fn download_and_unpack_plugin(
 	download_manifest_entry: &DownloadManifestEntry,
 	plugin_id: PluginId,
 	plugin_cache: &HcPluginCache,
+	mirrors: &PluginMirrors,
+	trust: &PluginTrust,
 ) -> Result<PluginManifest, Error> {
 	let download_dir = plugin_cache.plugin_download_dir(&plugin_id);
 
 	let output_path = download_plugin(
-		&download_manifest_entry.url,
+		&mirrors.rewrite(&download_manifest_entry.url)?,
 		download_dir.as_path(),
 		download_manifest_entry.size.bytes,
 		&download_manifest_entry.hash,
 	)
 	.map_err(|e| {
-		// delete any leftover remnants
-		let _ = remove(download_dir.as_path());
+		// leave any partial download in place, so the next attempt can resume it
 		hc_error!(
 			"Error [{}] downloading '{}'",
 			e,
@@ -212,22 +394,36 @@ fn download_and_unpack_plugin(
 		)
 	})?;
 
-	extract_plugin(
-		output_path.as_path(),
-		download_dir.as_path(),
-		download_manifest_entry.compress.format,
-		true,
-	)
-	.map_err(|e| {
-		// delete any leftover remnants
-		let _ = remove(download_dir.as_path());
-		hc_error!(
-			"Error [{}] extracting plugin '{}'",
-			e,
-			plugin_id.to_policy_file_plugin_identifier(),
-		)
+	let signature = match &download_manifest_entry.signature {
+		Some(signature) => Some(download_signature(&plugin_id, signature, mirrors)?),
+		None => None,
+	};
+	let archive = PluginArchive {
+		path: output_path,
+		format: download_manifest_entry.compress.format,
+		signature,
+	};
+
+	archive.verify(&plugin_id, trust).map_err(|e| {
+		// don't leave an untrusted plugin around to be extracted later
+		let _ = std::fs::remove_file(&archive.path);
+		e
 	})?;
 
+	archive
+		.extract(download_dir.as_path())
+		.and_then(|_| archive.keep(&pathbuf![&download_dir, ARCHIVE_DIR]))
+		.map_err(|e| {
+			// delete any leftover remnants
+			let _ = remove(download_dir.as_path());
+			hc_error!(
+				"Error [{}] extracting plugin '{}'",
+				e,
+				plugin_id.to_policy_file_plugin_identifier(),
+			)
+		})?;
+	std::fs::remove_file(&archive.path)?;
+
 	PluginManifest::from_file(plugin_cache.plugin_kdl(&plugin_id))
 }
 
@@ -238,75 +434,19 @@ fn download_plugin(
 	expected_size: u64,
 	expected_hash_with_digest: &HashWithDigest,
 ) -> Result<PathBuf, Error> {
-	// retrieve archive
-	let agent = agent();
-	let response = agent
-		.get(url.as_str())
-		.call()
-		.map_err(|e| hc_error!("Error [{}] retrieving download manifest {}", e, url))?;
-	let error_code = response.status();
-	if error_code != 200 {
-		return Err(hc_error!(
-			"HTTP error code {} when retrieving {}",
-			error_code,
-			url
-		));
-	}
-
-	// extract bytes from response
-	// preallocate 10 MB to cut down on number of allocations needed
-	let mut contents = Vec::with_capacity(10 * 1024 * 1024);
-	let amount_read = response
-		.into_reader()
-		.read_to_end(&mut contents)
-		.map_err(|e| hc_error!("Error [{}] reading download into buffer", e))?;
-	contents.truncate(amount_read);
-
-	// verify size of download
-	if expected_size != amount_read as u64 {
-		return Err(hc_error!(
-			"File size mismatch, Expected {} B, Found {} B",
-			expected_size,
-			amount_read
-		));
-	}
-
-	// verify hash
-	let actual_hash = match expected_hash_with_digest.hash_algorithm {
-		HashAlgorithm::Sha256 => sha256::digest(&contents),
-		HashAlgorithm::Blake3 => blake3::hash(&contents).to_string(),
-	};
-	if actual_hash != expected_hash_with_digest.digest {
-		return Err(hc_error!(
-			"Plugin hash mismatch. Expected [{}], Received [{}]",
-			actual_hash,
-			expected_hash_with_digest.digest
-		));
-	}
-
-	let filename = url.path_segments().unwrap().last().unwrap();
-	std::fs::create_dir_all(download_dir).map_err(|e| {
-		hc_error!(
-			"Error [{}] creating download directory {}",
-			e,
-			download_dir.to_string_lossy()
-		)
-	})?;
+	let filename = url
+		.path_segments()
+		.and_then(|segments| segments.last())
+		.ok_or_else(|| hc_error!("no file name in plugin URL {}", url))?;
 	let output_path = Path::new(download_dir).join(filename);
-	let mut file = File::create(&output_path).map_err(|e| {
-		hc_error!(
-			"Error [{}] creating file: {}",
-			e,
-			output_path.to_string_lossy()
-		)
-	})?;
-	file.write_all(&contents).map_err(|e| {
-		hc_error!(
-			"Error [{}] writing to file: {}",
-			e,
-			output_path.to_string_lossy()
-		)
-	})?;
+
+	Download::new(url)
+		.size(expected_size)
+		.checksum(Checksum::from(expected_hash_with_digest))
+		.to_file(
+			&output_path,
+			progress_phase(&format!("downloading {}", filename)),
+		)?;
 
 	Ok(output_path)
 }
@@ -316,7 +456,6 @@ fn extract_plugin(
 	bundle_path: &Path,
 	extract_dir: &Path,
 	archive_format: ArchiveFormat,
-	delete_bundle: bool,
 ) -> Result<(), Error> {
 	let file = File::open(bundle_path).map_err(|e| {
 		hc_error!(
@@ -382,10 +521,6 @@ fn extract_plugin(
 		}
 	}
 
-	if delete_bundle {
-		std::fs::remove_file(bundle_path)?;
-	}
-
 	Ok(())
 }
 
@@ -407,28 +542,8 @@ fn move_to_extract_dir(extract_dir: &Path, entry: &DirEntry) -> Result<(), Error
 
 /// fetch and deserialize a DownloadManifest from a URL
 fn retrieve_download_manifest(url: &Url) -> Result<DownloadManifest, Error> {
-	let agent = agent();
-	let response = agent
-		.get(url.as_str())
-		.call()
-		.map_err(|e| hc_error!("Error [{}] retrieving download manifest {}", e, url))?;
-	let error_code = response.status();
-	if error_code != 200 {
-		return Err(hc_error!(
-			"HTTP error code {} when retrieving {}",
-			error_code,
-			url
-		));
-	}
-
-	// extract bytes from response
-	// preallocate 10 MB to cut down on number of allocations needed
-	let mut contents = Vec::with_capacity(10 * 1024 * 1024);
-	let amount_read = response
-		.into_reader()
-		.read_to_end(&mut contents)
-		.map_err(|e| hc_error!("Error [{}] reading download manifest into buffer", e))?;
-	contents.truncate(amount_read);
-	let contents = String::from_utf8_lossy(&contents);
-	DownloadManifest::from_str(&contents)
+	let contents = Download::new(url)
+		.to_bytes()
+		.context("failed to retrieve download manifest")?;
+	DownloadManifest::from_str(&String::from_utf8_lossy(&contents))
 }
@@ -0,0 +1,272 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checking the signatures of downloaded plugins before they're run.
+//!
+//! A plugin's download manifest entry can point to a signature of its archive,
+//! either a detached signature made with `cosign sign-blob`, or a Sigstore
+//! bundle. The signature is checked against the public keys the exec config
+//! trusts for the plugin's publisher. Keyless signatures, whose bundles hold a
+//! certificate instead of relying on a known key, aren't supported.
+
+use crate::{
+	error::{Context as _, Result},
+	exec::{PluginMirrors, PluginTrust},
+	hc_error,
+	plugin::{
+		download_manifest::{Signature, SignatureFormat},
+		PluginId,
+	},
+	util::{fs::read_string, http::download::Download},
+};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use ring::signature::{UnparsedPublicKey, VerificationAlgorithm, ECDSA_P256_SHA256_ASN1, ED25519};
+use serde_json::Value;
+use std::{
+	path::Path,
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Whether plugins which can't be checked against a trusted key may be run anyway.
+static ALLOW_UNSIGNED: AtomicBool = AtomicBool::new(false);
+
+/// The DER encoding of an ECDSA P-256 `SubjectPublicKeyInfo`, up to the key itself.
+const P256_SPKI_PREFIX: &[u8] = &[
+	0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+	0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
+/// The DER encoding of an Ed25519 `SubjectPublicKeyInfo`, up to the key itself.
+const ED25519_SPKI_PREFIX: &[u8] = &[
+	0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+/// Allow running downloaded plugins which aren't signed, or whose publisher
+/// has no trusted keys.
+///
+/// Plugins with a signature which doesn't match a trusted key are still refused.
+pub fn set_allow_unsigned(allow: bool) {
+	ALLOW_UNSIGNED.store(allow, Ordering::Relaxed);
+}
+
+/// Download the signature of a plugin's archive, returning the raw signature.
+///
+/// Signatures are downloaded through the same mirrors as plugins.
+pub fn download_signature(
+	plugin_id: &PluginId,
+	signature: &Signature,
+	mirrors: &PluginMirrors,
+) -> Result<Vec<u8>> {
+	let plugin = plugin_id.to_policy_file_plugin_identifier();
+	let raw_signature = Download::new(&mirrors.rewrite(&signature.url)?)
+		.to_bytes()
+		.with_context(|| format!("failed to download the signature of plugin {plugin}"))?;
+	decode_signature(&raw_signature, signature.format)
+		.with_context(|| format!("invalid signature for plugin {plugin}"))
+}
+
+/// Check that a plugin archive was signed by a key trusted for the plugin's
+/// publisher, given the raw signature, if the archive was signed.
+pub fn verify_plugin_archive(
+	plugin_id: &PluginId,
+	archive: &Path,
+	signature: Option<&[u8]>,
+	trust: &PluginTrust,
+) -> Result<()> {
+	let plugin = plugin_id.to_policy_file_plugin_identifier();
+	let publisher = &plugin_id.publisher().0;
+
+	let Some(signature) = signature else {
+		return unverified(&plugin, "isn't signed");
+	};
+
+	let keys: Vec<_> = trust.keys_for(publisher).collect();
+	if keys.is_empty() {
+		return unverified(
+			&plugin,
+			&format!("is signed, but no key is trusted for publisher '{publisher}'"),
+		);
+	}
+
+	let contents = std::fs::read(archive)?;
+
+	for key in keys {
+		let public_key = read_string(&key.path)
+			.and_then(|pem| PublicKey::from_pem(&pem))
+			.with_context(|| format!("failed to load trusted key '{}'", key.path.display()))?;
+
+		if public_key.verify(&contents, signature) {
+			tracing::debug!(
+				"verified plugin {} with key '{}'",
+				plugin,
+				key.path.display()
+			);
+			return Ok(());
+		}
+	}
+
+	Err(hc_error!(
+		"the signature of plugin {} doesn't match any key trusted for publisher '{}'",
+		plugin,
+		publisher
+	))
+}
+
+/// Allow a plugin which can't be verified only if unsigned plugins are allowed.
+pub fn unverified(plugin: &str, reason: &str) -> Result<()> {
+	if ALLOW_UNSIGNED.load(Ordering::Relaxed) {
+		tracing::warn!("running plugin {} which {}", plugin, reason);
+		return Ok(());
+	}
+
+	Err(hc_error!(
+		"plugin {} {}; trust a key for its publisher in the exec config, or pass --allow-unsigned to run it anyway",
+		plugin,
+		reason
+	))
+}
+
+/// Get the raw signature bytes out of a downloaded signature.
+fn decode_signature(raw: &[u8], format: SignatureFormat) -> Result<Vec<u8>> {
+	let encoded = match format {
+		SignatureFormat::Detached => String::from_utf8_lossy(raw).trim().to_owned(),
+		SignatureFormat::Bundle => {
+			let bundle: Value = serde_json::from_slice(raw)?;
+			// Sigstore bundles hold the signature as `messageSignature.signature`,
+			// while older cosign bundles hold it as `base64Signature`
+			bundle
+				.pointer("/messageSignature/signature")
+				.or_else(|| bundle.get("base64Signature"))
+				.and_then(Value::as_str)
+				.ok_or_else(|| hc_error!("bundle has no message signature"))?
+				.to_owned()
+		}
+	};
+
+	Ok(BASE64_STANDARD.decode(encoded)?)
+}
+
+/// A public key trusted to sign plugins.
+#[derive(Debug, PartialEq, Eq)]
+enum PublicKey {
+	/// An uncompressed ECDSA P-256 point, as made by `cosign generate-key-pair`.
+	EcdsaP256(Vec<u8>),
+	/// An Ed25519 key.
+	Ed25519(Vec<u8>),
+}
+
+impl PublicKey {
+	/// Parse a PEM-encoded `SubjectPublicKeyInfo`.
+	fn from_pem(pem: &str) -> Result<Self> {
+		let body: String = pem
+			.lines()
+			.map(str::trim)
+			.filter(|line| !line.starts_with("-----"))
+			.collect();
+		let der = BASE64_STANDARD.decode(body)?;
+
+		if let Some(point) = der.strip_prefix(P256_SPKI_PREFIX) {
+			Ok(PublicKey::EcdsaP256(point.to_vec()))
+		} else if let Some(key) = der.strip_prefix(ED25519_SPKI_PREFIX) {
+			Ok(PublicKey::Ed25519(key.to_vec()))
+		} else {
+			Err(hc_error!(
+				"unsupported public key, expected an ECDSA P-256 or Ed25519 key"
+			))
+		}
+	}
+
+	/// Check that `signature` is a signature of `message` made with this key.
+	fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+		let (algorithm, key): (&'static dyn VerificationAlgorithm, &[u8]) = match self {
+			PublicKey::EcdsaP256(point) => (&ECDSA_P256_SHA256_ASN1, point),
+			PublicKey::Ed25519(key) => (&ED25519, key),
+		};
+
+		UnparsedPublicKey::new(algorithm, key)
+			.verify(message, signature)
+			.is_ok()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ring::{
+		rand::SystemRandom,
+		signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING},
+	};
+
+	/// PEM-encode a public key with the given `SubjectPublicKeyInfo` prefix.
+	fn to_pem(prefix: &[u8], key: &[u8]) -> String {
+		let der = [prefix, key].concat();
+		format!(
+			"-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n",
+			BASE64_STANDARD.encode(der)
+		)
+	}
+
+	#[test]
+	fn verify_ecdsa_p256() {
+		let rng = SystemRandom::new();
+		let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+		let pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+			.unwrap();
+		let signature = pair.sign(&rng, b"plugin archive").unwrap();
+
+		let key =
+			PublicKey::from_pem(&to_pem(P256_SPKI_PREFIX, pair.public_key().as_ref())).unwrap();
+		assert!(key.verify(b"plugin archive", signature.as_ref()));
+		assert!(!key.verify(b"tampered archive", signature.as_ref()));
+	}
+
+	#[test]
+	fn verify_ed25519() {
+		let rng = SystemRandom::new();
+		let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+		let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+		let signature = pair.sign(b"plugin archive");
+
+		let key =
+			PublicKey::from_pem(&to_pem(ED25519_SPKI_PREFIX, pair.public_key().as_ref())).unwrap();
+		assert!(key.verify(b"plugin archive", signature.as_ref()));
+		assert!(!key.verify(b"tampered archive", signature.as_ref()));
+	}
+
+	#[test]
+	fn unsupported_key() {
+		let pem = to_pem(&[0x30, 0x0d], &[0; 8]);
+		assert!(PublicKey::from_pem(&pem).is_err());
+	}
+
+	#[test]
+	fn decode_signature_formats() {
+		let encoded = BASE64_STANDARD.encode(b"signature");
+
+		let detached = format!("{encoded}\n");
+		assert_eq!(
+			decode_signature(detached.as_bytes(), SignatureFormat::Detached).unwrap(),
+			b"signature"
+		);
+
+		let bundle = serde_json::json!({
+			"mediaType": "application/vnd.dev.sigstore.bundle.v0.3+json",
+			"messageSignature": { "signature": encoded },
+		});
+		assert_eq!(
+			decode_signature(bundle.to_string().as_bytes(), SignatureFormat::Bundle).unwrap(),
+			b"signature"
+		);
+
+		let legacy_bundle = serde_json::json!({ "base64Signature": encoded });
+		assert_eq!(
+			decode_signature(
+				legacy_bundle.to_string().as_bytes(),
+				SignatureFormat::Bundle
+			)
+			.unwrap(),
+			b"signature"
+		);
+
+		assert!(decode_signature(b"{}", SignatureFormat::Bundle).is_err());
+	}
+}
@@ -2,7 +2,12 @@
 
 use crate::{
 	hc_error,
+	plugin::{
+		pool::{PoolRecord, Pooled},
+		QueryProgress, ResourceLimits,
+	},
 	policy_exprs::{std_parse, Expr},
+	util::secret::Secret,
 	Result,
 };
 use futures::{Stream, StreamExt};
@@ -10,12 +15,15 @@ use hipcheck_common::proto::{
 	plugin_service_client::PluginServiceClient, ConfigurationStatus, Empty,
 	ExplainDefaultQueryRequest, GetDefaultPolicyExpressionRequest, GetQuerySchemasRequest,
 	GetQuerySchemasResponse as PluginSchema, InitiateQueryProtocolRequest, Query as PluginQuery,
-	SetConfigurationRequest, SetConfigurationResponse as PluginConfigResult,
+	QueryState, SetConfigurationRequest, SetConfigurationResponse as PluginConfigResult,
+};
+use hipcheck_common::{
+	chunk::{negotiate_max_size, prepare_with_max_size, QuerySynthesizer},
+	types::*,
 };
-use hipcheck_common::{chunk::QuerySynthesizer, types::*};
 use serde_json::Value;
 use std::{
-	collections::{HashMap, VecDeque},
+	collections::{HashMap, HashSet, VecDeque},
 	convert::TryFrom,
 	future::poll_fn,
 	ops::Not as _,
@@ -23,8 +31,13 @@ use std::{
 	pin::Pin,
 	process::Child,
 	result::Result as StdResult,
+	sync::{Arc, Mutex as StdMutex},
+	thread,
+};
+use tokio::sync::{
+	mpsc::{self, error::TrySendError},
+	Mutex,
 };
-use tokio::sync::{mpsc, Mutex};
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{transport::Channel, Code, Status};
 
@@ -35,6 +48,14 @@ pub struct Plugin {
 	pub name: String,
 	pub working_dir: PathBuf,
 	pub entrypoint: String,
+	/// The directory the plugin may use for temporary files, if any.
+	pub scratch_dir: Option<PathBuf>,
+	/// The directory the plugin may keep data in between runs, if any.
+	pub data_dir: Option<PathBuf>,
+	/// The secrets given to the plugin, with the environment variables to pass them in.
+	pub secrets: Vec<(String, Secret)>,
+	/// The limits on the resources the plugin's process may use.
+	pub limits: ResourceLimits,
 }
 
 // Hipcheck-facing version of struct from crate::hipcheck
@@ -62,6 +83,8 @@ impl TryFrom<PluginSchema> for Schema {
 pub struct ConfigurationResult {
 	pub status: ConfigurationStatus,
 	pub message: Option<String>,
+	/// The largest query protocol message the plugin accepts, or zero if it didn't say.
+	pub max_message_size: u64,
 }
 
 impl TryFrom<PluginConfigResult> for ConfigurationResult {
@@ -69,7 +92,11 @@ impl TryFrom<PluginConfigResult> for ConfigurationResult {
 	fn try_from(value: PluginConfigResult) -> Result<Self> {
 		let status: ConfigurationStatus = value.status.try_into()?;
 		let message = value.message.is_empty().not().then_some(value.message);
-		Ok(ConfigurationResult { status, message })
+		Ok(ConfigurationResult {
+			status,
+			message,
+			max_message_size: value.max_message_size,
+		})
 	}
 }
 
@@ -158,11 +185,18 @@ pub struct PluginContext {
 	/// A gRPC client for interacting with the plugin.
 	pub grpc: HcPluginClient,
 
-	/// The child process in which the plugin is running.
-	pub proc: Child,
+	/// The child process in which the plugin is running, unless it's pooled
+	/// and kept running after this run.
+	pub proc: Option<Child>,
 
 	/// The size of the gRPC buffer
 	pub grpc_query_buffer_size: usize,
+
+	/// The largest query protocol message core sends or accepts, in bytes.
+	pub max_message_size: usize,
+
+	/// The plugin's place in the plugin pool, if it's pooled.
+	pub pool: Option<Pooled>,
 }
 
 // Redefinition of `grpc` field's functions with more useful types, additional
@@ -202,6 +236,7 @@ impl PluginContext {
 		self.grpc
 			.set_configuration(SetConfigurationRequest {
 				configuration: serde_json::to_string(&conf)?,
+				max_message_size: self.max_message_size as u64,
 			})
 			.await?
 			.into_inner()
@@ -280,7 +315,11 @@ impl PluginContext {
 	/// Consume self and produce a `PluginTransport` which will handle
 	/// execution of the query protocol over the still-open bidirectional
 	/// `InitiateQueryProtocol` RPC.
-	pub async fn initialize(mut self, config: Value) -> Result<PluginTransport> {
+	pub async fn initialize(
+		mut self,
+		config: Value,
+		progress: Arc<QueryProgress>,
+	) -> Result<PluginTransport> {
 		// NOTE: The order of these operations is purposeful, and they should _not_
 		// be re-ordered.
 
@@ -291,7 +330,33 @@ impl PluginContext {
 				.map(|schema| (schema.query_name.clone(), schema)),
 		);
 
-		self.set_configuration(&config).await?.as_result()?;
+		let max_message_size = match &self.pool {
+			// A plugin reused from the pool was configured by the run which started it.
+			Some(Pooled::Leased { record, .. }) => record.max_message_size,
+			_ => {
+				let config_result = self.set_configuration(&config).await?;
+				config_result.as_result()?;
+				let max_message_size =
+					negotiate_max_size(self.max_message_size, config_result.max_message_size);
+
+				// Now that it's configured, a pooled plugin can be reused.
+				if let Some(Pooled::Started(entry)) = &self.pool {
+					let record = PoolRecord {
+						port: self.port,
+						max_message_size,
+					};
+					let lease = entry.lease(record)?;
+					self.pool = Some(Pooled::Leased { record, lease });
+				}
+
+				max_message_size
+			}
+		};
+		tracing::debug!(
+			plugin = %self.plugin.name,
+			max_message_size,
+			"negotiated max message size"
+		);
 
 		let opt_str = self.get_default_policy_expression().await?;
 		// This is where we turn the `std_parse` error into a user-facing message
@@ -312,6 +377,7 @@ impl PluginContext {
 
 		let (tx, out_rx) = mpsc::channel::<PluginQuery>(self.grpc_query_buffer_size);
 		let rx = self.initiate_query_protocol(out_rx).await?;
+		let cancelled = Arc::new(StdMutex::new(HashSet::new()));
 
 		Ok(PluginTransport {
 			schemas,
@@ -319,13 +385,23 @@ impl PluginContext {
 			opt_explain_default_query,
 			ctx: self,
 			tx,
-			rx: Mutex::new(MultiplexedQueryReceiver::new(rx)),
+			rx: Mutex::new(MultiplexedQueryReceiver::new(
+				rx,
+				Arc::clone(&progress),
+				Arc::clone(&cancelled),
+			)),
+			cancelled,
+			max_message_size,
+			progress,
 		})
 	}
 }
 impl Drop for PluginContext {
 	fn drop(&mut self) {
-		if let Err(e) = self.proc.kill() {
+		let Some(proc) = &mut self.proc else {
+			return;
+		};
+		if let Err(e) = proc.kill() {
 			println!("Failed to kill child: {e}");
 		}
 	}
@@ -334,6 +410,10 @@ impl Drop for PluginContext {
 pub struct MultiplexedQueryReceiver {
 	rx: QueryStream,
 	backlog: HashMap<i32, VecDeque<PluginQuery>>,
+	/// Where progress the plugin reports is recorded.
+	progress: Arc<QueryProgress>,
+	/// The IDs of cancelled queries, whose messages are dropped.
+	cancelled: Arc<StdMutex<HashSet<i32>>>,
 }
 
 impl std::fmt::Debug for MultiplexedQueryReceiver {
@@ -341,6 +421,7 @@ impl std::fmt::Debug for MultiplexedQueryReceiver {
 		f.debug_struct("MultiplexedQueryReceiver")
 			.field("rx", &"<receiver>")
 			.field("backlog", &self.backlog)
+			.field("cancelled", &self.cancelled)
 			.finish()
 	}
 }
@@ -356,24 +437,74 @@ impl std::fmt::Debug for MultiplexedQueryReceiver {
 type QueryStream = Box<dyn Stream<Item = StdResult<PluginQuery, Status>> + Send + Unpin + 'static>;
 
 impl MultiplexedQueryReceiver {
-	pub fn new(rx: QueryStream) -> Self {
+	pub fn new(
+		rx: QueryStream,
+		progress: Arc<QueryProgress>,
+		cancelled: Arc<StdMutex<HashSet<i32>>>,
+	) -> Self {
 		Self {
 			rx,
 			backlog: HashMap::new(),
+			progress,
+			cancelled,
 		}
 	}
 
 	/// Poll the underlying stream future to get the next query, if present.
+	///
+	/// Progress reports aren't part of any query's messages, so they're recorded
+	/// here and never returned. Nothing is waiting on the messages of cancelled
+	/// queries, so they're dropped.
 	async fn message(&mut self) -> StdResult<Option<PluginQuery>, Status> {
-		match poll_fn(|cx| Pin::new(self.rx.as_mut()).poll_next(cx)).await {
-			Some(Ok(m)) => Ok(Some(m)),
-			Some(Err(e)) => Err(e),
-			None => Ok(None),
+		loop {
+			match poll_fn(|cx| Pin::new(self.rx.as_mut()).poll_next(cx)).await {
+				Some(Ok(m)) if self.was_cancelled(&m) => {
+					tracing::trace!(id = m.id, "dropping message for cancelled query");
+				}
+				Some(Ok(PluginQuery {
+					id,
+					plugin_name,
+					publisher_name,
+					progress: Some(progress),
+					..
+				})) => {
+					let plugin = format!("{publisher_name}/{plugin_name}");
+					self.progress
+						.update(&plugin, id, progress.completed, progress.total);
+				}
+				Some(Ok(m)) => return Ok(Some(m)),
+				Some(Err(e)) => return Err(e),
+				None => return Ok(None),
+			}
 		}
 	}
 
+	/// Check if a message is for a cancelled query. The query is forgotten once
+	/// the plugin is done sending messages for it.
+	fn was_cancelled(&self, query: &PluginQuery) -> bool {
+		let mut cancelled = self.cancelled.lock().unwrap();
+		if cancelled.contains(&query.id).not() {
+			return false;
+		}
+		let done = match query.state() {
+			QueryState::ReplyInProgress | QueryState::SubmitInProgress => false,
+			QueryState::Unspecified => query.progress.is_none(),
+			QueryState::ReplyComplete | QueryState::SubmitComplete => true,
+		};
+		if done {
+			cancelled.remove(&query.id);
+		}
+		true
+	}
+
 	// @Invariant - this function will never return an empty VecDeque
 	pub async fn recv(&mut self, id: i32) -> Result<Option<VecDeque<PluginQuery>>> {
+		// Messages for queries cancelled since they arrived won't be asked for
+		{
+			let cancelled = self.cancelled.lock().unwrap();
+			self.backlog.retain(|id, _| cancelled.contains(id).not());
+		}
+
 		// If we have 1+ messages on backlog for `id`, return them all,
 		// no need to waste time with successive calls
 		if let Some(msgs) = self.backlog.remove(&id) {
@@ -413,6 +544,12 @@ pub struct PluginTransport {
 	ctx: PluginContext,
 	tx: mpsc::Sender<PluginQuery>,
 	rx: Mutex<MultiplexedQueryReceiver>,
+	/// The IDs of cancelled queries, whose messages are dropped.
+	cancelled: Arc<StdMutex<HashSet<i32>>>,
+	/// The max message size negotiated with the plugin.
+	max_message_size: usize,
+	/// Where progress the plugin reports is recorded.
+	progress: Arc<QueryProgress>,
 }
 
 impl PluginTransport {
@@ -423,9 +560,13 @@ impl PluginTransport {
 	pub async fn query(&self, query: Query) -> Result<Option<Query>> {
 		// Send the query
 		let id = query.id as i32;
-		let queries = hipcheck_common::chunk::prepare(query).map_err(|e| hc_error!("{}", e))?;
+		// Chunks are produced as they're sent, so a full channel holds off
+		// chunking the rest of a large query until the plugin catches up
+		let queries =
+			prepare_with_max_size(query, self.max_message_size).map_err(|e| hc_error!("{}", e))?;
 
 		for query in queries {
+			let query = query.map_err(|e| hc_error!("{}", e))?;
 			self.tx
 				.send(query)
 				.await
@@ -433,7 +574,7 @@ impl PluginTransport {
 		}
 
 		// De-chunk received messages into a response Query object
-		let mut synth = QuerySynthesizer::default();
+		let mut synth = QuerySynthesizer::with_max_message_size(self.max_message_size);
 		let mut res: Option<Query> = None;
 		while res.is_none() {
 			// Get initial response batch
@@ -445,8 +586,52 @@ impl PluginTransport {
 			res = synth.add(msg_chunks.into_iter())?;
 		}
 
+		if let Some(Query {
+			direction: QueryDirection::Response,
+			..
+		}) = &res
+		{
+			self.progress.finish(self.name(), id);
+		}
+
 		Ok(res)
 	}
+
+	/// Tell the plugin to stop working on a query, since its answer is no
+	/// longer needed. Anything the plugin sends for the query afterward is
+	/// dropped.
+	///
+	/// This doesn't wait for the message to be sent, so queries can be
+	/// cancelled as they're abandoned.
+	pub fn cancel(&self, id: i32) {
+		tracing::debug!(plugin = self.name(), id, "cancelling query");
+		self.cancelled.lock().unwrap().insert(id);
+		self.progress.finish(self.name(), id);
+
+		let query = PluginQuery {
+			id,
+			state: QueryState::Unspecified as i32,
+			publisher_name: String::new(),
+			plugin_name: String::new(),
+			query_name: String::new(),
+			key: vec![],
+			output: vec![],
+			concern: vec![],
+			split: false,
+			fragment: vec![],
+			error: String::new(),
+			progress: None,
+			cancel: true,
+		};
+		match self.tx.try_send(query) {
+			Ok(()) | Err(TrySendError::Closed(_)) => (),
+			// Wait for room without holding up whatever abandoned the query
+			Err(TrySendError::Full(query)) => {
+				let tx = self.tx.clone();
+				thread::spawn(move || tx.blocking_send(query));
+			}
+		}
+	}
 }
 
 pub struct PluginWithConfig(pub Plugin, pub Value);
@@ -487,7 +672,9 @@ impl From<Query> for AwaitingResult {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct QueryResult {
 	pub value: Vec<Value>,
-	pub concerns: Vec<String>,
+	pub concerns: Vec<Value>,
+	/// Report sections the query added, as JSON; see `report::fragment`.
+	pub fragments: Vec<Value>,
 }
 
 #[derive(Clone, Debug)]
@@ -512,6 +699,7 @@ impl From<Query> for PluginResponse {
 			let result = QueryResult {
 				value: value.output,
 				concerns: value.concerns,
+				fragments: value.fragments,
 			};
 			PluginResponse::Completed(result)
 		} else {
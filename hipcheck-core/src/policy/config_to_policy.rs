@@ -52,6 +52,8 @@ pub fn config_to_policy(config: Config) -> Result<PolicyFile> {
 		plugins,
 		patch,
 		analyze,
+		limits: PolicyLimits::default(),
+		waivers: PolicyWaivers::default(),
 	})
 }
 
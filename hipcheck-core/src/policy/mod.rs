@@ -6,15 +6,19 @@ mod config_to_policy;
 mod macros;
 pub mod policy_file;
 mod tests;
+pub mod validate;
 
 pub use config_to_policy::config_to_policy;
 
 use crate::{
 	error::Result,
 	hc_error,
-	policy::policy_file::{PolicyAnalyze, PolicyPatchList, PolicyPluginList, PolicyPluginName},
+	policy::policy_file::{
+		PolicyAnalyze, PolicyLimits, PolicyPatchList, PolicyPluginList, PolicyPluginName,
+		PolicyWaivers,
+	},
 	util::fs as file,
-	util::kdl::extract_data,
+	util::kdl::{extract_data, ParseKdlNode},
 };
 use kdl::KdlDocument;
 use serde_json::Value;
@@ -25,6 +29,8 @@ pub struct PolicyFile {
 	pub plugins: PolicyPluginList,
 	pub patch: PolicyPatchList,
 	pub analyze: PolicyAnalyze,
+	pub limits: PolicyLimits,
+	pub waivers: PolicyWaivers,
 }
 
 impl FromStr for PolicyFile {
@@ -41,11 +47,30 @@ impl FromStr for PolicyFile {
 		let patch: PolicyPatchList = extract_data(nodes).unwrap_or_default();
 		let analyze: PolicyAnalyze =
 			extract_data(nodes).ok_or_else(|| hc_error!("Could not parse 'analyze'"))?;
+		// `limits` is an optional section, but mustn't be ignored if it's malformed
+		let limits: PolicyLimits = if nodes
+			.iter()
+			.any(|node| node.name().value() == PolicyLimits::kdl_key())
+		{
+			extract_data(nodes).ok_or_else(|| hc_error!("Could not parse 'limits'"))?
+		} else {
+			PolicyLimits::default()
+		};
+		let waivers: PolicyWaivers = if nodes
+			.iter()
+			.any(|node| node.name().value() == PolicyWaivers::kdl_key())
+		{
+			extract_data(nodes).ok_or_else(|| hc_error!("Could not parse 'waivers'"))?
+		} else {
+			PolicyWaivers::default()
+		};
 
 		Ok(Self {
 			plugins,
 			patch,
 			analyze,
+			limits,
+			waivers,
 		})
 	}
 }
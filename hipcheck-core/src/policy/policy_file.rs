@@ -6,13 +6,17 @@ use crate::{
 	error::Result,
 	hc_error,
 	plugin::{PluginId, PluginName, PluginPublisher, PluginVersion},
+	report::concern::Concern,
 	string_newtype_parse_kdl_node,
 	util::kdl::{extract_data, ParseKdlNode, ToKdlNode},
 };
 
+use chrono::NaiveDate;
 use kdl::KdlNode;
+use schemars::JsonSchema;
+use serde::Serialize;
 use serde_json::Value;
-use std::{collections::HashMap, fmt, fmt::Display, path::PathBuf};
+use std::{collections::HashMap, fmt, fmt::Display, path::PathBuf, str::FromStr};
 use url::Url;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -78,7 +82,7 @@ impl ParseKdlNode for PolicyPlugin {
 		let name = match PolicyPluginName::new(full_name) {
 			Ok(name) => name,
 			Err(e) => {
-				log::error!("{}", e);
+				tracing::error!("{}", e);
 				return None;
 			}
 		};
@@ -95,7 +99,7 @@ impl ParseKdlNode for PolicyPlugin {
 				} else if path.exists() {
 					Some(ManifestLocation::Local(path))
 				} else {
-					log::error!(
+					tracing::error!(
 						"Unable to parse provided manifest URL {} for plugin {} in the policy file",
 						raw_url,
 						name.to_string()
@@ -220,13 +224,15 @@ impl ParseKdlNode for PolicyConfig {
 					.insert(
 						description.clone(),
 						try_to_serde_json(info.value()).unwrap_or_else(|e| {
-							log::error!("error converting KDL node to serde_json::Value: {e:?}");
+							tracing::error!(
+								"error converting KDL node to serde_json::Value: {e:?}"
+							);
 							Value::Null
 						}),
 					)
 					.is_err()
 				{
-					log::error!(
+					tracing::error!(
 						"Duplicate configuration information detected for {}",
 						description
 					);
@@ -238,11 +244,59 @@ impl ParseKdlNode for PolicyConfig {
 	}
 }
 
+/// A standardized kind of supply chain risk an analysis speaks to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+#[schemars(crate = "schemars")]
+pub enum RiskCategory {
+	/// The project may no longer be maintained.
+	Abandonment,
+	/// Someone may have contributed malicious code.
+	MaliciousContribution,
+	/// The build or release process may have been tampered with.
+	BuildTampering,
+	/// The project may contain exploitable vulnerabilities.
+	Vulnerability,
+}
+
+impl RiskCategory {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			RiskCategory::Abandonment => "abandonment",
+			RiskCategory::MaliciousContribution => "malicious-contribution",
+			RiskCategory::BuildTampering => "build-tampering",
+			RiskCategory::Vulnerability => "vulnerability",
+		}
+	}
+}
+
+impl Display for RiskCategory {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
+
+impl FromStr for RiskCategory {
+	type Err = crate::error::Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		match s {
+			"abandonment" => Ok(RiskCategory::Abandonment),
+			"malicious-contribution" => Ok(RiskCategory::MaliciousContribution),
+			"build-tampering" => Ok(RiskCategory::BuildTampering),
+			"vulnerability" => Ok(RiskCategory::Vulnerability),
+			_ => Err(hc_error!("unknown risk category '{}'", s)),
+		}
+	}
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PolicyAnalysis {
 	pub name: PolicyPluginName,
 	pub policy_expression: Option<String>,
 	pub weight: Option<u16>,
+	pub concern_limit: Option<usize>,
+	pub risk_categories: Vec<RiskCategory>,
 	pub config: Option<PolicyConfig>,
 }
 
@@ -258,6 +312,8 @@ impl PolicyAnalysis {
 			name,
 			policy_expression,
 			weight,
+			concern_limit: None,
+			risk_categories: Vec::new(),
 			config,
 		}
 	}
@@ -277,7 +333,7 @@ impl ParseKdlNode for PolicyAnalysis {
 		let name = match PolicyPluginName::new(full_name) {
 			Ok(name) => name,
 			Err(e) => {
-				log::error!("{}", e);
+				tracing::error!("{}", e);
 				return None;
 			}
 		};
@@ -289,6 +345,27 @@ impl ParseKdlNode for PolicyAnalysis {
 			Some(entry) => Some(entry.as_integer()? as u16),
 			None => None,
 		};
+		let concern_limit = match node.get("concern-limit") {
+			Some(entry) => Some(entry.as_integer()? as usize),
+			None => None,
+		};
+
+		let risk_categories = match node.get("risk-categories") {
+			Some(entry) => {
+				let mut categories = Vec::new();
+				for category in entry.as_string()?.split(',') {
+					match RiskCategory::from_str(category.trim()) {
+						Ok(category) => categories.push(category),
+						Err(e) => {
+							tracing::error!("{}", e);
+							return None;
+						}
+					}
+				}
+				categories
+			}
+			None => Vec::new(),
+		};
 
 		let config = match node.children() {
 			Some(_) => PolicyConfig::parse_node(node),
@@ -299,6 +376,8 @@ impl ParseKdlNode for PolicyAnalysis {
 			name,
 			policy_expression,
 			weight,
+			concern_limit,
+			risk_categories,
 			config,
 		})
 	}
@@ -416,6 +495,97 @@ impl PolicyCategoryChild {
 pub struct InvestigatePolicy(pub String);
 string_newtype_parse_kdl_node!(InvestigatePolicy, "investigate");
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConditionsPolicy(pub String);
+string_newtype_parse_kdl_node!(ConditionsPolicy, "use-with-conditions");
+
+/// Custom wording for each kind of final recommendation.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct RecommendationText {
+	pub pass: Option<String>,
+	pub use_with_conditions: Option<String>,
+	pub investigate: Option<String>,
+}
+
+impl ParseKdlNode for RecommendationText {
+	fn kdl_key() -> &'static str {
+		"recommendation"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+
+		let mut text = Self::default();
+
+		for node in node.children()?.nodes() {
+			let value = node.entries().first()?.value().as_string()?.to_string();
+
+			match node.name().to_string().as_str() {
+				"pass" => text.pass = Some(value),
+				"use-with-conditions" => text.use_with_conditions = Some(value),
+				"investigate" => text.investigate = Some(value),
+				other => {
+					tracing::error!("Unknown recommendation kind '{}' in policy file", other);
+					return None;
+				}
+			}
+		}
+
+		Some(text)
+	}
+}
+
+/// When `hc check` exits with a failing status.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum FailOn {
+	/// When the recommendation is to investigate, or the target couldn't be analyzed.
+	Investigate,
+	/// Only when the target couldn't be analyzed.
+	#[default]
+	Error,
+	/// Never, whatever the results.
+	Never,
+}
+
+impl FromStr for FailOn {
+	type Err = crate::Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		match s {
+			"investigate" => Ok(FailOn::Investigate),
+			"error" => Ok(FailOn::Error),
+			"never" => Ok(FailOn::Never),
+			_ => Err(hc_error!(
+				"unknown fail-on setting '{}', expected 'investigate', 'error', or 'never'",
+				s
+			)),
+		}
+	}
+}
+
+impl ParseKdlNode for FailOn {
+	fn kdl_key() -> &'static str {
+		"fail-on"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+
+		let value = node.entries().first()?.value().as_string()?;
+		match FailOn::from_str(value) {
+			Ok(fail_on) => Some(fail_on),
+			Err(e) => {
+				tracing::error!("{}", e);
+				None
+			}
+		}
+	}
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct InvestigateIfFail(pub Vec<PolicyPluginName>);
 
@@ -474,6 +644,9 @@ impl ParseKdlNode for InvestigateIfFail {
 pub struct PolicyAnalyze {
 	pub investigate_policy: InvestigatePolicy,
 	pub if_fail: Option<InvestigateIfFail>,
+	pub conditions_policy: Option<ConditionsPolicy>,
+	pub recommendation_text: Option<RecommendationText>,
+	pub fail_on: Option<FailOn>,
 	pub categories: Vec<PolicyCategory>,
 }
 
@@ -483,6 +656,9 @@ impl PolicyAnalyze {
 		Self {
 			investigate_policy,
 			if_fail,
+			conditions_policy: None,
+			recommendation_text: None,
+			fail_on: None,
 			categories: Vec::new(),
 		}
 	}
@@ -496,6 +672,9 @@ impl PolicyAnalyze {
 		Self {
 			investigate_policy,
 			if_fail,
+			conditions_policy: None,
+			recommendation_text: None,
+			fail_on: None,
 			categories: Vec::with_capacity(capacity),
 		}
 	}
@@ -531,6 +710,17 @@ impl ParseKdlNode for PolicyAnalyze {
 
 		let investigate_policy: InvestigatePolicy = extract_data(nodes)?;
 		let if_fail: Option<InvestigateIfFail> = extract_data(nodes);
+		let conditions_policy: Option<ConditionsPolicy> = extract_data(nodes);
+		let recommendation_text: Option<RecommendationText> = extract_data(nodes);
+		// `fail-on` is optional, but mustn't be ignored if it's malformed
+		let fail_on: Option<FailOn> = if nodes
+			.iter()
+			.any(|node| node.name().value() == FailOn::kdl_key())
+		{
+			Some(extract_data(nodes)?)
+		} else {
+			None
+		};
 
 		let mut categories = Vec::new();
 
@@ -545,6 +735,9 @@ impl ParseKdlNode for PolicyAnalyze {
 		Some(Self {
 			investigate_policy,
 			if_fail,
+			conditions_policy,
+			recommendation_text,
+			fail_on,
 			categories,
 		})
 	}
@@ -603,7 +796,7 @@ impl ParseKdlNode for PolicyPatch {
 		let name = match PolicyPluginName::new(full_name) {
 			Ok(name) => name,
 			Err(e) => {
-				log::error!("{}", e);
+				tracing::error!("{}", e);
 				return None;
 			}
 		};
@@ -643,3 +836,218 @@ impl ParseKdlNode for PolicyPatchList {
 		Some(plugins)
 	}
 }
+
+/// What to do with a repository larger than the policy file's size limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RepoSizeAction {
+	/// Refuse to clone the repository.
+	#[default]
+	Refuse,
+	/// Warn about the repository's size, but clone it anyway.
+	Warn,
+}
+
+impl FromStr for RepoSizeAction {
+	type Err = crate::Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		match s {
+			"refuse" => Ok(RepoSizeAction::Refuse),
+			"warn" => Ok(RepoSizeAction::Warn),
+			_ => Err(hc_error!(
+				"unknown repository size action '{}', expected 'refuse' or 'warn'",
+				s
+			)),
+		}
+	}
+}
+
+/// Limits on the targets Hipcheck will analyze.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct PolicyLimits {
+	/// The size in bytes of the largest repository Hipcheck will clone.
+	pub max_repo_size: Option<u64>,
+	pub repo_size_action: RepoSizeAction,
+}
+
+impl ParseKdlNode for PolicyLimits {
+	fn kdl_key() -> &'static str {
+		"limits"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+
+		let mut limits = PolicyLimits::default();
+
+		for node in node.children()?.nodes() {
+			match node.name().to_string().as_str() {
+				"max-repo-size" => {
+					let size = node.entries().first()?.value().as_string()?;
+					match parse_size(size) {
+						Ok(size) => limits.max_repo_size = Some(size),
+						Err(e) => {
+							tracing::error!("{}", e);
+							return None;
+						}
+					}
+					if let Some(action) = node.get("action") {
+						match RepoSizeAction::from_str(action.as_string()?) {
+							Ok(action) => limits.repo_size_action = action,
+							Err(e) => {
+								tracing::error!("{}", e);
+								return None;
+							}
+						}
+					}
+				}
+				other => {
+					tracing::error!("Unknown limit '{}' in policy file", other);
+					return None;
+				}
+			}
+		}
+
+		Some(limits)
+	}
+}
+
+/// A concern the policy file suppresses until a date, with the reason why.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolicyWaiver {
+	/// The analysis whose concerns are waived.
+	pub analysis: PolicyPluginName,
+	/// What the waived concerns mention, like a file path or commit hash.
+	pub identifier: String,
+	pub justification: String,
+	/// The last day the waiver applies.
+	pub expires: NaiveDate,
+}
+
+impl PolicyWaiver {
+	/// Check if the waiver still applies on a day.
+	pub fn is_active(&self, today: NaiveDate) -> bool {
+		today <= self.expires
+	}
+}
+
+/// Parse a waiver like
+/// `waive "mitre/binary" "vendor/tool.exe" justification="..." expires="2026-12-31"`.
+fn parse_waiver(node: &KdlNode) -> Result<PolicyWaiver> {
+	let mut args = node.entries().iter().filter(|entry| entry.name().is_none());
+	let analysis = args
+		.next()
+		.and_then(|entry| entry.value().as_string())
+		.ok_or_else(|| hc_error!("waiver needs the analysis it applies to"))?;
+	let analysis = PolicyPluginName::new(analysis)?;
+	let identifier = args
+		.next()
+		.and_then(|entry| entry.value().as_string())
+		.ok_or_else(|| hc_error!("waiver for {} needs what its concerns mention", analysis))?;
+	let justification = node
+		.get("justification")
+		.and_then(|value| value.as_string())
+		.filter(|justification| !justification.trim().is_empty())
+		.ok_or_else(|| hc_error!("waiver for '{}' needs a justification", identifier))?;
+	let expires = node
+		.get("expires")
+		.and_then(|value| value.as_string())
+		.ok_or_else(|| hc_error!("waiver for '{}' needs an expiration date", identifier))?;
+	let expires = parse_date(expires)?;
+
+	Ok(PolicyWaiver {
+		analysis,
+		identifier: identifier.to_owned(),
+		justification: justification.to_owned(),
+		expires,
+	})
+}
+
+/// Parse a date like `2026-12-31`.
+pub fn parse_date(s: &str) -> Result<NaiveDate> {
+	NaiveDate::parse_from_str(s, "%Y-%m-%d")
+		.map_err(|_| hc_error!("invalid date '{}', expected YYYY-MM-DD", s))
+}
+
+/// Concerns the policy file suppresses, which are reported as waived instead.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct PolicyWaivers(pub Vec<PolicyWaiver>);
+
+impl PolicyWaivers {
+	/// Get the waiver which applies to a concern on a day, if any.
+	pub fn find(
+		&self,
+		analysis: &str,
+		concern: &Concern,
+		today: NaiveDate,
+	) -> Option<&PolicyWaiver> {
+		self.0.iter().find(|waiver| {
+			waiver.is_active(today)
+				&& waiver.analysis.to_string() == analysis
+				&& concern.mentions(&waiver.identifier)
+		})
+	}
+
+	/// Get the waivers which have expired by a day.
+	pub fn expired(&self, today: NaiveDate) -> impl Iterator<Item = &PolicyWaiver> {
+		self.0.iter().filter(move |waiver| !waiver.is_active(today))
+	}
+}
+
+impl ParseKdlNode for PolicyWaivers {
+	fn kdl_key() -> &'static str {
+		"waivers"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+
+		let mut waivers = Vec::new();
+
+		for node in node.children()?.nodes() {
+			if node.name().value() != "waive" {
+				tracing::error!("Unknown waiver '{}' in policy file", node.name().value());
+				return None;
+			}
+			match parse_waiver(node) {
+				Ok(waiver) => waivers.push(waiver),
+				Err(e) => {
+					tracing::error!("{}", e);
+					return None;
+				}
+			}
+		}
+
+		Some(Self(waivers))
+	}
+}
+
+/// Parse a size like `500MB` or `2 GB` into a number of bytes.
+pub fn parse_size(s: &str) -> Result<u64> {
+	let s = s.trim();
+	let unit_start = s
+		.find(|c: char| !c.is_ascii_digit() && c != '.')
+		.unwrap_or(s.len());
+	let (number, unit) = s.split_at(unit_start);
+	let number: f64 = number
+		.parse()
+		.map_err(|_| hc_error!("invalid size '{}'", s))?;
+	let multiplier = match unit.trim().to_uppercase().as_str() {
+		"" | "B" => 1.0,
+		"KB" => 1e3,
+		"MB" => 1e6,
+		"GB" => 1e9,
+		"TB" => 1e12,
+		_ => {
+			return Err(hc_error!(
+				"unknown unit in size '{}', expected B, KB, MB, GB or TB",
+				s
+			))
+		}
+	};
+	Ok((number * multiplier) as u64)
+}
@@ -7,6 +7,7 @@ mod test {
 		config::Config,
 		plugin::PluginVersion,
 		policy::{config_to_policy::config_to_policy, policy_file::*, PolicyFile, PolicyPatchList},
+		report::concern::Concern,
 		util::kdl::ParseKdlNode,
 	};
 
@@ -90,6 +91,136 @@ mod test {
 		assert_eq!(expected, InvestigateIfFail::parse_node(&node).unwrap())
 	}
 
+	#[test]
+	fn test_parsing_conditions_policy() {
+		let data = r#"use-with-conditions policy="(gt 0.7 $)""#;
+		let node = KdlNode::from_str(data).unwrap();
+
+		let expected = ConditionsPolicy("(gt 0.7 $)".to_string());
+
+		assert_eq!(expected, ConditionsPolicy::parse_node(&node).unwrap())
+	}
+
+	#[test]
+	fn test_parsing_recommendation_text() {
+		let data = r#"recommendation {
+            pass "Approved for use"
+            investigate "Submit for security review"
+        }"#;
+		let node = KdlNode::from_str(data).unwrap();
+
+		let expected = RecommendationText {
+			pass: Some("Approved for use".to_string()),
+			use_with_conditions: None,
+			investigate: Some("Submit for security review".to_string()),
+		};
+
+		assert_eq!(expected, RecommendationText::parse_node(&node).unwrap())
+	}
+
+	#[test]
+	fn test_parsing_fail_on() {
+		let node = KdlNode::from_str(r#"fail-on "investigate""#).unwrap();
+		assert_eq!(FailOn::parse_node(&node), Some(FailOn::Investigate));
+
+		let node = KdlNode::from_str(r#"fail-on "sometimes""#).unwrap();
+		assert!(FailOn::parse_node(&node).is_none());
+	}
+
+	#[test]
+	fn test_parsing_limits() {
+		let data = r#"limits {
+            max-repo-size "2GB" action="warn"
+        }"#;
+		let node = KdlNode::from_str(data).unwrap();
+
+		let expected = PolicyLimits {
+			max_repo_size: Some(2_000_000_000),
+			repo_size_action: RepoSizeAction::Warn,
+		};
+
+		assert_eq!(expected, PolicyLimits::parse_node(&node).unwrap())
+	}
+
+	#[test]
+	fn test_parsing_invalid_limits() {
+		let data = r#"limits {
+            max-repo-size "2 parsecs"
+        }"#;
+		let node = KdlNode::from_str(data).unwrap();
+
+		assert!(PolicyLimits::parse_node(&node).is_none())
+	}
+
+	#[test]
+	fn test_parsing_waivers() {
+		let data = r#"waivers {
+            waive "mitre/binary" "vendor/tool.exe" justification="Reviewed vendored build tool" expires="2026-12-31"
+        }"#;
+		let node = KdlNode::from_str(data).unwrap();
+
+		let expected = PolicyWaivers(vec![PolicyWaiver {
+			analysis: PolicyPluginName::new("mitre/binary").unwrap(),
+			identifier: "vendor/tool.exe".to_owned(),
+			justification: "Reviewed vendored build tool".to_owned(),
+			expires: parse_date("2026-12-31").unwrap(),
+		}]);
+
+		assert_eq!(expected, PolicyWaivers::parse_node(&node).unwrap())
+	}
+
+	#[test]
+	fn test_parsing_invalid_waivers() {
+		let data = r#"waivers {
+            waive "mitre/binary" "vendor/tool.exe" expires="2026-12-31"
+        }"#;
+		let node = KdlNode::from_str(data).unwrap();
+		assert!(PolicyWaivers::parse_node(&node).is_none());
+
+		let data = r#"waivers {
+            waive "mitre/binary" "vendor/tool.exe" justification="Reviewed" expires="next year"
+        }"#;
+		let node = KdlNode::from_str(data).unwrap();
+		assert!(PolicyWaivers::parse_node(&node).is_none());
+	}
+
+	#[test]
+	fn test_finding_waivers() {
+		let waivers = PolicyWaivers(vec![PolicyWaiver {
+			analysis: PolicyPluginName::new("mitre/binary").unwrap(),
+			identifier: "vendor/tool.exe".to_owned(),
+			justification: "Reviewed vendored build tool".to_owned(),
+			expires: parse_date("2026-12-31").unwrap(),
+		}]);
+		let concern = Concern::new("Found binary file at 'vendor/tool.exe'");
+		let today = parse_date("2026-12-31").unwrap();
+
+		assert!(waivers.find("mitre/binary", &concern, today).is_some());
+		assert!(waivers.find("mitre/entropy", &concern, today).is_none());
+		let other = Concern::new("Found binary file at 'bin/tool.exe'");
+		assert!(waivers.find("mitre/binary", &other, today).is_none());
+
+		// Concerns can name their file without mentioning it
+		let concern = Concern {
+			file: Some("vendor/tool.exe".to_owned()),
+			..Concern::new("Found a binary file")
+		};
+		assert!(waivers.find("mitre/binary", &concern, today).is_some());
+
+		let expired = parse_date("2027-01-01").unwrap();
+		assert!(waivers.find("mitre/binary", &concern, expired).is_none());
+		assert_eq!(waivers.expired(expired).count(), 1);
+	}
+
+	#[test]
+	fn test_parse_size() {
+		assert_eq!(parse_size("512").unwrap(), 512);
+		assert_eq!(parse_size("1.5 KB").unwrap(), 1500);
+		assert_eq!(parse_size("500mb").unwrap(), 500_000_000);
+		assert!(parse_size("GB").is_err());
+		assert!(parse_size("10 GiB").is_err());
+	}
+
 	#[test]
 	fn test_parsing_analysis_weight() {
 		let data = r#"analysis "mitre/typo" policy="(eq 0 (count $))" weight=3"#;
@@ -138,6 +269,33 @@ mod test {
 		assert_eq!(expected, PolicyAnalysis::parse_node(&node).unwrap())
 	}
 
+	#[test]
+	fn test_parsing_analysis_risk_categories() {
+		let data = r#"analysis "mitre/affiliation" risk-categories="malicious-contribution, build-tampering""#;
+		let node = KdlNode::from_str(data).unwrap();
+
+		let mut expected = PolicyAnalysis::new(
+			PolicyPluginName::new("mitre/affiliation").unwrap(),
+			None,
+			None,
+			None,
+		);
+		expected.risk_categories = vec![
+			RiskCategory::MaliciousContribution,
+			RiskCategory::BuildTampering,
+		];
+
+		assert_eq!(expected, PolicyAnalysis::parse_node(&node).unwrap())
+	}
+
+	#[test]
+	fn test_parsing_analysis_unknown_risk_category() {
+		let data = r#"analysis "mitre/affiliation" risk-categories="gremlins""#;
+		let node = KdlNode::from_str(data).unwrap();
+
+		assert!(PolicyAnalysis::parse_node(&node).is_none())
+	}
+
 	#[test]
 	fn test_parsing_analysis_multiple_configs() {
 		let data = r#"analysis "mitre/typo" policy="(eq 0 (count $))" weight=3 {
@@ -335,6 +493,8 @@ mod test {
 			plugins,
 			patch: PolicyPatchList::default(),
 			analyze,
+			limits: PolicyLimits::default(),
+			waivers: PolicyWaivers::default(),
 		};
 
 		assert_eq!(expected, PolicyFile::from_str(data).unwrap())
@@ -0,0 +1,761 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checking a policy file for mistakes without running an analysis.
+//!
+//! Each problem is reported with the line and column of the node it was found
+//! in, instead of surfacing partway through a `check` run.
+
+use crate::{
+	config::DEFAULT_QUERY,
+	error::Result,
+	plugin::HcPluginCore,
+	policy::{
+		macros,
+		policy_file::{parse_date, parse_size, PolicyPluginName, RepoSizeAction, RiskCategory},
+		PolicyFile,
+	},
+	policy_exprs::{self, std_parse, std_type_check, Expr},
+	util::fs as file,
+};
+use kdl::{KdlDocument, KdlEntry, KdlNode};
+use serde_json::{json, Value};
+use std::{collections::HashSet, fmt, path::Path, str::FromStr};
+
+/// The deepest a plugin's output schema is followed when building a sample output.
+const MAX_SCHEMA_DEPTH: usize = 32;
+
+/// A problem found in a policy file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyDiagnostic {
+	pub line: usize,
+	pub column: usize,
+	pub message: String,
+}
+
+impl fmt::Display for PolicyDiagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{}: {}", self.line, self.column, self.message)
+	}
+}
+
+/// An analysis found in a policy file.
+#[derive(Debug)]
+struct FoundAnalysis {
+	name: PolicyPluginName,
+	/// Where the analysis's node is in the policy file.
+	offset: usize,
+	/// The analysis's own policy expression, if it has one.
+	policy: Option<PolicyEntry>,
+}
+
+/// A policy expression given in a policy file.
+#[derive(Debug)]
+enum PolicyEntry {
+	Valid(Expr),
+	/// The expression didn't parse, which has already been reported.
+	Invalid,
+}
+
+/// The results of checking a policy file.
+#[derive(Debug)]
+pub struct PolicyValidation {
+	/// The policy file's contents, after macros are expanded.
+	source: String,
+	analyses: Vec<FoundAnalysis>,
+	pub diagnostics: Vec<PolicyDiagnostic>,
+}
+
+impl PolicyValidation {
+	/// Load the policy file at `path` and check it.
+	pub fn load_from(path: &Path) -> Result<PolicyValidation> {
+		file::exists(path)?;
+		let raw_data = file::read_string(path)?;
+		// Macros expand within a single line, so line numbers still match the
+		// original file.
+		let source = macros::preprocess_policy_file(raw_data.as_str(), path)?;
+		Ok(PolicyValidation::new(source))
+	}
+
+	/// Check the contents of a policy file, after macros are expanded.
+	pub fn new(source: String) -> PolicyValidation {
+		let mut validation = PolicyValidation {
+			source,
+			analyses: Vec::new(),
+			diagnostics: Vec::new(),
+		};
+		validation.check_document();
+		validation
+	}
+
+	pub fn is_valid(&self) -> bool {
+		self.diagnostics.is_empty()
+	}
+
+	/// Check each analysis's policy expression against the output of its
+	/// plugin's default query, using the plugins running in `core`.
+	///
+	/// Analyses without a policy expression are checked with their plugin's
+	/// default policy expression.
+	pub fn check_plugin_outputs(&mut self, core: &HcPluginCore) {
+		let mut problems = Vec::new();
+
+		for analysis in &self.analyses {
+			let name = analysis.name.to_string();
+			let Some(plugin) = core.plugins.get(&name) else {
+				problems.push((analysis.offset, format!("plugin {} was not started", name)));
+				continue;
+			};
+
+			let policy = match &analysis.policy {
+				Some(PolicyEntry::Valid(expr)) => expr.clone(),
+				Some(PolicyEntry::Invalid) => continue,
+				None => match plugin.get_default_policy_expr() {
+					Some(expr) => expr.clone(),
+					None => {
+						problems.push((
+							analysis.offset,
+							format!(
+								"plugin {} has no default policy, so the analysis needs a `policy`",
+								name
+							),
+						));
+						continue;
+					}
+				},
+			};
+
+			let Some(schema) = plugin.get_schema(DEFAULT_QUERY) else {
+				problems.push((
+					analysis.offset,
+					format!(
+						"plugin {} has no default query, so it can't be used in an analysis",
+						name
+					),
+				));
+				continue;
+			};
+
+			if let Err(e) = check_against_schema(policy, &schema.output_schema) {
+				problems.push((
+					analysis.offset,
+					format!("policy for {} doesn't fit the plugin's output: {}", name, e),
+				));
+			}
+		}
+
+		for (offset, message) in problems {
+			self.report(offset, message);
+		}
+	}
+
+	/// Record a problem at a byte offset into the policy file.
+	fn report(&mut self, offset: usize, message: impl Into<String>) {
+		let before = self.source.get(..offset).unwrap_or(&self.source);
+		let line = before.matches('\n').count() + 1;
+		let column = before
+			.rsplit('\n')
+			.next()
+			.unwrap_or_default()
+			.chars()
+			.count() + 1;
+		self.diagnostics.push(PolicyDiagnostic {
+			line,
+			column,
+			message: message.into(),
+		});
+	}
+
+	fn check_document(&mut self) {
+		let document = match KdlDocument::from_str(&self.source) {
+			Ok(document) => document,
+			Err(e) => {
+				if e.diagnostics.is_empty() {
+					self.report(0, "invalid KDL");
+				}
+				for diagnostic in e.diagnostics {
+					let message = diagnostic
+						.message
+						.or(diagnostic.label)
+						.unwrap_or_else(|| "invalid KDL".to_owned());
+					self.report(diagnostic.span.offset(), message);
+				}
+				return;
+			}
+		};
+
+		// Plugins may be declared after they're used, so find them all first.
+		let mut declared = HashSet::new();
+		let mut has_plugins = false;
+		for node in document.nodes() {
+			if node.name().value() == "plugins" {
+				has_plugins = true;
+				self.check_plugins(node, &mut declared);
+			}
+		}
+		if !has_plugins {
+			self.report(0, "policy file has no `plugins` section");
+		}
+
+		let mut has_analyze = false;
+		for node in document.nodes() {
+			match node.name().value() {
+				"plugins" => {}
+				"patch" => self.check_patch(node),
+				"limits" => self.check_limits(node),
+				"waivers" => self.check_waivers(node, &declared),
+				"analyze" => {
+					has_analyze = true;
+					self.check_analyze(node, &declared);
+				}
+				other => self.report(
+					node_offset(node),
+					format!(
+						"unknown section `{}`, expected `plugins`, `patch`, `limits`, `waivers` or `analyze`",
+						other
+					),
+				),
+			}
+		}
+		if !has_analyze {
+			self.report(0, "policy file has no `analyze` section");
+		}
+
+		// Anything still rejected by the policy file parser is a problem the
+		// checks above don't know about yet.
+		if self.is_valid() {
+			if let Err(e) = PolicyFile::from_str(&self.source) {
+				self.report(0, e.to_string());
+			}
+		}
+	}
+
+	fn check_plugins(&mut self, node: &KdlNode, declared: &mut HashSet<PolicyPluginName>) {
+		for child in child_nodes(node) {
+			if child.name().value() != "plugin" {
+				self.report_unexpected(child, "plugins");
+				continue;
+			}
+			let Some(name) = self.plugin_name(child) else {
+				continue;
+			};
+			if child.get("version").and_then(|v| v.as_string()).is_none() {
+				self.report(
+					node_offset(child),
+					format!("plugin {} needs a `version`", name),
+				);
+			}
+			if !declared.insert(name.clone()) {
+				self.report(
+					node_offset(child),
+					format!("plugin {} is listed more than once", name),
+				);
+			}
+		}
+	}
+
+	fn check_patch(&mut self, node: &KdlNode) {
+		for child in child_nodes(node) {
+			if child.name().value() != "plugin" {
+				self.report_unexpected(child, "patch");
+				continue;
+			}
+			self.plugin_name(child);
+		}
+	}
+
+	fn check_limits(&mut self, node: &KdlNode) {
+		for child in child_nodes(node) {
+			if child.name().value() != "max-repo-size" {
+				self.report_unexpected(child, "limits");
+				continue;
+			}
+			match child.entries().first() {
+				Some(entry) => match entry.value().as_string().map(parse_size) {
+					Some(Ok(_)) => {}
+					Some(Err(e)) => self.report(entry_offset(&self.source, entry), e.to_string()),
+					None => self.report(
+						entry_offset(&self.source, entry),
+						"`max-repo-size` must be a string, like \"2GB\"",
+					),
+				},
+				None => self.report(node_offset(child), "`max-repo-size` needs a size"),
+			}
+			if let Some(entry) = child.entry("action") {
+				let action = entry.value().as_string().unwrap_or_default();
+				if let Err(e) = RepoSizeAction::from_str(action) {
+					self.report(entry_offset(&self.source, entry), e.to_string());
+				}
+			}
+		}
+	}
+
+	fn check_waivers(&mut self, node: &KdlNode, declared: &HashSet<PolicyPluginName>) {
+		for child in child_nodes(node) {
+			if child.name().value() != "waive" {
+				self.report_unexpected(child, "waivers");
+				continue;
+			}
+			if let Some(name) = self.plugin_name(child) {
+				if !declared.contains(&name) {
+					self.report(
+						node_offset(child),
+						format!("plugin {} is not listed in `plugins`", name),
+					);
+				}
+			}
+			let has_identifier = child
+				.entries()
+				.iter()
+				.filter(|entry| entry.name().is_none())
+				.nth(1)
+				.and_then(|entry| entry.value().as_string())
+				.is_some();
+			if !has_identifier {
+				self.report(
+					node_offset(child),
+					"`waive` needs what the waived concerns mention, like a file path or commit hash",
+				);
+			}
+			if child
+				.get("justification")
+				.and_then(|v| v.as_string())
+				.is_none_or(|justification| justification.trim().is_empty())
+			{
+				self.report(node_offset(child), "`waive` needs a `justification`");
+			}
+			match child.entry("expires") {
+				Some(entry) => {
+					let expires = entry.value().as_string().unwrap_or_default();
+					if let Err(e) = parse_date(expires) {
+						self.report(entry_offset(&self.source, entry), e.to_string());
+					}
+				}
+				None => self.report(
+					node_offset(child),
+					"`waive` needs an `expires` date, like \"2026-12-31\"",
+				),
+			}
+		}
+	}
+
+	fn check_analyze(&mut self, node: &KdlNode, declared: &HashSet<PolicyPluginName>) {
+		let mut has_investigate = false;
+
+		for child in child_nodes(node) {
+			match child.name().value() {
+				"investigate" => {
+					has_investigate = true;
+					self.check_score_policy(child);
+				}
+				"use-with-conditions" => self.check_score_policy(child),
+				"investigate-if-fail" => {
+					for entry in child.entries() {
+						let offset = entry_offset(&self.source, entry);
+						match entry.value().as_string().map(PolicyPluginName::new) {
+							Some(Ok(name)) if !declared.contains(&name) => self.report(
+								offset,
+								format!("plugin {} is not listed in `plugins`", name),
+							),
+							Some(Ok(_)) => {}
+							Some(Err(e)) => self.report(offset, e.to_string()),
+							None => self.report(
+								offset,
+								"expected a plugin name, as \"<PUBLISHER>/<NAME>\"",
+							),
+						}
+					}
+				}
+				// Recommendation text is free-form, so there's nothing to check.
+				"recommendation" => {}
+				"category" => self.check_category(child, declared),
+				_ => self.report_unexpected(child, "analyze"),
+			}
+		}
+
+		if !has_investigate {
+			self.report(node_offset(node), "`analyze` needs an `investigate` policy");
+		}
+	}
+
+	fn check_category(&mut self, node: &KdlNode, declared: &HashSet<PolicyPluginName>) {
+		if node
+			.entries()
+			.first()
+			.and_then(|e| e.value().as_string())
+			.is_none()
+		{
+			self.report(node_offset(node), "`category` needs a name");
+		}
+		self.check_integer(node, "weight");
+
+		if child_nodes(node).is_empty() {
+			self.report(node_offset(node), "category has no analyses");
+		}
+
+		for child in child_nodes(node) {
+			match child.name().value() {
+				"analysis" => self.check_analysis(child, declared),
+				"category" => self.check_category(child, declared),
+				_ => self.report_unexpected(child, "category"),
+			}
+		}
+	}
+
+	fn check_analysis(&mut self, node: &KdlNode, declared: &HashSet<PolicyPluginName>) {
+		let offset = node_offset(node);
+		let name = self.plugin_name(node);
+
+		if let Some(name) = &name {
+			if !declared.contains(name) {
+				self.report(
+					offset,
+					format!("plugin {} is not listed in `plugins`", name),
+				);
+			}
+		}
+
+		self.check_integer(node, "weight");
+		self.check_integer(node, "concern-limit");
+
+		if let Some(entry) = node.entry("risk-categories") {
+			match entry.value().as_string() {
+				Some(categories) => {
+					for category in categories.split(',') {
+						if let Err(e) = RiskCategory::from_str(category.trim()) {
+							self.report(entry_offset(&self.source, entry), e.to_string());
+						}
+					}
+				}
+				None => self.report(
+					entry_offset(&self.source, entry),
+					"`risk-categories` must be a string",
+				),
+			}
+		}
+
+		let policy = node.entry("policy").map(|entry| self.parse_policy(entry));
+
+		if let Some(name) = name {
+			self.analyses.push(FoundAnalysis {
+				name,
+				offset,
+				policy,
+			});
+		}
+	}
+
+	/// Check the `investigate` or `use-with-conditions` policy, which are run
+	/// with the risk score.
+	fn check_score_policy(&mut self, node: &KdlNode) {
+		let Some(entry) = node.entries().first() else {
+			self.report(
+				node_offset(node),
+				format!("`{}` needs a policy expression", node.name().value()),
+			);
+			return;
+		};
+		if let PolicyEntry::Valid(expr) = self.parse_policy(entry) {
+			if let Err(e) = std_type_check(expr, &json!(0.0)) {
+				self.report(
+					entry_offset(&self.source, entry),
+					format!("invalid policy expression: {}", e),
+				);
+			}
+		}
+	}
+
+	fn parse_policy(&mut self, entry: &KdlEntry) -> PolicyEntry {
+		let offset = entry_offset(&self.source, entry);
+		let Some(raw) = entry.value().as_string() else {
+			self.report(offset, "policy expression must be a string");
+			return PolicyEntry::Invalid;
+		};
+		match std_parse(raw) {
+			Ok(expr) => PolicyEntry::Valid(expr),
+			Err(e) => {
+				self.report(offset, format!("invalid policy expression: {}", e));
+				PolicyEntry::Invalid
+			}
+		}
+	}
+
+	/// Get the plugin name given as the first argument of a node.
+	fn plugin_name(&mut self, node: &KdlNode) -> Option<PolicyPluginName> {
+		let Some(raw) = node.entries().first().and_then(|e| e.value().as_string()) else {
+			self.report(
+				node_offset(node),
+				format!(
+					"`{}` needs a plugin name, as \"<PUBLISHER>/<NAME>\"",
+					node.name().value()
+				),
+			);
+			return None;
+		};
+		match PolicyPluginName::new(raw) {
+			Ok(name) => Some(name),
+			Err(e) => {
+				self.report(node_offset(node), e.to_string());
+				None
+			}
+		}
+	}
+
+	fn check_integer(&mut self, node: &KdlNode, key: &str) {
+		if let Some(entry) = node.entry(key) {
+			if entry.value().as_integer().is_none() {
+				self.report(
+					entry_offset(&self.source, entry),
+					format!("`{}` must be an integer", key),
+				);
+			}
+		}
+	}
+
+	fn report_unexpected(&mut self, node: &KdlNode, section: &str) {
+		self.report(
+			node_offset(node),
+			format!("unexpected `{}` in `{}`", node.name().value(), section),
+		);
+	}
+}
+
+fn child_nodes(node: &KdlNode) -> &[KdlNode] {
+	node.children().map(|c| c.nodes()).unwrap_or_default()
+}
+
+fn node_offset(node: &KdlNode) -> usize {
+	node.name().span().offset()
+}
+
+/// Get where an entry starts, skipping the whitespace before it.
+fn entry_offset(source: &str, entry: &KdlEntry) -> usize {
+	let start = entry.span().offset();
+	let leading = source
+		.get(start..)
+		.map(|rest| rest.len() - rest.trim_start().len())
+		.unwrap_or_default();
+	start + leading
+}
+
+/// How a sample output shows strings whose format the schema doesn't give.
+///
+/// Strings can only be used in policy expressions as datetimes or spans.
+#[derive(Debug, Clone, Copy)]
+enum StringSample {
+	DateTime,
+	Span,
+}
+
+/// Type-check a policy expression against a sample of a query's output built
+/// from its JSON schema.
+fn check_against_schema(expr: Expr, schema: &Value) -> policy_exprs::Result<()> {
+	let mut first_error = None;
+
+	for strings in [StringSample::DateTime, StringSample::Span] {
+		let Some(sample) = sample_from_schema(schema, schema, strings, 0) else {
+			tracing::warn!("output schema is too complex to check policy expressions against");
+			return Ok(());
+		};
+		match std_type_check(expr.clone(), &sample) {
+			Ok(()) => return Ok(()),
+			Err(e) => {
+				first_error.get_or_insert(e);
+			}
+		}
+	}
+
+	first_error.map_or(Ok(()), Err)
+}
+
+/// Build a value matching a JSON schema, or `None` if the schema can't be
+/// followed.
+fn sample_from_schema(
+	schema: &Value,
+	root: &Value,
+	strings: StringSample,
+	depth: usize,
+) -> Option<Value> {
+	if depth > MAX_SCHEMA_DEPTH {
+		return None;
+	}
+	let sample = |schema: &Value| sample_from_schema(schema, root, strings, depth + 1);
+
+	if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+		return sample(root.pointer(reference.strip_prefix('#')?)?);
+	}
+
+	// `Option`s and enums are a choice of schemas; any non-null one will do.
+	for key in ["anyOf", "oneOf", "allOf"] {
+		if let Some(choices) = schema.get(key).and_then(Value::as_array) {
+			return choices
+				.iter()
+				.find(|choice| choice.get("type").and_then(Value::as_str) != Some("null"))
+				.and_then(sample);
+		}
+	}
+
+	let ty = match schema.get("type") {
+		Some(Value::String(ty)) => ty.as_str(),
+		Some(Value::Array(types)) => types
+			.iter()
+			.filter_map(Value::as_str)
+			.find(|ty| *ty != "null")?,
+		_ if schema.get("properties").is_some() => "object",
+		_ => return None,
+	};
+
+	match ty {
+		"boolean" => Some(json!(false)),
+		"integer" | "number" => Some(json!(0)),
+		"string" => Some(json!(match schema.get("format").and_then(Value::as_str) {
+			Some("date-time") => "2000-01-01T00:00:00Z",
+			Some("duration") => "PT0S",
+			_ => match strings {
+				StringSample::DateTime => "2000-01-01T00:00:00Z",
+				StringSample::Span => "PT0S",
+			},
+		})),
+		"array" => match schema.get("items") {
+			Some(Value::Array(items)) => items.iter().map(sample).collect(),
+			Some(items) => Some(json!([sample(items)?])),
+			None => Some(json!([])),
+		},
+		"object" => {
+			let mut object = serde_json::Map::new();
+			if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+				for (key, property) in properties {
+					object.insert(key.clone(), sample(property)?);
+				}
+			}
+			Some(Value::Object(object))
+		}
+		"null" => Some(Value::Null),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn validate(source: &str) -> Vec<String> {
+		PolicyValidation::new(source.to_owned())
+			.diagnostics
+			.iter()
+			.map(ToString::to_string)
+			.collect()
+	}
+
+	#[test]
+	fn test_valid_policy() {
+		let source = r#"
+plugins {
+	plugin "mitre/activity" version="0.1.0" manifest="https://example.com/activity.kdl"
+}
+analyze {
+	investigate policy="(gt 0.5 $)"
+	category "practices" {
+		analysis "mitre/activity" policy="(lte $ P52w)" weight=3
+	}
+}
+"#;
+		assert!(validate(source).is_empty());
+	}
+
+	#[test]
+	fn test_kdl_error_position() {
+		let diagnostics = validate("plugins {\n\tplugin \"mitre/activity\n}\n");
+		assert!(!diagnostics.is_empty());
+		assert!(diagnostics[0].starts_with("2:"));
+	}
+
+	#[test]
+	fn test_reports_problems_with_positions() {
+		let source = r#"plugins {
+	plugin "mitre/activity" version="0.1.0"
+}
+analyze {
+	investigate policy="(gt 0.5 $)"
+	category "practices" {
+		analysis "mitre/activity" policy="(lte $" weight="heavy"
+		analysis "mitre/typo"
+	}
+}
+"#;
+		let diagnostics = validate(source);
+		assert_eq!(diagnostics.len(), 3, "{:?}", diagnostics);
+		assert!(diagnostics[0].starts_with("7:29: invalid policy expression"));
+		assert_eq!(diagnostics[1], "7:45: `weight` must be an integer");
+		assert_eq!(
+			diagnostics[2],
+			"8:3: plugin mitre/typo is not listed in `plugins`"
+		);
+	}
+
+	#[test]
+	fn test_score_policy_must_return_bool() {
+		let source = r#"
+plugins {
+	plugin "mitre/activity" version="0.1.0"
+}
+analyze {
+	investigate policy="(add 1 $)"
+	category "practices" {
+		analysis "mitre/activity"
+	}
+}
+"#;
+		let diagnostics = validate(source);
+		assert_eq!(diagnostics.len(), 1);
+		assert!(diagnostics[0].starts_with("6:14: invalid policy expression"));
+	}
+
+	#[test]
+	fn test_checks_waivers() {
+		let source = r#"plugins {
+	plugin "mitre/activity" version="0.1.0"
+}
+waivers {
+	waive "mitre/activity" "1a2b3c4" expires="soon"
+}
+analyze {
+	investigate policy="(gt 0.5 $)"
+	analysis "mitre/activity"
+}
+"#;
+		let diagnostics = validate(source);
+		assert_eq!(
+			diagnostics,
+			vec![
+				"5:2: `waive` needs a `justification`",
+				"5:35: invalid date 'soon', expected YYYY-MM-DD",
+			]
+		);
+	}
+
+	#[test]
+	fn test_check_against_schema() {
+		let schema = json!({
+			"type": "array",
+			"items": { "$ref": "#/definitions/Commit" },
+			"definitions": {
+				"Commit": {
+					"type": "object",
+					"properties": {
+						"lines": { "type": "integer" },
+						"date": { "type": "string", "format": "date-time" }
+					}
+				}
+			}
+		});
+
+		let policy = |raw: &str| std_parse(raw).unwrap();
+		assert!(check_against_schema(policy("(lte (max $/*/lines) 100)"), &schema).is_ok());
+		assert!(check_against_schema(policy("(lte (max $/*/lines) P1w)"), &schema).is_err());
+		assert!(check_against_schema(policy("(lte (count $/*/missing) 1)"), &schema).is_err());
+
+		// Strings without a format may be either datetimes or spans.
+		let span = json!({ "type": "string" });
+		assert!(check_against_schema(policy("(lte $ P52w)"), &span).is_ok());
+	}
+}
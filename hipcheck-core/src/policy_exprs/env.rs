@@ -15,6 +15,9 @@ use std::{cmp::Ordering, collections::HashMap, ops::Not as _};
 use Expr::*;
 use Primitive::*;
 
+/// The number of seconds in a week of 7 24-hour days.
+const SECONDS_PER_WEEK: f64 = 7.0 * 24.0 * 60.0 * 60.0;
+
 /// Environment, containing bindings of names to functions and variables.
 pub struct Env<'parent> {
 	/// Map of bindings,.
@@ -278,7 +281,8 @@ fn ty_avg(args: &[Type]) -> Result<ReturnableType> {
 	}
 }
 
-fn ty_duration(args: &[Type]) -> Result<ReturnableType> {
+// Expects args to contain two datetimes
+fn expect_datetimes(args: &[Type]) -> Result<()> {
 	use PrimitiveType::*;
 	let opt_ty_1 = expect_primitive_at(args, 0)?;
 	let opt_ty_2 = expect_primitive_at(args, 1)?;
@@ -304,9 +308,24 @@ fn ty_duration(args: &[Type]) -> Result<ReturnableType> {
 			});
 		}
 	}
+	Ok(())
+}
+
+fn ty_duration(args: &[Type]) -> Result<ReturnableType> {
+	expect_datetimes(args)?;
 	Ok(PrimitiveType::Span.into())
 }
 
+fn ty_duration_weeks(args: &[Type]) -> Result<ReturnableType> {
+	expect_datetimes(args)?;
+	Ok(PrimitiveType::Float.into())
+}
+
+fn ty_datetime_comp(args: &[Type]) -> Result<ReturnableType> {
+	expect_datetimes(args)?;
+	Ok(PrimitiveType::Bool.into())
+}
+
 fn ty_bool_unary(args: &[Type]) -> Result<ReturnableType> {
 	use PrimitiveType::*;
 	match expect_primitive_at(args, 0)? {
@@ -377,6 +396,11 @@ impl Env<'_> {
 
 		// Additional datetime math functions
 		env.add_fn("duration", duration, 2, ty_duration);
+		env.add_fn("duration-weeks", duration_weeks, 2, ty_duration_weeks);
+
+		// Datetime comparison functions.
+		env.add_fn("before", before, 2, ty_datetime_comp);
+		env.add_fn("after", after, 2, ty_datetime_comp);
 
 		// Logical functions.
 		env.add_fn("and", and, 2, ty_bool_binary);
@@ -880,6 +904,44 @@ fn duration(env: &Env, args: &[Expr]) -> Result<Expr> {
 	binary_primitive_op(name, env, args, op)
 }
 
+// Finds the difference in time between two datetimes as a (possibly fractional) number of weeks,
+// treating every week as 7 24-hour days like span literals do
+fn duration_weeks(env: &Env, args: &[Expr]) -> Result<Expr> {
+	let name = "duration-weeks";
+
+	let op = |arg_1, arg_2| match (arg_1, arg_2) {
+		(DateTime(arg_1), DateTime(arg_2)) => {
+			let seconds = arg_1.timestamp().as_second() - arg_2.timestamp().as_second();
+			Ok(Float(F64::new(seconds as f64 / SECONDS_PER_WEEK)?))
+		}
+		(_, _) => Err(Error::BadType(name)),
+	};
+
+	binary_primitive_op(name, env, args, op)
+}
+
+fn before(env: &Env, args: &[Expr]) -> Result<Expr> {
+	let name = "before";
+
+	let op = |arg_1, arg_2| match (arg_1, arg_2) {
+		(DateTime(arg_1), DateTime(arg_2)) => Ok(Bool(arg_1 < arg_2)),
+		(_, _) => Err(Error::BadType(name)),
+	};
+
+	binary_primitive_op(name, env, args, op)
+}
+
+fn after(env: &Env, args: &[Expr]) -> Result<Expr> {
+	let name = "after";
+
+	let op = |arg_1, arg_2| match (arg_1, arg_2) {
+		(DateTime(arg_1), DateTime(arg_2)) => Ok(Bool(arg_1 > arg_2)),
+		(_, _) => Err(Error::BadType(name)),
+	};
+
+	binary_primitive_op(name, env, args, op)
+}
+
 fn and(env: &Env, args: &[Expr]) -> Result<Expr> {
 	let name = "and";
 
@@ -1348,6 +1410,6 @@ fn dbg(env: &Env, args: &[Expr]) -> Result<Expr> {
 	check_num_args(name, args, 1)?;
 	let arg = &args[0];
 	let result = env.visit_expr(arg.clone())?;
-	log::debug!("{arg} = {result}");
+	tracing::debug!("{arg} = {result}");
 	Ok(result)
 }
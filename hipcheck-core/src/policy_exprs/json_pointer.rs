@@ -3,9 +3,8 @@
 use crate::policy_exprs::{
 	error::{self, Error, JiffError, Result},
 	expr::{Array, Expr, Primitive},
-	ExprMutator, JsonPointer, LexingError,
+	token, ExprMutator, JsonPointer, LexingError,
 };
-use jiff::{tz::TimeZone, Timestamp, Zoned};
 use ordered_float::NotNan;
 use serde_json::Value;
 
@@ -26,14 +25,18 @@ impl ExprMutator for LookupJsonPointers<'_> {
 		let pointer = &jp.pointer;
 		let context = self.context;
 		let val = lookup_json_pointer(pointer, context)?;
-		let expr = json_to_policy_expr(val, pointer, context)?;
+		let expr = json_to_policy_expr(&val, pointer, context)?;
 		jp.value = Some(Box::new(expr));
 		Ok(jp.into())
 	}
 }
 
-/// Wrap serde_json's `Value::pointer` method to provide better error handling.
-fn lookup_json_pointer<'val>(pointer: &str, context: &'val Value) -> Result<&'val Value> {
+/// The pointer segment which selects every element of an array.
+const WILDCARD_SEGMENT: char = '*';
+
+/// Wrap serde_json's `Value::pointer` method to provide better error handling
+/// and support for wildcard segments.
+fn lookup_json_pointer(pointer: &str, context: &Value) -> Result<Value> {
 	// serde_json's JSON Pointer implementation does not distinguish between
 	// syntax errors and lookup errors, so we check the syntax ourselves.
 	// The only syntax error that serde_json currently recognizes is that a
@@ -45,7 +48,7 @@ fn lookup_json_pointer<'val>(pointer: &str, context: &'val Value) -> Result<&'va
 			});
 		}
 	}
-	match context.pointer(pointer) {
+	match select(pointer, context) {
 		Some(val) => Ok(val),
 		None => Err(Error::JSONPointerLookupFailed {
 			pointer: pointer.to_owned(),
@@ -54,6 +57,50 @@ fn lookup_json_pointer<'val>(pointer: &str, context: &'val Value) -> Result<&'va
 	}
 }
 
+/// Look up a pointer which may contain wildcard segments.
+///
+/// A `*` segment applied to an array selects the rest of the pointer from
+/// every element of the array, producing an array of the results. Results of
+/// nested wildcards are flattened into a single array. Applied to an object, a
+/// `*` segment is an ordinary field name, as in standard JSON Pointers.
+fn select(pointer: &str, value: &Value) -> Option<Value> {
+	let mut search_from = 0;
+
+	while let Some(idx) = find_wildcard(pointer, search_from) {
+		let (prefix, rest) = (&pointer[..idx], &pointer[idx + 2..]);
+
+		if let Value::Array(items) = value.pointer(prefix)? {
+			let flatten = find_wildcard(rest, 0).is_some();
+			let mut selected = Vec::with_capacity(items.len());
+
+			for item in items {
+				match select(rest, item)? {
+					Value::Array(inner) if flatten => selected.extend(inner),
+					val => selected.push(val),
+				}
+			}
+
+			return Some(Value::Array(selected));
+		}
+
+		search_from = idx + 2;
+	}
+
+	value.pointer(pointer).cloned()
+}
+
+/// Find the index of the '/' beginning the first wildcard segment of a pointer
+/// at or after `from`.
+fn find_wildcard(pointer: &str, from: usize) -> Option<usize> {
+	pointer[from..]
+		.match_indices('/')
+		.map(|(idx, _)| from + idx)
+		.find(|&idx| {
+			let mut segment = pointer[idx + 1..].chars();
+			segment.next() == Some(WILDCARD_SEGMENT) && matches!(segment.next(), None | Some('/'))
+		})
+}
+
 /// Attempt to interpret a JSON Value as a Policy Expression.
 /// `pointer` and `context` are only passed in to provide more context in the
 /// case of errors.
@@ -107,14 +154,9 @@ fn json_to_policy_expr(val: &Value, pointer: &str, context: &Value) -> Result<Ex
 	}
 }
 
+// Datetimes from plugins are parsed the same way as datetime literals
 fn parse_datetime(s: &str) -> Result<jiff::Zoned> {
-	let ts: Timestamp = s.parse().map_err(|jiff_error| {
-		Error::Lex(LexingError::InvalidDatetime(
-			s.to_owned(),
-			JiffError::new(jiff_error),
-		))
-	})?;
-	Ok(Zoned::new(ts, TimeZone::UTC))
+	token::parse_datetime(s).map_err(Error::Lex)
 }
 
 fn parse_span(s: &str) -> Result<jiff::Span> {
@@ -148,6 +190,7 @@ mod tests {
 	use super::*;
 	use crate::policy_exprs::expr::json_ptr;
 	use crate::policy_exprs::F64;
+	use jiff::{tz::TimeZone, Zoned};
 	use test_log::test;
 
 	#[test]
@@ -161,7 +204,22 @@ mod tests {
 		let expected = Primitive::DateTime(zo).into();
 
 		let val = lookup_json_pointer(pointer, &context).unwrap();
-		let result = json_to_policy_expr(val, pointer, &context);
+		let result = json_to_policy_expr(&val, pointer, &context);
+		assert_eq!(result, Ok(expected));
+	}
+
+	#[test]
+	fn json_date() {
+		let pointer = "/released";
+		let context = serde_json::json!({
+			"released": "2001-02-03"
+		});
+		let ts = "2001-02-03T00:00:00+00:00".parse().unwrap();
+		let zo = Zoned::new(ts, TimeZone::UTC);
+		let expected = Primitive::DateTime(zo).into();
+
+		let val = lookup_json_pointer(pointer, &context).unwrap();
+		let result = json_to_policy_expr(&val, pointer, &context);
 		assert_eq!(result, Ok(expected));
 	}
 
@@ -175,7 +233,7 @@ mod tests {
 		let expected = Primitive::Span(span).into();
 
 		let val = lookup_json_pointer(pointer, &context).unwrap();
-		let result = json_to_policy_expr(val, pointer, &context);
+		let result = json_to_policy_expr(&val, pointer, &context);
 		assert_eq!(result, Ok(expected));
 	}
 
@@ -286,4 +344,47 @@ mod tests {
 			})
 		);
 	}
+
+	#[test]
+	fn wildcard_selects_from_each_element() {
+		let context = serde_json::json!({
+			"contributors": [
+				{ "name": "a", "commits": { "count": 3 } },
+				{ "name": "b", "commits": { "count": 5 } },
+			]
+		});
+		let result = lookup_json_pointer("/contributors/*/commits/count", &context);
+		assert_eq!(result, Ok(serde_json::json!([3, 5])));
+	}
+
+	#[test]
+	fn nested_wildcards_are_flattened() {
+		let context = serde_json::json!([
+			{ "files": [{ "added": 1 }, { "added": 2 }] },
+			{ "files": [{ "added": 3 }] },
+		]);
+		let result = lookup_json_pointer("/*/files/*/added", &context);
+		assert_eq!(result, Ok(serde_json::json!([1, 2, 3])));
+	}
+
+	#[test]
+	fn wildcard_is_a_field_name_in_objects() {
+		let context = serde_json::json!({ "*": { "x": true } });
+		let result = lookup_json_pointer("/*/x", &context);
+		assert_eq!(result, Ok(serde_json::json!(true)));
+	}
+
+	#[test]
+	fn error_wildcard_lookup_failed() {
+		let expr = json_ptr("/*/count");
+		let context = serde_json::json!([{ "count": 1 }, { "total": 2 }]);
+		let result = LookupJsonPointers::with_context(&context).visit_expr(expr);
+		assert_eq!(
+			result,
+			Err(Error::JSONPointerLookupFailed {
+				pointer: "/*/count".into(),
+				context
+			})
+		);
+	}
 }
@@ -7,6 +7,7 @@ pub mod expr;
 mod json_pointer;
 mod pass;
 mod token;
+mod trace;
 
 use crate::policy_exprs::env::Env;
 pub(crate) use crate::policy_exprs::{bridge::Tokens, expr::F64};
@@ -15,9 +16,11 @@ pub use crate::policy_exprs::{
 	expr::{Array, Expr, Function, Ident, JsonPointer, Lambda},
 	pass::{ExprMutator, ExprVisitor, FunctionResolver, TypeChecker, TypeFixer},
 	token::LexingError,
+	trace::{std_trace, Step, Trace},
 };
 use env::Binding;
 pub use expr::{parse, Primitive};
+use expr::{PrimitiveType, ReturnableType};
 use json_pointer::LookupJsonPointers;
 use serde_json::Value;
 use std::{ops::Deref, str::FromStr, sync::LazyLock};
@@ -66,6 +69,19 @@ pub fn std_exec(expr: Expr, context: Option<&Value>) -> Result<bool> {
 	}
 }
 
+/// Type-check a parsed expression as if it were run with `context`, without
+/// evaluating it.
+///
+/// Fails if a JSON Pointer can't be looked up in `context`, or if the
+/// expression doesn't return a boolean.
+pub fn std_type_check(expr: Expr, context: &Value) -> Result<()> {
+	let expr = LookupJsonPointers::with_context(context).run(expr)?;
+	match PASS_STD_TYPE_CHK.run(&expr)?.get_return_type()? {
+		ReturnableType::Primitive(PrimitiveType::Bool) | ReturnableType::Unknown => Ok(()),
+		_ => Err(Error::DidNotReturnBool(expr)),
+	}
+}
+
 impl FromStr for Expr {
 	type Err = crate::policy_exprs::error::Error;
 
@@ -192,6 +208,16 @@ mod tests {
 		assert!(is_true);
 	}
 
+	#[test]
+	fn run_jsonptr_wildcard() {
+		let program = "(all (lt 10) $/contributors/*/commits)";
+		let context = serde_json::json!({
+			"contributors": [{ "commits": 3 }, { "commits": 5 }]
+		});
+		let is_true = Executor::std().run(program, &context).unwrap();
+		assert!(is_true);
+	}
+
 	#[test]
 	fn run_basic() {
 		let program = "(eq (add 1 2) 3)";
@@ -346,6 +372,66 @@ mod tests {
 		assert_eq!(expected, result2);
 	}
 
+	#[test]
+	fn eval_datetime_before_after() {
+		let program_and_expected = vec![
+			("(before 2024-09-26 2024-10-03)", true),
+			("(before 2024-10-03 2024-09-26)", false),
+			("(after 2024-10-03 2024-09-26)", true),
+			("(after 2024-09-26 2024-09-26)", false),
+			// The datetime from the JSON pointer is the first operand of the lambda
+			("(all (after 2024-01-01) $/commits/*/date)", true),
+		];
+		let context = serde_json::json!({
+			"commits": [{ "date": "2024-09-26T12:00:00Z" }, { "date": "2024-10-03" }]
+		});
+		for (program, expected) in program_and_expected.into_iter() {
+			let result = Executor::std().run(program, &context).unwrap();
+			assert_eq!(result, expected, "{program}");
+		}
+	}
+
+	#[test]
+	fn eval_duration_weeks() {
+		let program_and_expected = vec![
+			("(duration-weeks 2024-10-03 2024-09-26)", 1.0),
+			("(duration-weeks 2024-09-26 2024-10-03)", -1.0),
+			(
+				"(duration-weeks 2024-09-26T12:00:00Z 2024-09-26)",
+				1.0 / 14.0,
+			),
+		];
+		let context = Value::Null;
+		for (program, expected) in program_and_expected.into_iter() {
+			let result = Executor::std().parse_and_eval(program, &context).unwrap();
+			assert_eq!(
+				result,
+				Expr::Primitive(Primitive::Float(F64::new(expected).unwrap())),
+				"{program}"
+			);
+		}
+
+		let program = "(lte (duration-weeks 2024-10-03 $) 4)";
+		let context = serde_json::json!("2024-09-26");
+		assert!(Executor::std().run(program, &context).unwrap());
+	}
+
+	#[test]
+	fn type_datetime_comp_bad_arg() {
+		let program = "(before 2024-09-26 P1w)";
+		let expr = parse(program).unwrap();
+		let expr = FunctionResolver::std().run(expr).unwrap();
+		let res_ty = TypeChecker::default().run(&expr);
+		assert!(matches!(
+			res_ty,
+			Err(Error::BadFuncArgType {
+				idx: 1,
+				got: Type::Primitive(PrimitiveType::Span),
+				..
+			})
+		));
+	}
+
 	#[test]
 	fn type_lambda() {
 		let program = "(gt #t)";
@@ -49,10 +49,10 @@ pub enum Token {
 	Span(Box<Span>),
 
 	// Prioritize over span regex, which starts with a 'P'
-	#[regex(r"([a-zA-Z]+)", lex_ident, priority = 10)]
+	#[regex(r"([a-zA-Z]+(-[a-zA-Z]+)*)", lex_ident, priority = 10)]
 	Ident(String),
 
-	#[regex(r"\$[/~_[:alnum:]]*", lex_json_pointer)]
+	#[regex(r"\$[/~_*[:alnum:]]*", lex_json_pointer)]
 	JSONPointer(String),
 }
 
@@ -85,23 +85,25 @@ fn lex_float(input: &mut Lexer<'_, Token>) -> Result<F64> {
 
 /// Lex a single datetime value.
 fn lex_datetime(input: &mut Lexer<'_, Token>) -> Result<Box<Zoned>> {
-	let s = input.slice();
+	parse_datetime(input.slice()).map(Box::new)
+}
+
+/// Parse a datetime with as much detail as given, as in datetime literals.
+pub(crate) fn parse_datetime(s: &str) -> Result<Zoned> {
 	// Parse to a Zoned datetime value with as much detail as given
 	// If a UTC offset is provided, convert the datetime to the equivalent UTC datetime
 	if let Ok(timestamp) = s.parse::<Timestamp>() {
-		Ok(Box::new(timestamp.to_zoned(TimeZone::UTC)))
+		Ok(timestamp.to_zoned(TimeZone::UTC))
 	// If no offset is provided, assume the time is UTC
 	} else if let Ok(dt) = s.parse::<DateTime>() {
 		dt.to_zoned(TimeZone::UTC)
 			.map_err(|err| LexingError::InvalidDatetime(s.to_string(), JiffError::new(err)))
-			.map(Box::new)
 	} else {
 		match s.parse::<Date>() {
 			// If no time is provided, treat the time as midnight UTC on the given day
 			Ok(date) => date
 				.to_zoned(TimeZone::UTC)
-				.map_err(|err| LexingError::InvalidDatetime(s.to_string(), JiffError::new(err)))
-				.map(Box::new),
+				.map_err(|err| LexingError::InvalidDatetime(s.to_string(), JiffError::new(err))),
 			// If the string provided does not parse to a valid date or datetime, return an error
 			Err(err) => Err(LexingError::InvalidDatetime(
 				s.to_string(),
@@ -304,6 +306,14 @@ mod tests {
 		assert_eq!(tokens, expected);
 	}
 
+	#[test]
+	fn basic_lexing_with_jsonptr_wildcard() {
+		let raw_program = "$/commits/*/count";
+		let expected = vec![Token::JSONPointer(String::from("/commits/*/count"))];
+		let tokens = lex(raw_program).unwrap();
+		assert_eq!(tokens, expected);
+	}
+
 	#[test]
 	fn basic_lexing_with_jsonptr_in_expr() {
 		let raw_program = "(eq 1 $/data/one)";
@@ -353,6 +363,19 @@ mod tests {
 		assert_eq!(tokens, expected);
 	}
 
+	#[test]
+	fn lexing_hyphenated_ident() {
+		let raw_program = "(duration-weeks $ 2024-09-17)";
+		let tokens = lex(raw_program).unwrap();
+		assert_eq!(tokens[1], Token::Ident(String::from("duration-weeks")));
+
+		// A hyphen followed by a number is still a negative number
+		let raw_program = "(add x -1.5)";
+		let tokens = lex(raw_program).unwrap();
+		assert_eq!(tokens[2], Token::Ident(String::from("x")));
+		assert_eq!(tokens[3], Token::Float(F64::new(-1.5).unwrap()));
+	}
+
 	// Ensure that idents with capital P are prioritized over being treated as spans
 	#[test]
 	fn regression_lex_span_and_ident() {
@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Step-by-step evaluation of policy expressions, for debugging them.
+
+use crate::policy_exprs::{
+	env::Env,
+	error::Result,
+	expr::{Expr, Function, JsonPointer, Lambda, Primitive},
+	json_pointer::LookupJsonPointers,
+	parse,
+	pass::{ExprMutator, ExprVisitor},
+	std_pre_analysis_pipeline, PASS_STD_TYPE_CHK,
+};
+use serde_json::Value;
+use std::{
+	cell::{Cell, RefCell},
+	fmt::{self, Display, Formatter},
+};
+
+/// One step in evaluating a policy expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+	/// How deeply nested the step is in the expression.
+	pub depth: usize,
+	/// The function call or JSON Pointer, as written.
+	pub expr: Expr,
+	/// The function call with its arguments evaluated, for function calls.
+	pub evaluated: Option<Expr>,
+	/// The value the step evaluated to.
+	pub result: Expr,
+}
+
+impl Display for Step {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let indent = "  ".repeat(self.depth);
+		writeln!(f, "{indent}{}", self.expr)?;
+		// Don't repeat calls which had nothing to evaluate
+		if let Some(evaluated) = self.evaluated.as_ref().filter(|e| **e != self.expr) {
+			writeln!(f, "{indent}  = {evaluated}")?;
+		}
+		write!(f, "{indent}  = {}", self.result)
+	}
+}
+
+/// The steps taken in evaluating a policy expression, and its result.
+#[derive(Debug)]
+pub struct Trace {
+	/// The steps, innermost first, in the order they were evaluated.
+	pub steps: Vec<Step>,
+	/// The result of the expression, or where evaluating it failed.
+	pub result: Result<Expr>,
+}
+
+/// Evaluate a policy expression against `context`, recording each function
+/// call and JSON Pointer lookup along the way.
+///
+/// The expression goes through the same passes it would when run by Hipcheck,
+/// so parsing and type errors are reported as the result with no steps.
+/// Lambdas applied by higher-order functions like `filter` are shown as one
+/// step, rather than a step per array element.
+pub fn std_trace(raw_program: &str, context: &Value) -> Trace {
+	let expr = match prepare(raw_program, context) {
+		Ok(expr) => expr,
+		Err(e) => {
+			return Trace {
+				steps: Vec::new(),
+				result: Err(e),
+			}
+		}
+	};
+
+	let env = Env::std();
+	let tracer = Tracer {
+		env: &env,
+		depth: Cell::new(0),
+		steps: RefCell::new(Vec::new()),
+	};
+	let result = tracer.run(expr);
+
+	Trace {
+		steps: tracer.steps.into_inner(),
+		result,
+	}
+}
+
+/// Parse, type check, and look up the JSON Pointers of an expression.
+fn prepare(raw_program: &str, context: &Value) -> Result<Expr> {
+	let expr = std_pre_analysis_pipeline(parse(raw_program)?)?;
+	let expr = LookupJsonPointers::with_context(context).run(expr)?;
+	PASS_STD_TYPE_CHK.run(&expr)?;
+	Ok(expr)
+}
+
+/// Evaluates an expression the same way `Env` does, recording the steps.
+struct Tracer<'env> {
+	env: &'env Env<'env>,
+	depth: Cell<usize>,
+	steps: RefCell<Vec<Step>>,
+}
+
+impl Tracer<'_> {
+	/// Visit the arguments of a call one level deeper than the call itself.
+	fn visit_args(&self, args: Vec<Expr>) -> Result<Vec<Expr>> {
+		self.depth.set(self.depth.get() + 1);
+		let args = args
+			.into_iter()
+			.map(|a| self.visit_expr(a))
+			.collect::<Result<Vec<Expr>>>();
+		self.depth.set(self.depth.get() - 1);
+		args
+	}
+
+	fn record(&self, expr: Expr, evaluated: Option<Expr>, result: &Expr) {
+		self.steps.borrow_mut().push(Step {
+			depth: self.depth.get(),
+			expr,
+			evaluated,
+			result: result.clone(),
+		});
+	}
+}
+
+impl ExprMutator for Tracer<'_> {
+	fn visit_primitive(&self, prim: Primitive) -> Result<Expr> {
+		self.env.visit_primitive(prim)
+	}
+
+	fn visit_function(&self, f: Function) -> Result<Expr> {
+		let expr = Expr::Function(f.clone());
+		let mut f = f;
+		f.args = self.visit_args(f.args)?;
+		let evaluated = Expr::Function(f.clone());
+		let result = self.env.visit_function(f)?;
+		self.record(expr, Some(evaluated), &result);
+		Ok(result)
+	}
+
+	fn visit_lambda(&self, mut l: Lambda) -> Result<Expr> {
+		// Like `Env`, evaluate the arguments but leave the lambda to be applied
+		let args = l
+			.body
+			.args
+			.drain(..)
+			.map(|a| match a {
+				Expr::Primitive(Primitive::Identifier(_)) => Ok(vec![a]),
+				b => self.visit_args(vec![b]),
+			})
+			.collect::<Result<Vec<_>>>()?;
+		l.body.args = args.into_iter().flatten().collect();
+		Ok(l.into())
+	}
+
+	fn visit_json_pointer(&self, jp: JsonPointer) -> Result<Expr> {
+		let expr = Expr::JsonPointer(jp.clone());
+		let result = self.env.visit_json_pointer(jp)?;
+		self.record(expr, None, &result);
+		Ok(result)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::policy_exprs::{Error, F64};
+	use test_log::test;
+
+	fn float(f: f64) -> Expr {
+		Primitive::Float(F64::new(f).unwrap()).into()
+	}
+
+	#[test]
+	fn trace_nested_calls() {
+		let context = serde_json::json!({ "a": 3, "b": 4 });
+		let trace = std_trace("(lte (divz $/a $/b) 0.8)", &context);
+
+		assert_eq!(trace.result, Ok(Primitive::Bool(true).into()));
+		let steps: Vec<_> = trace
+			.steps
+			.iter()
+			.map(|s| (s.depth, s.expr.to_string(), s.result.clone()))
+			.collect();
+		assert_eq!(
+			steps,
+			vec![
+				(2, "$/a".to_owned(), float(3.0)),
+				(2, "$/b".to_owned(), float(4.0)),
+				(1, "(divz $/a $/b)".to_owned(), float(0.75)),
+				(
+					0,
+					"(lte (divz $/a $/b) 0.8)".to_owned(),
+					Primitive::Bool(true).into()
+				),
+			]
+		);
+		assert_eq!(
+			trace.steps[2].evaluated.as_ref().map(ToString::to_string),
+			Some("(divz 3 4)".to_owned())
+		);
+	}
+
+	#[test]
+	fn trace_lambda() {
+		let context = serde_json::json!([1, 5, 10]);
+		let trace = std_trace("(count (filter (gt (add 1 2)) $))", &context);
+
+		assert_eq!(trace.result, Ok(Primitive::Int(2).into()));
+		let exprs: Vec<_> = trace.steps.iter().map(|s| s.expr.to_string()).collect();
+		assert_eq!(
+			exprs,
+			vec![
+				"(add 1 2)",
+				"$",
+				"(filter (gt (add 1 2)) $)",
+				"(count (filter (gt (add 1 2)) $))"
+			]
+		);
+	}
+
+	#[test]
+	fn trace_error() {
+		let trace = std_trace("(lte $/missing 1)", &serde_json::json!({}));
+		assert!(trace.steps.is_empty());
+		assert!(matches!(
+			trace.result,
+			Err(Error::JSONPointerLookupFailed { .. })
+		));
+	}
+}
@@ -0,0 +1,222 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reports for runs which analyze several targets, like the components of an
+//! SBOM.
+//!
+//! Each target gets its own full `Report`, and the `BatchSummary` rolls the
+//! final recommendation for every target up into one place. When the
+//! contributors to each target are known, the `ContributorRollup` shows who
+//! contributes to many of them. When only a sample of the targets was analyzed,
+//! the `SampleSummary` estimates the results for all of them. The
+//! `PortfolioSummary` gives statistics over every target which was analyzed.
+
+use crate::{
+	error::Error,
+	report::{
+		contributors::ContributorRollup, portfolio::PortfolioSummary, sample::SampleSummary,
+		ErrorReport, RecommendationKind, Report,
+	},
+	shell::Shell,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// The reports for every target of a run, plus a summary of all of them.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct BatchReport {
+	/// The report for each target which could be analyzed.
+	pub reports: Vec<Report>,
+
+	/// The outcome for every target, including ones which couldn't be analyzed.
+	pub summary: BatchSummary,
+
+	/// The people and organizations contributing to several targets, if the
+	/// contributors to the targets are known.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub contributors: Option<ContributorRollup>,
+
+	/// Estimates for all the targets, if only a sample of them was analyzed.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub sample: Option<SampleSummary>,
+
+	/// Statistics over every target which could be analyzed, if any could.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub portfolio: Option<PortfolioSummary>,
+}
+
+impl BatchReport {
+	/// Collect the results of analyzing each target, in order.
+	pub fn new(results: Vec<(String, Result<Report, Error>)>) -> BatchReport {
+		let mut reports = Vec::new();
+		let mut components = Vec::new();
+
+		for (target, result) in results {
+			match result {
+				Ok(report) => {
+					components.push(TargetSummary {
+						target,
+						outcome: TargetOutcome::Analyzed {
+							recommendation: report.recommendation.kind,
+							risk_score: report.recommendation.risk_score.0,
+						},
+					});
+					reports.push(report);
+				}
+				Err(error) => components.push(TargetSummary {
+					target,
+					outcome: TargetOutcome::Errored {
+						error: ErrorReport::from(&error),
+					},
+				}),
+			}
+		}
+
+		BatchReport {
+			reports,
+			summary: BatchSummary {
+				targets: components,
+			},
+			contributors: None,
+			sample: None,
+			portfolio: None,
+		}
+	}
+
+	/// Get each target which could be analyzed, with its report, in order.
+	pub fn analyzed(&self) -> impl Iterator<Item = (&str, &Report)> {
+		let analyzed = self
+			.summary
+			.targets
+			.iter()
+			.filter(|target| matches!(target.outcome, TargetOutcome::Analyzed { .. }));
+		// Reports are kept in the order of the targets which could be analyzed
+		analyzed
+			.zip(&self.reports)
+			.map(|(target, report)| (target.target.as_str(), report))
+	}
+
+	/// Check if any target couldn't be analyzed.
+	pub fn has_errored_targets(&self) -> bool {
+		self.summary
+			.targets
+			.iter()
+			.any(|target| matches!(target.outcome, TargetOutcome::Errored { .. }))
+	}
+}
+
+/// The outcome of every target of a run.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct BatchSummary {
+	pub targets: Vec<TargetSummary>,
+}
+
+impl BatchSummary {
+	/// Count the targets with a given recommendation.
+	pub fn count(&self, kind: RecommendationKind) -> usize {
+		self.targets
+			.iter()
+			.filter(|t| t.recommendation() == Some(kind))
+			.count()
+	}
+
+	/// Count the targets which couldn't be analyzed.
+	pub fn errored_count(&self) -> usize {
+		self.targets
+			.iter()
+			.filter(|t| t.recommendation().is_none())
+			.count()
+	}
+
+	/// Describe how many targets got each recommendation.
+	pub fn statement(&self) -> String {
+		let mut statement = format!(
+			"{} targets: {} pass, {} use with conditions, {} investigate",
+			self.targets.len(),
+			self.count(RecommendationKind::Pass),
+			self.count(RecommendationKind::UseWithConditions),
+			self.count(RecommendationKind::Investigate),
+		);
+
+		let errored = self.errored_count();
+		if errored > 0 {
+			statement.push_str(&format!(", {} errored", errored));
+		}
+
+		statement
+	}
+}
+
+/// The outcome of a single target of a run.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct TargetSummary {
+	/// The target, as given in the SBOM.
+	pub target: String,
+
+	#[serde(flatten)]
+	pub outcome: TargetOutcome,
+}
+
+impl TargetSummary {
+	/// Get the final recommendation for the target, if it could be analyzed.
+	pub fn recommendation(&self) -> Option<RecommendationKind> {
+		match &self.outcome {
+			TargetOutcome::Analyzed { recommendation, .. } => Some(*recommendation),
+			TargetOutcome::Errored { .. } => None,
+		}
+	}
+
+	pub fn statement(&self) -> String {
+		match &self.outcome {
+			TargetOutcome::Analyzed { risk_score, .. } => format!(
+				"{}, risk rated as {}",
+				self.target,
+				Shell::get_locale().decimal(*risk_score, 2)
+			),
+			TargetOutcome::Errored { error } => {
+				format!("{}, analysis error: {}", self.target, error.msg)
+			}
+		}
+	}
+}
+
+/// Whether a target was analyzed, and what came of it.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(untagged)]
+#[schemars(crate = "schemars")]
+pub enum TargetOutcome {
+	Analyzed {
+		recommendation: RecommendationKind,
+		risk_score: f64,
+	},
+	Errored {
+		error: ErrorReport,
+	},
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hc_error;
+
+	#[test]
+	fn errored_targets_are_summarized() {
+		let batch = BatchReport::new(vec![(
+			"https://github.com/mitre/hipcheck".to_owned(),
+			Err(hc_error!("clone failed")),
+		)]);
+
+		assert!(batch.reports.is_empty());
+		assert_eq!(batch.summary.errored_count(), 1);
+		assert_eq!(
+			batch.summary.statement(),
+			"1 targets: 0 pass, 0 use with conditions, 0 investigate, 1 errored"
+		);
+		assert_eq!(
+			batch.summary.targets[0].statement(),
+			"https://github.com/mitre/hipcheck, analysis error: clone failed"
+		);
+	}
+}
@@ -0,0 +1,247 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Concerns raised by failing analyses, with the details plugins give.
+//!
+//! Plugins send each concern as a JSON object with a `message`, and optionally
+//! a `severity`, the `file` and `line` it's about, the `commit` it was found
+//! in, and other `identifiers` of what it's about, like e-mail addresses or
+//! package names. Plugins built with older SDKs send concerns as plain text,
+//! which become concerns with only a message.
+//!
+//! When a plugin names a concern's subject, its fingerprint comes from the
+//! file, commit, and identifiers given, instead of from what the message
+//! mentions, so rewording a message never changes it.
+
+use crate::{
+	error::Result,
+	hc_error,
+	report::fingerprint::{concern_fingerprint, concern_mentions, subject_fingerprint},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::{self, Display, Formatter};
+
+/// A concern raised by a failing analysis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct Concern {
+	/// What the concern is, for people reading the report.
+	pub message: String,
+
+	/// How serious the plugin says the concern is.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub severity: Option<Severity>,
+
+	/// The file the concern is about, relative to the root of the repository.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub file: Option<String>,
+
+	/// The line of `file` the concern is about, counting from 1.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub line: Option<u64>,
+
+	/// The hash of the commit the concern is about.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub commit: Option<String>,
+
+	/// Anything else identifying what the concern is about.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub identifiers: Vec<String>,
+}
+
+/// How serious a concern is.
+#[derive(
+	Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[schemars(crate = "schemars")]
+pub enum Severity {
+	Low,
+	Medium,
+	High,
+	Critical,
+}
+
+impl Display for Severity {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let severity = match self {
+			Severity::Low => "low",
+			Severity::Medium => "medium",
+			Severity::High => "high",
+			Severity::Critical => "critical",
+		};
+		f.write_str(severity)
+	}
+}
+
+impl Concern {
+	/// Make a concern with only a message.
+	pub fn new(message: impl Into<String>) -> Concern {
+		Concern {
+			message: message.into(),
+			severity: None,
+			file: None,
+			line: None,
+			commit: None,
+			identifiers: Vec::new(),
+		}
+	}
+
+	/// Read a concern sent by a plugin.
+	pub fn from_plugin(concern: Value) -> Result<Concern> {
+		let concern = match concern {
+			Value::String(message) => Concern::new(message),
+			concern => serde_json::from_value(concern)?,
+		};
+
+		if concern.message.trim().is_empty() {
+			return Err(hc_error!("concern has no message"));
+		}
+		if concern.line.is_some() && concern.file.is_none() {
+			return Err(hc_error!(
+				"concern '{}' has a line but no file",
+				concern.message
+			));
+		}
+		if concern.line == Some(0) {
+			return Err(hc_error!(
+				"concern '{}' is on line 0, but lines count from 1",
+				concern.message
+			));
+		}
+
+		Ok(concern)
+	}
+
+	/// Get where the concern is, as `<file>:<line>` or just the file.
+	pub fn location(&self) -> Option<String> {
+		let file = self.file.as_ref()?;
+		Some(match self.line {
+			Some(line) => format!("{file}:{line}"),
+			None => file.clone(),
+		})
+	}
+
+	/// Get what the plugin said the concern is about: its file, commit, and
+	/// other identifiers.
+	pub fn subject(&self) -> Vec<&str> {
+		self.file
+			.iter()
+			.chain(&self.commit)
+			.chain(&self.identifiers)
+			.map(String::as_str)
+			.collect()
+	}
+
+	/// Check if the concern is about an identifier, like a file path or commit
+	/// hash, either in its message or in its subject.
+	pub fn mentions(&self, identifier: &str) -> bool {
+		concern_mentions(&self.message, identifier)
+			|| self.subject().into_iter().any(|subject| {
+				subject == identifier.trim() || concern_mentions(subject, identifier)
+			})
+	}
+
+	/// Get the concern's fingerprint, to track it across runs.
+	pub fn fingerprint(&self, analysis: &str) -> String {
+		let subject = self.subject();
+		if subject.is_empty() {
+			concern_fingerprint(analysis, &self.message)
+		} else {
+			subject_fingerprint(analysis, &subject)
+		}
+	}
+
+	/// Describe the concern, with its severity and location if known.
+	pub fn statement(&self) -> String {
+		let mut statement = match self.severity {
+			Some(severity) => format!("[{}] {}", severity, self.message),
+			None => self.message.clone(),
+		};
+		// Messages often already say where they are
+		if let Some(location) = self.location() {
+			if !self.message.contains(&location) {
+				statement.push_str(&format!(" (at {location})"));
+			}
+		}
+		statement
+	}
+}
+
+impl From<&str> for Concern {
+	fn from(message: &str) -> Concern {
+		Concern::new(message)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn reads_plugin_concerns() {
+		let concern = Concern::from_plugin(json!({
+			"message": "Found unpinned action 'actions/checkout@v4'",
+			"severity": "medium",
+			"file": ".github/workflows/ci.yml",
+			"line": 12,
+			"identifiers": ["actions/checkout@v4"],
+		}))
+		.unwrap();
+		assert_eq!(concern.severity, Some(Severity::Medium));
+		assert_eq!(
+			concern.location(),
+			Some(".github/workflows/ci.yml:12".to_owned())
+		);
+		assert_eq!(
+			concern.statement(),
+			"[medium] Found unpinned action 'actions/checkout@v4' (at .github/workflows/ci.yml:12)"
+		);
+
+		// Older plugins send plain text
+		let concern = Concern::from_plugin(json!("lodahs")).unwrap();
+		assert_eq!(concern, Concern::new("lodahs"));
+		assert_eq!(concern.statement(), "lodahs");
+	}
+
+	#[test]
+	fn rejects_invalid_concerns() {
+		for concern in [
+			json!(" "),
+			json!({ "severity": "high" }),
+			json!({ "message": "Found a secret", "severity": "urgent" }),
+			json!({ "message": "Found a secret", "line": 3 }),
+			json!({ "message": "Found a secret", "file": "a.txt", "line": 0 }),
+			json!(["Found a secret"]),
+		] {
+			assert!(Concern::from_plugin(concern).is_err());
+		}
+	}
+
+	#[test]
+	fn subjects_are_fingerprinted() {
+		let mut concern = Concern::new("Found binary file at 'bin/tool.exe'");
+		let from_message = concern.fingerprint("mitre/binary");
+
+		// A file the message also mentions keeps the same fingerprint
+		concern.file = Some("bin/tool.exe".to_owned());
+		assert_eq!(concern.fingerprint("mitre/binary"), from_message);
+
+		concern.message = "Binary file found".to_owned();
+		assert_eq!(concern.fingerprint("mitre/binary"), from_message);
+	}
+
+	#[test]
+	fn subjects_are_mentioned() {
+		let concern = Concern {
+			commit: Some("1a2b3c4d5e6f".to_owned()),
+			identifiers: vec!["Jane Doe".to_owned()],
+			..Concern::new("Found a possible secret")
+		};
+		assert!(concern.mentions("1a2b3c4"));
+		assert!(concern.mentions("Jane Doe"));
+		assert!(!concern.mentions("secret.txt"));
+	}
+}
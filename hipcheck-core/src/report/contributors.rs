@@ -0,0 +1,357 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rolling up contributor activity across the targets of a batch run.
+//!
+//! When a batch run analyzes many dependencies at once, it's useful to know
+//! who contributes to many of them, since a single person or organization
+//! with commit access to much of a portfolio is a concentration of risk that
+//! no single target's report will show.
+//!
+//! Each contributor's influence over a target is their share of its commits,
+//! and their influence over the portfolio is the sum of those shares. Someone
+//! who authored half the commits to each of three targets has an influence of
+//! 1.5, while the sole author of one target has an influence of 1.0.
+
+use crate::{engine::HcEngine, error::Result, hc_error, shell::Shell, source::query::SourceQuery};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// The plugin and query used to get each target's contributor activity.
+const ACTIVITY_PUBLISHER: &str = "mitre";
+const ACTIVITY_PLUGIN: &str = "git";
+const ACTIVITY_QUERY: &str = "contributor_activity";
+
+/// Contributors must contribute to at least this many targets to be listed.
+const MIN_TARGETS: usize = 2;
+
+/// The most contributors or organizations to list.
+const MAX_LISTED: usize = 10;
+
+/// E-mail domains shared by unrelated people, which don't identify an organization.
+const SHARED_DOMAINS: &[&str] = &[
+	"163.com",
+	"gmail.com",
+	"googlemail.com",
+	"hotmail.com",
+	"icloud.com",
+	"me.com",
+	"outlook.com",
+	"protonmail.com",
+	"qq.com",
+	"users.noreply.github.com",
+	"yahoo.com",
+];
+
+/// The contributor activity of a single target.
+#[derive(Debug)]
+pub struct TargetActivity {
+	target: String,
+	contributors: Vec<ContributorActivity>,
+}
+
+/// One contributor identity's activity in a target, as returned by the
+/// `mitre/git/contributor_activity` query.
+#[derive(Debug, Deserialize)]
+struct ContributorActivity {
+	id: String,
+	contributors: Vec<Contributor>,
+	commits: i64,
+	total_commits: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Contributor {
+	name: String,
+}
+
+impl TargetActivity {
+	/// Query the contributor activity of a session's target.
+	pub fn query<D>(db: &D, target: &str) -> Result<TargetActivity>
+	where
+		D: HcEngine + SourceQuery,
+	{
+		let key = serde_json::to_value(&db.target().local)?;
+
+		let mut result = db.query(
+			ACTIVITY_PUBLISHER.to_owned(),
+			ACTIVITY_PLUGIN.to_owned(),
+			ACTIVITY_QUERY.to_owned(),
+			key,
+		)?;
+
+		let value = result
+			.value
+			.pop()
+			.ok_or_else(|| hc_error!("no contributor activity returned for '{}'", target))?;
+
+		Ok(TargetActivity {
+			target: target.to_owned(),
+			contributors: serde_json::from_value(value)?,
+		})
+	}
+}
+
+/// The contributors and organizations with the most influence over the
+/// targets of a batch run.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct ContributorRollup {
+	/// Individuals contributing to several targets, most influential first.
+	pub individuals: Vec<ContributorInfluence>,
+
+	/// Organizations, by e-mail domain, contributing to several targets, most
+	/// influential first.
+	pub organizations: Vec<OrganizationInfluence>,
+}
+
+/// One person's influence over the targets of a batch run.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct ContributorInfluence {
+	/// The canonical ID of the person, usually an e-mail address.
+	pub id: String,
+
+	/// Every name the person contributed under.
+	pub names: Vec<String>,
+
+	/// The targets the person contributed to.
+	pub targets: Vec<String>,
+
+	/// The number of commits the person authored across all targets.
+	pub commits: i64,
+
+	/// The sum of the person's share of the commits to each target.
+	pub influence: f64,
+}
+
+/// One organization's influence over the targets of a batch run.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct OrganizationInfluence {
+	/// The e-mail domain of the organization.
+	pub domain: String,
+
+	/// The number of people from the organization who contributed.
+	pub contributors: usize,
+
+	/// The targets the organization contributed to.
+	pub targets: Vec<String>,
+
+	/// The number of commits the organization authored across all targets.
+	pub commits: i64,
+
+	/// The sum of the organization's share of the commits to each target.
+	pub influence: f64,
+}
+
+impl ContributorRollup {
+	/// Roll up the activity of every target, or return `None` if no target's
+	/// activity is known.
+	pub fn new(activity: &[TargetActivity]) -> Option<ContributorRollup> {
+		if activity.is_empty() {
+			return None;
+		}
+
+		let mut individuals: HashMap<&str, ContributorInfluence> = HashMap::new();
+		let mut organizations: HashMap<&str, (OrganizationInfluence, BTreeSet<&str>)> =
+			HashMap::new();
+
+		for target in activity {
+			for contributor in &target.contributors {
+				let share = match contributor.total_commits {
+					0 => 0.0,
+					total => contributor.commits as f64 / total as f64,
+				};
+
+				let individual =
+					individuals
+						.entry(&contributor.id)
+						.or_insert_with(|| ContributorInfluence {
+							id: contributor.id.clone(),
+							names: Vec::new(),
+							targets: Vec::new(),
+							commits: 0,
+							influence: 0.0,
+						});
+				individual.names.extend(
+					contributor
+						.contributors
+						.iter()
+						.map(|c| c.name.clone())
+						.filter(|name| !name.is_empty()),
+				);
+				push_target(&mut individual.targets, &target.target);
+				individual.commits += contributor.commits;
+				individual.influence += share;
+
+				let Some(domain) = organization_domain(&contributor.id) else {
+					continue;
+				};
+
+				let (organization, members) = organizations.entry(domain).or_insert_with(|| {
+					(
+						OrganizationInfluence {
+							domain: domain.to_owned(),
+							contributors: 0,
+							targets: Vec::new(),
+							commits: 0,
+							influence: 0.0,
+						},
+						BTreeSet::new(),
+					)
+				});
+				members.insert(&contributor.id);
+				organization.contributors = members.len();
+				push_target(&mut organization.targets, &target.target);
+				organization.commits += contributor.commits;
+				organization.influence += share;
+			}
+		}
+
+		let individuals = most_influential(
+			individuals.into_values().map(|mut individual| {
+				individual.names.sort();
+				individual.names.dedup();
+				individual
+			}),
+			|i| (i.targets.len(), i.influence, &i.id),
+		);
+		let organizations = most_influential(
+			organizations
+				.into_values()
+				.map(|(organization, _)| organization),
+			|o| (o.targets.len(), o.influence, &o.domain),
+		);
+
+		Some(ContributorRollup {
+			individuals,
+			organizations,
+		})
+	}
+
+	/// Check if any individual or organization contributes to several targets.
+	pub fn is_empty(&self) -> bool {
+		self.individuals.is_empty() && self.organizations.is_empty()
+	}
+}
+
+impl ContributorInfluence {
+	pub fn statement(&self) -> String {
+		let name = self.names.first().map(String::as_str).unwrap_or(&self.id);
+		let locale = Shell::get_locale();
+		format!(
+			"{} <{}>, {} targets, {} commits, influence {}",
+			name,
+			self.id,
+			self.targets.len(),
+			locale.count(self.commits.max(0) as usize),
+			locale.decimal(self.influence, 2)
+		)
+	}
+}
+
+impl OrganizationInfluence {
+	pub fn statement(&self) -> String {
+		let locale = Shell::get_locale();
+		format!(
+			"{}, {} contributors, {} targets, {} commits, influence {}",
+			self.domain,
+			self.contributors,
+			self.targets.len(),
+			locale.count(self.commits.max(0) as usize),
+			locale.decimal(self.influence, 2)
+		)
+	}
+}
+
+/// Add a target to a list, unless it's the last one added.
+fn push_target(targets: &mut Vec<String>, target: &str) {
+	if targets.last().map(String::as_str) != Some(target) {
+		targets.push(target.to_owned());
+	}
+}
+
+/// Get the organization a contributor belongs to, from their ID's e-mail domain.
+fn organization_domain(id: &str) -> Option<&str> {
+	let (_, domain) = id.rsplit_once('@')?;
+	(!domain.is_empty() && !SHARED_DOMAINS.contains(&domain)).then_some(domain)
+}
+
+/// Keep the entries contributing to several targets, ordered by the number of
+/// targets, then influence, then name, and keep only the first few.
+fn most_influential<T, I, F>(entries: I, order: F) -> Vec<T>
+where
+	I: Iterator<Item = T>,
+	F: Fn(&T) -> (usize, f64, &String),
+{
+	let mut entries: Vec<T> = entries.filter(|e| order(e).0 >= MIN_TARGETS).collect();
+	entries.sort_by(|a, b| {
+		let (a_targets, a_influence, a_name) = order(a);
+		let (b_targets, b_influence, b_name) = order(b);
+		b_targets
+			.cmp(&a_targets)
+			.then_with(|| b_influence.total_cmp(&a_influence))
+			.then_with(|| a_name.cmp(b_name))
+	});
+	entries.truncate(MAX_LISTED);
+	entries
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn activity(target: &str, contributors: &[(&str, i64)]) -> TargetActivity {
+		let total_commits = contributors.iter().map(|(_, commits)| commits).sum();
+		TargetActivity {
+			target: target.to_owned(),
+			contributors: contributors
+				.iter()
+				.map(|(id, commits)| ContributorActivity {
+					id: (*id).to_owned(),
+					contributors: vec![Contributor {
+						name: id.split('@').next().unwrap().to_owned(),
+					}],
+					commits: *commits,
+					total_commits,
+				})
+				.collect(),
+		}
+	}
+
+	#[test]
+	fn rollup_weights_by_share_of_commits() {
+		let rollup = ContributorRollup::new(&[
+			activity("a", &[("jane@example.com", 1), ("john@gmail.com", 1)]),
+			activity("b", &[("jane@example.com", 3), ("sam@example.com", 1)]),
+			activity("c", &[("john@gmail.com", 4)]),
+		])
+		.unwrap();
+
+		assert_eq!(rollup.individuals.len(), 2);
+		assert_eq!(rollup.individuals[0].id, "john@gmail.com");
+		assert_eq!(rollup.individuals[0].influence, 1.5);
+		assert_eq!(rollup.individuals[1].id, "jane@example.com");
+		assert_eq!(rollup.individuals[1].influence, 1.25);
+		assert_eq!(rollup.individuals[1].commits, 4);
+
+		// gmail.com doesn't identify an organization.
+		assert_eq!(rollup.organizations.len(), 1);
+		assert_eq!(rollup.organizations[0].domain, "example.com");
+		assert_eq!(rollup.organizations[0].contributors, 2);
+		assert_eq!(rollup.organizations[0].influence, 1.5);
+	}
+
+	#[test]
+	fn contributors_to_one_target_are_not_listed() {
+		let rollup = ContributorRollup::new(&[
+			activity("a", &[("jane@example.com", 10)]),
+			activity("b", &[("john@example.org", 10)]),
+		])
+		.unwrap();
+
+		assert!(rollup.is_empty());
+		assert!(ContributorRollup::new(&[]).is_none());
+	}
+}
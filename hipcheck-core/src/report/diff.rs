@@ -0,0 +1,517 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Comparing a new report against a baseline report from a prior run.
+//!
+//! The baseline is read from the JSON output of an earlier `hc check`. Only
+//! the parts needed for the comparison are deserialized, so baselines from
+//! older versions of Hipcheck can still be used as long as they contain the
+//! analysis names and the final recommendation.
+//!
+//! When an analysis fails in both reports and the baseline has concern
+//! fingerprints, its concerns are compared too, so new concerns are noticed
+//! even though the analysis was already failing.
+
+use crate::{
+	error::{Context, Result},
+	report::{RecommendationKind, Report},
+	shell::Shell,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{BTreeMap, HashSet},
+	fmt::{self, Display, Formatter},
+	fs,
+	path::Path,
+};
+
+/// The parts of a prior JSON report needed to compare against it.
+#[derive(Debug, Deserialize)]
+pub struct Baseline {
+	/// The name of the repository the baseline analyzed.
+	pub repo_name: String,
+	repo_head: String,
+	#[serde(default)]
+	passing: Vec<BaselineAnalysis>,
+	#[serde(default)]
+	failing: Vec<BaselineAnalysis>,
+	#[serde(default)]
+	errored: Vec<BaselineIdent>,
+	#[serde(default)]
+	skipped: Vec<BaselineIdent>,
+	recommendation: BaselineRecommendation,
+}
+
+#[derive(Debug, Deserialize)]
+struct BaselineAnalysis {
+	name: String,
+	#[serde(default)]
+	concern_fingerprints: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BaselineIdent {
+	analysis: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BaselineRecommendation {
+	kind: RecommendationKind,
+	risk_score: f64,
+}
+
+impl Baseline {
+	/// Load a baseline from a JSON report on disk.
+	pub fn load_from(path: &Path) -> Result<Baseline> {
+		let contents = fs::read_to_string(path)
+			.with_context(|| format!("failed to read baseline report '{}'", path.display()))?;
+		Baseline::from_json(&contents)
+			.with_context(|| format!("failed to parse baseline report '{}'", path.display()))
+	}
+
+	/// Parse a baseline from the contents of a JSON report.
+	pub fn from_json(json: &str) -> Result<Baseline> {
+		Ok(serde_json::from_str(json)?)
+	}
+
+	/// Use a report from earlier in the same run as a baseline.
+	pub fn from_report(report: &Report) -> Result<Baseline> {
+		Baseline::from_json(&serde_json::to_string(report)?)
+	}
+
+	/// The status of each analysis in the baseline, keyed by analysis name.
+	fn statuses(&self) -> BTreeMap<&str, AnalysisStatus> {
+		let passing = self
+			.passing
+			.iter()
+			.map(|a| (a.name.as_str(), AnalysisStatus::Passed));
+		let failing = self
+			.failing
+			.iter()
+			.map(|a| (a.name.as_str(), AnalysisStatus::Failed));
+		let errored = self
+			.errored
+			.iter()
+			.map(|a| (a.analysis.as_str(), AnalysisStatus::Errored));
+		let skipped = self
+			.skipped
+			.iter()
+			.map(|a| (a.analysis.as_str(), AnalysisStatus::Skipped));
+
+		passing
+			.chain(failing)
+			.chain(errored)
+			.chain(skipped)
+			.collect()
+	}
+
+	/// The concern fingerprints of each failing analysis in the baseline which
+	/// has them, keyed by analysis name.
+	fn concern_fingerprints(&self) -> BTreeMap<&str, &[String]> {
+		self.failing
+			.iter()
+			.filter(|a| !a.concern_fingerprints.is_empty())
+			.map(|a| (a.name.as_str(), a.concern_fingerprints.as_slice()))
+			.collect()
+	}
+
+	/// Compare a new report against this baseline.
+	pub fn diff(&self, report: &Report) -> ReportDelta {
+		let mut before = self.statuses();
+		let after = report_statuses(report);
+		let before_concerns = self.concern_fingerprints();
+		let after_concerns = report
+			.failing_analyses()
+			.map(|a| (a.analysis().name.as_str(), a.concern_fingerprints()))
+			.collect::<BTreeMap<_, _>>();
+
+		let mut analyses = Vec::new();
+
+		for (name, after) in after {
+			let concerns = before_concerns
+				.get(name.as_str())
+				.zip(after_concerns.get(name.as_str()))
+				.map(|(before, after)| ConcernDelta::new(before, after));
+			let before = before.remove(name.as_str());
+			let mut delta = AnalysisDelta::new(name, before, Some(after));
+			delta.concerns = concerns;
+			analyses.push(delta);
+		}
+
+		for (name, before) in before {
+			analyses.push(AnalysisDelta::new(name.to_owned(), Some(before), None));
+		}
+
+		analyses.sort_by(|a, b| a.name.cmp(&b.name));
+
+		ReportDelta {
+			baseline_head: self.repo_head.clone(),
+			analyses,
+			risk_score: ScoreDelta {
+				before: self.recommendation.risk_score,
+				after: report.recommendation.risk_score.0,
+			},
+			recommendation: RecommendationDelta {
+				before: self.recommendation.kind,
+				after: report.recommendation.kind,
+			},
+		}
+	}
+}
+
+/// The status of each analysis in a new report, keyed by analysis name.
+fn report_statuses(report: &Report) -> BTreeMap<String, AnalysisStatus> {
+	let passing = report
+		.passing_analyses()
+		.map(|a| (a.name.clone(), AnalysisStatus::Passed));
+	let failing = report
+		.failing_analyses()
+		.map(|a| (a.analysis().name.clone(), AnalysisStatus::Failed));
+	let errored = report
+		.errored_analyses()
+		.map(|a| (a.analysis().to_string(), AnalysisStatus::Errored));
+	let skipped = report
+		.skipped_analyses()
+		.map(|a| (a.analysis().to_string(), AnalysisStatus::Skipped));
+
+	passing
+		.chain(failing)
+		.chain(errored)
+		.chain(skipped)
+		.collect()
+}
+
+/// How a report differs from a baseline report.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct ReportDelta {
+	/// The HEAD commit hash of the repository in the baseline report.
+	pub baseline_head: String,
+
+	/// How each analysis changed.
+	pub analyses: Vec<AnalysisDelta>,
+
+	/// How the overall risk score changed.
+	pub risk_score: ScoreDelta,
+
+	/// How the final recommendation changed.
+	pub recommendation: RecommendationDelta,
+}
+
+impl ReportDelta {
+	/// Get the analyses which got worse or better, or whose concerns changed,
+	/// since the baseline.
+	pub fn changed_analyses(&self) -> impl Iterator<Item = &AnalysisDelta> {
+		self.analyses.iter().filter(|a| a.is_changed())
+	}
+
+	/// Count the analyses which stayed the same since the baseline.
+	pub fn unchanged_count(&self) -> usize {
+		self.analyses.iter().filter(|a| !a.is_changed()).count()
+	}
+
+	/// Describe the baseline being compared against.
+	pub fn against(&self) -> String {
+		format!("compared against baseline ({})", self.baseline_head)
+	}
+}
+
+/// Whether an analysis passed, failed, errored, or was skipped in a report.
+#[derive(Debug, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[schemars(crate = "schemars")]
+pub enum AnalysisStatus {
+	Passed,
+	Failed,
+	Errored,
+	Skipped,
+}
+
+impl Display for AnalysisStatus {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			AnalysisStatus::Passed => write!(f, "passed"),
+			AnalysisStatus::Failed => write!(f, "failed"),
+			AnalysisStatus::Errored => write!(f, "errored"),
+			AnalysisStatus::Skipped => write!(f, "skipped"),
+		}
+	}
+}
+
+/// How an analysis changed since the baseline.
+#[derive(Debug, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[schemars(crate = "schemars")]
+pub enum AnalysisChange {
+	/// The analysis passed in the baseline and now fails or errors.
+	Regressed,
+	/// The analysis failed or errored in the baseline and now passes.
+	Improved,
+	/// The analysis has the same outcome as in the baseline.
+	Unchanged,
+	/// The analysis wasn't in the baseline.
+	Added,
+	/// The analysis was in the baseline but wasn't run this time.
+	Removed,
+}
+
+/// How one analysis changed since the baseline.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct AnalysisDelta {
+	/// The name of the analysis.
+	pub name: String,
+
+	/// How the analysis changed.
+	pub change: AnalysisChange,
+
+	/// The status of the analysis in the baseline, if it was run.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub before: Option<AnalysisStatus>,
+
+	/// The status of the analysis now, if it was run.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub after: Option<AnalysisStatus>,
+
+	/// How the analysis' concerns changed, if it failed both times and the
+	/// baseline has concern fingerprints.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub concerns: Option<ConcernDelta>,
+}
+
+impl AnalysisDelta {
+	fn new(name: String, before: Option<AnalysisStatus>, after: Option<AnalysisStatus>) -> Self {
+		use AnalysisStatus::*;
+
+		// Skipped analyses say nothing about the target, so moving to or from
+		// being skipped is neither better nor worse.
+		let change = match (before, after) {
+			(None, _) => AnalysisChange::Added,
+			(_, None) => AnalysisChange::Removed,
+			(Some(Passed), Some(Failed | Errored)) => AnalysisChange::Regressed,
+			(Some(Failed | Errored), Some(Passed)) => AnalysisChange::Improved,
+			_ => AnalysisChange::Unchanged,
+		};
+
+		AnalysisDelta {
+			name,
+			change,
+			before,
+			after,
+			concerns: None,
+		}
+	}
+
+	/// Check if the analysis' outcome or concerns changed since the baseline.
+	pub fn is_changed(&self) -> bool {
+		self.change != AnalysisChange::Unchanged
+			|| self.concerns.is_some_and(|concerns| concerns.is_changed())
+	}
+
+	pub fn statement(&self) -> String {
+		if let Some(concerns) = self.concerns.filter(ConcernDelta::is_changed) {
+			return format!("'{}' still failed, {}", self.name, concerns.statement());
+		}
+
+		match (self.before, self.after) {
+			(Some(before), Some(after)) if before != after => {
+				format!("'{}' {}, was {}", self.name, after, before)
+			}
+			(Some(before), Some(_)) => format!("'{}' still {}", self.name, before),
+			(None, Some(after)) => format!("'{}' {}, not in baseline", self.name, after),
+			(Some(before), None) => format!("'{}' not run, {} in baseline", self.name, before),
+			(None, None) => format!("'{}' not run", self.name),
+		}
+	}
+}
+
+/// How the concerns of an analysis which failed both times changed since the
+/// baseline, matched by their fingerprints.
+#[derive(Debug, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[schemars(crate = "schemars")]
+pub struct ConcernDelta {
+	/// The number of concerns which weren't in the baseline.
+	pub new: usize,
+
+	/// The number of concerns in the baseline which are gone now.
+	pub resolved: usize,
+}
+
+impl ConcernDelta {
+	fn new(before: &[String], after: &[String]) -> Self {
+		let before = before.iter().collect::<HashSet<_>>();
+		let after = after.iter().collect::<HashSet<_>>();
+
+		ConcernDelta {
+			new: after.difference(&before).count(),
+			resolved: before.difference(&after).count(),
+		}
+	}
+
+	pub fn is_changed(&self) -> bool {
+		self.new > 0 || self.resolved > 0
+	}
+
+	pub fn statement(&self) -> String {
+		format!("{} new concerns, {} resolved", self.new, self.resolved)
+	}
+}
+
+/// How the overall risk score changed since the baseline.
+#[derive(Debug, Serialize, JsonSchema, Clone, Copy)]
+#[schemars(crate = "schemars")]
+pub struct ScoreDelta {
+	pub before: f64,
+	pub after: f64,
+}
+
+/// How the final recommendation changed since the baseline.
+#[derive(Debug, Serialize, JsonSchema, Clone, Copy)]
+#[schemars(crate = "schemars")]
+pub struct RecommendationDelta {
+	pub before: RecommendationKind,
+	pub after: RecommendationKind,
+}
+
+impl RecommendationDelta {
+	pub fn is_changed(&self) -> bool {
+		self.before != self.after
+	}
+}
+
+impl ScoreDelta {
+	pub fn statement(&self) -> String {
+		let locale = Shell::get_locale();
+		let change = self.after - self.before;
+		format!(
+			"risk rated as {}, was {} ({}{})",
+			locale.decimal(self.after, 2),
+			locale.decimal(self.before, 2),
+			if change >= 0.0 { "+" } else { "" },
+			locale.decimal(change, 2)
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const BASELINE: &str = r#"{
+		"repo_name": "hipcheck",
+		"repo_head": "abc123",
+		"hipcheck_version": "3.0.0",
+		"analyzed_at": "2024-01-01T00:00:00Z",
+		"passing": [
+			{ "analysis": "Analysis", "name": "mitre/activity", "passed": true, "policy_expr": "", "message": "" },
+			{ "analysis": "Analysis", "name": "mitre/binary", "passed": true, "policy_expr": "", "message": "" }
+		],
+		"failing": [
+			{ "analysis": "Analysis", "name": "mitre/fuzz", "passed": false, "policy_expr": "", "message": "", "concerns": ["no fuzzing"], "concern_fingerprints": ["a1", "b2"] }
+		],
+		"errored": [
+			{ "analysis": "mitre/typo", "error": { "msg": "no language" } }
+		],
+		"recommendation": {
+			"kind": "Pass",
+			"risk_score": 0.25,
+			"risk_policy": "(lte $ 0.5)"
+		}
+	}"#;
+
+	#[test]
+	fn parse_baseline() {
+		let baseline = Baseline::from_json(BASELINE).unwrap();
+		let statuses = baseline.statuses();
+
+		assert_eq!(baseline.repo_name, "hipcheck");
+		assert_eq!(statuses["mitre/activity"], AnalysisStatus::Passed);
+		assert_eq!(statuses["mitre/fuzz"], AnalysisStatus::Failed);
+		assert_eq!(statuses["mitre/typo"], AnalysisStatus::Errored);
+		assert_eq!(baseline.recommendation.kind, RecommendationKind::Pass);
+	}
+
+	#[test]
+	fn baseline_requires_recommendation() {
+		assert!(Baseline::from_json(r#"{ "repo_name": "a", "repo_head": "b" }"#).is_err());
+	}
+
+	#[test]
+	fn classify_changes() {
+		use AnalysisStatus::*;
+
+		let cases = [
+			(Some(Passed), Some(Failed), AnalysisChange::Regressed),
+			(Some(Passed), Some(Errored), AnalysisChange::Regressed),
+			(Some(Failed), Some(Passed), AnalysisChange::Improved),
+			(Some(Errored), Some(Passed), AnalysisChange::Improved),
+			(Some(Failed), Some(Errored), AnalysisChange::Unchanged),
+			(Some(Passed), Some(Passed), AnalysisChange::Unchanged),
+			(Some(Passed), Some(Skipped), AnalysisChange::Unchanged),
+			(Some(Skipped), Some(Failed), AnalysisChange::Unchanged),
+			(None, Some(Passed), AnalysisChange::Added),
+			(Some(Failed), None, AnalysisChange::Removed),
+		];
+
+		for (before, after, expected) in cases {
+			let delta = AnalysisDelta::new("a".to_owned(), before, after);
+			assert_eq!(delta.change, expected, "{:?} -> {:?}", before, after);
+		}
+	}
+
+	#[test]
+	fn analysis_statements() {
+		use AnalysisStatus::*;
+
+		let regressed = AnalysisDelta::new("mitre/fuzz".to_owned(), Some(Passed), Some(Failed));
+		assert_eq!(regressed.statement(), "'mitre/fuzz' failed, was passed");
+
+		let removed = AnalysisDelta::new("mitre/typo".to_owned(), Some(Errored), None);
+		assert_eq!(
+			removed.statement(),
+			"'mitre/typo' not run, errored in baseline"
+		);
+	}
+
+	#[test]
+	fn concerns_are_matched_by_fingerprint() {
+		let baseline = Baseline::from_json(BASELINE).unwrap();
+		assert_eq!(
+			baseline.concern_fingerprints()["mitre/fuzz"],
+			["a1".to_owned(), "b2".to_owned()]
+		);
+
+		let before = ["a1".to_owned(), "b2".to_owned()];
+		let after = ["b2".to_owned(), "c3".to_owned(), "d4".to_owned()];
+		let concerns = ConcernDelta::new(&before, &after);
+		assert_eq!(
+			concerns,
+			ConcernDelta {
+				new: 2,
+				resolved: 1
+			}
+		);
+
+		let mut delta = AnalysisDelta::new(
+			"mitre/fuzz".to_owned(),
+			Some(AnalysisStatus::Failed),
+			Some(AnalysisStatus::Failed),
+		);
+		assert!(!delta.is_changed());
+		delta.concerns = Some(concerns);
+		assert!(delta.is_changed());
+		assert_eq!(
+			delta.statement(),
+			"'mitre/fuzz' still failed, 2 new concerns, 1 resolved"
+		);
+	}
+
+	#[test]
+	fn score_statement() {
+		let delta = ScoreDelta {
+			before: 0.25,
+			after: 0.5,
+		};
+		assert_eq!(delta.statement(), "risk rated as 0.50, was 0.25 (+0.25)");
+	}
+}
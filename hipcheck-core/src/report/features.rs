@@ -0,0 +1,234 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Flattening of a `Report` into a numeric feature vector.
+//!
+//! Feature vectors are intended for consumers who want to train or evaluate
+//! downstream models on Hipcheck's output, and so care more about the raw
+//! values each analysis produced than about the pass / fail determination.
+//!
+//! Feature names are stable and derived from the analysis name and the shape
+//! of its output:
+//!
+//! - `risk_score`: the final risk score of the target.
+//! - `<analysis>.passed`: `1` if the analysis passed, `0` otherwise.
+//! - `<analysis>.errored`: `1` if the analysis errored, `0` otherwise.
+//! - `<analysis>.concerns`: the number of concerns the analysis reported.
+//! - `<analysis>.value`: the output of the analysis, if it was a number or
+//!   boolean (booleans become `1` or `0`).
+//! - `<analysis>.value.count`, `.sum`, `.mean`, `.min`, `.max`: summary
+//!   statistics if the output was an array. Only `.count` is present if the
+//!   array contains non-numeric elements.
+//! - `<analysis>.value.<field>`: fields of an object output, flattened with
+//!   the same rules as above.
+//!
+//! Strings and nulls carry no numeric meaning and are omitted, as are the
+//! value features of analyses which errored or were skipped.
+
+use crate::report::Report;
+use serde::Serialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, fmt::Write as _, ops::Not as _};
+
+/// A flattened, numeric view of a single target's analysis results.
+#[derive(Debug, Serialize)]
+pub struct FeatureVector {
+	/// The name of the repository being analyzed.
+	pub target: String,
+
+	/// The HEAD commit hash of the repository during analysis.
+	pub head: String,
+
+	/// The version of Hipcheck used to analyze the repo.
+	pub hipcheck_version: String,
+
+	/// The features, ordered by name.
+	pub features: BTreeMap<String, f64>,
+}
+
+impl FeatureVector {
+	/// Flatten a report into a feature vector.
+	pub fn from_report(report: &Report) -> FeatureVector {
+		let mut features = BTreeMap::new();
+
+		features.insert(
+			"risk_score".to_owned(),
+			report.recommendation.risk_score.0,
+		);
+
+		for analysis in report.passing_analyses() {
+			add_analysis(&mut features, &analysis.name, true, 0, analysis.value());
+		}
+
+		for failing in report.failing_analyses() {
+			let analysis = failing.analysis();
+			add_analysis(
+				&mut features,
+				&analysis.name,
+				false,
+				failing.concern_count(),
+				analysis.value(),
+			);
+		}
+
+		for errored in report.errored_analyses() {
+			let name = &errored.analysis().0;
+			features.insert(format!("{name}.passed"), 0.0);
+			features.insert(format!("{name}.errored"), 1.0);
+			features.insert(format!("{name}.concerns"), 0.0);
+		}
+
+		for skipped in report.skipped_analyses() {
+			let name = &skipped.analysis().0;
+			features.insert(format!("{name}.passed"), 0.0);
+			features.insert(format!("{name}.errored"), 0.0);
+			features.insert(format!("{name}.concerns"), 0.0);
+		}
+
+		FeatureVector {
+			target: report.repo_name.to_string(),
+			head: report.repo_head.to_string(),
+			hipcheck_version: report.hipcheck_version.clone(),
+			features,
+		}
+	}
+
+	/// Render the feature vector as CSV, with a header row and a single data row.
+	pub fn to_csv(&self) -> String {
+		let mut out = String::new();
+
+		let header = ["target", "head", "hipcheck_version"]
+			.into_iter()
+			.chain(self.features.keys().map(String::as_str))
+			.map(csv_escape)
+			.collect::<Vec<_>>()
+			.join(",");
+
+		let row = [&self.target, &self.head, &self.hipcheck_version]
+			.into_iter()
+			.map(|s| csv_escape(s))
+			.chain(self.features.values().map(ToString::to_string))
+			.collect::<Vec<_>>()
+			.join(",");
+
+		// Writing to a `String` can't fail.
+		let _ = writeln!(out, "{header}");
+		let _ = writeln!(out, "{row}");
+
+		out
+	}
+}
+
+/// Add the features for a single analysis which produced output.
+fn add_analysis(
+	features: &mut BTreeMap<String, f64>,
+	name: &str,
+	passed: bool,
+	concerns: usize,
+	value: Option<&Value>,
+) {
+	features.insert(format!("{name}.passed"), if passed { 1.0 } else { 0.0 });
+	features.insert(format!("{name}.errored"), 0.0);
+	features.insert(format!("{name}.concerns"), concerns as f64);
+
+	if let Some(value) = value {
+		flatten(features, format!("{name}.value"), value);
+	}
+}
+
+/// Recursively flatten a JSON value into numeric features under `prefix`.
+fn flatten(features: &mut BTreeMap<String, f64>, prefix: String, value: &Value) {
+	match value {
+		Value::Number(n) => {
+			if let Some(n) = n.as_f64() {
+				features.insert(prefix, n);
+			}
+		}
+		Value::Bool(b) => {
+			features.insert(prefix, if *b { 1.0 } else { 0.0 });
+		}
+		Value::Array(items) => {
+			features.insert(format!("{prefix}.count"), items.len() as f64);
+
+			let nums = items.iter().map(Value::as_f64).collect::<Option<Vec<_>>>();
+
+			if let Some(nums) = nums.filter(|nums| nums.is_empty().not()) {
+				let sum = nums.iter().sum::<f64>();
+				let min = nums.iter().copied().fold(f64::INFINITY, f64::min);
+				let max = nums.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+				features.insert(format!("{prefix}.sum"), sum);
+				features.insert(format!("{prefix}.mean"), sum / nums.len() as f64);
+				features.insert(format!("{prefix}.min"), min);
+				features.insert(format!("{prefix}.max"), max);
+			}
+		}
+		Value::Object(fields) => {
+			for (key, value) in fields {
+				flatten(features, format!("{prefix}.{key}"), value);
+			}
+		}
+		Value::String(_) | Value::Null => {}
+	}
+}
+
+/// Quote a CSV field if it contains characters which would otherwise break parsing.
+pub(crate) fn csv_escape(field: &str) -> String {
+	if field.contains([',', '"', '\n', '\r']) {
+		format!("\"{}\"", field.replace('"', "\"\""))
+	} else {
+		field.to_owned()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn flatten_number_and_bool() {
+		let mut features = BTreeMap::new();
+		flatten(&mut features, "a".to_owned(), &json!(3));
+		flatten(&mut features, "b".to_owned(), &json!(true));
+		assert_eq!(features.get("a"), Some(&3.0));
+		assert_eq!(features.get("b"), Some(&1.0));
+	}
+
+	#[test]
+	fn flatten_numeric_array() {
+		let mut features = BTreeMap::new();
+		flatten(&mut features, "a".to_owned(), &json!([1, 2, 6]));
+		assert_eq!(features.get("a.count"), Some(&3.0));
+		assert_eq!(features.get("a.sum"), Some(&9.0));
+		assert_eq!(features.get("a.mean"), Some(&3.0));
+		assert_eq!(features.get("a.min"), Some(&1.0));
+		assert_eq!(features.get("a.max"), Some(&6.0));
+	}
+
+	#[test]
+	fn flatten_mixed_array_only_counts() {
+		let mut features = BTreeMap::new();
+		flatten(&mut features, "a".to_owned(), &json!([1, "x"]));
+		assert_eq!(features.get("a.count"), Some(&2.0));
+		assert!(features.get("a.sum").is_none());
+	}
+
+	#[test]
+	fn flatten_nested_object_skips_strings() {
+		let mut features = BTreeMap::new();
+		flatten(
+			&mut features,
+			"a".to_owned(),
+			&json!({ "x": { "y": 1.5 }, "name": "foo", "none": null }),
+		);
+		assert_eq!(features.len(), 1);
+		assert_eq!(features.get("a.x.y"), Some(&1.5));
+	}
+
+	#[test]
+	fn csv_escape_quotes_when_needed() {
+		assert_eq!(csv_escape("plain"), "plain");
+		assert_eq!(csv_escape("a,b"), "\"a,b\"");
+		assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+	}
+}
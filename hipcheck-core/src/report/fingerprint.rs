@@ -0,0 +1,220 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Stable fingerprints for concerns, to track the same finding across runs.
+//!
+//! Concerns are free text written by plugins, and their wording, counts, and
+//! dates may change from run to run even when they're about the same thing.
+//! A fingerprint is a hash of the analysis name and the identifiers of the
+//! concern's subject, like the file paths, e-mail addresses, URLs, and commit
+//! hashes it mentions, so "Contributor jane@example.com made 3 commits" and
+//! "Contributor jane@example.com made 5 commits" share a fingerprint.
+//!
+//! Concerns without any recognizable identifiers fall back to their text,
+//! ignoring case, numbers, and spacing.
+
+/// The version of the fingerprint algorithm, for tools which store fingerprints.
+pub const FINGERPRINT_VERSION: &str = "hipcheck/concern/v1";
+
+/// The number of hex digits kept from the hash.
+const FINGERPRINT_LEN: usize = 16;
+
+/// Get the fingerprint of a concern raised by an analysis.
+pub fn concern_fingerprint(analysis: &str, concern: &str) -> String {
+	let mut subject = identifiers(concern);
+	if subject.is_empty() {
+		subject.push(normalize(concern));
+	}
+
+	subject_fingerprint(analysis, &subject)
+}
+
+/// Get the fingerprint of a concern raised by an analysis, from the
+/// identifiers of its subject.
+pub(crate) fn subject_fingerprint<S: AsRef<str>>(analysis: &str, subject: &[S]) -> String {
+	let mut hasher = blake3::Hasher::new();
+	hasher.update(analysis.as_bytes());
+	for identifier in subject {
+		hasher.update(b"\0");
+		hasher.update(identifier.as_ref().as_bytes());
+	}
+
+	let mut hash = hasher.finalize().to_hex().to_string();
+	hash.truncate(FINGERPRINT_LEN);
+	hash
+}
+
+/// Check if a concern mentions an identifier, like a file path or commit hash.
+///
+/// Commit hashes match when one is an abbreviation of the other.
+pub fn concern_mentions(concern: &str, identifier: &str) -> bool {
+	let identifier = identifier.trim();
+	if identifier.is_empty() {
+		return false;
+	}
+
+	let (quoted, words) = split_words(concern);
+	quoted
+		.iter()
+		.chain(&words)
+		.any(|word| word == identifier || is_same_commit(word, identifier))
+}
+
+/// Get the identifiers a concern mentions, in order.
+pub(crate) fn identifiers(concern: &str) -> Vec<String> {
+	let (mut identifiers, words) = split_words(concern);
+	identifiers.extend(words.into_iter().filter(|word| is_identifier(word)));
+	identifiers
+}
+
+/// Split a concern into the text in its quotes and the rest of its words,
+/// without surrounding punctuation.
+fn split_words(concern: &str) -> (Vec<String>, Vec<String>) {
+	let mut quoted = Vec::new();
+
+	// Text in quotes is always an identifier, and may contain spaces.
+	let mut unquoted = String::new();
+	for (i, part) in concern.split(['\'', '"', '`']).enumerate() {
+		if i % 2 == 1 && !part.trim().is_empty() {
+			quoted.push(part.trim().to_owned());
+		} else {
+			unquoted.push_str(part);
+			unquoted.push(' ');
+		}
+	}
+
+	let words = unquoted
+		.split_whitespace()
+		.map(|word| word.trim_start_matches(|c: char| "([{<".contains(c)))
+		.map(|word| word.trim_end_matches(|c: char| ".,;:)]}>".contains(c)))
+		.filter(|word| !word.is_empty())
+		.map(str::to_owned)
+		.collect();
+
+	(quoted, words)
+}
+
+/// Check if two words are hashes of the same commit, one maybe abbreviated.
+fn is_same_commit(a: &str, b: &str) -> bool {
+	let is_hash =
+		|word: &str| (7..=40).contains(&word.len()) && word.chars().all(|c| c.is_ascii_hexdigit());
+	if !is_hash(a) || !is_hash(b) {
+		return false;
+	}
+	let (a, b) = (a.to_ascii_lowercase(), b.to_ascii_lowercase());
+	a.starts_with(&b) || b.starts_with(&a)
+}
+
+/// Check if a word looks like an e-mail address, URL, path, file name, or
+/// commit hash.
+fn is_identifier(word: &str) -> bool {
+	let has_letter = word.chars().any(|c| c.is_ascii_alphabetic());
+	let has_digit = word.chars().any(|c| c.is_ascii_digit());
+
+	let is_email = word
+		.split_once('@')
+		.is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.'));
+	let is_path = has_letter && (word.contains('/') || word.contains('\\'));
+	let is_file = has_letter
+		&& word
+			.rsplit_once('.')
+			.is_some_and(|(name, ext)| !name.is_empty() && ext.chars().any(char::is_alphabetic));
+	let is_commit = (7..=40).contains(&word.len())
+		&& has_letter
+		&& has_digit
+		&& word.chars().all(|c| c.is_ascii_hexdigit());
+
+	is_email || is_path || is_file || is_commit
+}
+
+/// Reduce a concern to its words, ignoring case, numbers, and spacing.
+fn normalize(concern: &str) -> String {
+	concern
+		.to_lowercase()
+		.split(|c: char| !c.is_alphabetic())
+		.filter(|word| !word.is_empty())
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn counts_do_not_change_fingerprints() {
+		let before = concern_fingerprint(
+			"mitre/trust",
+			"Contributor jane@example.com is not trusted but made 3 recent commit(s)",
+		);
+		let after = concern_fingerprint(
+			"mitre/trust",
+			"Contributor jane@example.com is not trusted but made 5 recent commit(s)",
+		);
+		assert_eq!(before, after);
+		assert_eq!(before.len(), FINGERPRINT_LEN);
+	}
+
+	#[test]
+	fn rewording_does_not_change_fingerprints() {
+		assert_eq!(
+			concern_fingerprint("mitre/binary", "Found binary file at 'bin/tool.exe'"),
+			concern_fingerprint("mitre/binary", "Binary file 'bin/tool.exe' found"),
+		);
+	}
+
+	#[test]
+	fn subjects_and_analyses_change_fingerprints() {
+		assert_ne!(
+			concern_fingerprint("mitre/binary", "Found binary file at 'bin/a.exe'"),
+			concern_fingerprint("mitre/binary", "Found binary file at 'bin/b.exe'"),
+		);
+		assert_ne!(
+			concern_fingerprint("mitre/typo", "lodahs"),
+			concern_fingerprint("mitre/binary", "lodahs"),
+		);
+	}
+
+	#[test]
+	fn concerns_without_identifiers_use_their_words() {
+		assert_eq!(
+			concern_fingerprint("mitre/affiliation", "Contributor Jane Doe has count 2"),
+			concern_fingerprint("mitre/affiliation", "contributor  Jane Doe has count 7"),
+		);
+		assert_ne!(
+			concern_fingerprint("mitre/affiliation", "Contributor Jane Doe has count 2"),
+			concern_fingerprint("mitre/affiliation", "Contributor John Doe has count 2"),
+		);
+	}
+
+	#[test]
+	fn recognizes_identifiers() {
+		assert_eq!(
+			identifiers("Commit 1a2b3c4d by jane@example.com changed src/main.rs (see https://example.com/x)."),
+			vec![
+				"1a2b3c4d",
+				"jane@example.com",
+				"src/main.rs",
+				"https://example.com/x",
+			]
+		);
+		assert!(identifiers("made 3 commits in version 1.2.3").is_empty());
+	}
+
+	#[test]
+	fn finds_mentioned_identifiers() {
+		assert!(concern_mentions(
+			"Found binary file at 'vendor/my tool.exe'",
+			"vendor/my tool.exe"
+		));
+		assert!(concern_mentions("lodahs", "lodahs"));
+		assert!(concern_mentions(
+			"Commit 1a2b3c4d5e6f by jane@example.com was not signed.",
+			"1a2b3c4"
+		));
+		assert!(!concern_mentions("Found binary file at 'bin/a.exe'", "bin"));
+		assert!(!concern_mentions(
+			"Commit 1a2b3c4d5e6f was not signed",
+			"1a2b3c5"
+		));
+	}
+}
@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Sections plugins add to the report, for results which aren't a pass or fail.
+//!
+//! A plugin's default query can record report fragments alongside its
+//! concerns, each with a title and a body written as either Markdown or JSON.
+//! They're shown whether the plugin's analysis passed or failed, so an
+//! informational plugin, like a license inventory, can present what it found
+//! without inventing a policy to fail.
+
+use crate::{error::Result, hc_error};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A section a plugin added to the report.
+#[derive(Debug, Serialize, JsonSchema, Clone)]
+#[schemars(crate = "schemars")]
+pub struct ReportFragment {
+	/// The plugin which added the section, as `<publisher>/<name>`.
+	pub plugin: String,
+
+	/// The heading of the section.
+	pub title: String,
+
+	/// How the body is written.
+	pub format: FragmentFormat,
+
+	/// The contents of the section. Markdown bodies are always strings.
+	pub body: Value,
+}
+
+/// How the body of a report fragment is written.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[schemars(crate = "schemars")]
+pub enum FragmentFormat {
+	Markdown,
+	Json,
+}
+
+/// A report fragment as a plugin sends it.
+#[derive(Debug, Deserialize)]
+struct PluginFragment {
+	title: String,
+	format: FragmentFormat,
+	body: Value,
+}
+
+impl ReportFragment {
+	/// Read a report fragment sent by a plugin, given as `<publisher>/<name>`.
+	pub fn from_plugin(plugin: &str, fragment: Value) -> Result<ReportFragment> {
+		let fragment: PluginFragment = serde_json::from_value(fragment)?;
+
+		if fragment.title.trim().is_empty() {
+			return Err(hc_error!("report fragment has no title"));
+		}
+		if fragment.format == FragmentFormat::Markdown && !fragment.body.is_string() {
+			return Err(hc_error!(
+				"Markdown report fragment '{}' has a body which isn't a string",
+				fragment.title
+			));
+		}
+
+		Ok(ReportFragment {
+			plugin: plugin.to_owned(),
+			title: fragment.title,
+			format: fragment.format,
+			body: fragment.body,
+		})
+	}
+
+	/// Describe where the section came from.
+	pub fn heading(&self) -> String {
+		format!("{} (from {})", self.title, self.plugin)
+	}
+
+	/// Get the body as text: Markdown as written, and JSON pretty-printed.
+	pub fn text(&self) -> String {
+		match (&self.format, &self.body) {
+			(FragmentFormat::Markdown, Value::String(text)) => text.clone(),
+			(_, body) => serde_json::to_string_pretty(body).unwrap_or_else(|_| body.to_string()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn reads_plugin_fragments() {
+		let fragment = ReportFragment::from_plugin(
+			"mitre/license",
+			json!({ "title": "Licenses", "format": "markdown", "body": "- MIT\n- Apache-2.0" }),
+		)
+		.unwrap();
+		assert_eq!(fragment.heading(), "Licenses (from mitre/license)");
+		assert_eq!(fragment.text(), "- MIT\n- Apache-2.0");
+
+		let fragment = ReportFragment::from_plugin(
+			"mitre/sbom",
+			json!({ "title": "Components", "format": "json", "body": { "count": 3 } }),
+		)
+		.unwrap();
+		assert_eq!(fragment.text(), "{\n  \"count\": 3\n}");
+	}
+
+	#[test]
+	fn rejects_invalid_fragments() {
+		for fragment in [
+			json!({ "title": "Licenses", "format": "markdown", "body": ["MIT"] }),
+			json!({ "title": " ", "format": "json", "body": {} }),
+			json!({ "title": "Licenses", "format": "html", "body": "<p>MIT</p>" }),
+			json!({ "format": "json", "body": {} }),
+		] {
+			assert!(ReportFragment::from_plugin("mitre/license", fragment).is_err());
+		}
+	}
+}
@@ -0,0 +1,330 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Output for GitHub Actions, so results show up inline in PR checks.
+//!
+//! The report is written as workflow commands, which the Actions runner turns
+//! into annotations:
+//!
+//! - Every concern of a failing analysis becomes an `::error` annotation
+//!   titled with the analysis name. When the plugin placed a concern in a
+//!   file, the annotation is placed on that file and line; otherwise, when a
+//!   concern names a file in the workspace, it's placed on that file. Failing
+//!   analyses with no concerns get a single annotation stating the policy
+//!   they failed.
+//! - Errored analyses become `::warning` annotations, and skipped analyses
+//!   become `::notice` annotations.
+//! - The recommendation becomes a final annotation, an `::error` for
+//!   "investigate", a `::warning` for "use with conditions", and a `::notice`
+//!   otherwise.
+//!
+//! When run in a workflow, a Markdown summary of the report is also appended
+//! to the job summary file named by `GITHUB_STEP_SUMMARY`.
+
+use crate::{
+	error::{Context as _, Result},
+	report::{concern::Concern, fingerprint::identifiers, RecommendationKind, Report},
+};
+use std::{
+	env,
+	fmt::Write as _,
+	fs::OpenOptions,
+	io::Write as _,
+	path::{Component, Path, PathBuf},
+};
+
+/// Render a report as GitHub Actions workflow commands.
+pub fn annotations(report: &Report) -> String {
+	let workspace = env::var_os("GITHUB_WORKSPACE").map(PathBuf::from);
+	annotations_in(report, workspace.as_deref())
+}
+
+/// Render a report as workflow commands, placing concerns on the files they
+/// name in a workspace, if given.
+fn annotations_in(report: &Report, workspace: Option<&Path>) -> String {
+	let mut out = String::new();
+
+	for failing in report.failing_analyses() {
+		let analysis = failing.analysis();
+		let title = title_property(&analysis.name);
+
+		if failing.concern_count() == 0 {
+			command(&mut out, "error", &title, &analysis.statement());
+			continue;
+		}
+
+		for concern in failing.concern_details() {
+			let message = concern.statement();
+			match location_properties(concern, workspace) {
+				Some(location) => command(
+					&mut out,
+					"error",
+					&format!("{},{}", location, title),
+					&message,
+				),
+				None => command(&mut out, "error", &title, &message),
+			}
+		}
+		if let Some(omitted) = failing.omitted_summary() {
+			command(&mut out, "error", &title, &omitted);
+		}
+	}
+
+	for errored in report.errored_analyses() {
+		let mut message = errored.top_msg();
+		for msg in errored.source_msgs() {
+			message.push_str(": ");
+			message.push_str(&msg);
+		}
+		let title = title_property(&errored.analysis().to_string());
+		command(&mut out, "warning", &title, &message);
+	}
+
+	for skipped in report.skipped_analyses() {
+		let title = title_property(&skipped.analysis().to_string());
+		command(&mut out, "notice", &title, &skipped.statement());
+	}
+
+	let recommendation = report.recommendation();
+	let (level, kind) = match recommendation.kind {
+		RecommendationKind::Pass => ("notice", "PASS"),
+		RecommendationKind::UseWithConditions => ("warning", "USE WITH CONDITIONS"),
+		RecommendationKind::Investigate => ("error", "INVESTIGATE"),
+	};
+	let message = match &recommendation.message {
+		Some(message) => format!("{} {}: {}", kind, report.analyzed(), message),
+		None => format!(
+			"{} {}: {}",
+			kind,
+			report.analyzed(),
+			recommendation.statement()
+		),
+	};
+	command(&mut out, level, &title_property("Hipcheck"), &message);
+
+	out
+}
+
+/// Append a Markdown summary of a report to the job summary file, if running
+/// in a GitHub Actions workflow.
+pub fn write_step_summary(report: &Report) -> Result<()> {
+	let Some(path) = env::var_os("GITHUB_STEP_SUMMARY").map(PathBuf::from) else {
+		return Ok(());
+	};
+
+	let mut file = OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(&path)
+		.with_context(|| format!("failed to open job summary '{}'", path.display()))?;
+	file.write_all(step_summary(report).as_bytes())
+		.with_context(|| format!("failed to write job summary '{}'", path.display()))?;
+
+	Ok(())
+}
+
+/// Render a report as a Markdown job summary.
+fn step_summary(report: &Report) -> String {
+	let mut md = String::new();
+
+	let recommendation = report.recommendation();
+	let kind = match recommendation.kind {
+		RecommendationKind::Pass => "✅ Pass",
+		RecommendationKind::UseWithConditions => "⚠️ Use with conditions",
+		RecommendationKind::Investigate => "❌ Investigate",
+	};
+	let _ = writeln!(md, "## Hipcheck: {}\n", escape_markdown(&report.repo_name));
+	let _ = writeln!(
+		md,
+		"**{}**, {}\n",
+		kind,
+		escape_markdown(&recommendation.statement())
+	);
+	if let Some(message) = &recommendation.message {
+		let _ = writeln!(md, "{}\n", escape_markdown(message));
+	}
+	let _ = writeln!(
+		md,
+		"_{} {}_\n",
+		escape_markdown(&report.analyzed()),
+		escape_markdown(&report.using())
+	);
+
+	if report.has_failing_analyses() {
+		md.push_str("### Failing\n\n");
+		for failing in report.failing_analyses() {
+			let analysis = failing.analysis();
+			let _ = writeln!(
+				md,
+				"- ❌ **{}**: {}",
+				escape_markdown(&analysis.name),
+				escape_markdown(&analysis.statement())
+			);
+			for concern in failing.concern_details() {
+				let _ = writeln!(md, "  - {}", escape_markdown(&concern.statement()));
+			}
+			if let Some(omitted) = failing.omitted_summary() {
+				let _ = writeln!(md, "  - _{}_", escape_markdown(&omitted));
+			}
+		}
+		md.push('\n');
+	}
+
+	if report.has_errored_analyses() {
+		md.push_str("### Errored\n\n");
+		for errored in report.errored_analyses() {
+			let _ = writeln!(md, "- ❓ {}", escape_markdown(&errored.top_msg()));
+		}
+		md.push('\n');
+	}
+
+	if report.has_waived_concerns() {
+		md.push_str("### Waived\n\n");
+		for waived in report.waived_concerns() {
+			let _ = writeln!(
+				md,
+				"- {} ({})",
+				escape_markdown(&waived.statement()),
+				escape_markdown(&waived.explanation())
+			);
+		}
+		md.push('\n');
+	}
+
+	if report.has_passing_analyses() {
+		md.push_str("<details><summary>Passing</summary>\n\n");
+		for analysis in report.passing_analyses() {
+			let _ = writeln!(md, "- ✅ {}", escape_markdown(&analysis.statement()));
+		}
+		md.push_str("\n</details>\n\n");
+	}
+
+	md
+}
+
+/// Get the `file` and `line` properties placing a concern, preferring the
+/// location the plugin gave over files the message names.
+fn location_properties(concern: &Concern, workspace: Option<&Path>) -> Option<String> {
+	if let Some(file) = &concern.file {
+		return Some(match concern.line {
+			Some(line) => format!("file={},line={}", escape_property(file), line),
+			None => format!("file={}", escape_property(file)),
+		});
+	}
+
+	let file = annotated_file(&concern.message, workspace?)?;
+	Some(format!("file={}", escape_property(&file)))
+}
+
+/// Get the first file a concern names which exists in the workspace, as a
+/// path relative to it.
+fn annotated_file(concern: &str, workspace: &Path) -> Option<String> {
+	identifiers(concern)
+		.into_iter()
+		.map(|identifier| identifier.trim_start_matches("./").to_owned())
+		// Only paths inside the workspace, without `..` or a root
+		.filter(|identifier| {
+			!identifier.contains("://")
+				&& Path::new(identifier)
+					.components()
+					.all(|component| matches!(component, Component::Normal(_)))
+		})
+		.find(|identifier| workspace.join(identifier).is_file())
+}
+
+/// Write a workflow command.
+fn command(out: &mut String, level: &str, properties: &str, message: &str) {
+	let _ = writeln!(out, "::{} {}::{}", level, properties, escape_data(message));
+}
+
+/// Escape a workflow command's message.
+fn escape_data(s: &str) -> String {
+	s.replace('%', "%25")
+		.replace('\r', "%0D")
+		.replace('\n', "%0A")
+}
+
+/// Get the `title` property of a workflow command.
+fn title_property(s: &str) -> String {
+	format!("title={}", escape_property(s))
+}
+
+/// Escape a workflow command's property value.
+fn escape_property(s: &str) -> String {
+	escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Escape the characters Markdown would format.
+fn escape_markdown(s: &str) -> String {
+	let mut escaped = String::with_capacity(s.len());
+	for c in s.chars() {
+		if "\\`*_[]<>|#".contains(c) {
+			escaped.push('\\');
+		}
+		escaped.push(c);
+	}
+	escaped
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn commands_are_escaped() {
+		let mut out = String::new();
+		command(
+			&mut out,
+			"error",
+			&title_property("mitre/binary"),
+			"100% binary\nfiles",
+		);
+		assert_eq!(out, "::error title=mitre/binary::100%25 binary%0Afiles\n");
+		assert_eq!(title_property("a:b,c"), "title=a%3Ab%2Cc");
+	}
+
+	#[test]
+	fn concerns_are_placed_on_workspace_files() {
+		let workspace = tempfile::tempdir().unwrap();
+		std::fs::create_dir(workspace.path().join("vendor")).unwrap();
+		std::fs::write(workspace.path().join("vendor").join("tool.exe"), "").unwrap();
+
+		assert_eq!(
+			annotated_file("Found binary file at 'vendor/tool.exe'", workspace.path()),
+			Some("vendor/tool.exe".to_owned())
+		);
+		assert_eq!(
+			annotated_file("Found binary file at 'bin/missing.exe'", workspace.path()),
+			None
+		);
+		assert_eq!(
+			annotated_file("See https://example.com/vendor/tool.exe", workspace.path()),
+			None
+		);
+		assert_eq!(
+			annotated_file("Found 'vendor/../vendor/tool.exe'", workspace.path()),
+			None
+		);
+	}
+
+	#[test]
+	fn plugin_locations_are_preferred() {
+		let concern = Concern {
+			file: Some(".github/workflows/ci.yml".to_owned()),
+			line: Some(12),
+			..Concern::new("Found unpinned action 'actions/checkout@v4'")
+		};
+		assert_eq!(
+			location_properties(&concern, None),
+			Some("file=.github/workflows/ci.yml,line=12".to_owned())
+		);
+		assert_eq!(
+			location_properties(&Concern::new("Found 'vendor/tool.exe'"), None),
+			None
+		);
+	}
+
+	#[test]
+	fn markdown_is_escaped() {
+		assert_eq!(escape_markdown("a_b *c* <d>"), "a\\_b \\*c\\* \\<d\\>");
+	}
+}
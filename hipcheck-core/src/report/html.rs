@@ -0,0 +1,329 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rendering a `Report` as a single, self-contained HTML page.
+//!
+//! The page has no external assets, so it can be attached to a ticket or
+//! archived as-is. It shows the recommendation, a chart of how much each
+//! analysis contributes to the risk score, and each failing analysis with its
+//! concerns in an expandable list.
+
+use crate::{
+	report::{Analysis, RecommendationKind, Report},
+	shell::Shell,
+};
+use std::fmt::Write as _;
+
+/// The page template, with `{{name}}` placeholders filled in by `render`.
+const TEMPLATE: &str = include_str!("report.html");
+
+/// Render a report as an HTML page.
+pub fn render(report: &Report) -> String {
+	let mut sections = String::new();
+	risk_chart(&mut sections, report);
+	failing(&mut sections, report);
+	passing(&mut sections, report);
+	errored(&mut sections, report);
+	skipped(&mut sections, report);
+	waived(&mut sections, report);
+	fragments(&mut sections, report);
+	risk_categories(&mut sections, report);
+	baseline(&mut sections, report);
+
+	TEMPLATE
+		.replace("{{version}}", &escape(&report.hipcheck_version))
+		.replace("{{repo}}", &escape(&report.repo_name))
+		.replace(
+			"{{meta}}",
+			&escape(&format!(
+				"{} {} {}",
+				report.analyzed(),
+				report.using(),
+				report.at_time()
+			)),
+		)
+		.replace("{{recommendation}}", &recommendation(report))
+		.replace("{{sections}}", &sections)
+}
+
+fn recommendation(report: &Report) -> String {
+	let recommendation = report.recommendation();
+	let (class, kind) = match recommendation.kind {
+		RecommendationKind::Pass => ("pass", "Pass"),
+		RecommendationKind::UseWithConditions => ("conditions", "Use with conditions"),
+		RecommendationKind::Investigate => ("investigate", "Investigate"),
+	};
+
+	let mut html = format!(
+		"<div class=\"recommendation {}\">\n<div class=\"kind\">{}</div>\n<div>{}</div>\n",
+		class,
+		kind,
+		escape(&recommendation.statement())
+	);
+	if let Some(message) = &recommendation.message {
+		let _ = writeln!(html, "<div>{}</div>", escape(message));
+	}
+	html.push_str("</div>");
+	html
+}
+
+/// A bar for each analysis, sized by its share of the risk score. Failing
+/// analyses add their share to the score, so they're highlighted.
+fn risk_chart(html: &mut String, report: &Report) {
+	let mut analyses = report
+		.passing_analyses()
+		.chain(report.failing_analyses().map(|f| f.analysis()))
+		.filter_map(|a| Some((a, a.weight()?)))
+		.collect::<Vec<_>>();
+
+	if analyses.is_empty() {
+		return;
+	}
+
+	analyses.sort_by(|(a, a_weight), (b, b_weight)| {
+		b_weight
+			.total_cmp(a_weight)
+			.then_with(|| a.name.cmp(&b.name))
+	});
+
+	html.push_str("<h2>Risk Contributions</h2>\n<div class=\"chart\">\n");
+	for (analysis, weight) in analyses {
+		let _ = writeln!(
+			html,
+			"<span>{}</span><div class=\"track\"><div class=\"fill{}\" style=\"width: {:.1}%\"></div></div><span class=\"value\">{}</span>",
+			escape(&analysis.name),
+			if analysis.is_passing() { "" } else { " failed" },
+			weight * 100.0,
+			escape(&Shell::get_locale().decimal(weight, 2))
+		);
+	}
+	html.push_str("</div>\n");
+}
+
+fn failing(html: &mut String, report: &Report) {
+	if !report.has_failing_analyses() {
+		return;
+	}
+
+	html.push_str("<h2>Failing</h2>\n<ul class=\"analyses\">\n");
+	for failing in report.failing_analyses() {
+		let analysis = failing.analysis();
+		let statement = format!(
+			"<span class=\"failed\">&#10007;</span> {}",
+			escape(&analysis.statement())
+		);
+		let explanation = explanation(analysis);
+
+		if failing.concern_count() == 0 {
+			let _ = writeln!(html, "<li>{}{}</li>", statement, explanation);
+			continue;
+		}
+
+		let _ = writeln!(
+			html,
+			"<li><details><summary>{} ({} concerns)</summary>{}<ul>",
+			statement,
+			failing.concern_count(),
+			explanation
+		);
+		for (concern, fingerprint) in failing.fingerprinted_concerns() {
+			let _ = writeln!(
+				html,
+				"<li data-fingerprint=\"{}\">{}</li>",
+				escape(fingerprint),
+				escape(&concern.statement())
+			);
+		}
+		if let Some(omitted) = failing.omitted_summary() {
+			let _ = writeln!(html, "<li class=\"omitted\">{}</li>", escape(&omitted));
+		}
+		html.push_str("</ul></details></li>\n");
+	}
+	html.push_str("</ul>\n");
+}
+
+fn passing(html: &mut String, report: &Report) {
+	if !report.has_passing_analyses() {
+		return;
+	}
+
+	html.push_str("<h2>Passing</h2>\n<ul class=\"analyses\">\n");
+	for analysis in report.passing_analyses() {
+		let _ = writeln!(
+			html,
+			"<li><span class=\"passed\">&#10003;</span> {}{}</li>",
+			escape(&analysis.statement()),
+			explanation(analysis)
+		);
+	}
+	html.push_str("</ul>\n");
+}
+
+fn errored(html: &mut String, report: &Report) {
+	if !report.has_errored_analyses() {
+		return;
+	}
+
+	html.push_str("<h2>Errored</h2>\n<ul class=\"analyses\">\n");
+	for errored in report.errored_analyses() {
+		let _ = write!(
+			html,
+			"<li><span class=\"errored\">?</span> {}",
+			escape(&errored.top_msg())
+		);
+		let sources = errored.source_msgs();
+		if !sources.is_empty() {
+			html.push_str("<ul>");
+			for source in sources {
+				let _ = write!(html, "<li class=\"explanation\">{}</li>", escape(&source));
+			}
+			html.push_str("</ul>");
+		}
+		html.push_str("</li>\n");
+	}
+	html.push_str("</ul>\n");
+}
+
+fn skipped(html: &mut String, report: &Report) {
+	if !report.has_skipped_analyses() {
+		return;
+	}
+
+	html.push_str("<h2>Skipped</h2>\n<ul class=\"analyses\">\n");
+	for skipped in report.skipped_analyses() {
+		let _ = writeln!(
+			html,
+			"<li class=\"skipped\">{}</li>",
+			escape(&skipped.statement())
+		);
+	}
+	html.push_str("</ul>\n");
+}
+
+fn waived(html: &mut String, report: &Report) {
+	if !report.has_waived_concerns() {
+		return;
+	}
+
+	html.push_str("<h2>Waived</h2>\n<ul class=\"analyses\">\n");
+	for waived in report.waived_concerns() {
+		let _ = writeln!(
+			html,
+			"<li class=\"skipped\">{}<div class=\"explanation\">{}</div></li>",
+			escape(&waived.statement()),
+			escape(&waived.explanation())
+		);
+	}
+	html.push_str("</ul>\n");
+}
+
+/// Plugin sections are shown as written, since they may be Markdown or JSON.
+fn fragments(html: &mut String, report: &Report) {
+	if !report.has_fragments() {
+		return;
+	}
+
+	html.push_str("<h2>Plugin Sections</h2>\n");
+	for fragment in report.fragments() {
+		let _ = writeln!(
+			html,
+			"<h3>{}</h3>\n<p class=\"meta\">from {}</p>\n<pre>{}</pre>",
+			escape(&fragment.title),
+			escape(&fragment.plugin),
+			escape(&fragment.text())
+		);
+	}
+}
+
+fn risk_categories(html: &mut String, report: &Report) {
+	if !report.has_risk_categories() {
+		return;
+	}
+
+	html.push_str("<h2>Risk Categories</h2>\n<ul class=\"analyses\">\n");
+	for rollup in report.risk_categories() {
+		let class = if rollup.is_passing() {
+			"passed"
+		} else {
+			"failed"
+		};
+		let _ = writeln!(
+			html,
+			"<li class=\"{}\">{}</li>",
+			class,
+			escape(&rollup.statement())
+		);
+	}
+	html.push_str("</ul>\n");
+}
+
+fn baseline(html: &mut String, report: &Report) {
+	let Some(delta) = report.delta() else {
+		return;
+	};
+
+	let _ = writeln!(
+		html,
+		"<h2>Since Baseline</h2>\n<p class=\"meta\">{}</p>\n<ul class=\"analyses\">",
+		escape(&delta.against())
+	);
+	for analysis in delta.changed_analyses() {
+		let _ = writeln!(html, "<li>{}</li>", escape(&analysis.statement()));
+	}
+	let _ = writeln!(
+		html,
+		"<li class=\"skipped\">{} unchanged</li>\n<li>{}</li>\n</ul>",
+		delta.unchanged_count(),
+		escape(&delta.risk_score.statement())
+	);
+}
+
+fn explanation(analysis: &Analysis) -> String {
+	let explanation = analysis.explanation();
+	if explanation.is_empty() {
+		return String::new();
+	}
+
+	format!("<div class=\"explanation\">{}</div>", escape(&explanation))
+}
+
+/// Escape text for use in HTML content and attribute values.
+fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			'\'' => escaped.push_str("&#39;"),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn escapes_markup() {
+		assert_eq!(
+			escape("<script>alert('x' & \"y\")</script>"),
+			"&lt;script&gt;alert(&#39;x&#39; &amp; &quot;y&quot;)&lt;/script&gt;"
+		);
+	}
+
+	#[test]
+	fn template_has_every_placeholder() {
+		for placeholder in [
+			"{{version}}",
+			"{{repo}}",
+			"{{meta}}",
+			"{{recommendation}}",
+			"{{sections}}",
+		] {
+			assert!(TEMPLATE.contains(placeholder), "missing {}", placeholder);
+		}
+	}
+}
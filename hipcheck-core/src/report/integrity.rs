@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! A manifest of the working tree Hipcheck analyzed, to check a checkout
+//! against later.
+//!
+//! The manifest is a hash over the path and contents of every file in the
+//! tree, leaving out the `.git` directory, so a source delivery without its
+//! git history can be checked against the report that accompanies it. Any
+//! added, removed, renamed, or changed file changes the hash.
+
+use crate::{
+	error::{Context, Result},
+	hc_error,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+	fmt::{self, Display, Formatter},
+	fs::{self, File},
+	io,
+	path::Path,
+};
+use walkdir::{DirEntry, WalkDir};
+
+/// The version of the tree hash algorithm, so reports made with a different
+/// algorithm aren't mistaken for mismatches.
+pub const TREE_HASH_VERSION: &str = "hipcheck/tree/v1";
+
+/// The hash of an analyzed working tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct TreeManifest {
+	/// The algorithm used to hash the tree.
+	pub algorithm: String,
+	/// The hash of the tree, as hex.
+	pub hash: String,
+	/// The number of files hashed.
+	pub files: u64,
+}
+
+impl TreeManifest {
+	/// Hash the working tree rooted at a directory.
+	pub fn for_tree(root: &Path) -> Result<TreeManifest> {
+		let mut hasher = blake3::Hasher::new();
+		let mut files = 0;
+
+		let entries = WalkDir::new(root)
+			.sort_by_file_name()
+			.into_iter()
+			.filter_entry(|entry| !is_git_dir(entry));
+
+		for entry in entries {
+			let entry = entry.with_context(|| format!("failed to walk '{}'", root.display()))?;
+			let file_type = entry.file_type();
+			if file_type.is_dir() {
+				continue;
+			}
+
+			// Paths are hashed with `/` separators, so the hash doesn't depend on
+			// the platform
+			let path = entry
+				.path()
+				.strip_prefix(root)
+				.unwrap_or(entry.path())
+				.components()
+				.map(|component| component.as_os_str().to_string_lossy())
+				.collect::<Vec<_>>()
+				.join("/");
+			hasher.update(path.as_bytes());
+			hasher.update(b"\0");
+
+			if file_type.is_symlink() {
+				let target = fs::read_link(entry.path())
+					.with_context(|| format!("failed to read link '{}'", path))?;
+				hasher.update(b"link\0");
+				hasher.update(target.to_string_lossy().as_bytes());
+			} else {
+				let mut file = File::open(entry.path())
+					.with_context(|| format!("failed to open '{}'", path))?;
+				let mut contents = blake3::Hasher::new();
+				io::copy(&mut file, &mut contents)
+					.with_context(|| format!("failed to read '{}'", path))?;
+				hasher.update(b"file\0");
+				hasher.update(contents.finalize().as_bytes());
+			}
+			hasher.update(b"\0");
+			files += 1;
+		}
+
+		Ok(TreeManifest {
+			algorithm: TREE_HASH_VERSION.to_owned(),
+			hash: hasher.finalize().to_hex().to_string(),
+			files,
+		})
+	}
+}
+
+impl Display for TreeManifest {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{} ({} files)", self.hash, self.files)
+	}
+}
+
+fn is_git_dir(entry: &DirEntry) -> bool {
+	entry.file_type().is_dir() && entry.file_name() == ".git"
+}
+
+/// The parts of a JSON report needed to check a checkout against it.
+#[derive(Debug, Deserialize)]
+struct AnalyzedTree {
+	repo_name: String,
+	repo_head: String,
+	target_tree: Option<TreeManifest>,
+}
+
+/// Check that a checkout matches the working tree a JSON report was made from.
+///
+/// Returns a description of the match, or an error describing the mismatch.
+pub fn verify_report(report: &Path, target: &Path) -> Result<String> {
+	let contents = fs::read_to_string(report)
+		.with_context(|| format!("failed to read report '{}'", report.display()))?;
+	let analyzed: AnalyzedTree = serde_json::from_str(&contents)
+		.with_context(|| format!("failed to parse report '{}'", report.display()))?;
+
+	let Some(expected) = analyzed.target_tree else {
+		return Err(hc_error!(
+			"report '{}' has no manifest of the analyzed tree; it may be from an older version of Hipcheck",
+			report.display()
+		));
+	};
+	if expected.algorithm != TREE_HASH_VERSION {
+		return Err(hc_error!(
+			"report '{}' hashed the analyzed tree with '{}', but this version of Hipcheck uses '{}'",
+			report.display(),
+			expected.algorithm,
+			TREE_HASH_VERSION
+		));
+	}
+
+	let found = TreeManifest::for_tree(target)?;
+	if found.hash != expected.hash {
+		return Err(hc_error!(
+			"'{}' does not match the tree analyzed for {} ({}): expected {}, found {}",
+			target.display(),
+			analyzed.repo_name,
+			analyzed.repo_head,
+			expected,
+			found
+		));
+	}
+
+	Ok(format!(
+		"'{}' matches the tree analyzed for {} ({}): {}",
+		target.display(),
+		analyzed.repo_name,
+		analyzed.repo_head,
+		found
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+
+	fn write(root: &Path, path: &str, contents: &str) {
+		let path = root.join(path);
+		fs::create_dir_all(path.parent().unwrap()).unwrap();
+		fs::write(path, contents).unwrap();
+	}
+
+	fn tree(files: &[(&str, &str)]) -> TempDir {
+		let root = TempDir::new().unwrap();
+		for (path, contents) in files {
+			write(root.path(), path, contents);
+		}
+		root
+	}
+
+	#[test]
+	fn hash_ignores_git_dir() {
+		let files = [("README.md", "# demo\n"), ("src/lib.rs", "pub fn f() {}\n")];
+		let a = tree(&files);
+		let b = tree(&files);
+		write(b.path(), ".git/HEAD", "ref: refs/heads/main\n");
+
+		let a = TreeManifest::for_tree(a.path()).unwrap();
+		let b = TreeManifest::for_tree(b.path()).unwrap();
+		assert_eq!(a, b);
+		assert_eq!(a.files, 2);
+	}
+
+	#[test]
+	fn hash_covers_names_and_contents() {
+		let hash = |files: &[(&str, &str)]| TreeManifest::for_tree(tree(files).path()).unwrap();
+		let original = hash(&[("src/lib.rs", "pub fn f() {}\n")]);
+
+		assert_ne!(original, hash(&[("src/lib.rs", "pub fn g() {}\n")]));
+		assert_ne!(original, hash(&[("src/main.rs", "pub fn f() {}\n")]));
+		assert_ne!(
+			original,
+			hash(&[("src/lib.rs", "pub fn f() {}\n"), ("build.rs", "")])
+		);
+	}
+
+	#[test]
+	fn verifies_reports() {
+		let target = tree(&[("src/lib.rs", "pub fn f() {}\n")]);
+		let manifest = TreeManifest::for_tree(target.path()).unwrap();
+		let dir = TempDir::new().unwrap();
+		let report = dir.path().join("report.json");
+		let json = serde_json::json!({
+			"repo_name": "demo",
+			"repo_head": "abc123",
+			"target_tree": manifest,
+		});
+		fs::write(&report, json.to_string()).unwrap();
+
+		assert!(verify_report(&report, target.path()).is_ok());
+
+		write(target.path(), "src/lib.rs", "pub fn g() {}\n");
+		let error = verify_report(&report, target.path()).unwrap_err();
+		assert!(error.to_string().contains("does not match"));
+	}
+}
@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rendering reports as JUnit XML, for the test report views of CI systems
+//! like Jenkins and GitLab.
+//!
+//! Each report becomes a test suite named after the analyzed target, and each
+//! analysis in it becomes a test case:
+//!
+//! - Passing analyses are passing test cases.
+//! - Failing analyses are test cases with a `<failure>`, whose message is the
+//!   analysis' statement and whose text lists its concerns.
+//! - Errored analyses are test cases with an `<error>`.
+//! - Skipped analyses are test cases marked `<skipped>`.
+//!
+//! The risk score and recommendation are kept as properties of the suite.
+
+use crate::report::{RecommendationKind, Report};
+use std::fmt::Write as _;
+
+/// Render reports as a single JUnit XML document, with a suite per report.
+pub fn render<'a>(reports: impl IntoIterator<Item = &'a Report>) -> String {
+	let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+	for report in reports {
+		suite(&mut xml, report);
+	}
+	xml.push_str("</testsuites>\n");
+	xml
+}
+
+fn suite(xml: &mut String, report: &Report) {
+	let tests =
+		report.passing.len() + report.failing.len() + report.errored.len() + report.skipped.len();
+	let _ = writeln!(
+		xml,
+		"  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" timestamp=\"{}\">",
+		escape(&report.analyzed()),
+		tests,
+		report.failing.len(),
+		report.errored.len(),
+		report.skipped.len(),
+		escape(&report.analyzed_at.0.to_rfc3339())
+	);
+
+	let recommendation = report.recommendation();
+	let kind = match recommendation.kind {
+		RecommendationKind::Pass => "pass",
+		RecommendationKind::UseWithConditions => "use-with-conditions",
+		RecommendationKind::Investigate => "investigate",
+	};
+	xml.push_str("    <properties>\n");
+	property(xml, "hipcheck.version", &report.hipcheck_version);
+	property(xml, "risk-score", &recommendation.risk_score.0.to_string());
+	property(xml, "recommendation", kind);
+	xml.push_str("    </properties>\n");
+
+	for analysis in report.passing_analyses() {
+		let _ = writeln!(xml, "    {} />", test_case(&analysis.name));
+	}
+
+	for failing in report.failing_analyses() {
+		let analysis = failing.analysis();
+		let _ = writeln!(xml, "    {}>", test_case(&analysis.name));
+		let mut text = analysis.explanation();
+		for concern in failing.concern_details() {
+			text.push('\n');
+			text.push_str(&concern.statement());
+		}
+		if let Some(omitted) = failing.omitted_summary() {
+			text.push('\n');
+			text.push_str(&omitted);
+		}
+		let _ = writeln!(
+			xml,
+			"      <failure message=\"{}\">{}</failure>",
+			escape(&analysis.statement()),
+			escape(&text)
+		);
+		xml.push_str("    </testcase>\n");
+	}
+
+	for errored in report.errored_analyses() {
+		let _ = writeln!(xml, "    {}>", test_case(&errored.analysis().to_string()));
+		let _ = writeln!(
+			xml,
+			"      <error message=\"{}\">{}</error>",
+			escape(&errored.top_msg()),
+			escape(&errored.source_msgs().join("\n"))
+		);
+		xml.push_str("    </testcase>\n");
+	}
+
+	for skipped in report.skipped_analyses() {
+		let _ = writeln!(xml, "    {}>", test_case(&skipped.analysis().to_string()));
+		let _ = writeln!(
+			xml,
+			"      <skipped message=\"{}\" />",
+			escape(&skipped.statement())
+		);
+		xml.push_str("    </testcase>\n");
+	}
+
+	xml.push_str("  </testsuite>\n");
+}
+
+/// Open a test case for an analysis, leaving the tag unclosed.
+///
+/// The publisher is used as the class name, so CI systems group analyses
+/// by who publishes them.
+fn test_case(analysis: &str) -> String {
+	let (publisher, _) = analysis.split_once('/').unwrap_or(("hipcheck", analysis));
+	format!(
+		"<testcase classname=\"{}\" name=\"{}\"",
+		escape(publisher),
+		escape(analysis)
+	)
+}
+
+fn property(xml: &mut String, name: &str, value: &str) {
+	let _ = writeln!(
+		xml,
+		"      <property name=\"{}\" value=\"{}\" />",
+		escape(name),
+		escape(value)
+	);
+}
+
+/// Escape text for XML, leaving out the control characters XML 1.0 can't hold.
+fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			'\'' => escaped.push_str("&apos;"),
+			'\t' | '\n' | '\r' => escaped.push(c),
+			c if c.is_control() => {}
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn escapes_markup_and_control_characters() {
+		assert_eq!(
+			escape("<a href='x'>\"b\" & c\u{1b}[0m</a>\n"),
+			"&lt;a href=&apos;x&apos;&gt;&quot;b&quot; &amp; c[0m&lt;/a&gt;\n"
+		);
+	}
+
+	#[test]
+	fn test_cases_are_grouped_by_publisher() {
+		assert_eq!(
+			test_case("mitre/typo"),
+			"<testcase classname=\"mitre\" name=\"mitre/typo\""
+		);
+		assert_eq!(
+			test_case("legacy"),
+			"<testcase classname=\"hipcheck\" name=\"legacy\""
+		);
+	}
+}
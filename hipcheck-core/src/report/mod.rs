@@ -0,0 +1,1037 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// A report encapsulates the results of a run of Hipcheck, specifically containing:
+//
+// 1. The successes (which analyses passed, with user-friendly explanations of what's good)
+// 2. The concerns (which analyses failed, and _why_)
+// 3. The recommendation (pass or investigate)
+
+// The report serves double-duty, because it's both the thing used to print user-friendly
+// results on the CLI, and the type that's serialized out to JSON for machine-friendly output.
+
+pub mod batch;
+pub mod concern;
+pub mod contributors;
+pub mod diff;
+pub mod features;
+pub mod fingerprint;
+pub mod fragment;
+pub mod github;
+pub mod html;
+pub mod integrity;
+pub mod junit;
+pub mod portfolio;
+pub mod report_builder;
+pub mod sample;
+pub mod sarif;
+pub mod schema;
+pub mod table;
+
+use crate::{
+	error::{Context, Error, Result},
+	policy::policy_file::{FailOn, PolicyWaiver, RiskCategory},
+	policy_exprs::{std_exec, Expr},
+	report::{
+		concern::Concern, diff::ReportDelta, fragment::ReportFragment, integrity::TreeManifest,
+		schema::SchemaVersion,
+	},
+	shell::Shell,
+	version::VersionQuery,
+};
+use chrono::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::{
+	default::Default,
+	fmt,
+	fmt::{Display, Formatter},
+	iter::Iterator,
+	ops::Not as _,
+	result::Result as StdResult,
+	sync::Arc,
+};
+
+/// The report output to the user.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct Report {
+	/// The major version of the schema the report is written in.
+	#[serde(skip_serializing_if = "SchemaVersion::is_unversioned")]
+	pub schema_version: SchemaVersion,
+
+	/// The name of the repository being analyzed.
+	pub repo_name: Arc<String>,
+
+	/// The HEAD commit hash of the repository during analysis.
+	pub repo_head: Arc<String>,
+
+	/// The version of Hipcheck used to analyze the repo.
+	pub hipcheck_version: String,
+
+	/// When the analysis was performed.
+	pub analyzed_at: Timestamp,
+
+	/// A hash of the working tree that was analyzed, to check checkouts against.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub target_tree: Option<TreeManifest>,
+
+	/// The package the repository was found from, if the target was one.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub package: Option<AnalyzedPackage>,
+
+	/// What analyses passed.
+	pub passing: Vec<PassingAnalysis>,
+
+	/// What analyses did _not_ pass, and why.
+	pub failing: Vec<FailingAnalysis>,
+
+	/// What analyses errored out, and why.
+	pub errored: Vec<ErroredAnalysis>,
+
+	/// What analyses didn't apply to the target, and why.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub skipped: Vec<SkippedAnalysis>,
+
+	/// What concerns the policy file waived, and why.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub waived: Vec<WaivedConcern>,
+
+	/// How the analyses in each risk category fared.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub risk_categories: Vec<RiskCategoryRollup>,
+
+	/// Sections plugins added to the report.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub fragments: Vec<ReportFragment>,
+
+	/// How much each analysis added to the risk score.
+	pub score_breakdown: Vec<ScoreContribution>,
+
+	/// The final recommendation to the user.
+	pub recommendation: Recommendation,
+
+	/// How the report differs from a baseline report, if one was given.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub delta: Option<ReportDelta>,
+
+	/// When the policy file says `hc check` should fail, if it says.
+	#[serde(skip)]
+	pub fail_on: Option<FailOn>,
+}
+
+impl Report {
+	/// Get the repository that was analyzed.
+	pub fn analyzed(&self) -> String {
+		match &self.package {
+			Some(package) => format!("{}, from {} ({})", package, self.repo_name, self.repo_head),
+			None => format!("{} ({})", self.repo_name, self.repo_head),
+		}
+	}
+
+	/// Get the version of Hipcheck used for the analysis.
+	pub fn using(&self) -> String {
+		format!("using Hipcheck {}", self.hipcheck_version)
+	}
+
+	// Get the time that the analysis occured.
+	pub fn at_time(&self) -> String {
+		format!("on {}", self.analyzed_at)
+	}
+
+	/// Check if there are passing analyses.
+	pub fn has_passing_analyses(&self) -> bool {
+		self.passing.is_empty().not()
+	}
+
+	/// Check if there are failing analyses.
+	pub fn has_failing_analyses(&self) -> bool {
+		self.failing.is_empty().not()
+	}
+
+	/// Check if there are errored analyses.
+	pub fn has_errored_analyses(&self) -> bool {
+		self.errored.is_empty().not()
+	}
+
+	/// Check if there are skipped analyses.
+	pub fn has_skipped_analyses(&self) -> bool {
+		self.skipped.is_empty().not()
+	}
+
+	/// Check if any concerns were waived.
+	pub fn has_waived_concerns(&self) -> bool {
+		self.waived.is_empty().not()
+	}
+
+	/// Get an iterator over all passing analyses.
+	pub fn passing_analyses(&self) -> impl Iterator<Item = &Analysis> {
+		self.passing.iter().map(|a| &a.0)
+	}
+
+	/// Get an iterator over all failing analyses.
+	pub fn failing_analyses(&self) -> impl Iterator<Item = &FailingAnalysis> {
+		self.failing.iter()
+	}
+
+	/// Get an iterator over all errored analyses.
+	pub fn errored_analyses(&self) -> impl Iterator<Item = &ErroredAnalysis> {
+		self.errored.iter()
+	}
+
+	/// Get an iterator over all skipped analyses.
+	pub fn skipped_analyses(&self) -> impl Iterator<Item = &SkippedAnalysis> {
+		self.skipped.iter()
+	}
+
+	/// Get an iterator over all waived concerns.
+	pub fn waived_concerns(&self) -> impl Iterator<Item = &WaivedConcern> {
+		self.waived.iter()
+	}
+
+	/// Check if any analyses were tagged with risk categories.
+	pub fn has_risk_categories(&self) -> bool {
+		self.risk_categories.is_empty().not()
+	}
+
+	/// Get an iterator over the per-category summaries.
+	pub fn risk_categories(&self) -> impl Iterator<Item = &RiskCategoryRollup> {
+		self.risk_categories.iter()
+	}
+
+	/// Check if any plugins added sections to the report.
+	pub fn has_fragments(&self) -> bool {
+		self.fragments.is_empty().not()
+	}
+
+	/// Get an iterator over the sections plugins added to the report.
+	pub fn fragments(&self) -> impl Iterator<Item = &ReportFragment> {
+		self.fragments.iter()
+	}
+
+	/// Check if there are any analyses in the score breakdown.
+	pub fn has_score_breakdown(&self) -> bool {
+		self.score_breakdown.is_empty().not()
+	}
+
+	/// Get how much each analysis added to the risk score, largest first.
+	pub fn score_breakdown(&self) -> impl Iterator<Item = &ScoreContribution> {
+		self.score_breakdown.iter()
+	}
+
+	/// Get the final recommendation.
+	pub fn recommendation(&self) -> &Recommendation {
+		&self.recommendation
+	}
+
+	/// Get the comparison against the baseline report, if any.
+	pub fn delta(&self) -> Option<&ReportDelta> {
+		self.delta.as_ref()
+	}
+}
+
+/// A package from a registry, analyzed through its source repository.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct AnalyzedPackage {
+	/// The registry the package is published on, like `crates.io`.
+	pub registry: String,
+	/// The name of the package.
+	pub name: String,
+	/// The version of the package which was analyzed.
+	pub version: String,
+}
+
+impl Display for AnalyzedPackage {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{}@{} on {}", self.name, self.version, self.registry)
+	}
+}
+
+/// How the analyses tagged with one risk category fared.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct RiskCategoryRollup {
+	/// The risk category.
+	pub category: RiskCategory,
+	/// How many analyses in the category passed.
+	pub passing: usize,
+	/// How many analyses in the category failed.
+	pub failing: usize,
+	/// How many analyses in the category errored out.
+	pub errored: usize,
+	/// How many concerns the failing analyses in the category identified.
+	pub concerns: usize,
+}
+
+impl RiskCategoryRollup {
+	pub fn new(category: RiskCategory) -> Self {
+		RiskCategoryRollup {
+			category,
+			passing: 0,
+			failing: 0,
+			errored: 0,
+			concerns: 0,
+		}
+	}
+
+	/// Check if every analysis in the category passed.
+	pub fn is_passing(&self) -> bool {
+		self.failing == 0 && self.errored == 0
+	}
+
+	pub fn statement(&self) -> String {
+		let total = self.passing + self.failing + self.errored;
+
+		let mut statement = format!(
+			"{}: {} of {} analyses failed",
+			self.category, self.failing, total
+		);
+
+		if self.errored > 0 {
+			statement.push_str(&format!(", {} errored", self.errored));
+		}
+
+		if self.concerns > 0 {
+			statement.push_str(&format!(
+				" ({} concerns)",
+				with_separators(self.concerns)
+			));
+		}
+
+		statement
+	}
+}
+
+/// An analysis which passed.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(transparent)]
+#[schemars(crate = "schemars")]
+pub struct PassingAnalysis(
+	/// The analysis which passed.
+	Analysis,
+);
+
+impl PassingAnalysis {
+	pub fn new(analysis: Analysis) -> PassingAnalysis {
+		PassingAnalysis(analysis)
+	}
+}
+
+/// An analysis which failed, including potential specific concerns.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct FailingAnalysis {
+	/// The analysis.
+	#[serde(flatten)]
+	analysis: Analysis,
+
+	/// Any concerns the analysis identified.
+	#[serde(skip_serializing_if = "no_concerns")]
+	concerns: Vec<String>,
+
+	/// The details the plugin gave about each concern, like its severity and
+	/// the file it's about, in the same order as `concerns`.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	concern_details: Vec<Concern>,
+
+	/// How many concerns were left out of `concerns` to keep the report readable.
+	#[serde(skip_serializing_if = "no_omitted_concerns")]
+	omitted_concerns: usize,
+
+	/// A stable fingerprint for every concern, in the same order as `concerns`
+	/// and including the omitted ones, to track concerns across runs.
+	#[serde(skip_serializing_if = "no_concerns")]
+	concern_fingerprints: Vec<String>,
+}
+
+impl FailingAnalysis {
+	/// Construct a new failing analysis, verifying that concerns are appropriate.
+	pub fn new(analysis: Analysis, concerns: Vec<Concern>) -> Result<FailingAnalysis> {
+		let concern_fingerprints = concerns
+			.iter()
+			.map(|concern| concern.fingerprint(&analysis.name))
+			.collect();
+
+		Ok(FailingAnalysis {
+			analysis,
+			concerns: concerns
+				.iter()
+				.map(|concern| concern.message.clone())
+				.collect(),
+			concern_details: concerns,
+			omitted_concerns: 0,
+			concern_fingerprints,
+		})
+	}
+
+	/// Keep only the first `limit` concerns, recording how many were left out.
+	pub fn limit_concerns(mut self, limit: usize) -> FailingAnalysis {
+		if self.concerns.len() > limit {
+			self.omitted_concerns += self.concerns.len() - limit;
+			self.concerns.truncate(limit);
+			self.concern_details.truncate(limit);
+		}
+
+		self
+	}
+
+	pub fn analysis(&self) -> &Analysis {
+		&self.analysis
+	}
+
+	pub fn concerns(&self) -> impl Iterator<Item = &String> {
+		self.concerns.iter()
+	}
+
+	/// Get each concern in the report with the details the plugin gave.
+	pub fn concern_details(&self) -> impl Iterator<Item = &Concern> {
+		self.concern_details.iter()
+	}
+
+	/// Get each concern in the report along with its fingerprint.
+	pub fn fingerprinted_concerns(&self) -> impl Iterator<Item = (&Concern, &String)> {
+		self.concern_details.iter().zip(&self.concern_fingerprints)
+	}
+
+	/// The fingerprints of every concern, including omitted ones.
+	pub fn concern_fingerprints(&self) -> &[String] {
+		&self.concern_fingerprints
+	}
+
+	/// The number of concerns left out of the report.
+	pub fn omitted_concerns(&self) -> usize {
+		self.omitted_concerns
+	}
+
+	/// The total number of concerns the analysis identified, including omitted ones.
+	pub fn concern_count(&self) -> usize {
+		self.concerns.len() + self.omitted_concerns
+	}
+
+	/// A summary of the concerns left out of the report, if any were.
+	pub fn omitted_summary(&self) -> Option<String> {
+		if self.omitted_concerns == 0 {
+			return None;
+		}
+
+		Some(format!(
+			"{} more concerns not shown; first {} shown (use --all-concerns to show all)",
+			with_separators(self.omitted_concerns),
+			with_separators(self.concerns.len())
+		))
+	}
+}
+
+/// Is the concern list empty?
+///
+/// This is a helper function for serialization of `FailedAnalysis`.
+fn no_concerns(concerns: &[String]) -> bool {
+	concerns.is_empty()
+}
+
+/// Were no concerns omitted?
+///
+/// This is a helper function for serialization of `FailedAnalysis`.
+fn no_omitted_concerns(omitted: &usize) -> bool {
+	*omitted == 0
+}
+
+/// Format a count with separators between each group of three digits, as
+/// the user's locale does.
+fn with_separators(n: usize) -> String {
+	Shell::get_locale().count(n)
+}
+
+/// An analysis that did _not_ succeed.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct ErroredAnalysis {
+	analysis: AnalysisIdent,
+	error: ErrorReport,
+	/// Whether the analysis errored because a plugin query timed out.
+	#[serde(skip_serializing_if = "std::ops::Not::not")]
+	timed_out: bool,
+}
+
+impl ErroredAnalysis {
+	/// Construct a new `ErroredAnalysis`.
+	pub fn new(analysis: AnalysisIdent, error: &Error) -> Self {
+		ErroredAnalysis {
+			analysis,
+			error: ErrorReport::from(error),
+			timed_out: error.timeout().is_some(),
+		}
+	}
+
+	pub fn analysis(&self) -> &AnalysisIdent {
+		&self.analysis
+	}
+
+	pub fn top_msg(&self) -> String {
+		format!("{} analysis error: {}", self.analysis, self.error.msg)
+	}
+
+	pub fn source_msgs(&self) -> Vec<String> {
+		let mut msgs = Vec::new();
+
+		try_add_msg(&mut msgs, &self.error.source);
+
+		msgs
+	}
+}
+
+/// An analysis that couldn't run because it doesn't apply to the target.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct SkippedAnalysis {
+	analysis: AnalysisIdent,
+	reason: String,
+}
+
+impl SkippedAnalysis {
+	/// Construct a new `SkippedAnalysis`.
+	pub fn new(analysis: AnalysisIdent, reason: impl Into<String>) -> Self {
+		SkippedAnalysis {
+			analysis,
+			reason: reason.into(),
+		}
+	}
+
+	pub fn analysis(&self) -> &AnalysisIdent {
+		&self.analysis
+	}
+
+	pub fn statement(&self) -> String {
+		format!("'{}' skipped, {}", self.analysis, self.reason)
+	}
+}
+
+/// A concern left out of a failing analysis because the policy file waived it.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct WaivedConcern {
+	analysis: String,
+	concern: String,
+	/// The details the plugin gave about the concern.
+	concern_details: Concern,
+	justification: String,
+	/// The last day the waiver applies.
+	expires: NaiveDate,
+}
+
+impl WaivedConcern {
+	/// Construct a new `WaivedConcern`.
+	pub fn new(analysis: &str, concern: Concern, waiver: &PolicyWaiver) -> Self {
+		WaivedConcern {
+			analysis: analysis.to_owned(),
+			concern: concern.message.clone(),
+			concern_details: concern,
+			justification: waiver.justification.clone(),
+			expires: waiver.expires,
+		}
+	}
+
+	pub fn analysis(&self) -> &str {
+		&self.analysis
+	}
+
+	pub fn concern(&self) -> &str {
+		&self.concern
+	}
+
+	pub fn concern_details(&self) -> &Concern {
+		&self.concern_details
+	}
+
+	pub fn justification(&self) -> &str {
+		&self.justification
+	}
+
+	pub fn statement(&self) -> String {
+		format!("'{}': {}", self.analysis, self.concern_details.statement())
+	}
+
+	pub fn explanation(&self) -> String {
+		format!("waived until {}: {}", self.expires, self.justification)
+	}
+}
+
+fn try_add_msg(msgs: &mut Vec<String>, error_report: &Option<Box<ErrorReport>>) {
+	if let Some(error_report) = error_report {
+		msgs.push(error_report.msg.clone());
+		try_add_msg(msgs, &error_report.source);
+	}
+}
+
+/// The name of the analyses.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct AnalysisIdent(String);
+
+impl Display for AnalysisIdent {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		write!(f, "{}", self.0)
+	}
+}
+
+/// A simple, serializable version of `Error`.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct ErrorReport {
+	msg: String,
+	#[serde(skip_serializing_if = "source_is_none")]
+	source: Option<Box<ErrorReport>>,
+}
+
+fn source_is_none(source: &Option<Box<ErrorReport>>) -> bool {
+	source.is_none()
+}
+
+impl From<&Error> for ErrorReport {
+	fn from(error: &Error) -> ErrorReport {
+		tracing::trace!("detailed error for report [error: {:#?}]", error);
+
+		let mut errors = error
+			.chain()
+			// This `collect` is needed because `hc_error::Chain` isn't a
+			// double-ended iterator, so it can't be reversed without
+			// first collecting into an intermediate container.
+			.collect::<Vec<_>>()
+			.into_iter()
+			.rev();
+
+		let mut report = ErrorReport {
+			// SAFETY: We're always guaranteed a minimum of one error
+			// message, so this is safe.
+			msg: errors.next().unwrap().to_string(),
+			source: None,
+		};
+
+		for error in errors {
+			report = ErrorReport {
+				msg: error.to_string(),
+				source: Some(Box::new(report)),
+			};
+		}
+
+		report
+	}
+}
+
+impl From<&(dyn std::error::Error + 'static)> for ErrorReport {
+	fn from(error: &(dyn std::error::Error + 'static)) -> ErrorReport {
+		let msg = error.to_string();
+		let source = error
+			.source()
+			.map(|error| Box::new(ErrorReport::from(error)));
+		ErrorReport { msg, source }
+	}
+}
+
+/// An analysis, with score and threshold.
+#[derive(Debug, Serialize, JsonSchema, Clone)]
+#[serde(tag = "analysis")]
+#[schemars(crate = "schemars")]
+pub struct Analysis {
+	/// The name of the plugin.
+	name: String,
+
+	/// If the analysis is passing or not.
+	///
+	/// Same as with the message, this is computed eagerly in the case of
+	/// plugin analyses.
+	passed: bool,
+
+	/// The policy expression used for the plugin.
+	///
+	/// We use this when printing the result to help explain to the user
+	/// *why* an analysis failed.
+	#[schemars(schema_with = "String::json_schema")]
+	policy_expr: Expr,
+
+	/// The default query explanation pulled from RPC with the plugin.
+	message: String,
+
+	/// The risk categories the policy file tagged the analysis with.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	risk_categories: Vec<RiskCategory>,
+
+	/// The share of the risk score the analysis adds if it fails, from the
+	/// weights in the policy file.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	weight: Option<f64>,
+
+	/// The raw output of the analysis, before the policy expression was applied.
+	///
+	/// This is not part of the JSON report, but is used to produce feature
+	/// vectors for downstream consumers.
+	#[serde(skip)]
+	value: Option<Value>,
+}
+
+// fn custom_schema(generator: &mut SchemaGenerator) -> Schema {
+// 	let mut schema = String::json_schema(generator);
+// 	schema
+// }
+
+impl Analysis {
+	pub fn plugin(
+		name: String,
+		passed: bool,
+		policy_expr: Expr,
+		message: String,
+		value: Option<Value>,
+	) -> Self {
+		Analysis {
+			name,
+			passed,
+			policy_expr,
+			message,
+			risk_categories: Vec::new(),
+			weight: None,
+			value,
+		}
+	}
+
+	/// Set the share of the risk score the analysis adds if it fails.
+	pub fn with_weight(mut self, weight: f64) -> Self {
+		self.weight = Some(weight);
+		self
+	}
+
+	/// Get the share of the risk score the analysis adds if it fails, if known.
+	pub fn weight(&self) -> Option<f64> {
+		self.weight
+	}
+
+	pub fn is_passing(&self) -> bool {
+		self.passed
+	}
+
+	pub fn statement(&self) -> String {
+		if self.is_passing() {
+			format!("'{}' passed, {}", self.name, self.policy_expr)
+		} else {
+			format!("'{}' failed, {}", self.name, self.policy_expr)
+		}
+	}
+
+	pub fn explanation(&self) -> String {
+		self.message.clone()
+	}
+
+	pub fn value(&self) -> Option<&Value> {
+		self.value.as_ref()
+	}
+
+	pub fn risk_categories(&self) -> &[RiskCategory] {
+		&self.risk_categories
+	}
+}
+
+/// Value and threshold for counting-based analyses.
+#[derive(Debug, Serialize, JsonSchema, Clone)]
+#[schemars(crate = "schemars")]
+pub struct Count {
+	value: u64,
+	policy: String,
+}
+
+/// Value for binary-based analyses.
+#[derive(Debug, Serialize, JsonSchema, Clone)]
+#[schemars(crate = "schemars")]
+pub struct Exists {
+	value: bool,
+	policy: String,
+}
+
+/// Value and threshold for percentage-based analyses.
+#[derive(Debug, Serialize, JsonSchema, Clone)]
+#[schemars(crate = "schemars")]
+pub struct Percent {
+	value: f64,
+	policy: String,
+}
+
+/// How much one analysis added to the final risk score.
+#[derive(Debug, Serialize, JsonSchema, Clone)]
+#[schemars(crate = "schemars")]
+pub struct ScoreContribution {
+	/// The name of the analysis.
+	pub analysis: String,
+
+	/// The raw output of the analysis, if it produced one.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub value: Option<Value>,
+
+	/// The analysis' score: 0 if it passed, or 1 if it didn't.
+	pub score: f64,
+
+	/// The analysis' share of the risk score, from the weights in the policy file.
+	pub weight: f64,
+
+	/// How much the analysis added to the risk score, its score times its weight.
+	pub contribution: f64,
+}
+
+impl ScoreContribution {
+	pub fn new(analysis: String, value: Option<Value>, passed: bool, weight: f64) -> Self {
+		let score = if passed { 0.0 } else { 1.0 };
+
+		ScoreContribution {
+			analysis,
+			value,
+			score,
+			weight,
+			contribution: score * weight,
+		}
+	}
+
+	/// Get a short description of the analysis' raw output, to fit in a table.
+	pub fn value_summary(&self) -> String {
+		const MAX_STRING_LEN: usize = 20;
+
+		match &self.value {
+			None => "-".to_owned(),
+			Some(Value::String(s)) if s.chars().count() > MAX_STRING_LEN => {
+				format!("{} chars", Shell::get_locale().count(s.chars().count()))
+			}
+			Some(Value::String(s)) => s.clone(),
+			Some(Value::Array(a)) => format!("{} items", Shell::get_locale().count(a.len())),
+			Some(Value::Object(o)) => format!("{} fields", Shell::get_locale().count(o.len())),
+			Some(value) => value.to_string(),
+		}
+	}
+}
+
+/// A final recommendation of whether to use or investigate a piece of software,
+/// including the risk threshold associated with that decision.
+#[derive(Debug, Serialize, JsonSchema, Clone)]
+#[schemars(crate = "schemars")]
+pub struct Recommendation {
+	pub kind: RecommendationKind,
+	risk_score: RiskScore,
+	risk_policy: RiskPolicy,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	conditions_policy: Option<RiskPolicy>,
+	/// Custom wording for the recommendation, configured in the policy file.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub message: Option<String>,
+}
+
+impl Recommendation {
+	/// Make a recommendation.
+	pub fn is(
+		risk_score: RiskScore,
+		risk_policy: RiskPolicy,
+		conditions_policy: Option<RiskPolicy>,
+	) -> Result<Recommendation> {
+		let kind = RecommendationKind::is(risk_score, &risk_policy, conditions_policy.as_ref())?;
+
+		Ok(Recommendation {
+			kind,
+			risk_score,
+			risk_policy,
+			conditions_policy,
+			message: None,
+		})
+	}
+
+	pub fn statement(&self) -> String {
+		let risk_score = Shell::get_locale().decimal(self.risk_score.0, 2);
+
+		match &self.conditions_policy {
+			Some(conditions_policy) => format!(
+				"risk rated as {}, policy was {}, conditions policy was {}",
+				risk_score, self.risk_policy.expr, conditions_policy.expr
+			),
+			None => format!(
+				"risk rated as {}, policy was {}",
+				risk_score, self.risk_policy.expr
+			),
+		}
+	}
+}
+
+/// The kind of recommendation being made.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[schemars(crate = "schemars")]
+pub enum RecommendationKind {
+	Pass,
+	UseWithConditions,
+	Investigate,
+}
+
+impl RecommendationKind {
+	fn is(
+		risk_score: RiskScore,
+		risk_policy: &RiskPolicy,
+		conditions_policy: Option<&RiskPolicy>,
+	) -> Result<RecommendationKind> {
+		let value = serde_json::to_value(risk_score.0).unwrap();
+
+		if std_exec(risk_policy.expr.clone(), Some(&value))
+			.context("investigate policy expression execution failed")?
+		{
+			return Ok(RecommendationKind::Pass);
+		}
+
+		// A score which doesn't pass may still fall in the "use with conditions" band.
+		if let Some(conditions_policy) = conditions_policy {
+			if std_exec(conditions_policy.expr.clone(), Some(&value))
+				.context("use-with-conditions policy expression execution failed")?
+			{
+				return Ok(RecommendationKind::UseWithConditions);
+			}
+		}
+
+		Ok(RecommendationKind::Investigate)
+	}
+}
+
+/// The overall final risk score for a repo.
+#[derive(Debug, Serialize, JsonSchema, Clone, Copy)]
+#[serde(transparent)]
+#[schemars(crate = "schemars")]
+pub struct RiskScore(pub f64);
+
+/// The risk threshold configured for the Hipcheck session.
+#[derive(Debug, Serialize, JsonSchema, Clone)]
+#[serde(transparent)]
+#[schemars(crate = "schemars")]
+pub struct RiskPolicy {
+	#[schemars(schema_with = "String::json_schema")]
+	pub expr: Expr,
+}
+impl RiskPolicy {
+	pub fn new(expr: Expr) -> Self {
+		RiskPolicy { expr }
+	}
+}
+
+/// A serializable and printable wrapper around a datetime with the local timezone.
+#[derive(Debug, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct Timestamp(DateTime<Local>);
+
+impl From<DateTime<FixedOffset>> for Timestamp {
+	fn from(date_time: DateTime<FixedOffset>) -> Timestamp {
+		Timestamp(date_time.with_timezone(&Local))
+	}
+}
+
+impl Display for Timestamp {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		// This is more human-readable than RFC 3339, which is good since this method
+		// will be used when outputting to end-users on the CLI.
+		write!(f, "{}", Shell::get_locale().date_time(&self.0))
+	}
+}
+
+impl Serialize for Timestamp {
+	fn serialize<S>(&self, serializer: S) -> StdResult<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		// The format is "1996-12-19T16:39:57-08:00"
+		//
+		// This isn't very human readable, but in the case of human output we won't be
+		// serializing anyway, so that's fine. The point here is to be machine-readable
+		// and use minimal space.
+		serializer.serialize_str(&self.0.to_rfc3339())
+	}
+}
+
+/// The format to report results in.
+#[derive(Debug, Default, Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+	/// JSON format.
+	Json,
+	/// Human-readable format.
+	#[default]
+	Human,
+	/// Flattened numeric feature vector, as JSON.
+	FeaturesJson,
+	/// Flattened numeric feature vector, as CSV.
+	FeaturesCsv,
+	/// SARIF 2.1.0, for code scanning tools.
+	Sarif,
+	/// Self-contained HTML page.
+	Html,
+	/// GitHub Actions workflow commands, with a job summary.
+	Github,
+	/// JUnit XML, for the test report views of CI systems.
+	Junit,
+	/// A CSV table with a row per target, for spreadsheets.
+	Csv,
+}
+
+impl Format {
+	pub fn use_json(json: bool) -> Format {
+		if json {
+			Format::Json
+		} else {
+			Format::Human
+		}
+	}
+}
+
+/// Queries for how Hipcheck reports session results
+#[salsa::query_group(ReportParamsStorage)]
+pub trait ReportParams: VersionQuery {
+	/// Returns the time the current Hipcheck session started
+	#[salsa::input]
+	fn started_at(&self) -> DateTime<FixedOffset>;
+
+	/// Returns the format of the final report
+	#[salsa::input]
+	fn format(&self) -> Format;
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn risk_category_statement() {
+		let mut rollup = RiskCategoryRollup::new(RiskCategory::Abandonment);
+		rollup.passing = 1;
+		rollup.failing = 1;
+		assert!(!rollup.is_passing());
+		assert_eq!(rollup.statement(), "abandonment: 1 of 2 analyses failed");
+
+		rollup.errored = 1;
+		rollup.concerns = 1200;
+		assert_eq!(
+			rollup.statement(),
+			"abandonment: 1 of 3 analyses failed, 1 errored (1,200 concerns)"
+		);
+	}
+
+	#[test]
+	fn score_contribution() {
+		let passed = ScoreContribution::new("mitre/activity".to_owned(), None, true, 0.25);
+		assert_eq!(passed.score, 0.0);
+		assert_eq!(passed.contribution, 0.0);
+		assert_eq!(passed.value_summary(), "-");
+
+		let failed = ScoreContribution::new(
+			"mitre/churn".to_owned(),
+			Some(serde_json::json!([1, 2, 3])),
+			false,
+			0.1665,
+		);
+		assert_eq!(failed.score, 1.0);
+		assert_eq!(failed.contribution, 0.1665);
+		assert_eq!(failed.value_summary(), "3 items");
+	}
+
+	#[test]
+	fn separators_every_three_digits() {
+		assert_eq!(with_separators(0), "0");
+		assert_eq!(with_separators(999), "999");
+		assert_eq!(with_separators(1243), "1,243");
+		assert_eq!(with_separators(1234567), "1,234,567");
+	}
+}
@@ -0,0 +1,294 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Statistics over every target of a run, to see a portfolio of targets, like
+//! the components of an SBOM, at a glance.
+//!
+//! The summary covers the targets which could be analyzed: how their risk
+//! scores are distributed, which analyses fail for the most targets, and which
+//! targets are riskiest.
+
+use crate::{
+	report::{batch::BatchReport, with_separators, RecommendationKind, Report},
+	shell::Shell,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The number of analyses listed as failing most often.
+const COMMON_FAILURES: usize = 10;
+
+/// The number of targets listed as riskiest.
+const WORST_TARGETS: usize = 5;
+
+/// The number of equal-width buckets risk scores are counted in.
+const SCORE_BUCKETS: usize = 10;
+
+/// Statistics over every analyzed target of a run.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct PortfolioSummary {
+	/// How the analyzed targets' risk scores are distributed.
+	pub risk_scores: RiskScoreDistribution,
+
+	/// The analyses which failed for the most targets, most common first.
+	pub common_failures: Vec<AnalysisFailures>,
+
+	/// The targets with the highest risk scores, riskiest first.
+	pub worst_targets: Vec<WorstTarget>,
+}
+
+/// How risk scores are distributed.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct RiskScoreDistribution {
+	pub count: usize,
+	pub min: f64,
+	pub max: f64,
+	pub mean: f64,
+	pub median: f64,
+	/// The score 90% of targets are at or below.
+	pub p90: f64,
+	/// How many scores fall in each tenth of the range from 0 to 1.
+	pub histogram: Vec<ScoreBucket>,
+}
+
+/// How many risk scores fall in a range, including its start.
+///
+/// The last bucket also includes its end, so a score of 1 is counted.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct ScoreBucket {
+	pub from: f64,
+	pub to: f64,
+	pub count: usize,
+}
+
+/// How often an analysis failed.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct AnalysisFailures {
+	pub analysis: String,
+	/// How many targets the analysis failed for.
+	pub failing: usize,
+	/// How many targets the analysis passed or failed for.
+	pub ran: usize,
+}
+
+/// One of the riskiest targets.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct WorstTarget {
+	/// The target, as given.
+	pub target: String,
+	pub risk_score: f64,
+	pub recommendation: RecommendationKind,
+	/// The analyses which failed for the target.
+	pub failing: Vec<String>,
+}
+
+impl PortfolioSummary {
+	/// Summarize the targets of a run which could be analyzed, if any could.
+	pub fn new(batch: &BatchReport) -> Option<PortfolioSummary> {
+		let analyzed = batch.analyzed().collect::<Vec<_>>();
+		let scores = analyzed
+			.iter()
+			.map(|(_, report)| report.recommendation.risk_score.0)
+			.collect::<Vec<_>>();
+		let risk_scores = RiskScoreDistribution::new(scores)?;
+
+		let mut counts = BTreeMap::<&str, AnalysisFailures>::new();
+		for (_, report) in &analyzed {
+			let outcomes = report
+				.passing_analyses()
+				.map(|analysis| (analysis.name.as_str(), false))
+				.chain(
+					report
+						.failing_analyses()
+						.map(|failing| (failing.analysis().name.as_str(), true)),
+				);
+			for (name, failed) in outcomes {
+				let entry = counts.entry(name).or_insert_with(|| AnalysisFailures {
+					analysis: name.to_owned(),
+					failing: 0,
+					ran: 0,
+				});
+				entry.ran += 1;
+				entry.failing += usize::from(failed);
+			}
+		}
+		let mut common_failures = counts
+			.into_values()
+			.filter(|counts| counts.failing > 0)
+			.collect::<Vec<_>>();
+		// Ties keep the order of the analysis names
+		common_failures.sort_by(|a, b| b.failing.cmp(&a.failing));
+		common_failures.truncate(COMMON_FAILURES);
+
+		let mut worst_targets = analyzed
+			.iter()
+			.map(|(target, report)| WorstTarget::new(target, report))
+			.collect::<Vec<_>>();
+		worst_targets.sort_by(|a, b| {
+			b.risk_score
+				.total_cmp(&a.risk_score)
+				.then_with(|| b.failing.len().cmp(&a.failing.len()))
+		});
+		worst_targets.truncate(WORST_TARGETS);
+
+		Some(PortfolioSummary {
+			risk_scores,
+			common_failures,
+			worst_targets,
+		})
+	}
+}
+
+impl RiskScoreDistribution {
+	/// Describe a set of risk scores, if there are any.
+	fn new(mut scores: Vec<f64>) -> Option<RiskScoreDistribution> {
+		if scores.is_empty() {
+			return None;
+		}
+		scores.sort_by(f64::total_cmp);
+
+		let count = scores.len();
+		let mean = scores.iter().sum::<f64>() / count as f64;
+		let median = if count % 2 == 0 {
+			(scores[count / 2 - 1] + scores[count / 2]) / 2.0
+		} else {
+			scores[count / 2]
+		};
+		// The nearest-rank percentile, which is always one of the scores
+		let p90 = scores[((count as f64 * 0.9).ceil() as usize).clamp(1, count) - 1];
+
+		let mut histogram = (0..SCORE_BUCKETS)
+			.map(|i| ScoreBucket {
+				from: i as f64 / SCORE_BUCKETS as f64,
+				to: (i + 1) as f64 / SCORE_BUCKETS as f64,
+				count: 0,
+			})
+			.collect::<Vec<_>>();
+		for score in &scores {
+			let bucket = ((score * SCORE_BUCKETS as f64) as usize).min(SCORE_BUCKETS - 1);
+			histogram[bucket].count += 1;
+		}
+
+		Some(RiskScoreDistribution {
+			count,
+			min: scores[0],
+			max: scores[count - 1],
+			mean,
+			median,
+			p90,
+			histogram,
+		})
+	}
+
+	/// Describe the distribution.
+	pub fn statement(&self) -> String {
+		let locale = Shell::get_locale();
+		format!(
+			"{} targets analyzed, risk rated from {} to {}, mean {}, median {}, 90th percentile {}",
+			with_separators(self.count),
+			locale.decimal(self.min, 2),
+			locale.decimal(self.max, 2),
+			locale.decimal(self.mean, 2),
+			locale.decimal(self.median, 2),
+			locale.decimal(self.p90, 2)
+		)
+	}
+}
+
+impl ScoreBucket {
+	/// Describe the bucket with a bar as wide as its share of the targets.
+	pub fn statement(&self, total: usize) -> String {
+		const BAR_WIDTH: usize = 20;
+
+		let locale = Shell::get_locale();
+		let width = (self.count * BAR_WIDTH).div_ceil(total.max(1));
+		format!(
+			"{}-{} {:<BAR_WIDTH$} {}",
+			locale.decimal(self.from, 1),
+			locale.decimal(self.to, 1),
+			"#".repeat(width),
+			with_separators(self.count)
+		)
+	}
+}
+
+impl AnalysisFailures {
+	pub fn statement(&self) -> String {
+		format!(
+			"'{}' failed for {} of {} targets",
+			self.analysis,
+			with_separators(self.failing),
+			with_separators(self.ran)
+		)
+	}
+}
+
+impl WorstTarget {
+	fn new(target: &str, report: &Report) -> WorstTarget {
+		WorstTarget {
+			target: target.to_owned(),
+			risk_score: report.recommendation.risk_score.0,
+			recommendation: report.recommendation.kind,
+			failing: report
+				.failing_analyses()
+				.map(|failing| failing.analysis().name.clone())
+				.collect(),
+		}
+	}
+
+	pub fn statement(&self) -> String {
+		let mut statement = format!(
+			"{}, risk rated as {}",
+			self.target,
+			Shell::get_locale().decimal(self.risk_score, 2)
+		);
+		if !self.failing.is_empty() {
+			statement.push_str(&format!(", failed {}", self.failing.join(", ")));
+		}
+		statement
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn describes_risk_scores() {
+		let distribution =
+			RiskScoreDistribution::new(vec![0.9, 0.1, 0.3, 0.2, 1.0, 0.0, 0.5, 0.4, 0.6, 0.7])
+				.unwrap();
+
+		assert_eq!(distribution.count, 10);
+		assert_eq!(distribution.min, 0.0);
+		assert_eq!(distribution.max, 1.0);
+		assert!((distribution.mean - 0.47).abs() < 1e-9);
+		assert!((distribution.median - 0.45).abs() < 1e-9);
+		assert_eq!(distribution.p90, 0.9);
+
+		let counts = distribution
+			.histogram
+			.iter()
+			.map(|bucket| bucket.count)
+			.collect::<Vec<_>>();
+		// 0.8 has no score, and 1.0 falls in the last bucket with 0.9
+		assert_eq!(counts, vec![1, 1, 1, 1, 1, 1, 1, 1, 0, 2]);
+	}
+
+	#[test]
+	fn no_scores_have_no_distribution() {
+		assert!(RiskScoreDistribution::new(Vec::new()).is_none());
+	}
+
+	#[test]
+	fn single_scores_are_their_own_percentiles() {
+		let distribution = RiskScoreDistribution::new(vec![0.25]).unwrap();
+		assert_eq!(distribution.median, 0.25);
+		assert_eq!(distribution.p90, 0.25);
+	}
+}
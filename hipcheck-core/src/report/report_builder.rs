@@ -0,0 +1,471 @@
+// SPDX-License-Identifier: Apache-2.0
+
+pub use crate::report::*;
+use crate::{
+	config::{ConfigSource, RiskConfigQuery},
+	engine::HcEngine,
+	error::{Error, Result},
+	hc_error,
+	plugin::{PluginName, PluginPublisher},
+	policy::policy_file::{PolicyPluginName, RiskCategory},
+	report::concern::Concern,
+	score::*,
+	session::Session,
+	source::SourceQuery,
+	version::VersionQuery,
+};
+use serde_json::Value;
+use std::{
+	collections::{BTreeMap, HashSet},
+	default::Default,
+};
+
+/// The number of concerns shown per failing analysis, unless overridden by the policy file.
+pub const DEFAULT_CONCERN_LIMIT: usize = 20;
+
+/// Read the concerns a plugin sent, keeping malformed ones as text so they
+/// aren't lost.
+fn plugin_concerns(plugin: &str, concerns: &[Value]) -> Vec<Concern> {
+	concerns
+		.iter()
+		.map(|concern| {
+			Concern::from_plugin(concern.clone()).unwrap_or_else(|e| {
+				tracing::warn!("malformed concern from '{}': {}", plugin, e);
+				Concern::new(concern.to_string())
+			})
+		})
+		.collect()
+}
+
+/// Print the final report of a Hipcheck run.
+///
+/// Unless `all_concerns` is set, each failing analysis only reports up to its
+/// concern limit, with a count of the concerns left out.
+pub fn build_report(
+	session: &Session,
+	scoring: &ScoringResults,
+	all_concerns: bool,
+) -> Result<Report> {
+	#[cfg(feature = "print-timings")]
+	let _0 = crate::benchmarking::print_scope_time!("build_report");
+
+	tracing::debug!("building final report");
+
+	// This function needs to:
+	//
+	// 1. Build a report from the information available.
+	// 2. Print that report.
+
+	let mut builder = ReportBuilder::for_session(session);
+	builder.set_all_concerns(all_concerns);
+
+	for (analysis, stored) in scoring.results.plugin_results() {
+		let name = format!(
+			"{}/{}",
+			analysis.publisher.as_str(),
+			analysis.plugin.as_str()
+		);
+
+		builder.add_score_contribution(ScoreContribution::new(
+			name.clone(),
+			stored
+				.response
+				.as_ref()
+				.ok()
+				.and_then(|res| res.value.first().cloned()),
+			stored.passed,
+			stored.weight,
+		));
+
+		match &stored.response {
+			Ok(res) => {
+				// This is the "explanation" pulled from the new gRPC call.
+				let message = session
+					.default_query_explanation(analysis.publisher.clone(), analysis.plugin.clone())?
+					.unwrap_or("no query explanation provided".to_owned());
+
+				builder.add_analysis(
+					Analysis::plugin(
+						name.clone(),
+						stored.passed,
+						stored.policy.clone(),
+						message,
+						res.value.first().cloned(),
+					)
+					.with_weight(stored.weight),
+					plugin_concerns(&name, &res.concerns),
+				)?;
+				builder.add_fragments(&name, &res.fragments);
+			}
+			Err(error) => match error.skip_reason() {
+				Some(reason) => {
+					builder.add_skipped_analysis(AnalysisIdent(name), reason);
+				}
+				None => {
+					builder.add_errored_analysis(AnalysisIdent(name), error);
+				}
+			},
+		}
+	}
+
+	builder
+		.set_risk_score(scoring.score.total)
+		.set_risk_policy(session.risk_policy()?.as_ref().clone());
+
+	if let Some(conditions_policy) = session.conditions_policy()? {
+		builder.set_conditions_policy(conditions_policy.as_ref().clone());
+	}
+
+	let report = builder.build()?;
+
+	tracing::info!("built final report");
+
+	Ok(report)
+}
+
+/// Builds a final `Report` of Hipcheck's results.
+pub struct ReportBuilder<'sess> {
+	/// The `Session`, containing general data from the run.
+	session: &'sess Session,
+
+	/// A lookup of which failed analyses warrant an immediate investigation
+	investigate_if_failed: HashSet<PolicyPluginName>,
+
+	/// What analyses passed.
+	passing: Vec<PassingAnalysis>,
+
+	/// What analyses failed.
+	failing: Vec<FailingAnalysis>,
+
+	/// What analyses encountered errors.
+	errored: Vec<ErroredAnalysis>,
+
+	/// What analyses didn't apply to the target.
+	skipped: Vec<SkippedAnalysis>,
+
+	/// What concerns the policy file waived.
+	waived: Vec<WaivedConcern>,
+
+	/// Whether to report every concern, ignoring concern limits.
+	all_concerns: bool,
+
+	/// How the analyses in each risk category fared.
+	risk_categories: BTreeMap<RiskCategory, RiskCategoryRollup>,
+
+	/// Sections plugins added to the report.
+	fragments: Vec<ReportFragment>,
+
+	/// How much each analysis added to the risk score.
+	score_breakdown: Vec<ScoreContribution>,
+
+	/// What risk threshold was configured for the run.
+	risk_policy: Option<Expr>,
+
+	/// What "use with conditions" threshold was configured for the run, if any.
+	conditions_policy: Option<Expr>,
+
+	/// What risk score Hipcheck assigned.
+	risk_score: Option<f64>,
+}
+
+impl<'sess> ReportBuilder<'sess> {
+	/// Initiate building a new `Report`.
+	pub fn for_session(session: &'sess Session) -> ReportBuilder<'sess> {
+		// Get investigate_if_failed hashset from policy
+		let policy = session.policy();
+		let investigate_if_failed = policy
+			.analyze
+			.if_fail
+			.as_ref()
+			.map_or(HashSet::new(), |x| HashSet::from_iter(x.0.iter().cloned()));
+
+		// Expired waivers are ignored, but shouldn't be forgotten in the policy file
+		for waiver in policy.waivers.expired(session.started_at().date_naive()) {
+			tracing::warn!(
+				"the waiver of '{}' for {} expired on {}, so its concerns are reported again",
+				waiver.identifier,
+				waiver.analysis,
+				waiver.expires
+			);
+		}
+
+		ReportBuilder {
+			session,
+			investigate_if_failed,
+			passing: Default::default(),
+			failing: Default::default(),
+			errored: Default::default(),
+			skipped: Default::default(),
+			waived: Default::default(),
+			all_concerns: Default::default(),
+			risk_categories: Default::default(),
+			fragments: Default::default(),
+			score_breakdown: Default::default(),
+			risk_policy: Default::default(),
+			conditions_policy: Default::default(),
+			risk_score: Default::default(),
+		}
+	}
+
+	/// Add an analysis.
+	///
+	/// Concerns of a failing analysis which the policy file waives are
+	/// reported as waived instead.
+	pub fn add_analysis(
+		&mut self,
+		mut analysis: Analysis,
+		mut concerns: Vec<Concern>,
+	) -> Result<&mut Self> {
+		if !analysis.is_passing() {
+			concerns = self.waive_concerns(&analysis.name, concerns);
+		}
+
+		analysis.risk_categories = self.policy_risk_categories(&analysis.name);
+
+		for category in analysis.risk_categories.iter() {
+			let rollup = self.rollup_for(*category);
+			if analysis.is_passing() {
+				rollup.passing += 1;
+			} else {
+				rollup.failing += 1;
+				rollup.concerns += concerns.len();
+			}
+		}
+
+		if analysis.is_passing() {
+			Ok(self.add_passing_analysis(analysis))
+		} else {
+			self.add_failing_analysis(analysis, concerns)
+		}
+	}
+
+	/// Add an errored analysis to the report.
+	pub fn add_errored_analysis(&mut self, analysis: AnalysisIdent, error: &Error) -> &mut Self {
+		for category in self.policy_risk_categories(&analysis.0) {
+			self.rollup_for(category).errored += 1;
+		}
+
+		self.errored.push(ErroredAnalysis::new(analysis, error));
+		self
+	}
+
+	/// Add an analysis that was skipped to the report.
+	pub fn add_skipped_analysis(&mut self, analysis: AnalysisIdent, reason: &str) -> &mut Self {
+		self.skipped.push(SkippedAnalysis::new(analysis, reason));
+		self
+	}
+
+	/// Add the sections a plugin sent to add to the report.
+	///
+	/// Fragments which aren't valid are left out of the report.
+	pub fn add_fragments(&mut self, plugin: &str, fragments: &[Value]) -> &mut Self {
+		for fragment in fragments {
+			match ReportFragment::from_plugin(plugin, fragment.clone()) {
+				Ok(fragment) => self.fragments.push(fragment),
+				Err(e) => tracing::warn!("ignoring report fragment from '{}': {}", plugin, e),
+			}
+		}
+		self
+	}
+
+	/// Add how much an analysis added to the risk score.
+	pub fn add_score_contribution(&mut self, contribution: ScoreContribution) -> &mut Self {
+		self.score_breakdown.push(contribution);
+		self
+	}
+
+	/// Add an analysis that passed.
+	fn add_passing_analysis(&mut self, analysis: Analysis) -> &mut Self {
+		self.passing.push(PassingAnalysis::new(analysis));
+		self
+	}
+
+	/// Add a failing analysis and any concerns associated with it.
+	fn add_failing_analysis(
+		&mut self,
+		analysis: Analysis,
+		concerns: Vec<Concern>,
+	) -> Result<&mut Self> {
+		let limit = self.concern_limit(&analysis.name);
+		let mut failing = FailingAnalysis::new(analysis, concerns)?;
+
+		if let Some(limit) = limit {
+			failing = failing.limit_concerns(limit);
+		}
+
+		self.failing.push(failing);
+		Ok(self)
+	}
+
+	/// Set aside the concerns the policy file waives, returning the rest.
+	fn waive_concerns(&mut self, analysis_name: &str, concerns: Vec<Concern>) -> Vec<Concern> {
+		let policy = self.session.policy();
+		let today = self.session.started_at().date_naive();
+
+		let mut kept = Vec::new();
+		for concern in concerns {
+			match policy.waivers.find(analysis_name, &concern, today) {
+				Some(waiver) => {
+					self.waived
+						.push(WaivedConcern::new(analysis_name, concern, waiver))
+				}
+				None => kept.push(concern),
+			}
+		}
+		kept
+	}
+
+	/// Get the risk categories the policy file tagged an analysis with.
+	fn policy_risk_categories(&self, analysis_name: &str) -> Vec<RiskCategory> {
+		self.session
+			.policy()
+			.analyze
+			.find_analysis_by_name(analysis_name)
+			.map(|analysis| analysis.risk_categories)
+			.unwrap_or_default()
+	}
+
+	/// Get the summary for a risk category, starting a new one if needed.
+	fn rollup_for(&mut self, category: RiskCategory) -> &mut RiskCategoryRollup {
+		self.risk_categories
+			.entry(category)
+			.or_insert_with(|| RiskCategoryRollup::new(category))
+	}
+
+	/// Get the maximum number of concerns to report for an analysis, if any.
+	fn concern_limit(&self, analysis_name: &str) -> Option<usize> {
+		if self.all_concerns {
+			return None;
+		}
+
+		let limit = self
+			.session
+			.policy()
+			.analyze
+			.find_analysis_by_name(analysis_name)
+			.and_then(|analysis| analysis.concern_limit)
+			.unwrap_or(DEFAULT_CONCERN_LIMIT);
+
+		Some(limit)
+	}
+
+	/// Set whether to report every concern, ignoring concern limits.
+	pub fn set_all_concerns(&mut self, all_concerns: bool) -> &mut Self {
+		self.all_concerns = all_concerns;
+		self
+	}
+
+	/// Set the overall risk score for the report.
+	pub fn set_risk_score(&mut self, risk_score: f64) -> &mut Self {
+		self.risk_score = Some(risk_score);
+		self
+	}
+
+	/// Set what's being recommended to the user.
+	pub fn set_risk_policy(&mut self, risk_policy: Expr) -> &mut Self {
+		self.risk_policy = Some(risk_policy);
+		self
+	}
+
+	/// Set the policy under which a non-passing score may be used with conditions.
+	pub fn set_conditions_policy(&mut self, conditions_policy: Expr) -> &mut Self {
+		self.conditions_policy = Some(conditions_policy);
+		self
+	}
+
+	/// Build a new report.
+	///
+	/// The `recommendation_kind` and `risk_score` _must_ be set before calling `build`,
+	/// or building will fail.
+	pub fn build(self) -> Result<Report> {
+		let repo_name = self.session.name();
+		let repo_head = self.session.head();
+		let hipcheck_version = self.session.hc_version().to_string();
+		let analyzed_at = Timestamp::from(self.session.started_at());
+		// A report is still useful without the hash, so failing to make it isn't fatal
+		let target_tree = match TreeManifest::for_tree(&self.session.local()) {
+			Ok(manifest) => Some(manifest),
+			Err(e) => {
+				tracing::warn!("failed to hash the analyzed tree: {}", e);
+				None
+			}
+		};
+		let passing = self.passing;
+		let failing = self.failing;
+		let errored = self.errored;
+		let skipped = self.skipped;
+		let waived = self.waived;
+		let risk_categories = self.risk_categories.into_values().collect();
+		let fragments = self.fragments;
+		let mut score_breakdown = self.score_breakdown;
+		// Largest contributions first, so the reasons for the score lead the table
+		score_breakdown.sort_by(|a, b| {
+			b.contribution
+				.total_cmp(&a.contribution)
+				.then_with(|| a.analysis.cmp(&b.analysis))
+		});
+		let recommendation = {
+			let score = self
+				.risk_score
+				.ok_or_else(|| hc_error!("no risk score set for report"))
+				.map(RiskScore)?;
+
+			let policy = self
+				.risk_policy
+				.ok_or_else(|| hc_error!("no risk threshold set for report"))
+				.map(RiskPolicy::new)?;
+
+			let conditions_policy = self.conditions_policy.map(RiskPolicy::new);
+
+			// Determine recommendation based on score and investigate policy expr
+			let mut rec = Recommendation::is(score, policy, conditions_policy)?;
+
+			// Override base recommendation if any `investigate-if-fail` analyses failed
+			for failed in failing.iter() {
+				let (publisher, name) = failed.analysis.name.as_str().split_once('/').unwrap();
+				let policy_plugin_name = PolicyPluginName {
+					publisher: PluginPublisher(publisher.to_owned()),
+					name: PluginName(name.to_owned()),
+				};
+				if self.investigate_if_failed.contains(&policy_plugin_name) {
+					rec.kind = RecommendationKind::Investigate;
+					break;
+				}
+			}
+
+			// Use the policy file's wording for the final recommendation, if any
+			if let Some(text) = &self.session.policy().analyze.recommendation_text {
+				rec.message = match rec.kind {
+					RecommendationKind::Pass => text.pass.clone(),
+					RecommendationKind::UseWithConditions => text.use_with_conditions.clone(),
+					RecommendationKind::Investigate => text.investigate.clone(),
+				};
+			}
+
+			rec
+		};
+
+		let report = Report {
+			schema_version: SchemaVersion::CURRENT,
+			repo_name,
+			repo_head,
+			hipcheck_version,
+			analyzed_at,
+			target_tree,
+			package: None,
+			passing,
+			failing,
+			errored,
+			skipped,
+			waived,
+			risk_categories,
+			fragments,
+			score_breakdown,
+			recommendation,
+			delta: None,
+			fail_on: self.session.policy().analyze.fail_on,
+		};
+
+		Ok(report)
+	}
+}
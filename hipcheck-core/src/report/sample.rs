@@ -0,0 +1,268 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Estimating the risk of every component of an SBOM from a sample.
+//!
+//! Each ecosystem's sampled targets stand in for all of its targets, so the
+//! estimates weigh each ecosystem by its share of the components. The share
+//! of targets to investigate comes with a margin of error, which shrinks as
+//! the sample grows.
+
+use crate::{
+	report::{
+		batch::{BatchSummary, TargetOutcome},
+		with_separators, RecommendationKind,
+	},
+	session::sample::Sample,
+	shell::Shell,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The z-score of a 95% confidence interval.
+const Z_95: f64 = 1.96;
+
+/// What a sample of the targets says about all of them.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct SampleSummary {
+	/// The seed the sample was drawn with. Passing it to `--sample-seed`
+	/// draws the same sample.
+	pub seed: u64,
+
+	/// How many targets the sample was drawn from.
+	pub population: usize,
+
+	/// The results from each ecosystem.
+	pub strata: Vec<StratumSummary>,
+
+	/// The estimated share of all targets which would be recommended for
+	/// investigation, if any sampled target could be analyzed.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub investigate_share: Option<f64>,
+
+	/// The margin of error of `investigate_share`, at 95% confidence.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub margin_of_error: Option<f64>,
+
+	/// The estimated mean risk score of all targets.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub mean_risk_score: Option<f64>,
+}
+
+/// The results of the sampled targets from one ecosystem.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct StratumSummary {
+	/// The ecosystem, like `npm` or `maven`.
+	pub ecosystem: String,
+	/// How many targets are from the ecosystem.
+	pub population: usize,
+	/// How many were sampled.
+	pub sampled: usize,
+	/// How many of the sampled targets could be analyzed.
+	pub analyzed: usize,
+	/// How many of the analyzed targets were recommended for investigation.
+	pub investigate: usize,
+	/// The mean risk score of the analyzed targets.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub mean_risk_score: Option<f64>,
+}
+
+impl StratumSummary {
+	/// Get the share of the analyzed targets recommended for investigation.
+	fn investigate_share(&self) -> f64 {
+		self.investigate as f64 / self.analyzed as f64
+	}
+
+	/// Describe the ecosystem's results.
+	pub fn statement(&self) -> String {
+		let mut statement = format!(
+			"{}: {} of {} sampled, {} analyzed, {} investigate",
+			self.ecosystem,
+			with_separators(self.sampled),
+			with_separators(self.population),
+			self.analyzed,
+			self.investigate
+		);
+		if let Some(score) = self.mean_risk_score {
+			statement.push_str(&format!(
+				", mean risk {}",
+				Shell::get_locale().decimal(score, 2)
+			));
+		}
+		statement
+	}
+}
+
+impl SampleSummary {
+	/// Summarize the results of analyzing a sample.
+	pub fn new(sample: &Sample, summary: &BatchSummary) -> SampleSummary {
+		let outcomes = summary
+			.targets
+			.iter()
+			.map(|target| (target.target.as_str(), &target.outcome))
+			.collect::<HashMap<_, _>>();
+
+		let strata = sample
+			.strata
+			.iter()
+			.map(|stratum| {
+				let mut analyzed = 0;
+				let mut investigate = 0;
+				let mut total_risk = 0.0;
+
+				for target in &stratum.sampled {
+					if let Some(TargetOutcome::Analyzed {
+						recommendation,
+						risk_score,
+					}) = outcomes.get(target.as_str())
+					{
+						analyzed += 1;
+						total_risk += risk_score;
+						if *recommendation == RecommendationKind::Investigate {
+							investigate += 1;
+						}
+					}
+				}
+
+				StratumSummary {
+					ecosystem: stratum.ecosystem.clone(),
+					population: stratum.population,
+					sampled: stratum.sampled.len(),
+					analyzed,
+					investigate,
+					mean_risk_score: (analyzed > 0).then(|| total_risk / analyzed as f64),
+				}
+			})
+			.collect::<Vec<_>>();
+
+		let population = sample.strata.iter().map(|stratum| stratum.population).sum();
+		let (investigate_share, margin_of_error, mean_risk_score) = match estimate(&strata) {
+			Some((share, margin, score)) => (Some(share), Some(margin), Some(score)),
+			None => (None, None, None),
+		};
+
+		SampleSummary {
+			seed: sample.seed,
+			population,
+			strata,
+			investigate_share,
+			margin_of_error,
+			mean_risk_score,
+		}
+	}
+
+	/// Get how many targets were sampled.
+	pub fn sampled(&self) -> usize {
+		self.strata.iter().map(|stratum| stratum.sampled).sum()
+	}
+
+	/// Describe the estimates for all the targets.
+	pub fn statement(&self) -> String {
+		let mut statement = format!(
+			"sampled {} of {} targets (seed {})",
+			with_separators(self.sampled()),
+			with_separators(self.population),
+			self.seed
+		);
+
+		match (
+			self.investigate_share,
+			self.margin_of_error,
+			self.mean_risk_score,
+		) {
+			(Some(share), Some(margin), Some(score)) => {
+				let locale = Shell::get_locale();
+				statement.push_str(&format!(
+					"; an estimated {}% to investigate (\u{b1}{}% at 95% confidence), mean risk {}",
+					locale.decimal(share * 100.0, 0),
+					locale.decimal(margin * 100.0, 0),
+					locale.decimal(score, 2)
+				))
+			}
+			_ => statement.push_str("; no sampled target could be analyzed"),
+		}
+
+		statement
+	}
+}
+
+/// Estimate the share of all targets to investigate, its margin of error, and
+/// the mean risk score, from the ecosystems with any analyzed targets.
+///
+/// Each ecosystem is weighed by its share of the targets. An ecosystem with a
+/// single analyzed target gives no sense of its variance, so the largest
+/// possible variance is assumed for it.
+fn estimate(strata: &[StratumSummary]) -> Option<(f64, f64, f64)> {
+	let analyzed = strata
+		.iter()
+		.filter(|stratum| stratum.analyzed > 0)
+		.collect::<Vec<_>>();
+	let population: usize = analyzed.iter().map(|stratum| stratum.population).sum();
+
+	if population == 0 {
+		return None;
+	}
+
+	let mut share = 0.0;
+	let mut variance = 0.0;
+	let mut score = 0.0;
+
+	for stratum in analyzed {
+		let weight = stratum.population as f64 / population as f64;
+		let n = stratum.analyzed as f64;
+		let p = stratum.investigate_share();
+
+		let stratum_variance = if stratum.analyzed > 1 {
+			p * (1.0 - p) * n / (n - 1.0)
+		} else {
+			0.25
+		};
+		let finite_population = 1.0 - n / stratum.population as f64;
+
+		share += weight * p;
+		variance += weight * weight * finite_population * stratum_variance / n;
+		score += weight * stratum.mean_risk_score.unwrap_or_default();
+	}
+
+	Some((share, Z_95 * variance.sqrt(), score))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn stratum(population: usize, analyzed: usize, investigate: usize) -> StratumSummary {
+		StratumSummary {
+			ecosystem: "npm".to_owned(),
+			population,
+			sampled: analyzed,
+			analyzed,
+			investigate,
+			mean_risk_score: Some(0.5),
+		}
+	}
+
+	#[test]
+	fn estimates_weigh_ecosystems_by_size() {
+		let (share, margin, score) =
+			estimate(&[stratum(900, 90, 9), stratum(100, 10, 5), stratum(50, 5, 0)]).unwrap();
+		// Each ecosystem's share to investigate, weighed by its number of targets
+		assert!((share - (900.0 * 0.1 + 100.0 * 0.5) / 1050.0).abs() < 1e-9);
+		assert!(margin > 0.0 && margin < 0.1);
+		assert!((score - 0.5).abs() < 1e-9);
+	}
+
+	#[test]
+	fn complete_samples_have_no_margin_of_error() {
+		let (share, margin, _) = estimate(&[stratum(10, 10, 3)]).unwrap();
+		assert!((share - 0.3).abs() < 1e-9);
+		assert_eq!(margin, 0.0);
+	}
+
+	#[test]
+	fn no_estimate_without_results() {
+		assert!(estimate(&[stratum(10, 0, 0)]).is_none());
+	}
+}
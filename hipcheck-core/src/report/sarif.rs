@@ -0,0 +1,432 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Conversion of a `Report` into a SARIF 2.1.0 log.
+//!
+//! SARIF is the format static analysis tools use to share their findings, and
+//! is accepted by GitHub Code Scanning among other consumers. The mapping is:
+//!
+//! - Every analysis in the report becomes a rule, identified by the analysis
+//!   name and described by the analysis' explanation.
+//! - Every concern of a failing analysis becomes a result for that analysis'
+//!   rule, with the concern's fingerprint as a partial fingerprint so
+//!   consumers can match it across runs. Its level comes from the severity the
+//!   plugin gave: `note` for low, `warning` for medium, and `error` for high,
+//!   critical, or none given. Failing analyses with no concerns get a single
+//!   `error` result stating the policy the analysis failed.
+//! - Concerns the policy file waived are results too, with an `external`
+//!   suppression giving the waiver's justification.
+//! - Errored analyses become tool execution notifications rather than
+//!   results, since they say nothing about the target. Skipped analyses
+//!   become `note` notifications for the same reason.
+//!
+//! Most of Hipcheck's findings are about the target as a whole rather than any
+//! particular file, so only concerns the plugin placed in a file carry a
+//! location.
+
+use crate::report::{
+	concern::{Concern, Severity},
+	fingerprint::FINGERPRINT_VERSION,
+	Analysis, Report,
+};
+use serde::Serialize;
+use std::{collections::BTreeMap, ops::Not as _};
+
+/// The location of the SARIF 2.1.0 JSON schema.
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The version of SARIF produced.
+const VERSION: &str = "2.1.0";
+
+/// Where SARIF consumers can point users for more on the tool.
+const INFORMATION_URI: &str = "https://hipcheck.mitre.org/";
+
+/// A SARIF log holding the results of a single run of Hipcheck.
+#[derive(Debug, Serialize)]
+pub struct SarifLog {
+	#[serde(rename = "$schema")]
+	schema: &'static str,
+	version: &'static str,
+	runs: Vec<Run>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run {
+	tool: Tool,
+	invocations: Vec<Invocation>,
+	results: Vec<SarifResult>,
+	properties: RunProperties,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+	driver: Driver,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+	name: &'static str,
+	version: String,
+	information_uri: &'static str,
+	rules: Vec<Rule>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+	id: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	short_description: Option<Message>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	properties: Option<RuleProperties>,
+}
+
+#[derive(Debug, Serialize)]
+struct RuleProperties {
+	/// The risk categories of the analysis.
+	tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Invocation {
+	execution_successful: bool,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	tool_execution_notifications: Vec<Notification>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Notification {
+	level: &'static str,
+	message: Message,
+	associated_rule: RuleReference,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleReference {
+	id: String,
+	index: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+	rule_id: String,
+	rule_index: usize,
+	level: &'static str,
+	message: Message,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	locations: Vec<Location>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	partial_fingerprints: BTreeMap<&'static str, String>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	suppressions: Vec<Suppression>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+	physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+	artifact_location: ArtifactLocation,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	region: Option<Region>,
+}
+
+#[derive(Debug, Serialize)]
+struct ArtifactLocation {
+	/// The file, relative to the root of the repository.
+	uri: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+	start_line: u64,
+}
+
+/// Why a result was suppressed, for concerns the policy file waived.
+#[derive(Debug, Serialize)]
+struct Suppression {
+	kind: &'static str,
+	justification: String,
+}
+
+impl SarifResult {
+	/// Make a result for a concern of an analysis.
+	fn concern(
+		analysis: &str,
+		rule_index: usize,
+		concern: &Concern,
+		fingerprint: String,
+	) -> SarifResult {
+		let level = match concern.severity {
+			Some(Severity::Low) => "note",
+			Some(Severity::Medium) => "warning",
+			Some(Severity::High | Severity::Critical) | None => "error",
+		};
+		let locations = concern
+			.file
+			.iter()
+			.map(|file| Location {
+				physical_location: PhysicalLocation {
+					artifact_location: ArtifactLocation { uri: file.clone() },
+					region: concern.line.map(|start_line| Region { start_line }),
+				},
+			})
+			.collect();
+
+		SarifResult {
+			rule_id: analysis.to_owned(),
+			rule_index,
+			level,
+			message: Message {
+				text: concern.message.clone(),
+			},
+			locations,
+			partial_fingerprints: BTreeMap::from([(FINGERPRINT_VERSION, fingerprint)]),
+			suppressions: Vec::new(),
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+	text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RunProperties {
+	/// The name of the repository being analyzed.
+	target: String,
+	/// The HEAD commit hash of the repository during analysis.
+	head: String,
+	risk_score: f64,
+	recommendation: String,
+}
+
+impl SarifLog {
+	/// Convert a report into a SARIF log.
+	pub fn from_report(report: &Report) -> SarifLog {
+		let mut rules = Rules::default();
+
+		for analysis in report.passing_analyses() {
+			rules.add_analysis(analysis);
+		}
+
+		let mut results = Vec::new();
+
+		for failing in report.failing_analyses() {
+			let analysis = failing.analysis();
+			let rule_index = rules.add_analysis(analysis);
+
+			results.extend(
+				failing
+					.fingerprinted_concerns()
+					.map(|(concern, fingerprint)| {
+						SarifResult::concern(
+							&analysis.name,
+							rule_index,
+							concern,
+							fingerprint.clone(),
+						)
+					}),
+			);
+
+			let mut messages = failing.omitted_summary().into_iter().collect::<Vec<_>>();
+			if failing.concern_count() == 0 {
+				messages.push(analysis.statement());
+			}
+			results.extend(messages.into_iter().map(|text| SarifResult {
+				rule_id: analysis.name.clone(),
+				rule_index,
+				level: "error",
+				message: Message { text },
+				locations: Vec::new(),
+				partial_fingerprints: BTreeMap::new(),
+				suppressions: Vec::new(),
+			}));
+		}
+
+		for waived in report.waived_concerns() {
+			let rule_index = rules.add(waived.analysis(), None, Vec::new());
+			let concern = waived.concern_details();
+			let mut result = SarifResult::concern(
+				waived.analysis(),
+				rule_index,
+				concern,
+				concern.fingerprint(waived.analysis()),
+			);
+			result.suppressions.push(Suppression {
+				kind: "external",
+				justification: waived.justification().to_owned(),
+			});
+			results.push(result);
+		}
+
+		let mut notifications: Vec<Notification> = report
+			.errored_analyses()
+			.map(|errored| {
+				let id = errored.analysis().0.clone();
+				let index = rules.add(&id, None, Vec::new());
+
+				let mut text = errored.top_msg();
+				for msg in errored.source_msgs() {
+					text.push_str(": ");
+					text.push_str(&msg);
+				}
+
+				Notification {
+					level: "error",
+					message: Message { text },
+					associated_rule: RuleReference { id, index },
+				}
+			})
+			.collect();
+
+		for skipped in report.skipped_analyses() {
+			let id = skipped.analysis().0.clone();
+			let index = rules.add(&id, None, Vec::new());
+
+			notifications.push(Notification {
+				level: "note",
+				message: Message {
+					text: skipped.statement(),
+				},
+				associated_rule: RuleReference { id, index },
+			});
+		}
+
+		let recommendation = match &report.recommendation.message {
+			Some(message) => message.clone(),
+			None => report.recommendation.statement(),
+		};
+
+		SarifLog {
+			schema: SCHEMA,
+			version: VERSION,
+			runs: vec![Run {
+				tool: Tool {
+					driver: Driver {
+						name: "Hipcheck",
+						version: report.hipcheck_version.clone(),
+						information_uri: INFORMATION_URI,
+						rules: rules.rules,
+					},
+				},
+				invocations: vec![Invocation {
+					execution_successful: true,
+					tool_execution_notifications: notifications,
+				}],
+				results,
+				properties: RunProperties {
+					target: report.repo_name.to_string(),
+					head: report.repo_head.to_string(),
+					risk_score: report.recommendation.risk_score.0,
+					recommendation,
+				},
+			}],
+		}
+	}
+}
+
+/// The rules of a run, indexed by ID so each analysis gets one rule.
+#[derive(Default)]
+struct Rules {
+	rules: Vec<Rule>,
+	indices: BTreeMap<String, usize>,
+}
+
+impl Rules {
+	/// Add the rule for an analysis, returning its index.
+	fn add_analysis(&mut self, analysis: &Analysis) -> usize {
+		let tags = analysis
+			.risk_categories()
+			.iter()
+			.map(ToString::to_string)
+			.collect();
+		self.add(&analysis.name, Some(analysis.explanation()), tags)
+	}
+
+	/// Add a rule, returning its index. Adding a rule which already exists
+	/// returns the existing rule's index.
+	fn add(&mut self, id: &str, description: Option<String>, tags: Vec<String>) -> usize {
+		if let Some(index) = self.indices.get(id) {
+			return *index;
+		}
+
+		let index = self.rules.len();
+		self.rules.push(Rule {
+			id: id.to_owned(),
+			short_description: description
+				.filter(|d| d.is_empty().not())
+				.map(|text| Message { text }),
+			properties: tags
+				.is_empty()
+				.not()
+				.then_some(RuleProperties { tags }),
+		});
+		self.indices.insert(id.to_owned(), index);
+		index
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rules_are_deduplicated() {
+		let mut rules = Rules::default();
+		assert_eq!(rules.add("mitre/activity", None, Vec::new()), 0);
+		assert_eq!(
+			rules.add("mitre/binary", Some("binaries".to_owned()), Vec::new()),
+			1
+		);
+		assert_eq!(rules.add("mitre/activity", None, Vec::new()), 0);
+		assert_eq!(rules.rules.len(), 2);
+	}
+
+	#[test]
+	fn concerns_keep_their_severity_and_location() {
+		let concern = Concern {
+			severity: Some(Severity::Medium),
+			file: Some(".github/workflows/ci.yml".to_owned()),
+			line: Some(12),
+			..Concern::new("Found unpinned action 'actions/checkout@v4'")
+		};
+		let result = SarifResult::concern("mitre/pinning", 0, &concern, "abc".to_owned());
+		assert_eq!(
+			serde_json::to_value(&result).unwrap(),
+			serde_json::json!({
+				"ruleId": "mitre/pinning",
+				"ruleIndex": 0,
+				"level": "warning",
+				"message": { "text": "Found unpinned action 'actions/checkout@v4'" },
+				"locations": [{
+					"physicalLocation": {
+						"artifactLocation": { "uri": ".github/workflows/ci.yml" },
+						"region": { "startLine": 12 },
+					},
+				}],
+				"partialFingerprints": { (FINGERPRINT_VERSION): "abc" },
+			})
+		);
+	}
+
+	#[test]
+	fn empty_rule_fields_are_omitted() {
+		let mut rules = Rules::default();
+		rules.add("mitre/activity", Some(String::new()), Vec::new());
+		let rule = serde_json::to_value(&rules.rules[0]).unwrap();
+		assert_eq!(rule, serde_json::json!({ "id": "mitre/activity" }));
+	}
+}
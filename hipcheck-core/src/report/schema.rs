@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Versions of the JSON report's schema.
+//!
+//! The schema's major version goes up when the report changes in a way which
+//! could break tools reading it, like a field being removed, renamed, or changing
+//! type. Adding a field doesn't change the version. Hipcheck can still write
+//! reports in the previous major version, so tools have time to catch up.
+//!
+//! The versions so far are:
+//!
+//! - 1: Reports from before the schema was versioned, which have no
+//!   `schema_version` field.
+//! - 2: Reports with a `schema_version` field.
+
+use crate::{
+	error::{Error, Result},
+	hc_error,
+	report::Report,
+};
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+use std::{
+	fmt::{self, Display, Formatter},
+	str::FromStr,
+};
+
+/// A major version of the JSON report's schema.
+#[derive(
+	Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(transparent)]
+#[schemars(crate = "schemars")]
+pub struct SchemaVersion(u32);
+
+impl SchemaVersion {
+	/// The version reports are written in unless another is asked for.
+	pub const CURRENT: SchemaVersion = SchemaVersion(2);
+
+	/// The oldest version reports can still be written in.
+	pub const OLDEST: SchemaVersion = SchemaVersion(1);
+
+	/// The version of reports from before the schema was versioned.
+	const UNVERSIONED: SchemaVersion = SchemaVersion(1);
+
+	/// Check if reports in this version have no `schema_version` field.
+	pub fn is_unversioned(&self) -> bool {
+		*self == SchemaVersion::UNVERSIONED
+	}
+
+	/// Get the JSON schema of reports written in this version.
+	pub fn report_schema(&self) -> RootSchema {
+		let mut schema = schema_for!(Report);
+
+		if self.is_unversioned() {
+			if let Some(object) = schema.schema.object.as_mut() {
+				object.properties.remove("schema_version");
+				object.required.remove("schema_version");
+			}
+		}
+
+		schema
+	}
+}
+
+impl Default for SchemaVersion {
+	fn default() -> Self {
+		SchemaVersion::CURRENT
+	}
+}
+
+impl Display for SchemaVersion {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.0)
+	}
+}
+
+impl FromStr for SchemaVersion {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		let version = s
+			.trim()
+			.parse()
+			.map(SchemaVersion)
+			.map_err(|_| hc_error!("invalid report schema version '{}'", s))?;
+
+		if version < SchemaVersion::OLDEST || version > SchemaVersion::CURRENT {
+			return Err(hc_error!(
+				"report schema version {} isn't supported, expected {} through {}",
+				version,
+				SchemaVersion::OLDEST,
+				SchemaVersion::CURRENT
+			));
+		}
+
+		Ok(version)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_supported_versions() {
+		assert_eq!("1".parse::<SchemaVersion>().unwrap(), SchemaVersion::OLDEST);
+		assert_eq!(
+			"2".parse::<SchemaVersion>().unwrap(),
+			SchemaVersion::CURRENT
+		);
+		assert!("0".parse::<SchemaVersion>().is_err());
+		assert!("3".parse::<SchemaVersion>().is_err());
+		assert!("latest".parse::<SchemaVersion>().is_err());
+	}
+
+	#[test]
+	fn unversioned_schema_has_no_version_field() {
+		let properties = |version: SchemaVersion| {
+			version
+				.report_schema()
+				.schema
+				.object
+				.expect("report schema is an object")
+				.properties
+		};
+
+		assert!(properties(SchemaVersion::CURRENT).contains_key("schema_version"));
+		assert!(!properties(SchemaVersion::OLDEST).contains_key("schema_version"));
+	}
+}
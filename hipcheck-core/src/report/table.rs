@@ -0,0 +1,194 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! A table of the outcome for each target of a run, as CSV.
+//!
+//! The table is meant for spreadsheets and BI tools comparing many targets,
+//! like the components of an SBOM, so it has one row per target:
+//!
+//! - `target`: the target, as given.
+//! - `head`: the HEAD commit hash of the repository during analysis.
+//! - `recommendation`: `pass`, `use-with-conditions`, or `investigate`.
+//! - `risk_score`: the final risk score of the target.
+//! - `error`: why the target couldn't be analyzed, if it couldn't.
+//! - `<analysis>`: `pass`, `fail`, `error`, or `skip` for each analysis run
+//!   on any target, left empty for targets it wasn't run on.
+//!
+//! Targets which couldn't be analyzed only have `target` and `error` set.
+
+use crate::report::{
+	batch::{BatchReport, TargetOutcome},
+	features::csv_escape,
+	RecommendationKind, Report,
+};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	fmt::Write as _,
+};
+
+/// The columns before the analysis columns.
+const FIXED_COLUMNS: [&str; 5] = ["target", "head", "recommendation", "risk_score", "error"];
+
+/// The outcome for each target of a run.
+#[derive(Debug, Default)]
+pub struct TargetTable {
+	/// Every analysis run on any target, ordered by name.
+	analyses: BTreeSet<String>,
+
+	rows: Vec<TargetRow>,
+}
+
+/// The outcome for one target.
+#[derive(Debug, Default)]
+struct TargetRow {
+	target: String,
+	head: String,
+	recommendation: String,
+	risk_score: String,
+	error: String,
+	/// The outcome of each analysis run on the target.
+	analyses: BTreeMap<String, &'static str>,
+}
+
+impl TargetTable {
+	/// Make a table with a single row, for a report.
+	pub fn from_report(report: &Report) -> TargetTable {
+		let mut table = TargetTable::default();
+		table.add_report(report.repo_name.to_string(), report);
+		table
+	}
+
+	/// Make a table with a row for every target of a batch run, in order.
+	pub fn from_batch(batch: &BatchReport) -> TargetTable {
+		let mut table = TargetTable::default();
+
+		// Reports are kept in the order of the targets which could be analyzed
+		let mut reports = batch.reports.iter();
+		for target in &batch.summary.targets {
+			match &target.outcome {
+				TargetOutcome::Analyzed { .. } => {
+					if let Some(report) = reports.next() {
+						table.add_report(target.target.clone(), report);
+					}
+				}
+				TargetOutcome::Errored { error } => table.rows.push(TargetRow {
+					target: target.target.clone(),
+					error: error.msg.clone(),
+					..TargetRow::default()
+				}),
+			}
+		}
+
+		table
+	}
+
+	fn add_report(&mut self, target: String, report: &Report) {
+		let mut analyses = BTreeMap::new();
+		for analysis in report.passing_analyses() {
+			analyses.insert(analysis.name.clone(), "pass");
+		}
+		for failing in report.failing_analyses() {
+			analyses.insert(failing.analysis().name.clone(), "fail");
+		}
+		for errored in report.errored_analyses() {
+			analyses.insert(errored.analysis().0.clone(), "error");
+		}
+		for skipped in report.skipped_analyses() {
+			analyses.insert(skipped.analysis().0.clone(), "skip");
+		}
+		self.analyses.extend(analyses.keys().cloned());
+
+		let recommendation = match report.recommendation.kind {
+			RecommendationKind::Pass => "pass",
+			RecommendationKind::UseWithConditions => "use-with-conditions",
+			RecommendationKind::Investigate => "investigate",
+		};
+
+		self.rows.push(TargetRow {
+			target,
+			head: report.repo_head.to_string(),
+			recommendation: recommendation.to_owned(),
+			risk_score: report.recommendation.risk_score.0.to_string(),
+			error: String::new(),
+			analyses,
+		});
+	}
+
+	/// Render the table as CSV, with a header row.
+	pub fn to_csv(&self) -> String {
+		let mut out = String::new();
+
+		let header = FIXED_COLUMNS
+			.into_iter()
+			.chain(self.analyses.iter().map(String::as_str))
+			.map(csv_escape)
+			.collect::<Vec<_>>()
+			.join(",");
+		// Writing to a `String` can't fail.
+		let _ = writeln!(out, "{header}");
+
+		for row in &self.rows {
+			let fixed = [
+				&row.target,
+				&row.head,
+				&row.recommendation,
+				&row.risk_score,
+				&row.error,
+			];
+			let outcomes = self
+				.analyses
+				.iter()
+				.map(|analysis| row.analyses.get(analysis).copied().unwrap_or_default());
+			let line = fixed
+				.into_iter()
+				.map(String::as_str)
+				.chain(outcomes)
+				.map(csv_escape)
+				.collect::<Vec<_>>()
+				.join(",");
+			let _ = writeln!(out, "{line}");
+		}
+
+		out
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hc_error;
+
+	#[test]
+	fn errored_targets_only_have_an_error() {
+		let batch = BatchReport::new(vec![(
+			"pkg:npm/left-pad@1.3.0".to_owned(),
+			Err(hc_error!("clone failed, with status 128")),
+		)]);
+
+		assert_eq!(
+			TargetTable::from_batch(&batch).to_csv(),
+			"target,head,recommendation,risk_score,error\n\
+			pkg:npm/left-pad@1.3.0,,,,\"clone failed, with status 128\"\n"
+		);
+	}
+
+	#[test]
+	fn missing_analyses_are_left_empty() {
+		let table = TargetTable {
+			analyses: BTreeSet::from(["mitre/activity".to_owned(), "mitre/typo".to_owned()]),
+			rows: vec![TargetRow {
+				target: "https://github.com/mitre/hipcheck".to_owned(),
+				head: "abc123".to_owned(),
+				recommendation: "pass".to_owned(),
+				risk_score: "0.2".to_owned(),
+				error: String::new(),
+				analyses: BTreeMap::from([("mitre/typo".to_owned(), "fail")]),
+			}],
+		};
+
+		assert_eq!(
+			table.to_csv(),
+			"target,head,recommendation,risk_score,error,mitre/activity,mitre/typo\n\
+			https://github.com/mitre/hipcheck,abc123,pass,0.2,,,fail\n"
+		);
+	}
+}
@@ -1,19 +1,21 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-	config::{visit_leaves, Analysis, AnalysisTree, WeightTreeProvider},
-	engine::HcEngine,
+	config::{visit_leaves, Analysis, AnalysisTree, PoliciedAnalysis, WeightTreeProvider},
+	engine::{HcEngine, QueryDispatcher},
 	error::Result,
 	hc_error,
-	plugin::QueryResult,
+	plugin::{get_plugin_key, Prerequisite, QueryResult},
 	policy_exprs::{std_exec, Expr},
 	shell::spinner_phase::SpinnerPhase,
 	source::SourceQuery,
 };
+use indexmap::IndexMap;
 use indextree::{Arena, NodeId};
 #[cfg(test)]
 use num_traits::identities::Zero;
-use std::{collections::HashMap, default::Default};
+use serde_json::Value;
+use std::{collections::HashMap, default::Default, ops::Not, sync::Arc};
 
 #[cfg(test)]
 pub const PRACTICES_PHASE: &str = "practices";
@@ -43,6 +45,8 @@ pub struct PluginAnalysisResult {
 	pub response: Result<QueryResult>,
 	pub policy: Expr,
 	pub passed: bool,
+	/// The share of the risk score the analysis adds if it fails.
+	pub weight: f64,
 }
 
 #[derive(Debug, Default)]
@@ -187,7 +191,7 @@ pub struct ScoreTreeNode {
 	pub weight: f64,
 }
 
-pub fn score_results(_phase: &SpinnerPhase, db: &dyn ScoringProvider) -> Result<ScoringResults> {
+pub fn score_results(phase: &SpinnerPhase, db: &dyn ScoringProvider) -> Result<ScoringResults> {
 	// Scoring should be performed by the construction of a "score tree" where scores are the
 	// nodes and weights are the edges. The leaves are the analyses themselves, which either
 	// pass (a score of 0) or fail (a score of 1). These are then combined with the other
@@ -198,25 +202,43 @@ pub fn score_results(_phase: &SpinnerPhase, db: &dyn ScoringProvider) -> Result<
 	// from children nodes with a score set by hc_analysis algorithms
 
 	let analysis_tree = db.analysis_tree()?;
+	let weights: HashMap<Analysis, f64> =
+		analysis_tree.get_analysis_weights().into_iter().collect();
 	let mut plugin_results = PluginAnalysisResults::default();
 
 	// RFD4 analysis style - get all "leaf" analyses and call through plugin architecture
 	let plugin_score_tree = {
 		let target_json = serde_json::to_value(db.target().as_ref())?;
-
-		for analysis in analysis_tree.get_analyses() {
+		let analyses = analysis_tree.get_analyses();
+
+		// The analyses are independent of each other, so their queries run
+		// concurrently, sharing any queries they have in common
+		let dispatcher = db.dispatcher();
+
+		run_prerequisites(&dispatcher, &analyses, &target_json);
+
+		// Perform queries, passing target in JSON
+		let queries = analyses
+			.iter()
+			.map(|analysis| {
+				(
+					analysis.0.publisher.clone(),
+					analysis.0.plugin.clone(),
+					analysis.0.query.clone(),
+					target_json.clone(),
+				)
+			})
+			.collect();
+
+		// Plugins which report progress on their queries drive the phase's status
+		dispatcher.core().progress().show_in(phase.clone());
+		let responses = dispatcher.query_all(queries, db.exec_config().concurrency.limit);
+
+		for (analysis, response) in analyses.into_iter().zip(responses) {
 			let policy = analysis.1.ok_or(hc_error!(
 				"We should not have been able to get this far without a policy expr"
 			))?;
 
-			// Perform query, passing target in JSON
-			let response = db.query(
-				analysis.0.publisher.clone(),
-				analysis.0.plugin.clone(),
-				analysis.0.query.clone(),
-				target_json.clone(),
-			);
-
 			// Determine if analysis passed by evaluating policy expr
 			let passed = {
 				if let Ok(output) = &response {
@@ -245,6 +267,7 @@ pub fn score_results(_phase: &SpinnerPhase, db: &dyn ScoringProvider) -> Result<
 					response,
 					policy,
 					passed,
+					weight: weights.get(&analysis.0).copied().unwrap_or_default(),
 				},
 			);
 		}
@@ -262,6 +285,68 @@ pub fn score_results(_phase: &SpinnerPhase, db: &dyn ScoringProvider) -> Result<
 	})
 }
 
+/// Run the queries the analyses declared they depend on, once each, before the
+/// analyses themselves.
+///
+/// The results are memoized, so each analysis asking for a prerequisite gets the
+/// same result without it being recomputed, and without the time to compute it
+/// counting against the analysis' timeout. A failed prerequisite is reported once
+/// with every analysis that depends on it; the analyses then fail with its error.
+fn run_prerequisites(
+	dispatcher: &Arc<QueryDispatcher>,
+	analyses: &[PoliciedAnalysis],
+	target: &Value,
+) {
+	let core = dispatcher.core();
+
+	// Group the analyses by the prerequisites they share
+	let mut groups: IndexMap<&Prerequisite, Vec<String>> = IndexMap::new();
+	for analysis in analyses {
+		let plugin = get_plugin_key(&analysis.0.publisher, &analysis.0.plugin);
+		for prerequisite in core.prerequisites(&plugin) {
+			let dependents = groups.entry(prerequisite).or_default();
+			if dependents.contains(&plugin).not() {
+				dependents.push(plugin.clone());
+			}
+		}
+	}
+
+	for (prerequisite, dependents) in groups {
+		let name = format!(
+			"{}/{}/{}",
+			prerequisite.publisher, prerequisite.plugin, prerequisite.query
+		);
+		let Some(key) = target.pointer(&prerequisite.key) else {
+			tracing::warn!(
+				"prerequisite {} of {} has key '{}', which isn't part of the target",
+				name,
+				dependents.join(", "),
+				prerequisite.key
+			);
+			continue;
+		};
+
+		tracing::info!(
+			"running prerequisite {} for {}",
+			name,
+			dependents.join(", ")
+		);
+		if let Err(e) = dispatcher.query_blocking(
+			prerequisite.publisher.clone(),
+			prerequisite.plugin.clone(),
+			prerequisite.query.clone(),
+			key.clone(),
+		) {
+			tracing::warn!(
+				"prerequisite {} failed, so {} will fail too: {}",
+				name,
+				dependents.join(", "),
+				e
+			);
+		}
+	}
+}
+
 fn decimal_truncate(score: f64) -> f64 {
 	(score * 100.0).round() / 100.0
 }
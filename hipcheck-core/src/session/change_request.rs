@@ -0,0 +1,247 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Analyzing GitHub pull requests and GitLab merge requests.
+//!
+//! A change request is analyzed as its repository at the request's head, with
+//! the analyses scoped to the commits the request would merge: those made
+//! since the head branched off from the branch the request targets.
+
+use crate::{
+	error::{Context as _, Result},
+	hc_error,
+	source::{self, git::merge_base},
+	target::{LocalGitRepo, TargetSeed, TargetSeedKind},
+	util::{http::agent::agent, network::require_network},
+};
+use serde_json::Value;
+use std::fmt;
+use url::Url;
+
+/// The site hosting a change request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeRequestHost {
+	GitHub,
+	GitLab,
+}
+
+/// A GitHub pull request or GitLab merge request to analyze.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeRequest {
+	/// The URL the request was given as.
+	pub url: String,
+	pub host: ChangeRequestHost,
+	/// The repository the request was made to.
+	pub repo: Url,
+	/// The request's number in its repository.
+	pub number: u64,
+}
+
+impl ChangeRequest {
+	/// Parse the URL of a change request, or get `None` if it isn't one.
+	///
+	/// GitHub pull requests look like `https://github.com/<owner>/<repo>/pull/<N>`,
+	/// and GitLab merge requests like `https://gitlab.com/<group>/<repo>/-/merge_requests/<N>`,
+	/// where the group may have subgroups and the GitLab instance may be self-hosted.
+	pub fn parse(target: &str) -> Option<ChangeRequest> {
+		let url = Url::parse(target).ok()?;
+		let segments = url.path_segments()?.collect::<Vec<_>>();
+
+		let (host, repo, number) = match segments.as_slice() {
+			[_, _, "pull", number, ..] if url.host_str() == Some("github.com") => {
+				(ChangeRequestHost::GitHub, &segments[..2], *number)
+			}
+			_ => {
+				let i = segments
+					.windows(2)
+					.position(|pair| pair == ["-", "merge_requests"])?;
+				(
+					ChangeRequestHost::GitLab,
+					&segments[..i],
+					*segments.get(i + 2)?,
+				)
+			}
+		};
+		if repo.len() < 2 || !number.bytes().all(|b| b.is_ascii_digit()) {
+			return None;
+		}
+		let number = number.parse().ok()?;
+
+		let mut repo_url = url.clone();
+		repo_url.set_path(&repo.join("/"));
+		repo_url.set_query(None);
+		repo_url.set_fragment(None);
+		Some(ChangeRequest {
+			url: target.to_owned(),
+			host,
+			repo: repo_url,
+			number,
+		})
+	}
+
+	/// The ref the request's host keeps the request's head at, which clones
+	/// don't fetch unless asked for.
+	pub fn head_ref(&self) -> String {
+		match self.host {
+			ChangeRequestHost::GitHub => format!("refs/pull/{}/head", self.number),
+			ChangeRequestHost::GitLab => format!("refs/merge-requests/{}/head", self.number),
+		}
+	}
+
+	/// Get the seed for the request's repository at the request's head.
+	pub fn seed(&self) -> Result<TargetSeed> {
+		Ok(TargetSeed {
+			kind: TargetSeedKind::RemoteRepo(source::get_remote_repo_from_url(self.repo.clone())?),
+			refspec: Some(self.head_ref()),
+			specifier: self.url.clone(),
+		})
+	}
+
+	/// Get the commit the request's head branched off from the branch it
+	/// targets, given a clone of its repository checked out at the head.
+	///
+	/// Only the commits made since are the request's own. If the host can't
+	/// be asked which branch the request targets, the repository's default
+	/// branch is assumed.
+	pub fn base(&self, repo: &LocalGitRepo) -> Result<String> {
+		let branch = match self.target_branch() {
+			Ok(branch) => Some(branch),
+			Err(e) => {
+				tracing::warn!(
+					"failed to get the branch {} targets, so assuming the default branch: {}",
+					self,
+					e
+				);
+				None
+			}
+		};
+		merge_base(&repo.path, branch.as_deref())
+			.with_context(|| format!("failed to find the commits {} contributes", self))
+	}
+
+	/// Ask the request's host which branch the request would be merged into.
+	fn target_branch(&self) -> Result<String> {
+		require_network(format!("asking which branch {} targets", self))?;
+
+		let project = self.repo.path().trim_start_matches('/');
+		let (api, field) = match self.host {
+			ChangeRequestHost::GitHub => (
+				format!(
+					"https://api.github.com/repos/{}/pulls/{}",
+					project, self.number
+				),
+				"/base/ref",
+			),
+			ChangeRequestHost::GitLab => {
+				let mut api = self.repo.clone();
+				api.set_path(&format!(
+					"/api/v4/projects/{}/merge_requests/{}",
+					project.replace('/', "%2F"),
+					self.number
+				));
+				(api.to_string(), "/target_branch")
+			}
+		};
+
+		let mut request = agent().get(&api);
+		if self.host == ChangeRequestHost::GitHub {
+			request = request.set("Accept", "application/vnd.github+json");
+			if let Ok(token) = std::env::var("HC_GITHUB_TOKEN") {
+				request = request.set("Authorization", &format!("Bearer {}", token));
+			}
+		}
+
+		let response: Value = request
+			.call()
+			.map_err(|e| hc_error!("{}", e))?
+			.into_json()
+			.map_err(|e| hc_error!("{}", e))?;
+		response
+			.pointer(field)
+			.and_then(Value::as_str)
+			.map(ToOwned::to_owned)
+			.ok_or_else(|| hc_error!("{} didn't say which branch {} targets", api, self))
+	}
+}
+
+impl fmt::Display for ChangeRequest {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.url.fmt(f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::target::KnownRemote;
+
+	#[test]
+	fn parses_github_pull_requests() {
+		let request =
+			ChangeRequest::parse("https://github.com/mitre/hipcheck/pull/9/files?diff=split")
+				.unwrap();
+		assert_eq!(request.host, ChangeRequestHost::GitHub);
+		assert_eq!(request.repo.as_str(), "https://github.com/mitre/hipcheck");
+		assert_eq!(request.number, 9);
+		assert_eq!(request.head_ref(), "refs/pull/9/head");
+
+		assert!(ChangeRequest::parse("https://github.com/mitre/hipcheck").is_none());
+		assert!(ChangeRequest::parse("https://github.com/mitre/hipcheck/pull/new").is_none());
+		assert!(ChangeRequest::parse("https://github.com/mitre/hipcheck/pull/").is_none());
+		assert!(ChangeRequest::parse("https://example.com/mitre/hipcheck/pull/9").is_none());
+		assert!(ChangeRequest::parse("./hipcheck").is_none());
+	}
+
+	#[test]
+	fn parses_gitlab_merge_requests() {
+		let request =
+			ChangeRequest::parse("https://gitlab.com/group/subgroup/project/-/merge_requests/7")
+				.unwrap();
+		assert_eq!(request.host, ChangeRequestHost::GitLab);
+		assert_eq!(
+			request.repo.as_str(),
+			"https://gitlab.com/group/subgroup/project"
+		);
+		assert_eq!(request.number, 7);
+		assert_eq!(request.head_ref(), "refs/merge-requests/7/head");
+
+		let request =
+			ChangeRequest::parse("https://git.example.com/group/project/-/merge_requests/12/diffs")
+				.unwrap();
+		assert_eq!(
+			request.repo.as_str(),
+			"https://git.example.com/group/project"
+		);
+		assert_eq!(request.number, 12);
+
+		assert!(ChangeRequest::parse("https://gitlab.com/project/-/merge_requests/7").is_none());
+		assert!(
+			ChangeRequest::parse("https://gitlab.com/group/project/-/merge_requests").is_none()
+		);
+	}
+
+	#[test]
+	fn seeds_the_repository_at_the_request_head() {
+		let request = ChangeRequest::parse("https://github.com/mitre/hipcheck/pull/123").unwrap();
+		let seed = request.seed().unwrap();
+		assert_eq!(seed.refspec.as_deref(), Some("refs/pull/123/head"));
+		assert_eq!(seed.specifier, "https://github.com/mitre/hipcheck/pull/123");
+		let TargetSeedKind::RemoteRepo(remote) = seed.kind else {
+			panic!("a change request should be analyzed as a remote repository");
+		};
+		assert_eq!(remote.url.as_str(), "https://github.com/mitre/hipcheck");
+		assert!(matches!(
+			remote.known_remote,
+			Some(KnownRemote::GitHub { ref owner, ref repo }) if owner == "mitre" && repo == "hipcheck"
+		));
+
+		let request =
+			ChangeRequest::parse("https://gitlab.com/group/project/-/merge_requests/7").unwrap();
+		let seed = request.seed().unwrap();
+		assert_eq!(seed.refspec.as_deref(), Some("refs/merge-requests/7/head"));
+		let TargetSeedKind::RemoteRepo(remote) = seed.kind else {
+			panic!("a change request should be analyzed as a remote repository");
+		};
+		assert_eq!(remote.url.as_str(), "https://gitlab.com/group/project");
+		assert!(remote.known_remote.is_none());
+	}
+}
@@ -5,16 +5,150 @@
 use crate::{
 	error::{Context as _, Result},
 	hc_error,
-	session::pm::{extract_repo_for_maven, extract_repo_for_npm, extract_repo_for_pypi},
+	session::{
+		pm::{extract_repo_for_maven, extract_repo_for_npm, extract_repo_for_pypi},
+		sbom::SbomComponent,
+	},
 };
-use cyclonedx_bom::prelude::*;
+use cyclonedx_bom::{models::external_reference::ExternalReferenceType, prelude::*};
 use packageurl::PackageUrl;
-use std::str::FromStr;
+use std::{ops::Not as _, str::FromStr};
 use url::Url;
 
 /// Extract the first compatible package download location from a
 /// CycloneDX document
 pub fn extract_cyclonedx_download_url(filepath: &str) -> Result<Url> {
+	extract_download_url(parse_bom(filepath)?)
+}
+
+/// Extract the download location of every component of a CycloneDX document
+/// which has one Hipcheck can resolve, including the document's own package,
+/// along with the ecosystem of its pURL.
+///
+/// Components which can't be resolved are left out.
+pub fn extract_cyclonedx_components(filepath: &str) -> Result<Vec<SbomComponent>> {
+	let bom = parse_bom(filepath)?;
+
+	let mut sources = Vec::new();
+
+	if let Some(component) = bom.metadata.and_then(|metadata| metadata.component) {
+		collect_sources(component, &mut sources);
+	}
+
+	for component in bom.components.map(|c| c.0).unwrap_or_default() {
+		collect_sources(component, &mut sources);
+	}
+
+	let mut components: Vec<SbomComponent> = Vec::new();
+
+	for source in sources {
+		match source.url() {
+			Ok(url) => {
+				if components.iter().any(|c| c.url == url).not() {
+					components.push(SbomComponent {
+						url,
+						ecosystem: source.ecosystem(),
+					});
+				}
+			}
+			Err(e) => tracing::warn!("skipping SBOM component '{}': {}", source.name, e),
+		}
+	}
+
+	Ok(components)
+}
+
+/// The parts of a CycloneDX component which can point to its source repository.
+#[derive(Debug)]
+struct ComponentSource {
+	/// The name of the component, for error messages.
+	name: String,
+	/// The component's pURL, if any.
+	purl: Option<String>,
+	/// The URLs of the component's external references of type `vcs`.
+	vcs: Vec<String>,
+}
+
+impl ComponentSource {
+	fn from_component(component: &Component) -> ComponentSource {
+		let vcs = component
+			.external_references
+			.iter()
+			.flat_map(|refs| refs.0.iter())
+			.filter(|r| r.external_reference_type == ExternalReferenceType::Vcs)
+			.map(|r| r.url.to_string())
+			.collect();
+
+		ComponentSource {
+			name: component.name.to_string(),
+			purl: component.purl.as_ref().map(|p| p.as_ref().to_owned()),
+			vcs,
+		}
+	}
+
+	/// Find the git repo URL for the component.
+	///
+	/// The pURL is tried first, since it names the package precisely, then
+	/// each `vcs` external reference in turn.
+	fn url(&self) -> Result<Url> {
+		let mut error = None;
+
+		if let Some(purl) = &self.purl {
+			match url_for_purl(purl) {
+				Ok(url) => return Ok(url),
+				Err(e) => error = Some(e),
+			}
+		}
+
+		for vcs in &self.vcs {
+			match url_for_vcs(vcs) {
+				Ok(url) => return Ok(url),
+				Err(e) => error = error.or(Some(e)),
+			}
+		}
+
+		Err(error.unwrap_or_else(|| {
+			hc_error!("component has neither a pURL nor a VCS external reference")
+		}))
+	}
+
+	/// Get the ecosystem the component is from, which is the type of its pURL.
+	fn ecosystem(&self) -> Option<String> {
+		let purl = PackageUrl::from_str(self.purl.as_ref()?).ok()?;
+		Some(purl.ty().to_owned())
+	}
+}
+
+// Collect the sources of a component and all the components nested in it
+fn collect_sources(component: Component, sources: &mut Vec<ComponentSource>) {
+	sources.push(ComponentSource::from_component(&component));
+
+	for child in component.components.map(|c| c.0).unwrap_or_default() {
+		collect_sources(child, sources);
+	}
+}
+
+// Get the git repo URL from the URL of a `vcs` external reference
+fn url_for_vcs(vcs: &str) -> Result<Url> {
+	// `git+` prefixes name the VCS; what follows is the transport URL
+	let mut url = Url::parse(vcs.strip_prefix("git+").unwrap_or(vcs))
+		.context("Invalid VCS external reference")?;
+
+	if matches!(url.scheme(), "git" | "http" | "https" | "ssh").not() {
+		return Err(hc_error!(
+			"VCS external reference '{}' is not a Git repository URL",
+			vcs
+		));
+	}
+
+	// Remove any ref or path given after the repository
+	url.set_fragment(None);
+
+	Ok(url)
+}
+
+// Parse and validate a CycloneDX document in JSON or XML format
+fn parse_bom(filepath: &str) -> Result<Bom> {
 	let contents = std::fs::read_to_string(filepath)?;
 
 	if filepath.contains(".json") {
@@ -22,14 +156,14 @@ pub fn extract_cyclonedx_download_url(filepath: &str) -> Result<Url> {
 			hc_error!("CycloneDX JSON file is corrupt or otherwise cannot be parsed. It may be in an incompatble CycloneDX format (only v. 1.3 - 1.5 supported)")
 		})?;
 		if bom.validate().passed() {
-			extract_download_url(bom)
+			Ok(bom)
 		} else {
 			Err(hc_error!("CycloneDX file is not a valid SBOM"))
 		}
 	} else if filepath.contains(".xml") {
 		let bom = parse_from_xml(contents)?;
 		if bom.validate().passed() {
-			extract_download_url(bom)
+			Ok(bom)
 		} else {
 			Err(hc_error!("CycloneDX file is not a valid SBOM"))
 		}
@@ -41,16 +175,23 @@ pub fn extract_cyclonedx_download_url(filepath: &str) -> Result<Url> {
 // Extract the metadata component download location from a CycloneDX
 // object obtained from a JSON or XML file
 fn extract_download_url(bom: Bom) -> Result<Url> {
-	let purl = PackageUrl::from_str(
-        bom
+	let component = bom
         .metadata
         .ok_or(hc_error!("CycloneDX file is missing a metadata field. Download location cannot be extracted."))?
         .component
-        .ok_or(hc_error!("CycloneDX file metadata missing a component field describing its own package. Download location cannot be extracted."))?
-        .purl
-        .ok_or(hc_error!("CycloneDX file metadata component information does not include a pURL. Download location cannot be extracted."))?
-        .as_ref()
-    )?;
+        .ok_or(hc_error!("CycloneDX file metadata missing a component field describing its own package. Download location cannot be extracted."))?;
+
+	let source = ComponentSource::from_component(&component);
+	if source.purl.is_none() && source.vcs.is_empty() {
+		return Err(hc_error!("CycloneDX file metadata component information does not include a pURL or VCS external reference. Download location cannot be extracted."));
+	}
+
+	source.url()
+}
+
+// Get the git repo URL for a component from its pURL
+fn url_for_purl(purl: &str) -> Result<Url> {
+	let purl = PackageUrl::from_str(purl)?;
 
 	match purl.ty() {
 		"github" => {
@@ -182,4 +323,49 @@ mod tests {
 			"https://github.com/juice-shop/juice-shop.git".to_string()
 		);
 	}
+
+	#[test]
+	fn test_unresolvable_purl() {
+		assert!(url_for_purl("pkg:cargo/serde@1.0.0").is_err());
+	}
+
+	#[test]
+	fn test_vcs_reference() {
+		let url = url_for_vcs("git+https://github.com/mitre/hipcheck.git#main").unwrap();
+		assert_eq!(url.as_str(), "https://github.com/mitre/hipcheck.git");
+
+		assert!(url_for_vcs("svn+https://example.com/repo").is_err());
+	}
+
+	#[test]
+	fn test_component_ecosystem() {
+		let source = ComponentSource {
+			name: "lodash".to_owned(),
+			purl: Some("pkg:npm/lodash@4.17.21".to_owned()),
+			vcs: vec![],
+		};
+		assert_eq!(source.ecosystem().as_deref(), Some("npm"));
+	}
+
+	#[test]
+	fn test_vcs_reference_used_without_purl() {
+		let source = ComponentSource {
+			name: "hipcheck".to_owned(),
+			purl: None,
+			vcs: vec![
+				"svn://example.com/repo".to_owned(),
+				"https://github.com/mitre/hipcheck".to_owned(),
+			],
+		};
+		assert_eq!(
+			source.url().unwrap().as_str(),
+			"https://github.com/mitre/hipcheck"
+		);
+	}
+
+	#[test]
+	fn test_github_purl() {
+		let url = url_for_purl("pkg:github/mitre/hipcheck@v3.0.0").unwrap();
+		assert_eq!(url.as_str(), "https://github.com/mitre/hipcheck.git");
+	}
 }
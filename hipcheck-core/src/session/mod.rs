@@ -1,25 +1,39 @@
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod change_request;
 pub mod cyclone_dx;
 pub mod pm;
+pub mod resolver;
+pub mod sample;
+pub mod sbom;
 pub mod spdx;
 
 use crate::{
-	cache::plugin::HcPluginCache,
-	cli::Format,
+	cache::{
+		plugin::HcPluginCache,
+		plugin_data::HcPluginDataCache,
+		remote::HcRemoteFileCache,
+		repo::{migrate_clone_dirs, HcRepoCache},
+		results::HcResultCache,
+		scratch::HcScratchCache,
+	},
 	config::{
 		Config, ConfigSource, ConfigSourceStorage, RiskConfigQuery, RiskConfigQueryStorage,
 		WeightTreeQueryStorage,
 	},
-	engine::{start_plugins, HcEngine, HcEngineStorage},
+	engine::{start_plugins, HcEngine, HcEngineStorage, QueryDispatcher},
 	error::{Context as _, Error, Result},
 	exec::ExecConfig,
 	hc_error,
+	plugin::{HcPluginCore, PluginPoolDir},
 	policy::{config_to_policy, PolicyFile},
-	report::{ReportParams, ReportParamsStorage},
+	report::{Format, ReportParams, ReportParamsStorage},
 	score::ScoringProviderStorage,
 	shell::{spinner_phase::SpinnerPhase, Shell},
-	source::{SourceQuery, SourceQueryStorage},
+	source::{
+		git::{is_partial_clone, subdir, try_set_repo_size_limit},
+		SourceQuery, SourceQueryStorage,
+	},
 	target::{
 		resolve::{TargetResolver, TargetResolverConfig},
 		Target, TargetSeed, TargetSeedKind,
@@ -29,12 +43,16 @@ use crate::{
 	version::{VersionQuery, VersionQueryStorage},
 };
 use chrono::prelude::*;
+use resolver::TargetRequest;
 use std::{
 	env, fmt,
 	path::{Path, PathBuf},
 	rc::Rc,
 	result::Result as StdResult,
-	sync::Arc,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
 	time::Duration,
 };
 
@@ -80,37 +98,14 @@ impl Session {
 	/// Construct a new `Session` which owns all the data needed in later phases.
 	#[allow(clippy::too_many_arguments)]
 	pub fn new(
-		target: &TargetSeed,
+		target: &TargetRequest,
 		config_path: Option<PathBuf>,
 		home_dir: Option<PathBuf>,
 		policy_path: Option<PathBuf>,
 		exec_path: Option<PathBuf>,
 		format: Format,
 	) -> StdResult<Session, Error> {
-		/*===================================================================
-		 *  Setting up the session.
-		 *-----------------------------------------------------------------*/
-
-		// Input query setters are implemented on `Session`, not
-		// `salsa::Storage<Session>`
-		let mut session = Session {
-			storage: Default::default(),
-		};
-
-		/*===================================================================
-		 * Printing the prelude.
-		 *-----------------------------------------------------------------*/
-
-		Shell::print_prelude(target.to_string());
-
-		/*===================================================================
-		 *  Loading current versions of needed software git, npm, and eslint into salsa.
-		 *-----------------------------------------------------------------*/
-
-		let (git_version, npm_version) = load_software_versions()?;
-
-		session.set_git_version(Rc::new(git_version));
-		session.set_npm_version(Rc::new(npm_version));
+		let mut session = Session::prepare(target)?;
 
 		/*===================================================================
 		 *  Loading configuration.
@@ -139,16 +134,11 @@ impl Session {
 			return Err(hc_error!("No policy file or (deprecated) config file found. Please provide a policy file before running Hipcheck."));
 		}
 
-		// Force eval the risk policy expr - wouldn't be necessary if the PolicyFile parsed
-		let _ = session.risk_policy()?;
-
 		/*===================================================================
 		 *  Load the Exec Configuration
 		 *-----------------------------------------------------------------*/
 		let exec = load_exec_config(exec_path.as_deref())?;
 
-		session.set_exec_config(Rc::new(exec));
-
 		/*===================================================================
 		 *  Resolving the Hipcheck home.
 		 *-----------------------------------------------------------------*/
@@ -158,16 +148,139 @@ impl Session {
 			.map(ToOwned::to_owned)
 			.ok_or_else(|| hc_error!("can't find cache directory"))?;
 
-		session.set_cache_dir(Rc::new(home.clone()));
+		Session::start(session, target, exec, home, format, None)
+	}
+
+	/// Construct a new `Session` from a policy and exec config which are
+	/// already loaded, rather than from the paths of their files.
+	pub fn with_policy(
+		target: &TargetRequest,
+		policy: PolicyFile,
+		exec: ExecConfig,
+		home: PathBuf,
+		format: Format,
+	) -> Result<Session> {
+		let mut session = Session::prepare(target)?;
+
+		// No config or policy file location
+		session.set_config_dir(None);
+		session.set_policy(Rc::new(policy));
+		session.set_policy_path(None);
 
-		let plugin_cache = HcPluginCache::new(&home);
+		Session::start(session, target, exec, home, format, None)
+	}
 
+	/// Construct a new `Session` like [`Session::with_policy`], but using
+	/// plugins which are already running instead of starting them.
+	///
+	/// The plugins must have been started by [`Session::start_core`] for an
+	/// equal policy. They're left running when the session is dropped, so
+	/// they can be used by the next session.
+	pub fn with_core(
+		target: &TargetRequest,
+		policy: PolicyFile,
+		exec: ExecConfig,
+		home: PathBuf,
+		format: Format,
+		core: Arc<HcPluginCore>,
+	) -> Result<Session> {
+		let mut session = Session::prepare(target)?;
+
+		// No config or policy file location
+		session.set_config_dir(None);
+		session.set_policy(Rc::new(policy));
+		session.set_policy_path(None);
+
+		Session::start(session, target, exec, home, format, Some(core))
+	}
+
+	/// Start the plugins a policy uses, for sessions made with
+	/// [`Session::with_core`].
+	pub fn start_core(
+		policy: &PolicyFile,
+		exec: &ExecConfig,
+		home: &Path,
+	) -> Result<Arc<HcPluginCore>> {
+		// Plugins are only kept running after the session if the exec config asks.
+		let pool = exec
+			.pool
+			.as_ref()
+			.and_then(|pool| PluginPoolDir::new(home, pool.idle_timeout));
+		let executor = ExecConfig::get_plugin_executor(exec)?.with_pool(pool);
+
+		start_plugins(
+			policy,
+			&HcPluginCache::new(home),
+			&HcRemoteFileCache::new(home),
+			HcScratchCache::new(home),
+			&HcPluginDataCache::new(home),
+			HcResultCache::new(home),
+			&exec.mirrors,
+			&exec.trust,
+			&exec.timeouts,
+			&exec.secrets,
+			&exec.limits,
+			executor,
+		)
+	}
+
+	/// Create an empty session and load the versions of the software it needs.
+	fn prepare(target: &TargetRequest) -> Result<Session> {
 		/*===================================================================
-		 *  Resolving the source.
+		 *  Setting up the session.
 		 *-----------------------------------------------------------------*/
 
-		let target = load_target(target, &home)?;
-		session.set_target(Arc::new(target));
+		// Input query setters are implemented on `Session`, not
+		// `salsa::Storage<Session>`
+		let mut session = Session {
+			storage: Default::default(),
+		};
+
+		/*===================================================================
+		 * Printing the prelude.
+		 *-----------------------------------------------------------------*/
+
+		Shell::print_prelude(target.to_string());
+
+		/*===================================================================
+		 *  Loading current versions of needed software git and npm into salsa.
+		 *-----------------------------------------------------------------*/
+
+		let (git_version, npm_version) = load_software_versions()?;
+
+		session.set_git_version(Rc::new(git_version));
+		session.set_npm_version(Rc::new(npm_version));
+
+		Ok(session)
+	}
+
+	/// Finish setting up a session whose policy is loaded, starting its
+	/// plugins, unless running ones are given, and resolving its target.
+	fn start(
+		mut session: Session,
+		target: &TargetRequest,
+		exec: ExecConfig,
+		home: PathBuf,
+		format: Format,
+		core: Option<Arc<HcPluginCore>>,
+	) -> Result<Session> {
+		// Force eval the risk policy expr - wouldn't be necessary if the PolicyFile parsed
+		let _ = session.risk_policy()?;
+		let _ = session.conditions_policy()?;
+
+		let limits = &session.policy().limits;
+		if let Some(max_size) = limits.max_repo_size {
+			try_set_repo_size_limit(max_size, limits.repo_size_action)?;
+		}
+
+		session.set_exec_config(Rc::new(exec));
+		session.set_cache_dir(Rc::new(home.clone()));
+
+		// Tidy the clones cache before cloning the target adds to it, unless
+		// it was already tidied for sessions running at once.
+		if !CLONES_CACHE_TIDIED.load(Ordering::Relaxed) {
+			tidy_clones_cache(&home, &session.exec_config());
+		}
 
 		/*===================================================================
 		 *  Resolving the Hipcheck version.
@@ -194,19 +307,102 @@ impl Session {
 		// has all the plugins up and running. However, HcPluginCore does not impl
 		// equal, and the idea of memoizing/invalidating it does not make sense.
 		// Thus, we will do the plugin startup here.
-		let policy = session.policy();
+		let core = match core {
+			// Plugins which ran for an earlier session may have cached results
+			// for its target, so forget it until this session's is resolved.
+			Some(core) => {
+				if let Some(results) = core.results() {
+					results.clear_head();
+				}
+				core
+			}
+			None => Session::start_core(&session.policy(), &session.exec_config(), &home)?,
+		};
+		// Queries are only shared within a session, since the same key, like
+		// the path a repository is cloned to, may refer to a different target
+		// in the next session of a batch run
+		session.set_dispatcher(QueryDispatcher::new(Arc::clone(&core)));
+		session.set_core(core);
+
+		/*===================================================================
+		 *  Resolving the source.
+		 *-----------------------------------------------------------------*/
 
-		let exec_config = session.exec_config();
+		// This happens after plugin startup so that a target resolver plugin
+		// can be asked for the source repository.
+		let request = target;
+		let seed = match request {
+			TargetRequest::Seed(seed) => seed.clone(),
+			TargetRequest::Plugin(seed) => seed.resolve(&session)?,
+			TargetRequest::ChangeRequest(request) => request.seed()?,
+		};
 
-		let executor = ExecConfig::get_plugin_executor(&exec_config)?;
+		let mut target = load_target(&seed, &home)?;
 
-		let core = start_plugins(policy.as_ref(), &plugin_cache, executor)?;
-		session.set_core(core);
+		// Scope the analyses to the commits a change request contributes.
+		if let TargetRequest::ChangeRequest(request) = request {
+			target.local.base = Some(request.base(&target.local)?);
+		}
+
+		// Scope the analyses to one package of a monorepo, if asked.
+		if let Some(subdir) = subdir() {
+			if !target.local.path.join(subdir).is_dir() {
+				return Err(hc_error!(
+					"subdirectory '{}' does not exist in the target repository",
+					subdir
+				));
+			}
+			target.local.subdir = Some(subdir.to_owned());
+		}
+
+		// Now that the commit being analyzed is known, plugin results from
+		// earlier runs against it can be reused. Results from a shallow or
+		// partial clone may be missing history, and results scoped to a
+		// subdirectory or a change request only cover part of it, so they
+		// aren't shared.
+		if let Some(results) = session.core().results() {
+			if !is_partial_clone(&target.local.path)
+				&& target.local.subdir.is_none()
+				&& target.local.base.is_none()
+			{
+				results.set_head(&target.local.path, &target.local.git_ref);
+			}
+		}
+
+		session.set_target(Arc::new(target));
 
 		Ok(session)
 	}
 }
 
+/// Whether the clones cache was tidied up front for sessions running at once,
+/// so they don't each tidy it as they start.
+static CLONES_CACHE_TIDIED: AtomicBool = AtomicBool::new(false);
+
+/// Tidy the clones cache once for sessions which will run at once, instead of
+/// each session tidying it as it starts, where they could race to move or
+/// delete the same clones.
+pub fn tidy_clones_cache_once(home: &Path, exec: &ExecConfig) {
+	if !CLONES_CACHE_TIDIED.swap(true, Ordering::Relaxed) {
+		tidy_clones_cache(home, exec);
+	}
+}
+
+/// Migrate clones made by older versions of Hipcheck, and keep the clones
+/// cache within the limits set in the exec config.
+fn tidy_clones_cache(home: &Path, exec: &ExecConfig) {
+	// Clones made by older versions of Hipcheck may use directory names
+	// which are no longer generated; failing to rename them only means
+	// they'll be cloned again.
+	if let Err(e) = migrate_clone_dirs(home) {
+		tracing::warn!("failed to migrate clones cache: {}", e);
+	}
+
+	if let Some(limits) = &exec.repo_cache {
+		HcRepoCache::new(home).prune_unused(limits);
+	}
+}
+
 fn load_software_versions() -> Result<(String, String)> {
 	let git_version = get_git_version()?;
 	DependentProgram::Git.check_version(&git_version)?;
@@ -265,7 +461,7 @@ pub fn load_policy_and_data(policy_path: Option<&Path>) -> Result<(PolicyFile, P
 	Ok((policy, valid_policy_path.to_path_buf()))
 }
 
-fn load_exec_config(exec_path: Option<&Path>) -> Result<ExecConfig> {
+pub fn load_exec_config(exec_path: Option<&Path>) -> Result<ExecConfig> {
 	// Start the phase
 	let phase = SpinnerPhase::start("loading exec config");
 	// Increment the phase into the "running" stage.
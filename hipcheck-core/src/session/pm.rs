@@ -4,7 +4,7 @@ use crate::{
 	error::{Context as _, Error, Result},
 	hc_error,
 	target::{Package, PackageHost},
-	util::http::agent,
+	util::{git::GitCommand, http::agent, network::require_network},
 	CheckKind, EXIT_FAILURE,
 };
 use serde_json::Value;
@@ -371,6 +371,49 @@ pub fn extract_package_version_from_url(url: Url) -> Result<(String, String)> {
 	}
 }
 
+/// Parse an NPM package, given as a URL or as `package[@version]`.
+pub fn parse_npm_package(raw_package: &str) -> Result<Package> {
+	let (name, version) = match Url::parse(raw_package) {
+		Ok(url_parsed) => extract_package_version_from_url(url_parsed)?,
+		_ => extract_package_version(raw_package)?,
+	};
+
+	// If the package is scoped, replace the leading '@' in the scope with %40 for proper pURL formatting
+	let purl = Url::parse(&match version.as_str() {
+		"no version" => format!("pkg:npm/{}", str::replace(&name, '@', "%40")),
+		_ => format!("pkg:npm/{}@{}", str::replace(&name, '@', "%40"), version),
+	})
+	.unwrap();
+
+	Ok(Package {
+		purl,
+		name,
+		version,
+		host: PackageHost::Npm,
+	})
+}
+
+/// Parse a PyPI package, given as a URL or as `package[@version]`.
+pub fn parse_pypi_package(raw_package: &str) -> Result<Package> {
+	let (name, version) = match Url::parse(raw_package) {
+		Ok(url_parsed) => extract_package_version_from_url(url_parsed)?,
+		_ => extract_package_version(raw_package)?,
+	};
+
+	let purl = Url::parse(&match version.as_str() {
+		"no version" => format!("pkg:pypi/{}", name),
+		_ => format!("pkg:pypi/{}@{}", name, version),
+	})
+	.unwrap();
+
+	Ok(Package {
+		purl,
+		name,
+		version,
+		host: PackageHost::PyPI,
+	})
+}
+
 /// Function to extract repo URL for NPM package given a Package struct
 fn extract_repo_for_npm_package(full_package: &Package) -> Result<Url> {
 	// Get the package and version
@@ -394,6 +437,7 @@ pub fn extract_repo_for_npm(package: &str, version: &str) -> Result<Url> {
 	};
 
 	// Make an HTTP request to that URL.
+	require_network("looking up the repository of an npm package")?;
 	let response = agent::agent()
 		.get(&registry)
 		.call()
@@ -447,6 +491,7 @@ pub fn extract_repo_for_pypi(package: &str, version: &str) -> Result<Url> {
 	};
 
 	// Make an HTTP request to that URL.
+	require_network("looking up the repository of a PyPI package")?;
 	let response = agent::agent()
 		.get(&registry)
 		.call()
@@ -479,6 +524,7 @@ pub fn extract_repo_for_pypi(package: &str, version: &str) -> Result<Url> {
 
 pub fn extract_repo_for_maven(url: &str) -> Result<Url> {
 	// Make an HTTP request to that URL to get the POM file.
+	require_network("looking up the repository of a Maven package")?;
 
 	let response = agent::agent()
 		.get(url)
@@ -498,6 +544,303 @@ pub fn extract_repo_for_maven(url: &str) -> Result<Url> {
 	best_scoring_url.ok_or_else(|| hc_error!("no valid repository URL found"))
 }
 
+/// Parse a Go module, given as `module[@version]`.
+pub fn parse_go_module(raw_module: &str) -> Result<(String, Option<String>)> {
+	let (module, version) = match raw_module.split_once('@') {
+		Some((module, version)) => (module, Some(version.to_owned())),
+		None => (raw_module, None),
+	};
+
+	if module.is_empty() || version.as_deref() == Some("") {
+		return Err(hc_error!(
+			"'{}' is not a Go module, which is given as <module>[@<version>]",
+			raw_module
+		));
+	}
+
+	Ok((module.to_owned(), version))
+}
+
+/// Function to extract the repo URL and the Git ref for a Go module given its
+/// path and version, or its latest version if none is given
+pub fn extract_repo_for_go(module: &str, version: Option<&str>) -> Result<(Url, String)> {
+	// Construct the module proxy URL.
+	let proxy = match version {
+		Some(version) => format!(
+			"https://proxy.golang.org/{}/@v/{}.info",
+			escape_go_path(module),
+			escape_go_path(version)
+		),
+		None => format!("https://proxy.golang.org/{}/@latest", escape_go_path(module)),
+	};
+
+	// Make an HTTP request to that URL.
+	require_network("looking up the repository of a Go module")?;
+	let response = agent::agent()
+		.get(&proxy)
+		.call()
+		.context("request to the Go module proxy failed, make sure the module path is correct as well as the module version")?;
+
+	// Parse the response as JSON.
+	let json: Value = {
+		let intermediate = response
+			.into_string()
+			.context("can't parse Go module proxy response")?;
+		serde_json::from_str(&intermediate).context("Go module proxy response isn't valid JSON")?
+	};
+
+	let version = json
+		.get("Version")
+		.and_then(Value::as_str)
+		.ok_or_else(|| hc_error!("no version field for Go module"))?;
+
+	// Newer versions record where they came from; older ones need the
+	// module's own `go-import` metadata to find the repo.
+	let origin = json.get("Origin");
+	let origin_url = origin
+		.and_then(|origin| origin.get("URL"))
+		.and_then(Value::as_str);
+	let (repository, subdir) = match origin_url {
+		Some(url) => {
+			let subdir = origin
+				.and_then(|origin| origin.get("Subdir"))
+				.and_then(Value::as_str)
+				.unwrap_or_default()
+				.to_owned();
+			(sanitize_url(url.to_owned())?, subdir)
+		}
+		None => extract_repo_for_go_import(module)?,
+	};
+
+	let origin_ref = origin
+		.and_then(|origin| origin.get("Ref"))
+		.and_then(Value::as_str)
+		.and_then(|git_ref| git_ref.strip_prefix("refs/tags/"));
+	let git_ref = match origin_ref {
+		Some(tag) => tag.to_owned(),
+		None => go_version_ref(version, &subdir),
+	};
+
+	Ok((repository, git_ref))
+}
+
+/// Find the repo of a Go module from the `go-import` meta tag its import path
+/// serves, along with the subdirectory of the repo the module is in.
+fn extract_repo_for_go_import(module: &str) -> Result<(Url, String)> {
+	let response = agent::agent()
+		.get(&format!("https://{}?go-get=1", module))
+		.call()
+		.context("request for the Go module's import metadata failed")?;
+	let html = response
+		.into_string()
+		.context("can't parse Go module import metadata")?;
+
+	let (prefix, url) = find_go_import(&html, module)
+		.ok_or_else(|| hc_error!("no git repository found for Go module '{}'", module))?;
+
+	// The major version suffix of a module is usually a branch, not a directory
+	let subdir = module
+		.strip_prefix(prefix)
+		.unwrap_or_default()
+		.trim_matches('/');
+	let subdir = match subdir.rsplit_once('/') {
+		Some((subdir, major)) if is_go_major_suffix(major) => subdir,
+		_ if is_go_major_suffix(subdir) => "",
+		_ => subdir,
+	};
+
+	Ok((sanitize_url(url.to_owned())?, subdir.to_owned()))
+}
+
+/// Get the import prefix and repo URL from the `go-import` meta tag for a
+/// module, if it's hosted in a git repo.
+fn find_go_import<'html>(html: &'html str, module: &str) -> Option<(&'html str, &'html str)> {
+	html.split("<meta")
+		.filter(|tag| tag.contains("name=\"go-import\""))
+		.filter_map(|tag| {
+			let content = tag.split("content=\"").nth(1)?.split('"').next()?;
+			let mut fields = content.split_whitespace();
+			Some((fields.next()?, fields.next()?, fields.next()?))
+		})
+		.find(|(prefix, vcs, _)| *vcs == "git" && module.starts_with(prefix))
+		.map(|(prefix, _, url)| (prefix, url))
+}
+
+/// Get the Git ref a Go module version was made from.
+///
+/// Pseudo-versions end with the commit they were made from; other versions
+/// are tags, prefixed with the module's subdirectory in the repo.
+fn go_version_ref(version: &str, subdir: &str) -> String {
+	let version = version.strip_suffix("+incompatible").unwrap_or(version);
+
+	if let Some((rest, hash)) = version.rsplit_once('-') {
+		let is_pseudo = hash.len() == 12
+			&& hash.chars().all(|c| c.is_ascii_hexdigit())
+			&& rest.len() >= 14
+			&& rest.as_bytes()[rest.len() - 14..]
+				.iter()
+				.all(u8::is_ascii_digit);
+		if is_pseudo {
+			return hash.to_owned();
+		}
+	}
+
+	match subdir {
+		"" => version.to_owned(),
+		subdir => format!("{}/{}", subdir, version),
+	}
+}
+
+/// Check if a path element is a Go major version suffix, like `v2`.
+fn is_go_major_suffix(element: &str) -> bool {
+	element
+		.strip_prefix('v')
+		.and_then(|major| major.parse::<u64>().ok())
+		.is_some_and(|major| major >= 2)
+}
+
+/// Escape a module path or version for the Go module proxy, which marks
+/// uppercase letters with a `!` for case-insensitive file systems.
+fn escape_go_path(path: &str) -> String {
+	let mut escaped = String::with_capacity(path.len());
+	for c in path.chars() {
+		if c.is_ascii_uppercase() {
+			escaped.push('!');
+			escaped.push(c.to_ascii_lowercase());
+		} else {
+			escaped.push(c);
+		}
+	}
+	escaped
+}
+
+/// Parse a Cargo crate, given as `crate[@version]`.
+pub fn parse_cargo_crate(raw_crate: &str) -> Result<(String, Option<String>)> {
+	let (name, version) = match raw_crate.split_once('@') {
+		Some((name, version)) => (name, Some(version.to_owned())),
+		None => (raw_crate, None),
+	};
+
+	if name.is_empty() || version.as_deref() == Some("") {
+		return Err(hc_error!(
+			"'{}' is not a crate, which is given as <crate>[@<version>]",
+			raw_crate
+		));
+	}
+
+	Ok((name.to_owned(), version))
+}
+
+/// Function to extract the repo URL for a crate on crates.io given its name and
+/// version, along with the version, which is the newest one if none is given
+pub fn extract_repo_for_crate(name: &str, version: Option<&str>) -> Result<(Url, String)> {
+	// Construct the registry URL.
+	let registry = format!("https://crates.io/api/v1/crates/{}", name);
+
+	// Make an HTTP request to that URL. crates.io turns away requests which
+	// don't say who's making them.
+	require_network("looking up the repository of a crate")?;
+	let response = agent::agent()
+		.get(&registry)
+		.set(
+			"User-Agent",
+			concat!("hipcheck/", env!("CARGO_PKG_VERSION"), " (https://hipcheck.mitre.org)"),
+		)
+		.call()
+		.context("request to crates.io API failed, make sure the crate name is correct")?;
+
+	// Parse the response as JSON.
+	let json: Value = {
+		let intermediate = response
+			.into_string()
+			.context("can't parse crates.io API response")?;
+		serde_json::from_str(&intermediate).context("crates.io API response isn't valid JSON")?
+	};
+
+	let version = match version {
+		Some(version) => {
+			let published = json
+				.get("versions")
+				.and_then(Value::as_array)
+				.into_iter()
+				.flatten()
+				.filter_map(|v| v.get("num").and_then(Value::as_str))
+				.any(|num| num == version);
+			if !published {
+				return Err(hc_error!(
+					"crate '{}' has no version {} on crates.io",
+					name,
+					version
+				));
+			}
+			version.to_owned()
+		}
+		None => {
+			let krate = json.get("crate");
+			krate
+				.and_then(|k| k.get("max_stable_version"))
+				.and_then(Value::as_str)
+				.or_else(|| krate.and_then(|k| k.get("newest_version")?.as_str()))
+				.ok_or_else(|| hc_error!("no version field for crate"))?
+				.to_owned()
+		}
+	};
+
+	let raw_repository = json
+		.get("crate")
+		.and_then(|k| k.get("repository"))
+		.and_then(Value::as_str)
+		.ok_or_else(|| hc_error!("crate '{}' doesn't list its repository", name))?;
+	let repository = sanitize_url(raw_repository.to_owned())?;
+
+	// Repositories are sometimes given as a path within them, like the
+	// directory of the crate in a workspace
+	let repository = if let Some("github.com" | "gitlab.com") = repository.host_str() {
+		pop_url_segments(repository)?
+	} else {
+		repository
+	};
+
+	Ok((repository, version))
+}
+
+/// Find the tag a crate version was released from in its repo, if it has one
+/// named in one of the usual ways.
+pub fn find_crate_tag(repository: &Url, name: &str, version: &str) -> Result<Option<String>> {
+	require_network("listing the tags of a crate's repository")?;
+	let output = GitCommand::new_repo(["ls-remote", "--tags", "--refs", repository.as_str()])?
+		.output()
+		.context("Git failed to list the tags of the crate's repository")?;
+
+	let tags: Vec<&str> = output
+		.lines()
+		.filter_map(|line| line.split_once("refs/tags/"))
+		.map(|(_, tag)| tag.trim())
+		.collect();
+
+	Ok(match_crate_tag(&tags, name, version).map(ToOwned::to_owned))
+}
+
+/// Pick the tag for a crate version out of a repo's tags.
+///
+/// Workspaces often tag each crate separately, so tags naming the crate are
+/// preferred over bare versions, which could belong to another crate.
+fn match_crate_tag<'tag>(tags: &[&'tag str], name: &str, version: &str) -> Option<&'tag str> {
+	let candidates = [
+		format!("{}-v{}", name, version),
+		format!("{}-{}", name, version),
+		format!("{}/v{}", name, version),
+		format!("{}@{}", name, version),
+		format!("v{}", version),
+		version.to_owned(),
+	];
+
+	candidates
+		.iter()
+		.find_map(|candidate| tags.iter().find(|tag| **tag == candidate.as_str()))
+		.copied()
+}
+
 /// Remove unnecessary path segments from tail end of repository URL.
 fn pop_url_segments(mut repo: Url) -> Result<Url> {
 	let times_to_pop = max(
@@ -543,11 +886,6 @@ fn is_none_or_empty(s: Option<&str>) -> bool {
 
 #[cfg(test)]
 mod tests {
-	use crate::{
-		cli::{CheckNpmArgs, CheckPypiArgs},
-		target::{TargetSeedKind, ToTargetSeedKind},
-	};
-
 	// Note this useful idiom: importing names from outer (for mod tests) scope.
 	use super::*;
 	use serde_json::json;
@@ -558,27 +896,19 @@ mod tests {
 		let link = "https://pypi.org/project/certifi/2021.5.30";
 		let link2 = "https://github.com/certifi/python-certifi";
 
-		let target_seed = CheckPypiArgs {
-			package: link.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:pypi/certifi@2021.5.30").unwrap(),
-					name: "certifi".to_string(),
-					version: "2021.5.30".to_string(),
-					host: PackageHost::PyPI
-				}
-			);
+		let package = parse_pypi_package(link).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:pypi/certifi@2021.5.30").unwrap(),
+				name: "certifi".to_string(),
+				version: "2021.5.30".to_string(),
+				host: PackageHost::PyPI
+			}
+		);
 
-			let pypi_git = Url::parse(link2).unwrap();
-			assert_eq!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
-		} else {
-			panic!()
-		}
+		let pypi_git = Url::parse(link2).unwrap();
+		assert_eq!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
 	}
 
 	#[test]
@@ -586,27 +916,19 @@ mod tests {
 		let link = "https://pypi.org/project/urllib3/1.26.6";
 		let link2 = "https://github.com/urllib3/urllib3";
 
-		let target_seed = CheckPypiArgs {
-			package: link.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:pypi/urllib3@1.26.6").unwrap(),
-					name: "urllib3".to_string(),
-					version: "1.26.6".to_string(),
-					host: PackageHost::PyPI
-				}
-			);
+		let package = parse_pypi_package(link).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:pypi/urllib3@1.26.6").unwrap(),
+				name: "urllib3".to_string(),
+				version: "1.26.6".to_string(),
+				host: PackageHost::PyPI
+			}
+		);
 
-			let pypi_git = Url::parse(link2).unwrap();
-			assert_eq!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
-		} else {
-			panic!()
-		}
+		let pypi_git = Url::parse(link2).unwrap();
+		assert_eq!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
 	}
 
 	#[test]
@@ -614,27 +936,19 @@ mod tests {
 		let link = "https://pypi.org/project/Flask/2.1.1";
 		let link2 = "https://github.com/pallets/flask";
 
-		let target_seed = CheckPypiArgs {
-			package: link.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:pypi/Flask@2.1.1").unwrap(),
-					name: "Flask".to_string(),
-					version: "2.1.1".to_string(),
-					host: PackageHost::PyPI
-				}
-			);
+		let package = parse_pypi_package(link).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:pypi/Flask@2.1.1").unwrap(),
+				name: "Flask".to_string(),
+				version: "2.1.1".to_string(),
+				host: PackageHost::PyPI
+			}
+		);
 
-			let pypi_git = Url::parse(link2).unwrap();
-			assert_eq!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
-		} else {
-			panic!()
-		}
+		let pypi_git = Url::parse(link2).unwrap();
+		assert_eq!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
 	}
 
 	#[test]
@@ -642,27 +956,19 @@ mod tests {
 		let link = "Flask@2.1.1";
 		let link2 = "https://github.com/pallets/flask";
 
-		let target_seed = CheckPypiArgs {
-			package: link.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:pypi/Flask@2.1.1").unwrap(),
-					name: "Flask".to_string(),
-					version: "2.1.1".to_string(),
-					host: PackageHost::PyPI
-				}
-			);
+		let package = parse_pypi_package(link).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:pypi/Flask@2.1.1").unwrap(),
+				name: "Flask".to_string(),
+				version: "2.1.1".to_string(),
+				host: PackageHost::PyPI
+			}
+		);
 
-			let pypi_git = Url::parse(link2).unwrap();
-			assert_eq!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
-		} else {
-			panic!()
-		}
+		let pypi_git = Url::parse(link2).unwrap();
+		assert_eq!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
 	}
 
 	#[test]
@@ -670,27 +976,19 @@ mod tests {
 		let link = "Flask";
 		let link2 = "https://github.com/pallets/flask";
 
-		let target_seed = CheckPypiArgs {
-			package: link.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:pypi/Flask").unwrap(),
-					name: "Flask".to_string(),
-					version: "no version".to_string(),
-					host: PackageHost::PyPI
-				}
-			);
+		let package = parse_pypi_package(link).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:pypi/Flask").unwrap(),
+				name: "Flask".to_string(),
+				version: "no version".to_string(),
+				host: PackageHost::PyPI
+			}
+		);
 
-			let pypi_git = Url::parse(link2).unwrap();
-			assert_eq!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
-		} else {
-			panic!()
-		}
+		let pypi_git = Url::parse(link2).unwrap();
+		assert_eq!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
 	}
 
 	#[test]
@@ -698,27 +996,19 @@ mod tests {
 		let link = "flask";
 		let link2 = "https://github.com/pallets/flask";
 
-		let target_seed = CheckPypiArgs {
-			package: link.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:pypi/flask").unwrap(),
-					name: "flask".to_string(),
-					version: "no version".to_string(),
-					host: PackageHost::PyPI
-				}
-			);
+		let package = parse_pypi_package(link).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:pypi/flask").unwrap(),
+				name: "flask".to_string(),
+				version: "no version".to_string(),
+				host: PackageHost::PyPI
+			}
+		);
 
-			let pypi_git = Url::parse(link2).unwrap();
-			assert_eq!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
-		} else {
-			panic!()
-		}
+		let pypi_git = Url::parse(link2).unwrap();
+		assert_eq!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
 	}
 
 	#[test]
@@ -727,31 +1017,23 @@ mod tests {
 		let link = "Flaskx";
 		let link2 = "https://github.com/pallets/flask";
 
-		let target_seed = CheckPypiArgs {
-			package: link.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:pypi/Flaskx").unwrap(),
-					name: "Flaskx".to_string(),
-					version: "no version".to_string(),
-					host: PackageHost::PyPI
-				}
-			);
-
-			let pypi_git = Url::parse(link2).unwrap();
-			println!(
-				"{}",
-				extract_repo_for_pypi_package(&package).unwrap().as_str()
-			);
-			assert_ne!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
-		} else {
-			panic!()
-		}
+		let package = parse_pypi_package(link).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:pypi/Flaskx").unwrap(),
+				name: "Flaskx".to_string(),
+				version: "no version".to_string(),
+				host: PackageHost::PyPI
+			}
+		);
+
+		let pypi_git = Url::parse(link2).unwrap();
+		println!(
+			"{}",
+			extract_repo_for_pypi_package(&package).unwrap().as_str()
+		);
+		assert_ne!(extract_repo_for_pypi_package(&package).unwrap(), pypi_git);
 	}
 
 	#[test]
@@ -759,27 +1041,19 @@ mod tests {
 		let link = "https://registry.npmjs.org/lodash/";
 		let link2 = "https://github.com/lodash/lodash.git";
 
-		let target_seed = CheckNpmArgs {
-			package: link.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:npm/lodash").unwrap(),
-					name: "lodash".to_string(),
-					version: "no version".to_string(),
-					host: PackageHost::Npm
-				}
-			);
+		let package = parse_npm_package(link).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:npm/lodash").unwrap(),
+				name: "lodash".to_string(),
+				version: "no version".to_string(),
+				host: PackageHost::Npm
+			}
+		);
 
-			let npm_git = Url::parse(link2).unwrap();
-			assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
-		} else {
-			panic!()
-		}
+		let npm_git = Url::parse(link2).unwrap();
+		assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
 	}
 
 	#[test]
@@ -787,27 +1061,19 @@ mod tests {
 		let link = "https://registry.npmjs.org/chalk/";
 		let link2 = "https://github.com/chalk/chalk.git";
 
-		let target_seed = CheckNpmArgs {
-			package: link.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:npm/chalk").unwrap(),
-					name: "chalk".to_string(),
-					version: "no version".to_string(),
-					host: PackageHost::Npm
-				}
-			);
+		let package = parse_npm_package(link).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:npm/chalk").unwrap(),
+				name: "chalk".to_string(),
+				version: "no version".to_string(),
+				host: PackageHost::Npm
+			}
+		);
 
-			let npm_git = Url::parse(link2).unwrap();
-			assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
-		} else {
-			panic!()
-		}
+		let npm_git = Url::parse(link2).unwrap();
+		assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
 	}
 
 	#[test]
@@ -815,27 +1081,19 @@ mod tests {
 		let link = "https://registry.npmjs.org/node-ipc/9.2.1";
 		let link2 = "https://github.com/RIAEvangelist/node-ipc.git";
 
-		let target_seed = CheckNpmArgs {
-			package: link.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:npm/node-ipc@9.2.1").unwrap(),
-					name: "node-ipc".to_string(),
-					version: "9.2.1".to_string(),
-					host: PackageHost::Npm
-				}
-			);
+		let package = parse_npm_package(link).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:npm/node-ipc@9.2.1").unwrap(),
+				name: "node-ipc".to_string(),
+				version: "9.2.1".to_string(),
+				host: PackageHost::Npm
+			}
+		);
 
-			let npm_git = Url::parse(link2).unwrap();
-			assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
-		} else {
-			panic!()
-		}
+		let npm_git = Url::parse(link2).unwrap();
+		assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
 	}
 
 	#[test]
@@ -843,27 +1101,19 @@ mod tests {
 		let npm_package = "node-ipc@9.2.1";
 		let link2 = "https://github.com/RIAEvangelist/node-ipc.git";
 
-		let target_seed = CheckNpmArgs {
-			package: npm_package.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:npm/node-ipc@9.2.1").unwrap(),
-					name: "node-ipc".to_string(),
-					version: "9.2.1".to_string(),
-					host: PackageHost::Npm
-				}
-			);
+		let package = parse_npm_package(npm_package).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:npm/node-ipc@9.2.1").unwrap(),
+				name: "node-ipc".to_string(),
+				version: "9.2.1".to_string(),
+				host: PackageHost::Npm
+			}
+		);
 
-			let npm_git = Url::parse(link2).unwrap();
-			assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
-		} else {
-			panic!()
-		}
+		let npm_git = Url::parse(link2).unwrap();
+		assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
 	}
 
 	#[test]
@@ -871,27 +1121,19 @@ mod tests {
 		let npm_package = "node-ipc";
 		let link2 = "https://github.com/RIAEvangelist/node-ipc.git";
 
-		let target_seed = CheckNpmArgs {
-			package: npm_package.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:npm/node-ipc").unwrap(),
-					name: "node-ipc".to_string(),
-					version: "no version".to_string(),
-					host: PackageHost::Npm
-				}
-			);
+		let package = parse_npm_package(npm_package).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:npm/node-ipc").unwrap(),
+				name: "node-ipc".to_string(),
+				version: "no version".to_string(),
+				host: PackageHost::Npm
+			}
+		);
 
-			let npm_git = Url::parse(link2).unwrap();
-			assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
-		} else {
-			panic!()
-		}
+		let npm_git = Url::parse(link2).unwrap();
+		assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
 	}
 
 	#[test]
@@ -899,27 +1141,19 @@ mod tests {
 		let npm_package = "@types/ua-parser-js@0.7.36";
 		let link2 = "https://github.com/DefinitelyTyped/DefinitelyTyped.git";
 
-		let target_seed = CheckNpmArgs {
-			package: npm_package.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:npm/%40types/ua-parser-js@0.7.36").unwrap(),
-					name: "@types/ua-parser-js".to_string(),
-					version: "0.7.36".to_string(),
-					host: PackageHost::Npm
-				}
-			);
+		let package = parse_npm_package(npm_package).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:npm/%40types/ua-parser-js@0.7.36").unwrap(),
+				name: "@types/ua-parser-js".to_string(),
+				version: "0.7.36".to_string(),
+				host: PackageHost::Npm
+			}
+		);
 
-			let npm_git = Url::parse(link2).unwrap();
-			assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
-		} else {
-			panic!()
-		}
+		let npm_git = Url::parse(link2).unwrap();
+		assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
 	}
 
 	#[test]
@@ -927,27 +1161,19 @@ mod tests {
 		let npm_package = "https://registry.npmjs.org/@types/ua-parser-js/0.7.36";
 		let link2 = "https://github.com/DefinitelyTyped/DefinitelyTyped.git";
 
-		let target_seed = CheckNpmArgs {
-			package: npm_package.to_string(),
-		}
-		.to_target_seed_kind()
-		.unwrap();
-		if let TargetSeedKind::Package(package) = target_seed {
-			assert_eq!(
-				package,
-				Package {
-					purl: Url::parse("pkg:npm/%40types/ua-parser-js@0.7.36").unwrap(),
-					name: "@types/ua-parser-js".to_string(),
-					version: "0.7.36".to_string(),
-					host: PackageHost::Npm
-				}
-			);
+		let package = parse_npm_package(npm_package).unwrap();
+		assert_eq!(
+			package,
+			Package {
+				purl: Url::parse("pkg:npm/%40types/ua-parser-js@0.7.36").unwrap(),
+				name: "@types/ua-parser-js".to_string(),
+				version: "0.7.36".to_string(),
+				host: PackageHost::Npm
+			}
+		);
 
-			let npm_git = Url::parse(link2).unwrap();
-			assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
-		} else {
-			panic!()
-		}
+		let npm_git = Url::parse(link2).unwrap();
+		assert_eq!(extract_repo_for_npm_package(&package).unwrap(), npm_git);
 	}
 
 	#[test]
@@ -1088,4 +1314,79 @@ mod tests {
 		];
 		assert_eq!(actual, expected);
 	}
+
+	#[test]
+	fn test_escape_go_path() {
+		assert_eq!(
+			escape_go_path("github.com/BurntSushi/toml"),
+			"github.com/!burnt!sushi/toml"
+		);
+		assert_eq!(escape_go_path("golang.org/x/net"), "golang.org/x/net");
+	}
+
+	#[test]
+	fn test_go_version_ref() {
+		assert_eq!(go_version_ref("v1.2.3", ""), "v1.2.3");
+		assert_eq!(go_version_ref("v1.2.3", "service/s3"), "service/s3/v1.2.3");
+		assert_eq!(go_version_ref("v2.0.0+incompatible", ""), "v2.0.0");
+		assert_eq!(
+			go_version_ref("v0.0.0-20240102150405-abcdef123456", ""),
+			"abcdef123456"
+		);
+		assert_eq!(
+			go_version_ref("v1.2.4-0.20240102150405-abcdef123456", "sub"),
+			"abcdef123456"
+		);
+	}
+
+	#[test]
+	fn test_find_go_import() {
+		let html = r#"
+			<html><head>
+			<meta name="go-import" content="golang.org/x/net git https://go.googlesource.com/net">
+			<meta name="go-source" content="golang.org/x/net https://github.com/golang/net/ https://github.com/golang/net/tree/master{/dir} https://github.com/golang/net/blob/master{/dir}/{file}#L{line}">
+			</head></html>
+		"#;
+		assert_eq!(
+			find_go_import(html, "golang.org/x/net/html"),
+			Some(("golang.org/x/net", "https://go.googlesource.com/net"))
+		);
+		assert_eq!(find_go_import(html, "golang.org/x/text"), None);
+	}
+
+	#[test]
+	fn test_parse_go_module() {
+		assert_eq!(
+			parse_go_module("golang.org/x/net@v0.30.0").unwrap(),
+			("golang.org/x/net".to_owned(), Some("v0.30.0".to_owned()))
+		);
+		assert_eq!(
+			parse_go_module("golang.org/x/net").unwrap(),
+			("golang.org/x/net".to_owned(), None)
+		);
+		assert!(parse_go_module("golang.org/x/net@").is_err());
+	}
+
+	#[test]
+	fn test_parse_cargo_crate() {
+		assert_eq!(
+			parse_cargo_crate("serde@1.0.210").unwrap(),
+			("serde".to_owned(), Some("1.0.210".to_owned()))
+		);
+		assert_eq!(parse_cargo_crate("serde").unwrap(), ("serde".to_owned(), None));
+		assert!(parse_cargo_crate("@1.0.210").is_err());
+	}
+
+	#[test]
+	fn test_match_crate_tag() {
+		let tags = ["v1.0.0", "1.0.1", "tokio-macros-v2.4.0", "tokio-1.40.0", "v1.40.0"];
+		assert_eq!(match_crate_tag(&tags, "foo", "1.0.0"), Some("v1.0.0"));
+		assert_eq!(match_crate_tag(&tags, "foo", "1.0.1"), Some("1.0.1"));
+		assert_eq!(
+			match_crate_tag(&tags, "tokio-macros", "2.4.0"),
+			Some("tokio-macros-v2.4.0")
+		);
+		assert_eq!(match_crate_tag(&tags, "tokio", "1.40.0"), Some("tokio-1.40.0"));
+		assert_eq!(match_crate_tag(&tags, "foo", "2.0.0"), None);
+	}
 }
@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Resolving targets through target resolver plugins.
+//!
+//! Hipcheck core only knows how to resolve packages from a handful of
+//! ecosystems. For anything else, the user can name a plugin with the
+//! `--resolver` flag, and core will call that plugin's `resolve` query with
+//! the package specifier to find the source repository to analyze.
+
+use crate::{
+	engine::HcEngine,
+	error::{Context as _, Result},
+	hc_error,
+	session::change_request::ChangeRequest,
+	source,
+	target::{TargetSeed, TargetSeedKind},
+};
+use hipcheck_common::QueryTarget;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use url::Url;
+
+/// The name of the query endpoint core calls on a target resolver plugin.
+const RESOLVE_QUERY: &str = "resolve";
+
+/// What the user asked Hipcheck to analyze.
+#[derive(Debug, Clone)]
+pub enum TargetRequest {
+	/// A target core knows how to resolve itself.
+	Seed(TargetSeed),
+	/// A target to be resolved by a plugin.
+	Plugin(PluginTargetSeed),
+	/// A pull or merge request, analyzed as the commits it contributes.
+	ChangeRequest(ChangeRequest),
+}
+
+impl fmt::Display for TargetRequest {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			TargetRequest::Seed(seed) => seed.fmt(f),
+			TargetRequest::Plugin(seed) => seed.fmt(f),
+			TargetRequest::ChangeRequest(request) => request.fmt(f),
+		}
+	}
+}
+
+/// A package specifier to be resolved into a target by a plugin.
+#[derive(Debug, Clone)]
+pub struct PluginTargetSeed {
+	/// The resolver plugin, as `<publisher>/<name>`.
+	pub resolver: String,
+	/// The package specifier, passed to the plugin as-is.
+	pub specifier: String,
+	/// The ref the user asked to analyze, if any.
+	pub refspec: Option<String>,
+}
+
+impl fmt::Display for PluginTargetSeed {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} (via {})", self.specifier, self.resolver)
+	}
+}
+
+/// The key passed to a resolver plugin's `resolve` query.
+#[derive(Debug, Serialize)]
+struct ResolverKey<'s> {
+	specifier: &'s str,
+	refspec: Option<&'s str>,
+}
+
+/// The output of a resolver plugin's `resolve` query.
+#[derive(Debug, Deserialize)]
+struct ResolvedTarget {
+	url: Url,
+	#[serde(rename = "ref")]
+	refspec: Option<String>,
+}
+
+impl PluginTargetSeed {
+	/// Ask the resolver plugin for the repository to analyze.
+	///
+	/// The plugin must already be running, which means it must be listed in
+	/// the `plugins` section of the policy file.
+	pub fn resolve(&self, db: &dyn HcEngine) -> Result<TargetSeed> {
+		let resolver = QueryTarget::from_str(&self.resolver)
+			.ok()
+			.filter(|t| t.query.is_none())
+			.ok_or_else(|| {
+				hc_error!(
+					"invalid resolver '{}'; expected '<publisher>/<name>'",
+					self.resolver
+				)
+			})?;
+
+		let key = serde_json::to_value(ResolverKey {
+			specifier: &self.specifier,
+			refspec: self.refspec.as_deref(),
+		})?;
+
+		let mut result = db
+			.query(
+				resolver.publisher,
+				resolver.plugin,
+				RESOLVE_QUERY.to_owned(),
+				key,
+			)
+			.with_context(|| {
+				format!(
+					"failed to resolve '{}' with resolver plugin '{}'; make sure the plugin is listed in the policy file",
+					self.specifier, self.resolver
+				)
+			})?;
+
+		let value = result.value.pop().ok_or_else(|| {
+			hc_error!("resolver plugin '{}' returned no target", self.resolver)
+		})?;
+
+		let resolved: ResolvedTarget = serde_json::from_value(value).map_err(|e| {
+			hc_error!(
+				"resolver plugin '{}' returned a malformed target: {}",
+				self.resolver,
+				e
+			)
+		})?;
+
+		tracing::debug!(
+			"resolver plugin '{}' resolved '{}' to {}",
+			self.resolver,
+			self.specifier,
+			resolved.url
+		);
+
+		let remote_repo = source::get_remote_repo_from_url(resolved.url)?;
+
+		Ok(TargetSeed {
+			kind: TargetSeedKind::RemoteRepo(remote_repo),
+			// A ref given on the command line wins over the one the plugin found.
+			refspec: self.refspec.clone().or(resolved.refspec),
+			specifier: self.specifier.clone(),
+		})
+	}
+}
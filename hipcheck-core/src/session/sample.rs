@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Analyzing a random sample of the components of a large SBOM.
+//!
+//! Analyzing every component of a large SBOM can take days. For a first
+//! survey of a portfolio's risk, a sample of the components is analyzed
+//! instead. The sample is stratified by ecosystem, so each ecosystem is
+//! represented in proportion to its share of the components, and the results
+//! are used to estimate the risk of the whole portfolio.
+
+use crate::{
+	error::Result,
+	hc_error,
+	session::{resolver::TargetRequest, sbom::ComponentTarget},
+};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use std::{collections::BTreeMap, str::FromStr};
+
+/// The ecosystem of components whose ecosystem isn't known.
+pub const UNKNOWN_ECOSYSTEM: &str = "unknown";
+
+/// How many targets to sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleSize {
+	/// A number of targets.
+	Count(usize),
+	/// A percentage of the targets.
+	Percent(f64),
+}
+
+impl SampleSize {
+	/// Get how many of a number of targets to sample, which is at least one.
+	fn of(&self, population: usize) -> usize {
+		let size = match self {
+			SampleSize::Count(count) => *count,
+			SampleSize::Percent(percent) => (population as f64 * percent / 100.0).ceil() as usize,
+		};
+		size.clamp(1, population.max(1))
+	}
+}
+
+impl FromStr for SampleSize {
+	type Err = crate::Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		let s = s.trim();
+
+		if let Some(percent) = s.strip_suffix('%') {
+			let percent: f64 = percent
+				.trim()
+				.parse()
+				.map_err(|_| hc_error!("invalid sample size '{}'", s))?;
+			if !(percent > 0.0 && percent <= 100.0) {
+				return Err(hc_error!(
+					"sample percentage must be more than 0% and at most 100%, not '{}'",
+					s
+				));
+			}
+			return Ok(SampleSize::Percent(percent));
+		}
+
+		match s.parse() {
+			Ok(0) | Err(_) => Err(hc_error!(
+				"invalid sample size '{}', expected a number of targets or a percentage like '10%'",
+				s
+			)),
+			Ok(count) => Ok(SampleSize::Count(count)),
+		}
+	}
+}
+
+/// The targets from one ecosystem, and which of them were sampled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stratum {
+	/// The ecosystem, like `npm` or `maven`.
+	pub ecosystem: String,
+	/// How many targets are from the ecosystem.
+	pub population: usize,
+	/// The names of the sampled targets.
+	pub sampled: Vec<String>,
+}
+
+/// A random sample of targets, stratified by ecosystem.
+#[derive(Debug, Clone)]
+pub struct Sample {
+	/// The targets from each ecosystem, in order of the ecosystems' names.
+	pub strata: Vec<Stratum>,
+	/// The seed the sample was drawn with, so the same sample can be drawn again.
+	pub seed: u64,
+}
+
+impl Sample {
+	/// Draw a sample of targets, using the seed if one is given.
+	///
+	/// Returns the sampled targets in their original order. Every ecosystem
+	/// gets at least one target, even if that makes the sample larger than
+	/// asked for, and the rest of the sample is shared out in proportion to
+	/// the number of targets from each.
+	pub fn draw(
+		targets: Vec<ComponentTarget>,
+		size: SampleSize,
+		seed: Option<u64>,
+	) -> (Sample, Vec<TargetRequest>) {
+		let seed = seed.unwrap_or_else(rand::random);
+		let mut rng = StdRng::seed_from_u64(seed);
+
+		let mut strata: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+		for (index, target) in targets.iter().enumerate() {
+			let ecosystem = target.ecosystem.as_deref().unwrap_or(UNKNOWN_ECOSYSTEM);
+			strata.entry(ecosystem.to_owned()).or_default().push(index);
+		}
+
+		let populations = strata.values().map(Vec::len).collect::<Vec<_>>();
+		let allocation = allocate(size.of(targets.len()), &populations);
+
+		let mut chosen = vec![false; targets.len()];
+		for (indices, count) in strata.values().zip(allocation) {
+			for index in indices.choose_multiple(&mut rng, count) {
+				chosen[*index] = true;
+			}
+		}
+
+		let strata = strata
+			.into_iter()
+			.map(|(ecosystem, indices)| Stratum {
+				population: indices.len(),
+				sampled: indices
+					.into_iter()
+					.filter(|index| chosen[*index])
+					.map(|index| targets[index].request.to_string())
+					.collect(),
+				ecosystem,
+			})
+			.collect::<Vec<_>>();
+
+		let sampled = targets
+			.into_iter()
+			.zip(chosen)
+			.filter_map(|(target, chosen)| chosen.then_some(target.request))
+			.collect::<Vec<_>>();
+
+		tracing::info!(
+			"sampled {} targets from {} ecosystems with seed {}",
+			sampled.len(),
+			strata.len(),
+			seed
+		);
+
+		(Sample { strata, seed }, sampled)
+	}
+}
+
+/// Share a sample of a number of targets among strata with the given
+/// populations, in proportion to their populations and with at least one
+/// target from each.
+fn allocate(size: usize, populations: &[usize]) -> Vec<usize> {
+	let total: usize = populations.iter().sum();
+	let size = size.max(populations.len()).min(total);
+
+	let quotas = populations
+		.iter()
+		.map(|population| size as f64 * *population as f64 / total as f64)
+		.collect::<Vec<_>>();
+	let mut allocation = quotas
+		.iter()
+		.zip(populations)
+		.map(|(quota, population)| (quota.floor() as usize).clamp(1, *population))
+		.collect::<Vec<_>>();
+	let remainder = |allocation: &[usize], i: usize| quotas[i] - allocation[i] as f64;
+
+	// Settle any rounding by the largest remainders.
+	loop {
+		let allocated: usize = allocation.iter().sum();
+
+		let next = if allocated < size {
+			(0..populations.len())
+				.filter(|i| allocation[*i] < populations[*i])
+				.max_by(|a, b| remainder(&allocation, *a).total_cmp(&remainder(&allocation, *b)))
+		} else if allocated > size {
+			(0..populations.len())
+				.filter(|i| allocation[*i] > 1)
+				.min_by(|a, b| remainder(&allocation, *a).total_cmp(&remainder(&allocation, *b)))
+		} else {
+			None
+		};
+
+		match next {
+			Some(i) if allocated < size => allocation[i] += 1,
+			Some(i) => allocation[i] -= 1,
+			None => break,
+		}
+	}
+
+	allocation
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_sample_sizes() {
+		assert_eq!(SampleSize::from_str("50").unwrap(), SampleSize::Count(50));
+		assert_eq!(
+			SampleSize::from_str("12.5%").unwrap(),
+			SampleSize::Percent(12.5)
+		);
+		assert!(SampleSize::from_str("0").is_err());
+		assert!(SampleSize::from_str("0%").is_err());
+		assert!(SampleSize::from_str("150%").is_err());
+		assert!(SampleSize::from_str("many").is_err());
+	}
+
+	#[test]
+	fn sample_sizes_are_bounded() {
+		assert_eq!(SampleSize::Percent(10.0).of(1001), 101);
+		assert_eq!(SampleSize::Percent(0.1).of(20), 1);
+		assert_eq!(SampleSize::Count(50).of(20), 20);
+	}
+
+	#[test]
+	fn allocation_is_proportional() {
+		assert_eq!(allocate(10, &[60, 30, 10]), vec![6, 3, 1]);
+		assert_eq!(allocate(10, &[90, 5, 5]), vec![8, 1, 1]);
+		assert_eq!(allocate(2, &[90, 5, 5]), vec![1, 1, 1]);
+		assert_eq!(allocate(50, &[3, 4]), vec![3, 4]);
+	}
+}
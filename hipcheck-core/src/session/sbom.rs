@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Expanding an SBOM into one target per component.
+//!
+//! Analyzing an SBOM on its own only looks at the package the SBOM describes.
+//! To analyze the whole supply chain it records, each component with a
+//! resolvable VCS or package URL becomes its own target, and each is analyzed
+//! in its own session.
+
+use crate::{
+	error::{Context as _, Result},
+	hc_error,
+	session::{
+		cyclone_dx::extract_cyclonedx_components, resolver::TargetRequest,
+		spdx::extract_spdx_download_urls,
+	},
+	source,
+	target::{Sbom, SbomStandard, TargetSeed, TargetSeedKind},
+};
+use url::Url;
+
+/// A component of an SBOM with a resolvable repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SbomComponent {
+	/// The URL of the component's repository.
+	pub url: Url,
+	/// The ecosystem the component is from, like `npm` or `maven`, if known.
+	pub ecosystem: Option<String>,
+}
+
+/// A target to analyze, along with the ecosystem of the SBOM component it
+/// was expanded from, if any.
+#[derive(Debug, Clone)]
+pub struct ComponentTarget {
+	pub request: TargetRequest,
+	pub ecosystem: Option<String>,
+}
+
+/// Expand a request to analyze an SBOM into a request for each component.
+///
+/// Any other request, or an SBOM with only one resolvable component, is
+/// returned as the only request, so it's analyzed the same way as before.
+pub fn expand_target_request(request: TargetRequest) -> Result<Vec<ComponentTarget>> {
+	let TargetRequest::Seed(TargetSeed {
+		kind: TargetSeedKind::Sbom(sbom),
+		..
+	}) = &request
+	else {
+		return Ok(vec![ComponentTarget {
+			request,
+			ecosystem: None,
+		}]);
+	};
+
+	let seeds = component_seeds(sbom)?;
+
+	if seeds.len() <= 1 {
+		return Ok(vec![ComponentTarget {
+			request,
+			ecosystem: None,
+		}]);
+	}
+
+	tracing::info!(
+		"expanded SBOM '{}' into {} components",
+		sbom.path.display(),
+		seeds.len()
+	);
+
+	Ok(seeds
+		.into_iter()
+		.map(|(seed, ecosystem)| ComponentTarget {
+			request: TargetRequest::Seed(seed),
+			ecosystem,
+		})
+		.collect())
+}
+
+/// Get a target for each component of an SBOM with a resolvable repository,
+/// along with the component's ecosystem.
+fn component_seeds(sbom: &Sbom) -> Result<Vec<(TargetSeed, Option<String>)>> {
+	let path = sbom
+		.path
+		.to_str()
+		.ok_or_else(|| hc_error!("SBOM path isn't UTF-8 encoded '{}'", sbom.path.display()))?;
+
+	// SPDX download locations don't say what ecosystem a package is from
+	let components = match sbom.standard {
+		SbomStandard::Spdx => extract_spdx_download_urls(path)?
+			.into_iter()
+			.filter_map(|url| Url::parse(&url).ok())
+			.map(|url| SbomComponent {
+				url,
+				ecosystem: None,
+			})
+			.collect(),
+		SbomStandard::CycloneDX => extract_cyclonedx_components(path)?,
+	};
+
+	components
+		.into_iter()
+		.map(|component| {
+			let specifier = component.url.to_string();
+			let remote_repo = source::get_remote_repo_from_url(component.url)
+				.with_context(|| format!("invalid SBOM component '{}'", specifier))?;
+
+			let seed = TargetSeed {
+				kind: TargetSeedKind::RemoteRepo(remote_repo),
+				refspec: None,
+				specifier,
+			};
+			Ok((seed, component.ecosystem))
+		})
+		.collect()
+}
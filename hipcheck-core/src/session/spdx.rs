@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Utilities for extracting repository info from SPDX documents.
+
+use crate::{
+	error::{Context as _, Result},
+	hc_error,
+};
+use spdx_rs::models::SPDX;
+use std::ops::Not as _;
+use url::Url;
+
+// The package download location field tag
+const DLOAD_LOCN_TAG: &str = "PackageDownloadLocation";
+
+// Tag-value delimiter
+const DELIMITER: char = ':';
+
+// Indicates that a download location does not exist
+const DLOAD_NONE: &str = "NONE";
+
+// Indicates that a download location may exist but is not present
+const DLOAD_NOASSERT: &str = "NOASSERTION";
+
+// Compatible download schemes
+const SCM_GIT: &str = "git";
+const SCM_GIT_PLUS: &str = "git+";
+const SCM_HTTP: &str = "http";
+const SCM_HTTPS: &str = "https";
+
+/// Extract the first compatible package download location from an
+/// SPDX document
+pub fn extract_spdx_download_url(filepath: &str) -> Result<String> {
+	extract_spdx_download_urls(filepath)?
+		.into_iter()
+		.next()
+		.ok_or_else(|| hc_error!("No compatible download URLs found"))
+}
+
+/// Extract every compatible package download location from an SPDX
+/// document, in the order the packages appear
+pub fn extract_spdx_download_urls(filepath: &str) -> Result<Vec<String>> {
+	let contents = std::fs::read_to_string(filepath)?;
+
+	let urls = if contents.contains(DLOAD_LOCN_TAG) {
+		extract_download_urls_text(&contents)
+	} else if let Ok(spdx) = serde_json::from_str(&contents) {
+		extract_download_urls_json(spdx)
+	} else {
+		return Err(hc_error!("SPDX file is corrupt or incompatible"));
+	};
+
+	// Several packages may come from the same repository
+	let mut unique: Vec<String> = Vec::with_capacity(urls.len());
+	for url in urls {
+		if unique.contains(&url).not() {
+			unique.push(url);
+		}
+	}
+
+	Ok(unique)
+}
+
+// Extract the compatible package download locations from an SPDX
+// object obtained from a JSON file
+fn extract_download_urls_json(spdx: SPDX) -> Vec<String> {
+	spdx.package_information
+		.iter()
+		.filter_map(|package| parse_download_url(&package.package_download_location).ok())
+		.collect()
+}
+
+// Extract the compatible package download locations from an SPDX
+// text document
+fn extract_download_urls_text(contents: &str) -> Vec<String> {
+	contents
+		.lines()
+		.filter_map(|line| match line.split_once(DELIMITER) {
+			Some((DLOAD_LOCN_TAG, value)) => match value.trim() {
+				DLOAD_NONE | DLOAD_NOASSERT => None,
+				locn => parse_download_url(locn).ok(),
+			},
+			_ => None,
+		})
+		.collect()
+}
+
+// Select and prepare compatible URIs and VCS locations for use
+fn parse_download_url(locn: &str) -> Result<String> {
+	let mut url = match locn.strip_prefix(SCM_GIT_PLUS) {
+		Some(rest) => {
+			// In this case, any secondary scheme is compatible
+			Url::parse(rest).context("Invalid download location")
+		}
+		None => {
+			let url = Url::parse(locn).context("Invalid download location")?;
+			if matches!(url.scheme(), SCM_GIT | SCM_HTTP | SCM_HTTPS) {
+				Ok(url)
+			} else {
+				Err(hc_error!("Package uses a non-Git VCS"))
+			}
+		}
+	}?;
+
+	// Remove element identifiers
+	url.set_fragment(None);
+
+	Ok(url.as_str().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn text_document_yields_every_package() {
+		let contents = "\
+PackageName: first
+PackageDownloadLocation: git+https://github.com/mitre/hipcheck.git#main
+PackageName: second
+PackageDownloadLocation: NOASSERTION
+PackageName: third
+PackageDownloadLocation: https://github.com/serde-rs/serde
+PackageName: fourth
+PackageDownloadLocation: svn+https://example.com/repo
+";
+
+		assert_eq!(
+			extract_download_urls_text(contents),
+			vec![
+				"https://github.com/mitre/hipcheck.git".to_owned(),
+				"https://github.com/serde-rs/serde".to_owned(),
+			]
+		);
+	}
+}
@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Utilities for controlling which characters the shell is allowed to print.
+
+use crate::error::{Error, Result};
+use console::Emoji;
+use indicatif::ProgressStyle;
+use std::{borrow::Cow, str::FromStr};
+
+/// Character used in place of any non-ASCII character when printing in ASCII mode.
+const REPLACEMENT: char = '?';
+
+/// Selection of which character encoding the CLI output should use.
+#[derive(Debug, Default, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Encoding {
+	/// Use UTF-8 output, including emoji and other symbols
+	Utf8,
+	/// Only output ASCII characters
+	Ascii,
+	/// Guess whether the terminal supports emoji and other symbols
+	#[default]
+	Auto,
+}
+
+impl Encoding {
+	/// Check whether output is restricted to ASCII characters.
+	pub fn is_ascii(&self) -> bool {
+		matches!(self, Encoding::Ascii)
+	}
+
+	/// Pick which variant of an [Emoji] to use with this encoding.
+	pub fn emoji(&self, emoji: &Emoji<'static, 'static>) -> Cow<'static, str> {
+		match self {
+			Encoding::Utf8 => Cow::Borrowed(emoji.0),
+			Encoding::Ascii => Cow::Borrowed(emoji.1),
+			// `Emoji`'s `Display` impl guesses based on the terminal.
+			Encoding::Auto => Cow::Owned(emoji.to_string()),
+		}
+	}
+
+	/// Replace any characters which can't be printed with this encoding.
+	pub fn sanitize<'s>(&self, msg: &'s str) -> Cow<'s, str> {
+		if self.is_ascii() && !msg.is_ascii() {
+			Cow::Owned(
+				msg.chars()
+					.map(|c| if c.is_ascii() { c } else { REPLACEMENT })
+					.collect(),
+			)
+		} else {
+			Cow::Borrowed(msg)
+		}
+	}
+
+	/// Adjust a progress bar or spinner style to only draw characters valid for this encoding.
+	pub fn progress_style(&self, style: ProgressStyle) -> ProgressStyle {
+		if self.is_ascii() {
+			style.tick_chars("-\\|/ ").progress_chars("#>-")
+		} else {
+			style
+		}
+	}
+}
+
+impl FromStr for Encoding {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		match s.to_lowercase().as_ref() {
+			"utf8" => Ok(Encoding::Utf8),
+			"ascii" => Ok(Encoding::Ascii),
+			"auto" => Ok(Encoding::Auto),
+			_ => Err(Error::msg("unknown encoding option")),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ascii_sanitizes_non_ascii() {
+		assert_eq!(Encoding::Ascii.sanitize("caf\u{e9} \u{1F680}"), "caf? ?");
+		assert_eq!(Encoding::Ascii.sanitize("plain"), "plain");
+	}
+
+	#[test]
+	fn utf8_leaves_text_alone() {
+		assert_eq!(Encoding::Utf8.sanitize("caf\u{e9}"), "caf\u{e9}");
+		assert_eq!(Encoding::Auto.sanitize("caf\u{e9}"), "caf\u{e9}");
+	}
+
+	#[test]
+	fn emoji_fallback() {
+		let emoji = Emoji("\u{1F680}", "....");
+		assert_eq!(Encoding::Ascii.emoji(&emoji), "....");
+		assert_eq!(Encoding::Utf8.emoji(&emoji), "\u{1F680}");
+	}
+}
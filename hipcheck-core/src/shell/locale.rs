@@ -0,0 +1,233 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Utilities for formatting numbers and dates the way the user's locale does.
+//!
+//! Only the conventions for numbers and dates are localized; the text of the
+//! report stays in English. Locales Hipcheck doesn't know the conventions of
+//! are formatted as in `en-US`.
+
+use crate::{error::Result, hc_error};
+use chrono::{DateTime, Local};
+use std::str::FromStr;
+
+/// The conventions for formatting numbers and dates in the human-readable
+/// output.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Locale {
+	/// The character between the whole and fractional parts of a number.
+	decimal: char,
+	/// The character between each group of three digits. Locales which group
+	/// digits with a (non-breaking) space use a plain space, so the output
+	/// stays ASCII.
+	group: char,
+	/// The `chrono` format string for dates and times.
+	date_time: &'static str,
+}
+
+/// US English, the default.
+const EN_US: Locale = Locale {
+	decimal: '.',
+	group: ',',
+	date_time: "%a %B %-d, %Y at %-I:%M%P",
+};
+
+impl Default for Locale {
+	fn default() -> Self {
+		EN_US
+	}
+}
+
+impl Locale {
+	/// Find the locale from the `LC_ALL` or `LANG` environment variables.
+	pub fn detect() -> Locale {
+		["LC_ALL", "LANG"]
+			.iter()
+			.filter_map(|var| std::env::var(var).ok())
+			.find(|value| !value.is_empty())
+			.and_then(|value| Locale::from_str(&value).ok())
+			.unwrap_or_default()
+	}
+
+	/// Format a count, with separators between each group of three digits.
+	pub fn count(&self, n: usize) -> String {
+		group_digits(&n.to_string(), self.group)
+	}
+
+	/// Format a number with a fixed number of decimal places.
+	pub fn decimal(&self, n: f64, places: usize) -> String {
+		let formatted = format!("{:.*}", places, n);
+		let (sign, unsigned) = match formatted.strip_prefix('-') {
+			Some(unsigned) => ("-", unsigned),
+			None => ("", formatted.as_str()),
+		};
+
+		match unsigned.split_once('.') {
+			Some((whole, fraction)) => format!(
+				"{}{}{}{}",
+				sign,
+				group_digits(whole, self.group),
+				self.decimal,
+				fraction
+			),
+			None => format!("{}{}", sign, group_digits(unsigned, self.group)),
+		}
+	}
+
+	/// Format a date and time.
+	pub fn date_time(&self, date_time: &DateTime<Local>) -> String {
+		date_time.format(self.date_time).to_string()
+	}
+}
+
+impl FromStr for Locale {
+	type Err = crate::Error;
+
+	/// Parse a locale given as a language tag, like `de-DE`, or as a POSIX
+	/// locale name, like `de_DE.UTF-8`.
+	fn from_str(s: &str) -> Result<Self> {
+		// Drop any encoding or modifier, as in `de_DE.UTF-8@euro`.
+		let name = s.split(['.', '@']).next().unwrap_or_default();
+		let mut parts = name.split(['_', '-']);
+		let language = parts.next().unwrap_or_default().to_lowercase();
+		let region = parts.next().unwrap_or_default().to_uppercase();
+
+		if language.is_empty() || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+			return Err(hc_error!("invalid locale '{}'", s));
+		}
+
+		let locale = match (language.as_str(), region.as_str()) {
+			("c" | "posix", _) | ("en", "US" | "") => EN_US,
+			("en", _) => Locale {
+				date_time: "%a %-d %B %Y at %H:%M",
+				..EN_US
+			},
+			("de", "CH") => Locale {
+				decimal: '.',
+				group: '\'',
+				date_time: "%d.%m.%Y, %H:%M",
+			},
+			("de", _) => Locale {
+				decimal: ',',
+				group: '.',
+				date_time: "%d.%m.%Y, %H:%M",
+			},
+			("nl", _) => Locale {
+				decimal: ',',
+				group: '.',
+				date_time: "%d-%m-%Y %H:%M",
+			},
+			("es" | "it" | "pt" | "el" | "id" | "tr", _) => Locale {
+				decimal: ',',
+				group: '.',
+				date_time: "%d/%m/%Y %H:%M",
+			},
+			("da", _) => Locale {
+				decimal: ',',
+				group: '.',
+				date_time: "%d.%m.%Y %H.%M",
+			},
+			("fr", "CA") => Locale {
+				decimal: ',',
+				group: ' ',
+				date_time: "%Y-%m-%d %H:%M",
+			},
+			("fr", "CH") => Locale {
+				decimal: '.',
+				group: '\'',
+				date_time: "%d.%m.%Y %H:%M",
+			},
+			("fr", _) => Locale {
+				decimal: ',',
+				group: ' ',
+				date_time: "%d/%m/%Y %H:%M",
+			},
+			("ru" | "uk" | "pl" | "cs" | "sk" | "fi" | "nb" | "no", _) => Locale {
+				decimal: ',',
+				group: ' ',
+				date_time: "%d.%m.%Y %H:%M",
+			},
+			("sv", _) => Locale {
+				decimal: ',',
+				group: ' ',
+				date_time: "%Y-%m-%d %H:%M",
+			},
+			("ja" | "zh", _) => Locale {
+				date_time: "%Y/%m/%d %H:%M",
+				..EN_US
+			},
+			("ko", _) => Locale {
+				date_time: "%Y. %-m. %-d. %H:%M",
+				..EN_US
+			},
+			_ => {
+				tracing::debug!("no conventions known for locale '{}', using en-US", s);
+				EN_US
+			}
+		};
+
+		Ok(locale)
+	}
+}
+
+/// Put a separator between each group of three digits.
+fn group_digits(digits: &str, separator: char) -> String {
+	let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+
+	for (i, c) in digits.chars().enumerate() {
+		if i > 0 && (digits.len() - i) % 3 == 0 {
+			out.push(separator);
+		}
+		out.push(c);
+	}
+
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::TimeZone;
+
+	#[test]
+	fn parses_locale_names() {
+		assert_eq!(Locale::from_str("en_US.UTF-8").unwrap(), EN_US);
+		assert_eq!(Locale::from_str("C").unwrap(), EN_US);
+		assert_eq!(
+			Locale::from_str("de-DE").unwrap(),
+			Locale::from_str("de_AT.UTF-8@euro").unwrap()
+		);
+		assert_eq!(Locale::from_str("xx_YY").unwrap(), EN_US);
+		assert!(Locale::from_str("").is_err());
+		assert!(Locale::from_str("1234").is_err());
+	}
+
+	#[test]
+	fn formats_numbers() {
+		let de = Locale::from_str("de_DE").unwrap();
+		let fr = Locale::from_str("fr_FR").unwrap();
+
+		assert_eq!(EN_US.count(1234567), "1,234,567");
+		assert_eq!(de.count(1234567), "1.234.567");
+		assert_eq!(fr.count(999), "999");
+
+		assert_eq!(EN_US.decimal(0.2, 2), "0.20");
+		assert_eq!(de.decimal(1234.5, 2), "1.234,50");
+		assert_eq!(fr.decimal(-1234.5, 1), "-1 234,5");
+		assert_eq!(de.decimal(12.0, 0), "12");
+	}
+
+	#[test]
+	fn formats_dates() {
+		let date_time = Local.with_ymd_and_hms(2024, 3, 9, 14, 5, 0).unwrap();
+
+		assert_eq!(EN_US.date_time(&date_time), "Sat March 9, 2024 at 2:05pm");
+		assert_eq!(
+			Locale::from_str("en_GB").unwrap().date_time(&date_time),
+			"Sat 9 March 2024 at 14:05"
+		);
+		assert_eq!(
+			Locale::from_str("de").unwrap().date_time(&date_time),
+			"09.03.2024, 14:05"
+		);
+	}
+}
@@ -0,0 +1,1033 @@
+// SPDX-License-Identifier: Apache-2.0
+
+#![deny(missing_docs)]
+
+use crate::{
+	error::{Error, Result},
+	hc_error,
+	report::{
+		batch::{BatchReport, BatchSummary},
+		contributors::ContributorRollup,
+		diff::AnalysisChange,
+		features::FeatureVector,
+		github, html, junit,
+		portfolio::PortfolioSummary,
+		sample::SampleSummary,
+		sarif::SarifLog,
+		table::TargetTable,
+		Format, RecommendationKind, Report,
+	},
+};
+use console::{Emoji, Style, Term};
+use indicatif::{MultiProgress, ProgressDrawTarget};
+use locale::Locale;
+use output::Output;
+use std::{
+	fmt,
+	fmt::{Alignment, Debug, Display, Formatter},
+	io::Write,
+	sync::{OnceLock, RwLock},
+};
+use encoding::Encoding;
+use verbosity::{SilenceGuard, Verbosity};
+
+#[cfg(feature = "print-timings")]
+use console::style;
+
+#[cfg(feature = "print-timings")]
+use std::time::Instant;
+
+pub mod color_choice;
+pub mod encoding;
+pub mod iter;
+pub mod locale;
+pub mod macros;
+pub mod output;
+pub mod par_iter;
+pub mod progress_phase;
+pub mod spinner_phase;
+pub mod verbosity;
+
+/// Global static shell instance, stored in a [`OnceLock`] to make it thread safe and lazy.
+static GLOBAL_SHELL: OnceLock<Shell> = OnceLock::new();
+
+const ROCKET_SHIP: Emoji = Emoji("🚀", "....");
+const HOUR_GLASS: Emoji = Emoji("⏳", ">>>>");
+
+/// The width of the left column when printing errors/reports/etc.
+pub const LEFT_COL_WIDTH: usize = 20;
+
+/// Empty static string used for drawing padding.
+const EMPTY: &str = "";
+
+/// Type interface to the global shell used to produce output in the user's terminal.
+#[derive(Debug)]
+pub struct Shell {
+	/// Multi-progress bar object rendering all the different progress bars we're using.
+	multi_progress: MultiProgress,
+	/// The verbosity of this shell.
+	verbosity: RwLock<Verbosity>,
+	/// The character encoding this shell is allowed to print.
+	encoding: RwLock<Encoding>,
+	/// How this shell formats numbers and dates.
+	locale: RwLock<Locale>,
+}
+
+impl Shell {
+	/// Initialize the global shell. Panics if the global shell is already initialized.
+	pub fn init(verbosity: Verbosity) {
+		if GLOBAL_SHELL.get().is_some() {
+			panic!("Global shell is already initialized");
+		}
+
+		GLOBAL_SHELL.get_or_init(move || Shell {
+			multi_progress: MultiProgress::new(),
+			verbosity: RwLock::new(verbosity),
+			encoding: RwLock::new(Encoding::default()),
+			locale: RwLock::new(Locale::default()),
+		});
+	}
+
+	/// Check if the global shell is initialized.
+	pub fn is_init() -> bool {
+		Shell::try_get().is_some()
+	}
+
+	/// Get the global shell if it's initialized or return [None].
+	#[inline]
+	pub fn try_get() -> Option<&'static Self> {
+		GLOBAL_SHELL.get()
+	}
+
+	/// Get a static reference to the global shell. Panics if the global shell has not yet been initialized.
+	pub fn get() -> &'static Self {
+		Self::try_get().expect("global shell needs to be initialized.")
+	}
+
+	/// Update the verbosity of the global shell.
+	pub fn set_verbosity(verbosity: Verbosity) {
+		// If the new verbosity is "silent", hide all progress bars.
+		if verbosity == Verbosity::Silent {
+			Shell::get()
+				.multi_progress
+				.set_draw_target(ProgressDrawTarget::hidden());
+		}
+
+		let mut write_guard = Self::get()
+			.verbosity
+			.write()
+			.expect("acquired write guard to global verbosity");
+
+		// If moving from a "silent" verbosity state to a not-silent verbosity, reset the
+		// draw target for the progress bars back to the stderr (default).
+		if *write_guard == Verbosity::Silent && verbosity != Verbosity::Silent {
+			Shell::get()
+				.multi_progress
+				.set_draw_target(ProgressDrawTarget::stderr());
+		}
+
+		*write_guard = verbosity;
+	}
+
+	/// Silence the global [Shell] for the remainder of this scope.
+	///
+	/// This is equivalent to using [Shell::set_verbosity] to silence the global shell,
+	/// and then reseting it back to the previous value whenever the returned [SilenceGuard] is [drop]ped.
+	#[allow(unused)]
+	pub fn silence() -> SilenceGuard {
+		let previous_verbosity = Shell::get_verbosity();
+		Shell::set_verbosity(Verbosity::Silent);
+		SilenceGuard { previous_verbosity }
+	}
+
+	/// Get the current verbosity of the global shell.
+	///
+	/// Be aware that the value may become outdated if another thread calls [Shell::set_verbosity].
+	pub fn get_verbosity() -> Verbosity {
+		let guard = Self::get()
+			.verbosity
+			.read()
+			.expect("acquired read guard to global verbosity");
+
+		// Deref-copy and return.
+		*guard
+	}
+
+	/// Update the character encoding of the global shell.
+	pub fn set_encoding(encoding: Encoding) {
+		let mut write_guard = Self::get()
+			.encoding
+			.write()
+			.expect("acquired write guard to global encoding");
+
+		*write_guard = encoding;
+	}
+
+	/// Get the current character encoding of the global shell.
+	pub fn get_encoding() -> Encoding {
+		let guard = Self::get()
+			.encoding
+			.read()
+			.expect("acquired read guard to global encoding");
+
+		// Deref-copy and return.
+		*guard
+	}
+
+	/// Update how the global shell formats numbers and dates.
+	pub fn set_locale(locale: Locale) {
+		let mut write_guard = Self::get()
+			.locale
+			.write()
+			.expect("acquired write guard to global locale");
+
+		*write_guard = locale;
+	}
+
+	/// Get how the global shell formats numbers and dates.
+	///
+	/// Reports are also formatted outside of a shell, as in tests, so this
+	/// falls back to the default locale if the global shell isn't initialized.
+	pub fn get_locale() -> Locale {
+		let Some(shell) = Self::try_get() else {
+			return Locale::default();
+		};
+
+		let guard = shell
+			.locale
+			.read()
+			.expect("acquired read guard to global locale");
+
+		// Deref-copy and return.
+		*guard
+	}
+
+	/// Update whether colors are enabled for all of hipcheck.
+	pub fn set_colors_enabled(enable: bool) {
+		console::set_colors_enabled(enable);
+		console::set_colors_enabled_stderr(enable);
+	}
+
+	/// Get a clone of the [`MultiProgress`] instance stored using [`Arc::clone`] under the hood.
+	#[allow(unused)]
+	pub fn progress_bars() -> MultiProgress {
+		Self::get().multi_progress.clone()
+	}
+
+	/// Print timing info. Only enabled while hipcheck is being benchmarked.
+	#[allow(unused)]
+	#[cfg(feature = "benchmarking")]
+	pub fn print_timing(timing: &crate::benchmarking::PrintTime) {
+		use crate::benchmarking::PrintTime;
+
+		// Destructure timing object.
+		let PrintTime { location, start } = timing;
+
+		Shell::in_suspend(|| {
+			eprintln!(
+				"[TIMINGS]: {}: {:.6} seconds elapsed.",
+				style(location).bold(),
+				(Instant::now() - *start).as_secs_f64()
+			);
+		})
+	}
+
+	/// Print a message to the standard output if the standard output is a terminal.
+	/// Panics if the global shell is not initialized or if there's an issue printing to the standard output.
+	#[allow(unused)]
+	pub fn println_if_terminal(msg: impl AsRef<str>) {
+		Shell::get()
+			.multi_progress
+			.println(msg)
+			.expect("could print to standard output")
+	}
+
+	/// Suspend and hide all progress bars to write to the standard output or standard error.
+	/// Do not do heavy coomputation here since the lock on the progress bars is held the whole time and
+	/// may cause other threads to block waiting on a lock.
+	///
+	/// # Panics
+	/// - Panics if the global shell is not initialized.
+	pub fn in_suspend<F, R>(f: F) -> R
+	where
+		F: FnOnce() -> R,
+	{
+		Self::get().multi_progress.suspend(f)
+	}
+
+	/// Print a message regardless of whether or not the standard output is a terminal.
+	/// [Shell::println_if_terminal] may be more desirable in some cases.
+	///
+	/// This will temporarily hide the progress bars to print.
+	///
+	/// # Panics
+	/// - Panics if the global logger is not initialized.
+	pub fn println(msg: impl Display) {
+		// Do not print if verbosity is set to silent.
+		if Shell::get_verbosity() == Verbosity::Silent {
+			return;
+		}
+
+		let msg = msg.to_string();
+		let msg = Shell::get_encoding().sanitize(&msg);
+
+		Shell::in_suspend(|| {
+			println!("{msg}");
+		})
+	}
+
+	/// Bypass the recommended styling and print a message to the standard error.
+	/// Temporarily hide the progress bar to print.
+	///
+	/// # Panics
+	/// - Panics if the global logger is not initialized.
+	pub fn eprintln(msg: impl Display) {
+		let msg = msg.to_string();
+		let msg = Shell::get_encoding().sanitize(&msg);
+
+		Shell::in_suspend(|| {
+			eprintln!("{}", msg);
+		})
+	}
+
+	/// Print "Analysing {source}" with the proper color/styling.
+	pub fn print_prelude(source: impl AsRef<str>) {
+		match Shell::get_verbosity() {
+			Verbosity::Normal => {
+				macros::println!("{:>LEFT_COL_WIDTH$} {}", Title::Analyzing, source.as_ref());
+			}
+			Verbosity::Quiet | Verbosity::Silent => {}
+		}
+	}
+
+	/// Print a hipcheck [Error]. Human readable errors will go to the standard error, JSON will go to the standard output.
+	pub fn print_error(err: &Error, format: Format) {
+		match format {
+			Format::Human
+			| Format::FeaturesCsv
+			| Format::Html
+			| Format::Github
+			| Format::Junit
+			| Format::Csv => {
+				// Print the root error -- the first in the chain should not be none.
+				let mut chain = err.chain();
+				macros::eprintln!("{}", chain.next().expect("chain is not empty"));
+
+				// Print remaining errors in chain.
+				for err in chain {
+					macros::eprintln!("{EMPTY:LEFT_COL_WIDTH$}{}", err);
+				}
+
+				// Print an extra newline at the end to separate error printing from other stuff.
+				macros::eprintln!();
+			}
+
+			Format::Json | Format::FeaturesJson | Format::Sarif => {
+				// Construct a JSON value from an error.
+				let current = err.to_string();
+				let context = err
+					.chain()
+					.skip(1)
+					.map(ToString::to_string)
+					.collect::<Vec<_>>();
+
+				let error_json = serde_json::json!({
+					"Error": {
+						"message": current,
+						"context": context,
+					}
+				});
+
+				tracing::trace!("writing message part [part='{:?}']", error_json);
+
+				// Suspend the progress bars to print the JSON.
+				Shell::in_suspend(|| {
+					let mut stdout = Term::buffered_stdout();
+
+					serde_json::to_writer_pretty(&mut stdout, &error_json)
+						.expect("Wrote JSON to standard output.");
+
+					writeln!(&mut stdout).expect("wrote newline to standard out");
+					stdout.flush().expect("flushed standard out");
+				});
+			}
+		}
+	}
+
+	/// Print the final repo report in the requested format to the output.
+	pub fn print_report(report: Report, format: Format, output: &Output) -> Result<()> {
+		match format {
+			Format::Human => {
+				require_stdout(output)?;
+				print_human(&report)
+			}
+			_ => output.write_with(|out| write_report(out, &report, format)),
+		}
+	}
+
+	/// Print one report of a batch as soon as it's done, rather than with the
+	/// rest of the batch, which only human output does. The batch's summary
+	/// follows with [`Shell::print_batch_summary`].
+	pub fn print_batch_report(report: &Report, output: &Output) -> Result<()> {
+		require_stdout(output)?;
+		print_human(report)
+	}
+
+	/// Print the summary of a batch whose reports were each printed with
+	/// [`Shell::print_batch_report`].
+	pub fn print_batch_summary(batch: BatchReport, output: &Output) -> Result<()> {
+		require_stdout(output)?;
+		print_batch_summary(
+			batch.summary,
+			batch.contributors,
+			batch.sample,
+			batch.portfolio,
+		)
+	}
+
+	/// Print the reports for several targets, with a summary of all of them.
+	///
+	/// JSON output is a single document holding every report and the summary,
+	/// JUnit output a single document with a test suite per report, and CSV
+	/// output a single table with a row per target. Human output prints each
+	/// report followed by the summary. Other formats print each report in turn.
+	pub fn print_batch(batch: BatchReport, format: Format, output: &Output) -> Result<()> {
+		match format {
+			Format::Json => output.write_with(|out| {
+				serde_json::to_writer_pretty(&mut *out, &batch)?;
+				writeln!(out)?;
+				Ok(())
+			}),
+			Format::Human => {
+				require_stdout(output)?;
+				let summary = batch.summary;
+				let contributors = batch.contributors;
+				let sample = batch.sample;
+				let portfolio = batch.portfolio;
+				for report in batch.reports {
+					print_human(&report)?;
+				}
+				print_batch_summary(summary, contributors, sample, portfolio)
+			}
+			// The reports share one document, as a test suite each
+			Format::Junit => output.write_with(|out| {
+				out.write_all(junit::render(&batch.reports).as_bytes())?;
+				Ok(())
+			}),
+			Format::Csv => output.write_with(|out| {
+				out.write_all(TargetTable::from_batch(&batch).to_csv().as_bytes())?;
+				Ok(())
+			}),
+			_ => output.write_with(|out| {
+				for report in &batch.reports {
+					write_report(out, report, format)?;
+				}
+				Ok(())
+			}),
+		}
+	}
+}
+
+/// Check that human output, which is styled for the terminal, isn't being
+/// written to a file.
+fn require_stdout(output: &Output) -> Result<()> {
+	if output.is_stdout() {
+		return Ok(());
+	}
+
+	Err(hc_error!(
+		"the human format can't be written to a file; choose another with -f/--format"
+	))
+}
+
+/// Write a report in a machine-readable format.
+fn write_report(out: &mut dyn Write, report: &Report, format: Format) -> Result<()> {
+	match format {
+		Format::Json => serde_json::to_writer_pretty(&mut *out, report)?,
+		Format::FeaturesJson => {
+			serde_json::to_writer_pretty(&mut *out, &FeatureVector::from_report(report))?;
+			writeln!(out)?;
+		}
+		Format::FeaturesCsv => {
+			out.write_all(FeatureVector::from_report(report).to_csv().as_bytes())?
+		}
+		Format::Sarif => {
+			serde_json::to_writer_pretty(&mut *out, &SarifLog::from_report(report))?;
+			writeln!(out)?;
+		}
+		Format::Html => out.write_all(html::render(report).as_bytes())?,
+		Format::Github => {
+			out.write_all(github::annotations(report).as_bytes())?;
+			github::write_step_summary(report)?;
+		}
+		Format::Junit => out.write_all(junit::render([report]).as_bytes())?,
+		Format::Csv => out.write_all(TargetTable::from_report(report).to_csv().as_bytes())?,
+		Format::Human => unreachable!("human reports are printed with `print_human`"),
+	}
+
+	Ok(())
+}
+
+fn print_batch_summary(
+	summary: BatchSummary,
+	contributors: Option<ContributorRollup>,
+	sample: Option<SampleSummary>,
+	portfolio: Option<PortfolioSummary>,
+) -> Result<()> {
+	//       Summary
+	//           3 targets: 1 pass, 0 use with conditions, 1 investigate, 1 errored
+	//      PASS https://github.com/mitre/hipcheck.git, risk rated as 0.20
+	//      INVESTIGATE https://github.com/serde-rs/serde.git, risk rated as 0.60
+	//         ? https://github.com/example/missing.git, analysis error: ...
+
+	macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Summary"));
+	macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", summary.statement());
+
+	for target in &summary.targets {
+		let title = match target.recommendation() {
+			Some(kind) => Title::from(kind),
+			None => Title::Errored,
+		};
+
+		macros::println!("{:>LEFT_COL_WIDTH$} {}", title, target.statement());
+	}
+
+	//     Portfolio
+	//           3 targets analyzed, risk rated from 0.10 to 0.60, mean 0.30, median 0.20, 90th percentile 0.60
+	//           0.1-0.2 #######              1
+	//           0.2-0.3 #######              1
+	//           0.6-0.7 #######              1
+	//           'mitre/typo' failed for 2 of 3 targets
+	//      INVESTIGATE https://github.com/serde-rs/serde.git, risk rated as 0.60, failed mitre/typo, mitre/entropy
+
+	if let Some(portfolio) = portfolio {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Portfolio"));
+		let scores = &portfolio.risk_scores;
+		macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", scores.statement());
+
+		for bucket in scores.histogram.iter().filter(|bucket| bucket.count > 0) {
+			macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", bucket.statement(scores.count));
+		}
+
+		for failures in &portfolio.common_failures {
+			macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", failures.statement());
+		}
+
+		for target in &portfolio.worst_targets {
+			let title = Title::from(target.recommendation);
+			macros::println!("{:>LEFT_COL_WIDTH$} {}", title, target.statement());
+		}
+	}
+
+	//       Contributors
+	//           Jane Doe <jane@example.com>, 3 targets, 120 commits, influence 1.40
+	//           example.com, 2 contributors, 3 targets, 150 commits, influence 1.75
+
+	if let Some(contributors) = contributors.filter(|c| !c.is_empty()) {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Contributors"));
+
+		for individual in &contributors.individuals {
+			macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", individual.statement());
+		}
+
+		for organization in &contributors.organizations {
+			macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", organization.statement());
+		}
+	}
+
+	//       Sample
+	//           sampled 120 of 1,200 targets (seed 42); an estimated 24% to investigate (±7% at 95% confidence), mean risk 0.41
+	//           maven: 40 of 400 sampled, 39 analyzed, 12 investigate, mean risk 0.47
+
+	if let Some(sample) = sample {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Sample"));
+		macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", sample.statement());
+
+		for stratum in &sample.strata {
+			macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", stratum.statement());
+		}
+	}
+
+	// Newline for spacing.
+	macros::println!();
+
+	Ok(())
+}
+
+fn print_human(report: &Report) -> Result<()> {
+	// Go through each part and print them individually.
+
+	//      Analyzed '<repo_name>' (<repo_head>)
+	//               using Hipcheck <hipcheck_version>
+	//               at <analyzed_at:pretty_print>
+	//
+	//       Passing
+	//            + no concerning contributors
+	//               0 found, 0 permitted
+	//            + no unusually large commits
+	//               0% of commits are unusually large, 2% permitted
+	//            + commits usually merged by someone other than the author
+	//               0% of commits merged by author, 20% permitted
+	//
+	//       Failing
+	//            - too many unusual-looking commits
+	//               1% of commits look unusual, 0% permitted
+	//               entropy scores over 3.0 are considered unusual
+	//
+	//              356828346723752 "fixing something" (entropy score: 5.4)
+	//              abab563268c3543 "adding a new block" (entropy score: 3.2)
+	//
+	//           - hasn't been updated in 136 weeks
+	//              require updates in the last 71 weeks
+	//
+	//        Errored
+	//           ? review analysis failed to get pull request reviews
+	//              cause: missing GitHub token with permissions for accessing public repository data in config
+	//           ? typo analysis failed to get dependencies
+	//              cause: can't identify a known language in the repository
+	//
+	//        Skipped
+	//           ~ 'mitre/review' skipped, target has no GitHub remote
+	//
+	//         Waived
+	//           = 'mitre/binary': Found binary file at 'vendor/tool.exe'
+	//              waived until 2026-12-31: vendored build tool, reviewed by security team
+	//
+	// Plugin Sections
+	//             Licenses (from mitre/license)
+	//               - MIT
+	//               - Apache-2.0
+	//
+	// Risk Categories
+	//           + abandonment: 0 of 1 analyses failed
+	//           - malicious-contribution: 1 of 3 analyses failed (2 concerns)
+	//
+	// Since Baseline
+	//           compared against baseline (<baseline_head>)
+	//           - 'mitre/fuzz' failed, was passed
+	//           4 unchanged
+	//           risk rated as 0.40, was 0.20 (+0.20)
+	//
+	// Recommendation
+	//           PASS risk rated as 0.4 (acceptable below 0.5)
+
+	/*===============================================================================
+	 * Header
+	 *
+	 * Says what we're analyzing, what version of Hipcheck we're using, and when
+	 * we're doing the analysis.
+	 */
+
+	// Start with an empty line.
+	macros::println!();
+	// What repo we analyzed.
+	macros::println!("{:>LEFT_COL_WIDTH$} {}", Title::Analyzed, report.analyzed());
+	// With what version of hipcheck.
+	macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", report.using());
+	// At what time.
+	macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", report.at_time());
+	// Space between this and analyses.
+	macros::println!();
+
+	/*===============================================================================
+	 * Passing analyses
+	 *
+	 * Says what analyses passed and why.
+	 */
+
+	if report.has_passing_analyses() {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Passing"));
+
+		for analysis in report.passing_analyses() {
+			macros::println!(
+				"{:>LEFT_COL_WIDTH$} {}",
+				Title::Passed,
+				analysis.statement()
+			);
+			macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", analysis.explanation());
+			// Empty line at end to space out analyses.
+			macros::println!();
+		}
+	}
+
+	/*===============================================================================
+	 * Failing analyses
+	 *
+	 * Says what analyses failed, why, and what information might be relevant to
+	 * check in detail.
+	 */
+
+	if report.has_failing_analyses() {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Failing"));
+
+		for failing_analysis in report.failing_analyses() {
+			let analysis = failing_analysis.analysis();
+
+			macros::println!(
+				"{:>LEFT_COL_WIDTH$} {}",
+				Title::Failed,
+				analysis.statement()
+			);
+			macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", analysis.explanation());
+
+			for concern in failing_analysis.concern_details() {
+				macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", concern.statement());
+			}
+
+			if let Some(summary) = failing_analysis.omitted_summary() {
+				macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", summary);
+			}
+
+			// Newline at the end for spacing.
+			macros::println!();
+		}
+	}
+
+	/*===============================================================================
+	 * Errored analyses
+	 *
+	 * Says what analyses encountered errors and what those errors were.
+	 */
+
+	if report.has_errored_analyses() {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Errored"));
+
+		for errored_analysis in report.errored_analyses() {
+			macros::println!(
+				"{:>LEFT_COL_WIDTH$} {}",
+				Title::Errored,
+				errored_analysis.top_msg()
+			);
+
+			for msg in &errored_analysis.source_msgs() {
+				macros::println!("{EMPTY:LEFT_COL_WIDTH$} {msg}");
+			}
+
+			// Newline for spacing.
+			macros::println!();
+		}
+	}
+
+	/*===============================================================================
+	 * Skipped analyses
+	 *
+	 * Says what analyses didn't apply to the target, and why, so they aren't
+	 * mistaken for broken ones.
+	 */
+
+	if report.has_skipped_analyses() {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Skipped"));
+
+		for skipped_analysis in report.skipped_analyses() {
+			macros::println!(
+				"{:>LEFT_COL_WIDTH$} {}",
+				Title::Skipped,
+				skipped_analysis.statement()
+			);
+		}
+
+		// Newline for spacing.
+		macros::println!();
+	}
+
+	/*===============================================================================
+	 * Waived concerns
+	 *
+	 * Says what concerns the policy file waived, and why, so they don't go
+	 * unnoticed once they're left out of the failing analyses.
+	 */
+
+	if report.has_waived_concerns() {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Waived"));
+
+		for waived in report.waived_concerns() {
+			macros::println!("{:>LEFT_COL_WIDTH$} {}", Title::Waived, waived.statement());
+			macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", waived.explanation());
+		}
+
+		// Newline for spacing.
+		macros::println!();
+	}
+
+	/*===============================================================================
+	 * Plugin sections
+	 *
+	 * Shows the sections plugins added to the report, for results which aren't
+	 * a pass or fail.
+	 */
+
+	if report.has_fragments() {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Plugin Sections"));
+
+		for fragment in report.fragments() {
+			macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", fragment.heading());
+
+			for line in fragment.text().lines() {
+				macros::println!("{EMPTY:LEFT_COL_WIDTH$}   {line}");
+			}
+
+			// Newline for spacing.
+			macros::println!();
+		}
+	}
+
+	/*===============================================================================
+	 * Risk categories
+	 *
+	 * Says how the analyses in each risk category fared, if the policy file
+	 * tagged any analyses with categories.
+	 */
+
+	if report.has_risk_categories() {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Risk Categories"));
+
+		for rollup in report.risk_categories() {
+			let title = if rollup.is_passing() {
+				Title::Passed
+			} else {
+				Title::Failed
+			};
+
+			macros::println!("{:>LEFT_COL_WIDTH$} {}", title, rollup.statement());
+		}
+
+		// Newline for spacing.
+		macros::println!();
+	}
+
+	/*===============================================================================
+	 * Baseline comparison
+	 *
+	 * Says which analyses got better or worse since the baseline report, if the
+	 * user gave one.
+	 */
+
+	if let Some(delta) = report.delta() {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Since Baseline"));
+		macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", delta.against());
+
+		for analysis in delta.changed_analyses() {
+			let title = match analysis.change {
+				AnalysisChange::Improved => Title::Passed,
+				AnalysisChange::Regressed => Title::Failed,
+				AnalysisChange::Unchanged => match analysis.concerns {
+					Some(concerns) if concerns.new > 0 => Title::Failed,
+					_ => Title::Passed,
+				},
+				_ => Title::Errored,
+			};
+
+			macros::println!("{:>LEFT_COL_WIDTH$} {}", title, analysis.statement());
+		}
+
+		macros::println!(
+			"{EMPTY:LEFT_COL_WIDTH$} {} unchanged",
+			delta.unchanged_count()
+		);
+		macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", delta.risk_score.statement());
+
+		if delta.recommendation.is_changed() {
+			macros::println!(
+				"{EMPTY:LEFT_COL_WIDTH$} recommendation was {}",
+				Title::from(delta.recommendation.before)
+			);
+		}
+
+		// Newline for spacing.
+		macros::println!();
+	}
+
+	/*===============================================================================
+	 * Score breakdown
+	 *
+	 * Says how much each analysis added to the risk score, so users can see where
+	 * the score came from.
+	 */
+
+	if report.has_score_breakdown() {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Score Breakdown"));
+
+		let locale = Shell::get_locale();
+		let rows: Vec<_> = report
+			.score_breakdown()
+			.map(|c| {
+				[
+					c.analysis.clone(),
+					c.value_summary(),
+					locale.decimal(c.score, 0),
+					locale.decimal(c.weight, 4),
+					locale.decimal(c.contribution, 4),
+				]
+			})
+			.collect();
+		let header = ["analysis", "value", "score", "weight", "contribution"].map(String::from);
+		let total = locale.decimal(report.score_breakdown().map(|c| c.contribution).sum(), 4);
+
+		// Size each column to fit its widest cell
+		let mut widths = header.clone().map(|h| h.len());
+		for row in &rows {
+			for (width, cell) in widths.iter_mut().zip(row) {
+				*width = (*width).max(cell.chars().count());
+			}
+		}
+		widths[4] = widths[4].max(total.len());
+		let [analysis_w, value_w, score_w, weight_w, contribution_w] = widths;
+
+		for [analysis, value, score, weight, contribution] in std::iter::once(header).chain(rows) {
+			macros::println!(
+				"{EMPTY:LEFT_COL_WIDTH$} {analysis:<analysis_w$}  {value:>value_w$}  {score:>score_w$}  {weight:>weight_w$}  {contribution:>contribution_w$}"
+			);
+		}
+
+		let total_w = analysis_w + value_w + score_w + weight_w + 6;
+		macros::println!(
+			"{EMPTY:LEFT_COL_WIDTH$} {:<total_w$}  {total:>contribution_w$}",
+			"total"
+		);
+
+		// Newline for spacing.
+		macros::println!();
+	}
+
+	/*===============================================================================
+	 * Recommendation
+	 *
+	 * Says what Hipcheck's final recommendation is for the target of analysis.
+	 */
+
+	let recommendation = report.recommendation();
+
+	macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Recommendation"));
+	macros::println!(
+		"{:>LEFT_COL_WIDTH$} {}",
+		Title::from(recommendation.kind),
+		recommendation.statement()
+	);
+	if let Some(message) = &recommendation.message {
+		macros::println!("{EMPTY:LEFT_COL_WIDTH$} {message}");
+	}
+	// Newline for spacing.
+	macros::println!();
+
+	Ok(())
+}
+
+/// The "title" of a message; may be accompanied by a timestamp or outcome.
+#[derive(Debug)]
+#[allow(unused)]
+enum Title {
+	/// "Analyzing"
+	Analyzing,
+	/// "Analyzed"
+	Analyzed,
+	/// The name of the section.
+	Section(&'static str),
+	/// An analysis passed.
+	Passed,
+	/// An analysis failed.
+	Failed,
+	/// An analysis errored out.
+	Errored,
+	/// An analysis was skipped.
+	Skipped,
+	/// A concern was waived.
+	Waived,
+	/// "In Progress"
+	InProgress,
+	/// "Done"
+	Done,
+	/// "PASS"
+	Pass,
+	/// "USE WITH CONDITIONS"
+	UseWithConditions,
+	/// "INVESTIGATE"
+	Investigate,
+	/// "Error"
+	Error,
+}
+
+impl Title {
+	const fn text(&self) -> &str {
+		use Title::*;
+
+		match self {
+			Analyzing => "Analyzing",
+			Analyzed => "Analyzed",
+			Section(s) => s,
+			Passed => "+",
+			Failed => "-",
+			Errored => "?",
+			Skipped => "~",
+			Waived => "=",
+			InProgress => "In Progress",
+			Done => "Done",
+			Pass => "PASS",
+			UseWithConditions => "USE WITH CONDITIONS",
+			Investigate => "INVESTIGATE",
+			Error => "Error",
+		}
+	}
+
+	fn style(&self) -> Style {
+		use console::Color::*;
+		use Title::*;
+
+		let color = match self {
+			Analyzed | Section(..) => Some(Blue),
+			Analyzing | Done | Skipped | Waived => Some(Cyan),
+			InProgress => Some(Magenta),
+			Passed | Pass => Some(Green),
+			Failed | Investigate => Some(Red),
+			Errored | UseWithConditions => Some(Yellow),
+			Error => Some(Red),
+		};
+
+		match color {
+			Some(c) => Style::new().fg(c).bold(),
+			None => Style::new(),
+		}
+	}
+}
+
+impl Display for Title {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match f.width() {
+			// No width designation -- just write the styled string itself.
+			None => write!(f, "{}", self.style().apply_to(self.text())),
+
+			// Width/alignment/padding handled here.
+			Some(width) => {
+				let styled: String = self.style().apply_to(self.text()).to_string();
+
+				// Convert the alignment passed to the formatter. If there is no alignment, default to
+				// left align.
+				let align = f
+					.align()
+					.map(convert_alignment)
+					.unwrap_or(console::Alignment::Left);
+
+				let padded = console::pad_str(&styled, width, align, None);
+				f.write_str(&padded)
+			}
+		}
+	}
+}
+
+/// Convert an [Alignment] from [std::fmt] to [console::Alignment] trivially, using a match arm.
+const fn convert_alignment(align: Alignment) -> console::Alignment {
+	match align {
+		Alignment::Left => console::Alignment::Left,
+		Alignment::Right => console::Alignment::Right,
+		Alignment::Center => console::Alignment::Center,
+	}
+}
+
+impl From<RecommendationKind> for Title {
+	fn from(kind: RecommendationKind) -> Title {
+		match kind {
+			RecommendationKind::Pass => Title::Pass,
+			RecommendationKind::UseWithConditions => Title::UseWithConditions,
+			RecommendationKind::Investigate => Title::Investigate,
+		}
+	}
+}
@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Where reports are written.
+
+use crate::{
+	error::{Context as _, Result},
+	shell::Shell,
+};
+use console::Term;
+use std::{
+	fs::File,
+	io::{BufWriter, Write},
+	path::PathBuf,
+};
+
+/// Where to write a report.
+///
+/// Progress bars and warnings always go to the terminal, so writing a report
+/// to a file leaves them visible without mixing them into the report.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Output {
+	/// The standard output.
+	#[default]
+	Stdout,
+	/// A file, which is created or replaced.
+	File(PathBuf),
+}
+
+impl Output {
+	/// Check if the output is the standard output.
+	pub fn is_stdout(&self) -> bool {
+		matches!(self, Output::Stdout)
+	}
+
+	/// Write to the output with `f`.
+	///
+	/// Progress bars are hidden while writing to the standard output.
+	pub fn write_with<F>(&self, f: F) -> Result<()>
+	where
+		F: FnOnce(&mut dyn Write) -> Result<()>,
+	{
+		match self {
+			Output::Stdout => Shell::in_suspend(|| {
+				let mut stdout = Term::stdout();
+				f(&mut stdout)?;
+				stdout.flush()?;
+				Ok(())
+			}),
+			Output::File(path) => {
+				let file = File::create(path)
+					.with_context(|| format!("failed to create '{}'", path.display()))?;
+				let mut writer = BufWriter::new(file);
+				f(&mut writer)
+					.and_then(|()| writer.flush().map_err(Into::into))
+					.with_context(|| format!("failed to write report to '{}'", path.display()))?;
+				tracing::info!("wrote report to '{}'", path.display());
+				Ok(())
+			}
+		}
+	}
+}
+
+impl From<Option<PathBuf>> for Output {
+	fn from(path: Option<PathBuf>) -> Output {
+		path.map_or(Output::Stdout, Output::File)
+	}
+}
@@ -64,7 +64,8 @@ impl ProgressPhase {
 	/// If you want a progress bar that prints progress as bytes, use [Self::start_bytes].
 	pub fn start(len: u64, name: impl Into<Arc<str>>) -> Self {
 		// Create the progress bar.
-		let bar = ProgressBar::new(len).with_style(get_unit_agnostic_style().clone());
+		let style = Shell::get_encoding().progress_style(get_unit_agnostic_style().clone());
+		let bar = ProgressBar::new(len).with_style(style);
 
 		// Add the progress bar to the shell.
 		Shell::progress_bars().add(bar.clone());
@@ -74,7 +75,7 @@ impl ProgressPhase {
 
 		// Set the bar's prefix and message.
 		bar.set_message(format!("{name} (starting...)"));
-		bar.set_prefix(ROCKET_SHIP.to_string());
+		bar.set_prefix(Shell::get_encoding().emoji(&ROCKET_SHIP).into_owned());
 
 		// Return.
 		Self { name, bar }
@@ -86,7 +87,9 @@ impl ProgressPhase {
 	/// The phase will remain in the "starting..." state until incremented.
 	pub fn start_bytes(bytes: u64, name: impl Into<Arc<str>>) -> Self {
 		let phase = Self::start(bytes, name);
-		phase.bar.set_style(get_bytes_style().clone());
+		phase
+			.bar
+			.set_style(Shell::get_encoding().progress_style(get_bytes_style().clone()));
 		// Force re-draw.
 		phase.bar.tick();
 		phase
@@ -101,7 +104,7 @@ impl ProgressPhase {
 	pub fn inc(&self, amount: u64) {
 		if self.bar.position() == 0 {
 			self.bar.set_message(format!("{} (running...)", self.name));
-			self.bar.set_prefix(HOUR_GLASS.to_string());
+			self.bar.set_prefix(Shell::get_encoding().emoji(&HOUR_GLASS).into_owned());
 		}
 
 		self.bar.inc(amount)
@@ -111,7 +114,7 @@ impl ProgressPhase {
 	pub fn set_position(&self, new_position: u64) {
 		if self.bar.position() == 0 && new_position > 0 {
 			self.bar.set_message(format!("{} (running...)", self.name));
-			self.bar.set_prefix(HOUR_GLASS.to_string());
+			self.bar.set_prefix(Shell::get_encoding().emoji(&HOUR_GLASS).into_owned());
 		}
 
 		self.bar.set_position(new_position);
@@ -121,7 +124,7 @@ impl ProgressPhase {
 	/// This status may be over-written if the bar changes states into "done" or the status is updated otherwise.
 	pub fn update_status(&self, status: impl Display) {
 		self.bar.set_message(format!("{} ({status})", self.name));
-		self.bar.set_prefix(HOUR_GLASS.to_string());
+		self.bar.set_prefix(Shell::get_encoding().emoji(&HOUR_GLASS).into_owned());
 	}
 
 	/// Finishes this bar, optionally leaving a "done" message with a timestamp in the terminal.
@@ -49,7 +49,8 @@ impl SpinnerPhase {
 				ProgressBar::with_draw_target(None, ProgressDrawTarget::hidden())
 			}
 			Verbosity::Normal => {
-				let bar = ProgressBar::new_spinner().with_style(spinner_style().clone());
+				let style = Shell::get_encoding().progress_style(spinner_style().clone());
+				let bar = ProgressBar::new_spinner().with_style(style);
 				Shell::progress_bars().add(bar.clone());
 				bar
 			}
@@ -58,7 +59,7 @@ impl SpinnerPhase {
 		let name = name.into();
 
 		// Set the initial message of the bar.
-		bar.set_prefix(ROCKET_SHIP.to_string());
+		bar.set_prefix(Shell::get_encoding().emoji(&ROCKET_SHIP).into_owned());
 		bar.set_message(format!("{name} (starting...)"));
 
 		// Return phase object.
@@ -74,7 +75,7 @@ impl SpinnerPhase {
 	pub fn inc(&self) {
 		if self.bar.position() == 0 {
 			self.bar.set_message(format!("{} (running...)", self.name));
-			self.bar.set_prefix(HOUR_GLASS.to_string());
+			self.bar.set_prefix(Shell::get_encoding().emoji(&HOUR_GLASS).into_owned());
 		}
 
 		self.bar.inc(1)
@@ -84,7 +85,12 @@ impl SpinnerPhase {
 	/// This status may be over-written if the bar changes states into "done" or the status is updated otherwise.
 	pub fn update_status(&self, status: impl Display) {
 		self.bar.set_message(format!("{} ({status})", self.name));
-		self.bar.set_prefix(HOUR_GLASS.to_string());
+		self.bar.set_prefix(Shell::get_encoding().emoji(&HOUR_GLASS).into_owned());
+	}
+
+	/// Update the status with how far along the phase is, for phases whose work reports its progress.
+	pub fn update_percent(&self, percent: u64) {
+		self.update_status(format!("{}% done", percent.min(100)));
 	}
 
 	/// Set this spinner phase to tick steadily.
@@ -12,7 +12,7 @@ pub enum Verbosity {
 	/// Output results and progress indicators.
 	#[default]
 	Normal,
-	// This one is only used in testing.
+	// This one is only used in testing, and when Hipcheck is used as a library.
 	/// Do not output anything.
 	#[value(hide = true)]
 	Silent,
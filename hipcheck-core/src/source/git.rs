@@ -0,0 +1,698 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Git related types and implementations for pulling/cloning source repos.
+
+use crate::{
+	error::{Context, Error as HcError, Result as HcResult},
+	hc_error,
+	policy::policy_file::RepoSizeAction,
+	shell::{progress_phase::ProgressPhase, verbosity::Verbosity, Shell},
+	source::get_github_owner_and_repo,
+	util::{
+		fs::FileLock,
+		git::GitCommand,
+		http::agent::agent,
+		network::{is_offline, require_network},
+	},
+};
+use console::Term;
+use git2::{
+	build::{CheckoutBuilder, RepoBuilder},
+	AnnotatedCommit, Branch, FetchOptions, Progress, Reference, RemoteCallbacks, Repository,
+};
+use serde_json::Value;
+use std::{cell::OnceCell, collections::BTreeMap, io::Write, path::Path, sync::OnceLock};
+use url::Url;
+
+/// The largest repository to clone, in bytes, and what to do with larger ones.
+static REPO_SIZE_LIMIT: OnceLock<(u64, RepoSizeAction)> = OnceLock::new();
+
+/// If set, only history after this date is cloned.
+static SHALLOW_SINCE: OnceLock<String> = OnceLock::new();
+
+/// If set, only this many commits of history are cloned.
+static CLONE_DEPTH: OnceLock<u32> = OnceLock::new();
+
+/// If set, the objects to leave out of clones, as for `git clone --filter`.
+static CLONE_FILTER: OnceLock<String> = OnceLock::new();
+
+/// If set, the subdirectory of the target repository analyses are scoped to.
+static SUBDIR: OnceLock<String> = OnceLock::new();
+
+/// Where hosts keep the heads of pull and merge requests, which clones don't
+/// fetch unless asked for.
+const CHANGE_REQUEST_REFS: &[&str] = &["refs/pull/", "refs/merge-requests/"];
+
+pub fn try_set_repo_size_limit(max_size: u64, action: RepoSizeAction) -> HcResult<()> {
+	let set_limit = REPO_SIZE_LIMIT.get_or_init(|| (max_size, action));
+	if *set_limit == (max_size, action) {
+		Ok(())
+	} else {
+		Err(hc_error!(
+			"Repository size limit could not be set to {} bytes, has already been set to {} bytes",
+			max_size,
+			set_limit.0
+		))
+	}
+}
+
+pub fn try_set_shallow_since(date: &str) -> HcResult<()> {
+	let set_date = SHALLOW_SINCE.get_or_init(|| date.to_owned());
+	if set_date == date {
+		Ok(())
+	} else {
+		Err(hc_error!(
+			"Shallow clone date could not be set to {}, has already been set to {}",
+			date,
+			set_date
+		))
+	}
+}
+
+pub fn try_set_clone_depth(depth: u32) -> HcResult<()> {
+	let set_depth = CLONE_DEPTH.get_or_init(|| depth);
+	if *set_depth == depth {
+		Ok(())
+	} else {
+		Err(hc_error!(
+			"Clone depth could not be set to {}, has already been set to {}",
+			depth,
+			set_depth
+		))
+	}
+}
+
+pub fn try_set_clone_filter(filter: &str) -> HcResult<()> {
+	let set_filter = CLONE_FILTER.get_or_init(|| filter.to_owned());
+	if set_filter == filter {
+		Ok(())
+	} else {
+		Err(hc_error!(
+			"Clone filter could not be set to {}, has already been set to {}",
+			filter,
+			set_filter
+		))
+	}
+}
+
+pub fn try_set_subdir(subdir: &str) -> HcResult<()> {
+	let subdir = normalize_subdir(subdir)?;
+	let set_subdir = SUBDIR.get_or_init(|| subdir.clone());
+	if *set_subdir == subdir {
+		Ok(())
+	} else {
+		Err(hc_error!(
+			"Subdirectory could not be set to {}, has already been set to {}",
+			subdir,
+			set_subdir
+		))
+	}
+}
+
+/// Get the subdirectory of the target repository analyses are scoped to, if any.
+pub fn subdir() -> Option<&'static str> {
+	SUBDIR.get().map(String::as_str)
+}
+
+/// Put a subdirectory in the form Git uses for paths in a repository, relative
+/// to its root and separated by forward slashes.
+fn normalize_subdir(subdir: &str) -> HcResult<String> {
+	if subdir.starts_with('/') {
+		return Err(hc_error!(
+			"subdirectory '{}' must be relative to the root of the repository",
+			subdir
+		));
+	}
+
+	let mut parts = Vec::new();
+	for part in subdir.split(['/', '\\']) {
+		match part {
+			"" | "." => continue,
+			".." => {
+				return Err(hc_error!(
+					"subdirectory '{}' can't be outside of the repository",
+					subdir
+				))
+			}
+			part => parts.push(part),
+		}
+	}
+
+	if parts.is_empty() {
+		return Err(hc_error!("subdirectory '{}' is the whole repository", subdir));
+	}
+	Ok(parts.join("/"))
+}
+
+/// Construct the remote callbacks object uesd when making callinging into [git2].
+fn make_remote_callbacks() -> RemoteCallbacks<'static> {
+	// Create progress phases for recieving the objects and resolving deltas.
+	let transfer_phase: OnceCell<ProgressPhase> = OnceCell::new();
+	let resolution_phase: OnceCell<ProgressPhase> = OnceCell::new();
+
+	// Create a struct to hold the callbacks.
+	let mut callbacks = RemoteCallbacks::new();
+
+	// Messages from the remote ("Counting objects" etc) are sent over the sideband.
+	// This involves clearing and replacing the line -- use console to do this effectively.
+
+	match Shell::get_verbosity() {
+		Verbosity::Normal => {
+			callbacks.sideband_progress(move |msg: &[u8]| {
+				Shell::in_suspend(|| {
+					// use the standard output.
+					let mut term = Term::stdout();
+
+					// Crash on errors here, since they should be relatively uncommon.
+					term.clear_line().expect("clear line on standard output");
+
+					write!(&mut term, "remote: {}", String::from_utf8_lossy(msg))
+						.expect("wrote to standard output");
+
+					term.flush().expect("flushed standard output");
+				});
+
+				true
+			});
+		}
+		Verbosity::Quiet | Verbosity::Silent => {}
+	}
+
+	callbacks.transfer_progress(move |prog: Progress| {
+		if prog.received_objects() > 0 {
+			let phase = transfer_phase.get_or_init(|| {
+				ProgressPhase::start(prog.total_objects() as u64, "(git) receiving objects")
+			});
+
+			phase.set_position(prog.received_objects() as u64);
+
+			if prog.received_objects() == prog.total_objects() && !phase.is_finished() {
+				phase.finish_successful(false);
+			}
+		}
+
+		if prog.indexed_deltas() > 0 {
+			let phase = resolution_phase.get_or_init(|| {
+				ProgressPhase::start(prog.total_deltas() as u64, "(git) resolving deltas")
+			});
+
+			phase.set_position(prog.indexed_deltas() as u64);
+
+			if prog.indexed_deltas() == prog.total_deltas() && !phase.is_finished() {
+				phase.finish_successful(false);
+			}
+		}
+
+		true
+	});
+
+	callbacks
+}
+
+/// Build fetch options for [git2] using [make_remote_callbacks].
+fn make_fetch_opts() -> FetchOptions<'static> {
+	let mut fetch_opts = FetchOptions::new();
+
+	fetch_opts
+		// Use the remote callbacks for transfer.
+		.remote_callbacks(make_remote_callbacks())
+		// Don't download any tags.
+		.download_tags(git2::AutotagOption::None);
+
+	fetch_opts
+}
+
+fn make_checkout_builder() -> CheckoutBuilder<'static> {
+	// Create a struct to hold callbacks while doing git checkout.
+	let mut checkout_opts = CheckoutBuilder::new();
+
+	// Make a phase to track the checkout progress.
+	let checkout_phase: OnceCell<ProgressPhase> = OnceCell::new();
+
+	// We don't care about the path being resolved, only the total and current numbers.
+	checkout_opts.progress(move |path, current, total| {
+		// Initialize the phase if we haven't already.
+		let phase =
+			checkout_phase.get_or_init(|| ProgressPhase::start(total as u64, "(git) checkout"));
+
+		// Set the bar to have the amount of progress in resolving.
+		phase.set_position(current as u64);
+		// Set the progress bar's status to the path being resolved.
+		phase.update_status(
+			path.map(Path::to_string_lossy)
+				.unwrap_or("resolving...".into()),
+		);
+
+		// If we have resolved everything, finish the phase.
+		if current == total {
+			phase.finish_successful(false);
+		}
+	});
+
+	checkout_opts
+}
+
+/// Clone a repo from the given url to a destination path in the filesystem.
+pub fn clone(url: &Url, dest: &Path) -> HcResult<()> {
+	tracing::debug!("remote repository cloning url is {}", url);
+	if is_offline() {
+		return Err(hc_error!(
+			"{} hasn't been cloned before, and can't be cloned with --offline; analyze a local repository or one Hipcheck has already cloned",
+			url
+		));
+	}
+
+	// Other Hipcheck runs may be cloning the same repo into the cache.
+	let _lock = FileLock::acquire(dest)?;
+
+	// A shallow or partial clone only fetches part of the repository, so the
+	// size of the whole repository doesn't apply to it.
+	let partial_args = partial_clone_args();
+	if !partial_args.is_empty() {
+		return partial_clone(url, dest, partial_args);
+	}
+
+	if let Some((max_size, action)) = REPO_SIZE_LIMIT.get() {
+		check_repo_size(url, *max_size, *action)?;
+	}
+
+	RepoBuilder::new()
+		.with_checkout(make_checkout_builder())
+		.fetch_options(make_fetch_opts())
+		.clone(url.as_str(), dest)?;
+
+	Ok(())
+}
+
+/// Get the arguments to `git clone` for the configured shallow or partial
+/// clone, if any.
+fn partial_clone_args() -> Vec<String> {
+	let mut args = Vec::new();
+
+	if let Some(since) = SHALLOW_SINCE.get() {
+		args.push(format!("--shallow-since={}", since));
+	}
+	if let Some(depth) = CLONE_DEPTH.get() {
+		args.push(format!("--depth={}", depth));
+	}
+	if let Some(filter) = CLONE_FILTER.get() {
+		args.push(format!("--filter={}", filter));
+	}
+
+	args
+}
+
+/// Clone only part of a repo, as limited by the given `git clone` arguments.
+///
+/// [git2] can't make shallow or partial clones, so this uses the git command
+/// instead.
+fn partial_clone(url: &Url, dest: &Path, partial_args: Vec<String>) -> HcResult<()> {
+	let dest = dest
+		.to_str()
+		.ok_or_else(|| hc_error!("destination isn't UTF-8 encoded '{}'", dest.display()))?;
+
+	let mut args = vec!["clone".to_owned(), "--no-tags".to_owned()];
+	args.extend(partial_args);
+	args.extend([url.to_string(), dest.to_owned()]);
+
+	GitCommand::new_repo(&args)?
+		.output()
+		.context("Git failed to make a shallow or partial clone")?;
+
+	Ok(())
+}
+
+/// Check if a clone is missing part of its history or objects.
+pub fn is_partial_clone(repo_path: &Path) -> bool {
+	let Ok(repo) = Repository::open(repo_path) else {
+		return false;
+	};
+
+	let is_promisor = repo
+		.config()
+		.and_then(|config| config.get_bool("remote.origin.promisor"))
+		.unwrap_or(false);
+
+	repo.is_shallow() || is_promisor
+}
+
+/// Fetch the history and objects missing from a shallow or partial clone.
+pub fn complete_clone(repo_path: &Path) -> HcResult<()> {
+	require_network("completing a shallow or partial clone")?;
+	let _lock = FileLock::acquire(repo_path)?;
+	let repo = Repository::open(repo_path)?;
+
+	if repo.is_shallow() {
+		GitCommand::for_repo(repo_path, ["fetch", "--no-tags", "--unshallow"])?
+			.output()
+			.context("Git failed to fetch the full history of a shallow clone")?;
+	}
+
+	// Without a filter, a refetch downloads every object, as a full clone would.
+	let mut config = repo.config()?;
+	let has_filter = config
+		.get_string("remote.origin.partialclonefilter")
+		.is_ok();
+	if has_filter {
+		config.remove("remote.origin.partialclonefilter")?;
+		GitCommand::for_repo(repo_path, ["fetch", "--no-tags", "--refetch"])?
+			.output()
+			.context("Git failed to fetch the missing objects of a partial clone")?;
+	}
+
+	Ok(())
+}
+
+/// Check the size of a remote repo against the policy file's limit before
+/// cloning it.
+///
+/// Only GitHub reports a repo's size before it's cloned, so repos on other
+/// hosts are always cloned.
+fn check_repo_size(url: &Url, max_size: u64, action: RepoSizeAction) -> HcResult<()> {
+	let size = match remote_repo_size(url) {
+		Ok(Some(size)) => size,
+		Ok(None) => {
+			tracing::debug!("size of {} is unknown before cloning", url);
+			return Ok(());
+		}
+		Err(e) => {
+			tracing::warn!("failed to get size of {} before cloning: {}", url, e);
+			return Ok(());
+		}
+	};
+
+	if size <= max_size {
+		return Ok(());
+	}
+
+	let message = format!(
+		"repository {} is {:.2} GB, over the policy file's limit of {:.2} GB. Use `--shallow-since <DATE>` to clone only recent history",
+		url,
+		size as f64 / 1e9,
+		max_size as f64 / 1e9
+	);
+	match action {
+		RepoSizeAction::Warn => {
+			Shell::eprintln(format!("warning: {}", message));
+			Ok(())
+		}
+		RepoSizeAction::Refuse => Err(hc_error!("{}", message)),
+	}
+}
+
+/// Get the size of a remote repo in bytes, if its host reports it.
+fn remote_repo_size(url: &Url) -> HcResult<Option<u64>> {
+	if url.host_str() != Some("github.com") {
+		return Ok(None);
+	}
+
+	let (owner, repo) = get_github_owner_and_repo(url)?;
+	let mut request = agent()
+		.get(&format!("https://api.github.com/repos/{}/{}", owner, repo))
+		.set("Accept", "application/vnd.github+json");
+	if let Ok(token) = std::env::var("HC_GITHUB_TOKEN") {
+		request = request.set("Authorization", &format!("Bearer {}", token));
+	}
+
+	let response: Value = request
+		.call()
+		.map_err(|e| hc_error!("{}", e))?
+		.into_json()
+		.map_err(|e| hc_error!("{}", e))?;
+
+	// GitHub reports sizes in kilobytes.
+	Ok(response
+		.get("size")
+		.and_then(Value::as_u64)
+		.map(|size| size * 1024))
+}
+
+/// For a given repo, checkout a particular ref in a detached HEAD state. If no
+/// ref is provided, instead try to resolve the most correct ref to target. If
+/// the repo has one branch, try fast-forwarding to match upstream, then set HEAD
+/// to top of branch. Else, if the repo has one remote, try to find a local branch
+/// tracking the default branch of remote and set HEAD to that. Otherwise, error.
+pub fn checkout(repo_path: &Path, refspec: Option<String>) -> HcResult<String> {
+	let _lock = FileLock::acquire(repo_path)?;
+	// Open the repo with git2.
+	let repo: Repository = Repository::open(repo_path)?;
+	// Get the repo's head.
+	let head: Reference = repo.head()?;
+	// Get the shortname for later debugging.
+	let init_short_name = head
+		.shorthand()
+		.ok_or(HcError::msg("HEAD shorthand should be UTF-8"))?;
+	let ret_str: String;
+	if let Some(refspec_str) = refspec {
+		if CHANGE_REQUEST_REFS
+			.iter()
+			.any(|prefix| refspec_str.starts_with(prefix))
+		{
+			fetch_change_request(&repo, &refspec_str)?;
+		}
+
+		// Parse refspec as an annotated commit, and set HEAD based on that
+
+		// Try refspec as given
+		let tgt_ref: AnnotatedCommit = match repo.revparse_single(&refspec_str) {
+			Ok(object) => repo.find_annotated_commit(object.peel_to_commit()?.id())?,
+			// If that refspec is not found, try it again with a leading "v"
+			Err(e) => {
+				return Err(hc_error!(
+					"Could not find repo with provided refspec: {}",
+					e
+				));
+			}
+		};
+
+		repo.set_head_detached_from_annotated(tgt_ref)?;
+		ret_str = refspec_str;
+	} else {
+		// Get names of remotes
+		let raw_remotes = repo.remotes()?;
+		let remotes = raw_remotes.into_iter().flatten().collect::<Vec<&str>>();
+		let mut local_branches = repo
+			.branches(Some(git2::BranchType::Local))?
+			.filter_map(|x| match x {
+				Ok((b, _)) => Some(b),
+				_ => None,
+			})
+			.collect::<Vec<Branch>>();
+		if local_branches.len() == 1 {
+			let mut local_branch = local_branches.remove(0);
+			// if applicable, update local_branch reference to match newest remote commit
+			if let Ok(upstr) = local_branch.upstream() {
+				let remote_ref = upstr.into_reference();
+				let target_commit = repo
+					.reference_to_annotated_commit(&remote_ref)
+					.context("Error creating annotated commit")?;
+				let reflog_msg = format!(
+					"Fast-forward {init_short_name} to id: {}",
+					target_commit.id()
+				);
+				// Set the local branch to the given commit
+				local_branch
+					.get_mut()
+					.set_target(target_commit.id(), &reflog_msg)?;
+			}
+			// Get branch name in form "refs/heads/<NAME>"
+			let tgt_ref = local_branch.get();
+			let local_name = tgt_ref.name().unwrap();
+			repo.set_head(local_name)?;
+			ret_str = tgt_ref.shorthand().unwrap_or(local_name).to_owned();
+		} else if remotes.len() == 1 {
+			// Get name of default branch for remote
+			let mut remote = repo.find_remote(remotes.first().unwrap())?;
+			remote.connect(git2::Direction::Fetch)?;
+			let default = remote.default_branch()?;
+			// Get the <NAME> in "refs/heads/<NAME>" for remote
+			let default_name = default.as_str().unwrap();
+			let (_, remote_branch_name) = default_name.rsplit_once('/').unwrap();
+			// Check if any local branches are tracking it
+			let mut opt_tgt_head: Option<&str> = None;
+			for branch in local_branches.iter() {
+				let Ok(upstr) = branch.upstream() else {
+					continue;
+				};
+				// Get the <NAME> in "refs/remote/<REMOTE>/<NAME>"
+				let upstream_name = upstr.get().name().unwrap();
+				let (_, upstream_branch_name) = upstream_name.rsplit_once('/').unwrap();
+				// If the branch names match, we have found our branch
+				if upstream_branch_name == remote_branch_name {
+					opt_tgt_head = Some(branch.get().name().unwrap());
+					break;
+				}
+			}
+			let Some(local_name) = opt_tgt_head else {
+				return Err(HcError::msg(
+					"could not find local branch tracking remote default",
+				));
+			};
+			repo.set_head(local_name)?;
+			let head_ref = repo.head()?;
+			ret_str = head_ref.shorthand().unwrap_or(local_name).to_owned();
+		} else {
+			return Err(HcError::msg(
+				"repo has multiple local branches and remotes, target is ambiguous",
+			));
+		}
+	}
+	repo.checkout_head(Some(make_checkout_builder().force()))?;
+
+	Ok(ret_str)
+}
+
+/// Fetch the head of a pull or merge request from the repo's `origin` remote,
+/// updating it if the request has changed since it was last fetched.
+///
+/// When offline, the head last fetched is used.
+fn fetch_change_request(repo: &Repository, git_ref: &str) -> HcResult<()> {
+	if is_offline() {
+		tracing::info!("offline, so using {} as it was last fetched", git_ref);
+		return Ok(());
+	}
+
+	let mut remote = repo.find_remote("origin")?;
+	remote
+		.fetch(
+			&[format!("+{0}:{0}", git_ref)],
+			Some(&mut make_fetch_opts()),
+			None,
+		)
+		.with_context(|| format!("failed to fetch {} from the target repository", git_ref))?;
+	Ok(())
+}
+
+/// Get the commit where the repo's `HEAD` branched off from a branch of its
+/// `origin` remote, or from the remote's default branch if none is given.
+///
+/// The branch is taken as it was last fetched.
+pub fn merge_base(repo_path: &Path, branch: Option<&str>) -> HcResult<String> {
+	let repo: Repository = Repository::open(repo_path)?;
+	let head = repo.head()?.peel_to_commit()?.id();
+	let branch_ref = match branch {
+		Some(branch) => format!("refs/remotes/origin/{}", branch),
+		None => "refs/remotes/origin/HEAD".to_owned(),
+	};
+	let branch = repo
+		.revparse_single(&branch_ref)
+		.with_context(|| format!("failed to find {}", branch_ref))?
+		.peel_to_commit()?
+		.id();
+	let base = repo
+		.merge_base(head, branch)
+		.with_context(|| format!("HEAD has no history in common with {}", branch_ref))?;
+	Ok(base.to_string())
+}
+
+/// Do a `git fetch` for all remotes in the repo.
+///
+/// When offline, the repo is left as it was last fetched.
+pub fn fetch(repo_path: &Path) -> HcResult<()> {
+	if is_offline() {
+		tracing::info!("offline, so using {} without fetching", repo_path.display());
+		return Ok(());
+	}
+
+	let _lock = FileLock::acquire(repo_path)?;
+	// Open the repo with git2.
+	let repo: Repository = Repository::open(repo_path)?;
+
+	// Do a general `git fetch` to get all new refs/tags
+	let remotes = repo.remotes()?;
+	for remote_name_str in remotes.into_iter().flatten() {
+		let mut remote = repo.find_remote(remote_name_str)?;
+		let refspecs = remote.fetch_refspecs()?;
+		let rs_arr = refspecs.into_iter().flatten().collect::<Vec<&str>>();
+		// Fetch the updated remote.
+		remote.fetch(rs_arr.as_slice(), Some(&mut make_fetch_opts()), None)?;
+	}
+
+	Ok(())
+}
+
+/// Get the commit `HEAD` and each ref of a repo point to, by ref name.
+///
+/// Comparing these between calls shows whether commits were made or refs
+/// were moved in the meantime.
+pub fn ref_commits(repo_path: &Path) -> HcResult<BTreeMap<String, String>> {
+	let repo: Repository = Repository::open(repo_path)?;
+	let mut commits = BTreeMap::new();
+
+	// A new repository has no commit for `HEAD` to point to yet
+	if let Ok(commit) = repo.head().and_then(|head| head.peel_to_commit()) {
+		commits.insert("HEAD".to_owned(), commit.id().to_string());
+	}
+
+	for reference in repo.references()? {
+		let reference = reference?;
+		// Refs to other objects, like notes, can't hold new commits
+		let (Some(name), Ok(commit)) = (reference.name(), reference.peel_to_commit()) else {
+			continue;
+		};
+		commits.insert(name.to_owned(), commit.id().to_string());
+	}
+
+	Ok(commits)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use git2::{Oid, Signature};
+	use tempfile::TempDir;
+
+	/// Make a commit of an empty tree with the given parents, without moving any ref.
+	fn commit(repo: &Repository, parents: &[Oid]) -> Oid {
+		let signature = Signature::now("Hipcheck", "hipcheck@example.com").unwrap();
+		let tree = repo
+			.find_tree(repo.index().unwrap().write_tree().unwrap())
+			.unwrap();
+		let parents = parents
+			.iter()
+			.map(|id| repo.find_commit(*id).unwrap())
+			.collect::<Vec<_>>();
+		repo.commit(
+			None,
+			&signature,
+			&signature,
+			"commit",
+			&tree,
+			&parents.iter().collect::<Vec<_>>(),
+		)
+		.unwrap()
+	}
+
+	#[test]
+	fn finds_where_head_branched_off() {
+		let dir = TempDir::new().unwrap();
+		let repo = Repository::init(dir.path()).unwrap();
+
+		// main:    root - merged - ahead
+		// release: root - merged - released
+		// HEAD:    root - merged - fork - change
+		let root = commit(&repo, &[]);
+		let merged = commit(&repo, &[root]);
+		let ahead = commit(&repo, &[merged]);
+		let released = commit(&repo, &[merged]);
+		let fork = commit(&repo, &[merged]);
+		let change = commit(&repo, &[fork]);
+		repo.reference("refs/remotes/origin/main", ahead, true, "")
+			.unwrap();
+		repo.reference("refs/remotes/origin/release", released, true, "")
+			.unwrap();
+		repo.reference_symbolic(
+			"refs/remotes/origin/HEAD",
+			"refs/remotes/origin/main",
+			true,
+			"",
+		)
+		.unwrap();
+		repo.set_head_detached(change).unwrap();
+
+		let merged = merged.to_string();
+		assert_eq!(merge_base(dir.path(), Some("release")).unwrap(), merged);
+		assert_eq!(merge_base(dir.path(), None).unwrap(), merged);
+		assert!(merge_base(dir.path(), Some("missing")).is_err());
+	}
+}
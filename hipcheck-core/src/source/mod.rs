@@ -36,7 +36,7 @@ pub fn try_resolve_remote_for_local(local: &Path) -> Result<RemoteGitRepo> {
 	let url = {
 		let symbolic_ref = get_symbolic_ref(local)?;
 
-		log::trace!("local source has symbolic ref [ref='{:?}']", symbolic_ref);
+		tracing::trace!("local source has symbolic ref [ref='{:?}']", symbolic_ref);
 
 		if symbolic_ref.is_empty() {
 			return Err(Error::msg("no symbolic ref found"));
@@ -44,7 +44,7 @@ pub fn try_resolve_remote_for_local(local: &Path) -> Result<RemoteGitRepo> {
 
 		let upstream = get_upstream_for_ref(local, &symbolic_ref)?;
 
-		log::trace!("local source has upstream [upstream='{:?}']", upstream);
+		tracing::trace!("local source has upstream [upstream='{:?}']", upstream);
 
 		if upstream.is_empty() {
 			return Err(Error::msg("no upstream found"));
@@ -53,7 +53,7 @@ pub fn try_resolve_remote_for_local(local: &Path) -> Result<RemoteGitRepo> {
 		let remote = get_remote_from_upstream(&upstream)
 			.ok_or_else(|| hc_error!("failed to get remote name from upstream '{}'", upstream))?;
 
-		log::trace!("local source has remote [remote='{:?}']", remote);
+		tracing::trace!("local source has remote [remote='{:?}']", remote);
 
 		if remote.is_empty() {
 			return Err(Error::msg("no remote found"));
@@ -61,7 +61,7 @@ pub fn try_resolve_remote_for_local(local: &Path) -> Result<RemoteGitRepo> {
 
 		let raw = get_url_for_remote(local, remote)?;
 
-		log::trace!("local source remote has url [url='{}']", raw);
+		tracing::trace!("local source remote has url [url='{}']", raw);
 
 		if raw.is_empty() {
 			return Err(Error::msg("no URL found for remote"));
@@ -112,6 +112,74 @@ pub fn get_github_owner_and_repo(url: &Url) -> Result<(String, String)> {
 	Ok((owner, repo))
 }
 
+/// The longest name given to a directory in the clones cache.
+///
+/// Windows limits whole paths to 260 characters by default, so clone
+/// directory names are kept well short of that to leave room for the cache
+/// root and the files inside the repository.
+const MAX_CLONE_DIR_LEN: usize = 64;
+
+/// Characters which can't appear in a file name on Windows.
+const RESERVED_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Names Windows reserves for devices, even when followed by an extension.
+const RESERVED_NAMES: &[&str] = &[
+	"CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+	"COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Turn a raw name into one which is safe to use for a directory in the clones
+/// cache on every platform.
+///
+/// Names which are already safe and short enough are kept as they are. Any
+/// other name has its reserved characters replaced, is truncated, and gets a
+/// hash of the raw name appended, so distinct raw names never share a
+/// directory. The result is always a safe name, so applying this to its own
+/// output doesn't change it.
+pub fn clone_dir_name(raw: &str) -> String {
+	let mut escaped: String = raw
+		.chars()
+		.map(|c| {
+			if c.is_control() || RESERVED_CHARS.contains(&c) {
+				'_'
+			} else {
+				c
+			}
+		})
+		.collect();
+
+	let stem = escaped.split('.').next().unwrap_or_default();
+	let is_reserved = RESERVED_NAMES
+		.iter()
+		.any(|name| name.eq_ignore_ascii_case(stem.trim_end()));
+	let is_safe = !escaped.is_empty()
+		&& escaped == raw
+		&& !is_reserved
+		&& !escaped.ends_with(['.', ' '])
+		&& escaped.len() <= MAX_CLONE_DIR_LEN;
+
+	if is_safe {
+		return escaped;
+	}
+
+	// The hash is appended after any extension, so a reserved name must be
+	// changed at the front instead.
+	if is_reserved {
+		escaped.insert(0, '_');
+	}
+
+	// 16 hex digits of hash, plus the separating dash.
+	let hash = blake3::hash(raw.as_bytes()).to_hex();
+	let hash = &hash[..16];
+	let mut prefix_len = MAX_CLONE_DIR_LEN - hash.len() - 1;
+	while !escaped.is_char_boundary(prefix_len.min(escaped.len())) {
+		prefix_len -= 1;
+	}
+	let prefix = escaped[..prefix_len.min(escaped.len())].trim_end_matches(['.', ' ']);
+
+	format!("{}-{}", prefix, hash)
+}
+
 pub fn build_unknown_remote_clone_dir(url: &Url) -> Result<String> {
 	let mut dir = String::new();
 
@@ -132,7 +200,7 @@ pub fn build_unknown_remote_clone_dir(url: &Url) -> Result<String> {
 		dir.push_str(segment);
 	}
 
-	Ok(dir)
+	Ok(clone_dir_name(&dir))
 }
 
 pub fn clone_local_repo_to_cache(src: &Path, root: &Path) -> Result<PathBuf> {
@@ -142,7 +210,11 @@ pub fn clone_local_repo_to_cache(src: &Path, root: &Path) -> Result<PathBuf> {
 	if src.starts_with(&hc_data_root) {
 		return Ok(src);
 	}
-	let dest = pathbuf![&hc_data_root, "local", src.file_name().unwrap()];
+	let name = src
+		.file_name()
+		.ok_or_else(|| hc_error!("source has no directory name '{}'", src.display()))?
+		.to_string_lossy();
+	let dest = pathbuf![&hc_data_root, "local", &clone_dir_name(&name)];
 	if dest.exists() {
 		std::fs::remove_dir_all(&dest)?;
 	}
@@ -180,3 +252,48 @@ fn get_url_for_remote(dest: &Path, remote: &str) -> Result<String> {
 
 	Ok(output.trim().to_owned())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn safe_names_are_unchanged() {
+		assert_eq!(clone_dir_name("hipcheck"), "hipcheck");
+		assert_eq!(
+			clone_dir_name("gitlab.com__owner__repo.git"),
+			"gitlab.com__owner__repo.git"
+		);
+	}
+
+	#[test]
+	fn unsafe_names_are_escaped_and_hashed() {
+		let name = clone_dir_name("example.com:8080__a?b");
+		assert!(name.starts_with("example.com_8080__a_b-"));
+		assert_ne!(name, clone_dir_name("example.com_8080__a_b"));
+
+		assert!(clone_dir_name("con").starts_with("_con-"));
+		assert!(clone_dir_name("NUL.txt").starts_with("_NUL.txt-"));
+		assert!(clone_dir_name("trailing.").starts_with("trailing-"));
+	}
+
+	#[test]
+	fn long_names_are_shortened() {
+		let long = "x".repeat(200);
+		let name = clone_dir_name(&long);
+		assert!(name.len() <= MAX_CLONE_DIR_LEN);
+		assert_ne!(name, clone_dir_name(&"x".repeat(201)));
+
+		let multibyte = "é".repeat(100);
+		assert!(clone_dir_name(&multibyte).len() <= MAX_CLONE_DIR_LEN);
+	}
+
+	#[test]
+	fn escaping_is_idempotent() {
+		let long = "y".repeat(100);
+		for raw in ["hipcheck", "a:b", "aux", "CON.a:b", "end ", long.as_str()] {
+			let name = clone_dir_name(raw);
+			assert_eq!(clone_dir_name(&name), name);
+		}
+	}
+}
@@ -28,7 +28,6 @@ static MIN_VERSIONS: Lazy<VersionMap> = Lazy::new(|| {
 	}
 
 	let mut versions = HashMap::new();
-	insert_version(&mut versions, EsLint);
 	insert_version(&mut versions, Git);
 	insert_version(&mut versions, Npm);
 	insert_version(&mut versions, ModuleDeps);
@@ -37,7 +36,6 @@ static MIN_VERSIONS: Lazy<VersionMap> = Lazy::new(|| {
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum DependentProgram {
-	EsLint,
 	Git,
 	Npm,
 	ModuleDeps,
@@ -68,9 +66,6 @@ impl DependentProgram {
 
 	fn min_version_str(&self) -> &'static str {
 		match self {
-			// https://github.com/eslint/eslint/blob/main/CHANGELOG.md
-			EsLint => "7.0.0",
-
 			// https://github.com/git/git/search?q="flag-goes-here"+in%3Afile+filename%3A*.txt+path%3ADocumentation%2FRelNotes%2F
 			Git => "2.14.0",
 
@@ -86,7 +81,6 @@ impl DependentProgram {
 impl Display for DependentProgram {
 	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
 		let name = match self {
-			EsLint => "eslint",
 			Git => "git",
 			Npm => "npm",
 			ModuleDeps => "module-deps",
@@ -100,7 +94,6 @@ fn parse_version(program: DependentProgram, version: &str) -> Result<Version> {
 	// Typical version strings, at least on MacOS:
 	//
 	// - git: `git version 2.30.1 (Apple Git-130)`
-	// - eslint: `v7.32.0`
 	// - npm: `6.14.15`
 
 	let re = Regex::new(r"(\d+\.\d+\.\d+)").context("failed to build version regex")?;
@@ -114,7 +107,7 @@ fn parse_version(program: DependentProgram, version: &str) -> Result<Version> {
 		.ok_or_else(|| hc_error!("failed to find a {} version", program))?
 		.as_str();
 
-	log::debug!("{} version detected [version='{}']", program, version);
+	tracing::debug!("{} version detected [version='{}']", program, version);
 
 	Ok(Version::parse(version)?)
 }
@@ -127,19 +120,19 @@ where
 {
 	let program = Git;
 
-	log::debug!("logging {} CLI args", program);
+	tracing::debug!("logging {} CLI args", program);
 
 	for arg in env::args() {
-		log::debug!("{} CLI environment arg [arg='{}']", program, arg);
+		tracing::debug!("{} CLI environment arg [arg='{}']", program, arg);
 	}
 
-	log::debug!("{} CLI executable location [path='{}']", program, git_path);
+	tracing::debug!("{} CLI executable location [path='{}']", program, git_path);
 
-	log::debug!("{} CLI repository location [path='{}']", program, repo_path);
+	tracing::debug!("{} CLI repository location [path='{}']", program, repo_path);
 
 	log_each_arg(args, program);
 
-	log::debug!("done logging {} CLI args", DependentProgram::Git);
+	tracing::debug!("done logging {} CLI args", DependentProgram::Git);
 }
 
 /// print command line args as well as commands and args for npm and other non git commands
@@ -148,14 +141,14 @@ where
 	I: IntoIterator<Item = S> + Copy,
 	S: AsRef<OsStr>,
 {
-	log::debug!("logging {} CLI args", &program);
+	tracing::debug!("logging {} CLI args", &program);
 
 	// https://doc.rust-lang.org/std/env/fn.args.html
 	for arg in env::args() {
-		log::debug!("{} CLI environment arg [arg='{}']", program, arg);
+		tracing::debug!("{} CLI environment arg [arg='{}']", program, arg);
 	}
 
-	log::debug!(
+	tracing::debug!(
 		"{} CLI executable location [path='{}']",
 		program,
 		command_path
@@ -163,7 +156,7 @@ where
 
 	log_each_arg(args, program);
 
-	log::debug!("done logging {} CLI args", &program);
+	tracing::debug!("done logging {} CLI args", &program);
 }
 
 pub fn log_each_arg<I, S>(args: I, program: DependentProgram)
@@ -177,7 +170,7 @@ where
 			.to_str()
 			.unwrap_or("argument for command could not be logged.");
 
-		log::debug!(
+		tracing::debug!(
 			"{} CLI argument [name='{}', value='{}']",
 			program,
 			index,
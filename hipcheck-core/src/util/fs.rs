@@ -0,0 +1,194 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+	error::{Context as _, Result},
+	hc_error,
+};
+use serde::de::DeserializeOwned;
+use std::{
+	ffi::OsString,
+	fs::{self, File, OpenOptions, TryLockError},
+	io::Write as _,
+	ops::Not,
+	path::{Path, PathBuf},
+	process,
+};
+
+/// Read a file to a string.
+pub fn read_string<P: AsRef<Path>>(path: P) -> Result<String> {
+	fn inner(path: &Path) -> Result<String> {
+		fs::read_to_string(path)
+			.with_context(|| format!("failed to read as UTF-8 string '{}'", path.display()))
+	}
+
+	inner(path.as_ref())
+}
+
+/// Read file to a byte buffer.
+pub fn read_bytes<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
+	fn inner(path: &Path) -> Result<Vec<u8>> {
+		fs::read(path).with_context(|| format!("failed to read as bytes '{}'", path.display()))
+	}
+
+	inner(path.as_ref())
+}
+
+/// Read file to a struct that can be deserialized from TOML format.
+pub fn read_toml<P: AsRef<Path>, T: DeserializeOwned>(path: P) -> Result<T> {
+	let path = path.as_ref();
+	let contents = read_string(path)?;
+	toml::de::from_str(&contents)
+		.with_context(|| format!("failed to read as TOML '{}'", path.display()))
+}
+
+/// Create a directory and missing parents.
+pub fn create_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
+	fn inner(path: &Path) -> Result<()> {
+		fs::create_dir_all(path)
+			.with_context(|| format!("failed to create directory '{}'", path.display()))
+	}
+
+	inner(path.as_ref())
+}
+
+/// Check that a given path exists.
+pub fn exists<P: AsRef<Path>>(path: P) -> Result<()> {
+	fn inner(path: &Path) -> Result<()> {
+		if path.exists().not() {
+			Err(hc_error!(
+				"'{}' not found at current directory",
+				path.display()
+			))
+		} else {
+			Ok(())
+		}
+	}
+
+	inner(path.as_ref())
+}
+
+/// return the sha256 of a file
+pub fn file_sha256<P: AsRef<Path>>(path: P) -> Result<String> {
+	let bytes = read_bytes(path)?;
+	Ok(sha256::digest(&bytes))
+}
+
+/// An advisory lock on a path in the cache, held until dropped.
+///
+/// The lock is an OS lock on a `<path>.lock` file next to the locked path, so
+/// the path itself doesn't need to exist yet, as when cloning into it. The OS
+/// releases the lock if its holder dies, so a crashed or interrupted run never
+/// leaves a path locked. Only other Hipcheck processes respect the lock.
+#[derive(Debug)]
+pub struct FileLock {
+	// Held only to keep the lock; closing the file releases it.
+	_file: File,
+}
+
+impl FileLock {
+	/// Take the lock on a path, waiting for any other holder to release it.
+	pub fn acquire<P: AsRef<Path>>(path: P) -> Result<FileLock> {
+		fn inner(path: &Path) -> Result<FileLock> {
+			let (file, lock_path) = open_lock_file(path)?;
+			match file.try_lock() {
+				Ok(()) => {}
+				Err(TryLockError::WouldBlock) => {
+					tracing::info!(
+						"waiting for another process to release '{}'",
+						lock_path.display()
+					);
+					file.lock().with_context(|| {
+						format!("failed to take lock '{}'", lock_path.display())
+					})?;
+				}
+				Err(TryLockError::Error(e)) => {
+					return Err(e)
+						.with_context(|| format!("failed to take lock '{}'", lock_path.display()))
+				}
+			}
+			Ok(FileLock::held(file))
+		}
+
+		inner(path.as_ref())
+	}
+
+	/// Take the lock on a path if no one else holds it, without waiting.
+	pub fn try_acquire<P: AsRef<Path>>(path: P) -> Result<Option<FileLock>> {
+		fn inner(path: &Path) -> Result<Option<FileLock>> {
+			let (file, lock_path) = open_lock_file(path)?;
+			match file.try_lock() {
+				Ok(()) => Ok(Some(FileLock::held(file))),
+				Err(TryLockError::WouldBlock) => Ok(None),
+				Err(TryLockError::Error(e)) => {
+					Err(e).with_context(|| format!("failed to take lock '{}'", lock_path.display()))
+				}
+			}
+		}
+
+		inner(path.as_ref())
+	}
+
+	fn held(mut file: File) -> FileLock {
+		// The PID is only to help users tell who holds the lock.
+		let _ = file.set_len(0);
+		let _ = write!(file, "{}", process::id());
+		FileLock { _file: file }
+	}
+}
+
+/// Open the lock file for a path, creating it if needed.
+///
+/// Lock files are left in place when released, since removing one could let
+/// two processes lock different files for the same path.
+fn open_lock_file(path: &Path) -> Result<(File, PathBuf)> {
+	let mut lock_name = path
+		.file_name()
+		.map(ToOwned::to_owned)
+		.unwrap_or_else(OsString::new);
+	lock_name.push(".lock");
+	let lock_path = path.with_file_name(lock_name);
+
+	if let Some(parent) = lock_path.parent() {
+		create_dir_all(parent)?;
+	}
+
+	let file = OpenOptions::new()
+		.write(true)
+		.create(true)
+		.truncate(false)
+		.open(&lock_path)
+		.with_context(|| format!("failed to create lock '{}'", lock_path.display()))?;
+	Ok((file, lock_path))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+
+	#[test]
+	fn lock_is_released_on_drop() {
+		let dir = TempDir::new().unwrap();
+		let target = dir.path().join("repo");
+
+		let lock = FileLock::acquire(&target).unwrap();
+		assert!(dir.path().join("repo.lock").exists());
+		assert!(FileLock::try_acquire(&target).unwrap().is_none());
+		drop(lock);
+
+		// It can be taken again once released.
+		assert!(FileLock::try_acquire(&target).unwrap().is_some());
+		let _lock = FileLock::acquire(&target).unwrap();
+	}
+
+	#[test]
+	fn leftover_lock_file_is_not_held() {
+		let dir = TempDir::new().unwrap();
+		let target = dir.path().join("repo");
+
+		// As left behind by a process which was killed while holding the lock.
+		fs::write(dir.path().join("repo.lock"), "12345").unwrap();
+
+		assert!(FileLock::try_acquire(&target).unwrap().is_some());
+	}
+}
@@ -0,0 +1,403 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Downloading files over HTTP(S), shared by everything Hipcheck downloads.
+//!
+//! Downloads are retried with exponential backoff when the connection fails or
+//! the server reports a temporary error, and a download to a file which was
+//! interrupted resumes from where it left off using a range request. Requests
+//! to the same host are spaced out so a batch of downloads doesn't trip the
+//! host's rate limits.
+
+use crate::{
+	error::{Context as _, Result},
+	hc_error,
+	shell::progress_phase::ProgressPhase,
+	util::{fs::create_dir_all, http::agent::agent, network::require_network},
+};
+use std::{
+	collections::HashMap,
+	fs::{self, OpenOptions},
+	io::{Read, Write},
+	path::{Path, PathBuf},
+	sync::{LazyLock, Mutex},
+	thread,
+	time::{Duration, Instant},
+};
+use url::Url;
+
+/// The most times a request is attempted before giving up.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// How long to wait before the first retry. Each retry waits twice as long.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// The longest to wait before a retry, including when a server asks for longer.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The least time between the start of two requests to the same host.
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The size of the buffer used to copy a response into a file.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// When the last request to each host was started.
+static LAST_REQUEST: LazyLock<Mutex<HashMap<String, Instant>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The checksum a downloaded file must have.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Checksum {
+	Sha256(String),
+	Blake3(String),
+}
+
+impl Checksum {
+	/// Check the contents of a file against the checksum.
+	fn verify(&self, contents: &[u8]) -> Result<()> {
+		let (expected, actual) = match self {
+			Checksum::Sha256(expected) => (expected, sha256::digest(contents)),
+			Checksum::Blake3(expected) => (expected, blake3::hash(contents).to_string()),
+		};
+
+		if !actual.eq_ignore_ascii_case(expected) {
+			return Err(hc_error!(
+				"hash mismatch, expected [{}], found [{}]",
+				expected,
+				actual
+			));
+		}
+
+		Ok(())
+	}
+}
+
+/// A file to download, and what it's expected to contain.
+#[derive(Clone, Debug)]
+pub struct Download {
+	url: Url,
+	size: Option<u64>,
+	checksum: Option<Checksum>,
+}
+
+impl Download {
+	pub fn new(url: &Url) -> Self {
+		Download {
+			url: url.clone(),
+			size: None,
+			checksum: None,
+		}
+	}
+
+	/// Require the download to be exactly this many bytes.
+	pub fn size(mut self, bytes: u64) -> Self {
+		self.size = Some(bytes);
+		self
+	}
+
+	/// Require the download to have this checksum.
+	pub fn checksum(mut self, checksum: Checksum) -> Self {
+		self.checksum = Some(checksum);
+		self
+	}
+
+	/// Download into memory.
+	pub fn to_bytes(&self) -> Result<Vec<u8>> {
+		require_network(format_args!("downloading {}", self.url))?;
+		let mut contents = Vec::new();
+
+		for attempt in 1.. {
+			contents.clear();
+			let result = self.request(None).and_then(|response| {
+				response
+					.into_reader()
+					.read_to_end(&mut contents)
+					.map_err(|e| Failure::Retry(hc_error!("{}", e), None))
+			});
+
+			match result {
+				Ok(_) => break,
+				Err(failure) => self.backoff(failure, attempt)?,
+			}
+		}
+
+		self.verify(&contents)?;
+		Ok(contents)
+	}
+
+	/// Download to a file, calling `progress` with the number of bytes
+	/// downloaded so far and the total, if it's known.
+	///
+	/// The file is written to a `.part` file next to it until the download is
+	/// complete and verified, so an interrupted download is never mistaken for
+	/// a finished one, and the next attempt can resume it.
+	pub fn to_file<F>(&self, path: &Path, mut progress: F) -> Result<()>
+	where
+		F: FnMut(u64, Option<u64>),
+	{
+		require_network(format_args!("downloading {}", self.url))?;
+		if let Some(dir) = path.parent() {
+			create_dir_all(dir)?;
+		}
+		let part = part_path(path);
+
+		for attempt in 1.. {
+			match self.download_part(&part, &mut progress) {
+				Ok(()) => break,
+				Err(failure) => self.backoff(failure, attempt)?,
+			}
+		}
+
+		let contents =
+			fs::read(&part).with_context(|| format!("failed to read '{}'", part.display()))?;
+		if let Err(e) = self.verify(&contents) {
+			// Don't resume from a bad download next time.
+			let _ = fs::remove_file(&part);
+			return Err(e);
+		}
+
+		fs::rename(&part, path).with_context(|| format!("failed to write '{}'", path.display()))?;
+		Ok(())
+	}
+
+	/// Make one attempt at downloading into the `.part` file, resuming from
+	/// whatever is already in it.
+	fn download_part<F>(&self, part: &Path, progress: &mut F) -> StdResult<(), Failure>
+	where
+		F: FnMut(u64, Option<u64>),
+	{
+		let mut downloaded = fs::metadata(part).map(|m| m.len()).unwrap_or(0);
+
+		if self.size.is_some_and(|size| size == downloaded) {
+			return Ok(());
+		}
+
+		let range = (downloaded > 0).then_some(downloaded);
+		let response = match self.request(range) {
+			// The server doesn't think there's anything left, so start over.
+			Err(Failure::Status(416, _)) => {
+				let _ = fs::remove_file(part);
+				return Err(Failure::Retry(
+					hc_error!("the server couldn't resume the download"),
+					None,
+				));
+			}
+			result => result?,
+		};
+
+		// Servers which don't support ranges send the whole file again.
+		let resumed = response.status() == 206;
+		if !resumed {
+			downloaded = 0;
+		}
+		let total = if resumed {
+			response
+				.header("Content-Range")
+				.and_then(|range| range.rsplit_once('/'))
+				.and_then(|(_, total)| total.parse().ok())
+		} else {
+			response
+				.header("Content-Length")
+				.and_then(|length| length.parse().ok())
+		}
+		.or(self.size);
+
+		let mut file = OpenOptions::new()
+			.create(true)
+			.write(true)
+			.append(resumed)
+			.truncate(!resumed)
+			.open(part)
+			.map_err(|e| Failure::Fatal(hc_error!("failed to open '{}': {}", part.display(), e)))?;
+
+		let mut reader = response.into_reader();
+		let mut buf = vec![0; CHUNK_SIZE];
+		progress(downloaded, total);
+		loop {
+			let read = reader
+				.read(&mut buf)
+				.map_err(|e| Failure::Retry(hc_error!("{}", e), None))?;
+			if read == 0 {
+				break;
+			}
+			file.write_all(&buf[..read]).map_err(|e| {
+				Failure::Fatal(hc_error!("failed to write '{}': {}", part.display(), e))
+			})?;
+			downloaded += read as u64;
+			progress(downloaded, total);
+		}
+
+		if total.is_some_and(|total| downloaded < total) {
+			return Err(Failure::Retry(
+				hc_error!("connection closed after {} bytes", downloaded),
+				None,
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Send a request for the file, optionally starting from a byte offset.
+	fn request(&self, start: Option<u64>) -> StdResult<ureq::Response, Failure> {
+		wait_for_host(&self.url);
+
+		let mut request = agent().get(self.url.as_str());
+		if let Some(start) = start {
+			tracing::debug!("resuming download of {} from byte {}", self.url, start);
+			request = request.set("Range", &format!("bytes={}-", start));
+		}
+
+		match request.call() {
+			Ok(response) => Ok(response),
+			Err(ureq::Error::Status(code, response)) => {
+				let retry_after = response
+					.header("Retry-After")
+					.and_then(|secs| secs.parse().ok())
+					.map(Duration::from_secs);
+				match code {
+					408 | 429 | 500..=599 => Err(Failure::Retry(
+						hc_error!("HTTP error code {}", code),
+						retry_after,
+					)),
+					_ => Err(Failure::Status(code, hc_error!("HTTP error code {}", code))),
+				}
+			}
+			Err(ureq::Error::Transport(transport)) => {
+				Err(Failure::Retry(hc_error!("{}", transport), None))
+			}
+		}
+	}
+
+	/// Wait before the next attempt, or give up if the failure is permanent
+	/// or there have been too many attempts.
+	fn backoff(&self, failure: Failure, attempt: u32) -> Result<()> {
+		let (error, retry_after) = match failure {
+			Failure::Retry(error, retry_after) if attempt < MAX_ATTEMPTS => (error, retry_after),
+			failure => {
+				return Err(failure
+					.into_error()
+					.context(format!("failed to download '{}'", self.url)));
+			}
+		};
+
+		let delay = retry_after
+			.unwrap_or(INITIAL_BACKOFF * 2u32.pow(attempt - 1))
+			.min(MAX_BACKOFF);
+		tracing::warn!(
+			"download of '{}' failed, retrying in {:?}: {}",
+			self.url,
+			delay,
+			error
+		);
+		thread::sleep(delay);
+		Ok(())
+	}
+
+	/// Check a download's size and checksum.
+	fn verify(&self, contents: &[u8]) -> Result<()> {
+		if let Some(size) = self.size {
+			if size != contents.len() as u64 {
+				return Err(hc_error!(
+					"File size mismatch, Expected {} B, Found {} B",
+					size,
+					contents.len()
+				));
+			}
+		}
+
+		if let Some(checksum) = &self.checksum {
+			checksum
+				.verify(contents)
+				.with_context(|| format!("'{}' failed verification", self.url))?;
+		}
+
+		Ok(())
+	}
+}
+
+type StdResult<T, E> = std::result::Result<T, E>;
+
+/// Why an attempt at a download failed.
+enum Failure {
+	/// A failure which may not happen again, like a dropped connection, and
+	/// how long the server asked to wait before trying again.
+	Retry(crate::Error, Option<Duration>),
+	/// An HTTP error which will happen again, like a missing file.
+	Status(u16, crate::Error),
+	/// A local failure, like being unable to write the file.
+	Fatal(crate::Error),
+}
+
+impl Failure {
+	fn into_error(self) -> crate::Error {
+		match self {
+			Failure::Retry(error, _) | Failure::Status(_, error) | Failure::Fatal(error) => error,
+		}
+	}
+}
+
+/// Report a download's progress in a shell progress phase.
+///
+/// The phase starts once the first bytes arrive.
+pub fn progress_phase(name: &str) -> impl FnMut(u64, Option<u64>) {
+	let name = name.to_owned();
+	let mut phase: Option<ProgressPhase> = None;
+
+	move |downloaded, total| {
+		let phase = phase
+			.get_or_insert_with(|| ProgressPhase::start_bytes(total.unwrap_or(0), name.as_str()));
+		phase.set_position(downloaded);
+
+		if total.is_some_and(|total| downloaded >= total) && !phase.is_finished() {
+			phase.finish_successful(false);
+		}
+	}
+}
+
+/// Wait until enough time has passed since the last request to a URL's host.
+fn wait_for_host(url: &Url) {
+	let host = url.host_str().unwrap_or_default().to_owned();
+
+	let wait = {
+		let mut last_request = LAST_REQUEST.lock().unwrap();
+		let now = Instant::now();
+		let start = match last_request.get(&host) {
+			Some(last) => (*last + MIN_REQUEST_INTERVAL).max(now),
+			None => now,
+		};
+		last_request.insert(host, start);
+		start - now
+	};
+
+	if !wait.is_zero() {
+		thread::sleep(wait);
+	}
+}
+
+/// The path an unfinished download to `path` is written to.
+fn part_path(path: &Path) -> PathBuf {
+	let mut file_name = path.file_name().unwrap_or_default().to_owned();
+	file_name.push(".part");
+	path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn checksums_are_case_insensitive() {
+		let digest = blake3::hash(b"hipcheck").to_string();
+		assert!(Checksum::Blake3(digest.to_uppercase())
+			.verify(b"hipcheck")
+			.is_ok());
+		assert!(Checksum::Blake3(digest).verify(b"hipcheck!").is_err());
+	}
+
+	#[test]
+	fn part_files_sit_next_to_the_download() {
+		assert_eq!(
+			part_path(Path::new("/cache/plugins/git.tar.gz")),
+			PathBuf::from("/cache/plugins/git.tar.gz.part")
+		);
+	}
+}
@@ -3,3 +3,4 @@
 //! Methods and types for making HTTP requests
 
 pub mod agent;
+pub mod download;
@@ -7,6 +7,6 @@ pub mod fs;
 pub mod git;
 pub mod http;
 pub mod kdl;
+pub mod network;
 pub mod npm;
-#[cfg(test)]
-pub mod test;
+pub mod secret;
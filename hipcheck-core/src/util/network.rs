@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Whether Hipcheck may use the network.
+//!
+//! With `hc check --offline`, anything which would reach the network fails
+//! with an error saying so, rather than trying and waiting for a timeout.
+
+use crate::{error::Result, hc_error};
+use std::{
+	fmt::Display,
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Whether network access is disabled.
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Disable or enable network access.
+pub fn set_offline(offline: bool) {
+	OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+/// Check if network access is disabled.
+pub fn is_offline() -> bool {
+	OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Fail if network access is disabled, saying what needed it.
+pub fn require_network(action: impl Display) -> Result<()> {
+	if is_offline() {
+		return Err(hc_error!(
+			"{} needs network access, which is disabled by --offline",
+			action
+		));
+	}
+	Ok(())
+}
@@ -13,7 +13,7 @@ pub fn get_version(raw_version: &str) -> Result<String> {
 	//        as `<version number> (<HEAD commit>)`
 
 	let version = Version::parse(raw_version).context("can't parse version in Cargo.toml")?;
-	log::debug!("detected Hipcheck version [version='{:?}']", version);
+	tracing::debug!("detected Hipcheck version [version='{:?}']", version);
 	Ok(raw_version.to_string())
 }
 
@@ -28,10 +28,6 @@ pub trait VersionQuery: salsa::Database {
 	#[salsa::input]
 	fn npm_version(&self) -> Rc<String>;
 
-	/// Returns the version of eslint currently running on user's machine
-	#[salsa::input]
-	fn eslint_version(&self) -> Rc<String>;
-
 	/// Returns the version of git currently running on user's machine
 	#[salsa::input]
 	fn git_version(&self) -> Rc<String>;
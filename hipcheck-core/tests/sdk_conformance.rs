@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Runs a `dummy/sha256` test plugin written with one of the plugin SDKs against Hipcheck core,
+//! to check the SDK speaks the plugin protocol as core does.
+//!
+//! The plugin to run is named by two environment variables:
+//!
+//! - `HC_SDK_TEST_PLUGIN_DIR`: the plugin's working directory, relative to the root of the
+//!   repository.
+//! - `HC_SDK_TEST_PLUGIN_ENTRYPOINT`: the command which starts the plugin, as in a plugin
+//!   manifest.
+//!
+//! These tests are ignored by default since the plugin has to be built first; see the READMEs of
+//! the test plugins for how to run them.
+
+use hipcheck_core::{
+	engine::{HcEngine, HcEngineImpl, PluginWithConfig},
+	plugin::{Plugin, ResourceLimits},
+	ExecConfig,
+};
+use serde_json::{json, Value};
+use std::{env, path::PathBuf};
+
+fn start_plugin() -> HcEngineImpl {
+	let var = |name: &str| {
+		env::var(name).unwrap_or_else(|_| panic!("set {name} to name the plugin to test"))
+	};
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+		.join("..")
+		.join(var("HC_SDK_TEST_PLUGIN_DIR"));
+	let plugin = Plugin {
+		name: "dummy/sha256".to_owned(),
+		working_dir,
+		entrypoint: var("HC_SDK_TEST_PLUGIN_ENTRYPOINT"),
+		scratch_dir: None,
+		data_dir: None,
+		secrets: vec![],
+		limits: ResourceLimits::default(),
+	};
+
+	let executor = ExecConfig::default()
+		.and_then(|config| config.get_plugin_executor())
+		.expect("default exec config is valid");
+	HcEngineImpl::new(executor, vec![PluginWithConfig(plugin, json!(null))])
+		.expect("plugin should start")
+}
+
+/// Queries the plugin's default query, returning the digest it gives as a hex string.
+fn query_sha256(engine: &HcEngineImpl, content: &[u8]) -> String {
+	let result = engine
+		.query(
+			"dummy".to_owned(),
+			"sha256".to_owned(),
+			"".to_owned(),
+			json!(content),
+		)
+		.expect("query should succeed");
+	assert_eq!(result.value.len(), 1);
+	let Value::Array(digest) = &result.value[0] else {
+		panic!("expected an array of bytes, got {}", result.value[0]);
+	};
+	digest
+		.iter()
+		.map(|byte| format!("{:02x}", byte.as_u64().expect("expected a byte")))
+		.collect()
+}
+
+#[test]
+#[ignore = "needs an SDK test plugin to be built"]
+fn sdk_plugin_answers_queries() {
+	let engine = start_plugin();
+	assert_eq!(
+		query_sha256(&engine, &[1, 2, 3]),
+		"039058c6f2c0cb492c533b0a4d14ef77cc0f78abccced5287d84a1a2011cfb81"
+	);
+	assert_eq!(
+		engine
+			.default_query_explanation("dummy".to_owned(), "sha256".to_owned())
+			.unwrap()
+			.as_deref(),
+		Some("calculate sha256 of provided array")
+	);
+}
+
+#[test]
+#[ignore = "needs an SDK test plugin to be built"]
+fn sdk_plugin_answers_queries_larger_than_a_message() {
+	let engine = start_plugin();
+	// Written as JSON, the key is about 6 MiB, so it has to be split into chunks
+	let content = vec![7u8; 3 * 1024 * 1024];
+	assert_eq!(query_sha256(&engine, &content), sha256::digest(&content));
+}
+
+#[test]
+#[ignore = "needs an SDK test plugin to be built"]
+fn identical_queries_share_one_answer() {
+	let engine = start_plugin();
+	let dispatcher = engine.dispatcher();
+	let query = |content: Value| {
+		dispatcher.query(
+			"dummy".to_owned(),
+			"sha256".to_owned(),
+			"".to_owned(),
+			content,
+		)
+	};
+
+	let first = query(json!([1, 2, 3]));
+	assert!(first.ptr_eq(&query(json!([1, 2, 3]))));
+	assert!(!first.ptr_eq(&query(json!([4, 5, 6]))));
+
+	// Once answered, the query is still shared rather than asked again
+	HcEngineImpl::runtime()
+		.block_on(first.clone())
+		.expect("query should succeed");
+	assert!(first.ptr_eq(&query(json!([1, 2, 3]))));
+	assert_eq!(
+		query_sha256(&engine, &[1, 2, 3]),
+		"039058c6f2c0cb492c533b0a4d14ef77cc0f78abccced5287d84a1a2011cfb81"
+	);
+}
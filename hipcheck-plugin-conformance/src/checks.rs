@@ -0,0 +1,518 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The checks a plugin is put through, in the order Hipcheck core talks to plugins.
+
+use crate::{
+	plugin::{PluginClient, PluginProcess, Session},
+	report::Report,
+};
+use anyhow::{anyhow, bail, Context as _, Result};
+use hipcheck_common::{
+	chunk::{chunk_with_size, GRPC_MIN_SIZE_BYTES},
+	error::Error as QueryError,
+	proto::{
+		ConfigurationStatus, ExplainDefaultQueryRequest, GetDefaultPolicyExpressionRequest,
+		GetQuerySchemasRequest, Query as PluginQuery, QueryState, SetConfigurationRequest,
+	},
+	types::Query,
+};
+use serde_json::Value;
+use std::{collections::HashSet, future::Future, result::Result as StdResult, time::Duration};
+
+/// The max message size the checks negotiate with the plugin, which is the smallest allowed so
+/// that the plugin's chunking is exercised by smaller keys and outputs.
+const MAX_MESSAGE_SIZE: usize = GRPC_MIN_SIZE_BYTES;
+
+/// Room left in each message for the fields which aren't chunked, as Hipcheck core leaves.
+const MESSAGE_OVERHEAD: usize = 1024;
+
+/// The size keys are chunked to when checking the plugin puts chunks back together, which is
+/// small enough to split almost any key but fits any single character.
+const SMALL_CHUNK_SIZE: usize = 16;
+
+/// The checks of the plugin's configuration and metadata.
+const CONFIG_CHECKS: [&str; 5] = [
+	"query-schemas",
+	"malformed-config",
+	"configuration",
+	"default-policy-expression",
+	"default-query-explanation",
+];
+
+/// The checks which send queries, which are skipped without a key to send.
+const QUERY_CHECKS: [&str; 7] = [
+	"query",
+	"chunked-query",
+	"concurrent-sessions",
+	"malformed-key",
+	"unexpected-reply",
+	"dropped-session",
+	"cancelled-query",
+];
+
+/// What to check the plugin with.
+pub struct Config {
+	/// The command which starts the plugin.
+	pub entrypoint: Vec<String>,
+	pub publisher: String,
+	pub plugin: String,
+	/// The configuration to give the plugin.
+	pub config: Value,
+	/// The name of the query to send, which is empty for the default query.
+	pub query: String,
+	/// A key the query accepts, if any.
+	pub key: Option<Value>,
+	/// How long to wait for the plugin to start, and for each check.
+	pub timeout: Duration,
+	pub show_logs: bool,
+}
+
+/// Run every check against the plugin.
+pub async fn run(config: &Config) -> Report {
+	let mut report = Report::default();
+
+	let started = PluginProcess::start(&config.entrypoint, config.show_logs, config.timeout).await;
+	let mut plugin = match started {
+		Ok(plugin) => {
+			report.record(
+				"handshake",
+				Ok("plugin started and accepted a connection".into()),
+			);
+			plugin
+		}
+		Err(e) => {
+			report.record("handshake", Err(e));
+			for name in CONFIG_CHECKS
+				.into_iter()
+				.chain(QUERY_CHECKS)
+				.chain(["still-running"])
+			{
+				report.skip(name, "the plugin didn't start");
+			}
+			return report;
+		}
+	};
+
+	let client = &mut plugin.client;
+	check(
+		&mut report,
+		"query-schemas",
+		config,
+		query_schemas(client, config),
+	)
+	.await;
+	check(
+		&mut report,
+		"malformed-config",
+		config,
+		malformed_config(client),
+	)
+	.await;
+	check(
+		&mut report,
+		"configuration",
+		config,
+		configuration(client, config),
+	)
+	.await;
+	check(
+		&mut report,
+		"default-policy-expression",
+		config,
+		default_policy_expression(client),
+	)
+	.await;
+	check(
+		&mut report,
+		"default-query-explanation",
+		config,
+		default_query_explanation(client),
+	)
+	.await;
+
+	match &config.key {
+		Some(key) => {
+			let key = key.to_string();
+			let expected = check(&mut report, "query", config, query(client, config, &key)).await;
+			let expected = expected.as_ref();
+			check(
+				&mut report,
+				"chunked-query",
+				config,
+				chunked_query(client, config, &key, expected),
+			)
+			.await;
+			check(
+				&mut report,
+				"concurrent-sessions",
+				config,
+				concurrent_sessions(client, config, &key, expected),
+			)
+			.await;
+			check(
+				&mut report,
+				"malformed-key",
+				config,
+				malformed_key(client, config),
+			)
+			.await;
+			check(
+				&mut report,
+				"unexpected-reply",
+				config,
+				unexpected_reply(client, config, &key),
+			)
+			.await;
+			check(
+				&mut report,
+				"dropped-session",
+				config,
+				dropped_session(client, config, &key),
+			)
+			.await;
+			check(
+				&mut report,
+				"cancelled-query",
+				config,
+				cancelled_query(client, config, &key),
+			)
+			.await;
+		}
+		None => {
+			for name in QUERY_CHECKS {
+				report.skip(name, "no query key given with --key");
+			}
+		}
+	}
+
+	let still_running = match plugin.exit_status() {
+		Some(status) => Err(anyhow!("plugin exited with {status} during the checks")),
+		None => Ok("plugin is still running after the checks".to_owned()),
+	};
+	report.record("still-running", still_running);
+
+	report
+}
+
+/// Run a check, failing it if the plugin takes too long, and record how it went. Returns the
+/// output of a query check which passed, for later checks to compare against.
+async fn check<F>(
+	report: &mut Report,
+	name: &'static str,
+	config: &Config,
+	check: F,
+) -> Option<Value>
+where
+	F: Future<Output = Result<Checked>>,
+{
+	let result = match tokio::time::timeout(config.timeout, check).await {
+		Ok(result) => result,
+		Err(_) => Err(anyhow!(
+			"no answer from the plugin within {}s",
+			config.timeout.as_secs()
+		)),
+	};
+	match result {
+		Ok(checked) => {
+			report.record(name, Ok(checked.message));
+			checked.output
+		}
+		Err(e) => {
+			report.record(name, Err(e));
+			None
+		}
+	}
+}
+
+/// What a check which passed found.
+struct Checked {
+	message: String,
+	/// The output of the query, for checks which send one.
+	output: Option<Value>,
+}
+
+impl From<String> for Checked {
+	fn from(message: String) -> Checked {
+		Checked {
+			message,
+			output: None,
+		}
+	}
+}
+
+impl From<&str> for Checked {
+	fn from(message: &str) -> Checked {
+		message.into()
+	}
+}
+
+async fn query_schemas(client: &mut PluginClient, config: &Config) -> Result<Checked> {
+	let mut stream = client
+		.get_query_schemas(GetQuerySchemasRequest { empty: None })
+		.await?
+		.into_inner();
+
+	let mut names = HashSet::new();
+	while let Some(schema) = stream.message().await? {
+		let name = schema.query_name;
+		let display = display_query(&name);
+		serde_json::from_str::<Value>(&schema.key_schema)
+			.with_context(|| format!("key schema of {display} isn't JSON"))?;
+		serde_json::from_str::<Value>(&schema.output_schema)
+			.with_context(|| format!("output schema of {display} isn't JSON"))?;
+		if !names.insert(name) {
+			bail!("plugin sent two schemas for {display}");
+		}
+	}
+
+	if !names.contains(&config.query) {
+		bail!(
+			"plugin sent no schema for {}, which is checked",
+			display_query(&config.query)
+		);
+	}
+	let default = if names.contains("") {
+		"including a default query"
+	} else {
+		"with no default query, so it can't be used at the top level of a policy"
+	};
+	Ok(format!("plugin has {} queries, {default}", names.len()).into())
+}
+
+async fn malformed_config(client: &mut PluginClient) -> Result<Checked> {
+	let response = client
+		.set_configuration(SetConfigurationRequest {
+			configuration: "{not json".to_owned(),
+			max_message_size: MAX_MESSAGE_SIZE as u64,
+		})
+		.await;
+	// Either failing the call or sending back an error status tells core the config is wrong
+	match response {
+		Err(status) => Ok(format!("plugin rejected it with status '{}'", status.code()).into()),
+		Ok(response) if response.get_ref().status() != ConfigurationStatus::None => {
+			Ok(format!("plugin rejected it: {}", response.get_ref().message).into())
+		}
+		Ok(_) => bail!("plugin accepted a configuration which isn't JSON"),
+	}
+}
+
+async fn configuration(client: &mut PluginClient, config: &Config) -> Result<Checked> {
+	let response = client
+		.set_configuration(SetConfigurationRequest {
+			configuration: config.config.to_string(),
+			max_message_size: MAX_MESSAGE_SIZE as u64,
+		})
+		.await?
+		.into_inner();
+	if response.status() != ConfigurationStatus::None {
+		bail!(
+			"plugin rejected the configuration with status '{}': {}",
+			response.status().as_str_name(),
+			response.message
+		);
+	}
+	// Zero is sent by plugins which predate negotiation, which core treats as the default size
+	let theirs = response.max_message_size;
+	if theirs != 0 && theirs < GRPC_MIN_SIZE_BYTES as u64 {
+		bail!(
+			"plugin sent a max message size of {theirs} bytes, under the minimum of {GRPC_MIN_SIZE_BYTES}"
+		);
+	}
+	Ok(
+		format!("plugin accepted the configuration, with a max message size of {theirs} bytes")
+			.into(),
+	)
+}
+
+async fn default_policy_expression(client: &mut PluginClient) -> Result<Checked> {
+	let response = client
+		.get_default_policy_expression(GetDefaultPolicyExpressionRequest { empty: None })
+		.await?
+		.into_inner();
+	match response.policy_expression.as_str() {
+		"" => Ok("plugin has no default policy expression".into()),
+		expr => Ok(format!("plugin sent '{expr}'").into()),
+	}
+}
+
+async fn default_query_explanation(client: &mut PluginClient) -> Result<Checked> {
+	let response = client
+		.explain_default_query(ExplainDefaultQueryRequest { empty: None })
+		.await?
+		.into_inner();
+	Ok(format!("plugin sent '{}'", response.explanation).into())
+}
+
+/// Make a query for the checked query endpoint.
+fn submit(config: &Config, id: i32, key: &str) -> PluginQuery {
+	PluginQuery {
+		id,
+		state: QueryState::SubmitComplete as i32,
+		publisher_name: config.publisher.clone(),
+		plugin_name: config.plugin.clone(),
+		query_name: config.query.clone(),
+		key: vec![key.to_owned()],
+		output: vec![],
+		concern: vec![],
+		split: false,
+		fragment: vec![],
+		error: String::new(),
+		progress: None,
+		cancel: false,
+	}
+}
+
+/// Ask the plugin a query, chunked as Hipcheck core would, returning its single output.
+async fn ask(session: &mut Session, config: &Config, id: i32, key: &str) -> Result<Value> {
+	session
+		.send(chunk_with_size(
+			submit(config, id, key),
+			MAX_MESSAGE_SIZE - MESSAGE_OVERHEAD,
+		)?)
+		.await?;
+	single_output(session.answer(id).await?)
+}
+
+fn single_output(answer: StdResult<Query, QueryError>) -> Result<Value> {
+	let mut answer = answer.context("plugin failed to answer the query")?;
+	if answer.output.len() != 1 {
+		bail!(
+			"plugin answered with {} outputs rather than one",
+			answer.output.len()
+		);
+	}
+	Ok(answer.output.remove(0))
+}
+
+fn check_output(output: &Value, expected: Option<&Value>) -> Result<()> {
+	match expected {
+		Some(expected) if output != expected => {
+			bail!("plugin answered {output}, but answered {expected} when the key wasn't chunked")
+		}
+		_ => Ok(()),
+	}
+}
+
+async fn query(client: &mut PluginClient, config: &Config, key: &str) -> Result<Checked> {
+	let mut session = Session::open(client, MAX_MESSAGE_SIZE).await?;
+	let output = ask(&mut session, config, 1, key).await?;
+	Ok(Checked {
+		message: format!("plugin answered {}", truncate(&output.to_string())),
+		output: Some(output),
+	})
+}
+
+async fn chunked_query(
+	client: &mut PluginClient,
+	config: &Config,
+	key: &str,
+	expected: Option<&Value>,
+) -> Result<Checked> {
+	let mut session = Session::open(client, MAX_MESSAGE_SIZE).await?;
+	let chunks = chunk_with_size(submit(config, 1, key), SMALL_CHUNK_SIZE)?;
+	let count = chunks.len();
+	session.send(chunks).await?;
+
+	let output = single_output(session.answer(1).await?)?;
+	check_output(&output, expected)?;
+	Ok(format!("plugin put a key sent in {count} chunks back together").into())
+}
+
+async fn concurrent_sessions(
+	client: &mut PluginClient,
+	config: &Config,
+	key: &str,
+	expected: Option<&Value>,
+) -> Result<Checked> {
+	let mut session = Session::open(client, MAX_MESSAGE_SIZE).await?;
+	let first = chunk_with_size(submit(config, 2, key), SMALL_CHUNK_SIZE)?;
+	let second = chunk_with_size(submit(config, 3, key), SMALL_CHUNK_SIZE)?;
+
+	// Interleave the chunks of the two queries, as core does when queries overlap
+	let mut first = first.into_iter();
+	let mut second = second.into_iter();
+	loop {
+		let chunks: Vec<_> = first.next().into_iter().chain(second.next()).collect();
+		if chunks.is_empty() {
+			break;
+		}
+		session.send(chunks).await?;
+	}
+
+	let mut answers = session.answers(&[2, 3]).await?;
+	for id in [2, 3] {
+		let output =
+			single_output(answers.remove(&id).unwrap()).with_context(|| format!("query {id}"))?;
+		check_output(&output, expected).with_context(|| format!("query {id}"))?;
+	}
+	Ok("plugin answered two queries sent at once".into())
+}
+
+async fn malformed_key(client: &mut PluginClient, config: &Config) -> Result<Checked> {
+	let mut session = Session::open(client, MAX_MESSAGE_SIZE).await?;
+	session.send([submit(config, 4, "{not json")]).await?;
+	match session.answer(4).await? {
+		Err(e) => Ok(format!("plugin said the query failed: {e}").into()),
+		Ok(_) => bail!("plugin answered a query whose key isn't JSON"),
+	}
+}
+
+async fn unexpected_reply(
+	client: &mut PluginClient,
+	config: &Config,
+	key: &str,
+) -> Result<Checked> {
+	let mut session = Session::open(client, MAX_MESSAGE_SIZE).await?;
+	// A reply to a query the plugin never made
+	session
+		.send([PluginQuery {
+			state: QueryState::ReplyComplete as i32,
+			key: vec![],
+			output: vec!["null".to_owned()],
+			..submit(config, 5, key)
+		}])
+		.await?;
+	ask(&mut session, config, 6, key)
+		.await
+		.context("plugin stopped answering after an unexpected reply")?;
+	Ok("plugin ignored a reply to a query it never made".into())
+}
+
+async fn dropped_session(client: &mut PluginClient, config: &Config, key: &str) -> Result<Checked> {
+	{
+		// Stop listening mid-query, as core does when a run is stopped
+		let session = Session::open(client, MAX_MESSAGE_SIZE).await?;
+		session.send([submit(config, 7, key)]).await?;
+	}
+	let mut session = Session::open(client, MAX_MESSAGE_SIZE).await?;
+	ask(&mut session, config, 7, key)
+		.await
+		.context("plugin stopped answering after a session was dropped mid-query")?;
+	Ok("plugin kept answering after a session was dropped mid-query".into())
+}
+
+async fn cancelled_query(client: &mut PluginClient, config: &Config, key: &str) -> Result<Checked> {
+	let mut session = Session::open(client, MAX_MESSAGE_SIZE).await?;
+	session.send([submit(config, 9, key)]).await?;
+	session.cancel(9).await?;
+	ask(&mut session, config, 11, key)
+		.await
+		.context("plugin stopped answering after a query was cancelled")?;
+	Ok("plugin kept answering after a query was cancelled".into())
+}
+
+fn display_query(name: &str) -> String {
+	match name {
+		"" => "the default query".to_owned(),
+		name => format!("query '{name}'"),
+	}
+}
+
+/// Shorten a long output for the report.
+fn truncate(text: &str) -> String {
+	const MAX_CHARS: usize = 60;
+	match text.char_indices().nth(MAX_CHARS) {
+		Some((end, _)) => format!("{}...", &text[..end]),
+		None => text.to_owned(),
+	}
+}
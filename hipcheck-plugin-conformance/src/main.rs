@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks that a Hipcheck plugin follows the Hipcheck plugin protocol, for authors of plugin SDKs
+//! and of plugins written without one.
+//!
+//! The plugin is started and spoken to as Hipcheck core would, and put through a series of
+//! checks covering the handshake, schema retrieval, configuration errors, chunked messages,
+//! concurrent and dropped sessions, cancelled queries, and malformed input. Each check passes or
+//! fails with what was found, and the process exits with an error if any failed.
+
+mod checks;
+mod plugin;
+mod report;
+
+use crate::checks::Config;
+use anyhow::{anyhow, Context as _, Result};
+use clap::Parser as _;
+use std::{process::ExitCode, time::Duration};
+
+/// Check that a Hipcheck plugin follows the Hipcheck plugin protocol.
+#[derive(Debug, clap::Parser)]
+#[clap(about, version, long_about = None)]
+struct Args {
+	/// The plugin's name, as "<PUBLISHER>/<NAME>"
+	#[arg(long = "plugin")]
+	plugin: String,
+
+	/// The configuration to give the plugin, as JSON
+	#[arg(long = "config", default_value = "null")]
+	config: String,
+
+	/// The query endpoint to send queries to; the default query if not given
+	#[arg(long = "query", default_value = "")]
+	query: String,
+
+	/// A key the query accepts, as JSON; the checks which send queries are skipped without one
+	#[arg(long = "key")]
+	key: Option<String>,
+
+	/// How long to wait for the plugin to start, and for each check, in seconds
+	#[arg(long = "timeout", default_value_t = 10)]
+	timeout: u64,
+
+	/// Show the plugin's log output
+	#[arg(long = "plugin-logs")]
+	plugin_logs: bool,
+
+	/// Print the report as JSON
+	#[arg(long = "json")]
+	json: bool,
+
+	/// The command which starts the plugin, as in the entrypoint of its manifest
+	#[arg(required = true, last = true)]
+	entrypoint: Vec<String>,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+	let args = Args::parse();
+
+	match run(args).await {
+		Ok(true) => ExitCode::SUCCESS,
+		Ok(false) => ExitCode::FAILURE,
+		Err(e) => {
+			eprintln!("error: {e:#}");
+			ExitCode::FAILURE
+		}
+	}
+}
+
+/// Run the checks and print the report, returning whether they all passed.
+async fn run(args: Args) -> Result<bool> {
+	let (publisher, plugin) = args.plugin.split_once('/').ok_or_else(|| {
+		anyhow!(
+			"plugin name '{}' isn't of the form <PUBLISHER>/<NAME>",
+			args.plugin
+		)
+	})?;
+	let config = Config {
+		entrypoint: args.entrypoint,
+		publisher: publisher.to_owned(),
+		plugin: plugin.to_owned(),
+		config: serde_json::from_str(&args.config).context("--config isn't JSON")?,
+		query: args.query,
+		key: args
+			.key
+			.map(|key| serde_json::from_str(&key))
+			.transpose()
+			.context("--key isn't JSON")?,
+		timeout: Duration::from_secs(args.timeout),
+		show_logs: args.plugin_logs,
+	};
+
+	let report = checks::run(&config).await;
+	if args.json {
+		println!("{}", report.to_json()?);
+	} else {
+		println!("{report}");
+	}
+	Ok(report.passed())
+}
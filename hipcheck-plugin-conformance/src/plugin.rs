@@ -0,0 +1,251 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Starting the plugin under test, and speaking the query protocol to it as Hipcheck core does.
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use hipcheck_common::{
+	chunk::QuerySynthesizer,
+	error::Error,
+	plugin_log::LOG_LEVEL_ENV_VAR,
+	proto::{
+		plugin_service_client::PluginServiceClient, InitiateQueryProtocolRequest,
+		InitiateQueryProtocolResponse, Query as PluginQuery, QueryState,
+	},
+	types::Query,
+};
+use prost::Message as _;
+use std::{
+	collections::{HashMap, HashSet},
+	net::TcpListener,
+	process::{Child, Command, ExitStatus, Stdio},
+	result::Result as StdResult,
+	time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Channel, Streaming};
+
+/// The largest message the plugin is allowed to send before configuration limits it, which is
+/// far beyond what any plugin should send, so oversized messages can be reported rather than
+/// failing to decode.
+const MAX_DECODING_SIZE: usize = 1024 * 1024 * 64;
+
+pub type PluginClient = PluginServiceClient<Channel>;
+
+/// The plugin under test, which is killed when dropped.
+pub struct PluginProcess {
+	child: Child,
+	pub client: PluginClient,
+}
+
+impl PluginProcess {
+	/// Start the plugin, passing it a port to listen on with `--port` as Hipcheck core does, and
+	/// wait until it accepts a connection.
+	pub async fn start(
+		entrypoint: &[String],
+		show_logs: bool,
+		timeout: Duration,
+	) -> Result<PluginProcess> {
+		let (program, args) = entrypoint
+			.split_first()
+			.ok_or_else(|| anyhow!("no plugin entrypoint given"))?;
+
+		let port = TcpListener::bind("127.0.0.1:0")
+			.and_then(|listener| listener.local_addr())
+			.context("failed to find a free port for the plugin")?
+			.port();
+
+		let mut cmd = Command::new(program);
+		cmd.args(args)
+			.arg("--port")
+			.arg(port.to_string())
+			.stdin(Stdio::null())
+			.stdout(Stdio::null());
+		if show_logs {
+			cmd.stderr(Stdio::inherit()).env(LOG_LEVEL_ENV_VAR, "INFO");
+		} else {
+			cmd.stderr(Stdio::null()).env(LOG_LEVEL_ENV_VAR, "OFF");
+		}
+		let mut child = cmd
+			.spawn()
+			.with_context(|| format!("failed to start plugin '{program}'"))?;
+
+		match connect(&mut child, port, timeout).await {
+			Ok(client) => Ok(PluginProcess { child, client }),
+			Err(e) => {
+				let _ = child.kill();
+				let _ = child.wait();
+				Err(e)
+			}
+		}
+	}
+
+	/// How the plugin exited, if it has.
+	pub fn exit_status(&mut self) -> Option<ExitStatus> {
+		self.child.try_wait().ok().flatten()
+	}
+}
+
+/// Connect to the plugin once it's listening, as long as it hasn't exited.
+async fn connect(child: &mut Child, port: u16, timeout: Duration) -> Result<PluginClient> {
+	let deadline = Instant::now() + timeout;
+	loop {
+		if let Some(status) = child.try_wait()? {
+			bail!("plugin exited with {status} before accepting a connection");
+		}
+		if let Ok(client) = PluginServiceClient::connect(format!("http://127.0.0.1:{port}")).await {
+			return Ok(client.max_decoding_message_size(MAX_DECODING_SIZE));
+		}
+		if Instant::now() > deadline {
+			bail!(
+				"plugin didn't accept a connection on port {port} within {}s",
+				timeout.as_secs()
+			);
+		}
+		tokio::time::sleep(Duration::from_millis(100)).await;
+	}
+}
+
+impl Drop for PluginProcess {
+	fn drop(&mut self) {
+		let _ = self.child.kill();
+		let _ = self.child.wait();
+	}
+}
+
+/// An open `InitiateQueryProtocol` stream to the plugin.
+pub struct Session {
+	tx: mpsc::Sender<InitiateQueryProtocolRequest>,
+	rx: Streaming<InitiateQueryProtocolResponse>,
+	max_message_size: usize,
+	/// The IDs of queries cancelled on this stream, whose messages are ignored.
+	cancelled: HashSet<i32>,
+}
+
+impl Session {
+	/// Open the query protocol, under which the plugin may send messages of at most
+	/// `max_message_size` bytes.
+	pub async fn open(client: &mut PluginClient, max_message_size: usize) -> Result<Session> {
+		let (tx, rx) = mpsc::channel(10);
+		let response = client
+			.initiate_query_protocol(ReceiverStream::new(rx))
+			.await
+			.context("plugin didn't open the query protocol")?;
+		Ok(Session {
+			tx,
+			rx: response.into_inner(),
+			max_message_size,
+			cancelled: HashSet::new(),
+		})
+	}
+
+	/// Send the chunks of a message to the plugin.
+	pub async fn send(&self, chunks: impl IntoIterator<Item = PluginQuery>) -> Result<()> {
+		for query in chunks {
+			self.tx
+				.send(InitiateQueryProtocolRequest { query: Some(query) })
+				.await
+				.map_err(|_| anyhow!("plugin closed the query protocol stream"))?;
+		}
+		Ok(())
+	}
+
+	/// Cancel a query, as Hipcheck core does when it no longer needs the answer. Anything the
+	/// plugin sends about the query afterwards is ignored.
+	pub async fn cancel(&mut self, id: i32) -> Result<()> {
+		self.cancelled.insert(id);
+		self.send([PluginQuery {
+			id,
+			state: QueryState::Unspecified as i32,
+			publisher_name: String::new(),
+			plugin_name: String::new(),
+			query_name: String::new(),
+			key: vec![],
+			output: vec![],
+			concern: vec![],
+			split: false,
+			fragment: vec![],
+			error: String::new(),
+			progress: None,
+			cancel: true,
+		}])
+		.await
+	}
+
+	/// Receive the plugin's answers to the queries with the given IDs, in whatever order the
+	/// plugin sends them.
+	///
+	/// An answer is an error if the plugin said the query failed. Anything Hipcheck core wouldn't
+	/// accept, like a message larger than the negotiated size or for a query that wasn't asked,
+	/// fails the whole exchange.
+	pub async fn answers(&mut self, ids: &[i32]) -> Result<HashMap<i32, StdResult<Query, Error>>> {
+		let mut pending: HashMap<i32, Vec<PluginQuery>> = HashMap::new();
+		let mut answers = HashMap::new();
+
+		while answers.len() < ids.len() {
+			let Some(response) = self
+				.rx
+				.message()
+				.await
+				.context("query protocol stream failed")?
+			else {
+				bail!("plugin closed the query protocol stream before answering");
+			};
+			let Some(query) = response.query else {
+				bail!("plugin sent a message with no query in it");
+			};
+
+			let size = query.encoded_len();
+			if size > self.max_message_size {
+				bail!(
+					"plugin sent a {size} byte message, over the negotiated max of {} bytes",
+					self.max_message_size
+				);
+			}
+			let id = query.id;
+			if self.cancelled.contains(&id) {
+				continue;
+			}
+			if !ids.contains(&id) || answers.contains_key(&id) {
+				bail!("plugin sent a message for query {id}, which it wasn't answering");
+			}
+
+			match query.state() {
+				QueryState::SubmitInProgress | QueryState::SubmitComplete => bail!(
+					"plugin queried '{}/{}/{}'; choose a query which doesn't need other plugins",
+					query.publisher_name,
+					query.plugin_name,
+					query.query_name
+				),
+				// Progress updates are sent in the unspecified state, and don't answer the query
+				QueryState::Unspecified if query.error.is_empty() && query.progress.is_some() => {
+					continue
+				}
+				QueryState::ReplyInProgress => {
+					pending.entry(id).or_default().push(query);
+					continue;
+				}
+				QueryState::Unspecified | QueryState::ReplyComplete => (),
+			}
+
+			let mut chunks = pending.remove(&id).unwrap_or_default();
+			chunks.push(query);
+			let answer = match QuerySynthesizer::with_max_message_size(self.max_message_size)
+				.add(chunks.into_iter())
+			{
+				Ok(Some(answer)) => Ok(answer),
+				Ok(None) => bail!("plugin's answer to query {id} was incomplete"),
+				Err(e) => Err(e),
+			};
+			answers.insert(id, answer);
+		}
+
+		Ok(answers)
+	}
+
+	/// Receive the plugin's answer to a single query.
+	pub async fn answer(&mut self, id: i32) -> Result<StdResult<Query, Error>> {
+		let mut answers = self.answers(&[id]).await?;
+		Ok(answers.remove(&id).unwrap())
+	}
+}
@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The pass/fail report of a conformance run.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+
+/// How a single check went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+	Pass,
+	Fail,
+	/// The check couldn't be run, e.g. because no query key was given.
+	Skip,
+}
+
+impl Display for Outcome {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Outcome::Pass => write!(f, "PASS"),
+			Outcome::Fail => write!(f, "FAIL"),
+			Outcome::Skip => write!(f, "SKIP"),
+		}
+	}
+}
+
+/// The result of a single check, with what was found or why it failed.
+#[derive(Debug, Serialize)]
+pub struct CheckResult {
+	pub name: &'static str,
+	pub outcome: Outcome,
+	pub message: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+	checks: Vec<CheckResult>,
+}
+
+impl Report {
+	/// Record the result of a check, which passed if it returned a description of what it found.
+	pub fn record(&mut self, name: &'static str, result: Result<String>) {
+		let (outcome, message) = match result {
+			Ok(message) => (Outcome::Pass, message),
+			Err(e) => (Outcome::Fail, format!("{e:#}")),
+		};
+		self.checks.push(CheckResult {
+			name,
+			outcome,
+			message,
+		});
+	}
+
+	/// Record that a check wasn't run, and why.
+	pub fn skip(&mut self, name: &'static str, reason: &str) {
+		self.checks.push(CheckResult {
+			name,
+			outcome: Outcome::Skip,
+			message: reason.to_owned(),
+		});
+	}
+
+	/// Whether no check failed.
+	pub fn passed(&self) -> bool {
+		self.count(Outcome::Fail) == 0
+	}
+
+	fn count(&self, outcome: Outcome) -> usize {
+		self.checks.iter().filter(|c| c.outcome == outcome).count()
+	}
+
+	pub fn to_json(&self) -> Result<String> {
+		#[derive(Serialize)]
+		struct JsonReport<'r> {
+			passed: bool,
+			checks: &'r [CheckResult],
+		}
+
+		Ok(serde_json::to_string_pretty(&JsonReport {
+			passed: self.passed(),
+			checks: &self.checks,
+		})?)
+	}
+}
+
+impl Display for Report {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let width = self.checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+		for check in &self.checks {
+			writeln!(
+				f,
+				"{}  {:width$}  {}",
+				check.outcome, check.name, check.message
+			)?;
+		}
+		write!(
+			f,
+			"\n{} passed, {} failed, {} skipped",
+			self.count(Outcome::Pass),
+			self.count(Outcome::Fail),
+			self.count(Outcome::Skip)
+		)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use anyhow::anyhow;
+
+	#[test]
+	fn failures_fail_the_report() {
+		let mut report = Report::default();
+		report.record("handshake", Ok("connected".to_owned()));
+		report.skip("query", "no key given");
+		assert!(report.passed());
+
+		report.record("query-schemas", Err(anyhow!("no schemas")));
+		assert!(!report.passed());
+		assert_eq!(
+			report.to_string(),
+			"PASS  handshake      connected\n\
+			 SKIP  query          no key given\n\
+			 FAIL  query-schemas  no schemas\n\
+			 \n\
+			 1 passed, 1 failed, 1 skipped"
+		);
+	}
+}
@@ -4,22 +4,13 @@ use convert_case::Casing;
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use std::ops::Not;
-use std::sync::{LazyLock, Mutex};
+use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
-use syn::{parse_macro_input, Error, Ident, ItemFn, Meta, PatType};
-
-static QUERIES: LazyLock<Mutex<Vec<NamedQuerySpec>>> = LazyLock::new(|| Mutex::new(vec![]));
-
-#[allow(unused)]
-#[derive(Debug, Clone)]
-struct NamedQuerySpec {
-	pub struct_name: String,
-	pub function: String,
-	pub default: bool,
-}
+use syn::{parse_macro_input, Error, Ident, ItemFn, Meta, PatType, Token, Visibility};
 
 struct QuerySpec {
 	pub function: Ident,
+	pub vis: Visibility,
 	pub input_type: syn::Type,
 	pub output_type: syn::Type,
 	pub default: bool,
@@ -80,6 +71,7 @@ fn parse_named_query_spec(opt_meta: Option<Meta>, item_fn: ItemFn) -> Result<Que
 	let sig = &item_fn.sig;
 
 	let function = sig.ident.clone();
+	let vis = item_fn.vis.clone();
 
 	let input_type: syn::Type = {
 		let inputs = &sig.inputs;
@@ -175,6 +167,7 @@ fn parse_named_query_spec(opt_meta: Option<Meta>, item_fn: ItemFn) -> Result<Que
 
 	Ok(QuerySpec {
 		function,
+		vis,
 		default,
 		input_type,
 		output_type,
@@ -209,11 +202,25 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
 		Span::call_site(),
 	);
 	let ident = &spec.function;
+	let vis = &spec.vis;
 	let input_type = spec.input_type;
 	let output_type = spec.output_type;
+	let name = match spec.default {
+		true => String::new(),
+		false => spec.function.to_string(),
+	};
 
 	let to_follow = quote::quote! {
-		struct #struct_name {}
+		#vis struct #struct_name {}
+
+		impl From<#struct_name> for hipcheck_sdk::prelude::NamedQuery {
+			fn from(query: #struct_name) -> hipcheck_sdk::prelude::NamedQuery {
+				hipcheck_sdk::prelude::NamedQuery {
+					name: #name,
+					inner: Box::new(query),
+				}
+			}
+		}
 
 		#[hipcheck_sdk::prelude::async_trait]
 		impl hipcheck_sdk::prelude::Query for #struct_name {
@@ -235,46 +242,59 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
 		}
 	};
 
-	QUERIES.lock().unwrap().push(NamedQuerySpec {
-		struct_name: struct_name.to_string(),
-		function: spec.function.to_string(),
-		default: spec.default,
-	});
-
 	to_return.extend(to_follow);
 	proc_macro::TokenStream::from(to_return)
 }
 
-/// Generates an implementation of the `Plugin::queries()` trait function using
-/// all previously-expanded `#[query]` attribute macros. Due to Rust's macro
-/// expansion ordering, all `#[query]` functions must come before this macro
-/// to ensure they are seen and added.
+/// Convert a path to a `#[query]` function into a path to its generated struct.
+fn query_struct_path(function: &syn::Path) -> syn::Path {
+	let mut path = function.clone();
+	// Panic: Safe to unwrap because there should be at least one element in the sequence
+	let last = path.segments.last_mut().unwrap();
+	last.ident = Ident::new(
+		last.ident
+			.to_string()
+			.to_case(convert_case::Case::Pascal)
+			.as_str(),
+		last.ident.span(),
+	);
+	path
+}
+
+/// Generates an implementation of the `Plugin::queries()` trait function from
+/// a comma-separated list of paths to `#[query]` functions, for example
+/// `queries! { activity, commits::commit_count }`. The functions may be
+/// declared anywhere in the crate, as long as they are visible from where this
+/// macro is invoked.
 #[proc_macro]
-pub fn queries(_item: TokenStream) -> TokenStream {
+pub fn queries(item: TokenStream) -> TokenStream {
+	let functions = parse_macro_input!(item with Punctuated::<syn::Path, Token![,]>::parse_terminated);
+
+	if functions.is_empty() {
+		return Error::new(
+			Span::call_site(),
+			"queries! must list the #[query] functions this plugin provides, e.g. `queries! { my_query }`",
+		)
+		.to_compile_error()
+		.into();
+	}
+
 	let mut agg = proc_macro2::TokenStream::new();
-	let q_lock = QUERIES.lock().unwrap();
-	// Create a NamedQuery for each #query func we've seen
-	for q in q_lock.iter() {
-		let name = match q.default {
-			true => "",
-			false => q.function.as_str(),
-		};
-		let inner = Ident::new(q.struct_name.as_str(), Span::call_site());
+	// Create a NamedQuery for each listed #query func
+	for function in functions.iter() {
+		let inner = query_struct_path(function);
 		let out = quote::quote! {
-			NamedQuery {
-				name: #name,
-				inner: Box::new(#inner {})
-			},
+			hipcheck_sdk::prelude::NamedQuery::from(#inner {}),
 		};
 		agg.extend(out);
 	}
 	log::info!(
-		"Auto-generating Plugin::queries() with {} detected queries",
-		q_lock.len()
+		"Auto-generating Plugin::queries() with {} listed queries",
+		functions.len()
 	);
 	// Impl `Plugin::queries` as a vec of generated NamedQuery instances
 	let out = quote::quote! {
-		fn queries(&self) -> impl Iterator<Item = NamedQuery> {
+		fn queries(&self) -> impl Iterator<Item = hipcheck_sdk::prelude::NamedQuery> {
 			vec![#agg].into_iter()
 		}
 	};
@@ -1,4 +0,0 @@
-// SPDX-License-Identifier: Apache-2.0
-
-pub mod plugin;
-pub mod repo;
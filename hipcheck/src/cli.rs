@@ -2,26 +2,42 @@
 
 //! Data structures for Hipcheck's main CLI.
 
-use crate::{
-	cache::repo::{RepoCacheDeleteScope, RepoCacheListScope, RepoCacheSort},
+use clap::{Parser as _, ValueEnum};
+use hipcheck_core::{
+	cache::repo::{
+		parse_age, RepoCacheDeleteScope, RepoCacheLimits, RepoCacheListScope, RepoCacheSort,
+	},
 	error::Context,
 	error::Result,
 	hc_error,
+	init::LogFormat,
 	plugin::Arch,
-	session::pm,
-	shell::{color_choice::ColorChoice, verbosity::Verbosity},
+	policy::policy_file::{parse_size, FailOn},
+	report::{schema::SchemaVersion, AnalyzedPackage, Format},
+	session::{
+		change_request::ChangeRequest,
+		pm,
+		resolver::{PluginTargetSeed, TargetRequest},
+		sample::SampleSize,
+		sbom::{expand_target_request, ComponentTarget},
+	},
+	shell::{color_choice::ColorChoice, encoding::Encoding, locale::Locale, verbosity::Verbosity},
 	source,
 	target::{
-		LocalGitRepo, MavenPackage, Package, PackageHost, Sbom, SbomStandard, TargetSeed,
-		TargetSeedKind, TargetType, ToTargetSeed, ToTargetSeedKind,
+		LocalGitRepo, MavenPackage, RemoteGitRepo, Sbom, SbomStandard, TargetSeed, TargetSeedKind,
+		TargetType, ToTargetSeed, ToTargetSeedKind,
 	},
+	util::fs::read_string,
 };
-use clap::{Parser as _, ValueEnum};
 use hipcheck_macros as hc;
 use pathbuf::pathbuf;
 use std::{
+	ffi::OsString,
+	io::{self, Read as _},
+	net::IpAddr,
 	path::{Path, PathBuf},
 	str::FromStr,
+	time::Duration,
 };
 use url::Url;
 
@@ -40,6 +56,10 @@ pub struct CliConfig {
 	#[clap(flatten)]
 	path_args: PathArgs,
 
+	/// Arguments configuring which plugins Hipcheck will run.
+	#[clap(flatten)]
+	trust_args: TrustArgs,
+
 	/// Soft-deprecated flags.
 	///
 	/// The following are flags which still work, but are hidden from help text.
@@ -71,6 +91,25 @@ struct OutputArgs {
 	)]
 	color: Option<ColorChoice>,
 
+	/// What character encoding to use.
+	#[arg(
+		long = "encoding",
+		global = true,
+		help_heading = "Output Flags",
+		long_help = "What character encoding to use. `ascii` replaces symbols and any non-ASCII text. Can also be set with the `HC_ENCODING` environment variable"
+	)]
+	encoding: Option<Encoding>,
+
+	/// How to format numbers and dates.
+	#[arg(
+		long = "locale",
+		global = true,
+		help_heading = "Output Flags",
+		value_parser = Locale::from_str,
+		long_help = "How to format numbers and dates, given as a locale like `de-DE` or `fr_FR.UTF-8`. Defaults to the `LC_ALL` or `LANG` environment variable. Can also be set with the `HC_LOCALE` environment variable"
+	)]
+	locale: Option<Locale>,
+
 	/// What format to use.
 	#[arg(
 		short = 'f',
@@ -80,6 +119,15 @@ struct OutputArgs {
 		long_help = "What format to use. Can also be set with the `HC_FORMAT` environment variable"
 	)]
 	format: Option<Format>,
+
+	/// What format to write log lines in.
+	#[arg(
+		long = "log-format",
+		global = true,
+		help_heading = "Output Flags",
+		long_help = "What format to write log lines in. `json` writes one JSON object per line, including the plugin and query each line is about. Can also be set with the `HC_LOG_FORMAT` environment variable"
+	)]
+	log_format: Option<LogFormat>,
 }
 
 /// Arguments configuring paths for Hipcheck to use.
@@ -116,6 +164,21 @@ struct PathArgs {
 	exec: Option<PathBuf>,
 }
 
+/// Arguments configuring which downloaded plugins Hipcheck trusts.
+#[derive(Debug, Default, clap::Args, hc::Update)]
+struct TrustArgs {
+	/// Run downloaded plugins which can't be verified.
+	#[arg(
+		long = "allow-unsigned",
+		global = true,
+		num_args = 0,
+		default_missing_value = "true",
+		help_heading = "Trust Flags",
+		long_help = "Run downloaded plugins which aren't signed, or whose publisher has no trusted key in the exec config. Plugins whose signature doesn't match a trusted key are still refused. Can also be set with the `HC_ALLOW_UNSIGNED` environment variable"
+	)]
+	allow_unsigned: Option<bool>,
+}
+
 /// Soft-deprecated arguments, to be removed in a future version.
 #[derive(Debug, Default, clap::Args, hc::Update)]
 struct DeprecatedArgs {
@@ -182,7 +245,7 @@ impl CliConfig {
 			(None, Some(quiet)) => Verbosity::use_quiet(quiet),
 			(Some(verbosity), None) => verbosity,
 			(Some(verbosity), Some(_quiet)) => {
-				log::warn!("verbosity specified with both -v/--verbosity and -q/--quiet; prefer -v/--verbosity");
+				tracing::warn!("verbosity specified with both -v/--verbosity and -q/--quiet; prefer -v/--verbosity");
 				verbosity
 			}
 		}
@@ -193,6 +256,16 @@ impl CliConfig {
 		self.output_args.color.unwrap_or_default()
 	}
 
+	/// Get the configured character encoding.
+	pub fn encoding(&self) -> Encoding {
+		self.output_args.encoding.unwrap_or_default()
+	}
+
+	/// Get the configured locale, or the one from the environment.
+	pub fn locale(&self) -> Locale {
+		self.output_args.locale.unwrap_or_else(Locale::detect)
+	}
+
 	/// Get the configured format.
 	pub fn format(&self) -> Format {
 		match (self.output_args.format, self.deprecated_args.json) {
@@ -200,7 +273,7 @@ impl CliConfig {
 			(None, Some(json)) => Format::use_json(json),
 			(Some(format), None) => format,
 			(Some(format), Some(_json)) => {
-				log::warn!(
+				tracing::warn!(
 					"format specified with both -f/--format and -j/--json; prefer -f/--format"
 				);
 				format
@@ -208,6 +281,11 @@ impl CliConfig {
 		}
 	}
 
+	/// Get the configured log format.
+	pub fn log_format(&self) -> LogFormat {
+		self.output_args.log_format.unwrap_or_default()
+	}
+
 	/// Get the path to the cache directory.
 	pub fn cache(&self) -> Option<&Path> {
 		match (&self.path_args.cache, &self.deprecated_args.home) {
@@ -215,7 +293,7 @@ impl CliConfig {
 			(None, Some(home)) => Some(home),
 			(Some(cache), None) => Some(cache),
 			(Some(cache), Some(_home)) => {
-				log::warn!("cache directory specified with both -C/--cache and -H/--home; prefer -C/--cache");
+				tracing::warn!("cache directory specified with both -C/--cache and -H/--home; prefer -C/--cache");
 				Some(cache)
 			}
 		}
@@ -236,6 +314,11 @@ impl CliConfig {
 		self.path_args.exec.as_deref()
 	}
 
+	/// Check if downloaded plugins which can't be verified may be run.
+	pub fn allow_unsigned(&self) -> bool {
+		self.trust_args.allow_unsigned.unwrap_or(false)
+	}
+
 	/// Check if the `--print-home` flag was used.
 	pub fn print_home(&self) -> bool {
 		self.deprecated_args.print_home.unwrap_or(false)
@@ -268,7 +351,10 @@ impl CliConfig {
 			output_args: OutputArgs {
 				verbosity: hc_env_var_value_enum("verbosity"),
 				color: hc_env_var_value_enum("color"),
+				encoding: hc_env_var_value_enum("encoding"),
+				locale: hc_env_var::<String>("locale").and_then(|s| s.parse().ok()),
 				format: hc_env_var_value_enum("format"),
+				log_format: hc_env_var_value_enum("log_format"),
 			},
 			path_args: PathArgs {
 				cache: hc_env_var("cache"),
@@ -277,6 +363,9 @@ impl CliConfig {
 				// For now, we don't get this from the environment
 				exec: None,
 			},
+			trust_args: TrustArgs {
+				allow_unsigned: hc_env_var::<String>("allow_unsigned").and_then(|s| s.parse().ok()),
+			},
 			deprecated_args: DeprecatedArgs {
 				config: hc_env_var("config"),
 				home: hc_env_var("home"),
@@ -375,6 +464,10 @@ pub enum FullCommands {
 	Update(UpdateArgs),
 	Cache(CacheArgs),
 	Plugin(PluginArgs),
+	Policy(PolicyArgs),
+	VerifyReport(VerifyReportArgs),
+	Serve(ServeArgs),
+	PluginPool(PluginPoolArgs),
 	PrintConfig,
 	PrintCache,
 	Scoring,
@@ -391,6 +484,10 @@ impl From<&Commands> for FullCommands {
 			Commands::Update(args) => FullCommands::Update(args.clone()),
 			Commands::Cache(args) => FullCommands::Cache(args.clone()),
 			Commands::Plugin(args) => FullCommands::Plugin(args.clone()),
+			Commands::Policy(args) => FullCommands::Policy(args.clone()),
+			Commands::VerifyReport(args) => FullCommands::VerifyReport(args.clone()),
+			Commands::Serve(args) => FullCommands::Serve(args.clone()),
+			Commands::PluginPool(args) => FullCommands::PluginPool(args.clone()),
 		}
 	}
 }
@@ -419,9 +516,17 @@ pub enum Commands {
 	Update(UpdateArgs),
 	/// Manage Hipcheck cache
 	Cache(CacheArgs),
-	/// Execute temporary code for exercising plugin engine
-	#[command(hide = true)]
+	/// Search for, install, uninstall, describe, and bundle plugins
 	Plugin(PluginArgs),
+	/// Check policy files for mistakes
+	Policy(PolicyArgs),
+	/// Check that a checkout matches the tree a JSON report was made from
+	VerifyReport(VerifyReportArgs),
+	/// Run a server which analyzes targets submitted over HTTP
+	Serve(ServeArgs),
+	/// Supervise a plugin kept running for later checks
+	#[command(hide = true)]
+	PluginPool(PluginPoolArgs),
 }
 
 // If no subcommand matched, default to use of '-t <TYPE> <TARGET' syntax. In
@@ -443,8 +548,95 @@ pub struct CheckArgs {
 
 	#[arg(short = 't', long = "target")]
 	pub target_type: Option<TargetType>,
+
+	/// Resolve the target with a target resolver plugin, given as <PUBLISHER>/<NAME>.
+	#[arg(long = "resolver", conflicts_with = "target_type")]
+	pub resolver: Option<String>,
+
+	/// Report every concern, instead of summarizing analyses with many concerns.
+	#[arg(long = "all-concerns")]
+	pub all_concerns: bool,
+
+	/// Only clone the target repository's history after this date, for
+	/// repositories too large to clone in full.
+	#[arg(long = "shallow-since", value_name = "DATE")]
+	pub shallow_since: Option<String>,
+
+	/// Only clone this many commits of the target repository's history.
+	#[arg(
+		long = "clone-depth",
+		value_name = "COMMITS",
+		conflicts_with = "shallow_since"
+	)]
+	pub clone_depth: Option<u32>,
+
+	/// Leave objects out of the clone of the target repository, as with `git clone --filter`,
+	/// for example 'blob:none'.
+	#[arg(long = "clone-filter", value_name = "FILTER")]
+	pub clone_filter: Option<String>,
+
+	/// Only analyze the commits and files under this directory of the target
+	/// repository, for a package kept in a monorepo.
+	#[arg(long = "subdir", value_name = "PATH")]
+	pub subdir: Option<String>,
+
+	/// Don't use the network. Plugins must already be in the cache, the target must be a local
+	/// repository or one cloned before, and analyses whose plugins need the network are skipped.
+	#[arg(long = "offline")]
+	pub offline: bool,
+
+	/// Compare the results against a JSON report from a previous run.
+	#[arg(long = "baseline", value_name = "PREVIOUS_REPORT")]
+	pub baseline: Option<PathBuf>,
+
+	/// Write the report to a file instead of stdout. Can't be used with the human format.
+	#[arg(short = 'o', long = "output", value_name = "PATH")]
+	pub output: Option<PathBuf>,
+
+	/// When to exit with a failing status: "investigate", "error", or "never".
+	/// Overrides the policy file's `fail-on` setting.
+	#[arg(long = "fail-on", value_name = "WHEN", value_parser = FailOn::from_str)]
+	pub fail_on: Option<FailOn>,
+
+	/// Write the JSON report in this major version of its schema, to keep tools
+	/// which read it working after the schema changes. Defaults to the latest.
+	#[arg(long = "schema-version", value_name = "N", value_parser = SchemaVersion::from_str)]
+	pub schema_version: Option<SchemaVersion>,
+
+	/// Only analyze a random sample of an SBOM's components or a target file's
+	/// targets, given as a number or a percentage like '10%', and estimate the
+	/// results for all of them.
+	#[arg(long = "sample", value_name = "SIZE", value_parser = SampleSize::from_str)]
+	pub sample: Option<SampleSize>,
+
+	/// Draw the sample with this seed, to repeat an earlier sample.
+	#[arg(long = "sample-seed", value_name = "SEED", requires = "sample")]
+	pub sample_seed: Option<u64>,
+
+	/// Analyze this many of an SBOM's components or a target file's targets at
+	/// once, each with its own plugins.
+	#[arg(
+		long = "jobs",
+		value_name = "N",
+		default_value_t = 1,
+		value_parser = clap::value_parser!(u16).range(1..)
+	)]
+	pub jobs: u16,
+
+	/// Keep running, and analyze a local repository again whenever commits are
+	/// made or its refs are moved, comparing each report against the last.
+	#[arg(
+		long = "watch",
+		conflicts_with_all = ["target_file", "sample", "baseline"]
+	)]
+	pub watch: bool,
+
+	/// Analyze every target listed in a file, one per line or as a JSON array
+	/// of strings. Use '-' to read the list from stdin.
+	#[arg(long = "target-file", value_name = "PATH", conflicts_with = "target")]
+	pub target_file: Option<PathBuf>,
 	#[arg(
-		required = true,
+		required_unless_present = "target_file",
 		help = "The target package, URL, commit, etc. for Hipcheck to analyze. If ambiguous, the -t flag must be set"
 	)]
 	pub target: Option<String>,
@@ -453,6 +645,26 @@ pub struct CheckArgs {
 }
 
 impl CheckArgs {
+	/// Parse the arguments of `hc check`, given without `hc check` itself.
+	pub fn from_args<I, T>(args: I) -> Result<CheckArgs>
+	where
+		I: IntoIterator<Item = T>,
+		T: Into<OsString>,
+	{
+		let args = ["hc", "check"]
+			.into_iter()
+			.map(OsString::from)
+			.chain(args.into_iter().map(Into::into));
+
+		match CliConfig::try_parse_from(args)
+			.map_err(|e| hc_error!("{}", e))?
+			.command
+		{
+			Some(Commands::Check(args)) => Ok(args),
+			_ => unreachable!("`hc check` always parses to the check command"),
+		}
+	}
+
 	fn target_to_check_command(&self) -> Result<CheckCommand> {
 		// Get target str
 		let Some(target) = self.target.clone() else {
@@ -464,8 +676,14 @@ impl CheckArgs {
 		let subcmd_str;
 		let target_str;
 
-		// Try to resolve the type by checking if the target string is a pURL, GitHub URL, or SBOM (SPDX or CycloneDX) file
-		match TargetType::try_resolve_from_target(target.as_str()) {
+		// A pull or merge request's URL is also a repository URL, so look for
+		// one before resolving the type any other way
+		let inferred = match ChangeRequest::parse(&target) {
+			Some(_) => Some((TargetType::Request, target.clone())),
+			// Try to resolve the type by checking if the target string is a pURL, GitHub URL, or SBOM (SPDX or CycloneDX) file
+			None => TargetType::try_resolve_from_target(target.as_str()),
+		};
+		match inferred {
 			Some((subcmd, new_target)) => {
 				subcmd_str = subcmd.as_str();
 				// If the type had to be resolved from a pURL, the target string must be reformatted
@@ -507,6 +725,98 @@ impl CheckArgs {
 		CheckCommand::try_parse_from(reconst_args).map_err(|e| hc_error!("{}", e))
 	}
 
+	/// Get what the user asked to analyze, deferring to a resolver plugin if one was named.
+	pub fn to_target_request(&self) -> Result<TargetRequest> {
+		let Some(resolver) = &self.resolver else {
+			// A change request is analyzed at its head, as the commits it contributes
+			if let CheckCommand::Request(args) = self.command()? {
+				let request = args.resolve()?;
+				if let Some(flag) = &self.refspec {
+					return Err(hc_error!("ambiguous ref for change request target: the request's head is {}, but refspec flag specified {}. please specify only one.", request.head_ref(), flag));
+				}
+				return Ok(TargetRequest::ChangeRequest(request));
+			}
+			return self.to_target_seed().map(TargetRequest::Seed);
+		};
+
+		if self.command.is_some() {
+			return Err(hc_error!(
+				"the --resolver flag cannot be combined with a target type subcommand"
+			));
+		}
+
+		let Some(specifier) = self.target.clone() else {
+			return Err(hc_error!(
+				"a target must be provided. The CLI should have caught this"
+			));
+		};
+
+		Ok(TargetRequest::Plugin(PluginTargetSeed {
+			resolver: resolver.clone(),
+			specifier,
+			refspec: self.refspec.clone(),
+		}))
+	}
+
+	/// Get every target the user asked to analyze, with SBOMs expanded into a
+	/// target for each of their components.
+	///
+	/// Targets listed in a target file are each resolved the same way as a
+	/// target given on the command line, with the same flags.
+	pub fn to_component_targets(&self) -> Result<Vec<ComponentTarget>> {
+		let Some(path) = &self.target_file else {
+			return self.to_target_request().and_then(expand_target_request);
+		};
+
+		if self.command.is_some() {
+			return Err(hc_error!(
+				"the --target-file flag cannot be combined with a target type subcommand"
+			));
+		}
+
+		let list = read_target_list(path)?;
+		if list.is_empty() {
+			return Err(hc_error!("no targets found in '{}'", path.display()));
+		}
+
+		let mut targets = Vec::new();
+		for target in list {
+			let args = CheckArgs {
+				target: Some(target.clone()),
+				target_file: None,
+				..self.clone()
+			};
+			let expanded = args
+				.to_target_request()
+				.and_then(expand_target_request)
+				.with_context(|| {
+					format!(
+						"failed to resolve target '{}' from '{}'",
+						target,
+						path.display()
+					)
+				})?;
+			targets.extend(expanded);
+		}
+		Ok(targets)
+	}
+
+	/// Get the crate a target request was resolved from, if the target is one.
+	///
+	/// The specifier of a crate's target names the version which was found.
+	pub fn analyzed_package(&self, target: &TargetRequest) -> Option<AnalyzedPackage> {
+		let (Ok(CheckCommand::Cargo(_)), TargetRequest::Seed(seed)) = (self.command(), target)
+		else {
+			return None;
+		};
+		let (name, version) = seed.specifier.split_once('@')?;
+		Some(AnalyzedPackage {
+			registry: "crates.io".to_owned(),
+			name: name.to_owned(),
+			version: version.to_owned(),
+		})
+	}
+
 	pub fn command(&self) -> Result<CheckCommand> {
 		if let Some(cmd) = self.command.clone() {
 			Ok(cmd)
@@ -515,13 +825,83 @@ impl CheckArgs {
 		}
 	}
 }
+/// Read a list of targets from a file, or from stdin if the path is `-`.
+fn read_target_list(path: &Path) -> Result<Vec<String>> {
+	let contents = if path == Path::new("-") {
+		let mut contents = String::new();
+		io::stdin()
+			.read_to_string(&mut contents)
+			.context("failed to read the list of targets from stdin")?;
+		contents
+	} else {
+		read_string(path)?
+	};
+	parse_target_list(&contents).with_context(|| {
+		format!(
+			"failed to parse the list of targets in '{}'",
+			path.display()
+		)
+	})
+}
+
+/// Parse a list of targets, given as a JSON array of strings or one per line.
+///
+/// Blank lines and lines starting with `#` are skipped.
+fn parse_target_list(contents: &str) -> Result<Vec<String>> {
+	if contents.trim_start().starts_with('[') {
+		let targets: Vec<String> = serde_json::from_str(contents)?;
+		return Ok(targets
+			.into_iter()
+			.map(|target| target.trim().to_owned())
+			.filter(|target| !target.is_empty())
+			.collect());
+	}
+
+	Ok(contents
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(ToOwned::to_owned)
+		.collect())
+}
+
 impl ToTargetSeed for CheckArgs {
 	fn to_target_seed(&self) -> Result<TargetSeed> {
 		let command = self.command()?;
+		let mut refspec = self.refspec.clone();
+		// A repository may be given with its ref, as `<source>@<ref>`
+		if let CheckCommand::Repo(args) = &command {
+			if let (_, Some(git_ref)) = args.source_and_ref() {
+				match &refspec {
+					Some(flag) if flag != git_ref => {
+						return Err(hc_error!("ambiguous ref for repository target: repository target specified {}, but refspec flag specified {}. please specify only one.", git_ref, flag));
+					}
+					_ => refspec = Some(git_ref.to_owned()),
+				}
+			}
+		}
+		let mut specifier = command.get_specifier().to_owned();
+		// A Go module is analyzed at the ref its version was made from, which
+		// is only known once the module proxy is asked about it
+		let kind = match &command {
+			CheckCommand::Cargo(args) => {
+				let (remote, package, git_ref) = args.resolve(refspec.as_deref())?;
+				// Pin the specifier to the version found, so the report can say which it was
+				specifier = format!("{}@{}", package.name, package.version);
+				refspec = git_ref;
+				TargetSeedKind::RemoteRepo(remote)
+			}
+			CheckCommand::Go(args) => {
+				let (remote, git_ref) = args.resolve(refspec.as_deref())?;
+				refspec = Some(git_ref);
+				TargetSeedKind::RemoteRepo(remote)
+			}
+			_ => command.to_target_seed_kind()?,
+		};
 		let target = TargetSeed {
-			kind: command.to_target_seed_kind()?,
-			refspec: self.refspec.clone(),
-			specifier: command.get_specifier().to_owned(),
+			kind,
+			refspec,
+			specifier,
 		};
 		// Validate
 		if let Some(refspec) = &target.refspec {
@@ -539,6 +919,10 @@ impl ToTargetSeed for CheckArgs {
 
 #[derive(Debug, Clone, clap::Parser)]
 pub enum CheckCommand {
+	/// Analyze a crate on crates.io with format <crate name>[@<optional version>]
+	Cargo(CheckCargoArgs),
+	/// Analyze a Go module git repo with format <module path>[@<optional version>]
+	Go(CheckGoArgs),
 	/// Analyze a maven package git repo via package URI
 	#[command(hide = true)]
 	Maven(CheckMavenArgs),
@@ -551,6 +935,9 @@ pub enum CheckCommand {
 	/// Analyze a repository and output an overall risk assessment
 	#[command(hide = true)]
 	Repo(CheckRepoArgs),
+	/// Analyze a GitHub pull request or GitLab merge request via its URL
+	#[command(hide = true)]
+	Request(CheckRequestArgs),
 	/// Analyze packages specified in an SBOM document
 	#[command(hide = true)]
 	Sbom(CheckSbomArgs),
@@ -560,10 +947,13 @@ impl CheckCommand {
 	fn get_specifier(&self) -> &str {
 		use CheckCommand::*;
 		match self {
+			Cargo(args) => &args.package,
+			Go(args) => &args.module,
 			Maven(args) => &args.package,
 			Npm(args) => &args.package,
 			Pypi(args) => &args.package,
-			Repo(args) => &args.source,
+			Repo(args) => args.source_and_ref().0,
+			Request(args) => &args.url,
 			Sbom(args) => &args.path,
 		}
 	}
@@ -572,15 +962,103 @@ impl CheckCommand {
 impl ToTargetSeedKind for CheckCommand {
 	fn to_target_seed_kind(&self) -> Result<TargetSeedKind> {
 		match self {
+			CheckCommand::Cargo(args) => args.to_target_seed_kind(),
+			CheckCommand::Go(args) => args.to_target_seed_kind(),
 			CheckCommand::Maven(args) => args.to_target_seed_kind(),
 			CheckCommand::Npm(args) => args.to_target_seed_kind(),
 			CheckCommand::Pypi(args) => args.to_target_seed_kind(),
 			CheckCommand::Repo(args) => args.to_target_seed_kind(),
+			// Without its head ref and base, a change request would be analyzed
+			// as its whole repository
+			CheckCommand::Request(args) => Err(hc_error!(
+				"'{}' is a change request, which can't be analyzed as a plain repository",
+				args.url
+			)),
 			CheckCommand::Sbom(args) => args.to_target_seed_kind(),
 		}
 	}
 }
 
+#[derive(Debug, Clone, clap::Args)]
+pub struct CheckCargoArgs {
+	/// Crate name, with an optional @<version>
+	pub package: String,
+}
+
+impl CheckCargoArgs {
+	/// Look up the repository of the crate, the version to analyze, and the
+	/// tag it was released from if one can be found, using the version given
+	/// with `--ref` if the crate has none.
+	fn resolve(
+		&self,
+		refspec: Option<&str>,
+	) -> Result<(RemoteGitRepo, AnalyzedPackage, Option<String>)> {
+		let (name, version) = pm::parse_cargo_crate(&self.package)?;
+		let version = match (version, refspec) {
+			(Some(version), Some(flag)) if version != flag => {
+				return Err(hc_error!("ambiguous version for crate target: crate target specified {}, but refspec flag specified {}. please specify only one.", version, flag));
+			}
+			(Some(version), _) => Some(version),
+			(None, flag) => flag.map(ToOwned::to_owned),
+		};
+		let (url, version) = pm::extract_repo_for_crate(&name, version.as_deref())?;
+
+		let git_ref = pm::find_crate_tag(&url, &name, &version)?;
+		if git_ref.is_none() {
+			tracing::warn!(
+				"no tag found for {} {}; analyzing the default branch of {}",
+				name,
+				version,
+				url
+			);
+		}
+
+		let package = AnalyzedPackage {
+			registry: "crates.io".to_owned(),
+			name,
+			version,
+		};
+		Ok((source::get_remote_repo_from_url(url)?, package, git_ref))
+	}
+}
+
+impl ToTargetSeedKind for CheckCargoArgs {
+	fn to_target_seed_kind(&self) -> Result<TargetSeedKind> {
+		let (remote_repo, _, _) = self.resolve(None)?;
+		Ok(TargetSeedKind::RemoteRepo(remote_repo))
+	}
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct CheckGoArgs {
+	/// Go module path, with an optional @<version>
+	pub module: String,
+}
+
+impl CheckGoArgs {
+	/// Look up the repository of the module and the ref its version was made
+	/// from, using the version given with `--ref` if the module has none.
+	fn resolve(&self, refspec: Option<&str>) -> Result<(RemoteGitRepo, String)> {
+		let (module, version) = pm::parse_go_module(&self.module)?;
+		let version = match (version, refspec) {
+			(Some(version), Some(flag)) if version != flag => {
+				return Err(hc_error!("ambiguous version for Go module target: module target specified {}, but refspec flag specified {}. please specify only one.", version, flag));
+			}
+			(Some(version), _) => Some(version),
+			(None, flag) => flag.map(ToOwned::to_owned),
+		};
+		let (url, git_ref) = pm::extract_repo_for_go(&module, version.as_deref())?;
+		Ok((source::get_remote_repo_from_url(url)?, git_ref))
+	}
+}
+
+impl ToTargetSeedKind for CheckGoArgs {
+	fn to_target_seed_kind(&self) -> Result<TargetSeedKind> {
+		let (remote_repo, _) = self.resolve(None)?;
+		Ok(TargetSeedKind::RemoteRepo(remote_repo))
+	}
+}
+
 #[derive(Debug, Clone, clap::Args)]
 pub struct CheckMavenArgs {
 	/// Maven package URI to analyze
@@ -605,26 +1083,9 @@ pub struct CheckNpmArgs {
 
 impl ToTargetSeedKind for CheckNpmArgs {
 	fn to_target_seed_kind(&self) -> Result<TargetSeedKind> {
-		let raw_package = &self.package;
-
-		let (name, version) = match Url::parse(raw_package) {
-			Ok(url_parsed) => pm::extract_package_version_from_url(url_parsed)?,
-			_ => pm::extract_package_version(raw_package)?,
-		};
-
-		// If the package is scoped, replace the leading '@' in the scope with %40 for proper pURL formatting
-		let purl = Url::parse(&match version.as_str() {
-			"no version" => format!("pkg:npm/{}", str::replace(&name, '@', "%40")),
-			_ => format!("pkg:npm/{}@{}", str::replace(&name, '@', "%40"), version),
-		})
-		.unwrap();
-
-		Ok(TargetSeedKind::Package(Package {
-			purl,
-			name,
-			version,
-			host: PackageHost::Npm,
-		}))
+		Ok(TargetSeedKind::Package(pm::parse_npm_package(
+			&self.package,
+		)?))
 	}
 }
 
@@ -636,45 +1097,56 @@ pub struct CheckPypiArgs {
 
 impl ToTargetSeedKind for CheckPypiArgs {
 	fn to_target_seed_kind(&self) -> Result<TargetSeedKind> {
-		let raw_package = &self.package;
-
-		let (name, version) = match Url::parse(raw_package) {
-			Ok(url_parsed) => pm::extract_package_version_from_url(url_parsed)?,
-			_ => pm::extract_package_version(raw_package)?,
-		};
-
-		let purl = Url::parse(&match version.as_str() {
-			"no version" => format!("pkg:pypi/{}", name),
-			_ => format!("pkg:pypi/{}@{}", name, version),
-		})
-		.unwrap();
-
-		Ok(TargetSeedKind::Package(Package {
-			purl,
-			name,
-			version,
-			host: PackageHost::PyPI,
-		}))
+		Ok(TargetSeedKind::Package(pm::parse_pypi_package(
+			&self.package,
+		)?))
 	}
 }
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct CheckRepoArgs {
-	/// Repository to analyze; can be a local path or a URI
+	/// Repository to analyze; can be a local path or a URI, with an optional @<ref>
 	pub source: String,
 }
 
+impl CheckRepoArgs {
+	/// Split the source into the repository and the ref to analyze, if one was
+	/// given as `<source>@<ref>`.
+	///
+	/// Only an `@` after the last `/` or `:` starts a ref, so the user in a URL
+	/// like `git@github.com:mitre/hipcheck.git` isn't taken for one. Refs with a
+	/// `/` in them must be given with `--ref` instead.
+	fn source_and_ref(&self) -> (&str, Option<&str>) {
+		let split = self
+			.source
+			.rsplit_once('@')
+			.filter(|(source, git_ref)| {
+				!source.is_empty() && !git_ref.is_empty() && !git_ref.contains(['/', ':'])
+			})
+			// A local repository may have an `@` in its name
+			.filter(|_| !Path::new(&self.source).exists());
+
+		match split {
+			Some((source, git_ref)) => (source, Some(git_ref)),
+			None => (&self.source, None),
+		}
+	}
+}
+
 impl ToTargetSeedKind for CheckRepoArgs {
 	fn to_target_seed_kind(&self) -> Result<TargetSeedKind> {
-		if let Ok(url) = Url::parse(&self.source) {
+		let (source, _) = self.source_and_ref();
+		if let Ok(url) = Url::parse(source) {
 			let remote_repo = source::get_remote_repo_from_url(url)?;
 			Ok(TargetSeedKind::RemoteRepo(remote_repo))
 		} else {
-			let path = Path::new(&self.source).canonicalize()?;
+			let path = Path::new(source).canonicalize()?;
 			if path.exists() {
 				Ok(TargetSeedKind::LocalRepo(LocalGitRepo {
 					path,
 					git_ref: "".to_owned(),
+					subdir: None,
+					base: None,
 				}))
 			} else {
 				Err(hc_error!("Provided target repository could not be identified as either a remote url or path to a local file"))
@@ -683,6 +1155,24 @@ impl ToTargetSeedKind for CheckRepoArgs {
 	}
 }
 
+#[derive(Debug, Clone, clap::Args)]
+pub struct CheckRequestArgs {
+	/// URL of the pull or merge request to analyze
+	pub url: String,
+}
+
+impl CheckRequestArgs {
+	/// Parse the request's URL.
+	fn resolve(&self) -> Result<ChangeRequest> {
+		ChangeRequest::parse(&self.url).ok_or_else(|| {
+			hc_error!(
+				"'{}' is not the URL of a GitHub pull request or a GitLab merge request",
+				self.url
+			)
+		})
+	}
+}
+
 #[derive(Debug, Clone, clap::Args)]
 pub struct CheckSbomArgs {
 	/// SPDX document to analyze
@@ -734,6 +1224,16 @@ pub enum SchemaCommand {
 	Pypi,
 	/// Print the JSON schema for running Hipcheck against a source repository
 	Repo,
+	/// Print the JSON schema of Hipcheck's JSON report
+	Report(SchemaReportArgs),
+}
+
+// Args for `hc schema report`
+#[derive(Debug, Clone, clap::Args)]
+pub struct SchemaReportArgs {
+	/// The major version of the report schema to print. Defaults to the latest.
+	#[arg(long = "version", value_name = "N", value_parser = SchemaVersion::from_str)]
+	pub version: Option<SchemaVersion>,
 }
 
 #[derive(Debug, Clone, clap::Args)]
@@ -779,7 +1279,7 @@ pub struct CacheArgs {
 	pub subcmd: CacheSubcmds,
 }
 impl TryFrom<CacheArgs> for CacheOp {
-	type Error = crate::error::Error;
+	type Error = hipcheck_core::error::Error;
 	fn try_from(value: CacheArgs) -> Result<Self> {
 		value.subcmd.try_into()
 	}
@@ -797,6 +1297,15 @@ pub enum CacheOp {
 		filter: Option<String>,
 		force: bool,
 	},
+	PluginResults {
+		plugin: Option<String>,
+		purge: bool,
+		force: bool,
+	},
+	Prune {
+		limits: RepoCacheLimits,
+		force: bool,
+	},
 }
 
 #[derive(Debug, Clone, clap::Subcommand)]
@@ -806,14 +1315,20 @@ pub enum CacheSubcmds {
 	List(CliCacheListArgs),
 	/// Delete existing caches.
 	Delete(CliCacheDeleteArgs),
+	/// List or purge cached plugin query results.
+	PluginResults(CliCachePluginResultsArgs),
+	/// Manage the cached target repositories.
+	Target(CliCacheTargetArgs),
 }
 impl TryFrom<CacheSubcmds> for CacheOp {
-	type Error = crate::error::Error;
+	type Error = hipcheck_core::error::Error;
 	fn try_from(value: CacheSubcmds) -> Result<Self> {
 		use CacheSubcmds::*;
 		match value {
 			List(args) => Ok(args.into()),
 			Delete(args) => args.try_into(),
+			PluginResults(args) => Ok(args.into()),
+			Target(args) => args.subcmd.try_into(),
 		}
 	}
 }
@@ -895,7 +1410,7 @@ pub struct CliCacheDeleteArgs {
 // Must be fallible conversion because we are doing validation that clap can't
 // support as of writing
 impl TryFrom<CliCacheDeleteArgs> for CacheOp {
-	type Error = crate::error::Error;
+	type Error = hipcheck_core::error::Error;
 	fn try_from(value: CliCacheDeleteArgs) -> Result<Self> {
 		if value.strategy.is_empty() && value.filter.is_none() {
 			return Err(hc_error!(
@@ -904,7 +1419,7 @@ impl TryFrom<CliCacheDeleteArgs> for CacheOp {
                 entries"
 			));
 		}
-		let scope: RepoCacheDeleteScope = value.strategy.try_into()?;
+		let scope = parse_delete_scope(value.strategy)?;
 		Ok(CacheOp::Delete {
 			scope,
 			filter: value.filter,
@@ -913,81 +1428,329 @@ impl TryFrom<CliCacheDeleteArgs> for CacheOp {
 	}
 }
 
+// Args for `hc cache plugin-results`
+#[derive(Debug, Clone, clap::Args)]
+pub struct CliCachePluginResultsArgs {
+	/// Consider only results from this plugin, given as '<PUBLISHER>/<NAME>'
+	#[arg(long)]
+	pub plugin: Option<String>,
+	/// Delete the cached results instead of listing them
+	#[arg(long, default_value_t = false)]
+	pub purge: bool,
+	/// Do not prompt user to confirm the results to delete
+	#[arg(long, default_value_t = false, requires = "purge")]
+	pub force: bool,
+}
+impl From<CliCachePluginResultsArgs> for CacheOp {
+	fn from(value: CliCachePluginResultsArgs) -> Self {
+		CacheOp::PluginResults {
+			plugin: value.plugin,
+			purge: value.purge,
+			force: value.force,
+		}
+	}
+}
+
+// Args for `hc cache target`
+#[derive(Debug, Clone, clap::Args)]
+pub struct CliCacheTargetArgs {
+	#[clap(subcommand)]
+	pub subcmd: CacheTargetSubcmds,
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+#[command(arg_required_else_help = true)]
+pub enum CacheTargetSubcmds {
+	/// Delete the least recently used repositories until the cache is within limits.
+	Prune(CliCacheTargetPruneArgs),
+}
+impl TryFrom<CacheTargetSubcmds> for CacheOp {
+	type Error = hipcheck_core::error::Error;
+	fn try_from(value: CacheTargetSubcmds) -> Result<Self> {
+		match value {
+			CacheTargetSubcmds::Prune(args) => args.try_into(),
+		}
+	}
+}
+
+// Args for `hc cache target prune`
+#[derive(Debug, Clone, clap::Args)]
+pub struct CliCacheTargetPruneArgs {
+	/// Most space the repositories may take up together, like '20GB'
+	#[arg(long)]
+	pub max_size: Option<String>,
+	/// Longest a repository may go unused, in hours, days, or weeks, like '90d'
+	#[arg(long)]
+	pub max_age: Option<String>,
+	/// Do not prompt user to confirm the entries to delete
+	#[arg(long, default_value_t = false)]
+	pub force: bool,
+}
+impl TryFrom<CliCacheTargetPruneArgs> for CacheOp {
+	type Error = hipcheck_core::error::Error;
+	fn try_from(value: CliCacheTargetPruneArgs) -> Result<Self> {
+		if value.max_size.is_none() && value.max_age.is_none() {
+			return Err(hc_error!(
+				"`hc cache target prune` needs a limit to prune to. please \
+                set --max-size, --max-age, or both"
+			));
+		}
+		let limits = RepoCacheLimits {
+			max_size: value
+				.max_size
+				.as_deref()
+				.map(parse_size)
+				.transpose()
+				.context("invalid --max-size")?,
+			max_age: value
+				.max_age
+				.as_deref()
+				.map(parse_age)
+				.transpose()
+				.context("invalid --max-age")?,
+		};
+		Ok(CacheOp::Prune {
+			limits,
+			force: value.force,
+		})
+	}
+}
+
 // A valid cli string for CacheDeleteScope may be:
 //  1. "all"
 //  2. "<SORT> <N>", where <SORT> is one of the CliSortStrategy variants, <N> is
 //      number of entries
 //  3. "<SORT>", same as #2 but <N> defaults to 1
-impl TryFrom<Vec<String>> for RepoCacheDeleteScope {
-	type Error = crate::error::Error;
-	fn try_from(value: Vec<String>) -> Result<Self> {
-		if value.len() > 2 {
-			return Err(hc_error!("strategy takes at most two tokens"));
+fn parse_delete_scope(value: Vec<String>) -> Result<RepoCacheDeleteScope> {
+	if value.len() > 2 {
+		return Err(hc_error!("strategy takes at most two tokens"));
+	}
+	let Some(raw_spec) = value.first() else {
+		return Ok(RepoCacheDeleteScope::All);
+	};
+	if raw_spec.to_lowercase() == "all" {
+		if let Some(n) = value.get(1) {
+			return Err(hc_error!(
+				"unnecessary additional token '{}' after 'all'",
+				n
+			));
 		}
-		let Some(raw_spec) = value.first() else {
-			return Ok(RepoCacheDeleteScope::All);
+		Ok(RepoCacheDeleteScope::All)
+	} else {
+		let strat = CliSortStrategy::from_str(raw_spec, true).or(Err(hc_error!(
+			"'{}' is not a valid sort strategy. strategies include {}, or 'all'",
+			raw_spec,
+			CliSortStrategy::value_variants()
+				.iter()
+				.map(|s| format!("'{s:?}'").to_lowercase())
+				.collect::<Vec<String>>()
+				.join(", "),
+		)))?;
+		let (sort, invert) = strat.to_cache_sort();
+		let n: usize = match value.get(1) {
+			Some(raw_n) => raw_n
+				.parse::<usize>()
+				.context("max entry token is not a valid unsigned int")?,
+			None => 1,
 		};
-		if raw_spec.to_lowercase() == "all" {
-			if let Some(n) = value.get(1) {
-				return Err(hc_error!(
-					"unnecessary additional token '{}' after 'all'",
-					n
-				));
-			}
-			Ok(RepoCacheDeleteScope::All)
-		} else {
-			let strat = CliSortStrategy::from_str(raw_spec, true).or(Err(hc_error!(
-				"'{}' is not a valid sort strategy. strategies include {}, or 'all'",
-				raw_spec,
-				CliSortStrategy::value_variants()
-					.iter()
-					.map(|s| format!("'{s:?}'").to_lowercase())
-					.collect::<Vec<String>>()
-					.join(", "),
-			)))?;
-			let (sort, invert) = strat.to_cache_sort();
-			let n: usize = match value.get(1) {
-				Some(raw_n) => raw_n
-					.parse::<usize>()
-					.context("max entry token is not a valid unsigned int")?,
-				None => 1,
-			};
-			Ok(RepoCacheDeleteScope::Group { sort, invert, n })
-		}
+		Ok(RepoCacheDeleteScope::Group { sort, invert, n })
 	}
 }
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct PluginArgs {
-	#[arg(long = "async")]
+	#[clap(subcommand)]
+	pub command: Option<PluginCommand>,
+	/// Execute temporary code for exercising plugin engine
+	#[arg(long = "async", hide = true)]
 	pub asynch: bool,
 }
 
-/// The format to report results in.
-#[derive(Debug, Default, Clone, Copy, clap::ValueEnum)]
-pub enum Format {
-	/// JSON format.
-	Json,
-	/// Human-readable format.
-	#[default]
-	Human,
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum PluginCommand {
+	/// Search the plugin registry for plugins.
+	Search(PluginSearchArgs),
+	/// Download a plugin from the plugin registry so policy files can use it.
+	Install(PluginInstallArgs),
+	/// Remove an installed plugin.
+	Uninstall(PluginUninstallArgs),
+	/// Print a plugin's queries, schemas, and defaults as JSON.
+	Describe(PluginDescribeArgs),
+	/// Write the plugins a policy file uses to a bundle, for loading where they can't be downloaded.
+	Bundle(PluginBundleArgs),
+	/// Add the plugins in a bundle made with `hc plugin bundle` to the plugin cache.
+	LoadBundle(PluginLoadBundleArgs),
 }
 
-impl Format {
-	pub fn use_json(json: bool) -> Format {
-		if json {
-			Format::Json
-		} else {
-			Format::Human
-		}
-	}
+// Args for `hc plugin search`
+#[derive(Debug, Clone, clap::Args)]
+pub struct PluginSearchArgs {
+	/// Text to look for in plugin names and descriptions. Lists every plugin if omitted
+	pub query: Option<String>,
+	/// URL of the registry index, overriding the one in the exec config
+	#[arg(long)]
+	pub registry: Option<Url>,
+}
+
+// Args for `hc plugin install`
+#[derive(Debug, Clone, clap::Args)]
+pub struct PluginInstallArgs {
+	/// The plugin to install, as <PUBLISHER>/<NAME>
+	pub plugin: String,
+	/// The version to install. Defaults to the newest version in the registry
+	#[arg(long)]
+	pub version: Option<String>,
+	/// URL of the registry index, overriding the one in the exec config
+	#[arg(long)]
+	pub registry: Option<Url>,
+}
+
+// Args for `hc plugin uninstall`
+#[derive(Debug, Clone, clap::Args)]
+pub struct PluginUninstallArgs {
+	/// The plugin to uninstall, as <PUBLISHER>/<NAME>
+	pub plugin: String,
+	/// The version to uninstall. Defaults to every installed version
+	#[arg(long)]
+	pub version: Option<String>,
+}
+
+// Args for `hc plugin describe`
+#[derive(Debug, Clone, clap::Args)]
+pub struct PluginDescribeArgs {
+	/// The plugin to describe, as <PUBLISHER>/<NAME>
+	pub plugin: String,
+	/// The version to describe. Defaults to the newest installed version, or the newest version in
+	/// the registry if none is installed
+	#[arg(long)]
+	pub version: Option<String>,
+	/// URL of the registry index, overriding the one in the exec config
+	#[arg(long)]
+	pub registry: Option<Url>,
+	/// Configuration to give the plugin, as a JSON object
+	#[arg(long, value_name = "JSON", default_value = "{}")]
+	pub plugin_config: String,
+}
+
+// Args for `hc plugin bundle`
+#[derive(Debug, Clone, clap::Args)]
+pub struct PluginBundleArgs {
+	/// Path to write the bundle to, as a tar archive
+	#[arg(long)]
+	pub out: PathBuf,
+}
+
+// Args for `hc plugin load-bundle`
+#[derive(Debug, Clone, clap::Args)]
+pub struct PluginLoadBundleArgs {
+	/// The bundle to load
+	pub bundle: PathBuf,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct PolicyArgs {
+	#[clap(subcommand)]
+	pub command: PolicyCommand,
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum PolicyCommand {
+	/// Check a policy file for mistakes without running an analysis.
+	Validate(PolicyValidateArgs),
+	/// Evaluate a policy expression against a JSON value, showing each step.
+	Eval(PolicyEvalArgs),
+}
+
+// Args for `hc policy validate`
+#[derive(Debug, Clone, clap::Args)]
+pub struct PolicyValidateArgs {
+	/// Path to the policy file to check
+	pub path: PathBuf,
+	/// Only check the policy file itself, without starting the plugins it uses
+	#[arg(long)]
+	pub offline: bool,
+}
+
+// Args for `hc policy eval`
+#[derive(Debug, Clone, clap::Args)]
+pub struct PolicyEvalArgs {
+	/// The policy expression to evaluate
+	#[arg(long)]
+	pub expr: String,
+	/// Path to a JSON file with the value the expression's JSON Pointers refer to
+	#[arg(long)]
+	pub input: Option<PathBuf>,
+}
+
+// Args for `hc verify-report`
+#[derive(Debug, Clone, clap::Args)]
+pub struct VerifyReportArgs {
+	/// Path to the JSON report
+	pub report: PathBuf,
+	/// Path to the checkout to check against the report
+	#[arg(long)]
+	pub target: PathBuf,
+}
+
+// Args for `hc serve`
+#[derive(Debug, Clone, clap::Args)]
+pub struct ServeArgs {
+	/// Port to listen on
+	#[arg(long, default_value_t = 8080)]
+	pub port: u16,
+	/// Address to listen on. Defaults to only accepting connections from this machine
+	#[arg(long, default_value = "127.0.0.1")]
+	pub host: IpAddr,
+	/// How long to keep a finished job's report, in hours (h), days (d), or weeks (w)
+	#[arg(long, value_name = "AGE", default_value = "24h", value_parser = parse_age)]
+	pub keep_reports: Duration,
+	/// The most policies whose plugins are kept running between jobs
+	#[arg(
+		long,
+		value_name = "N",
+		default_value_t = 4,
+		value_parser = clap::value_parser!(u16).range(1..)
+	)]
+	pub max_policies: u16,
+}
+
+// Args for `hc plugin-pool`, which Hipcheck runs to supervise pooled plugins
+#[derive(Debug, Clone, clap::Args)]
+pub struct PluginPoolArgs {
+	/// Path to the record of the plugin, written once it's configured
+	#[arg(long)]
+	pub record: PathBuf,
+	/// Path to the directory holding the plugin's log and temporary files
+	#[arg(long)]
+	pub dir: PathBuf,
+	/// Port the plugin listens on
+	#[arg(long)]
+	pub port: u16,
+	/// How long the plugin is kept running without being used
+	#[arg(long, value_name = "SECONDS")]
+	pub idle_timeout: u64,
+	/// The most memory the plugin may map
+	#[arg(long, value_name = "BYTES")]
+	pub memory: Option<u64>,
+	/// The most CPU time the plugin may use
+	#[arg(long, value_name = "SECONDS")]
+	pub cpu_time: Option<u64>,
+	/// The most files the plugin may have open at once
+	#[arg(long, value_name = "COUNT")]
+	pub open_files: Option<u64>,
+	/// The user the plugin runs as
+	#[arg(long)]
+	pub user: Option<String>,
+	/// The plugin's command
+	#[arg(last = true, required = true)]
+	pub command: Vec<OsString>,
 }
 
 /// Test CLI commands
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use crate::{cli::CliConfig, util::test::with_env_vars};
+	use crate::{cli::CliConfig, test::with_env_vars};
 	use clap::CommandFactory;
 	use tempfile::TempDir;
 
@@ -1150,6 +1913,23 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn resolve_allow_unsigned_with_env_var() {
+		let vars = vec![("HC_ALLOW_UNSIGNED", Some("true"))];
+
+		with_env_vars(vars, || {
+			let config = {
+				let mut temp = CliConfig::empty();
+				temp.update(&CliConfig::from_env());
+				temp
+			};
+
+			assert!(config.allow_unsigned());
+		});
+
+		assert!(!CliConfig::empty().allow_unsigned());
+	}
+
 	#[test]
 	fn resolve_policy_with_flag() {
 		let tempdir = TempDir::with_prefix(TEMPDIR_PREFIX).unwrap();
@@ -1193,6 +1973,167 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn hc_check_output_flag() {
+		let parsed = CliConfig::try_parse_from(vec![
+			"hc",
+			"check",
+			"-f",
+			"sarif",
+			"-o",
+			"report.sarif",
+			"https://github.com/mitre/hipcheck.git",
+		])
+		.unwrap();
+		let Some(Commands::Check(args)) = parsed.command else {
+			unreachable!();
+		};
+		assert_eq!(args.output, Some(PathBuf::from("report.sarif")));
+	}
+
+	#[test]
+	fn hc_check_offline_flag() {
+		let parsed =
+			CliConfig::try_parse_from(vec!["hc", "check", "--offline", "./hipcheck"]).unwrap();
+		let Some(Commands::Check(args)) = parsed.command else {
+			unreachable!();
+		};
+		assert!(args.offline);
+	}
+
+	#[test]
+	fn hc_check_fail_on_flag() {
+		let parsed = CliConfig::try_parse_from(vec![
+			"hc",
+			"check",
+			"--fail-on",
+			"investigate",
+			"https://github.com/mitre/hipcheck.git",
+		])
+		.unwrap();
+		let Some(Commands::Check(args)) = parsed.command else {
+			unreachable!();
+		};
+		assert_eq!(args.fail_on, Some(FailOn::Investigate));
+
+		assert!(CliConfig::try_parse_from(vec![
+			"hc",
+			"check",
+			"--fail-on",
+			"sometimes",
+			"https://github.com/mitre/hipcheck.git",
+		])
+		.is_err());
+	}
+
+	#[test]
+	fn hc_check_sample_flags() {
+		let parsed = CliConfig::try_parse_from(vec![
+			"hc",
+			"check",
+			"--sample",
+			"10%",
+			"--sample-seed",
+			"42",
+			"bom.spdx",
+		])
+		.unwrap();
+		let Some(Commands::Check(args)) = parsed.command else {
+			unreachable!();
+		};
+		assert_eq!(args.sample, Some(SampleSize::Percent(10.0)));
+		assert_eq!(args.sample_seed, Some(42));
+
+		assert!(
+			CliConfig::try_parse_from(vec!["hc", "check", "--sample-seed", "42", "bom.spdx"])
+				.is_err()
+		);
+	}
+
+	#[test]
+	fn hc_check_jobs_flag() {
+		let parsed =
+			CliConfig::try_parse_from(vec!["hc", "check", "--jobs", "4", "bom.spdx"]).unwrap();
+		let Some(Commands::Check(args)) = parsed.command else {
+			unreachable!();
+		};
+		assert_eq!(args.jobs, 4);
+
+		let parsed = CliConfig::try_parse_from(vec!["hc", "check", "bom.spdx"]).unwrap();
+		let Some(Commands::Check(args)) = parsed.command else {
+			unreachable!();
+		};
+		assert_eq!(args.jobs, 1);
+
+		assert!(CliConfig::try_parse_from(vec!["hc", "check", "--jobs", "0", "bom.spdx"]).is_err());
+	}
+
+	#[test]
+	fn hc_check_target_file_flag() {
+		let parsed =
+			CliConfig::try_parse_from(vec!["hc", "check", "--target-file", "targets.txt"]).unwrap();
+		let Some(Commands::Check(args)) = parsed.command else {
+			unreachable!();
+		};
+		assert_eq!(args.target_file, Some(PathBuf::from("targets.txt")));
+		assert_eq!(args.target, None);
+
+		assert!(CliConfig::try_parse_from(vec![
+			"hc",
+			"check",
+			"--target-file",
+			"targets.txt",
+			"https://github.com/mitre/hipcheck.git",
+		])
+		.is_err());
+	}
+
+	#[test]
+	fn hc_check_watch_flag() {
+		let parsed = CliConfig::try_parse_from(vec!["hc", "check", "--watch", "."]).unwrap();
+		let Some(Commands::Check(args)) = parsed.command else {
+			unreachable!();
+		};
+		assert!(args.watch);
+
+		assert!(CliConfig::try_parse_from(vec![
+			"hc",
+			"check",
+			"--watch",
+			"--baseline",
+			"before.json",
+			"."
+		])
+		.is_err());
+	}
+
+	#[test]
+	fn parses_target_lists() {
+		let lines = "# Our dependencies\n\
+			https://github.com/mitre/hipcheck\n\
+			\n\
+			  pkg:npm/left-pad@1.3.0  \r\n";
+		assert_eq!(
+			parse_target_list(lines).unwrap(),
+			vec![
+				"https://github.com/mitre/hipcheck",
+				"pkg:npm/left-pad@1.3.0"
+			]
+		);
+
+		let json = r#"["https://github.com/mitre/hipcheck", " ", "pkg:npm/left-pad@1.3.0"]"#;
+		assert_eq!(
+			parse_target_list(json).unwrap(),
+			vec![
+				"https://github.com/mitre/hipcheck",
+				"pkg:npm/left-pad@1.3.0"
+			]
+		);
+
+		assert!(parse_target_list(r#"["unterminated"#).is_err());
+		assert!(parse_target_list("").unwrap().is_empty());
+	}
+
 	fn get_check_cmd_from_cli(args: Vec<&str>) -> Result<CheckCommand> {
 		let parsed = CliConfig::try_parse_from(args);
 		assert!(parsed.is_ok());
@@ -1205,10 +2146,13 @@ mod tests {
 
 	fn get_target_from_cmd(cmd: CheckCommand) -> String {
 		match cmd {
+			CheckCommand::Cargo(args) => args.package,
+			CheckCommand::Go(args) => args.module,
 			CheckCommand::Maven(args) => args.package,
 			CheckCommand::Npm(args) => args.package,
 			CheckCommand::Pypi(args) => args.package,
 			CheckCommand::Repo(args) => args.source,
+			CheckCommand::Request(args) => args.url,
 			CheckCommand::Sbom(args) => args.path,
 		}
 	}
@@ -1224,6 +2168,26 @@ mod tests {
 		assert!(matches!(cmd, Ok(CheckCommand::Repo(..))));
 	}
 
+	#[test]
+	fn test_check_go() {
+		let cmd = get_check_cmd_from_cli(vec!["hc", "check", "go", "golang.org/x/net@v0.30.0"]);
+		assert!(matches!(cmd, Ok(CheckCommand::Go(..))));
+		if let Ok(chk_cmd) = cmd {
+			let target = get_target_from_cmd(chk_cmd);
+			assert_eq!(target, "golang.org/x/net@v0.30.0");
+		}
+	}
+
+	#[test]
+	fn test_check_cargo() {
+		let cmd = get_check_cmd_from_cli(vec!["hc", "check", "cargo", "serde@1.0.210"]);
+		assert!(matches!(cmd, Ok(CheckCommand::Cargo(..))));
+		if let Ok(chk_cmd) = cmd {
+			let target = get_target_from_cmd(chk_cmd);
+			assert_eq!(target, "serde@1.0.210");
+		}
+	}
+
 	#[test]
 	fn test_deductive_check_no_match() {
 		let cmd = get_check_cmd_from_cli(vec!["hc", "check", "pkg:unsupportedtype/someurl"]);
@@ -1351,4 +2315,152 @@ mod tests {
 		]);
 		assert!(matches!(cmd, Err(..)));
 	}
+
+	#[test]
+	fn test_check_args_from_args() {
+		let args = CheckArgs::from_args([
+			"--ref",
+			"v1.0.0",
+			"-t",
+			"repo",
+			"https://github.com/mitre/hipcheck.git",
+		])
+		.unwrap();
+		assert_eq!(args.refspec.as_deref(), Some("v1.0.0"));
+		assert!(matches!(args.command(), Ok(CheckCommand::Repo(..))));
+
+		assert!(CheckArgs::from_args(["--no-such-flag", "express"]).is_err());
+	}
+
+	#[test]
+	fn test_check_repo_with_ref() {
+		let args = CheckArgs::from_args([
+			"-t",
+			"repo",
+			"https://github.com/mitre/hipcheck.git@1a2b3c4",
+		])
+		.unwrap();
+		let seed = args.to_target_seed().unwrap();
+		assert_eq!(seed.refspec.as_deref(), Some("1a2b3c4"));
+		assert_eq!(seed.specifier, "https://github.com/mitre/hipcheck.git");
+
+		// The user of an SSH URL isn't a ref
+		let args = CheckArgs::from_args([
+			"-t",
+			"repo",
+			"ssh://git@github.com/mitre/hipcheck.git@v3.0.0",
+		])
+		.unwrap();
+		let seed = args.to_target_seed().unwrap();
+		assert_eq!(seed.refspec.as_deref(), Some("v3.0.0"));
+		assert_eq!(seed.specifier, "ssh://git@github.com/mitre/hipcheck.git");
+
+		let args = CheckArgs::from_args(["-t", "repo", "ssh://git@github.com/mitre/hipcheck.git"])
+			.unwrap();
+		assert_eq!(args.to_target_seed().unwrap().refspec, None);
+	}
+
+	#[test]
+	fn test_deductive_check_change_request() {
+		let cmd = get_check_cmd_from_cli(vec![
+			"hc",
+			"check",
+			"https://github.com/mitre/hipcheck/pull/123",
+		]);
+		assert!(matches!(cmd, Ok(CheckCommand::Request(..))));
+
+		let cmd = get_check_cmd_from_cli(vec![
+			"hc",
+			"check",
+			"-t",
+			"repo",
+			"https://github.com/mitre/hipcheck/pull/123",
+		]);
+		assert!(matches!(cmd, Err(..)));
+	}
+
+	#[test]
+	fn test_check_change_request_target() {
+		let args =
+			CheckArgs::from_args(["https://github.com/mitre/hipcheck/pull/123/files"]).unwrap();
+		let Ok(TargetRequest::ChangeRequest(request)) = args.to_target_request() else {
+			panic!("a pull request should be analyzed as a change request");
+		};
+		assert_eq!(request.repo.as_str(), "https://github.com/mitre/hipcheck");
+		assert_eq!(request.head_ref(), "refs/pull/123/head");
+
+		let args = CheckArgs::from_args([
+			"-t",
+			"request",
+			"https://gitlab.com/group/project/-/merge_requests/7",
+		])
+		.unwrap();
+		let Ok(TargetRequest::ChangeRequest(request)) = args.to_target_request() else {
+			panic!("a merge request should be analyzed as a change request");
+		};
+		assert_eq!(request.repo.as_str(), "https://gitlab.com/group/project");
+		assert_eq!(request.head_ref(), "refs/merge-requests/7/head");
+
+		// The request's head and base can't be described by a plain repository seed
+		assert!(args.to_target_seed().is_err());
+
+		let args = CheckArgs::from_args([
+			"--ref",
+			"main",
+			"https://github.com/mitre/hipcheck/pull/123",
+		])
+		.unwrap();
+		assert!(args.to_target_request().is_err());
+	}
+
+	#[test]
+	fn test_check_repo_with_conflicting_refs() {
+		let args = CheckArgs::from_args([
+			"--ref",
+			"v2.0.0",
+			"-t",
+			"repo",
+			"https://github.com/mitre/hipcheck.git@v3.0.0",
+		])
+		.unwrap();
+		assert!(args.to_target_seed().is_err());
+	}
+
+	fn get_cache_op_from_cli(args: Vec<&str>) -> Result<CacheOp> {
+		let config = CliConfig::try_parse_from(args).map_err(|e| hc_error!("{}", e))?;
+		match config.subcommand() {
+			Some(FullCommands::Cache(args)) => args.try_into(),
+			_ => Err(hc_error!("not a cache command")),
+		}
+	}
+
+	#[test]
+	fn test_cache_target_prune() {
+		let op = get_cache_op_from_cli(vec![
+			"hc",
+			"cache",
+			"target",
+			"prune",
+			"--max-size",
+			"20GB",
+			"--max-age",
+			"90d",
+		])
+		.unwrap();
+		let CacheOp::Prune { limits, force } = op else {
+			panic!("expected a prune op, got {:?}", op);
+		};
+		assert_eq!(limits.max_size, Some(20_000_000_000));
+		assert_eq!(
+			limits.max_age,
+			Some(std::time::Duration::from_secs(90 * 24 * 60 * 60))
+		);
+		assert!(!force);
+
+		assert!(get_cache_op_from_cli(vec!["hc", "cache", "target", "prune"]).is_err());
+		assert!(
+			get_cache_op_from_cli(vec!["hc", "cache", "target", "prune", "--max-age", "3mo"])
+				.is_err()
+		);
+	}
 }
@@ -1,65 +1,89 @@
 // SPDX-License-Identifier: Apache-2.0
 
-#[cfg(feature = "benchmarking")]
-mod benchmarking;
-mod cache;
 mod cli;
-mod config;
-mod engine;
-mod error;
-mod exec;
-mod init;
-mod plugin;
-mod policy;
-mod policy_exprs;
-mod report;
-mod score;
-mod session;
+mod serve;
 mod setup;
-mod shell;
-mod source;
-mod target;
-mod util;
-mod version;
+#[cfg(test)]
+mod test;
 
 use crate::{
-	cache::repo::HcRepoCache,
-	cli::Format,
-	config::{normalized_unresolved_analysis_tree_from_policy, Config},
-	error::{Context as _, Error, Result},
-	exec::ExecConfig,
-	plugin::{try_set_arch, Plugin, PluginWithConfig},
-	policy::{config_to_policy, PolicyFile},
-	report::report_builder::{build_report, Report},
-	score::score_results,
-	session::Session,
+	cli::{
+		CacheArgs, CacheOp, CheckArgs, CliConfig, FullCommands, PluginArgs, PluginCommand,
+		PluginPoolArgs, PolicyArgs, PolicyCommand, PolicyEvalArgs, PolicyValidateArgs, SchemaArgs,
+		SchemaCommand, ServeArgs, SetupArgs, UpdateArgs, VerifyReportArgs,
+	},
+	serve::{serve, ServeConfig},
 	setup::{resolve_and_transform_source, SourceType},
-	shell::Shell,
 };
-use cli::{
-	CacheArgs, CacheOp, CheckArgs, CliConfig, FullCommands, PluginArgs, SchemaArgs, SchemaCommand,
-	SetupArgs, UpdateArgs,
-};
-use config::AnalysisTreeNode;
 use core::fmt;
+use hipcheck_core::{
+	analyze, analyze_complete,
+	cache::{
+		plugin::HcPluginCache, plugin_data::HcPluginDataCache, remote::HcRemoteFileCache,
+		repo::HcRepoCache, results::HcResultCache, scratch::HcScratchCache,
+	},
+	config::{normalized_unresolved_analysis_tree_from_policy, AnalysisTreeNode, Config},
+	engine,
+	error::{Context as _, Error, Result},
+	exec::ExecConfig,
+	hc_error, init,
+	plugin::{
+		create_bundle, describe::describe_plugin, install_plugin, load_bundle, set_allow_unsigned,
+		set_pool_supervisor, supervise, try_set_arch, Plugin, PluginWithConfig, RegistryIndex,
+		ResourceLimits, Supervised,
+	},
+	policy::{
+		config_to_policy,
+		policy_file::{FailOn, PolicyPluginName},
+		validate::PolicyValidation,
+		PolicyFile,
+	},
+	policy_exprs::{std_trace, Expr, Primitive},
+	report::{
+		batch::BatchReport,
+		contributors::{ContributorRollup, TargetActivity},
+		diff::Baseline,
+		integrity,
+		portfolio::PortfolioSummary,
+		report_builder::{RecommendationKind, Report},
+		sample::SampleSummary,
+		schema::SchemaVersion,
+		Format,
+	},
+	session::{
+		load_exec_config, load_policy_and_data, resolver::TargetRequest, sample::Sample,
+		tidy_clones_cache_once, Session,
+	},
+	shell::{color_choice::ColorChoice, output::Output, Shell},
+	source::git::{
+		ref_commits, try_set_clone_depth, try_set_clone_filter, try_set_shallow_since,
+		try_set_subdir,
+	},
+	util::{
+		self,
+		command::DependentProgram,
+		fs::{create_dir_all, read_string},
+		network::set_offline,
+	},
+	version, TargetSeed, TargetSeedKind,
+};
 use indextree::{Arena, NodeId};
 use ordered_float::NotNan;
 use pathbuf::pathbuf;
-use schemars::schema_for;
-use shell::{color_choice::ColorChoice, spinner_phase::SpinnerPhase};
 use std::{
+	collections::BTreeMap,
 	env,
 	fmt::{Display, Formatter},
 	io::Write,
+	net::SocketAddr,
 	ops::Not as _,
 	path::{Path, PathBuf},
 	process::{Command, ExitCode},
 	result::Result as StdResult,
+	sync::{mpsc, Mutex},
+	thread,
 	time::Duration,
 };
-use target::{TargetSeed, ToTargetSeed};
-use util::command::DependentProgram;
-use util::fs::create_dir_all;
 use which::which;
 
 /// Entry point for Hipcheck.
@@ -72,7 +96,7 @@ fn main() -> ExitCode {
 
 	// Start tracking the timing for `main` after logging is initiated.
 	#[cfg(feature = "print-timings")]
-	let _0 = benchmarking::print_scope_time!("main");
+	let _0 = hipcheck_core::benchmarking::print_scope_time!("main");
 
 	let config = CliConfig::load();
 
@@ -86,6 +110,23 @@ fn main() -> ExitCode {
 		ColorChoice::Auto => {}
 	}
 
+	// Set which characters are allowed in the output.
+	Shell::set_encoding(config.encoding());
+
+	// Set how numbers and dates are formatted.
+	Shell::set_locale(config.locale());
+
+	// Set what format log lines are written in.
+	init::set_log_format(config.log_format());
+
+	// Set whether downloaded plugins which can't be verified may run.
+	set_allow_unsigned(config.allow_unsigned());
+
+	// Plugins kept running between checks are supervised by `hc plugin-pool`.
+	if let Ok(exe) = env::current_exe() {
+		set_pool_supervisor(exe);
+	}
+
 	match config.subcommand() {
 		Some(FullCommands::Check(args)) => return cmd_check(&args, &config),
 		Some(FullCommands::Schema(args)) => cmd_schema(&args),
@@ -94,6 +135,10 @@ fn main() -> ExitCode {
 		Some(FullCommands::Update(args)) => cmd_update(&args),
 		Some(FullCommands::Cache(args)) => return cmd_cache(args, &config),
 		Some(FullCommands::Plugin(args)) => return cmd_plugin(args, &config),
+		Some(FullCommands::Policy(args)) => return cmd_policy(args, &config),
+		Some(FullCommands::VerifyReport(args)) => return cmd_verify_report(&args),
+		Some(FullCommands::Serve(args)) => return cmd_serve(&args, &config),
+		Some(FullCommands::PluginPool(args)) => return cmd_plugin_pool(args),
 		Some(FullCommands::PrintConfig) => cmd_print_config(config.config()),
 		Some(FullCommands::PrintCache) => cmd_print_home(config.cache()),
 		Some(FullCommands::Scoring) => {
@@ -121,13 +166,92 @@ fn cmd_check(args: &CheckArgs, config: &CliConfig) -> ExitCode {
 			return ExitCode::FAILURE;
 		}
 	}
-	let target = match args.to_target_seed() {
-		Ok(target) => target,
+	if let Some(date) = &args.shallow_since {
+		if let Err(e) = try_set_shallow_since(date) {
+			Shell::print_error(&e, Format::Human);
+			return ExitCode::FAILURE;
+		}
+	}
+	if let Some(depth) = args.clone_depth {
+		if let Err(e) = try_set_clone_depth(depth) {
+			Shell::print_error(&e, Format::Human);
+			return ExitCode::FAILURE;
+		}
+	}
+	if let Some(filter) = &args.clone_filter {
+		if let Err(e) = try_set_clone_filter(filter) {
+			Shell::print_error(&e, Format::Human);
+			return ExitCode::FAILURE;
+		}
+	}
+	if let Some(subdir) = &args.subdir {
+		if let Err(e) = try_set_subdir(subdir) {
+			Shell::print_error(&e, Format::Human);
+			return ExitCode::FAILURE;
+		}
+	}
+	set_offline(args.offline);
+	if args.output.is_some() && matches!(config.format(), Format::Human) {
+		let e = hc_error!(
+			"--output can't be used with the human format; choose another with -f/--format"
+		);
+		Shell::print_error(&e, Format::Human);
+		return ExitCode::FAILURE;
+	}
+	// Load the baseline up front, so a bad path doesn't waste a full analysis.
+	let baseline = match args
+		.baseline
+		.as_deref()
+		.map(Baseline::load_from)
+		.transpose()
+	{
+		Ok(baseline) => baseline,
+		Err(e) => {
+			Shell::print_error(&e, Format::Human);
+			return ExitCode::FAILURE;
+		}
+	};
+	let targets = match args.to_component_targets() {
+		Ok(targets) => targets,
 		Err(e) => {
 			Shell::print_error(&e, Format::Human);
 			return ExitCode::FAILURE;
 		}
 	};
+	let (sample, mut targets) = match args.sample {
+		Some(size) if targets.len() > 1 => {
+			let (sample, targets) = Sample::draw(targets, size, args.sample_seed);
+			(Some(sample), targets)
+		}
+		Some(_) => {
+			let e = hc_error!(
+				"--sample can only be used with an SBOM or target file of several targets"
+			);
+			Shell::print_error(&e, Format::Human);
+			return ExitCode::FAILURE;
+		}
+		None => (None, targets.into_iter().map(|t| t.request).collect()),
+	};
+
+	if targets.len() > 1 || sample.is_some() {
+		if baseline.is_some() {
+			let e = hc_error!(
+				"--baseline can't be used with an SBOM or target file of several targets"
+			);
+			Shell::print_error(&e, Format::Human);
+			return ExitCode::FAILURE;
+		}
+
+		return check_batch(targets, sample, args, config);
+	}
+
+	let target = targets.remove(0);
+
+	if args.watch {
+		return check_watch(target, args, config);
+	}
+
+	let package = args.analyzed_package(&target);
 
 	let report = run(
 		target,
@@ -136,29 +260,305 @@ fn cmd_check(args: &CheckArgs, config: &CliConfig) -> ExitCode {
 		config.policy().map(ToOwned::to_owned),
 		config.exec().map(ToOwned::to_owned),
 		config.format(),
-	);
+		args.all_concerns,
+	)
+	.map(|report| compare_to_baseline(report, baseline.as_ref()))
+	.map(|mut report| {
+		report.schema_version = args.schema_version.unwrap_or_default();
+		report.package = package;
+		report
+	});
+
+	let output = Output::from(args.output.clone());
 
 	match report {
-		Ok(report) => Shell::print_report(report, config.format())
-			.map(|()| ExitCode::SUCCESS)
-			.unwrap_or_else(|err| {
-				Shell::print_error(&err, Format::Human);
-				ExitCode::FAILURE
-			}),
+		Ok(report) => {
+			let fail_on = args.fail_on.or(report.fail_on).unwrap_or_default();
+			let investigate = matches!(report.recommendation.kind, RecommendationKind::Investigate);
+
+			Shell::print_report(report, config.format(), &output)
+				.map(|()| check_exit_code(fail_on, false, investigate))
+				.unwrap_or_else(|err| {
+					Shell::print_error(&err, Format::Human);
+					ExitCode::FAILURE
+				})
+		}
 		Err(e) => {
 			Shell::print_error(&e, config.format());
+			check_exit_code(args.fail_on.unwrap_or_default(), true, false)
+		}
+	}
+}
+
+/// How often a watched repository is checked for new commits.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Analyze a local repository, then analyze it again whenever commits are made
+/// or its refs are moved, printing each report compared against the last.
+///
+/// Plugin query results are cached by commit, so each new run only repeats
+/// the queries the new commits affect. Runs until interrupted.
+fn check_watch(target: TargetRequest, args: &CheckArgs, config: &CliConfig) -> ExitCode {
+	let TargetRequest::Seed(TargetSeed {
+		kind: TargetSeedKind::LocalRepo(repo),
+		..
+	}) = &target
+	else {
+		let e = hc_error!("--watch can only be used with a local repository target");
+		Shell::print_error(&e, Format::Human);
+		return ExitCode::FAILURE;
+	};
+	let path = repo.path.clone();
+	let output = Output::from(args.output.clone());
+
+	let mut watched = None;
+	let mut previous = None;
+
+	loop {
+		// The refs are read before analyzing, so commits made during an
+		// analysis lead to another one.
+		let refs = match ref_commits(&path) {
+			Ok(refs) => refs,
+			Err(e) => {
+				Shell::print_error(&e, Format::Human);
+				return ExitCode::FAILURE;
+			}
+		};
+		if watched.as_ref() == Some(&refs) {
+			thread::sleep(WATCH_INTERVAL);
+			continue;
+		}
+		watched = Some(refs);
+
+		let report = run(
+			target.clone(),
+			config.config().map(ToOwned::to_owned),
+			config.cache().map(ToOwned::to_owned),
+			config.policy().map(ToOwned::to_owned),
+			config.exec().map(ToOwned::to_owned),
+			config.format(),
+			args.all_concerns,
+		)
+		.map(|report| compare_to_baseline(report, previous.as_ref()))
+		.map(|mut report| {
+			report.schema_version = args.schema_version.unwrap_or_default();
+			report
+		});
+
+		match report {
+			Ok(report) => {
+				match Baseline::from_report(&report) {
+					Ok(baseline) => previous = Some(baseline),
+					Err(e) => tracing::warn!("can't compare the next run against this one: {}", e),
+				}
+				if let Err(e) = Shell::print_report(report, config.format(), &output) {
+					Shell::print_error(&e, Format::Human);
+				}
+			}
+			// A failed run doesn't stop the watch, since the next commit may fix it
+			Err(e) => Shell::print_error(&e, config.format()),
+		}
+
+		Shell::eprintln(format!(
+			"watching '{}' for new commits, press Ctrl-C to stop",
+			path.display()
+		));
+	}
+}
+
+/// Analyze several targets, up to `--jobs` at once, then print every report
+/// along with a summary, and estimates for all the targets if they were sampled.
+///
+/// A target which fails to be analyzed doesn't stop the others; its error is
+/// recorded in the summary instead. Reports are listed in the order the
+/// targets were given, however they finish. Human reports are printed as soon
+/// as they and the ones before them are done, while other formats put the
+/// whole batch in one document once every target is.
+fn check_batch(
+	targets: Vec<TargetRequest>,
+	sample: Option<Sample>,
+	args: &CheckArgs,
+	config: &CliConfig,
+) -> ExitCode {
+	let jobs = usize::from(args.jobs).min(targets.len()).max(1);
+	let output = Output::from(args.output.clone());
+	let stream = matches!(config.format(), Format::Human) && output.is_stdout();
+	let schema_version = args.schema_version.unwrap_or_default();
+
+	// Each session would tidy the clones cache as it starts, racing the others
+	// running at once, so it's done once before any of them start. If the
+	// cache or exec config can't be found, the sessions fail before tidying.
+	if let (Some(home), Ok(exec)) = (config.cache(), load_exec_config(config.exec())) {
+		tidy_clones_cache_once(home, &exec);
+	}
+
+	let mut results = Vec::new();
+	let mut activity = Vec::new();
+	// Sessions can't be shared between threads, so each check makes its own
+	run_batch(
+		targets,
+		jobs,
+		|target| check_batch_target(target, args, config),
+		|(name, report, target_activity)| {
+			let report = report.map(|mut report| {
+				report.schema_version = schema_version;
+				report
+			});
+			if let (true, Ok(report)) = (stream, &report) {
+				if let Err(e) = Shell::print_batch_report(report, &output) {
+					Shell::print_error(&e, Format::Human);
+				}
+			}
+			activity.extend(target_activity);
+			results.push((name, report));
+		},
+	);
+
+	let mut batch = BatchReport::new(results);
+	batch.contributors = ContributorRollup::new(&activity);
+	batch.sample = sample.map(|sample| SampleSummary::new(&sample, &batch.summary));
+	batch.portfolio = PortfolioSummary::new(&batch);
+
+	let fail_on = args
+		.fail_on
+		.or_else(|| batch.reports.iter().find_map(|report| report.fail_on))
+		.unwrap_or_default();
+	let errored = batch.has_errored_targets();
+	let investigate = batch
+		.reports
+		.iter()
+		.any(|report| matches!(report.recommendation.kind, RecommendationKind::Investigate));
+
+	let printed = if stream {
+		Shell::print_batch_summary(batch, &output)
+	} else {
+		Shell::print_batch(batch, config.format(), &output)
+	};
+	printed
+		.map(|()| check_exit_code(fail_on, errored, investigate))
+		.unwrap_or_else(|err| {
+			Shell::print_error(&err, Format::Human);
 			ExitCode::FAILURE
+		})
+}
+
+/// Check each target of a batch with `check`, up to `jobs` at once, handing
+/// each result to `finished` in the order the targets were given, as soon as
+/// it and the results before it are done.
+fn run_batch<T, R>(
+	targets: Vec<T>,
+	jobs: usize,
+	check: impl Fn(&T) -> R + Sync,
+	mut finished: impl FnMut(R),
+) where
+	T: Send,
+	R: Send,
+{
+	let queue = Mutex::new(targets.into_iter().enumerate());
+	let (sender, receiver) = mpsc::channel();
+
+	thread::scope(|scope| {
+		for _ in 0..jobs {
+			let sender = sender.clone();
+			let (queue, check) = (&queue, &check);
+			scope.spawn(move || loop {
+				let Some((i, target)) = queue.lock().unwrap().next() else {
+					break;
+				};
+				if sender.send((i, check(&target))).is_err() {
+					break;
+				}
+			});
+		}
+		drop(sender);
+
+		// Results which finished before an earlier one, held until it's done
+		let mut pending = BTreeMap::new();
+		let mut next = 0;
+		for (i, result) in receiver {
+			pending.insert(i, result);
+			while let Some(result) = pending.remove(&next) {
+				finished(result);
+				next += 1;
+			}
+		}
+	});
+}
+
+/// Analyze one target of a batch, returning its name, its report, and its
+/// contributors' activity if it could be found.
+fn check_batch_target(
+	target: &TargetRequest,
+	args: &CheckArgs,
+	config: &CliConfig,
+) -> (String, Result<Report>, Option<TargetActivity>) {
+	let name = target.to_string();
+	let mut activity = None;
+	let report = Session::new(
+		target,
+		config.config().map(ToOwned::to_owned),
+		config.cache().map(ToOwned::to_owned),
+		config.policy().map(ToOwned::to_owned),
+		config.exec().map(ToOwned::to_owned),
+		config.format(),
+	)
+	.and_then(|session| {
+		let report = analyze(&session, args.all_concerns)?;
+
+		// The rollup is only possible if the policy uses the `mitre/git`
+		// plugin, so a failure here doesn't fail the target.
+		match TargetActivity::query(&session, &name) {
+			Ok(target_activity) => activity = Some(target_activity),
+			Err(e) => {
+				tracing::warn!("failed to get contributor activity for '{}': {}", name, e)
+			}
+		}
+
+		Ok(report)
+	});
+	(name, report, activity)
+}
+
+/// The exit code when a target is recommended for investigation and the check
+/// fails on investigate, kept apart from the code for errors so CI can tell them
+/// apart.
+const INVESTIGATE_EXIT_CODE: u8 = 2;
+
+/// Pick the exit code for a check, from whether any target couldn't be analyzed
+/// and whether any was recommended for investigation.
+fn check_exit_code(fail_on: FailOn, errored: bool, investigate: bool) -> ExitCode {
+	match fail_on {
+		FailOn::Never => ExitCode::SUCCESS,
+		_ if errored => ExitCode::FAILURE,
+		FailOn::Investigate if investigate => ExitCode::from(INVESTIGATE_EXIT_CODE),
+		_ => ExitCode::SUCCESS,
+	}
+}
+
+/// Attach the comparison against the baseline report, if one was given.
+fn compare_to_baseline(mut report: Report, baseline: Option<&Baseline>) -> Report {
+	if let Some(baseline) = baseline {
+		if baseline.repo_name != *report.repo_name {
+			Shell::eprintln(format!(
+				"warning: baseline report is for '{}', not '{}'",
+				baseline.repo_name, report.repo_name
+			));
 		}
+
+		report.delta = Some(baseline.diff(&report));
 	}
+
+	report
 }
 
 /// Run the `schema` command.
 fn cmd_schema(args: &SchemaArgs) {
-	match args.command {
+	match &args.command {
 		SchemaCommand::Maven => print_maven_schema(),
 		SchemaCommand::Npm => print_npm_schema(),
 		SchemaCommand::Pypi => print_pypi_schema(),
-		SchemaCommand::Repo => print_report_schema(),
+		SchemaCommand::Repo => print_report_schema(SchemaVersion::CURRENT),
+		SchemaCommand::Report(args) => print_report_schema(args.version.unwrap_or_default()),
 	}
 }
 
@@ -242,7 +642,7 @@ fn cmd_print_weights(config: &CliConfig) -> Result<()> {
 
 	// Print the output using indextree's debug pretty printer.
 	let output = print_root.debug_pretty_print(&print_tree.0);
-	shell::macros::println!("{output:?}");
+	Shell::println(format!("{output:?}"));
 
 	Ok(())
 }
@@ -488,10 +888,19 @@ fn check_policy_path(config: &CliConfig) -> StdResult<PathBuf, PathCheckError> {
 }
 
 fn cmd_plugin(args: PluginArgs, config: &CliConfig) -> ExitCode {
-	use crate::engine::{async_query, HcEngine, HcEngineImpl};
-	use std::sync::Arc;
+	use hipcheck_core::engine::{HcEngine, HcEngineImpl};
 	use tokio::task::JoinSet;
 
+	if let Some(command) = args.command {
+		return match run_plugin_command(command, config) {
+			Ok(()) => ExitCode::SUCCESS,
+			Err(e) => {
+				Shell::print_error(&e, Format::Human);
+				ExitCode::FAILURE
+			}
+		};
+	}
+
 	let working_dir = PathBuf::from("./target/debug");
 
 	let entrypoint1 = pathbuf!["dummy_rand_data"];
@@ -500,11 +909,19 @@ fn cmd_plugin(args: PluginArgs, config: &CliConfig) -> ExitCode {
 		name: "dummy/rand_data".to_owned(),
 		working_dir: working_dir.clone(),
 		entrypoint: entrypoint1.display().to_string(),
+		scratch_dir: None,
+		data_dir: None,
+		secrets: vec![],
+		limits: ResourceLimits::default(),
 	};
 	let plugin2 = Plugin {
 		name: "dummy/sha256".to_owned(),
 		working_dir: working_dir.clone(),
 		entrypoint: entrypoint2.display().to_string(),
+		scratch_dir: None,
+		data_dir: None,
+		secrets: vec![],
+		limits: ResourceLimits::default(),
 	};
 	let res_exec_config = if let Some(p) = config.exec() {
 		ExecConfig::from_file(p)
@@ -551,15 +968,13 @@ fn cmd_plugin(args: PluginArgs, config: &CliConfig) -> ExitCode {
 	};
 	if args.asynch {
 		// @Note - how to initiate multiple queries with async calls
-		let core = engine.core();
+		let dispatcher = engine.dispatcher();
 		let handle = HcEngineImpl::runtime();
 		handle.block_on(async move {
 			let mut futs = JoinSet::new();
 			for i in 1..10 {
-				let arc_core = Arc::clone(&core);
 				println!("Spawning");
-				futs.spawn(async_query(
-					arc_core,
+				futs.spawn(dispatcher.query(
 					"dummy".to_owned(),
 					"rand_data".to_owned(),
 					"rand_data".to_owned(),
@@ -608,6 +1023,306 @@ fn cmd_plugin(args: PluginArgs, config: &CliConfig) -> ExitCode {
 	ExitCode::SUCCESS
 }
 
+/// Run the `plugin search`, `plugin install`, and `plugin uninstall` commands.
+fn run_plugin_command(command: PluginCommand, config: &CliConfig) -> Result<()> {
+	let cache_path = config.cache().ok_or_else(|| {
+		hc_error!("cache path must be defined by cmdline arg or $HC_CACHE env var")
+	})?;
+	let plugin_cache = HcPluginCache::new(cache_path);
+
+	let exec_config = match config.exec() {
+		Some(p) => ExecConfig::from_file(p),
+		None => ExecConfig::find_file(),
+	}
+	.context("Failed to load the exec config")?;
+
+	// The registry given on the command line takes precedence over the exec config
+	let fetch_index = |registry: Option<url::Url>| -> Result<RegistryIndex> {
+		let url = registry
+			.or_else(|| exec_config.registry.as_ref().map(|r| r.0.clone()))
+			.ok_or_else(|| {
+				hc_error!(
+					"no plugin registry configured. Use `--registry` or add a `registry` node to the exec config"
+				)
+			})?;
+		RegistryIndex::fetch(&url, &exec_config.mirrors)
+	};
+
+	match command {
+		PluginCommand::Search(args) => {
+			let index = fetch_index(args.registry)?;
+			let found = index.search(args.query.as_deref().unwrap_or_default());
+			if found.is_empty() {
+				println!("No plugins found");
+			} else {
+				println!("{}", tabled::Table::new(found));
+			}
+		}
+		PluginCommand::Install(args) => {
+			let index = fetch_index(args.registry)?;
+			let entry = index.find(&args.plugin, args.version.as_deref())?;
+			let installed = install_plugin(
+				entry.plugin_id()?,
+				entry.manifest.clone(),
+				&plugin_cache,
+				&exec_config.mirrors,
+				&exec_config.trust,
+			)?;
+			for plugin_id in installed {
+				println!("Installed {}", plugin_id);
+			}
+			println!(
+				"\nUse it in a policy file with:\n\n\tplugin \"{}\" version=\"{}\"",
+				entry.plugin, entry.version
+			);
+		}
+		PluginCommand::Uninstall(args) => {
+			let name = PolicyPluginName::new(&args.plugin)?;
+			let removed =
+				plugin_cache.remove(&name.publisher.0, &name.name.0, args.version.as_deref())?;
+			if removed.is_empty() {
+				return Err(hc_error!("{} is not installed", args.plugin));
+			}
+			for version in removed {
+				println!("Uninstalled {} version {}", args.plugin, version);
+			}
+		}
+		PluginCommand::Describe(args) => {
+			let name = PolicyPluginName::new(&args.plugin)?;
+			let plugin_config: serde_json::Value = serde_json::from_str(&args.plugin_config)
+				.map_err(|e| hc_error!("invalid plugin configuration: {}", e))?;
+
+			// Use an installed copy of the plugin if there is one, otherwise
+			// install it from the registry.
+			let installed =
+				plugin_cache.find(&name.publisher.0, &name.name.0, args.version.as_deref());
+			let plugin_id = match installed {
+				Some(plugin_id) => plugin_id,
+				None => {
+					let index = fetch_index(args.registry)?;
+					let entry = index.find(&args.plugin, args.version.as_deref())?;
+					let plugin_id = entry.plugin_id()?;
+					install_plugin(
+						plugin_id.clone(),
+						entry.manifest.clone(),
+						&plugin_cache,
+						&exec_config.mirrors,
+						&exec_config.trust,
+					)?;
+					plugin_id
+				}
+			};
+
+			let scratch = HcScratchCache::new(cache_path);
+			let description = describe_plugin(
+				engine::HcEngineImpl::runtime(),
+				&plugin_id,
+				&plugin_cache,
+				&scratch,
+				ExecConfig::get_plugin_executor(&exec_config)?,
+				plugin_config,
+			)?;
+
+			println!("{}", serde_json::to_string_pretty(&description)?);
+		}
+		PluginCommand::Bundle(args) => {
+			let policy_path = config.policy().ok_or_else(|| {
+				hc_error!("a policy file must be given with -p/--policy to bundle its plugins")
+			})?;
+			let policy = PolicyFile::load_from(policy_path)?;
+			let bundled = create_bundle(
+				&policy.plugins.0,
+				&plugin_cache,
+				&exec_config.mirrors,
+				&exec_config.trust,
+				&args.out,
+			)?;
+			for plugin_id in bundled {
+				println!("Bundled {}", plugin_id);
+			}
+			println!("\nWrote bundle to {}", args.out.display());
+		}
+		PluginCommand::LoadBundle(args) => {
+			for plugin_id in load_bundle(&args.bundle, &plugin_cache, &exec_config.trust)? {
+				println!("Loaded {}", plugin_id);
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Run the `policy` command.
+fn cmd_policy(args: PolicyArgs, config: &CliConfig) -> ExitCode {
+	let result = match args.command {
+		PolicyCommand::Validate(args) => validate_policy(&args, config),
+		PolicyCommand::Eval(args) => eval_policy(&args),
+	};
+	match result {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			Shell::print_error(&e, Format::Human);
+			ExitCode::FAILURE
+		}
+	}
+}
+
+/// Check a policy file, then start the plugins it uses to check its policy
+/// expressions against their output.
+fn validate_policy(args: &PolicyValidateArgs, config: &CliConfig) -> Result<()> {
+	let mut validation = PolicyValidation::load_from(&args.path)?;
+
+	// The plugins can only be started if the policy file parsed.
+	if validation.is_valid() && !args.offline {
+		let cache_path = config.cache().ok_or_else(|| {
+			hc_error!("cache path must be defined by cmdline arg or $HC_CACHE env var")
+		})?;
+		let exec_config = match config.exec() {
+			Some(p) => ExecConfig::from_file(p),
+			None => ExecConfig::find_file(),
+		}
+		.context("Failed to load the exec config")?;
+		let policy = PolicyFile::load_from(&args.path)?;
+		let core = engine::start_plugins(
+			&policy,
+			&HcPluginCache::new(cache_path),
+			&HcRemoteFileCache::new(cache_path),
+			HcScratchCache::new(cache_path),
+			&HcPluginDataCache::new(cache_path),
+			HcResultCache::new(cache_path),
+			&exec_config.mirrors,
+			&exec_config.trust,
+			&exec_config.timeouts,
+			&exec_config.secrets,
+			&exec_config.limits,
+			ExecConfig::get_plugin_executor(&exec_config)?,
+		)
+		.context("Failed to start the plugins used by the policy file")?;
+		validation.check_plugin_outputs(&core);
+	}
+
+	for diagnostic in &validation.diagnostics {
+		println!("{}:{}", args.path.display(), diagnostic);
+	}
+
+	if validation.is_valid() {
+		println!("{} is valid", args.path.display());
+		Ok(())
+	} else {
+		Err(hc_error!(
+			"found {} problem(s) in {}",
+			validation.diagnostics.len(),
+			args.path.display()
+		))
+	}
+}
+
+/// Evaluate a policy expression against a JSON value, printing each step of
+/// the evaluation.
+fn eval_policy(args: &PolicyEvalArgs) -> Result<()> {
+	let context = match &args.input {
+		Some(path) => serde_json::from_str(&read_string(path)?)
+			.with_context(|| format!("failed to parse '{}' as JSON", path.display()))?,
+		None => serde_json::Value::Null,
+	};
+
+	let trace = std_trace(&args.expr, &context);
+	for step in &trace.steps {
+		println!("{}", step);
+	}
+
+	match trace
+		.result
+		.context("failed to evaluate policy expression")?
+	{
+		result @ Expr::Primitive(Primitive::Bool(_)) => {
+			println!("result: {}", result);
+			Ok(())
+		}
+		result => Err(hc_error!(
+			"policy expression returned {}, but policy expressions must return a boolean",
+			result
+		)),
+	}
+}
+
+/// Check that a checkout matches the tree a JSON report was made from.
+fn cmd_verify_report(args: &VerifyReportArgs) -> ExitCode {
+	match integrity::verify_report(&args.report, &args.target) {
+		Ok(message) => {
+			println!("{}", message);
+			ExitCode::SUCCESS
+		}
+		Err(e) => {
+			Shell::print_error(&e, Format::Human);
+			ExitCode::FAILURE
+		}
+	}
+}
+
+/// Run the `serve` command.
+fn cmd_serve(args: &ServeArgs, config: &CliConfig) -> ExitCode {
+	match start_server(args, config) {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			Shell::print_error(&e, Format::Human);
+			ExitCode::FAILURE
+		}
+	}
+}
+
+/// Load what the server analyzes with, then serve until the process is stopped.
+fn start_server(args: &ServeArgs, config: &CliConfig) -> Result<()> {
+	let home = config
+		.cache()
+		.map(ToOwned::to_owned)
+		.ok_or_else(|| hc_error!("can't find cache directory"))?;
+
+	let exec = load_exec_config(config.exec())?;
+
+	// Jobs may bring their own policy, so the server doesn't need one.
+	let policy = match config.policy() {
+		Some(path) => Some(load_policy_and_data(Some(path))?.0),
+		None => None,
+	};
+
+	serve(
+		SocketAddr::new(args.host, args.port),
+		ServeConfig {
+			home,
+			exec,
+			policy,
+			keep_reports: args.keep_reports,
+			max_policies: usize::from(args.max_policies),
+		},
+	)
+}
+
+/// Run the `plugin-pool` command, supervising a pooled plugin until it's idle.
+fn cmd_plugin_pool(args: PluginPoolArgs) -> ExitCode {
+	let supervised = Supervised {
+		record: args.record,
+		dir: args.dir,
+		port: args.port,
+		idle_timeout: Duration::from_secs(args.idle_timeout),
+		command: args.command,
+		limits: ResourceLimits {
+			memory: args.memory,
+			cpu_time: args.cpu_time.map(Duration::from_secs),
+			open_files: args.open_files,
+			user: args.user,
+		},
+	};
+
+	match supervise(supervised) {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			Shell::print_error(&e, Format::Human);
+			ExitCode::FAILURE
+		}
+	}
+}
+
 fn cmd_ready(config: &CliConfig) {
 	let ready = ReadyChecks {
 		hipcheck_version_check: check_hipcheck_version(),
@@ -705,6 +1420,27 @@ fn cmd_cache(args: CacheArgs, config: &CliConfig) -> ExitCode {
 			return ExitCode::FAILURE;
 		}
 	};
+	// The plugin results cache is separate from the clones cache, so don't
+	// load the clones cache index just to inspect it.
+	if let CacheOp::PluginResults {
+		plugin,
+		purge,
+		force,
+	} = op
+	{
+		let res = if purge {
+			HcResultCache::purge(path, plugin.as_deref(), force)
+		} else {
+			HcResultCache::list(path, plugin.as_deref())
+		};
+		return match res {
+			Ok(()) => ExitCode::SUCCESS,
+			Err(e) => {
+				println!("{e}");
+				ExitCode::FAILURE
+			}
+		};
+	}
 	let mut cache = HcRepoCache::new(path);
 	let res = match op {
 		CacheOp::List { scope, filter } => cache.list(scope, filter),
@@ -712,7 +1448,13 @@ fn cmd_cache(args: CacheArgs, config: &CliConfig) -> ExitCode {
 			scope,
 			filter,
 			force,
-		} => cache.delete(scope, filter, force),
+		} => cache
+			.delete(scope, filter, force)
+			// Plugin scratch directories are normally deleted at the end of each run,
+			// so any still around were left behind by a run that didn't exit cleanly.
+			.and_then(|_| HcScratchCache::delete_leftovers(path)),
+		CacheOp::Prune { limits, force } => cache.prune(&limits, force),
+		CacheOp::PluginResults { .. } => unreachable!("handled above"),
 	};
 	drop(cache);
 	if let Err(e) = res {
@@ -751,9 +1493,9 @@ fn cmd_print_config(config_path: Option<&Path>) {
 	}
 }
 
-/// Print the JSON schema of the report.
-fn print_report_schema() {
-	let schema = schema_for!(Report);
+/// Print the JSON schema of the report, as written in the given version.
+fn print_report_schema(version: SchemaVersion) {
+	let schema = version.report_schema();
 	let report_text = serde_json::to_string_pretty(&schema).unwrap();
 	println!("{}", report_text);
 }
@@ -778,62 +1520,75 @@ fn print_missing() {
 	println!("This feature is not implemented yet.");
 }
 
-/// An `f64` that is never `NaN`.
-type F64 = ordered_float::NotNan<f64>;
-
-// Constants for exiting with error codes.
-/// Indicates the program failed.
-const EXIT_FAILURE: i32 = 1;
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum CheckKind {
-	Npm,
-	Pypi,
-}
-
-impl CheckKind {
-	/// Get the name of the check.
-	const fn name(&self) -> &'static str {
-		match self {
-			CheckKind::Npm => "npm",
-			CheckKind::Pypi => "pypi",
-		}
-	}
-}
-
 // This is for testing purposes.
 /// Now that we're fully-initialized, run Hipcheck's analyses.
 #[allow(clippy::too_many_arguments)]
 fn run(
-	target: TargetSeed,
+	target: TargetRequest,
 	config_path: Option<PathBuf>,
 	home_dir: Option<PathBuf>,
 	policy_path: Option<PathBuf>,
 	exec_path: Option<PathBuf>,
 	format: Format,
+	all_concerns: bool,
 ) -> Result<Report> {
-	// Initialize the session.
-	let session = Session::new(
-		&target,
-		config_path,
-		home_dir,
-		policy_path,
-		exec_path,
-		format,
-	)?;
-
-	// Run analyses against a repo and score the results (score calls analyses that call metrics).
-	let phase = SpinnerPhase::start("analyzing and scoring results");
-
-	// Enable steady ticking on the spinner, since we currently don't increment it manually.
-	phase.enable_steady_tick(Duration::from_millis(250));
-
-	let scoring = score_results(&phase, &session)?;
+	analyze_complete(
+		|| {
+			Session::new(
+				&target,
+				config_path.clone(),
+				home_dir.clone(),
+				policy_path.clone(),
+				exec_path.clone(),
+				format,
+			)
+		},
+		all_concerns,
+	)
+}
 
-	phase.finish_successful();
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::{
+		atomic::{AtomicUsize, Ordering},
+		Barrier,
+	};
 
-	// Build the final report.
-	let report = build_report(&session, &scoring).context("failed to build final report")?;
+	#[test]
+	fn batches_run_targets_at_once_and_finish_in_order() {
+		let jobs = 4;
+		// Every worker has to take a target before any of them can finish, so
+		// the batch only completes if the targets really are checked at once.
+		let barrier = Barrier::new(jobs);
+		let checked = AtomicUsize::new(0);
+		let mut finished = Vec::new();
+
+		run_batch(
+			(0..12).collect(),
+			jobs,
+			|&target: &usize| {
+				// Of the first targets, later ones finish first, so results
+				// have to be reordered, and the rest are much slower
+				let millis = if target < jobs {
+					barrier.wait();
+					(jobs - target) as u64 * 10
+				} else {
+					200
+				};
+				thread::sleep(Duration::from_millis(millis));
+				checked.fetch_add(1, Ordering::SeqCst);
+				target
+			},
+			|target| finished.push((target, checked.load(Ordering::SeqCst))),
+		);
 
-	Ok(report)
+		let order = finished
+			.iter()
+			.map(|(target, _)| *target)
+			.collect::<Vec<_>>();
+		assert_eq!(order, (0..12).collect::<Vec<_>>());
+		// The first result is handed on before the whole batch is done
+		assert!(finished[0].1 < 12);
+	}
 }
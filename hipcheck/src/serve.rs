@@ -0,0 +1,525 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! `hc serve`, which analyzes targets submitted over HTTP.
+//!
+//! Jobs are submitted with `POST /analyze`, and run one at a time in the order
+//! they were submitted. Their reports are fetched with `GET /reports/<id>`, and
+//! kept for a while after the job finishes before being discarded.
+//!
+//! The plugins for each policy are started by the first job using it, and kept
+//! running for later jobs with an equal policy, so those don't pay for plugin
+//! startup again. Only the plugins for the policies used most recently are
+//! kept running; the rest are shut down.
+
+use crate::cli::CheckArgs;
+use axum::{
+	body::Bytes,
+	extract::{Path, State},
+	http::{header, StatusCode},
+	response::{IntoResponse, Response},
+	routing::{get, post},
+	Router,
+};
+use hipcheck_core::{
+	analyze_complete,
+	error::Result,
+	exec::ExecConfig,
+	hc_error,
+	plugin::HcPluginCore,
+	policy::PolicyFile,
+	report::Format,
+	session::{resolver::TargetRequest, sbom::expand_target_request, Session},
+	shell::Shell,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{HashMap, VecDeque},
+	net::SocketAddr,
+	path::PathBuf,
+	str::FromStr,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		mpsc::{self, Receiver, Sender},
+		Arc, Mutex,
+	},
+	thread,
+	time::{Duration, Instant},
+};
+
+/// What a server is analyzing with.
+pub struct ServeConfig {
+	/// Where plugins, clones, and cached results are kept.
+	pub home: PathBuf,
+	/// How plugins are run.
+	pub exec: ExecConfig,
+	/// The policy for jobs which don't give one.
+	pub policy: Option<PolicyFile>,
+	/// How long a finished job's report is kept.
+	pub keep_reports: Duration,
+	/// The most policies whose plugins are kept running between jobs.
+	pub max_policies: usize,
+}
+
+/// Serve the HTTP API on `addr` until the process is stopped.
+pub fn serve(addr: SocketAddr, config: ServeConfig) -> Result<()> {
+	let (queue, jobs) = mpsc::channel();
+
+	let server = Arc::new(Server {
+		policy: config.policy.clone(),
+		next_id: AtomicU64::new(1),
+		statuses: Mutex::new(Statuses::default()),
+		keep_reports: config.keep_reports,
+		queue: Mutex::new(queue),
+	});
+
+	{
+		let server = Arc::clone(&server);
+		thread::spawn(move || run_jobs(&server, jobs, &config));
+	}
+
+	let app = Router::new()
+		.route("/analyze", post(submit))
+		.route("/reports/:id", get(report))
+		.with_state(server);
+
+	let runtime = tokio::runtime::Builder::new_multi_thread()
+		.enable_all()
+		.build()?;
+
+	runtime.block_on(async {
+		let listener = tokio::net::TcpListener::bind(addr)
+			.await
+			.map_err(|e| hc_error!("failed to listen on {}: {}", addr, e))?;
+
+		Shell::eprintln(format!("Listening on http://{}", addr));
+
+		axum::serve(listener, app)
+			.await
+			.map_err(|e| hc_error!("server failed: {}", e))
+	})
+}
+
+/// The state shared by the request handlers and the job worker.
+struct Server {
+	/// The policy for jobs which don't give one.
+	policy: Option<PolicyFile>,
+	/// The ID of the next job to be submitted.
+	next_id: AtomicU64,
+	/// The status of every job which hasn't been discarded.
+	statuses: Mutex<Statuses>,
+	/// How long a finished job's report is kept.
+	keep_reports: Duration,
+	/// Where jobs are sent to the worker.
+	queue: Mutex<Sender<Job>>,
+}
+
+impl Server {
+	fn set_status(&self, id: u64, status: JobStatus) {
+		self.statuses
+			.lock()
+			.unwrap()
+			.set(id, status, self.keep_reports);
+	}
+
+	/// Get the status of a job, or `None` if there's no such job or it was
+	/// discarded.
+	fn status(&self, id: u64) -> Option<JobStatus> {
+		let mut statuses = self.statuses.lock().unwrap();
+		statuses.discard_finished(self.keep_reports);
+		statuses.jobs.get(&id).cloned()
+	}
+}
+
+/// The status of each job, with finished jobs discarded once they've been
+/// kept long enough, so a long-running server doesn't hold every report.
+#[derive(Debug, Default)]
+struct Statuses {
+	/// The status of each job, by ID.
+	jobs: HashMap<u64, JobStatus>,
+	/// When each finished job finished, oldest first.
+	finished: VecDeque<(Instant, u64)>,
+}
+
+impl Statuses {
+	fn set(&mut self, id: u64, status: JobStatus, keep: Duration) {
+		if matches!(status, JobStatus::Done(_) | JobStatus::Failed(_)) {
+			self.finished.push_back((Instant::now(), id));
+		}
+		self.jobs.insert(id, status);
+		self.discard_finished(keep);
+	}
+
+	/// Discard the jobs which finished more than `keep` ago.
+	fn discard_finished(&mut self, keep: Duration) {
+		while let Some(&(finished, id)) = self.finished.front() {
+			if finished.elapsed() < keep {
+				break;
+			}
+			self.jobs.remove(&id);
+			self.finished.pop_front();
+		}
+	}
+}
+
+/// An analysis waiting for the worker.
+struct Job {
+	id: u64,
+	target: TargetRequest,
+	policy: PolicyFile,
+	all_concerns: bool,
+}
+
+/// How far along a job is.
+#[derive(Debug, Clone)]
+enum JobStatus {
+	Queued,
+	Running,
+	/// Finished, with the report serialized as JSON.
+	Done(Arc<String>),
+	/// Couldn't be analyzed, with the reason why.
+	Failed(String),
+}
+
+/// The body of `POST /analyze`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AnalyzeRequest {
+	/// The target, given the same way as to `hc check`.
+	target: String,
+	/// The type of the target, if it can't be inferred, as with `hc check -t`.
+	#[serde(default)]
+	target_type: Option<String>,
+	/// The ref of the target to analyze.
+	#[serde(default, rename = "ref")]
+	refspec: Option<String>,
+	/// The contents of a policy file, instead of the server's policy.
+	#[serde(default)]
+	policy: Option<String>,
+	/// Whether to report every concern, instead of summarizing.
+	#[serde(default)]
+	all_concerns: bool,
+}
+
+impl AnalyzeRequest {
+	/// Get what to analyze, interpreting the target the same way `hc check` would.
+	fn target(&self) -> Result<TargetRequest> {
+		let mut args = Vec::new();
+		if let Some(refspec) = &self.refspec {
+			args.extend(["--ref", refspec]);
+		}
+		if let Some(target_type) = &self.target_type {
+			args.extend(["-t", target_type]);
+		}
+		args.extend(["--", &self.target]);
+
+		let mut targets = CheckArgs::from_args(args)?
+			.to_target_request()
+			.and_then(expand_target_request)?;
+
+		if targets.len() > 1 {
+			return Err(hc_error!(
+				"an SBOM of several components can't be analyzed as one job; submit a job for each component"
+			));
+		}
+
+		Ok(targets.remove(0).request)
+	}
+
+	/// Get the policy to analyze with, falling back to the server's.
+	///
+	/// Macros like `#env` aren't expanded in a submitted policy, since they'd
+	/// be resolved on the server rather than where the policy was written.
+	fn policy(&self, default: Option<&PolicyFile>) -> Result<PolicyFile> {
+		match (&self.policy, default) {
+			(Some(policy), _) => PolicyFile::from_str(policy),
+			(None, Some(policy)) => Ok(policy.clone()),
+			(None, None) => Err(hc_error!(
+				"no policy was given, and the server has no default policy"
+			)),
+		}
+	}
+}
+
+/// The body of responses about a job which hasn't produced a report.
+#[derive(Debug, Serialize)]
+struct StatusBody<'s> {
+	id: u64,
+	status: &'s str,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	error: Option<&'s str>,
+}
+
+/// Handle `POST /analyze`, queueing a job and responding with its ID.
+async fn submit(State(server): State<Arc<Server>>, body: Bytes) -> Response {
+	let request = match serde_json::from_slice::<AnalyzeRequest>(&body) {
+		Ok(request) => request,
+		Err(e) => return error(StatusCode::BAD_REQUEST, &format!("invalid request: {}", e)),
+	};
+
+	// Check the target and policy now, so mistakes are reported to whoever
+	// submitted them instead of failing the job later. Resolving the target
+	// may read files or ask a package registry, so it's done off the runtime.
+	let checked = {
+		let server = Arc::clone(&server);
+		tokio::task::spawn_blocking(move || -> Result<_> {
+			let target = request.target()?;
+			let policy = request.policy(server.policy.as_ref())?;
+			Ok((target, policy, request.all_concerns))
+		})
+		.await
+	};
+	let (target, policy, all_concerns) = match checked {
+		Ok(Ok(checked)) => checked,
+		Ok(Err(e)) => return error(StatusCode::BAD_REQUEST, &e.to_string()),
+		Err(e) => {
+			return error(
+				StatusCode::INTERNAL_SERVER_ERROR,
+				&format!("failed to check the request: {}", e),
+			)
+		}
+	};
+
+	let job = Job {
+		id: server.next_id.fetch_add(1, Ordering::Relaxed),
+		target,
+		policy,
+		all_concerns,
+	};
+	let id = job.id;
+	server.set_status(id, JobStatus::Queued);
+	if server.queue.lock().unwrap().send(job).is_err() {
+		server.set_status(id, JobStatus::Failed("the job worker has stopped".into()));
+	}
+
+	let status = StatusBody {
+		id,
+		status: "queued",
+		error: None,
+	};
+	let mut response = json(StatusCode::ACCEPTED, &status);
+	if let Ok(location) = format!("/reports/{}", id).parse() {
+		response.headers_mut().insert(header::LOCATION, location);
+	}
+	response
+}
+
+/// Handle `GET /reports/<id>`, responding with the report once the job is done.
+async fn report(State(server): State<Arc<Server>>, Path(id): Path<u64>) -> Response {
+	let status = server.status(id);
+
+	let (code, status, reason) = match &status {
+		// IDs are handed out in order, so an earlier one was a job whose report
+		// has been discarded
+		None if id < server.next_id.load(Ordering::Relaxed) => {
+			return error(
+				StatusCode::GONE,
+				&format!("the report of job {} has been discarded", id),
+			)
+		}
+		None => return error(StatusCode::NOT_FOUND, &format!("no job with ID {}", id)),
+		Some(JobStatus::Done(report)) => {
+			return (
+				StatusCode::OK,
+				[(header::CONTENT_TYPE, "application/json")],
+				report.to_string(),
+			)
+				.into_response()
+		}
+		Some(JobStatus::Queued) => (StatusCode::ACCEPTED, "queued", None),
+		Some(JobStatus::Running) => (StatusCode::ACCEPTED, "running", None),
+		Some(JobStatus::Failed(reason)) => (
+			StatusCode::INTERNAL_SERVER_ERROR,
+			"failed",
+			Some(reason.as_str()),
+		),
+	};
+
+	json(
+		code,
+		&StatusBody {
+			id,
+			status,
+			error: reason,
+		},
+	)
+}
+
+/// Respond with a value serialized as JSON.
+fn json<T: Serialize>(code: StatusCode, body: &T) -> Response {
+	match serde_json::to_string(body) {
+		Ok(body) => (code, [(header::CONTENT_TYPE, "application/json")], body).into_response(),
+		Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+	}
+}
+
+/// Respond with an error message.
+fn error(code: StatusCode, message: &str) -> Response {
+	json(code, &serde_json::json!({ "error": message }))
+}
+
+/// Run jobs in the order they're received, until the server is gone.
+fn run_jobs(server: &Server, jobs: Receiver<Job>, config: &ServeConfig) {
+	let mut cores = Cores::new(config.max_policies);
+
+	for job in jobs {
+		server.set_status(job.id, JobStatus::Running);
+
+		let status = match run_job(&job, &mut cores, config) {
+			Ok(report) => JobStatus::Done(Arc::new(report)),
+			Err(e) => {
+				// The plugins may be why the job failed, so they're restarted
+				// for the next job with this policy.
+				cores.remove(&job.policy);
+				JobStatus::Failed(e.to_string())
+			}
+		};
+
+		server.set_status(job.id, status);
+	}
+}
+
+/// The running plugins for the policies of recent jobs, most recently used
+/// first.
+///
+/// Once plugins for more policies than the limit would be running, the ones
+/// used longest ago are shut down, which happens when the last handle to
+/// their core is dropped.
+struct Cores<C = Arc<HcPluginCore>> {
+	max: usize,
+	cores: VecDeque<(PolicyFile, C)>,
+}
+
+impl<C: Clone> Cores<C> {
+	fn new(max: usize) -> Self {
+		Cores {
+			max: max.max(1),
+			cores: VecDeque::new(),
+		}
+	}
+
+	/// Get the running plugins for a policy, starting them with `start` if
+	/// they aren't running.
+	fn get_or_start(
+		&mut self,
+		policy: &PolicyFile,
+		start: impl FnOnce() -> Result<C>,
+	) -> Result<C> {
+		match self.cores.iter().position(|(p, _)| p == policy) {
+			Some(i) => {
+				let used = self.cores.remove(i).unwrap();
+				self.cores.push_front(used);
+			}
+			None => {
+				// Make room before starting more plugins, so no more than the
+				// limit are ever running at once
+				while self.cores.len() >= self.max {
+					self.cores.pop_back();
+					tracing::info!("shut down the plugins for the least recently used policy");
+				}
+				self.cores.push_front((policy.clone(), start()?));
+			}
+		}
+		Ok(self.cores[0].1.clone())
+	}
+
+	/// Shut down the plugins for a policy, if they're running.
+	fn remove(&mut self, policy: &PolicyFile) {
+		self.cores.retain(|(p, _)| p != policy);
+	}
+}
+
+/// Analyze a job's target, starting its policy's plugins if they aren't
+/// already running, and serialize its report.
+fn run_job(job: &Job, cores: &mut Cores, config: &ServeConfig) -> Result<String> {
+	let core = cores.get_or_start(&job.policy, || {
+		Session::start_core(&job.policy, &config.exec, &config.home)
+	})?;
+
+	let report = analyze_complete(
+		|| {
+			Session::with_core(
+				&job.target,
+				job.policy.clone(),
+				config.exec.clone(),
+				config.home.clone(),
+				Format::Json,
+				Arc::clone(&core),
+			)
+		},
+		job.all_concerns,
+	)?;
+
+	Ok(serde_json::to_string_pretty(&report)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use hipcheck_core::policy::policy_file::{
+		InvestigatePolicy, PolicyAnalyze, PolicyLimits, PolicyPatchList, PolicyPluginList,
+		PolicyWaivers,
+	};
+
+	fn policy(investigate: &str) -> PolicyFile {
+		PolicyFile {
+			plugins: PolicyPluginList::new(),
+			patch: PolicyPatchList::default(),
+			analyze: PolicyAnalyze::new(InvestigatePolicy(investigate.to_owned()), None),
+			limits: PolicyLimits::default(),
+			waivers: PolicyWaivers::default(),
+		}
+	}
+
+	#[test]
+	fn least_recently_used_plugins_are_shut_down() {
+		let (a, b, c) = (
+			policy("(gt 0.1 $)"),
+			policy("(gt 0.2 $)"),
+			policy("(gt 0.3 $)"),
+		);
+		let core = |id: u32| {
+			let core = Arc::new(id);
+			move || Ok(core)
+		};
+		let mut cores = Cores::new(2);
+
+		let first = cores.get_or_start(&a, core(1)).unwrap();
+		cores.get_or_start(&b, core(2)).unwrap();
+		// Using `a` again makes `b` the least recently used
+		assert_eq!(cores.get_or_start(&a, core(3)).unwrap(), first);
+		drop(first);
+
+		let started = cores.get_or_start(&c, core(4)).unwrap();
+		assert_eq!(*started, 4);
+		assert_eq!(cores.cores.len(), 2);
+		// `b` was shut down, so it's started again, in place of `a`
+		assert_eq!(*cores.get_or_start(&b, core(5)).unwrap(), 5);
+		assert_eq!(*cores.get_or_start(&c, core(6)).unwrap(), 4);
+
+		// Once the last handle to a core is dropped, its plugins stop
+		let running = cores.get_or_start(&c, core(7)).unwrap();
+		cores.remove(&c);
+		assert_eq!(Arc::strong_count(&running), 1);
+	}
+
+	#[test]
+	fn finished_jobs_are_discarded_after_being_kept() {
+		let mut statuses = Statuses::default();
+		let keep = Duration::from_secs(60 * 60);
+
+		statuses.set(1, JobStatus::Queued, keep);
+		statuses.set(2, JobStatus::Done(Arc::new("{}".to_owned())), keep);
+		assert_eq!(statuses.jobs.len(), 2);
+
+		// Unfinished jobs are kept however long they take
+		statuses.set(
+			3,
+			JobStatus::Failed("no such repository".to_owned()),
+			Duration::ZERO,
+		);
+		assert!(statuses.jobs.contains_key(&1));
+		assert!(!statuses.jobs.contains_key(&2));
+		assert!(!statuses.jobs.contains_key(&3));
+		assert!(statuses.finished.is_empty());
+	}
+}
@@ -1,6 +1,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{cli::SetupArgs, error::Result, hc_error, util::http::agent};
+use crate::cli::SetupArgs;
+use hipcheck_core::{
+	error::Result,
+	hc_error,
+	util::http::download::{progress_phase, Download},
+};
 use regex::Regex;
 use std::{
 	fs::File,
@@ -8,6 +13,7 @@ use std::{
 	sync::OnceLock,
 };
 use tar::Archive;
+use url::Url;
 use xz2::read::XzDecoder;
 
 static R_HC_SOURCE: OnceLock<Regex> = OnceLock::new();
@@ -83,7 +89,7 @@ pub enum SourceType {
 	Zip(PathBuf),
 }
 impl TryFrom<&Path> for SourceType {
-	type Error = crate::error::Error;
+	type Error = hipcheck_core::error::Error;
 	fn try_from(value: &Path) -> Result<SourceType> {
 		use SourceType::*;
 		let source_regex = get_source_regex();
@@ -156,18 +162,14 @@ pub fn try_resolve_source_path(args: &SetupArgs) -> Result<SetupSourcePath> {
 		// Since we're just getting the conf/target dir from here, we don't
 		// technically need to grab the right version
 		let f_name: &str = "hipcheck-x86_64-unknown-linux-gnu.tar.xz";
-		let remote = format!(
+		let remote = Url::parse(&format!(
 			"https://github.com/mitre/hipcheck/releases/download/hipcheck-v{}/{}",
 			env!("CARGO_PKG_VERSION"),
 			f_name
-		);
-
-		let mut out_file = File::create(f_name)?;
-		let agent = agent::agent();
+		))?;
 
 		println!("Downloading Hipcheck release from remote.");
-		let resp = agent.get(remote.as_str()).call()?;
-		std::io::copy(&mut resp.into_reader(), &mut out_file)?;
+		Download::new(&remote).to_file(Path::new(f_name), progress_phase(f_name))?;
 
 		return Ok(SetupSourcePath {
 			path: SourceType::Tar(std::fs::canonicalize(f_name)?),
@@ -1,9 +1,12 @@
 // SPDX-License-Identifier: Apache-2.0
 
-//! Plugin for querying how long it has been since a commit was last made to a repo
+//! Plugin for querying how long it has been since a commit or release was last made to a repo
 
 use clap::Parser;
-use hipcheck_sdk::{prelude::*, types::Target};
+use hipcheck_sdk::{
+	prelude::*,
+	types::{LocalGitRepo, Target},
+};
 use jiff::Timestamp;
 use serde::Deserialize;
 use std::{result::Result as StdResult, sync::OnceLock};
@@ -11,45 +14,78 @@ use std::{result::Result as StdResult, sync::OnceLock};
 #[derive(Deserialize)]
 struct Config {
 	weeks: Option<u16>,
+	releases: Option<bool>,
 }
 
 static CONFIG: OnceLock<Config> = OnceLock::new();
 
 /// Returns the span of time since the most recent commit to a Git repo as `jiff:Span` displayed as a String
 /// (Which means that anything expecting a `Span` must parse the output of this query appropriately)
+///
+/// If the plugin is configured to count releases, the span is instead since the most recent commit
+/// or tag, whichever is newer
 #[query(default)]
 async fn activity(engine: &mut PluginEngine, target: Target) -> Result<String> {
 	log::debug!("running activity query");
 
-	let repo = target.local;
+	let releases = CONFIG.get().and_then(|conf| conf.releases).unwrap_or(false);
 
+	time_since_last_activity(engine, target.local, releases).await
+}
+
+async fn time_since_last_activity(
+	engine: &mut PluginEngine,
+	repo: LocalGitRepo,
+	releases: bool,
+) -> Result<String> {
 	// Get today's date
 	let today = Timestamp::now();
 
 	// Get the date of the most recent commit.
-	let value = engine
-		.query("mitre/git/last_commit_date", repo)
-		.await
-		.map_err(|e| {
-			log::error!("failed to get last commit date for activity metric: {}", e);
-			Error::UnspecifiedQueryState
-		})?;
+	let last_commit_date = last_date(engine, "mitre/git/last_commit_date", &repo)
+		.await?
+		.ok_or(Error::UnexpectedPluginQueryInputFormat)?;
+
+	// A project may commit rarely yet still tag releases, such as for security fixes, so a
+	// recent release counts as activity too.
+	let mut last_activity = last_commit_date;
+	if releases {
+		if let Some(last_tag_date) = last_date(engine, "mitre/git/last_tag_date", &repo).await? {
+			last_activity = last_activity.max(last_tag_date);
+		}
+	}
 
-	let Value::String(date_string) = value else {
-		return Err(Error::UnexpectedPluginQueryInputFormat);
-	};
-	let last_commit_date: Timestamp = date_string.parse().map_err(|e| {
+	// Get the time between the most recent activity and today.
+	let time_since_last_activity = today.since(last_activity).map_err(|e| {
 		log::error!("{}", e);
 		Error::UnspecifiedQueryState
 	})?;
 
-	// Get the time between the most recent commit and today.
-	let time_since_last_commit = today.since(last_commit_date).map_err(|e| {
+	Ok(time_since_last_activity.to_string())
+}
+
+/// Query a date from the `mitre/git` plugin, which is `null` if there's nothing to date
+async fn last_date(
+	engine: &mut PluginEngine,
+	query: &str,
+	repo: &LocalGitRepo,
+) -> Result<Option<Timestamp>> {
+	let value = engine.query(query, repo.clone()).await.map_err(|e| {
+		log::error!("failed to get {} for activity metric: {}", query, e);
+		Error::UnspecifiedQueryState
+	})?;
+
+	let date_string = match value {
+		Value::String(date_string) => date_string,
+		Value::Null => return Ok(None),
+		_ => return Err(Error::UnexpectedPluginQueryInputFormat),
+	};
+	let date: Timestamp = date_string.parse().map_err(|e| {
 		log::error!("{}", e);
 		Error::UnspecifiedQueryState
 	})?;
 
-	Ok(time_since_last_commit.to_string())
+	Ok(Some(date))
 }
 
 #[derive(Clone, Debug)]
@@ -85,7 +121,7 @@ impl Plugin for ActivityPlugin {
 		))
 	}
 
-	queries! {}
+	queries! { activity }
 }
 
 #[derive(Parser, Debug)]
@@ -106,7 +142,6 @@ async fn main() -> Result<()> {
 mod test {
 	use super::*;
 
-	use hipcheck_sdk::types::LocalGitRepo;
 	use jiff::{Span, SpanRound, Unit};
 	use std::result::Result as StdResult;
 
@@ -114,6 +149,8 @@ mod test {
 		LocalGitRepo {
 			path: "/home/users/me/.cache/hipcheck/clones/github/expressjs/express/".to_string(),
 			git_ref: "main".to_string(),
+			subdir: None,
+			base: None,
 		}
 	}
 
@@ -123,10 +160,24 @@ mod test {
 
 		// when calling into query, the input repo gets passed to `last_commit_date`, lets assume it returns the datetime `output`
 		let mut mock_responses = MockResponses::new();
-		mock_responses.insert("mitre/git/last_commit_date", repo, Ok(output))?;
+		mock_responses.insert("mitre/git/last_commit_date", repo.clone(), Ok(output))?;
+		mock_responses.insert(
+			"mitre/git/last_tag_date",
+			repo,
+			Ok(Some("2024-09-02T08:00:00Z".to_string())),
+		)?;
 		Ok(mock_responses)
 	}
 
+	fn days_since(date: &str) -> Span {
+		let date: Timestamp = date.parse().unwrap();
+		Timestamp::now()
+			.since(date)
+			.unwrap()
+			.round(SpanRound::new().smallest(Unit::Day))
+			.unwrap()
+	}
+
 	#[tokio::test]
 	async fn test_activity() {
 		let repo = repo();
@@ -142,13 +193,22 @@ mod test {
 		let span: Span = output.parse().unwrap();
 		let result = span.round(SpanRound::new().smallest(Unit::Day)).unwrap();
 
-		let today = Timestamp::now();
-		let last_commit: Timestamp = "2024-06-19T19:22:45Z".parse().unwrap();
-		let expected = today
-			.since(last_commit)
-			.unwrap()
-			.round(SpanRound::new().smallest(Unit::Day))
+		let expected = days_since("2024-06-19T19:22:45Z");
+
+		assert_eq!(result, expected);
+	}
+
+	#[tokio::test]
+	async fn test_activity_with_releases() {
+		let mut engine = PluginEngine::mock(mock_responses().unwrap());
+		let output = time_since_last_activity(&mut engine, repo(), true)
+			.await
 			.unwrap();
+		let span: Span = output.parse().unwrap();
+		let result = span.round(SpanRound::new().smallest(Unit::Day)).unwrap();
+
+		// The tag is newer than the last commit, so the span is since the tag
+		let expected = days_since("2024-09-02T08:00:00Z");
 
 		assert_eq!(result, expected);
 	}
@@ -17,10 +17,11 @@ use hipcheck_sdk::{
 	prelude::*,
 	types::{LocalGitRepo, Target},
 };
+use jiff::{civil::Date, tz::TimeZone, Timestamp};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{
-	collections::{HashMap, HashSet},
+	collections::HashSet,
 	fmt::{self, Display, Formatter},
 	path::PathBuf,
 	result::Result as StdResult,
@@ -150,6 +151,53 @@ pub struct ContributorView {
 	pub commits: Vec<Commit>,
 }
 
+/// One person who may have contributed under several names or e-mail addresses
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct ContributorIdentity {
+	pub id: String,
+	pub contributors: Vec<Contributor>,
+}
+
+impl ContributorIdentity {
+	/// The contributor whose address is the identity's ID, to name the identity by
+	fn primary(&self) -> Option<&Contributor> {
+		self.contributors
+			.iter()
+			.find(|c| c.email.trim().eq_ignore_ascii_case(&self.id))
+			.or_else(|| self.contributors.first())
+	}
+}
+
+/// How much of the repository's history `mitre/git` covers
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct HistoryWindow {
+	pub max_commits: Option<u64>,
+	pub since: Option<String>,
+	pub commits: u64,
+	pub truncated: bool,
+}
+
+impl HistoryWindow {
+	/// A concern noting that older history was left out, if it was
+	fn concern(&self) -> Option<String> {
+		if !self.truncated {
+			return None;
+		}
+		let mut limits = Vec::new();
+		if let Some(max_commits) = self.max_commits {
+			limits.push(format!("at most {} commits", max_commits));
+		}
+		if let Some(since) = &self.since {
+			limits.push(format!("commits since {}", since));
+		}
+		Some(format!(
+			"Only the newest {} commit(s) were analyzed; the history was limited to {}",
+			self.commits,
+			limits.join(" and ")
+		))
+	}
+}
+
 /// A type which encapsulates checking whether a given string matches an org in the orgs file,
 /// based on the mode in question. If the mode is Independent, then you're looking for
 /// the strings that _don't match_ any of the hosts in the set. If the mode is Affiliated,
@@ -162,15 +210,18 @@ struct Affiliator<'haystack> {
 impl<'haystack> Affiliator<'haystack> {
 	/// Check whether the given string is a match for the set of hosts, based on the mode.
 	///
+	/// The date is that of the commit the string is from, for orgs which are only matched
+	/// over a range of dates.
+	///
 	/// If independent mode is on, you're looking for strings which do not match any of
 	/// the hosts.
 	///
 	/// If affiliated mode is on, you're looking for strings which do match one of the
 	/// hosts.
-	fn is_match(&self, s: &str) -> bool {
+	fn is_match(&self, s: &str, date: Option<Date>) -> bool {
 		match self.mode {
-			Mode::Independent => !self.patterns.is_match(s),
-			Mode::Affiliated => self.patterns.is_match(s),
+			Mode::Independent => !self.patterns.is_match(s, date),
+			Mode::Affiliated => self.patterns.is_match(s, date),
 			Mode::All => true,
 			Mode::None => false,
 		}
@@ -204,8 +255,10 @@ pub enum AffiliatedType {
 
 impl AffiliatedType {
 	fn is(affiliator: &Affiliator, commit_view: &CommitContributorView) -> AffiliatedType {
-		let author_is_match = affiliator.is_match(&commit_view.author.email);
-		let committer_is_match = affiliator.is_match(&commit_view.committer.email);
+		let written_on = commit_date(&commit_view.commit.written_on);
+		let committed_on = commit_date(&commit_view.commit.committed_on);
+		let author_is_match = affiliator.is_match(&commit_view.author.email, written_on);
+		let committer_is_match = affiliator.is_match(&commit_view.committer.email, committed_on);
 
 		match (author_is_match, committer_is_match) {
 			(true, true) => AffiliatedType::Both,
@@ -220,6 +273,12 @@ impl AffiliatedType {
 	}
 }
 
+/// Get the day a commit was written or committed on, if its timestamp is known
+fn commit_date(timestamp: &StdResult<String, String>) -> Option<Date> {
+	let timestamp: Timestamp = timestamp.as_ref().ok()?.parse().ok()?;
+	Some(timestamp.to_zoned(TimeZone::UTC).date())
+}
+
 // Can be hopefully removed once Submit has chunking
 mod chunk {
 	use super::*;
@@ -281,7 +340,8 @@ mod chunk {
 	}
 }
 
-/// Returns a boolean list with one entry per contributor to the repo
+/// Returns a boolean list with one entry per contributor to the repo, counting each person who
+/// contributed under several names or addresses once
 /// A `true` entry corresponds to an affiliated contributor
 #[query(default)]
 async fn affiliation(engine: &mut PluginEngine, key: Target) -> Result<Vec<bool>> {
@@ -345,7 +405,6 @@ async fn affiliation(engine: &mut PluginEngine, key: Target) -> Result<Vec<bool>
 	// 	.filter(|a| a.affiliated_type.is_affiliated())
 
 	let mut contributors = HashSet::new();
-	let mut contributor_freq_map = HashMap::new();
 
 	// Get the hashes for each commit
 	let hashes = commits.iter().map(|c| c.hash.clone()).collect();
@@ -420,8 +479,41 @@ async fn affiliation(engine: &mut PluginEngine, key: Target) -> Result<Vec<bool>
 	// 	let commit_view: CommitContributorView = serde_json::from_value(view_value)
 	// 		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
 
-	// Get the emails for each affiliated contributor
-	let emails = contributors.iter().map(|c| c.1.clone()).collect();
+	// Merge contributors who are the same person under several names or addresses, so each
+	// person is only counted once
+	let identities_value = engine
+		.query("mitre/git/contributor_identities", repo.clone())
+		.await
+		.map_err(|e| {
+			log::error!("failed to get contributor identities: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
+	let identities: Vec<ContributorIdentity> = serde_json::from_value(identities_value)
+		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
+
+	// A person is affiliated if they were under any of their addresses
+	let affiliated_emails: HashSet<String> = contributors.iter().map(|c| c.1.clone()).collect();
+	let affiliated_identities: Vec<&ContributorIdentity> = identities
+		.iter()
+		.filter(|identity| {
+			identity
+				.contributors
+				.iter()
+				.any(|c| affiliated_emails.contains(&c.email))
+		})
+		.collect();
+	let affiliations = identities
+		.iter()
+		.map(|identity| affiliated_identities.contains(&identity))
+		.collect();
+
+	// Get the emails of every affiliated person
+	let mut emails: Vec<String> = affiliated_identities
+		.iter()
+		.flat_map(|identity| identity.contributors.iter().map(|c| c.email.clone()))
+		.collect();
+	emails.sort();
+	emails.dedup();
 	// Repo with the email of every affiliated contributor
 	let contributor_batch_repo = BatchGitRepo {
 		local: repo.clone(),
@@ -442,39 +534,46 @@ async fn affiliation(engine: &mut PluginEngine, key: Target) -> Result<Vec<bool>
 	let contributor_views: Vec<ContributorView> = serde_json::from_value(contributor_values)
 		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
 
-	// For each affiliated contributor, count how many commits they contributed to,
-	// then add the contributor's name and its commit count to the contributor frequency hash map
-	for contributor_view in contributor_views {
-		let count = contributor_view.commits.len();
-		contributor_freq_map.insert(
-			format!(
-				"{} ({})",
-				contributor_view.contributor.name, contributor_view.contributor.email
-			),
-			count,
+	// For each affiliated person, count how many commits they contributed to under any of
+	// their addresses, and add the count as a concern
+	for identity in affiliated_identities {
+		let commits: HashSet<&str> = contributor_views
+			.iter()
+			.filter(|view| identity.contributors.contains(&view.contributor))
+			.flat_map(|view| view.commits.iter().map(|c| c.hash.as_str()))
+			.collect();
+		let Some(primary) = identity.primary() else {
+			continue;
+		};
+		let mut concern = format!(
+			"Contributor {} ({}) has count {}",
+			primary.name,
+			primary.email,
+			commits.len()
 		);
+		let others: Vec<String> = identity
+			.contributors
+			.iter()
+			.filter(|c| *c != primary)
+			.map(ToString::to_string)
+			.collect();
+		if !others.is_empty() {
+			concern.push_str(&format!(", also as {}", others.join(", ")));
+		}
+		engine.record_concern(concern);
 	}
 
-	let all_contributors_value = engine
-		.query("mitre/git/contributors", repo.clone())
+	// Contributors who only committed before the history window aren't checked
+	let window_value = engine
+		.query("mitre/git/history_window", repo)
 		.await
 		.map_err(|e| {
-			log::error!("failed to get list of all contributors to repo: {}", e);
+			log::error!("failed to get history window: {}", e);
 			Error::UnspecifiedQueryState
 		})?;
-	let all_contributors: Vec<Contributor> = serde_json::from_value(all_contributors_value)
+	let window: HistoryWindow = serde_json::from_value(window_value)
 		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
-	let all_emails: Vec<String> = all_contributors.iter().map(|c| c.email.clone()).collect();
-
-	let affiliated_emails: Vec<String> = contributors.iter().map(|c| c.1.clone()).collect();
-	let affiliations = all_emails
-		.iter()
-		.map(|e| affiliated_emails.contains(e))
-		.collect();
-
-	// Add each contributor-count pair as a concern
-	for (contributor, count) in contributor_freq_map.into_iter() {
-		let concern = format!("Contributor {} has count {}", contributor, count);
+	if let Some(concern) = window.concern() {
 		engine.record_concern(concern);
 	}
 
@@ -533,7 +632,7 @@ impl Plugin for AffiliationPlugin {
 		))
 	}
 
-	queries! {}
+	queries! { affiliation }
 }
 
 #[derive(Parser, Debug)]
@@ -560,6 +659,8 @@ mod test {
 		LocalGitRepo {
 			path: "/home/users/me/.cache/hipcheck/clones/github/foo/bar/".to_string(),
 			git_ref: "main".to_string(),
+			subdir: None,
+			base: None,
 		}
 	}
 
@@ -657,9 +758,30 @@ mod test {
 			.unwrap();
 		mock_responses
 			.insert(
-				"mitre/git/contributors",
+				"mitre/git/contributor_identities",
+				repo.clone(),
+				Ok(vec![
+					ContributorIdentity {
+						id: "jdoe@gmail.com".to_string(),
+						contributors: vec![contributor_2],
+					},
+					ContributorIdentity {
+						id: "jsmith@mitre.org".to_string(),
+						contributors: vec![contributor_1],
+					},
+				]),
+			)
+			.unwrap();
+		mock_responses
+			.insert(
+				"mitre/git/history_window",
 				repo,
-				Ok(vec![contributor_1, contributor_2]),
+				Ok(HistoryWindow {
+					max_commits: Some(3),
+					since: None,
+					commits: 3,
+					truncated: true,
+				}),
 			)
 			.unwrap();
 		mock_responses
@@ -717,8 +839,98 @@ mod test {
 		let num_affiliated = output.iter().filter(|&n| *n).count();
 		assert_eq!(num_affiliated, 1);
 		assert_eq!(
-			concerns[0],
+			concerns[0].message,
 			"Contributor Jane Doe (jdoe@gmail.com) has count 2"
-		)
+		);
+		assert_eq!(
+			concerns[1].message,
+			"Only the newest 3 commit(s) were analyzed; the history was limited to at most 3 commits"
+		);
+	}
+
+	#[tokio::test]
+	async fn test_affiliation_merges_identities() {
+		let orgs_file = pathbuf![&env::current_dir().unwrap(), "test", "test_orgs.kdl"];
+		let orgs_spec = OrgSpec::load_from(&orgs_file).unwrap();
+		ORGSSPEC.get_or_init(|| orgs_spec);
+
+		let repo = repo();
+		let jane = Contributor {
+			name: "Jane Doe".to_string(),
+			email: "jdoe@gmail.com".to_string(),
+		};
+		let jane_noreply = Contributor {
+			name: "Jane Doe".to_string(),
+			email: "12345+janedoe@users.noreply.github.com".to_string(),
+		};
+		let john = Contributor {
+			name: "John Smith".to_string(),
+			email: "jsmith@mitre.org".to_string(),
+		};
+		let commit = |hash: &str| Commit {
+			hash: hash.to_string(),
+			written_on: Ok("2024-06-21T20:00:00Z".to_string()),
+			committed_on: Ok("2024-06-21T21:00:00Z".to_string()),
+		};
+
+		// Jane also committed under her GitHub noreply address, which `mitre/git` merged
+		// with her other address
+		let mut mock_responses = mock_responses().unwrap();
+		mock_responses
+			.insert(
+				"mitre/git/contributor_identities",
+				repo.clone(),
+				Ok(vec![
+					ContributorIdentity {
+						id: "jdoe@gmail.com".to_string(),
+						contributors: vec![jane_noreply.clone(), jane.clone()],
+					},
+					ContributorIdentity {
+						id: "jsmith@mitre.org".to_string(),
+						contributors: vec![john],
+					},
+				]),
+			)
+			.unwrap();
+		mock_responses
+			.insert(
+				"mitre/git/batch_commits_for_contributor",
+				BatchGitRepo {
+					local: repo.clone(),
+					details: vec![
+						"12345+janedoe@users.noreply.github.com".to_string(),
+						"jdoe@gmail.com".to_string(),
+					],
+				},
+				Ok(vec![
+					ContributorView {
+						contributor: jane_noreply,
+						commits: vec![commit("ghi-789"), commit("jkl-012")],
+					},
+					ContributorView {
+						contributor: jane,
+						commits: vec![commit("def-456"), commit("ghi-789")],
+					},
+				]),
+			)
+			.unwrap();
+
+		let target = Target {
+			specifier: "bar".to_string(),
+			local: repo,
+			remote: None,
+			package: None,
+		};
+
+		let mut engine = PluginEngine::mock(mock_responses);
+		let output = affiliation(&mut engine, target).await.unwrap();
+
+		let concerns = engine.get_concerns();
+
+		assert_eq!(output, vec![true, false]);
+		assert_eq!(
+			concerns[0].message,
+			"Contributor Jane Doe (jdoe@gmail.com) has count 3, also as Jane Doe <12345+janedoe@users.noreply.github.com>"
+		);
 	}
 }
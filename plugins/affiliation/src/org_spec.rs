@@ -3,42 +3,88 @@
 //! Organization specification that can be parsed from a KDL file
 
 use crate::{
-	org_types::{Mode, OrgList, Strategy},
+	org_types::{DateRange, Mode, OrgList, Strategy},
 	util::{fs as file, kdl::extract_data},
 };
 use anyhow::{anyhow, Context as _, Result};
+use jiff::civil::Date;
 use kdl::KdlDocument;
 use serde::Deserialize;
 use std::{cell::RefCell, collections::HashMap, path::Path, str::FromStr};
 
+/// Checks e-mail addresses against the hosts of the orgs in an org spec.
+///
+/// A host matches an address's domain and its subdomains, so `example.com`
+/// matches `example.com` and `eng.example.com`. A host may also be written as:
+///
+/// - `*.example.com`, to match only the subdomains of `example.com`
+/// - `example.*`, to match `example` under any public suffix, like
+///   `example.com`, `example.de`, or `example.co.jp`, and their subdomains
 #[derive(Default)]
 pub struct Matcher<'haystack> {
-	cache: RefCell<HashMap<String, bool>>,
-	hosts: Vec<&'haystack str>,
+	/// The date ranges of the orgs each address previously matched
+	cache: RefCell<HashMap<String, Vec<DateRange>>>,
+	hosts: Vec<(&'haystack str, DateRange)>,
 }
 
 impl<'haystack> Matcher<'haystack> {
-	pub fn new(hosts: Vec<&'haystack str>) -> Matcher<'haystack> {
+	pub fn new(hosts: Vec<(&'haystack str, DateRange)>) -> Matcher<'haystack> {
 		Matcher {
 			hosts,
 			..Matcher::default()
 		}
 	}
 
-	pub fn is_match(&self, s: &str) -> bool {
-		if let Some(prior_result) = self.cache.borrow().get(s) {
-			return *prior_result;
-		}
+	/// Check whether an address matches the host of an org whose date range
+	/// includes the date of the commit the address is from, if it's known
+	pub fn is_match(&self, s: &str, date: Option<Date>) -> bool {
+		let mut cache = self.cache.borrow_mut();
+		let ranges = cache.entry(s.to_owned()).or_insert_with(|| {
+			let domain = domain(s);
+			self.hosts
+				.iter()
+				.filter(|(host, _)| host_matches(&host.to_lowercase(), &domain))
+				.map(|(_, dates)| *dates)
+				.collect()
+		});
 
-		for host in &self.hosts {
-			if s.ends_with(host) {
-				self.cache.borrow_mut().insert(s.to_owned(), true);
-				return true;
-			}
-		}
+		ranges.iter().any(|dates| dates.contains(date))
+	}
+}
+
+/// Get the domain of an e-mail address, in lowercase
+fn domain(address: &str) -> String {
+	let domain = address
+		.rsplit_once('@')
+		.map_or(address, |(_, domain)| domain);
+	domain.trim_end_matches('.').to_lowercase()
+}
 
-		false
+/// Check whether a domain matches a host from an org spec
+fn host_matches(host: &str, domain: &str) -> bool {
+	if let Some(parent) = host.strip_prefix("*.") {
+		return domain
+			.strip_suffix(parent)
+			.is_some_and(|subdomain| subdomain.ends_with('.'));
 	}
+
+	if let Some(name) = host.strip_suffix(".*") {
+		// Compare the part of the registrable domain before its public suffix,
+		// so `example.co.jp` is `example` under `co.jp`, not `co` under `jp`
+		let Some((registrable, suffix)) = psl::domain_str(domain).zip(psl::suffix_str(domain))
+		else {
+			return false;
+		};
+		return registrable
+			.strip_suffix(suffix)
+			.and_then(|label| label.strip_suffix('.'))
+			.is_some_and(|label| label == name);
+	}
+
+	domain == host
+		|| domain
+			.strip_suffix(host)
+			.is_some_and(|subdomain| subdomain.ends_with('.'))
 }
 
 /// An overall organization metric specification, with a strategy for how the
@@ -84,7 +130,7 @@ impl OrgSpec {
 
 			for org in &self.orgs.0 {
 				for host in org.hosts() {
-					hosts.push(host);
+					hosts.push((host, org.dates()));
 				}
 			}
 
@@ -98,7 +144,7 @@ impl OrgSpec {
 				.context("can't resolve orgs to analyze from spec")?
 			{
 				for host in org.hosts() {
-					hosts.push(host);
+					hosts.push((host, org.dates()));
 				}
 			}
 
@@ -114,13 +160,14 @@ impl OrgSpec {
 
 #[cfg(test)]
 mod test {
-	use super::OrgSpec;
+	use super::{Matcher, OrgSpec};
 
 	use crate::org_types::{
-		Host, Mode, Org, OrgList, Strategy, StrategyChild, StrategyCountry, StrategyOrg,
+		DateRange, Host, Mode, Org, OrgList, Strategy, StrategyChild, StrategyCountry, StrategyOrg,
 	};
+	use jiff::civil::date;
 	use pathbuf::pathbuf;
-	use std::env;
+	use std::{env, str::FromStr};
 
 	#[test]
 	fn test_org_spec_parser() {
@@ -154,4 +201,64 @@ mod test {
 
 		assert_eq!(expected, result);
 	}
+
+	#[test]
+	fn test_org_spec_parser_dates() {
+		let spec = OrgSpec::from_str(
+			r#"
+			strategy "affiliated"
+			orgs {
+				org "Example" country="Japan" since="2022-01-01" {
+					host "*.example.com"
+					host "example.*"
+				}
+			}
+			"#,
+		)
+		.unwrap();
+
+		let mut example =
+			Org::new("Example".to_string(), "Japan".to_string()).with_dates(DateRange {
+				since: Some(date(2022, 1, 1)),
+				until: None,
+			});
+		example.push(Host::new("*.example.com".to_string()));
+		example.push(Host::new("example.*".to_string()));
+
+		assert_eq!(spec.orgs.0, vec![example]);
+	}
+
+	#[test]
+	fn test_matcher_hosts() {
+		let matcher = Matcher::new(vec![
+			("mitre.org", DateRange::default()),
+			("*.example.com", DateRange::default()),
+			("acme.*", DateRange::default()),
+		]);
+
+		assert!(matcher.is_match("jsmith@mitre.org", None));
+		assert!(matcher.is_match("jsmith@lab.MITRE.org", None));
+		assert!(!matcher.is_match("jsmith@notmitre.org", None));
+
+		assert!(matcher.is_match("jdoe@eng.example.com", None));
+		assert!(!matcher.is_match("jdoe@example.com", None));
+
+		assert!(matcher.is_match("kim@acme.com", None));
+		assert!(matcher.is_match("kim@tokyo.acme.co.jp", None));
+		assert!(!matcher.is_match("kim@acme.example.com", None));
+	}
+
+	#[test]
+	fn test_matcher_dates() {
+		let dates = DateRange {
+			since: Some(date(2022, 1, 1)),
+			until: Some(date(2024, 1, 1)),
+		};
+		let matcher = Matcher::new(vec![("example.com", dates)]);
+
+		assert!(!matcher.is_match("jdoe@example.com", Some(date(2021, 12, 31))));
+		assert!(matcher.is_match("jdoe@example.com", Some(date(2022, 1, 1))));
+		assert!(!matcher.is_match("jdoe@example.com", Some(date(2024, 1, 1))));
+		assert!(matcher.is_match("jdoe@example.com", None));
+	}
 }
@@ -4,6 +4,7 @@
 
 use crate::{string_newtype_parse_kdl_node, util::kdl::ParseKdlNode};
 use anyhow::{anyhow, Context as _, Result};
+use jiff::civil::Date;
 use kdl::KdlNode;
 use serde::Deserialize;
 use std::str::FromStr;
@@ -174,11 +175,15 @@ impl ParseKdlNode for OrgList {
 
 /// A single organization, with a name, a list of hosts (which form the basis
 /// for the hosts used in the analyzer), and an affiliated country.
+///
+/// An org may also have a range of commit dates, outside of which its hosts
+/// aren't counted as affiliated with it.
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
 pub struct Org {
 	name: String,
 	country: String,
 	children: Vec<Host>,
+	dates: DateRange,
 }
 
 impl Org {
@@ -188,6 +193,7 @@ impl Org {
 			name,
 			country,
 			children: Vec::new(),
+			dates: DateRange::default(),
 		}
 	}
 
@@ -196,6 +202,12 @@ impl Org {
 		self.children.push(child);
 	}
 
+	#[allow(dead_code)]
+	pub fn with_dates(mut self, dates: DateRange) -> Self {
+		self.dates = dates;
+		self
+	}
+
 	/// Return the hosts in the org as `&str`
 	pub fn hosts(&self) -> Vec<&str> {
 		let mut hosts = Vec::new();
@@ -205,6 +217,11 @@ impl Org {
 
 		hosts
 	}
+
+	/// Return the range of commit dates the org's hosts are affiliated with it
+	pub fn dates(&self) -> DateRange {
+		self.dates
+	}
 }
 
 impl ParseKdlNode for Org {
@@ -220,6 +237,17 @@ impl ParseKdlNode for Org {
 		let name = node.entries().first()?.value().as_string()?.to_string();
 		let country = node.get("country")?.value().as_string()?.to_string();
 
+		let date = |key: &str| -> Option<Option<Date>> {
+			match node.get(key) {
+				Some(entry) => entry.value().as_string()?.parse().ok().map(Some),
+				None => Some(None),
+			}
+		};
+		let dates = DateRange {
+			since: date("since")?,
+			until: date("until")?,
+		};
+
 		let mut children = Vec::new();
 		for node in node.children()?.nodes() {
 			children.push(Host::parse_node(node)?)
@@ -229,10 +257,30 @@ impl ParseKdlNode for Org {
 			name,
 			country,
 			children,
+			dates,
 		})
 	}
 }
 
+/// The commit dates during which an org's hosts are affiliated with it, from
+/// `since` up to but not including `until`. Either end may be left open.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct DateRange {
+	pub since: Option<Date>,
+	pub until: Option<Date>,
+}
+
+impl DateRange {
+	/// Whether a commit made on a date is in the range. A commit with an
+	/// unknown date is in every range.
+	pub fn contains(&self, date: Option<Date>) -> bool {
+		let Some(date) = date else {
+			return true;
+		};
+		self.since.is_none_or(|since| date >= since) && self.until.is_none_or(|until| date < until)
+	}
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
 pub struct Host(pub String);
 string_newtype_parse_kdl_node!(Host, "host");
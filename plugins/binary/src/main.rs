@@ -6,27 +6,38 @@ mod fs;
 
 use crate::binary_detector::{detect_binary_files, BinaryFileDetector};
 use clap::Parser;
+use glob::{MatchOptions, Pattern};
 use hipcheck_sdk::{
 	prelude::*,
 	types::{LocalGitRepo, Target},
 };
 use pathbuf::pathbuf;
 use serde::Deserialize;
-use std::{path::PathBuf, result::Result as StdResult, sync::OnceLock};
+use std::{
+	path::{Path, PathBuf},
+	result::Result as StdResult,
+	sync::OnceLock,
+};
 
 pub static DETECTOR: OnceLock<BinaryFileDetector> = OnceLock::new();
 
+/// Globs matching the binary files a project is expected to have, which aren't counted.
+pub static ALLOWLIST: OnceLock<Vec<Pattern>> = OnceLock::new();
+
 #[derive(Deserialize)]
 struct RawConfig {
 	#[serde(rename = "binary-file")]
 	binary_file: Option<PathBuf>,
 	#[serde(rename = "binary-file-threshold")]
 	binary_file_threshold: Option<u64>,
+	#[serde(rename = "binary-file-allowlist")]
+	binary_file_allowlist: Option<String>,
 }
 
 struct Config {
 	binary_file: PathBuf,
 	opt_threshold: Option<u64>,
+	allowlist: Vec<Pattern>,
 }
 
 impl TryFrom<RawConfig> for Config {
@@ -40,9 +51,23 @@ impl TryFrom<RawConfig> for Config {
 			});
 		};
 		let opt_threshold = value.binary_file_threshold;
+		// The allowlist is given as globs separated by whitespace
+		let allowlist = value
+			.binary_file_allowlist
+			.unwrap_or_default()
+			.split_whitespace()
+			.map(|glob| {
+				Pattern::new(glob).map_err(|e| ConfigError::InvalidConfigValue {
+					field_name: "binary-file-allowlist".to_owned(),
+					value: glob.to_owned(),
+					reason: format!("invalid glob: {}", e),
+				})
+			})
+			.collect::<StdResult<Vec<_>, _>>()?;
 		Ok(Config {
 			binary_file,
 			opt_threshold,
+			allowlist,
 		})
 	}
 }
@@ -50,21 +75,54 @@ impl TryFrom<RawConfig> for Config {
 #[query]
 async fn files(_engine: &mut PluginEngine, value: LocalGitRepo) -> Result<Vec<PathBuf>> {
 	let bfd = DETECTOR.get().ok_or(Error::UnspecifiedQueryState)?;
-	let repo = pathbuf![&value.path];
-	let out: Vec<PathBuf> = detect_binary_files(&repo)
+	// Only search the subdirectory the target was scoped to, if any, while
+	// still reporting paths relative to the root of the repo
+	let subdir = PathBuf::from(value.subdir.unwrap_or_default());
+	let dir = pathbuf![&value.path].join(&subdir);
+	let out: Vec<PathBuf> = detect_binary_files(&dir)
 		.map_err(|_| Error::UnspecifiedQueryState)?
 		.into_iter()
 		.filter(|f| bfd.is_likely_binary_file(f))
+		.map(|f| subdir.join(f))
 		.collect();
 	Ok(out)
 }
 
+/// Check if a binary file matches one of the globs in the allowlist
+fn is_allowed(path: &Path) -> bool {
+	// Only `**` matches across directories
+	let options = MatchOptions {
+		require_literal_separator: true,
+		..MatchOptions::new()
+	};
+	ALLOWLIST.get().is_some_and(|allowlist| {
+		allowlist
+			.iter()
+			.any(|pattern| pattern.matches_path_with(path, options))
+	})
+}
+
 #[query(default)]
 async fn binary(engine: &mut PluginEngine, value: Target) -> Result<usize> {
 	let paths = files(engine, value.local).await?;
+	let (allowed, paths): (Vec<_>, Vec<_>) = paths.into_iter().partition(|f| is_allowed(f));
 	paths.iter().for_each(|f| {
-		engine.record_concern(format!("Found binary file at '{}'", f.to_string_lossy()))
+		let path = f.to_string_lossy();
+		engine.record_concern(
+			Concern::new(format!("Found binary file at '{}'", path))
+				.with_severity(Severity::Medium)
+				.with_file(path),
+		)
 	});
+	// Allowed binary files are still listed, so the allowlist can be reviewed
+	if !allowed.is_empty() {
+		let list = allowed
+			.iter()
+			.map(|f| format!("- `{}`", f.to_string_lossy()))
+			.collect::<Vec<_>>()
+			.join("\n");
+		engine.record_report_fragment(ReportFragment::markdown("Allowed binary files", list));
+	}
 	Ok(paths.len())
 }
 
@@ -98,6 +156,12 @@ impl Plugin for BinaryPlugin {
 				message: e.to_string(),
 			})?;
 
+		ALLOWLIST
+			.set(conf.allowlist)
+			.map_err(|_e| ConfigError::Unspecified {
+				message: "config was already set".to_owned(),
+			})?;
+
 		// Make the salsa db globally accessible
 		DETECTOR.set(bfd).map_err(|_e| ConfigError::Unspecified {
 			message: "config was already set".to_owned(),
@@ -113,11 +177,11 @@ impl Plugin for BinaryPlugin {
 
 	fn explain_default_query(&self) -> Result<Option<String>> {
 		Ok(Some(
-			"Returns number of detected binary files in a repo".to_owned(),
+			"Returns number of detected binary files in a repo, leaving out allowed ones".to_owned(),
 		))
 	}
 
-	queries! {}
+	queries! { files, binary }
 }
 
 #[derive(Parser, Debug)]
@@ -0,0 +1,235 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Plugin for checking that the default branch of a repo requires review and status checks before
+//! changes are merged into it
+
+use clap::Parser;
+use hipcheck_sdk::{prelude::*, types::Target};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{result::Result as StdResult, sync::OnceLock};
+
+#[derive(Deserialize)]
+struct RawConfig {
+	#[serde(rename = "required-reviews")]
+	required_reviews: Option<u64>,
+	#[serde(rename = "require-status-checks")]
+	require_status_checks: Option<bool>,
+}
+
+#[derive(Clone, Debug)]
+struct Config {
+	required_reviews: u64,
+	require_status_checks: bool,
+}
+
+impl From<RawConfig> for Config {
+	fn from(value: RawConfig) -> Config {
+		Config {
+			required_reviews: value.required_reviews.unwrap_or(1),
+			require_status_checks: value.require_status_checks.unwrap_or(true),
+		}
+	}
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+fn get_config() -> Result<&'static Config> {
+	CONFIG.get().ok_or_else(|| {
+		log::error!("tried to access config before set by Hipcheck core!");
+		Error::UnspecifiedQueryState
+	})
+}
+
+/// What the default branch of a repo requires before a change can be merged into it, as
+/// `mitre/github/branch_protection` returns it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct BranchProtection {
+	pub branch: String,
+	pub requires_pull_request: bool,
+	pub required_reviews: u64,
+	pub required_status_checks: Vec<String>,
+	pub classic_rules_visible: bool,
+}
+
+/// Returns what the default branch of a repo requires before a change can be merged into it
+#[query(default)]
+async fn branch_protection(engine: &mut PluginEngine, value: Target) -> Result<BranchProtection> {
+	let config = get_config()?;
+
+	// Confirm that the target is a GitHub repo
+	let Some(remote) = value.remote else {
+		log::error!("target repository does not have a remote repository URL");
+		return Err(Error::UnexpectedPluginQueryInputFormat);
+	};
+
+	let Some(known_remote) = remote.known_remote else {
+		log::error!("target repository is not a GitHub repository or else is missing GitHub repo information");
+		return Err(Error::UnexpectedPluginQueryInputFormat);
+	};
+
+	let value = engine
+		.query("mitre/github/branch_protection", known_remote)
+		.await
+		.map_err(|e| {
+			log::error!("failed to get branch protection from GitHub: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
+	let protection: BranchProtection =
+		serde_json::from_value(value).map_err(Error::InvalidJsonInQueryOutput)?;
+
+	let branch = &protection.branch;
+	if !protection.requires_pull_request {
+		engine.record_concern(format!(
+			"Changes can be pushed to the default branch '{}' without a pull request",
+			branch
+		));
+	} else if protection.required_reviews < config.required_reviews {
+		engine.record_concern(format!(
+			"Pull requests to the default branch '{}' need {} approving review(s), fewer than the {} required",
+			branch, protection.required_reviews, config.required_reviews
+		));
+	}
+	if config.require_status_checks && protection.required_status_checks.is_empty() {
+		engine.record_concern(format!(
+			"Changes can be merged into the default branch '{}' without passing any status checks",
+			branch
+		));
+	}
+	if !protection.classic_rules_visible {
+		engine.record_concern(format!(
+			"Classic branch protection rules for '{}' couldn't be read, so only rulesets were checked",
+			branch
+		));
+	}
+
+	Ok(protection)
+}
+
+#[derive(Clone, Debug)]
+struct BranchProtectionPlugin;
+
+impl Plugin for BranchProtectionPlugin {
+	const PUBLISHER: &'static str = "mitre";
+
+	const NAME: &'static str = "branch-protection";
+
+	fn set_config(&self, config: Value) -> StdResult<(), ConfigError> {
+		let conf: Config = serde_json::from_value::<RawConfig>(config)
+			.map_err(|e| ConfigError::Unspecified {
+				message: e.to_string(),
+			})?
+			.into();
+		CONFIG.set(conf).map_err(|_e| ConfigError::Unspecified {
+			message: "config was already set".to_owned(),
+		})
+	}
+
+	fn default_policy_expr(&self) -> Result<String> {
+		let conf = get_config()?;
+
+		let reviews = format!("(gte $/required_reviews {})", conf.required_reviews);
+		if conf.require_status_checks {
+			Ok(format!(
+				"(and {} (gt (count $/required_status_checks) 0))",
+				reviews
+			))
+		} else {
+			Ok(reviews)
+		}
+	}
+
+	fn explain_default_query(&self) -> Result<Option<String>> {
+		Ok(Some(
+			"The reviews and status checks required to merge into the repo's default branch"
+				.to_string(),
+		))
+	}
+
+	queries! { branch_protection }
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+	#[arg(long)]
+	port: u16,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+	let args = Args::try_parse().unwrap();
+	PluginServer::register(BranchProtectionPlugin)
+		.listen(args.port)
+		.await
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use hipcheck_sdk::types::{KnownRemote, LocalGitRepo, RemoteGitRepo};
+	use url::Url;
+
+	fn known_remote() -> KnownRemote {
+		KnownRemote::GitHub {
+			owner: "expressjs".to_string(),
+			repo: "express".to_string(),
+		}
+	}
+
+	fn target() -> Target {
+		Target {
+			specifier: "express".to_string(),
+			local: LocalGitRepo {
+				path: "/home/users/me/.cache/hipcheck/clones/github/expressjs/express/".to_string(),
+				git_ref: "main".to_string(),
+				subdir: None,
+				base: None,
+			},
+			remote: Some(RemoteGitRepo {
+				url: Url::parse("https://github.com/expressjs/express.git").unwrap(),
+				known_remote: Some(known_remote()),
+			}),
+			package: None,
+		}
+	}
+
+	#[tokio::test]
+	async fn test_branch_protection() {
+		CONFIG.get_or_init(|| Config {
+			required_reviews: 2,
+			require_status_checks: true,
+		});
+
+		let protection = BranchProtection {
+			branch: "master".to_string(),
+			requires_pull_request: true,
+			required_reviews: 1,
+			required_status_checks: vec![],
+			classic_rules_visible: true,
+		};
+		let mut mock_responses = MockResponses::new();
+		mock_responses
+			.insert(
+				"mitre/github/branch_protection",
+				known_remote(),
+				Ok(protection.clone()),
+			)
+			.unwrap();
+
+		let mut engine = PluginEngine::mock(mock_responses);
+		let result = branch_protection(&mut engine, target()).await.unwrap();
+		assert_eq!(result, protection);
+
+		let concerns = engine.get_concerns();
+		assert_eq!(concerns.len(), 2);
+		assert_eq!(
+			concerns[0].message,
+			"Pull requests to the default branch 'master' need 1 approving review(s), fewer than the 2 required"
+		);
+		assert_eq!(
+			concerns[1].message,
+			"Changes can be merged into the default branch 'master' without passing any status checks"
+		);
+	}
+}
@@ -0,0 +1,234 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Plugin for finding how many maintainers a project depends on
+//!
+//! The bus factor is the smallest number of contributors who together authored more than half of
+//! the commits in the recent window. Contributors are the identities from
+//! `mitre/git/contributor_summary`, so the same person committing under several names or e-mail
+//! addresses is only counted once.
+
+use clap::Parser;
+use hipcheck_sdk::{
+	prelude::*,
+	types::{LocalGitRepo, Target},
+};
+use jiff::{Span, Zoned};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{result::Result as StdResult, sync::OnceLock};
+
+#[derive(Deserialize)]
+struct Config {
+	#[serde(rename = "bus-factor-threshold")]
+	bus_factor_threshold: Option<u64>,
+	#[serde(rename = "month-count-threshold")]
+	month_count_threshold: Option<u32>,
+}
+
+impl Config {
+	/// The number of months back from today the recent window covers.
+	fn months(&self) -> u32 {
+		self.month_count_threshold.unwrap_or(12)
+	}
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// A locally stored git repo, with optional additional details
+/// The details will vary based on the query (e.g. a date, a committer e-mail address, a commit hash)
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DetailedGitRepo {
+	/// The local repo
+	local: LocalGitRepo,
+
+	/// Optional additional information for the query
+	pub details: Option<String>,
+}
+
+/// The fields of a contributor's activity from `mitre/git` this plugin needs.
+#[derive(Debug, Clone, Deserialize)]
+struct ContributorActivity {
+	id: String,
+	commits: i64,
+	total_commits: i64,
+}
+
+/// A contributor who is one of the project's key maintainers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Maintainer {
+	/// The canonical ID of the contributor.
+	pub id: String,
+	/// The number of commits the contributor authored in the recent window.
+	pub commits: u64,
+	/// The number of commits in the recent window.
+	pub total_commits: u64,
+}
+
+fn get_config() -> Result<&'static Config> {
+	CONFIG.get().ok_or_else(|| {
+		log::error!("tried to access config before set by Hipcheck core!");
+		Error::UnspecifiedQueryState
+	})
+}
+
+/// Find the fewest contributors who together authored more than half of the commits, taking the
+/// most active contributors first.
+fn key_maintainers(activity: &[ContributorActivity]) -> Vec<Maintainer> {
+	let mut activity = activity.to_vec();
+	activity.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.id.cmp(&b.id)));
+
+	let mut maintainers = Vec::new();
+	let mut covered = 0;
+	for contributor in activity {
+		if 2 * covered > contributor.total_commits {
+			break;
+		}
+		covered += contributor.commits;
+		maintainers.push(Maintainer {
+			id: contributor.id,
+			commits: contributor.commits.max(0) as u64,
+			total_commits: contributor.total_commits.max(0) as u64,
+		});
+	}
+	maintainers
+}
+
+/// Returns the fewest contributors who together authored more than half of the recent commits
+#[query]
+async fn maintainers(engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<Maintainer>> {
+	let config = get_config()?;
+
+	let window_start = Zoned::now()
+		.checked_sub(Span::new().months(i64::from(config.months())))
+		.map_err(|e| {
+			log::error!("failed to compute start of recent window: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
+
+	let detailed_repo = DetailedGitRepo {
+		local: repo,
+		details: Some(window_start.timestamp().to_string()),
+	};
+	let activity_value = engine
+		.query("mitre/git/contributor_summary", detailed_repo)
+		.await
+		.map_err(|e| {
+			log::error!("failed to get contributor summary: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
+	let activity: Vec<ContributorActivity> = serde_json::from_value(activity_value)
+		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
+
+	Ok(key_maintainers(&activity))
+}
+
+/// Returns the bus factor of the repo
+#[query(default)]
+async fn bus_factor(engine: &mut PluginEngine, target: Target) -> Result<u64> {
+	let maintainers = maintainers(engine, target.local).await?;
+
+	for maintainer in &maintainers {
+		engine.record_concern(format!(
+			"Contributor {} authored {} of {} recent commit(s)",
+			maintainer.id, maintainer.commits, maintainer.total_commits
+		));
+	}
+
+	Ok(maintainers.len() as u64)
+}
+
+#[derive(Clone, Debug)]
+struct BusFactorPlugin;
+
+impl Plugin for BusFactorPlugin {
+	const PUBLISHER: &'static str = "mitre";
+
+	const NAME: &'static str = "bus-factor";
+
+	fn set_config(&self, config: Value) -> StdResult<(), ConfigError> {
+		let conf =
+			serde_json::from_value::<Config>(config).map_err(|e| ConfigError::Unspecified {
+				message: e.to_string(),
+			})?;
+
+		if conf.months() == 0 {
+			return Err(ConfigError::InvalidConfigValue {
+				field_name: "month-count-threshold".to_owned(),
+				value: "0".to_owned(),
+				reason: "the recent window must cover at least one month".to_owned(),
+			});
+		}
+
+		CONFIG.set(conf).map_err(|_e| ConfigError::Unspecified {
+			message: "config was already set".to_owned(),
+		})
+	}
+
+	fn default_policy_expr(&self) -> Result<String> {
+		let conf = get_config()?;
+
+		Ok(format!(
+			"(gte $ {})",
+			conf.bus_factor_threshold.unwrap_or(2)
+		))
+	}
+
+	fn explain_default_query(&self) -> Result<Option<String>> {
+		Ok(Some(
+			"The fewest contributors who authored more than half of the recent commits".to_string(),
+		))
+	}
+
+	queries! { maintainers, bus_factor }
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+	#[arg(long)]
+	port: u16,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+	let args = Args::try_parse().unwrap();
+	PluginServer::register(BusFactorPlugin)
+		.listen(args.port)
+		.await
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn activity(counts: &[(&str, i64)]) -> Vec<ContributorActivity> {
+		let total_commits = counts.iter().map(|(_, commits)| commits).sum();
+		counts
+			.iter()
+			.map(|(id, commits)| ContributorActivity {
+				id: id.to_string(),
+				commits: *commits,
+				total_commits,
+			})
+			.collect()
+	}
+
+	fn ids(maintainers: &[Maintainer]) -> Vec<&str> {
+		maintainers.iter().map(|m| m.id.as_str()).collect()
+	}
+
+	#[test]
+	fn test_key_maintainers() {
+		// A single contributor with most of the commits is the only key maintainer.
+		let solo = activity(&[("bob", 5), ("alice", 40), ("carol", 5)]);
+		assert_eq!(ids(&key_maintainers(&solo)), vec!["alice"]);
+
+		// Exactly half isn't more than half.
+		let pair = activity(&[("alice", 10), ("bob", 6), ("carol", 4)]);
+		assert_eq!(ids(&key_maintainers(&pair)), vec!["alice", "bob"]);
+
+		let spread = activity(&[("a", 1), ("b", 1), ("c", 1), ("d", 1), ("e", 1)]);
+		assert_eq!(key_maintainers(&spread).len(), 3);
+
+		assert!(key_maintainers(&[]).is_empty());
+	}
+}
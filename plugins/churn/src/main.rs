@@ -8,7 +8,7 @@ mod types;
 use crate::{
 	linguist::*,
 	metric::*,
-	types::{CommitChurn, CommitChurnFreq, CommitDiff},
+	types::{CommitChurn, CommitChurnFreq, CommitDiff, HistoryWindow},
 };
 use clap::Parser;
 use hipcheck_sdk::{prelude::*, types::Target};
@@ -117,6 +117,8 @@ async fn commit_churns(
 			.file_diffs
 			.iter()
 			.filter(|file_diff| linguist.is_likely_source_file(file_diff.file_name.clone()))
+			// Moving a file doesn't churn it, so refactors which only move files don't count
+			.filter(|file_diff| !file_diff.is_pure_rename())
 			.collect::<Vec<_>>();
 
 		// Update files changed.
@@ -222,9 +224,20 @@ async fn commit_churns(
 #[query(default)]
 async fn churn(engine: &mut PluginEngine, value: Target) -> Result<Vec<f64>> {
 	let local = value.local;
-	let val_commits = engine.query("mitre/git/commit_diffs", local).await?;
+	let val_commits = engine
+		.query("mitre/git/commit_diffs", local.clone())
+		.await?;
 	let commits: Vec<CommitDiff> =
 		serde_json::from_value(val_commits).map_err(Error::InvalidJsonInQueryOutput)?;
+
+	// Note when the history was limited, since the scores are relative to the commits analyzed
+	let val_window = engine.query("mitre/git/history_window", local).await?;
+	let window: HistoryWindow =
+		serde_json::from_value(val_window).map_err(Error::InvalidJsonInQueryOutput)?;
+	if let Some(concern) = window.concern() {
+		engine.record_concern(concern);
+	}
+
 	Ok(commit_churns(engine, commits)
 		.await?
 		.iter()
@@ -302,7 +315,7 @@ impl Plugin for ChurnPlugin {
 		))
 	}
 
-	queries! {}
+	queries! { commit_churns, churn }
 }
 
 #[derive(Parser, Debug)]
@@ -361,18 +374,21 @@ mod test {
 					additions: Some(80),
 					deletions: Some(0),
 					patch: "".to_owned(),
+					renamed_from: None,
 				},
 				FileDiff {
 					file_name: "bar.java".to_owned(),
 					additions: Some(10),
 					deletions: Some(15),
 					patch: "".to_owned(),
+					renamed_from: None,
 				},
 				FileDiff {
 					file_name: "baz.java".to_owned(),
 					additions: Some(10),
 					deletions: Some(5),
 					patch: "".to_owned(),
+					renamed_from: None,
 				},
 			],
 		};
@@ -385,18 +401,21 @@ mod test {
 					additions: Some(100),
 					deletions: Some(1200),
 					patch: "".to_owned(),
+					renamed_from: None,
 				},
 				FileDiff {
 					file_name: "bar.java".to_owned(),
 					additions: Some(1800),
 					deletions: Some(300),
 					patch: "".to_owned(),
+					renamed_from: None,
 				},
 				FileDiff {
 					file_name: "baz.java".to_owned(),
 					additions: Some(100),
 					deletions: Some(0),
 					patch: "".to_owned(),
+					renamed_from: None,
 				},
 			],
 		};
@@ -430,4 +449,54 @@ mod test {
 		assert_eq!(freqs[0].churn, -1.0);
 		assert_eq!(freqs[1].churn, 1.0);
 	}
+
+	/// The test data, with a third commit so churn isn't split evenly around the mean
+	fn test_data_with_third_commit() -> Vec<CommitDiff> {
+		let c3 = Commit {
+			hash: "ghi789".to_owned(),
+			written_on: Ok("10/24/2024".to_owned()),
+			committed_on: Ok("10/24/2024".to_owned()),
+		};
+		let d3 = Diff {
+			additions: Some(40),
+			deletions: Some(10),
+			file_diffs: vec![FileDiff {
+				file_name: "foo.java".to_owned(),
+				additions: Some(40),
+				deletions: Some(10),
+				patch: "".to_owned(),
+				renamed_from: None,
+			}],
+		};
+		let mut data = test_data();
+		data.push(CommitDiff {
+			commit: c3,
+			diff: d3,
+		});
+		data
+	}
+
+	#[tokio::test]
+	async fn test_renames_are_not_churn() {
+		init_db_if_uninited();
+
+		let mut engine = PluginEngine::mock(MockResponses::new());
+		let without_moves = commit_churns(&mut engine, test_data_with_third_commit())
+			.await
+			.unwrap();
+
+		// Moving files in the last commit, without changing them, leaves the churn the same
+		let mut data = test_data_with_third_commit();
+		data[2].diff.file_diffs.extend((0..50).map(|i| FileDiff {
+			file_name: format!("src/moved{}.java", i),
+			additions: Some(0),
+			deletions: Some(0),
+			patch: "".to_owned(),
+			renamed_from: Some(format!("moved{}.java", i)),
+		}));
+		let with_moves = commit_churns(&mut engine, data).await.unwrap();
+
+		let churns = |freqs: &[CommitChurnFreq]| freqs.iter().map(|f| f.churn).collect::<Vec<_>>();
+		assert_eq!(churns(&without_moves), churns(&with_moves));
+	}
 }
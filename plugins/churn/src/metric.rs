@@ -7,10 +7,12 @@ use crate::{
 use std::iter::Iterator;
 
 /// Check if a commit diff is a likely source file.
+///
+/// Source files which were only moved don't count, since moving them changes no code.
 pub fn is_likely_source_file_cd(linguist: &Linguist, commit_diff: &CommitDiff) -> bool {
 	let mut has_source = false;
 	for fd in commit_diff.diff.file_diffs.iter() {
-		has_source |= linguist.is_likely_source_file(fd.file_name.clone());
+		has_source |= !fd.is_pure_rename() && linguist.is_likely_source_file(fd.file_name.clone());
 	}
 	has_source
 }
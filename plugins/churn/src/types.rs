@@ -17,6 +17,15 @@ pub struct FileDiff {
 	pub additions: Option<i64>,
 	pub deletions: Option<i64>,
 	pub patch: String,
+	#[serde(default)]
+	pub renamed_from: Option<String>,
+}
+
+impl FileDiff {
+	/// Whether the file was only renamed or moved, without changing its contents
+	pub fn is_pure_rename(&self) -> bool {
+		self.renamed_from.is_some() && self.additions == Some(0) && self.deletions == Some(0)
+	}
 }
 
 #[derive(Clone, Debug, Serialize, PartialEq, Eq, JsonSchema, Deserialize)]
@@ -46,3 +55,33 @@ pub struct CommitChurn {
 	pub files_changed: i64,
 	pub lines_changed: i64,
 }
+
+/// How much of the repository's history `mitre/git` covers.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct HistoryWindow {
+	pub max_commits: Option<u64>,
+	pub since: Option<String>,
+	pub commits: u64,
+	pub truncated: bool,
+}
+
+impl HistoryWindow {
+	/// A concern noting that older history was left out, if it was.
+	pub fn concern(&self) -> Option<String> {
+		if !self.truncated {
+			return None;
+		}
+		let mut limits = Vec::new();
+		if let Some(max_commits) = self.max_commits {
+			limits.push(format!("at most {} commits", max_commits));
+		}
+		if let Some(since) = &self.since {
+			limits.push(format!("commits since {}", since));
+		}
+		Some(format!(
+			"Only the newest {} commit(s) were analyzed; the history was limited to {}",
+			self.commits,
+			limits.join(" and ")
+		))
+	}
+}
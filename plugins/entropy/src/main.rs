@@ -136,9 +136,20 @@ async fn commit_entropies(
 #[query(default)]
 async fn entropy(engine: &mut PluginEngine, value: Target) -> Result<Vec<f64>> {
 	let local = value.local;
-	let val_commits = engine.query("mitre/git/commit_diffs", local).await?;
+	let val_commits = engine
+		.query("mitre/git/commit_diffs", local.clone())
+		.await?;
 	let commits: Vec<CommitDiff> =
 		serde_json::from_value(val_commits).map_err(Error::InvalidJsonInQueryOutput)?;
+
+	// Note when the history was limited, since the scores are relative to the commits analyzed
+	let val_window = engine.query("mitre/git/history_window", local).await?;
+	let window: HistoryWindow =
+		serde_json::from_value(val_window).map_err(Error::InvalidJsonInQueryOutput)?;
+	if let Some(concern) = window.concern() {
+		engine.record_concern(concern);
+	}
+
 	Ok(commit_entropies(engine, commits)
 		.await?
 		.iter()
@@ -214,7 +225,7 @@ impl Plugin for EntropyPlugin {
 		))
 	}
 
-	queries! {}
+	queries! { commit_entropies, entropy }
 }
 
 #[derive(Parser, Debug)]
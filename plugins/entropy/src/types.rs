@@ -75,3 +75,33 @@ pub struct GraphemeFreqView<'gra> {
 	/// The freq (fine to copy)
 	pub freq: f64,
 }
+
+/// How much of the repository's history `mitre/git` covers.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct HistoryWindow {
+	pub max_commits: Option<u64>,
+	pub since: Option<String>,
+	pub commits: u64,
+	pub truncated: bool,
+}
+
+impl HistoryWindow {
+	/// A concern noting that older history was left out, if it was.
+	pub fn concern(&self) -> Option<String> {
+		if !self.truncated {
+			return None;
+		}
+		let mut limits = Vec::new();
+		if let Some(max_commits) = self.max_commits {
+			limits.push(format!("at most {} commits", max_commits));
+		}
+		if let Some(since) = &self.since {
+			limits.push(format!("commits since {}", since));
+		}
+		Some(format!(
+			"Only the newest {} commit(s) were analyzed; the history was limited to {}",
+			self.commits,
+			limits.join(" and ")
+		))
+	}
+}
@@ -0,0 +1,198 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Plugin for checking how far behind their latest versions a project's
+//! dependencies are
+
+mod manifest;
+mod registry;
+
+use crate::{
+	manifest::{find_dependencies, Dependency},
+	registry::{latest_version, majors_behind},
+};
+use clap::Parser;
+use hipcheck_sdk::{
+	prelude::*,
+	types::{LocalGitRepo, Target},
+};
+use schemars::JsonSchema;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path, result::Result as StdResult, sync::OnceLock};
+
+#[derive(Deserialize)]
+struct RawConfig {
+	#[serde(rename = "lag-threshold")]
+	lag_threshold: Option<u64>,
+	#[serde(rename = "percent-threshold")]
+	percent_threshold: Option<f64>,
+}
+
+#[derive(Clone, Debug)]
+struct PolicyExprConf {
+	pub lag_threshold: u64,
+	pub percent_threshold: f64,
+}
+
+impl TryFrom<RawConfig> for PolicyExprConf {
+	type Error = hipcheck_sdk::error::ConfigError;
+	fn try_from(value: RawConfig) -> StdResult<PolicyExprConf, Self::Error> {
+		let lag_threshold = value.lag_threshold.unwrap_or(2);
+		if lag_threshold == 0 {
+			return Err(ConfigError::InvalidConfigValue {
+				field_name: "lag-threshold".to_owned(),
+				value: lag_threshold.to_string(),
+				reason: "a dependency must be at least 1 major version behind to be outdated"
+					.to_owned(),
+			});
+		}
+		let percent_threshold = value.percent_threshold.unwrap_or(0.2);
+		if !(0.0..=1.0).contains(&percent_threshold) {
+			return Err(ConfigError::InvalidConfigValue {
+				field_name: "percent-threshold".to_owned(),
+				value: percent_threshold.to_string(),
+				reason: "percentage must be between 0.0 and 1.0, inclusive".to_owned(),
+			});
+		}
+		Ok(PolicyExprConf {
+			lag_threshold,
+			percent_threshold,
+		})
+	}
+}
+
+/// A dependency, and how it compares to the latest version in its registry.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+struct DependencyFreshness {
+	/// The dependency as declared
+	dependency: Dependency,
+	/// The latest stable version in the dependency's registry
+	latest: Version,
+	/// How many major versions behind the latest version the dependency is
+	majors_behind: u64,
+}
+
+/// Returns every dependency declared by the repository's package manifests
+#[query]
+async fn dependencies(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<Dependency>> {
+	find_dependencies(Path::new(&repo.path)).map_err(|e| {
+		log::error!("failed to search for dependencies: {}", e);
+		Error::UnspecifiedQueryState
+	})
+}
+
+/// Returns every dependency whose latest version could be found, compared to
+/// that version
+#[query]
+async fn outdated(
+	engine: &mut PluginEngine,
+	repo: LocalGitRepo,
+) -> Result<Vec<DependencyFreshness>> {
+	let dependencies = dependencies(engine, repo).await?;
+
+	// The same package is often declared by several manifests
+	let mut latest_versions = HashMap::new();
+	let mut freshness = Vec::new();
+
+	for dependency in dependencies {
+		let latest = latest_versions
+			.entry((dependency.ecosystem, dependency.name.clone()))
+			.or_insert_with(|| latest_version(dependency.ecosystem, &dependency.name));
+
+		match latest {
+			Ok(latest) => freshness.push(DependencyFreshness {
+				majors_behind: majors_behind(&dependency.version, latest),
+				latest: latest.clone(),
+				dependency,
+			}),
+			Err(e) => log::warn!("skipping '{}': {:#}", dependency.name, e),
+		}
+	}
+
+	Ok(freshness)
+}
+
+/// Returns how many major versions behind the latest version each dependency is
+#[query(default)]
+async fn freshness(engine: &mut PluginEngine, value: Target) -> Result<Vec<u64>> {
+	let freshness = outdated(engine, value.local).await?;
+	freshness
+		.iter()
+		.filter(|f| f.majors_behind > 0)
+		.for_each(|f| {
+			let message = format!(
+				"{} dependency '{}' ({} in '{}') is {} major version(s) behind the latest, {}",
+				f.dependency.ecosystem,
+				f.dependency.name,
+				f.dependency.requirement,
+				f.dependency.path.display(),
+				f.majors_behind,
+				f.latest
+			);
+			engine.record_concern(
+				Concern::new(message)
+					.with_file(f.dependency.path.to_string_lossy())
+					.with_identifier(&f.dependency.name),
+			)
+		});
+	Ok(freshness.iter().map(|f| f.majors_behind).collect())
+}
+
+#[derive(Clone, Debug, Default)]
+struct FreshnessPlugin {
+	policy_conf: OnceLock<PolicyExprConf>,
+}
+
+impl Plugin for FreshnessPlugin {
+	const PUBLISHER: &'static str = "mitre";
+	const NAME: &'static str = "freshness";
+
+	fn set_config(&self, config: Value) -> StdResult<(), ConfigError> {
+		// Deserialize and validate the config struct
+		let conf: PolicyExprConf = serde_json::from_value::<RawConfig>(config)
+			.map_err(|e| ConfigError::Unspecified {
+				message: e.to_string(),
+			})?
+			.try_into()?;
+
+		// Store the PolicyExprConf to be accessed only in the `default_policy_expr()` impl
+		self.policy_conf
+			.set(conf)
+			.map_err(|_| ConfigError::Unspecified {
+				message: "plugin was already configured".to_string(),
+			})
+	}
+
+	fn default_policy_expr(&self) -> Result<String> {
+		match self.policy_conf.get() {
+			None => Err(Error::UnspecifiedQueryState),
+			Some(policy_conf) => Ok(format!(
+				"(lte (divz (count (filter (gte {}) $)) (count $)) {})",
+				policy_conf.lag_threshold, policy_conf.percent_threshold
+			)),
+		}
+	}
+
+	fn explain_default_query(&self) -> Result<Option<String>> {
+		Ok(Some(
+			"Returns how many major versions behind the latest version each dependency of a repo is"
+				.to_owned(),
+		))
+	}
+
+	queries! { dependencies, outdated, freshness }
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+	#[arg(long)]
+	port: u16,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+	let args = Args::try_parse().unwrap();
+	PluginServer::register(FreshnessPlugin::default())
+		.listen(args.port)
+		.await
+}
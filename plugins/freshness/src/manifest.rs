@@ -0,0 +1,337 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Finding the dependencies declared by the package manifests in a source
+//! tree, and the versions they're pinned to.
+
+use anyhow::{Context as _, Result};
+use schemars::JsonSchema;
+use semver::Version;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::{
+	collections::HashSet,
+	fmt::{self, Display, Formatter},
+	fs,
+	path::{Path, PathBuf},
+};
+use toml::{Table, Value as TomlValue};
+use walkdir::{DirEntry, WalkDir};
+
+/// Directories which hold dependencies or build output rather than the
+/// project's own manifests.
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target", "vendor"];
+
+/// The sections of a `Cargo.toml` which declare dependencies.
+const CARGO_SECTIONS: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// The fields of a `package.json` which declare dependencies.
+const NPM_SECTIONS: &[&str] = &["dependencies", "devDependencies", "optionalDependencies"];
+
+/// The package ecosystems whose manifests are understood.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+	/// Rust crates, from `Cargo.toml` and crates.io.
+	Cargo,
+	/// JavaScript packages, from `package.json` and the npm registry.
+	Npm,
+	/// Python packages, from `requirements.txt` and PyPI.
+	Pypi,
+}
+
+impl Display for Ecosystem {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Ecosystem::Cargo => write!(f, "Cargo"),
+			Ecosystem::Npm => write!(f, "npm"),
+			Ecosystem::Pypi => write!(f, "PyPI"),
+		}
+	}
+}
+
+/// A dependency declared by a package manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Dependency {
+	/// The ecosystem the dependency comes from.
+	pub ecosystem: Ecosystem,
+	/// The name of the dependency in its registry.
+	pub name: String,
+	/// The version requirement as written in the manifest.
+	pub requirement: String,
+	/// The manifest declaring the dependency, relative to the root of the tree.
+	pub path: PathBuf,
+	/// The oldest version the requirement allows.
+	pub version: Version,
+}
+
+/// Find the dependencies declared by every manifest in a source tree.
+///
+/// Dependencies without a version requirement, like those taken from a path
+/// or a git repository, are left out, as are manifests which can't be parsed.
+pub fn find_dependencies(root: &Path) -> Result<Vec<Dependency>> {
+	let mut dependencies = Vec::new();
+	let mut seen = HashSet::new();
+
+	let entries = WalkDir::new(root)
+		.sort_by_file_name()
+		.into_iter()
+		.filter_entry(|entry| !is_skipped_dir(entry));
+
+	for entry in entries {
+		let entry = entry.context("failed to walk the repository")?;
+		let Some(ecosystem) = manifest_ecosystem(entry.file_name().to_str()) else {
+			continue;
+		};
+
+		let path = entry.path();
+		let relative = path.strip_prefix(root).unwrap_or(path).to_path_buf();
+		let contents = match fs::read_to_string(path) {
+			Ok(contents) => contents,
+			Err(e) => {
+				log::warn!("failed to read '{}': {}", relative.display(), e);
+				continue;
+			}
+		};
+
+		let found = match ecosystem {
+			Ecosystem::Cargo => cargo_dependencies(&contents),
+			Ecosystem::Npm => npm_dependencies(&contents),
+			Ecosystem::Pypi => Ok(pypi_dependencies(&contents)),
+		};
+		let found = match found {
+			Ok(found) => found,
+			Err(e) => {
+				log::warn!("failed to parse '{}': {:#}", relative.display(), e);
+				continue;
+			}
+		};
+
+		for (name, requirement) in found {
+			let Some(version) = min_version(&requirement) else {
+				continue;
+			};
+			if seen.insert((ecosystem, name.clone(), relative.clone())) {
+				dependencies.push(Dependency {
+					ecosystem,
+					name,
+					requirement,
+					path: relative.clone(),
+					version,
+				});
+			}
+		}
+	}
+
+	Ok(dependencies)
+}
+
+fn is_skipped_dir(entry: &DirEntry) -> bool {
+	entry.file_type().is_dir()
+		&& entry
+			.file_name()
+			.to_str()
+			.is_some_and(|name| SKIPPED_DIRS.contains(&name))
+}
+
+/// Get the ecosystem of a manifest from its file name.
+fn manifest_ecosystem(file_name: Option<&str>) -> Option<Ecosystem> {
+	let file_name = file_name?;
+	match file_name {
+		"Cargo.toml" => Some(Ecosystem::Cargo),
+		"package.json" => Some(Ecosystem::Npm),
+		_ if file_name.starts_with("requirements") && file_name.ends_with(".txt") => {
+			Some(Ecosystem::Pypi)
+		}
+		_ => None,
+	}
+}
+
+/// Get the name and version requirement of each dependency in a `Cargo.toml`,
+/// including those shared by a workspace.
+fn cargo_dependencies(contents: &str) -> Result<Vec<(String, String)>> {
+	let manifest: Table = contents.parse()?;
+	let mut tables = Vec::new();
+
+	for section in CARGO_SECTIONS {
+		tables.extend(manifest.get(*section).and_then(TomlValue::as_table));
+	}
+	// Dependencies for specific targets, like `[target.'cfg(unix)'.dependencies]`
+	for target in manifest
+		.get("target")
+		.and_then(TomlValue::as_table)
+		.into_iter()
+		.flat_map(Table::values)
+	{
+		for section in CARGO_SECTIONS {
+			tables.extend(target.get(*section).and_then(TomlValue::as_table));
+		}
+	}
+	tables.extend(
+		manifest
+			.get("workspace")
+			.and_then(|workspace| workspace.get("dependencies"))
+			.and_then(TomlValue::as_table),
+	);
+
+	Ok(tables
+		.into_iter()
+		.flat_map(|table| table.iter())
+		.filter_map(|(key, spec)| match spec {
+			TomlValue::String(version) => Some((key.clone(), version.clone())),
+			TomlValue::Table(spec) => {
+				let version = spec.get("version")?.as_str()?;
+				// A renamed dependency gives its real name as `package`
+				let name = spec
+					.get("package")
+					.and_then(TomlValue::as_str)
+					.unwrap_or(key);
+				Some((name.to_owned(), version.to_owned()))
+			}
+			_ => None,
+		})
+		.collect())
+}
+
+/// Get the name and version requirement of each dependency in a
+/// `package.json`.
+fn npm_dependencies(contents: &str) -> Result<Vec<(String, String)>> {
+	let manifest: JsonValue = serde_json::from_str(contents)?;
+
+	Ok(NPM_SECTIONS
+		.iter()
+		.filter_map(|section| manifest.get(section)?.as_object())
+		.flat_map(|dependencies| dependencies.iter())
+		.filter_map(|(name, requirement)| Some((name.clone(), requirement.as_str()?.to_owned())))
+		.collect())
+}
+
+/// Get the name and version requirement of each dependency in a
+/// `requirements.txt`. Only requirements naming a version are kept.
+fn pypi_dependencies(contents: &str) -> Vec<(String, String)> {
+	contents
+		.lines()
+		.filter_map(|line| {
+			// Drop comments and environment markers
+			let line = line.split('#').next()?;
+			let line = line.split(';').next()?.trim();
+			// Options, like `-r other.txt`, aren't requirements
+			if line.is_empty() || line.starts_with('-') {
+				return None;
+			}
+
+			let start = line.find(['=', '<', '>', '~', '!'])?;
+			let name = line[..start].split('[').next()?.trim();
+			let requirement = line[start..].trim();
+			(!name.is_empty()).then(|| (name.to_owned(), requirement.to_owned()))
+		})
+		.collect()
+}
+
+/// Get the oldest version a version requirement allows, like `1.2.0` for
+/// `^1.2` or `>=1.2, <2`.
+///
+/// Requirements which don't start from a version, like `*`, `latest`, or a
+/// git URL, have no oldest version.
+pub fn min_version(requirement: &str) -> Option<Version> {
+	let first = requirement.split([',', '|']).next()?.trim();
+	// Upper bounds and exclusions don't say where the requirement starts
+	if first.starts_with(['<', '!']) {
+		return None;
+	}
+	let version = first.trim_start_matches(['^', '~', '=', '>', 'v', ' ']);
+	parse_version(version.split_whitespace().next()?)
+}
+
+/// Parse a version leniently, filling in missing parts with zeros and ignoring
+/// anything after the numbered parts, like pre-release or build tags.
+pub fn parse_version(version: &str) -> Option<Version> {
+	let mut parts = version
+		.split(['.', '-', '+'])
+		.map_while(|part| part.parse::<u64>().ok());
+	let major = parts.next()?;
+	Some(Version::new(
+		major,
+		parts.next().unwrap_or(0),
+		parts.next().unwrap_or(0),
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+
+	fn write(root: &Path, path: &str, contents: &str) {
+		let path = root.join(path);
+		fs::create_dir_all(path.parent().unwrap()).unwrap();
+		fs::write(path, contents).unwrap();
+	}
+
+	#[test]
+	fn versions_of_requirements() {
+		let version = |major, minor, patch| Some(Version::new(major, minor, patch));
+		assert_eq!(min_version("1.2.3"), version(1, 2, 3));
+		assert_eq!(min_version("^0.4"), version(0, 4, 0));
+		assert_eq!(min_version("~=2.28"), version(2, 28, 0));
+		assert_eq!(min_version(">=1.2, <2"), version(1, 2, 0));
+		assert_eq!(min_version("==3.0.0rc1"), version(3, 0, 0));
+		assert_eq!(min_version("v5.1.0-beta.2"), version(5, 1, 0));
+		assert_eq!(min_version("1.x"), version(1, 0, 0));
+		assert_eq!(min_version(">= 1.2.3 < 2"), version(1, 2, 3));
+		assert_eq!(min_version("1.2.3 - 2.0.0"), version(1, 2, 3));
+		assert_eq!(min_version("*"), None);
+		assert_eq!(min_version("latest"), None);
+		assert_eq!(min_version("<2"), None);
+		assert_eq!(min_version("github:user/repo"), None);
+	}
+
+	#[test]
+	fn finds_dependencies_in_manifests() {
+		let root = TempDir::new().unwrap();
+		write(
+			root.path(),
+			"Cargo.toml",
+			r#"
+[dependencies]
+serde = "1.0"
+local = { path = "../local" }
+yaml = { package = "serde_yaml", version = "0.8" }
+
+[target.'cfg(unix)'.dev-dependencies]
+nix = { version = "0.26", features = ["fs"] }
+"#,
+		);
+		write(
+			root.path(),
+			"web/package.json",
+			r#"{ "dependencies": { "react": "^17.0.2", "local": "file:../local" }, "devDependencies": { "jest": "~26.6.0" } }"#,
+		);
+		write(
+			root.path(),
+			"requirements.txt",
+			"-r base.txt\nrequests[socks]==2.25.1 ; python_version > '3'\nflask\n# comment\nDjango>=3.2,<4\n",
+		);
+		write(
+			root.path(),
+			"web/node_modules/left-pad/package.json",
+			r#"{ "dependencies": { "ignored": "1.0.0" } }"#,
+		);
+
+		let found = find_dependencies(root.path())
+			.unwrap()
+			.into_iter()
+			.map(|d| (d.ecosystem, d.name, d.version.to_string()))
+			.collect::<Vec<_>>();
+		let expected = [
+			(Ecosystem::Cargo, "serde", "1.0.0"),
+			(Ecosystem::Cargo, "serde_yaml", "0.8.0"),
+			(Ecosystem::Cargo, "nix", "0.26.0"),
+			(Ecosystem::Pypi, "requests", "2.25.1"),
+			(Ecosystem::Pypi, "Django", "3.2.0"),
+			(Ecosystem::Npm, "react", "17.0.2"),
+			(Ecosystem::Npm, "jest", "26.6.0"),
+		]
+		.map(|(ecosystem, name, version)| (ecosystem, name.to_owned(), version.to_owned()));
+		assert_eq!(found, expected);
+	}
+}
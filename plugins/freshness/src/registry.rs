@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Looking up the latest published versions of packages in their registries.
+
+use crate::manifest::{parse_version, Ecosystem};
+use anyhow::{anyhow, Context as _, Result};
+use rustls::{ClientConfig, RootCertStore};
+use semver::Version;
+use serde_json::Value;
+use std::sync::{Arc, OnceLock};
+use ureq::{Agent, AgentBuilder};
+
+/// crates.io asks that clients identify themselves.
+const USER_AGENT: &str = "hipcheck-freshness (https://github.com/mitre/hipcheck)";
+
+/// Global static holding the agent with the appropriate TLS certs.
+static AGENT: OnceLock<Agent> = OnceLock::new();
+
+/// Get or initialize the global static agent used to query registries.
+///
+/// # Panics
+/// - If native certs cannot be loaded the first time this function is called.
+fn agent() -> &'static Agent {
+	AGENT.get_or_init(|| {
+		// Retrieve system certs
+		let mut roots = RootCertStore::empty();
+		let native_certs =
+			rustls_native_certs::load_native_certs().expect("should load native certs");
+		roots.add_parsable_certificates(native_certs);
+
+		// Add certs to connection configuration
+		let tls_config = ClientConfig::builder()
+			.with_root_certificates(roots)
+			.with_no_client_auth();
+
+		// Construct agent
+		AgentBuilder::new()
+			.tls_config(Arc::new(tls_config))
+			.user_agent(USER_AGENT)
+			.build()
+	})
+}
+
+/// Get the latest stable version of a package published to its ecosystem's
+/// registry.
+pub fn latest_version(ecosystem: Ecosystem, name: &str) -> Result<Version> {
+	let (url, pointers): (String, &[&str]) = match ecosystem {
+		Ecosystem::Cargo => (
+			format!("https://crates.io/api/v1/crates/{}", name),
+			&["/crate/max_stable_version", "/crate/max_version"],
+		),
+		// Scoped package names keep their `@`, but their `/` must be escaped
+		Ecosystem::Npm => (
+			format!(
+				"https://registry.npmjs.org/{}/latest",
+				name.replace('/', "%2F")
+			),
+			&["/version"],
+		),
+		Ecosystem::Pypi => (
+			format!("https://pypi.org/pypi/{}/json", name),
+			&["/info/version"],
+		),
+	};
+
+	let response: Value = agent()
+		.get(&url)
+		.call()
+		.with_context(|| format!("failed to query {} for '{}'", ecosystem, name))?
+		.into_json()
+		.with_context(|| format!("invalid response from {} for '{}'", ecosystem, name))?;
+
+	pointers
+		.iter()
+		.filter_map(|pointer| response.pointer(pointer)?.as_str())
+		.find_map(parse_version)
+		.ok_or_else(|| anyhow!("{} has no version for '{}'", ecosystem, name))
+}
+
+/// Get how many major versions behind the latest version a version is.
+///
+/// Before 1.0.0, each minor version may break compatibility, so it counts as a
+/// major version.
+pub fn majors_behind(version: &Version, latest: &Version) -> u64 {
+	if latest <= version {
+		return 0;
+	}
+	if version.major == 0 && latest.major == 0 {
+		latest.minor - version.minor
+	} else {
+		latest.major - version.major
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn counts_major_versions_behind() {
+		let behind = |version: &str, latest: &str| {
+			majors_behind(
+				&Version::parse(version).unwrap(),
+				&Version::parse(latest).unwrap(),
+			)
+		};
+		assert_eq!(behind("1.2.3", "1.9.0"), 0);
+		assert_eq!(behind("1.2.3", "3.0.0"), 2);
+		assert_eq!(behind("0.4.0", "0.7.2"), 3);
+		assert_eq!(behind("0.9.1", "1.0.0"), 1);
+		assert_eq!(behind("0.1.0", "0.1.9"), 0);
+		// Pinned to something newer than the registry's latest stable version
+		assert_eq!(behind("2.0.0", "1.5.0"), 0);
+	}
+}
@@ -40,7 +40,7 @@ impl Plugin for FuzzAnalysisPlugin {
 		Ok(Some("Does the target repo do fuzzing".to_owned()))
 	}
 
-	queries! {}
+	queries! { fuzz }
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -60,6 +60,8 @@ mod test {
 		let local = LocalGitRepo {
 			path: "/home/users/me/.cache/hipcheck/clones/github/mitre/hipcheck/".to_string(),
 			git_ref: "main".to_string(),
+			subdir: None,
+			base: None,
 		};
 		let known_remote = Some(KnownRemote::GitHub {
 			owner: "mitre".to_owned(),
@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: Apache-2.0
 
+use gix::bstr::ByteSlice;
 use hipcheck_sdk::types::LocalGitRepo;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -64,7 +65,7 @@ impl TryFrom<gix::Commit<'_>> for RawCommit {
 /// The `written_on` and `committed_on` datetime fields contain Strings that are created from `jiff:Timestamps`.
 /// Because `Timestamp` does not `impl JsonSchema`, we display the datetimes as Strings for passing out of this plugin.
 /// Other plugins that expect a `Timestamp`` should parse the provided Strings into `Timestamps` as needed.
-#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, JsonSchema)]
 pub struct Commit {
 	pub hash: String,
 	pub written_on: Result<String, String>,
@@ -102,6 +103,118 @@ impl Display for Contributor {
 	}
 }
 
+/// A single person who may have contributed under several names or e-mail addresses.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct ContributorIdentity {
+	/// Canonical ID of the identity, stable across runs on the same repository.
+	pub id: String,
+	/// Every name/e-mail pair the person contributed under.
+	pub contributors: Vec<Contributor>,
+}
+
+/// How many commits a single contributor identity authored in a repository.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct ContributorActivity {
+	/// Canonical ID of the identity, as in `ContributorIdentity`.
+	pub id: String,
+	/// Every name/e-mail pair the person contributed under.
+	pub contributors: Vec<Contributor>,
+	/// The number of commits the person authored.
+	pub commits: i64,
+	/// The number of commits in the repository.
+	pub total_commits: i64,
+}
+
+/// How much of a repository's history the queries cover, given the configured history limits.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct HistoryWindow {
+	/// The most commits covered, if limited.
+	pub max_commits: Option<u64>,
+	/// The oldest commit time covered, if limited.
+	pub since: Option<String>,
+	/// The number of commits covered.
+	pub commits: u64,
+	/// Whether older commits were left out.
+	pub truncated: bool,
+}
+
+/// The kind of signature on a commit, from the armor around it.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureKind {
+	/// An OpenPGP signature, made with GPG.
+	Gpg,
+	/// An SSH signature.
+	Ssh,
+	/// An X.509 signature, made with `gpgsm` or `smimesign`.
+	X509,
+}
+
+impl SignatureKind {
+	/// Get the kind of a signature from its armored text.
+	pub fn from_armor(signature: &[u8]) -> SignatureKind {
+		if signature.contains_str("-----BEGIN SSH SIGNATURE-----") {
+			SignatureKind::Ssh
+		} else if signature.contains_str("-----BEGIN SIGNED MESSAGE-----") {
+			SignatureKind::X509
+		} else {
+			SignatureKind::Gpg
+		}
+	}
+}
+
+/// Whether a commit's signature verifies, as reported by git's `%G?` format.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignatureStatus {
+	/// A good signature from a trusted key (`G`).
+	Good,
+	/// A good signature from a key of unknown validity (`U`).
+	UnknownValidity,
+	/// A good signature which has expired (`X`).
+	Expired,
+	/// A good signature made by a key which has expired (`Y`).
+	ExpiredKey,
+	/// A good signature made by a key which has been revoked (`R`).
+	RevokedKey,
+	/// A bad signature (`B`).
+	Bad,
+	/// A signature which can't be checked, usually because the key is missing (`E`).
+	MissingKey,
+	/// A signature which couldn't be checked at all, because git isn't installed or failed.
+	Unverifiable,
+}
+
+impl SignatureStatus {
+	/// Get the status for a `%G?` code. Unsigned commits (`N`) have no status.
+	pub fn from_code(code: &str) -> Option<SignatureStatus> {
+		match code {
+			"G" => Some(SignatureStatus::Good),
+			"U" => Some(SignatureStatus::UnknownValidity),
+			"X" => Some(SignatureStatus::Expired),
+			"Y" => Some(SignatureStatus::ExpiredKey),
+			"R" => Some(SignatureStatus::RevokedKey),
+			"B" => Some(SignatureStatus::Bad),
+			"E" => Some(SignatureStatus::MissingKey),
+			_ => None,
+		}
+	}
+}
+
+/// The signature on a commit, and whether it verifies.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct CommitSignature {
+	pub commit: Commit,
+	/// The kind of signature, or `None` if the commit is unsigned.
+	pub kind: Option<SignatureKind>,
+	/// Whether the signature verifies, or `None` if it is unsigned.
+	pub status: Option<SignatureStatus>,
+	/// The key which made the signature, if known.
+	pub key: Option<String>,
+	/// The signer named by the signature, if known.
+	pub signer: Option<String>,
+}
+
 /// "Join struct" for commits and contributors.
 #[derive(Debug, PartialEq, Eq, Serialize, Clone)]
 pub struct CommitContributor {
@@ -128,7 +241,7 @@ pub struct ContributorView {
 }
 
 /// View into commits and diffs joined together.
-#[derive(Debug, Serialize, PartialEq, Eq, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub struct CommitDiff {
 	pub commit: Commit,
 	pub diff: Diff,
@@ -150,6 +263,15 @@ impl Display for CommitDiff {
 	}
 }
 
+/// The files changed by a commit, without the changes themselves.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct CommitFiles {
+	pub commit: Commit,
+	pub author: Contributor,
+	/// Paths of the changed files, relative to the root of the repository.
+	pub files: Vec<String>,
+}
+
 /// A set of changes in a commit.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub struct Diff {
@@ -165,6 +287,9 @@ pub struct FileDiff {
 	pub additions: i64,
 	pub deletions: i64,
 	pub patch: String,
+	/// The file's previous name, if the commit renamed or moved it
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub renamed_from: Option<String>,
 }
 
 impl FileDiff {
@@ -174,6 +299,7 @@ impl FileDiff {
 			additions: 0,
 			deletions: 0,
 			patch: String::new(),
+			renamed_from: None,
 		}
 	}
 
@@ -188,4 +314,8 @@ impl FileDiff {
 	pub fn set_patch(&mut self, patch_data: String) {
 		self.patch = patch_data;
 	}
+
+	pub fn set_renamed_from(&mut self, previous_file_name: String) {
+		self.renamed_from = Some(previous_file_name);
+	}
 }
@@ -4,6 +4,8 @@ use crate::data::*;
 
 use crate::Error;
 use crate::CACHE;
+use crate::HISTORY_LIMITS;
+use crate::TRACK_RENAMES;
 use anyhow::Context;
 use anyhow::Result;
 use gix::bstr::ByteSlice;
@@ -21,50 +23,123 @@ use gix::ObjectId;
 use gix::Repository;
 use jiff::Timestamp;
 use lru::LruCache;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::thread;
 
-/// used to cache all of the `RawCommit` from the last repo/HEAD combination analyzed by this
-pub type GitRawCommitCache = LruCache<(PathBuf, ObjectId), Vec<RawCommit>>;
+/// used to cache all of the `RawCommit` from the last repo/commit range combination analyzed by this
+pub type GitRawCommitCache = LruCache<(PathBuf, CommitRange), Vec<RawCommit>>;
 
-/// retrieve a handle to the git repo at this path, as well as determine the commit hash of
-/// HEAD
-fn initialize_repo<P>(repo_path: P) -> Result<(Repository, ObjectId)>
+/// The commits to walk: those reachable from HEAD, less those reachable from the base of a
+/// change request, so that only the commits the request contributes are seen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CommitRange {
+	head: ObjectId,
+	base: Option<ObjectId>,
+}
+
+/// retrieve a handle to the git repo at this path, as well as determine the range of commits
+/// to walk, from HEAD back to `base` if it's given
+fn initialize_repo<P>(repo_path: P, base: Option<&str>) -> Result<(Repository, CommitRange)>
 where
 	P: AsRef<Path>,
 {
 	let repo = gix::discover(repo_path).context("failed to find repo")?;
-	let head_commit = repo.head_commit()?.id;
-	Ok((repo, head_commit))
+	let head = repo.head_commit()?.id;
+	let base = base
+		.map(|base| {
+			ObjectId::from_hex(base.as_bytes())
+				.with_context(|| format!("invalid base commit '{}'", base))
+		})
+		.transpose()?;
+	Ok((repo, CommitRange { head, base }))
 }
 
-/// Retrieves an iterator that walks the repo's commits
+/// Retrieves an iterator that walks the repo's commits in a range
 ///
 /// Commits are sorted by commit time and the newest commit (HEAD) is seen first
-fn get_commit_walker(repo: &Repository, starting_commit: ObjectId) -> Result<Walk<'_>> {
+fn get_commit_walker(repo: &Repository, range: CommitRange) -> Result<Walk<'_>> {
 	let repo_walker = repo
-		.rev_walk(Some(starting_commit))
+		.rev_walk(Some(range.head))
+		.with_hidden(range.base)
 		.sorting(Sorting::ByCommitTime(CommitTimeOrder::NewestFirst))
 		.all()?;
 	Ok(repo_walker)
 }
 
+/// Limits on how much of a repo's history is walked, starting from the newest commit
+///
+/// Very large repos can take a long time to analyze in full, so the plugin's configuration may
+/// limit the history to the newest commits, or to those made since some time.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryLimits {
+	/// The most commits to walk
+	pub max_commits: Option<usize>,
+	/// The oldest commit time to walk back to
+	pub since: Option<Timestamp>,
+}
+
+impl HistoryLimits {
+	/// Whether the limits cover a commit, given how many newer commits the walk has already seen
+	fn covers(&self, newer_commits: usize, commit: &gix::Commit<'_>) -> Result<bool> {
+		if self
+			.max_commits
+			.is_some_and(|max_commits| newer_commits >= max_commits)
+		{
+			return Ok(false);
+		}
+		if let Some(since) = self.since {
+			let committed_on = Timestamp::from_second(commit.time()?.seconds)?;
+			return Ok(committed_on >= since);
+		}
+		Ok(true)
+	}
+
+	fn is_limited(&self) -> bool {
+		self.max_commits.is_some() || self.since.is_some()
+	}
+}
+
+/// The configured history limits, or no limits if none were configured
+fn history_limits() -> HistoryLimits {
+	HISTORY_LIMITS.get().cloned().unwrap_or_default()
+}
+
 /// Function to call on a commit in the repo to attempt to convert it to type `T`
 type MapFn<'a, T> = &'a dyn Fn(&Repository, gix::Commit<'_>) -> Result<T>;
 /// Function to call on a commit to determine if iteration should be halted (if Ok(true) is returned)
 type BreakNowFn<'a> = &'a dyn Fn(&gix::Commit<'_>) -> Result<bool>;
 
 /// Utility function for walking all of the commits in a git repo and running a function on each commit to generate some result and breaking out of the walk if `break_now` is true
+///
+/// The walk also stops at the end of the configured history limits, so every query sees the same window of history.
+/// If `subdir` is given, commits which didn't change anything under it are skipped, and don't count against the limits
 fn walk_commits<'repo, T>(
 	repo: &'repo Repository,
 	repo_walker: Walk<'repo>,
 	transform_fn: MapFn<T>,
 	break_now_fn: Option<BreakNowFn>,
+	subdir: Option<&Path>,
 ) -> Result<Vec<T>> {
+	let limits = history_limits();
 	// since we are walking commit by commit, 5,000 was arbitrarily chosen to reduce allocations for small/medium repo sizes
 	let mut results = Vec::with_capacity(5_000);
 	for object in repo_walker {
 		let commit = object?.object()?;
+		if let Some(subdir) = subdir {
+			if !touches(repo, &commit, subdir)? {
+				continue;
+			}
+		}
+		if !limits.covers(results.len(), &commit)? {
+			break;
+		}
 		if let Some(ref break_now_fn) = break_now_fn {
 			if let Ok(true) = break_now_fn(&commit) {
 				break;
@@ -76,21 +151,75 @@ fn walk_commits<'repo, T>(
 	Ok(results)
 }
 
-pub fn get_latest_commit<P>(repo_path: P) -> Result<Option<RawCommit>>
+/// Whether a commit changed anything under a subdirectory of the repo
+fn touches(repo: &Repository, commit: &gix::Commit<'_>, subdir: &Path) -> Result<bool> {
+	let subdir_id = |tree: gix::Tree<'_>| -> Result<Option<ObjectId>> {
+		Ok(tree
+			.lookup_entry_by_path(subdir)?
+			.map(|entry| entry.oid().to_owned()))
+	};
+	let current = subdir_id(commit.tree()?)?;
+	let parent = match commit.parent_ids().next() {
+		Some(id) => subdir_id(repo.find_commit(id)?.tree()?)?,
+		None => None,
+	};
+	Ok(current != parent)
+}
+
+/// Whether a path in the repo is under a subdirectory, or anywhere if there's no subdirectory
+fn is_under(location: &str, subdir: Option<&Path>) -> bool {
+	subdir.is_none_or(|subdir| Path::new(location).starts_with(subdir))
+}
+
+/// Get the newest commit, or the newest to change anything under `subdir` if it's given
+pub fn get_latest_commit<P>(repo_path: P, subdir: Option<&Path>) -> Result<Option<RawCommit>>
+where
+	P: AsRef<Path>,
+{
+	let (repo, range) = initialize_repo(repo_path, None)?;
+	for object in get_commit_walker(&repo, range)? {
+		let commit = object?.object()?;
+		if let Some(subdir) = subdir {
+			if !touches(&repo, &commit, subdir)? {
+				continue;
+			}
+		}
+		return Ok(Some(RawCommit::try_from(commit)?));
+	}
+	Ok(None)
+}
+
+/// Get the date of the newest tag in the repo, if it has any
+///
+/// Annotated tags are dated by when they were tagged, and lightweight tags by the date of the
+/// commit they point to. Tags of anything besides a commit are ignored.
+pub fn get_latest_tag_date<P>(repo_path: P) -> Result<Option<Timestamp>>
 where
 	P: AsRef<Path>,
 {
-	let (repo, head_commit) = initialize_repo(repo_path)?;
-	let mut commit_walker = get_commit_walker(&repo, head_commit)?;
-	match commit_walker.next() {
-		Some(object) => {
-			let info = object?;
-			let commit = info.object()?;
-			let raw_commit = RawCommit::try_from(commit)?;
-			Ok(Some(raw_commit))
+	let repo = gix::discover(repo_path).context("failed to find repo")?;
+	let references = repo.references()?;
+	let mut latest = None;
+	for reference in references.tags().map_err(|e| anyhow::anyhow!("{}", e))? {
+		let reference = reference.map_err(|e| anyhow::anyhow!("{}", e))?;
+		let Some(id) = reference.target().try_id().map(ToOwned::to_owned) else {
+			continue;
+		};
+		let object = repo.find_object(id)?;
+		let seconds = match object.kind {
+			object::Kind::Tag => match object.into_tag().tagger()? {
+				Some(tagger) => tagger.time.seconds,
+				None => continue,
+			},
+			object::Kind::Commit => object.into_commit().time()?.seconds,
+			_ => continue,
+		};
+		let date = Timestamp::from_second(seconds)?;
+		if latest.is_none_or(|latest| date > latest) {
+			latest = Some(date);
 		}
-		None => Ok(None),
 	}
+	Ok(latest)
 }
 
 /// Convert a `gix::Commit` into a `RawCommit`
@@ -102,54 +231,110 @@ fn get_raw_commit(_repo: &Repository, commit: gix::Commit) -> Result<RawCommit>
 fn get_all_raw_commits_inner<P>(
 	repo: &Repository,
 	repo_path: P,
-	head_commit: ObjectId,
-) -> Result<(PathBuf, ObjectId, Vec<RawCommit>)>
+	range: CommitRange,
+) -> Result<(PathBuf, CommitRange, Vec<RawCommit>)>
 where
 	P: AsRef<Path>,
 {
-	let commit_walker = get_commit_walker(repo, head_commit)?;
-	let commits = walk_commits(repo, commit_walker, &get_raw_commit, None)?;
-	Ok((repo_path.as_ref().to_path_buf(), head_commit, commits))
+	let commit_walker = get_commit_walker(repo, range)?;
+	let commits = walk_commits(repo, commit_walker, &get_raw_commit, None, None)?;
+	Ok((repo_path.as_ref().to_path_buf(), range, commits))
 }
 
-/// Retrieve all of the `RawCommit` from a repo **sorted from newest to oldest**
+/// Retrieve all of the `RawCommit` from a repo **sorted from newest to oldest**, only those after
+/// `base` if it's given
 ///
 /// This contains a cache of all `RawCommit` to avoid needing to recompute this and should be used as the starting point for a
 /// function if all of the desired data can be derived from all of the `RawCommit` in the repo.
 ///
-/// This cache is maintained for a single repo_path/HEAD commit combination. If a new repo/HEAD
-/// combination is requested, then the cache will be flushed and updated.
-pub fn get_all_raw_commits<P>(repo_path: P) -> Result<Vec<RawCommit>>
+/// This cache is maintained for a single repo_path/commit range combination. If a new repo/commit
+/// range combination is requested, then the cache will be flushed and updated.
+pub fn get_all_raw_commits<P>(repo_path: P, base: Option<&str>) -> Result<Vec<RawCommit>>
 where
 	P: AsRef<Path>,
 {
-	let (repo, head_commit) = initialize_repo(repo_path.as_ref())?;
+	let (repo, range) = initialize_repo(repo_path.as_ref(), base)?;
 	let unlocked_cache = CACHE.get().ok_or(Error::UnspecifiedQueryState)?;
 	let mut cache = unlocked_cache
 		.lock()
 		.map_err(|_| Error::UnspecifiedQueryState)?;
 
-	// if there is a value in cache, and it is the same repo with the same HEAD commit, then we can use the
+	// if there is a value in cache, and it is the same repo with the same commit range, then we can use the
 	// cached value
-	if let Some(cached_value) = cache.get_mut(&(repo_path.as_ref().to_path_buf(), head_commit)) {
+	if let Some(cached_value) = cache.get_mut(&(repo_path.as_ref().to_path_buf(), range)) {
 		return Ok(cached_value.clone());
 	}
 
-	// otherwise the cache needs to be updated with the data from this repo_path/HEAD combination
-	let updated_value = get_all_raw_commits_inner(&repo, repo_path.as_ref(), head_commit)?;
+	// otherwise the cache needs to be updated with the data from this repo_path/commit range combination
+	let updated_value = get_all_raw_commits_inner(&repo, repo_path.as_ref(), range)?;
 	let raw_commits = updated_value.2.clone();
 	cache.put(
-		(repo_path.as_ref().to_path_buf(), head_commit),
+		(repo_path.as_ref().to_path_buf(), range),
 		raw_commits.clone(),
 	);
 	Ok(raw_commits)
 }
 
-pub fn get_commits_from_date<P>(repo_path: P, cutoff_date: Timestamp) -> Result<Vec<RawCommit>>
+/// Get how much of a repo's history the configured history limits cover
+///
+/// If `base` is given, only the commits after it are counted, and if `subdir` is given, only the
+/// commits which changed anything under it
+pub fn get_history_window<P>(
+	repo_path: P,
+	base: Option<&str>,
+	subdir: Option<&Path>,
+) -> Result<HistoryWindow>
+where
+	P: AsRef<Path>,
+{
+	let limits = history_limits();
+	let max_commits = limits.max_commits.map(|max_commits| max_commits as u64);
+	let since = limits.since.map(|since| since.to_string());
+
+	// Without limits, the whole history is covered, and is likely already cached
+	if !limits.is_limited() && subdir.is_none() {
+		return Ok(HistoryWindow {
+			max_commits,
+			since,
+			commits: get_all_raw_commits(repo_path, base)?.len() as u64,
+			truncated: false,
+		});
+	}
+
+	let (repo, range) = initialize_repo(repo_path, base)?;
+	let mut commits = 0;
+	let mut truncated = false;
+	for object in get_commit_walker(&repo, range)? {
+		let commit = object?.object()?;
+		if let Some(subdir) = subdir {
+			if !touches(&repo, &commit, subdir)? {
+				continue;
+			}
+		}
+		if !limits.covers(commits, &commit)? {
+			truncated = true;
+			break;
+		}
+		commits += 1;
+	}
+
+	Ok(HistoryWindow {
+		max_commits,
+		since,
+		commits: commits as u64,
+		truncated,
+	})
+}
+
+pub fn get_commits_from_date<P>(
+	repo_path: P,
+	base: Option<&str>,
+	cutoff_date: Timestamp,
+) -> Result<Vec<RawCommit>>
 where
 	P: AsRef<Path>,
 {
-	let raw_commits = get_all_raw_commits(repo_path)?;
+	let raw_commits = get_all_raw_commits(repo_path, base)?;
 	let raw_commits_since_cutoff: Vec<RawCommit> = raw_commits
 		.into_iter()
 		.filter(|raw_commit| {
@@ -174,7 +359,21 @@ fn diff_objects(old_object: Option<&str>, new_object: Option<&str>) -> Counter<S
 	)
 }
 
-fn get_diff(repo: &Repository, commit: gix::Commit) -> Result<Diff> {
+/// How to detect renamed files when diffing a commit, or `None` if renames aren't tracked
+///
+/// Renames are tracked unless the plugin's configuration turns them off, since a moved file
+/// otherwise counts as every line of it being deleted and added again. Files are paired up as
+/// renames the same way `git diff -M` does, when at least half of their contents are the same.
+fn rewrites() -> Option<gix::diff::Rewrites> {
+	TRACK_RENAMES
+		.get()
+		.copied()
+		.unwrap_or(true)
+		.then(gix::diff::Rewrites::default)
+}
+
+/// Get the diff of a commit, only including the files under `subdir` if it's given
+fn get_diff(repo: &Repository, commit: gix::Commit, subdir: Option<&Path>) -> Result<Diff> {
 	let current_tree = commit.tree()?;
 	let parent_tree = match commit.parent_ids().next() {
 		Some(id) => repo.find_commit(id)?.tree()?,
@@ -185,8 +384,7 @@ fn get_diff(repo: &Repository, commit: gix::Commit) -> Result<Diff> {
 	let changes = repo.diff_tree_to_tree(
 		Some(&parent_tree),
 		Some(&current_tree),
-		// this is recommended to increase performance!
-		gix::diff::Options::default().with_rewrites(None),
+		gix::diff::Options::default().with_rewrites(rewrites()),
 	)?;
 
 	let mut file_diffs = Vec::with_capacity(changes.len());
@@ -197,6 +395,9 @@ fn get_diff(repo: &Repository, commit: gix::Commit) -> Result<Diff> {
 		if !matches!(change_kind, EntryKind::Blob | EntryKind::BlobExecutable) {
 			continue;
 		}
+		if !is_under(&change.location().to_str_lossy(), subdir) {
+			continue;
+		}
 		match change {
 			object::tree::diff::ChangeDetached::Addition {
 				location,
@@ -247,19 +448,34 @@ fn get_diff(repo: &Repository, commit: gix::Commit) -> Result<Diff> {
 				file_diff.set_patch(diff.wrapped);
 				file_diffs.push(file_diff);
 			}
-			// because we are not tracking rewrites (for performance reasons), this branch will never get hit
+			// a renamed file only counts the lines which changed as it moved, like `git diff -M`
 			object::tree::diff::ChangeDetached::Rewrite {
-				source_location: _,
+				source_location,
 				source_entry_mode: _,
 				source_relation: _,
-				source_id: _,
+				source_id,
 				diff: _,
 				entry_mode: _,
-				id: _,
-				location: _,
+				id,
+				location,
 				relation: _,
 				copy: _,
-			} => {}
+			} => {
+				let mut file_diff = FileDiff::new(location.to_string());
+				file_diff.set_renamed_from(source_location.to_string());
+				if source_id != id {
+					let current_blob = repo.find_blob(id)?;
+					let previous_blob = repo.find_blob(source_id)?;
+					let current_blob_data = current_blob.data.to_str_lossy();
+					let previous_blob_data = previous_blob.data.to_str_lossy();
+
+					let diff = diff_objects(Some(&previous_blob_data), Some(&current_blob_data));
+					file_diff.increment_additions(diff.insertions as i64);
+					file_diff.increment_deletions(diff.removals as i64);
+					file_diff.set_patch(diff.wrapped);
+				}
+				file_diffs.push(file_diff);
+			}
 		}
 	}
 
@@ -271,21 +487,92 @@ fn get_diff(repo: &Repository, commit: gix::Commit) -> Result<Diff> {
 	Ok(diff)
 }
 
-pub fn get_diffs<P>(repo_path: P) -> Result<Vec<Diff>>
+/// Get the diffs of the commits in a repo, only those after `base` and changing anything under
+/// `subdir` if they're given
+pub fn get_diffs<P>(repo_path: P, base: Option<&str>, subdir: Option<&Path>) -> Result<Vec<Diff>>
+where
+	P: AsRef<Path>,
+{
+	let (repo, range) = initialize_repo(repo_path, base)?;
+	let commit_walker = get_commit_walker(&repo, range)?;
+	walk_commits(
+		&repo,
+		commit_walker,
+		&|repo, commit| get_diff(repo, commit, subdir),
+		None,
+		subdir,
+	)
+}
+
+/// Get the paths of the files a commit changed, without diffing their contents
+fn get_commit_files(
+	repo: &Repository,
+	commit: gix::Commit,
+	subdir: Option<&Path>,
+) -> Result<CommitFiles> {
+	let raw_commit = RawCommit::try_from(commit.clone())?;
+	let current_tree = commit.tree()?;
+	let parent_tree = match commit.parent_ids().next() {
+		Some(id) => repo.find_commit(id)?.tree()?,
+		// if there is no parent, then this must be the first commit which can be represented with an empty tree
+		None => repo.empty_tree(),
+	};
+
+	let changes = repo.diff_tree_to_tree(
+		Some(&parent_tree),
+		Some(&current_tree),
+		gix::diff::Options::default().with_rewrites(None),
+	)?;
+
+	let files = changes
+		.iter()
+		.filter(|change| {
+			matches!(
+				EntryKind::from(change.entry_mode()),
+				EntryKind::Blob | EntryKind::BlobExecutable
+			)
+		})
+		.map(|change| change.location().to_string())
+		.filter(|location| is_under(location, subdir))
+		.collect();
+
+	Ok(CommitFiles {
+		author: raw_commit.author.clone(),
+		commit: raw_commit.into(),
+		files,
+	})
+}
+
+/// Get the files changed by every commit in a repo, **sorted from newest to oldest**
+///
+/// If `base` is given, only the commits after it are included, and if `subdir` is given, only the
+/// commits and files under it
+pub fn get_file_history<P>(
+	repo_path: P,
+	base: Option<&str>,
+	subdir: Option<&Path>,
+) -> Result<Vec<CommitFiles>>
 where
 	P: AsRef<Path>,
 {
-	let (repo, head_commit) = initialize_repo(repo_path)?;
-	let commit_walker = get_commit_walker(&repo, head_commit)?;
-	walk_commits(&repo, commit_walker, &get_diff, None)
+	let (repo, range) = initialize_repo(repo_path, base)?;
+	let commit_walker = get_commit_walker(&repo, range)?;
+	walk_commits(
+		&repo,
+		commit_walker,
+		&|repo, commit| get_commit_files(repo, commit, subdir),
+		None,
+		subdir,
+	)
 }
 
-/// Get all of the contributors (committers and authors) in a repo's history
-pub fn get_contributors<P>(repo_path: P) -> Result<Vec<Contributor>>
+/// Get all of the contributors (committers and authors) in a repo's history, only of the commits
+/// after `base` if it's given
+pub fn get_contributors<P>(repo_path: P, base: Option<&str>) -> Result<Vec<Contributor>>
 where
 	P: AsRef<Path>,
 {
-	let commits = get_all_raw_commits(repo_path)?;
+	let commits = get_all_raw_commits(repo_path, base)?;
 	let mut contributors: Vec<Contributor> = commits
 		.into_iter()
 		.map(|raw_commit| [raw_commit.author, raw_commit.committer])
@@ -297,18 +584,193 @@ where
 }
 
 /// Get the `CommitDiff` for a commit
-fn get_commit_diff(repo: &Repository, commit: gix::Commit) -> Result<CommitDiff> {
+fn get_commit_diff(
+	repo: &Repository,
+	commit: gix::Commit,
+	subdir: Option<&Path>,
+) -> Result<CommitDiff> {
 	let raw_commit = get_raw_commit(repo, commit.clone())?;
-	let diff = get_diff(repo, commit)?;
+	let diff = get_diff(repo, commit, subdir)?;
 	Ok(CommitDiff::new(raw_commit.into(), diff))
 }
 
-pub fn get_commit_diffs<P>(repo_path: P) -> Result<Vec<CommitDiff>>
+/// Get the commit-diff pairs of a repo, only for the commits after `base` and the commits and files
+/// under `subdir` if they're given
+///
+/// The diffs in `previous`, from an earlier run against the repo, are reused for the commits they
+/// cover, so only the commits made since then are diffed
+pub fn get_commit_diffs<P>(
+	repo_path: P,
+	base: Option<&str>,
+	subdir: Option<&Path>,
+	previous: Vec<CommitDiff>,
+) -> Result<Vec<CommitDiff>>
 where
 	P: AsRef<Path>,
 {
-	let (repo, head_commit) = initialize_repo(repo_path)?;
-	let commit_walker = get_commit_walker(&repo, head_commit)?;
-	let commit_diffs = walk_commits(&repo, commit_walker, &get_commit_diff, None)?;
+	let (repo, range) = initialize_repo(repo_path, base)?;
+	let commit_walker = get_commit_walker(&repo, range)?;
+	// A commit's diff never changes, so it can be taken as is from the earlier run
+	let previous = RefCell::new(
+		previous
+			.into_iter()
+			.map(|commit_diff| (commit_diff.commit.hash.clone(), commit_diff))
+			.collect::<HashMap<_, _>>(),
+	);
+	let commit_diffs = walk_commits(
+		&repo,
+		commit_walker,
+		&|repo, commit| match previous.borrow_mut().remove(&commit.id.to_string()) {
+			Some(commit_diff) => Ok(commit_diff),
+			None => get_commit_diff(repo, commit, subdir),
+		},
+		None,
+		subdir,
+	)?;
 	Ok(commit_diffs)
 }
+
+/// Get a commit and the kind of signature it carries, if any
+fn get_signed_commit(
+	_repo: &Repository,
+	commit: gix::Commit,
+) -> Result<(RawCommit, Option<SignatureKind>)> {
+	let kind = commit
+		.signature()?
+		.map(|(signature, _)| SignatureKind::from_armor(signature.as_ref()));
+	Ok((RawCommit::try_from(commit)?, kind))
+}
+
+/// The outcome of checking a signature with git
+struct Verification {
+	status: Option<SignatureStatus>,
+	key: Option<String>,
+	signer: Option<String>,
+}
+
+/// Whether `git` can be run to check signatures, which is only tried once
+fn git_available() -> bool {
+	static AVAILABLE: OnceLock<bool> = OnceLock::new();
+	*AVAILABLE.get_or_init(|| {
+		Command::new("git")
+			.arg("--version")
+			.stdout(Stdio::null())
+			.stderr(Stdio::null())
+			.status()
+			.is_ok_and(|status| status.success())
+	})
+}
+
+/// Check the signatures on commits with `git log`, which verifies them with the GPG keyring and
+/// SSH allowed signers file of the user running Hipcheck
+fn verify_signatures(repo_path: &Path, hashes: &[String]) -> Result<HashMap<String, Verification>> {
+	let mut child = Command::new("git")
+		.arg("-C")
+		.arg(repo_path)
+		.args([
+			"log",
+			"--no-walk=unsorted",
+			"--stdin",
+			"--format=%H%x00%G?%x00%GK%x00%GS",
+		])
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::null())
+		.spawn()
+		.context("failed to run git")?;
+
+	// Write the hashes from another thread, so a full stdout pipe can't block the write
+	let mut stdin = child.stdin.take().context("failed to open git's stdin")?;
+	let input = hashes.join("\n");
+	let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+	let output = child
+		.wait_with_output()
+		.context("failed to read git's output")?;
+	writer
+		.join()
+		.map_err(|_| anyhow::anyhow!("failed to write to git's stdin"))?
+		.context("failed to write to git's stdin")?;
+	if !output.status.success() {
+		return Err(anyhow::anyhow!("git exited with {}", output.status));
+	}
+
+	Ok(parse_verifications(&String::from_utf8_lossy(
+		&output.stdout,
+	)))
+}
+
+/// Parse the output of `git log --format=%H%x00%G?%x00%GK%x00%GS`
+fn parse_verifications(output: &str) -> HashMap<String, Verification> {
+	let non_empty =
+		|field: Option<&str>| field.filter(|field| !field.is_empty()).map(str::to_owned);
+	output
+		.lines()
+		.filter_map(|line| {
+			let mut fields = line.split('\0');
+			let hash = fields.next()?.to_owned();
+			let status = fields.next().and_then(SignatureStatus::from_code);
+			let key = non_empty(fields.next());
+			let signer = non_empty(fields.next());
+			Some((
+				hash,
+				Verification {
+					status,
+					key,
+					signer,
+				},
+			))
+		})
+		.collect()
+}
+
+/// Get the signature on every commit in a repo, and whether it verifies, **sorted from newest to
+/// oldest**, only of the commits after `base` if it's given
+///
+/// If the signatures can't be checked, for example because git isn't installed, they are still
+/// returned, with the `Unverifiable` status
+pub fn get_commit_signatures<P>(repo_path: P, base: Option<&str>) -> Result<Vec<CommitSignature>>
+where
+	P: AsRef<Path>,
+{
+	let (repo, range) = initialize_repo(repo_path.as_ref(), base)?;
+	let commit_walker = get_commit_walker(&repo, range)?;
+	let signed_commits = walk_commits(&repo, commit_walker, &get_signed_commit, None, None)?;
+
+	// Only signed commits need to be checked
+	let signed_hashes: Vec<String> = signed_commits
+		.iter()
+		.filter(|(_, kind)| kind.is_some())
+		.map(|(raw_commit, _)| raw_commit.hash.clone())
+		.collect();
+	let mut verifications = if signed_hashes.is_empty() {
+		HashMap::new()
+	} else if !git_available() {
+		log::warn!("git isn't installed, so commit signatures can't be verified");
+		HashMap::new()
+	} else {
+		verify_signatures(repo_path.as_ref(), &signed_hashes).unwrap_or_else(|e| {
+			log::warn!("failed to verify commit signatures: {:#}", e);
+			HashMap::new()
+		})
+	};
+
+	Ok(signed_commits
+		.into_iter()
+		.map(|(raw_commit, kind)| {
+			let (status, key, signer) = match kind.map(|_| verifications.remove(&raw_commit.hash)) {
+				None => (None, None, None),
+				Some(Some(verification)) => {
+					(verification.status, verification.key, verification.signer)
+				}
+				Some(None) => (Some(SignatureStatus::Unverifiable), None, None),
+			};
+			CommitSignature {
+				commit: raw_commit.into(),
+				kind,
+				status,
+				key,
+				signer,
+			}
+		})
+		.collect())
+}
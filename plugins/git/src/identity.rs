@@ -0,0 +1,443 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Clustering the name/e-mail pairs found in commits into contributor identities.
+//!
+//! The same person often commits under several names or e-mail addresses, for
+//! example with a work and a personal address, or through GitHub's web UI with
+//! a `noreply` address. Two contributors are placed in the same identity if:
+//!
+//! - They have the same e-mail address, ignoring case.
+//! - They have the same name, ignoring case and whitespace, unless that name is
+//!   too generic to identify anyone (like "root").
+//! - One's GitHub `noreply` address names a login matching the other's name.
+//! - The repository's `.mailmap` file maps one to the other's name or e-mail address.
+
+use crate::data::{Contributor, ContributorActivity, ContributorIdentity, RawCommit};
+use std::{collections::HashMap, fs, path::Path};
+
+/// The domain of the `noreply` addresses GitHub gives its users.
+const GITHUB_NOREPLY_DOMAIN: &str = "users.noreply.github.com";
+
+/// Names shared by too many unrelated people to be used for clustering.
+const GENERIC_NAMES: &[&str] = &[
+	"admin",
+	"github",
+	"github action",
+	"github actions",
+	"root",
+	"unknown",
+	"user",
+];
+
+/// A repository's `.mailmap` file, which maps the names and e-mail addresses
+/// found in commits to the contributor's proper name and address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mailmap {
+	entries: Vec<MailmapEntry>,
+}
+
+/// One line of a `.mailmap` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MailmapEntry {
+	proper_name: Option<String>,
+	proper_email: Option<String>,
+	commit_name: Option<String>,
+	commit_email: String,
+}
+
+impl Mailmap {
+	/// Load the `.mailmap` file at the root of a repository, or an empty
+	/// mailmap if it doesn't have one.
+	pub fn load(repo_path: &Path) -> Mailmap {
+		match fs::read_to_string(repo_path.join(".mailmap")) {
+			Ok(contents) => Mailmap::parse(&contents),
+			Err(_) => Mailmap::default(),
+		}
+	}
+
+	/// Parse the contents of a `.mailmap` file, skipping lines which aren't
+	/// in one of the forms Git understands:
+	///
+	/// ```text
+	/// Proper Name <commit@email>
+	/// <proper@email> <commit@email>
+	/// Proper Name <proper@email> <commit@email>
+	/// Proper Name <proper@email> Commit Name <commit@email>
+	/// ```
+	pub fn parse(contents: &str) -> Mailmap {
+		let entries = contents.lines().filter_map(MailmapEntry::parse).collect();
+		Mailmap { entries }
+	}
+
+	/// Find the entry for a contributor, preferring one which names them over
+	/// one which only gives their address.
+	fn lookup(&self, contributor: &Contributor) -> Option<&MailmapEntry> {
+		let matching = self.entries.iter().filter(|entry| {
+			entry
+				.commit_email
+				.eq_ignore_ascii_case(contributor.email.trim())
+		});
+		let mut fallback = None;
+		for entry in matching {
+			match &entry.commit_name {
+				Some(name) if normalize_name(name) == normalize_name(&contributor.name) => {
+					return Some(entry)
+				}
+				Some(_) => {}
+				None => fallback = fallback.or(Some(entry)),
+			}
+		}
+		fallback
+	}
+}
+
+impl MailmapEntry {
+	fn parse(line: &str) -> Option<MailmapEntry> {
+		let line = line.split(" #").next()?.trim();
+		if line.is_empty() || line.starts_with('#') {
+			return None;
+		}
+
+		// Split the line into the text and e-mail addresses between its `<>`s
+		let mut names = Vec::new();
+		let mut emails = Vec::new();
+		let mut rest = line;
+		while let Some((name, after)) = rest.split_once('<') {
+			let (email, after) = after.split_once('>')?;
+			names.push(name.trim());
+			emails.push(email.trim());
+			rest = after;
+		}
+
+		let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_owned());
+		match (names.as_slice(), emails.as_slice()) {
+			([proper_name], [commit_email]) => Some(MailmapEntry {
+				proper_name: Some(non_empty(proper_name)?),
+				proper_email: None,
+				commit_name: None,
+				commit_email: commit_email.to_lowercase(),
+			}),
+			([proper_name, commit_name], [proper_email, commit_email]) => Some(MailmapEntry {
+				proper_name: non_empty(proper_name),
+				proper_email: non_empty(proper_email),
+				commit_name: non_empty(commit_name),
+				commit_email: commit_email.to_lowercase(),
+			}),
+			_ => None,
+		}
+	}
+}
+
+/// Cluster contributors into identities.
+///
+/// Identities are sorted by ID, and each identity's contributors are sorted.
+pub fn cluster_identities(
+	contributors: &[Contributor],
+	mailmap: &Mailmap,
+) -> Vec<ContributorIdentity> {
+	let mut clusters = DisjointSet::new(contributors.len());
+	let mut first_with_key: HashMap<String, usize> = HashMap::new();
+
+	for (index, contributor) in contributors.iter().enumerate() {
+		for key in identity_keys(contributor, mailmap) {
+			match first_with_key.get(&key) {
+				Some(&other) => clusters.union(index, other),
+				None => {
+					first_with_key.insert(key, index);
+				}
+			}
+		}
+	}
+
+	let mut members: HashMap<usize, Vec<Contributor>> = HashMap::new();
+	for (index, contributor) in contributors.iter().enumerate() {
+		members
+			.entry(clusters.find(index))
+			.or_default()
+			.push(contributor.clone());
+	}
+
+	let mut identities: Vec<ContributorIdentity> = members
+		.into_values()
+		.map(|mut contributors| {
+			contributors.sort();
+			contributors.dedup();
+			ContributorIdentity {
+				id: canonical_id(&contributors),
+				contributors,
+			}
+		})
+		.collect();
+	identities.sort_by(|a, b| a.id.cmp(&b.id));
+	identities
+}
+
+/// Count the commits authored by each identity among the authors of some commits.
+///
+/// Activity is sorted from the most commits to the fewest, then by ID.
+pub fn identity_activity(commits: &[RawCommit], mailmap: &Mailmap) -> Vec<ContributorActivity> {
+	let authors = commits.iter().map(|c| c.author.clone()).collect::<Vec<_>>();
+	let identities = cluster_identities(&authors, mailmap);
+	let total_commits = authors.len() as i64;
+
+	let mut identity_of: HashMap<&Contributor, usize> = HashMap::new();
+	for (index, identity) in identities.iter().enumerate() {
+		for contributor in &identity.contributors {
+			identity_of.insert(contributor, index);
+		}
+	}
+
+	let mut counts: Vec<i64> = vec![0; identities.len()];
+	for author in &authors {
+		counts[identity_of[author]] += 1;
+	}
+
+	let mut activity: Vec<ContributorActivity> = identities
+		.into_iter()
+		.zip(counts)
+		.map(|(identity, commits)| ContributorActivity {
+			id: identity.id,
+			contributors: identity.contributors,
+			commits,
+			total_commits,
+		})
+		.collect();
+	activity.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.id.cmp(&b.id)));
+	activity
+}
+
+/// Get the keys which place a contributor in the same identity as any other
+/// contributor sharing one of them.
+fn identity_keys(contributor: &Contributor, mailmap: &Mailmap) -> Vec<String> {
+	let mut keys = Vec::new();
+
+	// The mailmap's proper name and address join the contributor with any
+	// other who uses them, or is mapped to them
+	if let Some(entry) = mailmap.lookup(contributor) {
+		if let Some(email) = &entry.proper_email {
+			keys.push(format!("email:{}", email.to_lowercase()));
+		}
+		if let Some(name) = &entry.proper_name {
+			let name = normalize_name(name);
+			if !GENERIC_NAMES.contains(&name.as_str()) {
+				keys.push(format!("name:{}", name));
+			}
+		}
+	}
+
+	let email = contributor.email.trim().to_lowercase();
+	if !email.is_empty() {
+		if let Some(login) = github_noreply_login(&email) {
+			keys.push(format!("name:{}", login));
+		}
+		keys.push(format!("email:{}", email));
+	}
+
+	let name = normalize_name(&contributor.name);
+	if !name.is_empty() && !GENERIC_NAMES.contains(&name.as_str()) {
+		keys.push(format!("name:{}", name));
+	}
+
+	keys
+}
+
+/// Lowercase a name and collapse its whitespace.
+fn normalize_name(name: &str) -> String {
+	name.split_whitespace()
+		.collect::<Vec<_>>()
+		.join(" ")
+		.to_lowercase()
+}
+
+/// Get the GitHub login from a GitHub `noreply` address.
+///
+/// These come in two forms, `<login>@users.noreply.github.com` and the newer
+/// `<id>+<login>@users.noreply.github.com`.
+fn github_noreply_login(email: &str) -> Option<&str> {
+	let (local, domain) = email.rsplit_once('@')?;
+
+	if domain != GITHUB_NOREPLY_DOMAIN {
+		return None;
+	}
+
+	let login = local.split_once('+').map_or(local, |(_, login)| login);
+	(!login.is_empty()).then_some(login)
+}
+
+/// Pick the ID of an identity from its sorted contributors.
+///
+/// The ID is the lowest e-mail address in the identity, preferring addresses
+/// which aren't GitHub `noreply` addresses, so it stays the same across runs.
+fn canonical_id(contributors: &[Contributor]) -> String {
+	let emails = contributors
+		.iter()
+		.map(|c| c.email.trim().to_lowercase())
+		.filter(|e| !e.is_empty());
+
+	emails
+		.clone()
+		.filter(|e| github_noreply_login(e).is_none())
+		.min()
+		.or_else(|| emails.min())
+		// Every contributor lacks an e-mail address, so fall back to the name.
+		.unwrap_or_else(|| normalize_name(&contributors[0].name))
+}
+
+/// A minimal union-find over contributor indices.
+struct DisjointSet {
+	parents: Vec<usize>,
+}
+
+impl DisjointSet {
+	fn new(len: usize) -> Self {
+		DisjointSet {
+			parents: (0..len).collect(),
+		}
+	}
+
+	fn find(&mut self, index: usize) -> usize {
+		let parent = self.parents[index];
+		if parent == index {
+			return index;
+		}
+		let root = self.find(parent);
+		self.parents[index] = root;
+		root
+	}
+
+	fn union(&mut self, a: usize, b: usize) {
+		let (a, b) = (self.find(a), self.find(b));
+		if a != b {
+			self.parents[a.max(b)] = a.min(b);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn contributor(name: &str, email: &str) -> Contributor {
+		Contributor {
+			name: name.to_owned(),
+			email: email.to_owned(),
+		}
+	}
+
+	fn commit(author: Contributor) -> RawCommit {
+		RawCommit {
+			hash: String::new(),
+			committer: author.clone(),
+			author,
+			written_on: Err(String::new()),
+			committed_on: Err(String::new()),
+		}
+	}
+
+	#[test]
+	fn activity_is_counted_per_identity() {
+		let activity = identity_activity(
+			&[
+				commit(contributor("Jane Doe", "jane@work.example")),
+				commit(contributor("John Roe", "john@work.example")),
+				commit(contributor("jane doe", "jane@home.example")),
+			],
+			&Mailmap::default(),
+		);
+		assert_eq!(activity.len(), 2);
+		assert_eq!(activity[0].id, "jane@home.example");
+		assert_eq!(activity[0].commits, 2);
+		assert_eq!(activity[0].total_commits, 3);
+		assert_eq!(activity[1].id, "john@work.example");
+		assert_eq!(activity[1].commits, 1);
+	}
+
+	#[test]
+	fn same_name_different_email() {
+		let identities = cluster_identities(
+			&[
+				contributor("Jane Doe", "jane@work.example"),
+				contributor("jane  doe", "jane@home.example"),
+				contributor("John Roe", "john@work.example"),
+			],
+			&Mailmap::default(),
+		);
+		assert_eq!(identities.len(), 2);
+		assert_eq!(identities[0].id, "jane@home.example");
+		assert_eq!(identities[0].contributors.len(), 2);
+		assert_eq!(identities[1].id, "john@work.example");
+	}
+
+	#[test]
+	fn github_noreply_matches_login() {
+		let identities = cluster_identities(
+			&[
+				contributor("janedoe", "jane@work.example"),
+				contributor("Jane Doe", "12345+janedoe@users.noreply.github.com"),
+				contributor("Jane Doe", "JANE@home.example"),
+			],
+			&Mailmap::default(),
+		);
+		assert_eq!(identities.len(), 1);
+		assert_eq!(identities[0].id, "jane@home.example");
+	}
+
+	#[test]
+	fn generic_names_are_not_clustered() {
+		let identities = cluster_identities(
+			&[
+				contributor("root", "root@one.example"),
+				contributor("root", "root@two.example"),
+			],
+			&Mailmap::default(),
+		);
+		assert_eq!(identities.len(), 2);
+	}
+
+	#[test]
+	fn noreply_only_identity_uses_noreply_id() {
+		let identities = cluster_identities(
+			&[contributor("Jane", "janedoe@users.noreply.github.com")],
+			&Mailmap::default(),
+		);
+		assert_eq!(identities[0].id, "janedoe@users.noreply.github.com");
+	}
+
+	#[test]
+	fn mailmap_joins_addresses() {
+		let mailmap = Mailmap::parse(
+			"# Jane's old addresses\n\
+			 Jane Doe <jane@home.example> <jd@old.example>\n\
+			 <jane@home.example> J. Doe <jdoe@laptop.local>\n\
+			 not a mailmap line\n",
+		);
+		let identities = cluster_identities(
+			&[
+				contributor("Jane Doe", "jane@home.example"),
+				contributor("jdoe", "JD@old.example"),
+				contributor("J. Doe", "jdoe@laptop.local"),
+				contributor("Build Bot", "build@ci.example"),
+			],
+			&mailmap,
+		);
+		assert_eq!(identities.len(), 2);
+		assert_eq!(identities[0].id, "build@ci.example");
+		assert_eq!(identities[1].id, "jane@home.example");
+		assert_eq!(identities[1].contributors.len(), 3);
+	}
+
+	#[test]
+	fn mailmap_parses_every_form() {
+		let mailmap = Mailmap::parse(
+			"Jane Doe <jane@example>\n\
+			 <jane@example> <JANE@old.example>\n\
+			 Jane Doe <jane@example> Jane <jane@laptop.local> # a comment\n",
+		);
+		assert_eq!(mailmap.entries.len(), 3);
+		assert_eq!(mailmap.entries[0].proper_name.as_deref(), Some("Jane Doe"));
+		assert_eq!(mailmap.entries[0].proper_email, None);
+		assert_eq!(mailmap.entries[1].proper_name, None);
+		assert_eq!(mailmap.entries[1].commit_email, "jane@old.example");
+		assert_eq!(mailmap.entries[2].commit_name.as_deref(), Some("Jane"));
+	}
+}
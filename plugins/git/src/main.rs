@@ -4,30 +4,41 @@
 
 mod data;
 mod git;
+mod identity;
 
 use crate::{
 	data::{
-		Commit, CommitContributor, CommitContributorView, CommitDiff, Contributor, ContributorView,
-		DetailedGitRepo, Diff, RawCommit,
+		Commit, CommitContributor, CommitContributorView, CommitDiff, CommitFiles, CommitSignature,
+		Contributor, ContributorActivity, ContributorIdentity, ContributorView, DetailedGitRepo,
+		Diff, HistoryWindow, RawCommit,
 	},
 	git::{
-		get_all_raw_commits, get_commit_diffs, get_commits_from_date, get_contributors, get_diffs,
-		get_latest_commit, GitRawCommitCache,
+		get_all_raw_commits, get_commit_diffs, get_commit_signatures, get_commits_from_date,
+		get_contributors, get_diffs, get_file_history, get_history_window, get_latest_commit,
+		get_latest_tag_date, GitRawCommitCache, HistoryLimits,
 	},
+	identity::{cluster_identities, identity_activity, Mailmap},
 };
 use clap::Parser;
 use hipcheck_sdk::{prelude::*, types::LocalGitRepo};
-use jiff::Timestamp;
+use jiff::{Span, Timestamp, Zoned};
 use lru::LruCache;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{
 	num::NonZero,
+	path::Path,
 	sync::{Mutex, OnceLock},
 };
 
 pub static CACHE: OnceLock<Mutex<GitRawCommitCache>> = OnceLock::new();
 
+pub static HISTORY_LIMITS: OnceLock<HistoryLimits> = OnceLock::new();
+
+pub static TRACK_RENAMES: OnceLock<bool> = OnceLock::new();
+
+pub static INCREMENTAL: OnceLock<bool> = OnceLock::new();
+
 /// A locally stored git repo, with a list of additional details
 /// The details will vary based on the query (e.g. a date, a committer e-mail address, a commit hash)
 ///
@@ -42,9 +53,23 @@ pub struct BatchGitRepo {
 	pub details: Vec<String>,
 }
 
+/// The subdirectory of the repo to scope queries to, if the target was limited to one
+///
+/// Only the queries about what changed in the repo are scoped, since contributors and their
+/// commits are about the whole project
+fn subdir(repo: &LocalGitRepo) -> Option<&Path> {
+	repo.subdir.as_deref().map(Path::new)
+}
+
+/// The commit a change request branched off from, if the target is one, so only the commits it
+/// contributes are analyzed
+fn base(repo: &LocalGitRepo) -> Option<&str> {
+	repo.base.as_deref()
+}
+
 /// Returns all raw commits extracted from the repository
 fn local_raw_commits(repo: LocalGitRepo) -> Result<Vec<RawCommit>> {
-	get_all_raw_commits(&repo.path).map_err(|e| {
+	get_all_raw_commits(&repo.path, base(&repo)).map_err(|e| {
 		log::error!("failed to get raw commits: {}", e);
 		Error::UnspecifiedQueryState
 	})
@@ -55,7 +80,7 @@ fn local_raw_commits(repo: LocalGitRepo) -> Result<Vec<RawCommit>> {
 #[query]
 async fn last_commit_date(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<String> {
 	let path = &repo.path;
-	let last_commit = get_latest_commit(path)?;
+	let last_commit = get_latest_commit(path, subdir(&repo))?;
 	match last_commit {
 		Some(commit) => match commit.written_on {
 			Ok(date) => Ok(date.to_string()),
@@ -71,11 +96,24 @@ async fn last_commit_date(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Res
 	}
 }
 
+/// Returns the date of the most recent tag in a Git repo as `jiff:Timestamp` displayed as a String,
+/// or nothing if the repo has no tags
+///
+/// Projects which tag their releases can be seen to release from this, even when they commit rarely
+#[query]
+async fn last_tag_date(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Option<String>> {
+	let date = get_latest_tag_date(&repo.path).map_err(|e| {
+		log::error!("failed to get last tag date: {}", e);
+		Error::UnspecifiedQueryState
+	})?;
+	Ok(date.map(|date| date.to_string()))
+}
+
 /// Returns all diffs extracted from the repository
 #[query]
 async fn diffs(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<Diff>> {
 	let path = &repo.path;
-	let diffs = get_diffs(path).map_err(|e| {
+	let diffs = get_diffs(path, base(&repo), subdir(&repo)).map_err(|e| {
 		log::error!("{}", e);
 		Error::UnspecifiedQueryState
 	})?;
@@ -86,7 +124,7 @@ async fn diffs(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<Dif
 #[query]
 async fn commits(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<Commit>> {
 	let path = &repo.path;
-	let raw_commits = get_all_raw_commits(path).map_err(|e| {
+	let raw_commits = get_all_raw_commits(path, base(&repo)).map_err(|e| {
 		log::error!("failed to get raw commits: {}", e);
 		Error::UnspecifiedQueryState
 	})?;
@@ -114,10 +152,11 @@ async fn commits_from_date(
 		}
 	};
 	// The called function will return an error if the date is not formatted correctly, so we do not need to check for ahead of time
-	let raw_commits_from_date = get_commits_from_date(path, date).map_err(|e| {
-		log::error!("failed to get raw commits from date: {}", e);
-		Error::UnspecifiedQueryState
-	})?;
+	let raw_commits_from_date =
+		get_commits_from_date(path, base(&repo.local), date).map_err(|e| {
+			log::error!("failed to get raw commits from date: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
 	let commits = raw_commits_from_date
 		.into_iter()
 		.map(Commit::from)
@@ -130,23 +169,167 @@ async fn commits_from_date(
 #[query]
 async fn contributors(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<Contributor>> {
 	let path = &repo.path;
-	let contributors = get_contributors(path).map_err(|e| {
+	let contributors = get_contributors(path, base(&repo)).map_err(|e| {
 		log::error!("failed to get contributors: {}", e);
 		Error::UnspecifiedQueryState
 	})?;
 	Ok(contributors)
 }
 
-/// Returns all commit-diff pairs
+/// Returns the contributors to the repository clustered into identities, where each identity is
+/// one person who may have contributed under several names or e-mail addresses
 #[query]
-async fn commit_diffs(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<CommitDiff>> {
-	let commit_diffs = get_commit_diffs(&repo.path).map_err(|e| {
-		log::error!("Error finding commit diffs: {}", e);
+async fn contributor_identities(
+	_engine: &mut PluginEngine,
+	repo: LocalGitRepo,
+) -> Result<Vec<ContributorIdentity>> {
+	let contributors = get_contributors(&repo.path, base(&repo)).map_err(|e| {
+		log::error!("failed to get contributors: {}", e);
 		Error::UnspecifiedQueryState
 	})?;
+	Ok(cluster_identities(
+		&contributors,
+		&Mailmap::load(Path::new(&repo.path)),
+	))
+}
+
+/// Returns how many commits each contributor identity authored, from the most to the fewest
+#[query]
+async fn contributor_activity(
+	_engine: &mut PluginEngine,
+	repo: LocalGitRepo,
+) -> Result<Vec<ContributorActivity>> {
+	let commits = get_all_raw_commits(&repo.path, base(&repo)).map_err(|e| {
+		log::error!("failed to get raw commits: {}", e);
+		Error::UnspecifiedQueryState
+	})?;
+	Ok(identity_activity(
+		&commits,
+		&Mailmap::load(Path::new(&repo.path)),
+	))
+}
+
+/// Returns how many commits each contributor identity authored since the date given in the
+/// `details` field, from the most to the fewest, or over the whole history if no date is given
+#[query]
+async fn contributor_summary(
+	_engine: &mut PluginEngine,
+	repo: DetailedGitRepo,
+) -> Result<Vec<ContributorActivity>> {
+	let path = &repo.local.path;
+	let mailmap = Mailmap::load(Path::new(path));
+	let commits = match repo.details {
+		Some(date) => {
+			let date = Timestamp::from_str(&date).map_err(|e| {
+				log::error!("Failed to convert to jiff::Timestamp: {}", e);
+				Error::UnspecifiedQueryState
+			})?;
+			get_commits_from_date(path, base(&repo.local), date).map_err(|e| {
+				log::error!("failed to get raw commits from date: {}", e);
+				Error::UnspecifiedQueryState
+			})?
+		}
+		None => local_raw_commits(repo.local)?,
+	};
+	Ok(identity_activity(&commits, &mailmap))
+}
+
+/// Returns all commit-diff pairs
+///
+/// With incremental analysis on, the diffs from the previous run against the repo are reused, so
+/// only the commits made since then are diffed
+#[query]
+async fn commit_diffs(engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<CommitDiff>> {
+	let previous = previous_commit_diffs(engine, &repo).await;
+	let commit_diffs =
+		get_commit_diffs(&repo.path, base(&repo), subdir(&repo), previous).map_err(|e| {
+			log::error!("Error finding commit diffs: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
 	Ok(commit_diffs)
 }
 
+/// Get the commit-diff pairs Hipcheck cached from the previous run against the repo, at the HEAD
+/// that run analyzed, if incremental analysis is on
+///
+/// Without any, such as on the first run or with a version of Hipcheck which doesn't keep them, every
+/// commit is diffed
+async fn previous_commit_diffs(engine: &mut PluginEngine, repo: &LocalGitRepo) -> Vec<CommitDiff> {
+	if !INCREMENTAL.get().copied().unwrap_or(false) {
+		return Vec::new();
+	}
+
+	let previous_head = match engine
+		.query("hipcheck/cache/previous_head", Value::Null)
+		.await
+	{
+		Ok(Value::String(head)) if head != repo.git_ref => head,
+		Ok(_) => return Vec::new(),
+		Err(e) => {
+			log::warn!("failed to get previously analyzed HEAD: {}", e);
+			return Vec::new();
+		}
+	};
+
+	let key = serde_json::json!({
+		"query": "commit_diffs",
+		"key": LocalGitRepo {
+			git_ref: previous_head.clone(),
+			..repo.clone()
+		},
+	});
+	let value = match engine.query("hipcheck/cache/previous_result", key).await {
+		Ok(value) => value,
+		Err(e) => {
+			log::warn!("failed to get commit diffs from previous run: {}", e);
+			return Vec::new();
+		}
+	};
+
+	let previous = serde_json::from_value::<Option<Vec<CommitDiff>>>(value)
+		.ok()
+		.flatten()
+		.unwrap_or_default();
+	log::debug!(
+		"reusing {} commit diffs from {}",
+		previous.len(),
+		previous_head
+	);
+	previous
+}
+
+/// Returns the files changed by each commit, with each commit's author, from newest to oldest
+#[query]
+async fn file_history(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<CommitFiles>> {
+	get_file_history(&repo.path, base(&repo), subdir(&repo)).map_err(|e| {
+		log::error!("failed to get file history: {}", e);
+		Error::UnspecifiedQueryState
+	})
+}
+
+/// Returns the kind of signature on each commit, if any, and whether it verifies, from newest to
+/// oldest
+#[query]
+async fn commit_signatures(
+	_engine: &mut PluginEngine,
+	repo: LocalGitRepo,
+) -> Result<Vec<CommitSignature>> {
+	get_commit_signatures(&repo.path, base(&repo)).map_err(|e| {
+		log::error!("failed to get commit signatures: {}", e);
+		Error::UnspecifiedQueryState
+	})
+}
+
+/// Returns how much of the repository's history the other queries cover, given the history limits
+/// in the plugin's configuration
+#[query]
+async fn history_window(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<HistoryWindow> {
+	get_history_window(&repo.path, base(&repo), subdir(&repo)).map_err(|e| {
+		log::error!("failed to get history window: {}", e);
+		Error::UnspecifiedQueryState
+	})
+}
+
 /// Returns the commits associated with a given contributor (identified by e-mail address in the `details` value)
 #[query]
 async fn commits_for_contributor(
@@ -407,7 +590,7 @@ async fn commit_contributors(
 		log::error!("failed to get contributors: {}", e);
 		Error::UnspecifiedQueryState
 	})?;
-	let raw_commits = get_all_raw_commits(path).map_err(|e| {
+	let raw_commits = get_all_raw_commits(path, base(&repo)).map_err(|e| {
 		log::error!("failed to get raw commits: {}", e);
 		Error::UnspecifiedQueryState
 	})?;
@@ -439,6 +622,48 @@ async fn commit_contributors(
 struct Config {
 	#[serde(default = "default_commit_cache_size")]
 	commit_cache_size: usize,
+	#[serde(rename = "max-commits")]
+	max_commits: Option<usize>,
+	#[serde(rename = "max-age")]
+	max_age: Option<String>,
+	#[serde(rename = "track-renames")]
+	track_renames: Option<bool>,
+	incremental: Option<bool>,
+}
+
+impl Config {
+	/// Get the history limits from the config, with a maximum age converted to the time it reaches
+	/// back to from now
+	fn history_limits(&self) -> std::result::Result<HistoryLimits, ConfigError> {
+		if self.max_commits == Some(0) {
+			return Err(ConfigError::InvalidConfigValue {
+				field_name: "max-commits".to_owned(),
+				value: "0".to_owned(),
+				reason: "the history must include at least one commit".to_owned(),
+			});
+		}
+
+		let since = match &self.max_age {
+			None => None,
+			Some(max_age) => {
+				let invalid = |reason: String| ConfigError::InvalidConfigValue {
+					field_name: "max-age".to_owned(),
+					value: max_age.clone(),
+					reason,
+				};
+				let span = Span::from_str(max_age).map_err(|e| invalid(e.to_string()))?;
+				let since = Zoned::now()
+					.checked_sub(span)
+					.map_err(|e| invalid(e.to_string()))?;
+				Some(since.timestamp())
+			}
+		};
+
+		Ok(HistoryLimits {
+			max_commits: self.max_commits,
+			since,
+		})
+	}
 }
 
 fn default_commit_cache_size() -> usize {
@@ -461,6 +686,24 @@ impl Plugin for GitPlugin {
 			})?;
 		let cache_size = conf.commit_cache_size;
 
+		HISTORY_LIMITS
+			.set(conf.history_limits()?)
+			.map_err(|_e| ConfigError::Unspecified {
+				message: "config was already set".to_owned(),
+			})?;
+
+		TRACK_RENAMES
+			.set(conf.track_renames.unwrap_or(true))
+			.map_err(|_e| ConfigError::Unspecified {
+				message: "config was already set".to_owned(),
+			})?;
+
+		INCREMENTAL
+			.set(conf.incremental.unwrap_or(false))
+			.map_err(|_e| ConfigError::Unspecified {
+				message: "config was already set".to_owned(),
+			})?;
+
 		CACHE
 			.set(Mutex::new(LruCache::new(NonZero::new(cache_size).unwrap())))
 			.map_err(|_e| ConfigError::Unspecified {
@@ -476,7 +719,25 @@ impl Plugin for GitPlugin {
 		Ok(None)
 	}
 
-	queries! {}
+	queries! {
+		last_commit_date,
+		last_tag_date,
+		diffs,
+		commits,
+		commits_from_date,
+		contributors,
+		contributor_identities,
+		contributor_activity,
+		contributor_summary,
+		commit_diffs,
+		file_history,
+		commit_signatures,
+		history_window,
+		commits_for_contributor,
+		batch_commits_for_contributor,
+		contributors_for_commit,
+		batch_contributors_for_commit,
+	}
 }
 
 #[derive(Parser, Debug)]
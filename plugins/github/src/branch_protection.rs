@@ -0,0 +1,247 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Finding what the default branch of a repository requires before a change
+//! can be merged into it.
+//!
+//! A branch can be protected by classic branch protection rules, by rulesets,
+//! or by both. Rulesets can be read by anyone who can read the repository, but
+//! classic rules can only be read with admin access to it, so without that
+//! access only the rulesets are checked.
+
+use crate::{
+	drift::{BRANCH_PROTECTION_V1, BRANCH_RULES_V1, REPOSITORY_V1},
+	util::authenticated_agent::AuthenticatedAgent,
+};
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const GH_API_V3_REPOS: &str = "https://api.github.com/repos";
+
+/// The message GitHub returns for a branch without classic protection rules.
+const NOT_PROTECTED: &str = "Branch not protected";
+
+/// What the default branch of a repository requires before a change can be
+/// merged into it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct BranchProtection {
+	/// The name of the default branch.
+	pub branch: String,
+	/// Whether changes must be made through a pull request.
+	pub requires_pull_request: bool,
+	/// The number of approving reviews a pull request needs.
+	pub required_reviews: u64,
+	/// The status checks which must pass.
+	pub required_status_checks: Vec<String>,
+	/// Whether the classic branch protection rules could be read. If not, only
+	/// the rulesets were checked.
+	pub classic_rules_visible: bool,
+}
+
+/// Version 1 of the response from the REST repository API.
+#[derive(Debug, Deserialize)]
+struct RepositoryV1 {
+	default_branch: String,
+}
+
+/// Version 1 of the response from the REST branch protection API.
+#[derive(Debug, Deserialize)]
+struct ProtectionV1 {
+	required_pull_request_reviews: Option<RequiredReviewsV1>,
+	required_status_checks: Option<RequiredStatusChecksV1>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RequiredReviewsV1 {
+	#[serde(default)]
+	required_approving_review_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RequiredStatusChecksV1 {
+	#[serde(default)]
+	contexts: Vec<String>,
+	#[serde(default)]
+	checks: Vec<StatusCheckV1>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusCheckV1 {
+	context: String,
+}
+
+/// Version 1 of a rule from the REST rules for a branch API.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RuleV1 {
+	PullRequest {
+		parameters: PullRequestRuleV1,
+	},
+	RequiredStatusChecks {
+		parameters: StatusChecksRuleV1,
+	},
+	#[serde(other)]
+	Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequestRuleV1 {
+	required_approving_review_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusChecksRuleV1 {
+	required_status_checks: Vec<StatusCheckV1>,
+}
+
+impl BranchProtection {
+	/// Add the requirements of the rulesets which apply to the branch.
+	fn add_rules(&mut self, rules: Vec<RuleV1>) {
+		for rule in rules {
+			match rule {
+				RuleV1::PullRequest { parameters } => {
+					self.require_reviews(parameters.required_approving_review_count)
+				}
+				RuleV1::RequiredStatusChecks { parameters } => self.require_checks(
+					parameters
+						.required_status_checks
+						.into_iter()
+						.map(|check| check.context),
+				),
+				RuleV1::Other => {}
+			}
+		}
+	}
+
+	/// Add the requirements of the classic branch protection rules.
+	fn add_classic(&mut self, protection: ProtectionV1) {
+		if let Some(reviews) = protection.required_pull_request_reviews {
+			self.require_reviews(reviews.required_approving_review_count);
+		}
+		if let Some(checks) = protection.required_status_checks {
+			self.require_checks(
+				checks
+					.contexts
+					.into_iter()
+					.chain(checks.checks.into_iter().map(|check| check.context)),
+			);
+		}
+	}
+
+	fn require_reviews(&mut self, count: u64) {
+		self.requires_pull_request = true;
+		self.required_reviews = self.required_reviews.max(count);
+	}
+
+	fn require_checks(&mut self, checks: impl Iterator<Item = String>) {
+		for check in checks {
+			if !self.required_status_checks.contains(&check) {
+				self.required_status_checks.push(check);
+			}
+		}
+	}
+}
+
+/// Get what the default branch of a repository requires before a change can
+/// be merged into it.
+pub fn get_branch_protection(
+	agent: &AuthenticatedAgent<'_>,
+	owner: &str,
+	repo: &str,
+) -> Result<BranchProtection> {
+	let repo_url = format!("{}/{}/{}", GH_API_V3_REPOS, owner, repo);
+
+	let json = get_request(agent, &repo_url).context("unable to query repository info")?;
+	let repository: RepositoryV1 = REPOSITORY_V1.parse(json)?;
+
+	let mut protection = BranchProtection {
+		branch: repository.default_branch,
+		..BranchProtection::default()
+	};
+
+	let rules_url = format!("{}/rules/branches/{}", repo_url, protection.branch);
+	let json = get_request(agent, &rules_url).context("unable to query branch rulesets")?;
+	protection.add_rules(BRANCH_RULES_V1.parse(json)?);
+
+	let protection_url = format!("{}/branches/{}/protection", repo_url, protection.branch);
+	match agent.get(&protection_url).call() {
+		Ok(response) => {
+			let json: Value = response.into_json()?;
+			protection.add_classic(BRANCH_PROTECTION_V1.parse(json)?);
+			protection.classic_rules_visible = true;
+		}
+		Err(ureq::Error::Status(status @ (403 | 404), response)) => {
+			let message = response
+				.into_json::<Value>()
+				.ok()
+				.and_then(|body| body["message"].as_str().map(str::to_owned));
+			if status == 404 && message.as_deref() == Some(NOT_PROTECTED) {
+				protection.classic_rules_visible = true;
+			} else {
+				log::warn!(
+					"unable to read classic branch protection for {}/{}, so only rulesets were checked; reading it needs a token with admin access to the repository",
+					owner,
+					repo
+				);
+			}
+		}
+		Err(e) => return Err(e).context("unable to query branch protection"),
+	}
+
+	Ok(protection)
+}
+
+/// Get call using agent
+fn get_request(agent: &AuthenticatedAgent<'_>, url: &str) -> Result<Value> {
+	let response = agent.get(url).call()?.into_json()?;
+	Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn combines_rulesets_and_classic_rules() {
+		let rules = json!([
+			{ "type": "deletion", "ruleset_id": 1 },
+			{
+				"type": "pull_request",
+				"ruleset_id": 2,
+				"parameters": { "required_approving_review_count": 1, "dismiss_stale_reviews_on_push": true }
+			},
+			{
+				"type": "required_status_checks",
+				"ruleset_id": 2,
+				"parameters": { "required_status_checks": [{ "context": "ci/test" }] }
+			}
+		]);
+		let classic = json!({
+			"url": "https://api.github.com/repos/o/r/branches/main/protection",
+			"required_pull_request_reviews": { "required_approving_review_count": 2 },
+			"required_status_checks": {
+				"strict": true,
+				"contexts": ["ci/test"],
+				"checks": [{ "context": "ci/test", "app_id": null }, { "context": "lint", "app_id": 1 }]
+			}
+		});
+
+		let mut protection = BranchProtection::default();
+		protection.add_rules(BRANCH_RULES_V1.parse(rules).unwrap());
+		protection.add_classic(BRANCH_PROTECTION_V1.parse(classic).unwrap());
+
+		assert!(protection.requires_pull_request);
+		assert_eq!(protection.required_reviews, 2);
+		assert_eq!(protection.required_status_checks, ["ci/test", "lint"]);
+	}
+
+	#[test]
+	fn unprotected_branches_require_nothing() {
+		let mut protection = BranchProtection::default();
+		protection.add_rules(BRANCH_RULES_V1.parse(json!([])).unwrap());
+		protection.add_classic(BRANCH_PROTECTION_V1.parse(json!({})).unwrap());
+		assert_eq!(protection, BranchProtection::default());
+	}
+}
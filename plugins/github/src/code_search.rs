@@ -1,11 +1,18 @@
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::util::authenticated_agent::AuthenticatedAgent;
-use anyhow::{anyhow, Result};
+use crate::{drift::CODE_SEARCH_V1, util::authenticated_agent::AuthenticatedAgent};
+use anyhow::{Context, Result};
+use serde::Deserialize;
 use serde_json::Value;
 
 const GH_API_V4_SEARCH: &str = "https://api.github.com/search/code";
 
+/// Version 1 of the response from the code search API.
+#[derive(Debug, Deserialize)]
+struct CodeSearchV1 {
+	total_count: u64,
+}
+
 /// Make a request to the GitHub Code Search API.
 pub fn search_code_request(
 	agent: &AuthenticatedAgent<'_>,
@@ -34,12 +41,10 @@ pub fn search_code_request(
 	let query = format!("{}?q={}", GH_API_V4_SEARCH.to_owned(), sub_query);
 
 	// Make the get request.
-	let json = get_request(agent, query).map_err(|_| anyhow!("unable to query fuzzing info"))?;
+	let json = get_request(agent, query).context("unable to query fuzzing info")?;
 
-	match &json["total_count"].to_string().parse::<u64>() {
-		Ok(count) => Ok(count > &0),
-		_ => Err(anyhow!("unable to get fuzzing status")),
-	}
+	let response: CodeSearchV1 = CODE_SEARCH_V1.parse(json)?;
+	Ok(response.total_count > 0)
 }
 
 /// Get call using agent
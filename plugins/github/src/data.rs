@@ -1,11 +1,14 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-	code_search::search_code_request, graphql::get_all_reviews, types::GitHubPullRequest,
+	branch_protection::{get_branch_protection, BranchProtection},
+	code_search::search_code_request,
+	drift::SchemaDrift,
+	graphql::{get_all_reviews, ReviewFetch, ReviewWindow},
 	util::authenticated_agent::AuthenticatedAgent,
 };
-use anyhow::{Context, Result};
-use std::rc::Rc;
+use anyhow::Result;
+use std::{path::Path, rc::Rc};
 
 pub struct GitHub<'a> {
 	owner: &'a str,
@@ -23,10 +26,31 @@ impl<'a> GitHub<'a> {
 	}
 
 	pub fn fuzz_check(&self, repo_uri: Rc<String>) -> Result<bool> {
-		search_code_request(&self.agent, repo_uri).context("unable to search fuzzing information; please ensure the provided system environment variable exists and contains a valid GitHub API token")
+		search_code_request(&self.agent, repo_uri).map_err(|e| {
+			// The token has nothing to do with schema drift, so don't suggest it does
+			if e.is::<SchemaDrift>() {
+				e
+			} else {
+				e.context("unable to search fuzzing information; please ensure the provided system environment variable exists and contains a valid GitHub API token")
+			}
+		})
+	}
+
+	pub fn branch_protection(&self) -> Result<BranchProtection> {
+		get_branch_protection(&self.agent, self.owner, self.repo).map_err(|e| {
+			if e.is::<SchemaDrift>() {
+				e
+			} else {
+				e.context("unable to get branch protection; please ensure the provided system environment variable exists and contains a valid GitHub API token")
+			}
+		})
 	}
 
-	pub fn get_reviews_for_pr(&self) -> Result<Vec<GitHubPullRequest>> {
-		get_all_reviews(&self.agent, self.owner, self.repo)
+	pub fn get_reviews_for_pr(
+		&self,
+		window: &ReviewWindow,
+		checkpoint_dir: Option<&Path>,
+	) -> Result<ReviewFetch> {
+		get_all_reviews(&self.agent, self.owner, self.repo, window, checkpoint_dir)
 	}
 }
@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Detecting when GitHub's API responses no longer match what the plugin
+//! expects.
+//!
+//! Each response is parsed into a versioned model of the response as it was
+//! when the plugin was written. Models ignore fields they don't know about, so
+//! GitHub adding to its API doesn't break the plugin. Any other change is
+//! reported as schema drift, which tells the user the plugin needs updating
+//! instead of failing with a generic parse error.
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{
+	error::Error as StdError,
+	fmt::{self, Display, Formatter},
+};
+
+/// The GraphQL error codes GitHub uses when a query doesn't fit its schema.
+const SCHEMA_ERROR_CODES: &[&str] = &[
+	"undefinedField",
+	"undefinedType",
+	"argumentNotAccepted",
+	"argumentLiteralsIncompatible",
+	"missingRequiredArguments",
+];
+
+/// A version of the response from one of GitHub's APIs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseModel {
+	/// Which API the response is from.
+	pub api: &'static str,
+	/// The version of the model, increased whenever the model changes.
+	pub version: u32,
+}
+
+/// The response to the `Reviews` GraphQL query.
+pub const REVIEWS_V1: ResponseModel = ResponseModel {
+	api: "GraphQL pull request reviews",
+	version: 1,
+};
+
+/// The response from the REST code search API.
+pub const CODE_SEARCH_V1: ResponseModel = ResponseModel {
+	api: "REST code search",
+	version: 1,
+};
+
+/// The response from the REST repository API.
+pub const REPOSITORY_V1: ResponseModel = ResponseModel {
+	api: "REST repository",
+	version: 1,
+};
+
+/// The response from the REST rules for a branch API.
+pub const BRANCH_RULES_V1: ResponseModel = ResponseModel {
+	api: "REST branch rules",
+	version: 1,
+};
+
+/// The response from the REST branch protection API.
+pub const BRANCH_PROTECTION_V1: ResponseModel = ResponseModel {
+	api: "REST branch protection",
+	version: 1,
+};
+
+/// A response which doesn't match the model the plugin expects, most likely
+/// because GitHub changed its API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaDrift {
+	model: ResponseModel,
+	detail: String,
+}
+
+impl Display for SchemaDrift {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"provider schema drift: GitHub's {} response no longer matches the plugin's v{} model ({}); the GitHub API may have changed, so the mitre/github plugin may need to be updated",
+			self.model.api, self.model.version, self.detail
+		)
+	}
+}
+
+impl StdError for SchemaDrift {}
+
+impl ResponseModel {
+	/// Parse a response into this model, or report how it has drifted.
+	pub fn parse<T: DeserializeOwned>(&self, response: Value) -> Result<T, SchemaDrift> {
+		serde_json::from_value(response).map_err(|e| self.drift(e.to_string()))
+	}
+
+	/// Get the drift from this model, given what was wrong with a response.
+	pub fn drift(&self, detail: impl Into<String>) -> SchemaDrift {
+		SchemaDrift {
+			model: *self,
+			detail: detail.into(),
+		}
+	}
+
+	/// Warn that a response left out something this model expects, but can do
+	/// without.
+	pub fn warn(&self, detail: &str) {
+		log::warn!(
+			"GitHub's {} response differs from the plugin's v{} model: {}; the GitHub API may have changed",
+			self.api,
+			self.version,
+			detail
+		);
+	}
+
+	/// Check a GraphQL response for errors saying the query no longer fits
+	/// GitHub's schema.
+	pub fn graphql_drift(&self, response: &Value) -> Option<SchemaDrift> {
+		let messages = response["errors"]
+			.as_array()?
+			.iter()
+			.filter(|error| {
+				error["extensions"]["code"]
+					.as_str()
+					.is_some_and(|code| SCHEMA_ERROR_CODES.contains(&code))
+			})
+			.filter_map(|error| error["message"].as_str())
+			.collect::<Vec<_>>();
+
+		(!messages.is_empty()).then(|| self.drift(messages.join("; ")))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde::Deserialize;
+	use serde_json::json;
+
+	#[derive(Debug, Deserialize)]
+	struct CodeSearch {
+		total_count: u64,
+	}
+
+	#[test]
+	fn new_fields_are_tolerated() {
+		let response = json!({ "total_count": 2, "incomplete_results": false, "new": [] });
+		let parsed: CodeSearch = CODE_SEARCH_V1.parse(response).unwrap();
+		assert_eq!(parsed.total_count, 2);
+	}
+
+	#[test]
+	fn changed_fields_are_drift() {
+		let response = json!({ "count": 2 });
+		let drift = CODE_SEARCH_V1.parse::<CodeSearch>(response).unwrap_err();
+		let message = drift.to_string();
+		assert!(message.starts_with("provider schema drift: GitHub's REST code search response"));
+		assert!(message.contains("missing field `total_count`"));
+	}
+
+	#[test]
+	fn graphql_schema_errors_are_drift() {
+		let response = json!({
+			"errors": [
+				{
+					"message": "Field 'reviews' doesn't exist on type 'PullRequest'",
+					"extensions": { "code": "undefinedField" }
+				},
+				{ "message": "API rate limit exceeded", "type": "RATE_LIMITED" }
+			]
+		});
+		let drift = REVIEWS_V1.graphql_drift(&response).unwrap();
+		assert_eq!(
+			drift.detail,
+			"Field 'reviews' doesn't exist on type 'PullRequest'"
+		);
+
+		let rate_limited = json!({ "errors": [{ "message": "API rate limit exceeded" }] });
+		assert!(REVIEWS_V1.graphql_drift(&rate_limited).is_none());
+	}
+}
@@ -1,12 +1,24 @@
 // SPDX-License-Identifier: Apache-2.0
 
-use std::convert::TryInto;
+use std::{
+	convert::TryInto,
+	fmt::{self, Display, Formatter},
+	fs,
+	path::Path,
+	thread,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use self::reviews::{ResponseData, ReviewsRepositoryPullRequestsNodes as RawPull, Variables};
-use crate::{types::*, util::authenticated_agent::AuthenticatedAgent};
+use crate::{
+	drift::{SchemaDrift, REVIEWS_V1},
+	types::*,
+	util::authenticated_agent::AuthenticatedAgent,
+};
 use anyhow::{anyhow, Result};
 use graphql_client::{GraphQLQuery, QueryBody, Response};
-use serde_json::{from_value as from_json_value, to_value as to_json_value};
+use serde::{Deserialize, Serialize};
+use serde_json::{to_value as to_json_value, Value};
 
 /// The URL of the GitHub GraphQL API.
 const GH_API_V4: &str = "https://api.github.com/graphql";
@@ -20,24 +32,222 @@ const GH_API_V4: &str = "https://api.github.com/graphql";
 )]
 pub struct Reviews;
 
+/// How many times to try fetching a page of PRs before giving up.
+const MAX_PAGE_ATTEMPTS: u32 = 3;
+
+/// How many times to wait out a rate limit while fetching a page of PRs.
+const MAX_RATE_LIMIT_WAITS: u32 = 5;
+
+/// The longest to wait for a rate limit to lift before giving up on it.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(15 * 60);
+
+/// How long to wait after hitting a secondary rate limit which doesn't say, as GitHub advises.
+const SECONDARY_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// GitHub refused a request because a rate limit was hit.
+#[derive(Debug)]
+pub struct RateLimited {
+	/// How long until the limit lifts.
+	pub wait: Duration,
+}
+
+impl Display for RateLimited {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"GitHub API rate limit hit, which lifts in {} seconds",
+			self.wait.as_secs()
+		)
+	}
+}
+
+impl std::error::Error for RateLimited {}
+
+/// The PRs fetched for a repo, which may not be all of them if fetching stopped partway.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewFetch {
+	pub prs: Vec<GitHubPullRequest>,
+	/// Why fetching stopped before every PR in the window was fetched, if it did.
+	pub incomplete: Option<String>,
+}
+
+/// Limits on which PRs are fetched.
+///
+/// PRs are fetched newest first, so both limits select the most recent PRs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewWindow {
+	/// Only fetch this many of the most recent PRs.
+	pub max_prs: Option<u64>,
+	/// Only fetch PRs created on or after this date, formatted `YYYY-MM-DD`.
+	pub since: Option<String>,
+}
+
+impl ReviewWindow {
+	/// Add a page of PRs (newest first) to `data`, returning whether the window is now full.
+	fn extend(&self, data: &mut Vec<GitHubPullRequest>, prs: Vec<GitHubPullRequest>) -> bool {
+		for pr in prs {
+			if self.is_full(data) {
+				return true;
+			}
+
+			// Dates are RFC 3339, so they compare correctly as strings.
+			if let Some(since) = &self.since {
+				if pr.created_at.as_str() < since.as_str() {
+					return true;
+				}
+			}
+
+			data.push(pr);
+		}
+
+		self.is_full(data)
+	}
+
+	fn is_full(&self, data: &[GitHubPullRequest]) -> bool {
+		self.max_prs.is_some_and(|max| data.len() as u64 >= max)
+	}
+}
+
+/// The progress of a partially-completed fetch, saved so a later run can resume it.
+#[derive(Debug, Deserialize)]
+struct Checkpoint {
+	window: ReviewWindow,
+	cursor: Cursor,
+	prs: Vec<GitHubPullRequest>,
+}
+
+impl Checkpoint {
+	/// Load a checkpoint, if there is one for the same window.
+	fn load(path: &Path, window: &ReviewWindow) -> Option<Checkpoint> {
+		let contents = fs::read_to_string(path).ok()?;
+		let checkpoint: Checkpoint = serde_json::from_str(&contents).ok()?;
+		(&checkpoint.window == window).then_some(checkpoint)
+	}
+
+	fn save(
+		path: &Path,
+		window: &ReviewWindow,
+		cursor: &Cursor,
+		prs: &[GitHubPullRequest],
+	) -> Result<()> {
+		let checkpoint = serde_json::json!({
+			"window": window,
+			"cursor": cursor,
+			"prs": prs,
+		});
+		fs::write(path, checkpoint.to_string())?;
+		Ok(())
+	}
+}
+
 /// Query the GitHub GraphQL API for reviews performed on PRs for a repo.
+///
+/// If `checkpoint_dir` is given, progress is saved there after each page, so
+/// a fetch which fails partway through resumes where it left off next time.
+///
+/// If a page can't be fetched after some PRs were, the PRs fetched so far are
+/// returned, marked as incomplete, rather than failing outright.
 pub fn get_all_reviews(
 	agent: &AuthenticatedAgent<'_>,
 	owner: &str,
 	repo: &str,
-) -> Result<Vec<GitHubPullRequest>> {
+	window: &ReviewWindow,
+	checkpoint_dir: Option<&Path>,
+) -> Result<ReviewFetch> {
 	let vars = Vars::new(owner, repo);
 
-	let mut data = Vec::new();
-	let mut cursor = None;
+	let checkpoint_path =
+		checkpoint_dir.map(|dir| dir.join(format!("{owner}-{repo}-reviews.json")));
+
+	let (mut data, mut cursor) = match checkpoint_path
+		.as_deref()
+		.and_then(|path| Checkpoint::load(path, window))
+	{
+		Some(checkpoint) => {
+			log::info!(
+				"resuming fetch of PRs for {owner}/{repo} after {} PRs",
+				checkpoint.prs.len()
+			);
+			(checkpoint.prs, checkpoint.cursor)
+		}
+		None => (Vec::new(), None),
+	};
 
 	// Keep making requests so long as there's cursor data indicating more
-	// requests need to be made.
-	while let new_cursor @ Some(_) = get_reviews(agent, vars.with_cursor(cursor), &mut data)? {
+	// requests need to be made, and the window isn't full.
+	loop {
+		let (prs, new_cursor) = match get_reviews_with_retries(agent, &vars, cursor) {
+			Ok(page) => page,
+			// Schema drift means the PRs can't be trusted, so isn't worth reporting partially
+			Err(e) if !data.is_empty() && !e.is::<SchemaDrift>() => {
+				log::warn!(
+					"stopped fetching PRs for {owner}/{repo} after {} PRs: {e}",
+					data.len()
+				);
+				return Ok(ReviewFetch {
+					prs: data,
+					incomplete: Some(e.to_string()),
+				});
+			}
+			Err(e) => return Err(e),
+		};
+		let full = window.extend(&mut data, prs);
 		cursor = new_cursor;
+
+		if full || cursor.is_none() {
+			break;
+		}
+
+		if let Some(path) = &checkpoint_path {
+			Checkpoint::save(path, window, &cursor, &data)?;
+		}
+	}
+
+	// The fetch is complete, so there's nothing left to resume.
+	if let Some(path) = &checkpoint_path {
+		let _ = fs::remove_file(path);
 	}
 
-	Ok(data)
+	Ok(ReviewFetch {
+		prs: data,
+		incomplete: None,
+	})
+}
+
+/// Fetch a page of PRs, retrying from the same cursor if the request fails.
+///
+/// Schema drift won't go away by retrying, so it isn't retried. Rate limits
+/// are waited out, if they lift soon enough, without counting as an attempt.
+fn get_reviews_with_retries(
+	agent: &AuthenticatedAgent<'_>,
+	vars: &Vars<'_>,
+	cursor: Cursor,
+) -> Result<(Vec<GitHubPullRequest>, Cursor)> {
+	let mut attempt = 1;
+	let mut rate_limit_waits = 0;
+
+	loop {
+		match get_reviews(agent, vars.with_cursor(cursor.clone())) {
+			Ok(page) => return Ok(page),
+			Err(e) if e.is::<RateLimited>() => {
+				let wait = e.downcast_ref::<RateLimited>().unwrap().wait;
+				if rate_limit_waits >= MAX_RATE_LIMIT_WAITS || wait > MAX_RATE_LIMIT_WAIT {
+					return Err(e);
+				}
+				log::warn!("{e}, waiting to fetch more PRs");
+				thread::sleep(wait);
+				rate_limit_waits += 1;
+			}
+			Err(e) if attempt < MAX_PAGE_ATTEMPTS && !e.is::<SchemaDrift>() => {
+				log::warn!(
+					"fetching PRs failed (attempt {attempt} of {MAX_PAGE_ATTEMPTS}), retrying: {e}"
+				);
+				thread::sleep(Duration::from_secs(2u64.pow(attempt)));
+				attempt += 1;
+			}
+			Err(e) => return Err(e),
+		}
+	}
 }
 
 /// Convenience struct for creating the `Variables` struct needed for a query.
@@ -65,12 +275,11 @@ impl<'a> Vars<'a> {
 /// Convenient shorthand for a cursor from the GitHub API.
 type Cursor = Option<String>;
 
-/// Query the GitHub GraphQL API for reviews performed on PRs for a repo.
+/// Query the GitHub GraphQL API for one page of reviews performed on PRs for a repo.
 fn get_reviews(
 	agent: &AuthenticatedAgent<'_>,
 	variables: Variables,
-	data: &mut Vec<GitHubPullRequest>,
-) -> Result<Cursor> {
+) -> Result<(Vec<GitHubPullRequest>, Cursor)> {
 	// Setup the query.
 	let query = Reviews::build_query(variables);
 
@@ -81,9 +290,9 @@ fn get_reviews(
 	let cursor = get_cursor(&body);
 
 	// Process and collect all the PRs.
-	data.extend(get_prs(body)?.into_iter().map(process_pr));
+	let prs = get_prs(body)?.into_iter().map(process_pr).collect();
 
-	Ok(cursor)
+	Ok((prs, cursor))
 }
 
 /// Make a request to the GitHub API.
@@ -91,9 +300,42 @@ fn make_request(
 	agent: &AuthenticatedAgent<'_>,
 	query: QueryBody<Variables>,
 ) -> Result<Response<ResponseData>> {
-	let response = agent.post(GH_API_V4).send_json(to_json_value(query)?)?;
+	let response = match agent.post(GH_API_V4).send_json(to_json_value(query)?) {
+		Ok(response) => response,
+		// GitHub refuses requests over a rate limit as forbidden or as too many requests
+		Err(ureq::Error::Status(status @ (403 | 429), response)) => {
+			let wait = rate_limit_wait(&response);
+			let status_text = response.status_text().to_owned();
+			let body = response.into_string().unwrap_or_default();
+			return match wait {
+				Some(wait) => Err(RateLimited { wait }.into()),
+				None if body.contains("secondary rate limit") => Err(RateLimited {
+					wait: SECONDARY_RATE_LIMIT_WAIT,
+				}
+				.into()),
+				None => Err(anyhow!(
+					"request to GitHub API returned the following HTTP status: {} {}",
+					status,
+					status_text
+				)),
+			};
+		}
+		Err(e) => return Err(e.into()),
+	};
 	if response.status() == 200 {
-		return Ok(from_json_value(response.into_json()?)?);
+		let wait = rate_limit_wait(&response);
+		let body: Value = response.into_json()?;
+		// The GraphQL API reports its primary rate limit as an error in an otherwise fine response
+		if is_rate_limited(&body) {
+			return Err(RateLimited {
+				wait: wait.unwrap_or(SECONDARY_RATE_LIMIT_WAIT),
+			}
+			.into());
+		}
+		if let Some(drift) = REVIEWS_V1.graphql_drift(&body) {
+			return Err(drift.into());
+		}
+		return Ok(REVIEWS_V1.parse(body)?);
 	}
 	Err(anyhow!(
 		"request to GitHub API returned the following HTTP status: {} {}",
@@ -102,6 +344,45 @@ fn make_request(
 	))
 }
 
+/// Get how long until a rate limit lifts from the headers of a response, if it hit one.
+fn rate_limit_wait(response: &ureq::Response) -> Option<Duration> {
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |now| now.as_secs());
+	parse_rate_limit_wait(
+		response.header("retry-after"),
+		response.header("x-ratelimit-remaining"),
+		response.header("x-ratelimit-reset"),
+		now,
+	)
+}
+
+/// Get how long until a rate limit lifts from the `retry-after` header, or
+/// else from the reset time of a used-up primary rate limit.
+fn parse_rate_limit_wait(
+	retry_after: Option<&str>,
+	remaining: Option<&str>,
+	reset: Option<&str>,
+	now: u64,
+) -> Option<Duration> {
+	if let Some(seconds) = retry_after.and_then(|s| s.trim().parse::<u64>().ok()) {
+		return Some(Duration::from_secs(seconds));
+	}
+	if remaining.map(str::trim) != Some("0") {
+		return None;
+	}
+	let reset = reset?.trim().parse::<u64>().ok()?;
+	// Wait a moment past the reset, so the limit has certainly lifted
+	Some(Duration::from_secs(reset.saturating_sub(now) + 1))
+}
+
+/// Whether a GraphQL response failed because of a rate limit.
+fn is_rate_limited(body: &Value) -> bool {
+	body["errors"]
+		.as_array()
+		.is_some_and(|errors| errors.iter().any(|e| e["type"] == "RATE_LIMITED"))
+}
+
 /// Get the cursor, if there is one.
 fn get_cursor(body: &Response<ResponseData>) -> Cursor {
 	let page_info = &body
@@ -130,14 +411,23 @@ fn get_prs(body: Response<ResponseData>) -> Result<Vec<RawPull>> {
 		.pull_requests
 		.nodes;
 
-	match prs {
-		None => Ok(Vec::new()),
-		// Convert Vec<Option<_>> into Option<Vec<_>>.
-		Some(prs) => match prs.into_iter().collect() {
-			None => Ok(Vec::new()),
-			Some(prs) => Ok(prs),
-		},
+	let Some(prs) = prs else {
+		REVIEWS_V1.warn("the page of pull requests has no nodes");
+		return Ok(Vec::new());
+	};
+
+	// Skip any PRs GitHub couldn't return, rather than losing the whole page.
+	let count = prs.len();
+	let prs = prs.into_iter().flatten().collect::<Vec<_>>();
+	if prs.len() < count {
+		REVIEWS_V1.warn(&format!(
+			"{} of {} pull requests are null",
+			count - prs.len(),
+			count
+		));
 	}
+
+	Ok(prs)
 }
 
 /// Convert a single RawPull to a GitHubPullRequest
@@ -153,5 +443,90 @@ fn process_pr(pr: RawPull) -> GitHubPullRequest {
 	.try_into()
 	.unwrap();
 
-	GitHubPullRequest { number, reviews }
+	GitHubPullRequest {
+		number,
+		reviews,
+		created_at: pr.created_at,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pr(number: u64, created_at: &str) -> GitHubPullRequest {
+		GitHubPullRequest {
+			number,
+			reviews: 1,
+			created_at: created_at.to_owned(),
+		}
+	}
+
+	fn page() -> Vec<GitHubPullRequest> {
+		vec![
+			pr(3, "2024-03-01T00:00:00Z"),
+			pr(2, "2024-02-01T00:00:00Z"),
+			pr(1, "2024-01-01T00:00:00Z"),
+		]
+	}
+
+	#[test]
+	fn unbounded_window_takes_everything() {
+		let mut data = Vec::new();
+		assert!(!ReviewWindow::default().extend(&mut data, page()));
+		assert_eq!(data.len(), 3);
+	}
+
+	#[test]
+	fn window_stops_at_max_prs() {
+		let window = ReviewWindow {
+			max_prs: Some(2),
+			since: None,
+		};
+		let mut data = Vec::new();
+		assert!(window.extend(&mut data, page()));
+		assert_eq!(data.iter().map(|pr| pr.number).collect::<Vec<_>>(), [3, 2]);
+	}
+
+	#[test]
+	fn window_stops_at_since() {
+		let window = ReviewWindow {
+			max_prs: None,
+			since: Some("2024-02-01".to_owned()),
+		};
+		let mut data = Vec::new();
+		assert!(window.extend(&mut data, page()));
+		assert_eq!(data.iter().map(|pr| pr.number).collect::<Vec<_>>(), [3, 2]);
+	}
+
+	#[test]
+	fn rate_limit_wait_prefers_retry_after() {
+		assert_eq!(
+			parse_rate_limit_wait(Some("30"), Some("0"), Some("1000"), 900),
+			Some(Duration::from_secs(30))
+		);
+	}
+
+	#[test]
+	fn rate_limit_wait_until_reset() {
+		assert_eq!(
+			parse_rate_limit_wait(None, Some("0"), Some("1000"), 900),
+			Some(Duration::from_secs(101))
+		);
+		assert_eq!(
+			parse_rate_limit_wait(None, Some("12"), Some("1000"), 900),
+			None
+		);
+		assert_eq!(parse_rate_limit_wait(None, None, None, 900), None);
+	}
+
+	#[test]
+	fn rate_limited_graphql_errors() {
+		let limited = serde_json::json!({
+			"errors": [{ "type": "RATE_LIMITED", "message": "API rate limit exceeded" }]
+		});
+		let fine = serde_json::json!({ "data": { "repository": null } });
+		assert!(is_rate_limited(&limited));
+		assert!(!is_rate_limited(&fine));
+	}
 }
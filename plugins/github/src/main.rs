@@ -1,12 +1,14 @@
 // SPDX-License-Identifier: Apache-2.0
 
+mod branch_protection;
 mod code_search;
 mod data;
+mod drift;
 mod graphql;
 mod types;
 mod util;
 
-use crate::data::GitHub;
+use crate::{branch_protection::BranchProtection, data::GitHub, graphql::ReviewWindow};
 use clap::Parser;
 use hipcheck_sdk::{
 	prelude::*,
@@ -14,18 +16,36 @@ use hipcheck_sdk::{
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::result::Result as StdResult;
 use std::sync::OnceLock;
 
 struct Config {
 	pub api_token: String,
+	pub review_window: ReviewWindow,
+	pub review_checkpoint_dir: Option<PathBuf>,
 }
 
 #[derive(Deserialize)]
 struct RawConfig {
 	#[serde(rename = "api-token-var")]
 	api_token_var: Option<String>,
+	#[serde(rename = "review-max-prs")]
+	review_max_prs: Option<u64>,
+	#[serde(rename = "review-since")]
+	review_since: Option<String>,
+	#[serde(rename = "review-checkpoint-dir")]
+	review_checkpoint_dir: Option<PathBuf>,
+}
+
+/// Check that a date is formatted `YYYY-MM-DD`.
+fn is_date(date: &str) -> bool {
+	date.len() == 10
+		&& date.chars().enumerate().all(|(i, c)| match i {
+			4 | 7 => c == '-',
+			_ => c.is_ascii_digit(),
+		})
 }
 
 impl TryFrom<RawConfig> for Config {
@@ -38,7 +58,23 @@ impl TryFrom<RawConfig> for Config {
 					value: atv,
 					reason: "could not find an env var with that name".to_owned(),
 				})?;
-			Ok(Config { api_token })
+			if let Some(since) = &value.review_since {
+				if !is_date(since) {
+					return Err(ConfigError::InvalidConfigValue {
+						field_name: "review-since".to_owned(),
+						value: since.clone(),
+						reason: "must be a date formatted YYYY-MM-DD".to_owned(),
+					});
+				}
+			}
+			Ok(Config {
+				api_token,
+				review_window: ReviewWindow {
+					max_prs: value.review_max_prs,
+					since: value.review_since,
+				},
+				review_checkpoint_dir: value.review_checkpoint_dir,
+			})
 		} else {
 			Err(ConfigError::MissingRequiredConfig {
 				field_name: "api-token-var".to_owned(),
@@ -57,36 +93,42 @@ pub struct PullRequest {
 	pub reviews: u64,
 }
 
+fn get_config() -> Result<&'static Config> {
+	CONFIG.get().ok_or_else(|| {
+		log::error!("tried to access config before set by Hipcheck core!");
+		Error::UnspecifiedQueryState
+	})
+}
+
 fn get_github_agent<'a>(owner: &'a str, repo: &'a str) -> Result<GitHub<'a>> {
-	GitHub::new(
-		owner,
-		repo,
-		CONFIG
-			.get()
-			.ok_or_else(|| {
-				log::error!("tried to access config before set by Hipcheck core!");
-				Error::UnspecifiedQueryState
-			})?
-			.api_token
-			.as_str(),
-	)
+	GitHub::new(owner, repo, get_config()?.api_token.as_str())
 	.map_err(|e| {
 		log::error!("{}", e);
 		Error::UnspecifiedQueryState
 	})
 }
 
-#[query]
-async fn pr_reviews(_engine: &mut PluginEngine, key: KnownRemote) -> Result<Vec<PullRequest>> {
-	let (owner, repo) = match &key {
+/// The PRs fetched for a repo, and why fetching stopped early, if it did.
+#[derive(Debug, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct PartialPullRequests {
+	pub pull_requests: Vec<PullRequest>,
+	pub incomplete: Option<String>,
+}
+
+fn fetch_pr_reviews(key: &KnownRemote) -> Result<PartialPullRequests> {
+	let (owner, repo) = match key {
 		KnownRemote::GitHub { owner, repo } => (owner, repo),
 	};
-	let results = get_github_agent(owner, repo)?
-		.get_reviews_for_pr()
-		.map_err(|e| {
-			log::error!("{}", e);
-			Error::UnspecifiedQueryState
-		})?
+	let config = get_config()?;
+	let fetch = get_github_agent(owner, repo)?
+		.get_reviews_for_pr(
+			&config.review_window,
+			config.review_checkpoint_dir.as_deref(),
+		)
+		// Pass the error on, so a clear error like schema drift reaches the report
+		.map_err(Error::from)?;
+	let pull_requests = fetch
+		.prs
 		.into_iter()
 		.map(|pr| PullRequest {
 			id: pr.number,
@@ -94,7 +136,50 @@ async fn pr_reviews(_engine: &mut PluginEngine, key: KnownRemote) -> Result<Vec<
 		})
 		.collect();
 
-	Ok(results)
+	Ok(PartialPullRequests {
+		pull_requests,
+		incomplete: fetch.incomplete,
+	})
+}
+
+/// Returns the number of reviews on each of the repository's PRs
+#[query]
+async fn pr_reviews(_engine: &mut PluginEngine, key: KnownRemote) -> Result<Vec<PullRequest>> {
+	let results = fetch_pr_reviews(&key)?;
+	// Callers of this query expect every PR, so fetching only some is an error
+	if let Some(reason) = results.incomplete {
+		return Err(Error::from(anyhow::anyhow!(
+			"fetched only {} PRs from GitHub: {}",
+			results.pull_requests.len(),
+			reason
+		)));
+	}
+
+	Ok(results.pull_requests)
+}
+
+/// Returns the number of reviews on each of the repository's PRs, along with
+/// why fetching stopped early if the GitHub API only gave some of them
+#[query]
+async fn partial_pr_reviews(
+	_engine: &mut PluginEngine,
+	key: KnownRemote,
+) -> Result<PartialPullRequests> {
+	fetch_pr_reviews(&key)
+}
+
+/// Returns what the repository's default branch requires before a change can be merged into it
+#[query]
+async fn branch_protection(
+	_engine: &mut PluginEngine,
+	key: KnownRemote,
+) -> Result<BranchProtection> {
+	let (owner, repo) = match &key {
+		KnownRemote::GitHub { owner, repo } => (owner, repo),
+	};
+	get_github_agent(owner, repo)?
+		.branch_protection()
+		.map_err(Error::from)
 }
 
 #[query(default)]
@@ -104,10 +189,9 @@ async fn has_fuzz(_engine: &mut PluginEngine, key: RemoteGitRepo) -> Result<bool
 		None => ("", ""),
 	};
 	let url = Rc::new(key.url.to_string());
-	get_github_agent(owner, repo)?.fuzz_check(url).map_err(|e| {
-		log::error!("{}", e);
-		Error::UnspecifiedQueryState
-	})
+	get_github_agent(owner, repo)?
+		.fuzz_check(url)
+		.map_err(Error::from)
 }
 
 #[derive(Parser, Debug)]
@@ -142,7 +226,7 @@ impl Plugin for GithubAPIPlugin {
 		Ok(None)
 	}
 
-	queries! {}
+	queries! { pr_reviews, partial_pr_reviews, branch_protection, has_fuzz }
 }
 
 #[tokio::main(flavor = "current_thread")]
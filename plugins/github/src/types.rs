@@ -1,9 +1,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct GitHubPullRequest {
 	pub number: u64,
 	pub reviews: u64,
+	pub created_at: String,
 }
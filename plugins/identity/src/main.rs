@@ -8,15 +8,22 @@ use hipcheck_sdk::{
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{
+	collections::BTreeMap,
 	fmt::{self, Display, Formatter},
 	result::Result as StdResult,
 	sync::OnceLock,
 };
 
+/// Names or emails of committers, like bots and merge queues, whose commits
+/// aren't counted as having the same author and committer.
+pub static EXEMPT_COMMITTERS: OnceLock<Vec<String>> = OnceLock::new();
+
 #[derive(Deserialize)]
 struct Config {
 	#[serde(rename = "percent-threshold")]
 	percent_threshold: Option<f64>,
+	#[serde(rename = "exempt-committers")]
+	exempt_committers: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize, JsonSchema)]
@@ -38,6 +45,17 @@ impl Display for Contributor {
 	}
 }
 
+impl Contributor {
+	/// Check if the contributor is one of the exempt committers, by name or email.
+	fn is_exempt(&self) -> bool {
+		EXEMPT_COMMITTERS.get().is_some_and(|exempt| {
+			exempt.iter().any(|entry| {
+				entry.eq_ignore_ascii_case(&self.name) || entry.eq_ignore_ascii_case(&self.email)
+			})
+		})
+	}
+}
+
 /// Temporary data structure for looking up the contributors of a commit
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct CommitContributorView {
@@ -60,8 +78,11 @@ impl Display for Commit {
 	}
 }
 
-#[query]
-async fn commit_identity(engine: &mut PluginEngine, key: DetailedGitRepo) -> Result<bool> {
+/// Get the author and committer of a commit.
+async fn contributors_for_commit(
+	engine: &mut PluginEngine,
+	key: DetailedGitRepo,
+) -> Result<CommitContributorView> {
 	let value = engine
 		.query("mitre/git/contributors_for_commit", key)
 		.await
@@ -69,8 +90,12 @@ async fn commit_identity(engine: &mut PluginEngine, key: DetailedGitRepo) -> Res
 			log::error!("failed to get last commits for identity metric: {}", e);
 			Error::UnspecifiedQueryState
 		})?;
-	let ccv = serde_json::from_value::<CommitContributorView>(value)
-		.map_err(Error::InvalidJsonInQueryOutput)?;
+	serde_json::from_value::<CommitContributorView>(value).map_err(Error::InvalidJsonInQueryOutput)
+}
+
+#[query]
+async fn commit_identity(engine: &mut PluginEngine, key: DetailedGitRepo) -> Result<bool> {
+	let ccv = contributors_for_commit(engine, key).await?;
 	Ok(ccv.author == ccv.committer)
 }
 
@@ -88,12 +113,27 @@ async fn identity(engine: &mut PluginEngine, key: Target) -> Result<Vec<bool>> {
 	let commits: Vec<Commit> =
 		serde_json::from_value(value).map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
 	let mut res = vec![];
+	// How many commits by each exempt committer would otherwise have counted
+	let mut exempted: BTreeMap<String, usize> = BTreeMap::new();
 	for c in commits {
 		let key = DetailedGitRepo {
 			local: repo.clone(),
 			details: c.hash,
 		};
-		res.push(commit_identity(engine, key).await?);
+		let ccv = contributors_for_commit(engine, key).await?;
+		let same = ccv.author == ccv.committer;
+		if same && ccv.committer.is_exempt() {
+			*exempted.entry(ccv.committer.to_string()).or_default() += 1;
+			res.push(false);
+		} else {
+			res.push(same);
+		}
+	}
+	for (committer, count) in exempted {
+		engine.record_concern(format!(
+			"Exempted {} commits authored and committed by {}",
+			count, committer
+		));
 	}
 	Ok(res)
 }
@@ -118,6 +158,21 @@ impl Plugin for IdentityPlugin {
 			.map_err(|_| ConfigError::Unspecified {
 				message: "plugin was already configured".to_string(),
 			})?;
+		// Exempt committers are given as names or emails separated by commas,
+		// since names may have spaces
+		let exempt_committers = conf
+			.exempt_committers
+			.unwrap_or_default()
+			.split(',')
+			.map(str::trim)
+			.filter(|entry| !entry.is_empty())
+			.map(ToOwned::to_owned)
+			.collect();
+		EXEMPT_COMMITTERS
+			.set(exempt_committers)
+			.map_err(|_| ConfigError::Unspecified {
+				message: "plugin was already configured".to_string(),
+			})?;
 		Ok(())
 	}
 
@@ -136,12 +191,12 @@ impl Plugin for IdentityPlugin {
 
 	fn explain_default_query(&self) -> Result<Option<String>> {
 		Ok(Some(
-			"Returns whether each commit in the repo was commited and authored by the same person"
+			"Returns whether each commit in the repo was commited and authored by the same person, other than exempt committers"
 				.to_owned(),
 		))
 	}
 
-	queries! {}
+	queries! { commit_identity, identity }
 }
 
 #[derive(Parser, Debug)]
@@ -166,6 +221,8 @@ mod test {
 		let local = LocalGitRepo {
 			git_ref: "HEAD".to_owned(),
 			path: ".".to_owned(),
+			subdir: None,
+			base: None,
 		};
 		Target {
 			specifier: "".to_owned(),
@@ -235,4 +292,47 @@ mod test {
 		let res = identity(&mut engine, target()).await.unwrap();
 		assert_eq!(vec![true, false], res);
 	}
+
+	#[tokio::test]
+	async fn test_identity_exempt_committers() {
+		EXEMPT_COMMITTERS.get_or_init(|| {
+			vec!["41898282+github-actions[bot]@users.noreply.github.com".to_owned()]
+		});
+
+		let mut res = mock().unwrap();
+		let local = target().local;
+		let bot = Contributor {
+			name: "github-actions[bot]".to_owned(),
+			email: "41898282+github-actions[bot]@users.noreply.github.com".to_owned(),
+		};
+		let commit = Commit {
+			hash: "fed789".to_owned(),
+			written_on: Ok("10/24/2024".to_owned()),
+			committed_on: Ok("10/24/2024".to_owned()),
+		};
+		let detailed = DetailedGitRepo {
+			local: local.clone(),
+			details: commit.hash.clone(),
+		};
+		res.insert("mitre/git/commits", local, Ok(vec![commit.clone()]))
+			.unwrap();
+		res.insert(
+			"mitre/git/contributors_for_commit",
+			detailed,
+			Ok(CommitContributorView {
+				commit,
+				author: bot.clone(),
+				committer: bot,
+			}),
+		)
+		.unwrap();
+
+		let mut engine = PluginEngine::mock(res);
+		let res = identity(&mut engine, target()).await.unwrap();
+		assert_eq!(vec![false], res);
+		assert_eq!(
+			engine.get_concerns(),
+			vec![Concern::new("Exempted 1 commits authored and committed by github-actions[bot] <41898282+github-actions[bot]@users.noreply.github.com>")]
+		);
+	}
 }
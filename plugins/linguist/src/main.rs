@@ -64,7 +64,7 @@ impl Plugin for LinguistPlugin {
 		Ok(None)
 	}
 
-	queries! {}
+	queries! { is_likely_source_file }
 }
 
 #[derive(Parser, Debug)]
@@ -0,0 +1,389 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Plugin for querying how many recent contributors changed modules they had never touched before
+//!
+//! Modules are detected from the paths of changed files: the module of a file is the directory
+//! `module-depth` levels below the root of the repository that contains it. Contributors are
+//! identified by the canonical IDs from `mitre/git/contributor_identities`, so the same person
+//! committing under several names or e-mail addresses is only counted once.
+
+use clap::Parser;
+use hipcheck_sdk::{
+	prelude::*,
+	types::{LocalGitRepo, Target},
+};
+use jiff::{Span, Timestamp, Zoned};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+	result::Result as StdResult,
+	sync::OnceLock,
+};
+
+/// The module files directly in the root of the repository belong to.
+const ROOT_MODULE: &str = ".";
+
+#[derive(Deserialize)]
+struct Config {
+	#[serde(rename = "module-depth")]
+	module_depth: Option<usize>,
+	#[serde(rename = "window-months")]
+	window_months: Option<u32>,
+	#[serde(rename = "percent-threshold")]
+	percent_threshold: Option<f64>,
+}
+
+impl Config {
+	/// The number of directory levels below the root which make up a module's name.
+	fn module_depth(&self) -> usize {
+		self.module_depth.unwrap_or(1)
+	}
+
+	/// The number of months back from today in which contributions are checked.
+	fn window_months(&self) -> u32 {
+		self.window_months.unwrap_or(3)
+	}
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// The fields of a commit from `mitre/git` this plugin needs.
+#[derive(Debug, Clone, Deserialize)]
+struct Commit {
+	hash: String,
+	committed_on: StdResult<String, String>,
+}
+
+/// Authors or committers of a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+struct Contributor {
+	name: String,
+	email: String,
+}
+
+/// The files changed by a commit.
+#[derive(Debug, Clone, Deserialize)]
+struct CommitFiles {
+	commit: Commit,
+	author: Contributor,
+	files: Vec<String>,
+}
+
+/// A single person who may have contributed under several names or e-mail addresses.
+#[derive(Debug, Clone, Deserialize)]
+struct ContributorIdentity {
+	id: String,
+	contributors: Vec<Contributor>,
+}
+
+/// A commit in which a contributor changed a module for the first time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct NewModuleContribution {
+	/// The canonical ID of the contributor.
+	pub contributor: String,
+	pub module: String,
+	/// The commit in which the contributor first changed the module.
+	pub hash: String,
+}
+
+fn get_config() -> Result<&'static Config> {
+	CONFIG.get().ok_or_else(|| {
+		log::error!("tried to access config before set by Hipcheck core!");
+		Error::UnspecifiedQueryState
+	})
+}
+
+/// Get the module a file belongs to.
+fn module_of(file: &str, depth: usize) -> String {
+	let dirs: Vec<&str> = file.split('/').collect();
+	// The last component is the file name itself, which is never part of the module.
+	let dirs = &dirs[..dirs.len() - 1];
+
+	if dirs.is_empty() || depth == 0 {
+		return ROOT_MODULE.to_owned();
+	}
+
+	dirs[..depth.min(dirs.len())].join("/")
+}
+
+/// Find the modules changed by contributors for the first time within the window.
+///
+/// `history` must be sorted from newest to oldest, as `mitre/git/file_history` returns it.
+/// Returns the new contributions, and the IDs of every contributor who authored a commit in the
+/// window.
+fn find_new_contributions(
+	history: &[CommitFiles],
+	identities: &[ContributorIdentity],
+	depth: usize,
+	window_start: Timestamp,
+) -> Result<(Vec<NewModuleContribution>, BTreeSet<String>)> {
+	let id_of: HashMap<&Contributor, &str> = identities
+		.iter()
+		.flat_map(|i| i.contributors.iter().map(|c| (c, i.id.as_str())))
+		.collect();
+
+	let mut seen: HashSet<(&str, String)> = HashSet::new();
+	let mut new_contributions = Vec::new();
+	let mut active = BTreeSet::new();
+
+	for entry in history.iter().rev() {
+		let id = id_of.get(&entry.author).copied().ok_or_else(|| {
+			log::error!("contributor {} has no identity", entry.author.email);
+			Error::UnspecifiedQueryState
+		})?;
+
+		// Commits with unparseable dates are treated as being outside the window.
+		let in_window = entry
+			.commit
+			.committed_on
+			.as_ref()
+			.ok()
+			.and_then(|date| date.parse::<Timestamp>().ok())
+			.is_some_and(|date| date > window_start);
+
+		if in_window {
+			active.insert(id.to_owned());
+		}
+
+		let modules: BTreeSet<String> = entry.files.iter().map(|f| module_of(f, depth)).collect();
+		for module in modules {
+			if seen.insert((id, module.clone())) && in_window {
+				new_contributions.push(NewModuleContribution {
+					contributor: id.to_owned(),
+					module,
+					hash: entry.commit.hash.clone(),
+				});
+			}
+		}
+	}
+
+	Ok((new_contributions, active))
+}
+
+/// Get the new module contributions and active contributors for a repo.
+async fn get_new_contributions(
+	engine: &mut PluginEngine,
+	repo: LocalGitRepo,
+) -> Result<(Vec<NewModuleContribution>, BTreeSet<String>)> {
+	let config = get_config()?;
+
+	let window_start = Zoned::now()
+		.checked_sub(Span::new().months(i64::from(config.window_months())))
+		.map_err(|e| {
+			log::error!("failed to compute start of window: {}", e);
+			Error::UnspecifiedQueryState
+		})?
+		.timestamp();
+
+	let history_value = engine
+		.query("mitre/git/file_history", repo.clone())
+		.await
+		.map_err(|e| {
+			log::error!("failed to get file history: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
+	let history: Vec<CommitFiles> = serde_json::from_value(history_value)
+		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
+
+	let identities_value = engine
+		.query("mitre/git/contributor_identities", repo)
+		.await
+		.map_err(|e| {
+			log::error!("failed to get contributor identities: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
+	let identities: Vec<ContributorIdentity> = serde_json::from_value(identities_value)
+		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
+
+	find_new_contributions(&history, &identities, config.module_depth(), window_start)
+}
+
+/// Returns every module in the repo's history, as detected from the paths of changed files
+#[query]
+async fn modules(engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<String>> {
+	let depth = get_config()?.module_depth();
+
+	let history_value = engine
+		.query("mitre/git/file_history", repo)
+		.await
+		.map_err(|e| {
+			log::error!("failed to get file history: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
+	let history: Vec<CommitFiles> = serde_json::from_value(history_value)
+		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
+
+	let modules: BTreeSet<String> = history
+		.iter()
+		.flat_map(|entry| entry.files.iter().map(|f| module_of(f, depth)))
+		.collect();
+
+	Ok(modules.into_iter().collect())
+}
+
+/// Returns each module a contributor changed for the first time within the window
+#[query]
+async fn new_module_contributions(
+	engine: &mut PluginEngine,
+	repo: LocalGitRepo,
+) -> Result<Vec<NewModuleContribution>> {
+	Ok(get_new_contributions(engine, repo).await?.0)
+}
+
+/// Returns whether each contributor active within the window changed a module new to them
+#[query(default)]
+async fn module_contributors(engine: &mut PluginEngine, target: Target) -> Result<Vec<bool>> {
+	log::debug!("running module contributors query");
+
+	let (new_contributions, active) = get_new_contributions(engine, target.local).await?;
+
+	let mut modules_by_contributor: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+	for contribution in &new_contributions {
+		modules_by_contributor
+			.entry(contribution.contributor.as_str())
+			.or_default()
+			.push(contribution.module.as_str());
+	}
+
+	for (contributor, modules) in &modules_by_contributor {
+		engine.record_concern(format!(
+			"Contributor {} changed new module(s): {}",
+			contributor,
+			modules.join(", ")
+		));
+	}
+
+	log::info!("completed module contributors query");
+
+	Ok(active
+		.iter()
+		.map(|id| modules_by_contributor.contains_key(id.as_str()))
+		.collect())
+}
+
+#[derive(Clone, Debug)]
+struct ModuleContributorsPlugin;
+
+impl Plugin for ModuleContributorsPlugin {
+	const PUBLISHER: &'static str = "mitre";
+
+	const NAME: &'static str = "module-contributors";
+
+	fn set_config(&self, config: Value) -> StdResult<(), ConfigError> {
+		let conf =
+			serde_json::from_value::<Config>(config).map_err(|e| ConfigError::Unspecified {
+				message: e.to_string(),
+			})?;
+		CONFIG.set(conf).map_err(|_e| ConfigError::Unspecified {
+			message: "config was already set".to_owned(),
+		})
+	}
+
+	fn default_policy_expr(&self) -> Result<String> {
+		let conf = get_config()?;
+
+		let threshold = conf.percent_threshold.unwrap_or(0.3);
+
+		Ok(format!(
+			"(lte (divz (count (filter (eq #t) $)) (count $)) {})",
+			threshold
+		))
+	}
+
+	fn explain_default_query(&self) -> Result<Option<String>> {
+		Ok(Some(
+			"Whether each recent contributor changed a module they had not changed before"
+				.to_string(),
+		))
+	}
+
+	queries! { modules, new_module_contributions, module_contributors }
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+	#[arg(long)]
+	port: u16,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+	let args = Args::try_parse().unwrap();
+	PluginServer::register(ModuleContributorsPlugin)
+		.listen(args.port)
+		.await
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn contributor(name: &str) -> Contributor {
+		Contributor {
+			name: name.to_owned(),
+			email: format!("{}@example.com", name.to_lowercase()),
+		}
+	}
+
+	fn commit(hash: &str, date: &str, author: &Contributor, files: &[&str]) -> CommitFiles {
+		CommitFiles {
+			commit: Commit {
+				hash: hash.to_owned(),
+				committed_on: Ok(date.to_owned()),
+			},
+			author: author.clone(),
+			files: files.iter().map(|f| f.to_string()).collect(),
+		}
+	}
+
+	#[test]
+	fn test_module_of() {
+		assert_eq!(module_of("README.md", 1), ".");
+		assert_eq!(module_of("src/lib.rs", 1), "src");
+		assert_eq!(module_of("src/net/tcp.rs", 1), "src");
+		assert_eq!(module_of("src/net/tcp.rs", 2), "src/net");
+		assert_eq!(module_of("src/lib.rs", 2), "src");
+		assert_eq!(module_of("src/lib.rs", 0), ".");
+	}
+
+	#[test]
+	fn test_find_new_contributions() {
+		let alice = contributor("Alice");
+		let bob = contributor("Bob");
+		let identities = vec![
+			ContributorIdentity {
+				id: "alice@example.com".to_owned(),
+				contributors: vec![alice.clone()],
+			},
+			ContributorIdentity {
+				id: "bob@example.com".to_owned(),
+				contributors: vec![bob.clone()],
+			},
+		];
+
+		// Newest first, as `mitre/git/file_history` returns it.
+		let history = vec![
+			commit("4", "2024-06-04T00:00:00Z", &bob, &["src/lib.rs"]),
+			commit("3", "2024-06-03T00:00:00Z", &alice, &["docs/a.md", "src/b.rs"]),
+			commit("2", "2024-01-02T00:00:00Z", &alice, &["src/a.rs"]),
+			commit("1", "2024-01-01T00:00:00Z", &bob, &["src/main.rs"]),
+		];
+		let window_start: Timestamp = "2024-06-01T00:00:00Z".parse().unwrap();
+
+		let (new, active) = find_new_contributions(&history, &identities, 1, window_start).unwrap();
+
+		assert_eq!(
+			new,
+			vec![NewModuleContribution {
+				contributor: "alice@example.com".to_owned(),
+				module: "docs".to_owned(),
+				hash: "3".to_owned(),
+			}]
+		);
+		assert_eq!(
+			active.into_iter().collect::<Vec<_>>(),
+			vec!["alice@example.com", "bob@example.com"]
+		);
+	}
+}
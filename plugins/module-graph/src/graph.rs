@@ -0,0 +1,444 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Building the graph of a repository's JavaScript and TypeScript modules,
+//! resolving imports the way Node does.
+
+use crate::scan::{find_evals, find_imports, EvalKind};
+use anyhow::{Context as _, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+	ffi::OsString,
+	fs,
+	path::{Component, Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+/// The extensions of source files, in the order they're tried when resolving
+/// an import without one.
+const EXTENSIONS: &[&str] = &["js", "mjs", "cjs", "jsx", "ts", "mts", "cts", "tsx"];
+
+/// The modules built into Node.
+const BUILTINS: &[&str] = &[
+	"assert",
+	"async_hooks",
+	"buffer",
+	"child_process",
+	"cluster",
+	"console",
+	"constants",
+	"crypto",
+	"dgram",
+	"diagnostics_channel",
+	"dns",
+	"domain",
+	"events",
+	"fs",
+	"http",
+	"http2",
+	"https",
+	"inspector",
+	"module",
+	"net",
+	"os",
+	"path",
+	"perf_hooks",
+	"process",
+	"punycode",
+	"querystring",
+	"readline",
+	"repl",
+	"stream",
+	"string_decoder",
+	"sys",
+	"timers",
+	"tls",
+	"trace_events",
+	"tty",
+	"url",
+	"util",
+	"v8",
+	"vm",
+	"wasi",
+	"worker_threads",
+	"zlib",
+];
+
+/// The modules of a repository and what each imports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct ModuleGraph {
+	pub modules: Vec<Module>,
+}
+
+/// A source file and the modules it imports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Module {
+	/// The path of the file, relative to the root of the repository.
+	pub path: String,
+	pub imports: Vec<Import>,
+}
+
+/// What an import refers to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(tag = "kind", content = "name", rename_all = "lowercase")]
+pub enum Import {
+	/// A file in the repository, relative to its root.
+	Local(String),
+	/// A package installed from a registry, by name.
+	Package(String),
+	/// A module built into Node, by name.
+	Builtin(String),
+	/// A relative import which doesn't match any file in the repository.
+	Unresolved(String),
+}
+
+/// A use of `eval` or an equivalent in a source file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct EvalUse {
+	/// The path of the file, relative to the root of the repository.
+	pub path: String,
+	pub line: usize,
+	pub kind: EvalKind,
+}
+
+impl ModuleGraph {
+	/// Build the graph of every source file in the repository rooted at `root`.
+	///
+	/// Installed packages in `node_modules` directories aren't part of the
+	/// graph; imports of them are recorded as packages.
+	pub fn build(root: &Path) -> Result<ModuleGraph> {
+		let mut modules = Vec::new();
+
+		for_each_source_file(root, |rel_path, source| {
+			let mut imports: Vec<Import> = Vec::new();
+			for spec in find_imports(source) {
+				let import = resolve(root, rel_path, &spec);
+				if !imports.contains(&import) {
+					imports.push(import);
+				}
+			}
+
+			modules.push(Module {
+				path: to_slash(rel_path),
+				imports,
+			});
+		})?;
+
+		Ok(ModuleGraph { modules })
+	}
+
+	/// Get the names of the packages imported by any module, sorted and
+	/// without duplicates.
+	pub fn packages(&self) -> Vec<String> {
+		let mut packages: Vec<String> = self
+			.modules
+			.iter()
+			.flat_map(|m| m.imports.iter())
+			.filter_map(|i| match i {
+				Import::Package(name) => Some(name.clone()),
+				_ => None,
+			})
+			.collect();
+		packages.sort();
+		packages.dedup();
+		packages
+	}
+}
+
+/// Find every use of `eval` or an equivalent in the source files of the
+/// repository rooted at `root`.
+pub fn find_eval_uses(root: &Path) -> Result<Vec<EvalUse>> {
+	let mut uses = Vec::new();
+
+	for_each_source_file(root, |rel_path, source| {
+		let path = to_slash(rel_path);
+		uses.extend(find_evals(source).into_iter().map(|(kind, line)| EvalUse {
+			path: path.clone(),
+			line,
+			kind,
+		}));
+	})?;
+
+	Ok(uses)
+}
+
+/// Call `f` with the path relative to `root` and the contents of every source
+/// file in the repository, in order of path. Files in `node_modules`
+/// directories are skipped.
+fn for_each_source_file(root: &Path, mut f: impl FnMut(&Path, &str)) -> Result<()> {
+	let files = WalkDir::new(root)
+		.sort_by_file_name()
+		.into_iter()
+		.filter_entry(|e| e.file_name() != ".git" && e.file_name() != "node_modules");
+
+	for entry in files {
+		let entry = entry?;
+		if !entry.file_type().is_file() || !is_source_file(entry.path()) {
+			continue;
+		}
+
+		let rel_path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+		let bytes = fs::read(entry.path())
+			.with_context(|| format!("failed to read '{}'", entry.path().display()))?;
+		f(rel_path, &String::from_utf8_lossy(&bytes));
+	}
+
+	Ok(())
+}
+
+fn is_source_file(path: &Path) -> bool {
+	path.extension()
+		.and_then(|e| e.to_str())
+		.is_some_and(|e| EXTENSIONS.contains(&e))
+}
+
+/// Resolve what an import specifier in the file at `from` refers to.
+fn resolve(root: &Path, from: &Path, spec: &str) -> Import {
+	if spec.starts_with("./") || spec.starts_with("../") || spec == "." || spec == ".." {
+		let dir = from.parent().unwrap_or(Path::new(""));
+		return normalize(&dir.join(spec))
+			.and_then(|path| resolve_path(root, &path))
+			.map(|path| Import::Local(to_slash(&path)))
+			.unwrap_or_else(|| Import::Unresolved(spec.to_owned()));
+	}
+
+	// Absolute paths and URLs can't be resolved within the repository.
+	if spec.starts_with('/') || spec.contains("://") {
+		return Import::Unresolved(spec.to_owned());
+	}
+
+	if let Some(name) = spec.strip_prefix("node:") {
+		return Import::Builtin(package_name(name).to_owned());
+	}
+
+	let name = package_name(spec);
+	if BUILTINS.contains(&name) {
+		Import::Builtin(name.to_owned())
+	} else {
+		Import::Package(name.to_owned())
+	}
+}
+
+/// Get the package a bare specifier like `lodash/fp` or `@scope/pkg/sub` is in.
+fn package_name(spec: &str) -> &str {
+	let segments = if spec.starts_with('@') { 2 } else { 1 };
+	match spec.match_indices('/').nth(segments - 1) {
+		Some((index, _)) => &spec[..index],
+		None => spec,
+	}
+}
+
+/// Resolve `.` and `..` in a path relative to the root of the repository,
+/// returning `None` if the path leaves the repository.
+fn normalize(path: &Path) -> Option<PathBuf> {
+	let mut normalized = PathBuf::new();
+
+	for component in path.components() {
+		match component {
+			Component::Normal(part) => normalized.push(part),
+			Component::CurDir => {}
+			Component::ParentDir => {
+				if !normalized.pop() {
+					return None;
+				}
+			}
+			Component::RootDir | Component::Prefix(_) => return None,
+		}
+	}
+
+	Some(normalized)
+}
+
+/// Find the file a relative path refers to, trying it as a file and then as a
+/// directory.
+fn resolve_path(root: &Path, path: &Path) -> Option<PathBuf> {
+	resolve_file(root, path).or_else(|| resolve_dir(root, path))
+}
+
+/// Find the file a path refers to, adding an extension if needed.
+fn resolve_file(root: &Path, path: &Path) -> Option<PathBuf> {
+	if root.join(path).is_file() {
+		return Some(path.to_path_buf());
+	}
+
+	if let Some(found) = EXTENSIONS
+		.iter()
+		.map(|ext| with_extension_appended(path, ext))
+		.find(|p| root.join(p).is_file())
+	{
+		return Some(found);
+	}
+
+	// TypeScript sources import each other by the name of their compiled
+	// output, so `./foo.js` may refer to `./foo.ts`.
+	let ts_ext = match path.extension()?.to_str()? {
+		"js" => "ts",
+		"jsx" => "tsx",
+		"mjs" => "mts",
+		"cjs" => "cts",
+		_ => return None,
+	};
+	let ts_path = path.with_extension(ts_ext);
+	root.join(&ts_path).is_file().then_some(ts_path)
+}
+
+/// Find the file a directory refers to, using the `main` field of its
+/// `package.json` or else its index file.
+fn resolve_dir(root: &Path, path: &Path) -> Option<PathBuf> {
+	if !root.join(path).is_dir() {
+		return None;
+	}
+
+	if let Some(main) = package_main(&root.join(path)) {
+		if let Some(found) = normalize(&path.join(main)).and_then(|p| resolve_file(root, &p)) {
+			return Some(found);
+		}
+	}
+
+	EXTENSIONS
+		.iter()
+		.map(|ext| path.join(format!("index.{}", ext)))
+		.find(|p| root.join(p).is_file())
+}
+
+/// Get the `main` field of the `package.json` in a directory, if any.
+fn package_main(dir: &Path) -> Option<String> {
+	#[derive(Deserialize)]
+	struct PackageJson {
+		main: Option<String>,
+	}
+
+	let contents = fs::read_to_string(dir.join("package.json")).ok()?;
+	serde_json::from_str::<PackageJson>(&contents).ok()?.main
+}
+
+/// Append an extension to a path, keeping any extension it already has.
+fn with_extension_appended(path: &Path, ext: &str) -> PathBuf {
+	let mut path = OsString::from(path);
+	path.push(".");
+	path.push(ext);
+	PathBuf::from(path)
+}
+
+/// Render a relative path with `/` separators on every platform.
+fn to_slash(path: &Path) -> String {
+	path.components()
+		.map(|c| c.as_os_str().to_string_lossy())
+		.collect::<Vec<_>>()
+		.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn write(root: &Path, path: &str, contents: &str) {
+		let path = root.join(path);
+		fs::create_dir_all(path.parent().unwrap()).unwrap();
+		fs::write(path, contents).unwrap();
+	}
+
+	#[test]
+	fn package_names() {
+		assert_eq!(package_name("lodash"), "lodash");
+		assert_eq!(package_name("lodash/fp"), "lodash");
+		assert_eq!(package_name("@scope/pkg"), "@scope/pkg");
+		assert_eq!(package_name("@scope/pkg/sub/path"), "@scope/pkg");
+	}
+
+	#[test]
+	fn builds_graph() {
+		let root = tempfile::tempdir().unwrap();
+		let root = root.path();
+		write(
+			root,
+			"src/index.js",
+			r#"
+				const util = require("./util");
+				import lib from "./lib";
+				import { helper } from "../helper.js";
+				import fs from "node:fs";
+				import path from "path";
+				import fp from "lodash/fp";
+				import missing from "./missing";
+				import again from "./util.js";
+			"#,
+		);
+		write(root, "src/util.js", "");
+		write(root, "src/lib/package.json", r#"{ "main": "./main" }"#);
+		write(root, "src/lib/main.js", "");
+		write(root, "helper.ts", "export * from '@scope/pkg/sub';");
+		write(root, "node_modules/lodash/fp.js", "require('ignored')");
+
+		let graph = ModuleGraph::build(root).unwrap();
+
+		assert_eq!(
+			graph.modules,
+			vec![
+				Module {
+					path: "helper.ts".to_owned(),
+					imports: vec![Import::Package("@scope/pkg".to_owned())],
+				},
+				Module {
+					path: "src/index.js".to_owned(),
+					imports: vec![
+						Import::Local("src/util.js".to_owned()),
+						Import::Local("src/lib/main.js".to_owned()),
+						Import::Local("helper.ts".to_owned()),
+						Import::Builtin("fs".to_owned()),
+						Import::Builtin("path".to_owned()),
+						Import::Package("lodash".to_owned()),
+						Import::Unresolved("./missing".to_owned()),
+					],
+				},
+				Module {
+					path: "src/lib/main.js".to_owned(),
+					imports: vec![],
+				},
+				Module {
+					path: "src/util.js".to_owned(),
+					imports: vec![],
+				},
+			]
+		);
+		assert_eq!(graph.packages(), vec!["@scope/pkg", "lodash"]);
+	}
+
+	#[test]
+	fn finds_eval_uses() {
+		let root = tempfile::tempdir().unwrap();
+		let root = root.path();
+		write(root, "a.js", "const x = 1;\neval(code);");
+		write(root, "lib/b.ts", "setTimeout('go()', 10);");
+		write(root, "node_modules/c/index.js", "eval(ignored);");
+
+		assert_eq!(
+			find_eval_uses(root).unwrap(),
+			vec![
+				EvalUse {
+					path: "a.js".to_owned(),
+					line: 2,
+					kind: EvalKind::Eval,
+				},
+				EvalUse {
+					path: "lib/b.ts".to_owned(),
+					line: 1,
+					kind: EvalKind::ImpliedEval,
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn imports_outside_repo_are_unresolved() {
+		let root = tempfile::tempdir().unwrap();
+		let root = root.path();
+		write(root, "index.js", "require('../outside')");
+
+		let graph = ModuleGraph::build(root).unwrap();
+		assert_eq!(
+			graph.modules[0].imports,
+			vec![Import::Unresolved("../outside".to_owned())]
+		);
+	}
+}
@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Plugin for querying the module graph of JavaScript and TypeScript repositories
+//!
+//! Imports are found and resolved in-process, so no Node or npm installation is needed on the
+//! analysis host, and nothing is fetched over the network.
+
+mod graph;
+mod scan;
+
+use crate::graph::{find_eval_uses, EvalUse, ModuleGraph};
+use clap::Parser;
+use hipcheck_sdk::{prelude::*, types::LocalGitRepo};
+
+/// Returns every JavaScript and TypeScript module in the repo and what each imports
+#[query]
+async fn graph(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<ModuleGraph> {
+	ModuleGraph::build(&repo.path).map_err(|e| {
+		log::error!("failed to build module graph: {:#}", e);
+		Error::UnspecifiedQueryState
+	})
+}
+
+/// Returns the names of the packages imported by any module in the repo
+#[query]
+async fn packages(engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<String>> {
+	Ok(graph(engine, repo).await?.packages())
+}
+
+/// Returns every use of `eval`, the `Function` constructor, or string arguments to `setTimeout`
+/// and `setInterval` in the repo's modules
+#[query]
+async fn eval_uses(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<EvalUse>> {
+	find_eval_uses(&repo.path).map_err(|e| {
+		log::error!("failed to search for eval uses: {:#}", e);
+		Error::UnspecifiedQueryState
+	})
+}
+
+#[derive(Clone, Debug)]
+struct ModuleGraphPlugin;
+
+impl Plugin for ModuleGraphPlugin {
+	const PUBLISHER: &'static str = "mitre";
+
+	const NAME: &'static str = "module-graph";
+
+	fn set_config(&self, _config: Value) -> std::result::Result<(), ConfigError> {
+		Ok(())
+	}
+
+	fn default_policy_expr(&self) -> hipcheck_sdk::prelude::Result<String> {
+		Ok("".to_owned())
+	}
+
+	fn explain_default_query(&self) -> hipcheck_sdk::prelude::Result<Option<String>> {
+		Ok(None)
+	}
+
+	queries! { graph, packages, eval_uses }
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+	#[arg(long)]
+	port: u16,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+	let args = Args::try_parse().unwrap();
+	PluginServer::register(ModuleGraphPlugin)
+		.listen(args.port)
+		.await
+}
@@ -0,0 +1,420 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Finding the modules a JavaScript or TypeScript source file imports, and its
+//! uses of `eval` and equivalents.
+//!
+//! This isn't a full parser; it's a lexer just thorough enough to skip
+//! comments, strings, template literals and regular expressions, so that only
+//! real `import` declarations, `export ... from` declarations, `require` calls
+//! and dynamic `import` calls with a literal specifier are found.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+	/// An identifier, keyword or number.
+	Word(String),
+	/// The contents of a string literal.
+	Str(String),
+	/// Any other character, including the end of a template literal.
+	Punct(char),
+}
+
+/// Keywords after which a `/` starts a regular expression rather than a division.
+const REGEX_PRECEDING_KEYWORDS: &[&str] = &[
+	"await",
+	"case",
+	"delete",
+	"do",
+	"else",
+	"in",
+	"instanceof",
+	"new",
+	"of",
+	"return",
+	"throw",
+	"typeof",
+	"void",
+	"yield",
+];
+
+/// Global objects through which `eval` and `Function` may also be reached.
+const GLOBAL_OBJECTS: &[&str] = &["global", "globalThis", "self", "window"];
+
+/// The ways code can be evaluated from a string at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum EvalKind {
+	/// A call to `eval`.
+	Eval,
+	/// A call to the `Function` constructor, with or without `new`.
+	Function,
+	/// A call to `setTimeout` or `setInterval` with a string instead of a
+	/// function, which is evaluated like `eval`.
+	ImpliedEval,
+}
+
+/// Find the specifiers of every module imported by a source file, in the order
+/// they appear.
+pub fn find_imports(source: &str) -> Vec<String> {
+	let tokens: Vec<Token> = tokenize(source).into_iter().map(|(t, _)| t).collect();
+	let mut imports = Vec::new();
+	// Whether we're inside an `import` or `export` declaration which may end
+	// with `from '...'`.
+	let mut in_declaration = false;
+
+	for (i, token) in tokens.iter().enumerate() {
+		// Skip property accesses like `foo.require(...)` or `import.meta`.
+		let after_dot = i > 0 && tokens[i - 1] == Token::Punct('.');
+
+		match token {
+			Token::Word(word) if !after_dot && (word == "import" || word == "export") => {
+				match (tokens.get(i + 1), tokens.get(i + 2)) {
+					// `import 'module'`
+					(Some(Token::Str(spec)), _) if word == "import" => imports.push(spec.clone()),
+					// `import('module')`
+					(Some(Token::Punct('(')), Some(Token::Str(spec))) if word == "import" => {
+						imports.push(spec.clone())
+					}
+					_ => in_declaration = true,
+				}
+			}
+			Token::Word(word) if !after_dot && word == "require" => {
+				if let (Some(Token::Punct('(')), Some(Token::Str(spec)), Some(Token::Punct(')'))) =
+					(tokens.get(i + 1), tokens.get(i + 2), tokens.get(i + 3))
+				{
+					imports.push(spec.clone());
+				}
+			}
+			Token::Word(word) if in_declaration && word == "from" => {
+				if let Some(Token::Str(spec)) = tokens.get(i + 1) {
+					imports.push(spec.clone());
+					in_declaration = false;
+				}
+			}
+			Token::Punct(';') => in_declaration = false,
+			_ => {}
+		}
+	}
+
+	imports
+}
+
+/// Find every use of `eval` or an equivalent in a source file, with the line
+/// it's on, in the order they appear.
+pub fn find_evals(source: &str) -> Vec<(EvalKind, usize)> {
+	let tokens = tokenize(source);
+	let mut evals = Vec::new();
+
+	for (i, (token, line)) in tokens.iter().enumerate() {
+		let Token::Word(word) = token else {
+			continue;
+		};
+
+		// Skip property accesses like `obj.eval(...)`, except on global objects
+		// like `window.eval(...)`.
+		if i > 0 && tokens[i - 1].0 == Token::Punct('.') {
+			let on_global = match i.checked_sub(2).map(|j| &tokens[j].0) {
+				Some(Token::Word(object)) => GLOBAL_OBJECTS.contains(&object.as_str()),
+				_ => false,
+			};
+			if !on_global {
+				continue;
+			}
+		}
+
+		let next = tokens.get(i + 1).map(|(t, _)| t);
+		let first_arg = tokens.get(i + 2).map(|(t, _)| t);
+		if next != Some(&Token::Punct('(')) {
+			continue;
+		}
+
+		let kind = match word.as_str() {
+			"eval" => EvalKind::Eval,
+			"Function" => EvalKind::Function,
+			"setTimeout" | "setInterval"
+				if matches!(first_arg, Some(Token::Str(_)) | Some(Token::Punct('`'))) =>
+			{
+				EvalKind::ImpliedEval
+			}
+			_ => continue,
+		};
+		evals.push((kind, *line));
+	}
+
+	evals
+}
+
+/// Split a source file into tokens, each with the line it starts on.
+fn tokenize(source: &str) -> Vec<(Token, usize)> {
+	let chars: Vec<char> = source.chars().collect();
+	let newlines: Vec<usize> = chars
+		.iter()
+		.enumerate()
+		.filter(|(_, c)| **c == '\n')
+		.map(|(i, _)| i)
+		.collect();
+	let line_of = |i: usize| newlines.partition_point(|&n| n < i) + 1;
+
+	let mut tokens = Vec::new();
+	let mut depth = 0;
+	// The brace depths at which template literal substitutions were opened.
+	let mut templates = Vec::new();
+	let mut i = 0;
+
+	while i < chars.len() {
+		let c = chars[i];
+		let line = line_of(i);
+
+		match c {
+			_ if c.is_whitespace() => i += 1,
+			'/' if chars.get(i + 1) == Some(&'/') => {
+				while i < chars.len() && chars[i] != '\n' {
+					i += 1;
+				}
+			}
+			'/' if chars.get(i + 1) == Some(&'*') => {
+				i += 2;
+				while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+					i += 1;
+				}
+				i += 2;
+			}
+			'/' if regex_allowed(tokens.last().map(|(t, _)| t)) => i = skip_regex(&chars, i + 1),
+			'\'' | '"' => {
+				let (contents, next) = read_string(&chars, i + 1, c);
+				tokens.push((Token::Str(contents), line));
+				i = next;
+			}
+			'`' => {
+				i = skip_template(&chars, i + 1, &mut depth, &mut templates);
+				tokens.push((Token::Punct('`'), line));
+			}
+			'{' => {
+				depth += 1;
+				tokens.push((Token::Punct('{'), line));
+				i += 1;
+			}
+			'}' if templates.last() == Some(&depth) => {
+				templates.pop();
+				depth -= 1;
+				i = skip_template(&chars, i + 1, &mut depth, &mut templates);
+				tokens.push((Token::Punct('`'), line));
+			}
+			'}' => {
+				depth = depth.saturating_sub(1);
+				tokens.push((Token::Punct('}'), line));
+				i += 1;
+			}
+			_ if is_word_char(c) => {
+				let start = i;
+				while i < chars.len() && is_word_char(chars[i]) {
+					i += 1;
+				}
+				tokens.push((Token::Word(chars[start..i].iter().collect()), line));
+			}
+			_ => {
+				tokens.push((Token::Punct(c), line));
+				i += 1;
+			}
+		}
+	}
+
+	tokens
+}
+
+fn is_word_char(c: char) -> bool {
+	c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Check if a `/` following the given token starts a regular expression.
+fn regex_allowed(previous: Option<&Token>) -> bool {
+	match previous {
+		None => true,
+		Some(Token::Word(word)) => REGEX_PRECEDING_KEYWORDS.contains(&word.as_str()),
+		Some(Token::Str(_)) => false,
+		Some(Token::Punct(c)) => !matches!(c, ')' | ']' | '}' | '`'),
+	}
+}
+
+/// Read a string literal starting after its opening quote, returning its
+/// contents and the index after its closing quote.
+fn read_string(chars: &[char], mut i: usize, quote: char) -> (String, usize) {
+	let mut contents = String::new();
+
+	while i < chars.len() {
+		match chars[i] {
+			'\\' => {
+				if let Some(&escaped) = chars.get(i + 1) {
+					contents.push(escaped);
+				}
+				i += 2;
+			}
+			// Unterminated string; stop at the end of the line.
+			'\n' => return (contents, i),
+			c if c == quote => return (contents, i + 1),
+			c => {
+				contents.push(c);
+				i += 1;
+			}
+		}
+	}
+
+	(contents, i)
+}
+
+/// Skip a regular expression literal starting after its opening slash,
+/// returning the index after its flags.
+fn skip_regex(chars: &[char], mut i: usize) -> usize {
+	let mut in_class = false;
+
+	while i < chars.len() {
+		match chars[i] {
+			'\\' => i += 1,
+			'[' => in_class = true,
+			']' => in_class = false,
+			'/' if !in_class => {
+				i += 1;
+				break;
+			}
+			// Not actually a regular expression; resume on the next line.
+			'\n' => return i,
+			_ => {}
+		}
+		i += 1;
+	}
+
+	while i < chars.len() && is_word_char(chars[i]) {
+		i += 1;
+	}
+
+	i
+}
+
+/// Skip the text of a template literal, starting after its opening backtick or
+/// the `}` closing a substitution.
+///
+/// Returns the index after the closing backtick, or after the `${` opening the
+/// next substitution, in which case the substitution is recorded so tokenizing
+/// can resume inside it.
+fn skip_template(
+	chars: &[char],
+	mut i: usize,
+	depth: &mut usize,
+	templates: &mut Vec<usize>,
+) -> usize {
+	while i < chars.len() {
+		match chars[i] {
+			'\\' => i += 2,
+			'`' => return i + 1,
+			'$' if chars.get(i + 1) == Some(&'{') => {
+				*depth += 1;
+				templates.push(*depth);
+				return i + 2;
+			}
+			_ => i += 1,
+		}
+	}
+
+	i
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn finds_import_forms() {
+		let source = r#"
+			import defaultExport from "a";
+			import * as name from 'b';
+			import { x, y as z } from "c";
+			import "d";
+			export { w } from "e";
+			export * from "f";
+			import type { T } from "g";
+			const h = require("h");
+			const i = await import("i");
+		"#;
+		assert_eq!(
+			find_imports(source),
+			vec!["a", "b", "c", "d", "e", "f", "g", "h", "i"]
+		);
+	}
+
+	#[test]
+	fn multiline_import() {
+		let source = "import {\n\tfoo,\n\tbar,\n} from './foo'\nfoo(bar)\n";
+		assert_eq!(find_imports(source), vec!["./foo"]);
+	}
+
+	#[test]
+	fn ignores_comments_and_strings() {
+		let source = r#"
+			// const a = require("a");
+			/* import b from "b"; */
+			const c = "require('c')";
+			const d = `import e from "e"`;
+			obj.require("f");
+			const g = /require\("g"\)/;
+			const h = import.meta.url;
+		"#;
+		assert!(find_imports(source).is_empty());
+	}
+
+	#[test]
+	fn finds_require_in_template_substitution() {
+		let source = "const s = `${require('a')} and ${`${require(\"b\")}`}`;\nrequire('c');";
+		assert_eq!(find_imports(source), vec!["a", "b", "c"]);
+	}
+
+	#[test]
+	fn division_is_not_a_regex() {
+		let source = "const x = a / b; const y = require('y'); const z = (c) / 2 / d;";
+		assert_eq!(find_imports(source), vec!["y"]);
+	}
+
+	#[test]
+	fn finds_evals() {
+		let source = r#"
+			eval("1 + 1");
+			const f = new Function("a", "return a");
+			const g = Function("return this")();
+			setTimeout("alert(1)", 100);
+			setInterval(`tick()`, 100);
+			window.eval(code);
+		"#;
+		assert_eq!(
+			find_evals(source),
+			vec![
+				(EvalKind::Eval, 2),
+				(EvalKind::Function, 3),
+				(EvalKind::Function, 4),
+				(EvalKind::ImpliedEval, 5),
+				(EvalKind::ImpliedEval, 6),
+				(EvalKind::Eval, 7),
+			]
+		);
+	}
+
+	#[test]
+	fn ignores_safe_lookalikes() {
+		let source = r#"
+			// eval("commented out");
+			const s = "eval('in a string')";
+			setTimeout(() => run(), 100);
+			setInterval(tick, 100);
+			parser.eval(expr);
+			const evaluate = 1;
+			const fn = eval;
+		"#;
+		assert!(find_evals(source).is_empty());
+	}
+
+	#[test]
+	fn export_without_from() {
+		let source = "export const from = 1;\nconst s = 'x';\nexport default foo;\n";
+		assert!(find_imports(source).is_empty());
+	}
+}
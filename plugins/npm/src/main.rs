@@ -112,7 +112,7 @@ impl Plugin for DependenciesPlugin {
 		Ok(None)
 	}
 
-	queries! {}
+	queries! { dependencies }
 }
 
 #[derive(Parser, Debug)]
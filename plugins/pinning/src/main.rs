@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Plugin for detecting unpinned dependencies of CI workflows and container
+//! builds
+
+mod scan;
+
+use crate::scan::{find_unpinned, UnpinnedReference};
+use clap::Parser;
+use hipcheck_sdk::{
+	prelude::*,
+	types::{LocalGitRepo, Target},
+};
+use serde::Deserialize;
+use std::{path::Path, result::Result as StdResult, sync::OnceLock};
+
+#[derive(Deserialize)]
+struct Config {
+	#[serde(rename = "unpinned-threshold")]
+	unpinned_threshold: Option<u64>,
+}
+
+/// Returns every unpinned action, image, and install script referenced in the
+/// repository
+#[query]
+async fn references(
+	_engine: &mut PluginEngine,
+	repo: LocalGitRepo,
+) -> Result<Vec<UnpinnedReference>> {
+	find_unpinned(Path::new(&repo.path)).map_err(|e| {
+		log::error!("failed to search for unpinned references: {}", e);
+		Error::UnspecifiedQueryState
+	})
+}
+
+/// Returns the number of unpinned references in the repository
+#[query(default)]
+async fn pinning(engine: &mut PluginEngine, value: Target) -> Result<usize> {
+	let references = references(engine, value.local).await?;
+	references.iter().for_each(|r| {
+		let message = format!(
+			"Found unpinned {} '{}' at '{}:{}' ({})",
+			r.kind,
+			r.reference,
+			r.path.display(),
+			r.line,
+			r.reason
+		);
+		engine.record_concern(
+			Concern::new(message)
+				.with_file(r.path.to_string_lossy())
+				.with_line(r.line as u64)
+				.with_identifier(&r.reference),
+		)
+	});
+	Ok(references.len())
+}
+
+#[derive(Clone, Debug, Default)]
+struct PinningPlugin {
+	policy_conf: OnceLock<Option<u64>>,
+}
+
+impl Plugin for PinningPlugin {
+	const PUBLISHER: &'static str = "mitre";
+	const NAME: &'static str = "pinning";
+
+	fn set_config(&self, config: Value) -> StdResult<(), ConfigError> {
+		let conf =
+			serde_json::from_value::<Config>(config).map_err(|e| ConfigError::Unspecified {
+				message: e.to_string(),
+			})?;
+
+		// Store the policy conf to be accessed only in the `default_policy_expr()` impl
+		self.policy_conf
+			.set(conf.unpinned_threshold)
+			.map_err(|_| ConfigError::Unspecified {
+				message: "plugin was already configured".to_string(),
+			})
+	}
+
+	fn default_policy_expr(&self) -> Result<String> {
+		match self.policy_conf.get() {
+			None => Err(Error::UnspecifiedQueryState),
+			Some(policy_conf) => Ok(format!("(lte $ {})", policy_conf.unwrap_or(0))),
+		}
+	}
+
+	fn explain_default_query(&self) -> Result<Option<String>> {
+		Ok(Some(
+			"Returns number of unpinned actions, images, and install scripts in a repo".to_owned(),
+		))
+	}
+
+	queries! { references, pinning }
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+	#[arg(long)]
+	port: u16,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+	let args = Args::try_parse().unwrap();
+	PluginServer::register(PinningPlugin::default())
+		.listen(args.port)
+		.await
+}
@@ -0,0 +1,410 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Finding references to actions, images, and install scripts which aren't
+//! pinned to an immutable version.
+
+use anyhow::Result;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::{
+	fmt::{self, Display, Formatter},
+	fs,
+	path::{Path, PathBuf},
+	sync::LazyLock,
+};
+use walkdir::WalkDir;
+
+/// Matches a step or job using an action or reusable workflow.
+static USES: LazyLock<Regex> =
+	LazyLock::new(|| Regex::new(r#"^\s*(?:-\s*)?uses:\s*["']?([^\s"'#]+)"#).expect("valid regex"));
+
+/// Matches the image of a job container or service container.
+static WORKFLOW_IMAGE: LazyLock<Regex> = LazyLock::new(|| {
+	Regex::new(r#"^\s*(?:-\s*)?(?:image|container):\s*["']?([^\s"'#]+)"#).expect("valid regex")
+});
+
+/// Matches the base image of a Dockerfile stage, and the stage's name.
+static FROM: LazyLock<Regex> = LazyLock::new(|| {
+	Regex::new(r"(?i)^\s*FROM\s+(?:--\S+\s+)*(\S+)(?:\s+AS\s+(\S+))?").expect("valid regex")
+});
+
+/// Matches a downloaded script being run by a shell, as in `curl ... | sh` or
+/// `bash <(curl ...)`.
+static PIPE_TO_SHELL: LazyLock<Regex> = LazyLock::new(|| {
+	Regex::new(
+		r"\b(?:curl|wget)\b[^|\n]*\|\s*(?:sudo\s+(?:-\S+\s+)*)?(?:ba|da|k|z)?sh\b|\b(?:ba|z)?sh\s+<\(\s*(?:curl|wget)\b",
+	)
+	.expect("valid regex")
+});
+
+/// Matches a URL.
+static URL: LazyLock<Regex> =
+	LazyLock::new(|| Regex::new(r#"https?://[^\s"'|)]+"#).expect("valid regex"));
+
+/// Branches commonly used as the ref of an action.
+const BRANCHES: &[&str] = &["main", "master", "develop", "dev", "trunk", "HEAD"];
+
+/// What an unpinned reference refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReferenceKind {
+	/// A GitHub Action or reusable workflow.
+	Action,
+	/// A container image.
+	Image,
+	/// A script downloaded and run by a shell.
+	InstallScript,
+}
+
+impl Display for ReferenceKind {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			ReferenceKind::Action => write!(f, "action"),
+			ReferenceKind::Image => write!(f, "image"),
+			ReferenceKind::InstallScript => write!(f, "install script"),
+		}
+	}
+}
+
+/// A reference which can change what it refers to without the repository
+/// changing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct UnpinnedReference {
+	/// What the reference refers to.
+	pub kind: ReferenceKind,
+	/// The reference, as written.
+	pub reference: String,
+	/// The file the reference is in, relative to the root of the tree.
+	pub path: PathBuf,
+	/// The line the reference is on, starting from 1.
+	pub line: usize,
+	/// Why the reference isn't pinned.
+	pub reason: String,
+}
+
+/// The kinds of file which are scanned for references.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+	/// A GitHub Actions workflow, or the metadata of a composite action.
+	Workflow,
+	Dockerfile,
+	ShellScript,
+}
+
+impl FileKind {
+	/// Find what kind of file is at a path relative to the root of the tree, if
+	/// it's one that is scanned.
+	fn of(rel_path: &Path) -> Option<FileKind> {
+		let name = rel_path.file_name()?.to_str()?.to_lowercase();
+		let extension = rel_path
+			.extension()
+			.and_then(|e| e.to_str())
+			.map(str::to_lowercase);
+		let is_yaml = matches!(extension.as_deref(), Some("yml" | "yaml"));
+
+		if is_yaml
+			&& (rel_path.starts_with(".github/workflows")
+				|| name == "action.yml"
+				|| name == "action.yaml")
+		{
+			Some(FileKind::Workflow)
+		} else if name == "dockerfile"
+			|| name == "containerfile"
+			|| name.starts_with("dockerfile.")
+			|| name.ends_with(".dockerfile")
+		{
+			Some(FileKind::Dockerfile)
+		} else if matches!(extension.as_deref(), Some("sh" | "bash")) {
+			Some(FileKind::ShellScript)
+		} else {
+			None
+		}
+	}
+}
+
+/// Find the unpinned references in the workflows, Dockerfiles, and shell
+/// scripts of the tree rooted at `root`.
+pub fn find_unpinned(root: &Path) -> Result<Vec<UnpinnedReference>> {
+	let mut found = Vec::new();
+
+	let files = WalkDir::new(root)
+		.sort_by_file_name()
+		.into_iter()
+		.filter_entry(|e| e.file_name() != ".git")
+		.filter(|e| e.as_ref().map_or(true, |e| e.file_type().is_file()));
+
+	for entry in files {
+		let path = entry?.into_path();
+		let rel_path = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+
+		let Some(kind) = FileKind::of(&rel_path) else {
+			continue;
+		};
+
+		// Files which aren't text can't hold references.
+		let Ok(contents) = fs::read_to_string(&path) else {
+			log::debug!("skipping '{}', which isn't UTF-8", rel_path.display());
+			continue;
+		};
+
+		found.extend(scan_file(kind, &rel_path, &contents));
+	}
+
+	Ok(found)
+}
+
+/// Find the unpinned references in the contents of a file.
+fn scan_file(kind: FileKind, rel_path: &Path, contents: &str) -> Vec<UnpinnedReference> {
+	let mut found = Vec::new();
+	// The names of the earlier stages of a Dockerfile, which later stages can
+	// build from.
+	let mut stages: Vec<String> = Vec::new();
+
+	for (index, line) in contents.lines().enumerate() {
+		let mut push = |kind: ReferenceKind, reference: &str, reason: String| {
+			found.push(UnpinnedReference {
+				kind,
+				reference: reference.to_owned(),
+				path: rel_path.to_path_buf(),
+				line: index + 1,
+				reason,
+			})
+		};
+
+		if line.trim_start().starts_with('#') {
+			continue;
+		}
+
+		match kind {
+			FileKind::Workflow => {
+				if let Some(captures) = USES.captures(line) {
+					let reference = &captures[1];
+					match reference.strip_prefix("docker://") {
+						Some(image) => {
+							if let Some(reason) = unpinned_image(image) {
+								push(ReferenceKind::Image, reference, reason);
+							}
+						}
+						None => {
+							if let Some(reason) = unpinned_action(reference) {
+								push(ReferenceKind::Action, reference, reason);
+							}
+						}
+					}
+				} else if let Some(captures) = WORKFLOW_IMAGE.captures(line) {
+					let image = &captures[1];
+					if let Some(reason) = unpinned_image(image) {
+						push(ReferenceKind::Image, image, reason);
+					}
+				}
+			}
+			FileKind::Dockerfile => {
+				if let Some(captures) = FROM.captures(line) {
+					let image = &captures[1];
+					let is_stage = stages.iter().any(|s| s.eq_ignore_ascii_case(image));
+					if !is_stage {
+						if let Some(reason) = unpinned_image(image) {
+							push(ReferenceKind::Image, image, reason);
+						}
+					}
+					if let Some(stage) = captures.get(2) {
+						stages.push(stage.as_str().to_owned());
+					}
+				}
+			}
+			FileKind::ShellScript => {}
+		}
+
+		if let Some(matched) = PIPE_TO_SHELL.find(line) {
+			let reference = URL
+				.find(line)
+				.map_or_else(|| matched.as_str().trim(), |url| url.as_str());
+			push(
+				ReferenceKind::InstallScript,
+				reference,
+				"pipes a downloaded script into a shell without checking it".to_owned(),
+			);
+		}
+	}
+
+	found
+}
+
+/// Get why a reference to an action isn't pinned, if it isn't.
+///
+/// Only a full commit SHA pins an action; tags can be moved and branches move
+/// with every commit, and the two can't be told apart from the reference.
+/// Local actions are part of the repository, so they're always pinned.
+fn unpinned_action(reference: &str) -> Option<String> {
+	if reference.starts_with("./") || reference.contains("${{") {
+		return None;
+	}
+
+	match reference.rsplit_once('@') {
+		None => Some("has no version".to_owned()),
+		Some((_, git_ref)) if is_commit_sha(git_ref) => None,
+		Some((_, git_ref)) if BRANCHES.contains(&git_ref) => {
+			Some(format!("tracks the `{}` branch", git_ref))
+		}
+		Some((_, git_ref)) => Some(format!("uses the ref `{}`, which can be moved", git_ref)),
+	}
+}
+
+/// Get why a reference to a container image isn't pinned, if it isn't.
+///
+/// Only a digest pins an image; tags can be pushed again. Images named by
+/// variables can't be checked, so they're skipped.
+fn unpinned_image(image: &str) -> Option<String> {
+	if image.contains('@') || image.contains('$') || image.eq_ignore_ascii_case("scratch") {
+		return None;
+	}
+
+	// A `:` before the last `/` separates a registry's host and port.
+	let name = image.rsplit('/').next().unwrap_or(image);
+	match name.split_once(':') {
+		None => Some("has no tag, so uses `latest`".to_owned()),
+		Some((_, "latest")) => Some("uses the `latest` tag".to_owned()),
+		Some((_, tag)) => Some(format!("uses the tag `{}`, which can be moved", tag)),
+	}
+}
+
+/// Check if a Git ref is a full commit SHA.
+fn is_commit_sha(git_ref: &str) -> bool {
+	git_ref.len() == 40 && git_ref.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn scan(kind: FileKind, contents: &str) -> Vec<(ReferenceKind, String, usize)> {
+		scan_file(kind, Path::new("file"), contents)
+			.into_iter()
+			.map(|r| (r.kind, r.reference, r.line))
+			.collect()
+	}
+
+	#[test]
+	fn classifies_files() {
+		let of = |path: &str| FileKind::of(Path::new(path));
+		assert_eq!(of(".github/workflows/ci.yml"), Some(FileKind::Workflow));
+		assert_eq!(of("actions/setup/action.yaml"), Some(FileKind::Workflow));
+		assert_eq!(of("config/ci.yml"), None);
+		assert_eq!(of("docker/Dockerfile"), Some(FileKind::Dockerfile));
+		assert_eq!(of("Dockerfile.dev"), Some(FileKind::Dockerfile));
+		assert_eq!(of("web.Dockerfile"), Some(FileKind::Dockerfile));
+		assert_eq!(of("scripts/install.sh"), Some(FileKind::ShellScript));
+		assert_eq!(of("src/main.rs"), None);
+	}
+
+	#[test]
+	fn finds_unpinned_actions() {
+		let workflow = r#"
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    container: node:20
+    steps:
+      - uses: actions/checkout@b4ffde65f46336ab88eb53be808477a3936bae11 # v4
+      - uses: actions/setup-node@v4
+      - uses: "someone/action@master"
+      - uses: ./.github/actions/local
+      - uses: docker://alpine
+      # - uses: commented/out@v1
+  call:
+    uses: org/repo/.github/workflows/build.yml@main
+"#;
+		assert_eq!(
+			scan(FileKind::Workflow, workflow),
+			vec![
+				(ReferenceKind::Image, "node:20".to_owned(), 5),
+				(ReferenceKind::Action, "actions/setup-node@v4".to_owned(), 8),
+				(ReferenceKind::Action, "someone/action@master".to_owned(), 9),
+				(ReferenceKind::Image, "docker://alpine".to_owned(), 11),
+				(
+					ReferenceKind::Action,
+					"org/repo/.github/workflows/build.yml@main".to_owned(),
+					14
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn finds_unpinned_images() {
+		let dockerfile = "\
+FROM --platform=$BUILDPLATFORM rust:1.83 AS builder
+FROM registry.example.com:5000/base
+FROM debian@sha256:2a3c5a0d1e5b0b2ab3a3b5e8c3d0a8e3d9e7b1c7d7a2c2f0a9c3e1b5f7d9e2a1
+FROM builder AS test
+from ubuntu:latest
+FROM ${BASE_IMAGE}
+FROM scratch
+";
+		assert_eq!(
+			scan(FileKind::Dockerfile, dockerfile),
+			vec![
+				(ReferenceKind::Image, "rust:1.83".to_owned(), 1),
+				(
+					ReferenceKind::Image,
+					"registry.example.com:5000/base".to_owned(),
+					2
+				),
+				(ReferenceKind::Image, "ubuntu:latest".to_owned(), 5),
+			]
+		);
+	}
+
+	#[test]
+	fn finds_install_scripts() {
+		let script = "\
+#!/bin/sh
+curl -sSfL https://example.com/install.sh | sudo -E bash
+wget -qO- https://example.com/setup | sh -s -- -y
+bash <(curl -s https://example.com/get)
+curl -o tool.tar.gz https://example.com/tool.tar.gz
+curl https://example.com/data | jq .
+";
+		assert_eq!(
+			scan(FileKind::ShellScript, script),
+			vec![
+				(
+					ReferenceKind::InstallScript,
+					"https://example.com/install.sh".to_owned(),
+					2
+				),
+				(
+					ReferenceKind::InstallScript,
+					"https://example.com/setup".to_owned(),
+					3
+				),
+				(
+					ReferenceKind::InstallScript,
+					"https://example.com/get".to_owned(),
+					4
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn explains_why_references_are_unpinned() {
+		assert_eq!(
+			unpinned_action("actions/checkout@v4").as_deref(),
+			Some("uses the ref `v4`, which can be moved")
+		);
+		assert_eq!(
+			unpinned_action("actions/checkout@main").as_deref(),
+			Some("tracks the `main` branch")
+		);
+		assert_eq!(
+			unpinned_image("alpine").as_deref(),
+			Some("has no tag, so uses `latest`")
+		);
+		assert_eq!(
+			unpinned_image("localhost:5000/app").as_deref(),
+			Some("has no tag, so uses `latest`")
+		);
+	}
+}
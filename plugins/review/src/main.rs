@@ -23,6 +23,14 @@ pub struct PullRequest {
 	pub reviews: u64,
 }
 
+/// The PRs fetched from GitHub, and why fetching stopped early, if it did.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct PartialPullRequests {
+	pub pull_requests: Vec<PullRequest>,
+	#[serde(default)]
+	pub incomplete: Option<String>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct PullReview {
 	pub pull_request: PullRequest,
@@ -47,12 +55,24 @@ async fn review(engine: &mut PluginEngine, value: Target) -> Result<Vec<bool>> {
 
 	// Get a list of all pull requests to the repo, with their corresponding number of reviews
 	let value = engine
-		.query("mitre/github/pr_reviews", known_remote)
+		.query("mitre/github/partial_pr_reviews", known_remote)
 		.await
 		.context("failed to get pull request reviews from GitHub")?;
 
-	let pull_requests: Vec<PullRequest> =
-		serde_json::from_value(value).map_err(Error::InvalidJsonInQueryOutput)?;
+	let PartialPullRequests {
+		pull_requests,
+		incomplete,
+	} = serde_json::from_value(value).map_err(Error::InvalidJsonInQueryOutput)?;
+
+	// GitHub may stop giving PRs partway, as when rate limited, so the result is
+	// based on the PRs fetched, with a concern saying it may be off
+	if let Some(reason) = incomplete {
+		engine.record_concern(format!(
+			"Only {} pull requests could be fetched from GitHub, so the result may be inaccurate: {}",
+			pull_requests.len(),
+			reason
+		));
+	}
 
 	log::trace!("got pull requests [requests='{:#?}']", pull_requests);
 
@@ -104,7 +124,7 @@ impl Plugin for ReviewPlugin {
 		))
 	}
 
-	queries! {}
+	queries! { review }
 }
 
 #[derive(Parser, Debug)]
@@ -143,34 +163,63 @@ mod test {
 		let pr2 = PullRequest { id: 2, reviews: 3 };
 		let pr3 = PullRequest { id: 3, reviews: 0 };
 		let pr4 = PullRequest { id: 4, reviews: 1 };
-		let prs = vec![pr1, pr2, pr3, pr4];
+		let prs = PartialPullRequests {
+			pull_requests: vec![pr1, pr2, pr3, pr4],
+			incomplete: None,
+		};
 
-		// when calling into query, the input known_remote gets passed to `pr_reviews`, lets assume it returns the vec of PullRequests `prs`
+		// when calling into query, the input known_remote gets passed to `partial_pr_reviews`, lets assume it returns the PullRequests `prs`
 		let mut mock_responses = MockResponses::new();
-		mock_responses.insert("mitre/github/pr_reviews", known_remote, Ok(prs))?;
+		mock_responses.insert("mitre/github/partial_pr_reviews", known_remote, Ok(prs))?;
 		Ok(mock_responses)
 	}
 
-	#[tokio::test]
-	async fn test_activity() {
-		let target = Target {
+	fn target() -> Target {
+		Target {
 			specifier: "express".to_string(),
 			local: LocalGitRepo {
 				path: "/home/users/me/.cache/hipcheck/clones/github/expressjs/express/".to_string(),
 				git_ref: "main".to_string(),
+				subdir: None,
+				base: None,
 			},
 			remote: Some(RemoteGitRepo {
 				url: Url::parse("https://github.com/expressjs/express.git").unwrap(),
 				known_remote: Some(known_remote()),
 			}),
 			package: None,
-		};
+		}
+	}
 
+	#[tokio::test]
+	async fn test_activity() {
 		let mut engine = PluginEngine::mock(mock_responses().unwrap());
-		let result = review(&mut engine, target).await.unwrap();
+		let result = review(&mut engine, target()).await.unwrap();
 
 		let expected = vec![true, true, false, true];
 
 		assert_eq!(result, expected);
+		assert!(engine.get_concerns().is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_partial_reviews() {
+		let prs = PartialPullRequests {
+			pull_requests: vec![PullRequest { id: 1, reviews: 0 }],
+			incomplete: Some("GitHub API rate limit hit, which lifts in 3600 seconds".to_string()),
+		};
+		let mut mock_responses = mock_responses().unwrap();
+		mock_responses
+			.insert("mitre/github/partial_pr_reviews", known_remote(), Ok(prs))
+			.unwrap();
+
+		let mut engine = PluginEngine::mock(mock_responses);
+		let result = review(&mut engine, target()).await.unwrap();
+
+		assert_eq!(result, vec![false]);
+		assert_eq!(
+			engine.get_concerns(),
+			vec![Concern::new("Only 1 pull requests could be fetched from GitHub, so the result may be inaccurate: GitHub API rate limit hit, which lifts in 3600 seconds")]
+		);
 	}
 }
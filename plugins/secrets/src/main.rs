@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Plugin for detecting credentials leaked in a repository's history
+
+mod detect;
+mod types;
+
+use crate::{detect::*, types::*};
+use clap::Parser;
+use hipcheck_sdk::{prelude::*, types::Target};
+use serde::Deserialize;
+use std::{result::Result as StdResult, sync::OnceLock};
+
+pub static DETECTOR: OnceLock<SecretDetector> = OnceLock::new();
+
+#[derive(Deserialize)]
+struct RawConfig {
+	#[serde(rename = "entropy-threshold")]
+	entropy_threshold: Option<f64>,
+	#[serde(rename = "count-threshold")]
+	count_threshold: Option<u64>,
+}
+
+struct Config {
+	entropy_threshold: f64,
+	opt_threshold: Option<u64>,
+}
+
+impl TryFrom<RawConfig> for Config {
+	type Error = hipcheck_sdk::error::ConfigError;
+	fn try_from(value: RawConfig) -> StdResult<Config, Self::Error> {
+		let entropy_threshold = value.entropy_threshold.unwrap_or(DEFAULT_ENTROPY_THRESHOLD);
+		if entropy_threshold <= 0.0 {
+			return Err(ConfigError::InvalidConfigValue {
+				field_name: "entropy-threshold".to_owned(),
+				value: entropy_threshold.to_string(),
+				reason: "entropy must be greater than 0.0 bits per character".to_owned(),
+			});
+		}
+		Ok(Config {
+			entropy_threshold,
+			opt_threshold: value.count_threshold,
+		})
+	}
+}
+
+/// Returns the possible secrets added by each commit
+#[query]
+async fn commit_secrets(
+	_engine: &mut PluginEngine,
+	commit_diffs: Vec<CommitDiff>,
+) -> Result<Vec<CommitSecrets>> {
+	let detector = DETECTOR.get().ok_or(Error::UnspecifiedQueryState)?;
+	Ok(commit_diffs
+		.into_iter()
+		.map(|commit_diff| detector.commit_secrets(commit_diff))
+		.collect())
+}
+
+/// Returns the number of possible secrets added by each commit
+#[query(default)]
+async fn secrets(engine: &mut PluginEngine, value: Target) -> Result<Vec<usize>> {
+	let local = value.local;
+	let val_commits = engine.query("mitre/git/commit_diffs", local).await?;
+	let commits: Vec<CommitDiff> =
+		serde_json::from_value(val_commits).map_err(Error::InvalidJsonInQueryOutput)?;
+
+	let commit_secrets = commit_secrets(engine, commits).await?;
+	for cs in commit_secrets.iter().filter(|cs| !cs.secrets.is_empty()) {
+		let found = cs
+			.secrets
+			.iter()
+			.map(|s| format!("{} '{}' in '{}'", s.kind, s.redacted, s.file_name))
+			.collect::<Vec<_>>()
+			.join(", ");
+		let mut concern = Concern::new(format!(
+			"Commit {} adds possible secrets: {}",
+			cs.commit.hash, found
+		))
+		.with_severity(Severity::High)
+		.with_commit(&cs.commit.hash);
+		for secret in &cs.secrets {
+			if !concern.identifiers.contains(&secret.file_name) {
+				concern = concern.with_identifier(&secret.file_name);
+			}
+		}
+		engine.record_concern(concern);
+	}
+
+	Ok(commit_secrets.iter().map(|cs| cs.secrets.len()).collect())
+}
+
+#[derive(Clone, Debug, Default)]
+struct SecretsPlugin {
+	policy_conf: OnceLock<Option<u64>>,
+}
+
+impl Plugin for SecretsPlugin {
+	const PUBLISHER: &'static str = "mitre";
+	const NAME: &'static str = "secrets";
+
+	fn set_config(&self, config: Value) -> StdResult<(), ConfigError> {
+		// Deserialize and validate the config struct
+		let conf: Config = serde_json::from_value::<RawConfig>(config)
+			.map_err(|e| ConfigError::Unspecified {
+				message: e.to_string(),
+			})?
+			.try_into()?;
+
+		// Store the policy conf to be accessed only in the `default_policy_expr()` impl
+		self.policy_conf
+			.set(conf.opt_threshold)
+			.map_err(|_| ConfigError::Unspecified {
+				message: "plugin was already configured".to_string(),
+			})?;
+
+		DETECTOR
+			.set(SecretDetector::new(conf.entropy_threshold))
+			.map_err(|_e| ConfigError::Unspecified {
+				message: "config was already set".to_owned(),
+			})
+	}
+
+	fn default_policy_expr(&self) -> Result<String> {
+		match self.policy_conf.get() {
+			None => Err(Error::UnspecifiedQueryState),
+			Some(policy_conf) => Ok(format!(
+				"(lte (count (filter (gt 0) $)) {})",
+				policy_conf.unwrap_or(0)
+			)),
+		}
+	}
+
+	fn explain_default_query(&self) -> Result<Option<String>> {
+		Ok(Some(
+			"Returns the number of possible secrets added by each commit in a repo".to_owned(),
+		))
+	}
+
+	queries! { commit_secrets, secrets }
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+	#[arg(long)]
+	port: u16,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+	let args = Args::try_parse().unwrap();
+	PluginServer::register(SecretsPlugin::default())
+		.listen(args.port)
+		.await
+}
@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+	fmt::{self, Display, Formatter},
+	result::Result,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, JsonSchema)]
+pub struct Commit {
+	pub hash: String,
+	pub written_on: Result<String, String>,
+	pub committed_on: Result<String, String>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq, JsonSchema, Deserialize)]
+pub struct FileDiff {
+	pub file_name: String,
+	pub additions: Option<i64>,
+	pub deletions: Option<i64>,
+	pub patch: String,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq, JsonSchema, Deserialize)]
+pub struct Diff {
+	pub additions: Option<i64>,
+	pub deletions: Option<i64>,
+	pub file_diffs: Vec<FileDiff>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, JsonSchema, Deserialize)]
+pub struct CommitDiff {
+	pub commit: Commit,
+	pub diff: Diff,
+}
+
+/// The kinds of secret which can be detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecretKind {
+	/// An AWS access key ID.
+	AwsAccessKey,
+	/// An AWS secret access key.
+	AwsSecretKey,
+	/// A GitHub personal access token, OAuth token, or app token.
+	GitHubToken,
+	/// The start of a PEM-encoded private key.
+	PrivateKey,
+	/// A token too random to be ordinary text.
+	HighEntropy,
+}
+
+impl Display for SecretKind {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			SecretKind::AwsAccessKey => write!(f, "AWS access key"),
+			SecretKind::AwsSecretKey => write!(f, "AWS secret key"),
+			SecretKind::GitHubToken => write!(f, "GitHub token"),
+			SecretKind::PrivateKey => write!(f, "private key"),
+			SecretKind::HighEntropy => write!(f, "high-entropy token"),
+		}
+	}
+}
+
+/// A possible secret added by a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Secret {
+	/// What kind of secret it seems to be.
+	pub kind: SecretKind,
+	/// The file the secret was added to.
+	pub file_name: String,
+	/// The start of the secret, so it can be found without being repeated in
+	/// full.
+	pub redacted: String,
+}
+
+/// The possible secrets added by a single commit.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct CommitSecrets {
+	/// The commit
+	pub commit: Commit,
+	/// The possible secrets it added
+	pub secrets: Vec<Secret>,
+}
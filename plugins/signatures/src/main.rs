@@ -0,0 +1,275 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Plugin for checking how many of a repo's commits are signed, and whether their signatures
+//! verify
+
+use clap::Parser;
+use hipcheck_sdk::{
+	prelude::*,
+	types::{LocalGitRepo, Target},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+	fmt::{self, Display, Formatter},
+	result::Result as StdResult,
+	sync::OnceLock,
+};
+
+#[derive(Deserialize)]
+struct RawConfig {
+	#[serde(rename = "percent-threshold")]
+	percent_threshold: Option<f64>,
+	#[serde(rename = "require-verified")]
+	require_verified: Option<bool>,
+}
+
+#[derive(Clone, Debug)]
+struct Config {
+	percent_threshold: f64,
+	require_verified: bool,
+}
+
+impl TryFrom<RawConfig> for Config {
+	type Error = hipcheck_sdk::error::ConfigError;
+	fn try_from(value: RawConfig) -> StdResult<Config, Self::Error> {
+		let percent_threshold = value.percent_threshold.unwrap_or(0.5);
+		if !(0.0..=1.0).contains(&percent_threshold) {
+			return Err(ConfigError::InvalidConfigValue {
+				field_name: "percent-threshold".to_owned(),
+				value: percent_threshold.to_string(),
+				reason: "percentage must be between 0.0 and 1.0, inclusive".to_owned(),
+			});
+		}
+		Ok(Config {
+			percent_threshold,
+			require_verified: value.require_verified.unwrap_or(false),
+		})
+	}
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+fn get_config() -> Result<&'static Config> {
+	CONFIG.get().ok_or_else(|| {
+		log::error!("tried to access config before set by Hipcheck core!");
+		Error::UnspecifiedQueryState
+	})
+}
+
+/// The fields of a commit from `mitre/git` this plugin needs.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Commit {
+	pub hash: String,
+}
+
+/// The kind of signature on a commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureKind {
+	Gpg,
+	Ssh,
+	X509,
+}
+
+impl Display for SignatureKind {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			SignatureKind::Gpg => write!(f, "GPG"),
+			SignatureKind::Ssh => write!(f, "SSH"),
+			SignatureKind::X509 => write!(f, "X.509"),
+		}
+	}
+}
+
+/// Whether a commit's signature verifies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignatureStatus {
+	Good,
+	UnknownValidity,
+	Expired,
+	ExpiredKey,
+	RevokedKey,
+	Bad,
+	MissingKey,
+	Unverifiable,
+}
+
+/// The signature on a commit, and whether it verifies, as `mitre/git/commit_signatures` returns it.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CommitSignature {
+	pub commit: Commit,
+	pub kind: Option<SignatureKind>,
+	pub status: Option<SignatureStatus>,
+	pub key: Option<String>,
+	pub signer: Option<String>,
+}
+
+impl CommitSignature {
+	/// Whether the commit counts as signed.
+	///
+	/// Bad signatures and signatures from revoked keys never count. Signatures which couldn't be
+	/// verified, because the key is missing or they couldn't be checked at all, only count if
+	/// verification isn't required.
+	fn is_signed(&self, require_verified: bool) -> bool {
+		if self.kind.is_none() {
+			return false;
+		}
+		match self.status {
+			Some(SignatureStatus::Bad) | Some(SignatureStatus::RevokedKey) => false,
+			Some(SignatureStatus::MissingKey) | Some(SignatureStatus::Unverifiable) | None => {
+				!require_verified
+			}
+			Some(_) => true,
+		}
+	}
+
+	/// A concern about the signature, if it is bad or made by a revoked key.
+	fn concern(&self) -> Option<String> {
+		let kind = self.kind?;
+		let key = self.key.as_deref().unwrap_or("an unknown key");
+		match self.status? {
+			SignatureStatus::Bad => Some(format!(
+				"Commit {} has a bad {} signature from {}",
+				self.commit.hash, kind, key
+			)),
+			SignatureStatus::RevokedKey => Some(format!(
+				"Commit {} has a {} signature from revoked key {}",
+				self.commit.hash, kind, key
+			)),
+			_ => None,
+		}
+	}
+}
+
+/// Returns the signature on each commit, if any, and whether it verifies
+#[query]
+async fn signatures(engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<CommitSignature>> {
+	let value = engine
+		.query("mitre/git/commit_signatures", repo)
+		.await
+		.map_err(|e| {
+			log::error!("failed to get commit signatures: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
+	serde_json::from_value(value).map_err(|_| Error::UnexpectedPluginQueryInputFormat)
+}
+
+/// Returns whether each commit in a repo is signed with a signature which isn't bad
+#[query(default)]
+async fn signed(engine: &mut PluginEngine, target: Target) -> Result<Vec<bool>> {
+	let config = get_config()?;
+	let signatures = signatures(engine, target.local).await?;
+
+	for concern in signatures.iter().filter_map(CommitSignature::concern) {
+		engine.record_concern(concern);
+	}
+
+	Ok(signatures
+		.iter()
+		.map(|signature| signature.is_signed(config.require_verified))
+		.collect())
+}
+
+#[derive(Clone, Debug)]
+struct SignaturesPlugin;
+
+impl Plugin for SignaturesPlugin {
+	const PUBLISHER: &'static str = "mitre";
+
+	const NAME: &'static str = "signatures";
+
+	fn set_config(&self, config: Value) -> StdResult<(), ConfigError> {
+		let conf: Config = serde_json::from_value::<RawConfig>(config)
+			.map_err(|e| ConfigError::Unspecified {
+				message: e.to_string(),
+			})?
+			.try_into()?;
+
+		CONFIG.set(conf).map_err(|_e| ConfigError::Unspecified {
+			message: "config was already set".to_owned(),
+		})
+	}
+
+	fn default_policy_expr(&self) -> Result<String> {
+		let conf = get_config()?;
+
+		Ok(format!(
+			"(gte (divz (count (filter (eq #t) $)) (count $)) {})",
+			conf.percent_threshold
+		))
+	}
+
+	fn explain_default_query(&self) -> Result<Option<String>> {
+		Ok(Some(
+			"Returns whether each commit in a repo is signed with a signature which isn't bad"
+				.to_string(),
+		))
+	}
+
+	queries! { signatures, signed }
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+	#[arg(long)]
+	port: u16,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+	let args = Args::try_parse().unwrap();
+	PluginServer::register(SignaturesPlugin)
+		.listen(args.port)
+		.await
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn signature(kind: Option<SignatureKind>, status: Option<SignatureStatus>) -> CommitSignature {
+		CommitSignature {
+			commit: Commit {
+				hash: "abc123".to_owned(),
+			},
+			kind,
+			status,
+			key: Some("ABCDEF0123456789".to_owned()),
+			signer: None,
+		}
+	}
+
+	#[test]
+	fn test_is_signed() {
+		let gpg = Some(SignatureKind::Gpg);
+
+		assert!(!signature(None, None).is_signed(false));
+		assert!(signature(gpg, Some(SignatureStatus::Good)).is_signed(true));
+		assert!(signature(gpg, Some(SignatureStatus::ExpiredKey)).is_signed(true));
+		assert!(!signature(gpg, Some(SignatureStatus::Bad)).is_signed(false));
+		assert!(!signature(gpg, Some(SignatureStatus::RevokedKey)).is_signed(false));
+
+		// Unverifiable signatures only count when verification isn't required
+		assert!(signature(gpg, Some(SignatureStatus::MissingKey)).is_signed(false));
+		assert!(!signature(gpg, Some(SignatureStatus::MissingKey)).is_signed(true));
+		let ssh = Some(SignatureKind::Ssh);
+		assert!(signature(ssh, Some(SignatureStatus::Unverifiable)).is_signed(false));
+		assert!(!signature(ssh, Some(SignatureStatus::Unverifiable)).is_signed(true));
+		assert!(signature(ssh, None).is_signed(false));
+		assert!(!signature(ssh, None).is_signed(true));
+	}
+
+	#[test]
+	fn test_concerns() {
+		assert_eq!(
+			signature(Some(SignatureKind::Ssh), Some(SignatureStatus::Bad)).concern(),
+			Some("Commit abc123 has a bad SSH signature from ABCDEF0123456789".to_owned())
+		);
+		assert_eq!(
+			signature(Some(SignatureKind::Gpg), Some(SignatureStatus::Good)).concern(),
+			None
+		);
+	}
+}
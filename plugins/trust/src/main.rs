@@ -0,0 +1,426 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Plugin for querying how many recent commits were made by trusted contributors
+//!
+//! A contributor is trusted if they made enough commits within the trust window, and a commit is
+//! trusted if both its author and committer are trusted contributors. Contributors are identified
+//! by the canonical IDs from `mitre/git/contributor_identities`, so the same person committing
+//! under several names or e-mail addresses is only counted once.
+
+use clap::Parser;
+use hipcheck_sdk::{
+	prelude::*,
+	types::{LocalGitRepo, Target},
+};
+use jiff::{Span, Zoned};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{HashMap, HashSet},
+	result::Result as StdResult,
+	sync::OnceLock,
+};
+
+/// The number of commit hashes to send in each batched query to the git plugin, chosen to keep
+/// each request well under the gRPC message size limit.
+const HASH_CHUNK_LEN: usize = 10_000;
+
+#[derive(Deserialize)]
+struct Config {
+	#[serde(rename = "value-threshold")]
+	value_threshold: Option<u64>,
+	#[serde(rename = "trust-month-count-threshold")]
+	trust_month_count_threshold: Option<u32>,
+	#[serde(rename = "percent-threshold")]
+	percent_threshold: Option<f64>,
+}
+
+impl Config {
+	/// The number of commits in the trust window a contributor needs to be trusted.
+	fn value_threshold(&self) -> u64 {
+		self.value_threshold.unwrap_or(3)
+	}
+
+	/// The number of months back from today the trust window covers.
+	fn trust_months(&self) -> u32 {
+		self.trust_month_count_threshold.unwrap_or(3)
+	}
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// A locally stored git repo, with optional additional details
+/// The details will vary based on the query (e.g. a date, a committer e-mail address, a commit hash)
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DetailedGitRepo {
+	/// The local repo
+	local: LocalGitRepo,
+
+	/// Optional additional information for the query
+	pub details: Option<String>,
+}
+
+/// A locally stored git repo, with a list of additional details
+/// The details will vary based on the query (e.g. a date, a committer e-mail address, a commit hash)
+///
+/// This struct exists for using the temproary "batch" queries until proper batching is implemented
+/// TODO: Remove this struct once batching works
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct BatchGitRepo {
+	/// The local repo
+	local: LocalGitRepo,
+
+	/// Optional additional information for the query
+	pub details: Vec<String>,
+}
+
+/// The fields of a commit from `mitre/git` this plugin needs.
+#[derive(Debug, Clone, Deserialize)]
+struct Commit {
+	hash: String,
+}
+
+/// Authors or committers of a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+struct Contributor {
+	name: String,
+	email: String,
+}
+
+/// Temporary data structure for looking up the contributors of a commit
+#[derive(Debug, Clone, Deserialize)]
+struct CommitContributorView {
+	commit: Commit,
+	author: Contributor,
+	committer: Contributor,
+}
+
+/// A single person who may have contributed under several names or e-mail addresses.
+#[derive(Debug, Clone, Deserialize)]
+struct ContributorIdentity {
+	id: String,
+	contributors: Vec<Contributor>,
+}
+
+/// Whether a contributor is trusted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct ContributorTrust {
+	/// The canonical ID of the contributor.
+	pub id: String,
+	/// The number of commits the contributor authored or committed in the trust window.
+	pub recent_commits: u64,
+	pub trusted: bool,
+}
+
+/// Whether a commit in the trust window was made by trusted contributors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct CommitTrust {
+	pub hash: String,
+	/// The canonical ID of the commit's author.
+	pub author: String,
+	/// The canonical ID of the commit's committer.
+	pub committer: String,
+	pub trusted: bool,
+}
+
+/// The trust of every contributor, and of every commit in the trust window.
+#[derive(Debug)]
+struct TrustScores {
+	contributors: Vec<ContributorTrust>,
+	commits: Vec<CommitTrust>,
+}
+
+fn get_config() -> Result<&'static Config> {
+	CONFIG.get().ok_or_else(|| {
+		log::error!("tried to access config before set by Hipcheck core!");
+		Error::UnspecifiedQueryState
+	})
+}
+
+/// Score the trust of contributors and commits.
+///
+/// `recent` holds the commits in the trust window, and `identities` every contributor to the
+/// repository.
+fn score(
+	recent: &[CommitContributorView],
+	identities: &[ContributorIdentity],
+	value_threshold: u64,
+) -> Result<TrustScores> {
+	let id_of: HashMap<&Contributor, &str> = identities
+		.iter()
+		.flat_map(|i| i.contributors.iter().map(|c| (c, i.id.as_str())))
+		.collect();
+
+	let lookup = |contributor: &Contributor| {
+		id_of.get(contributor).copied().ok_or_else(|| {
+			log::error!("contributor {} has no identity", contributor.email);
+			Error::UnspecifiedQueryState
+		})
+	};
+
+	// Count each commit once per person, even if they both authored and committed it.
+	let mut recent_commits: HashMap<&str, u64> = HashMap::new();
+	let mut commit_ids = Vec::with_capacity(recent.len());
+	for view in recent {
+		let author = lookup(&view.author)?;
+		let committer = lookup(&view.committer)?;
+		for id in HashSet::from([author, committer]) {
+			*recent_commits.entry(id).or_default() += 1;
+		}
+		commit_ids.push((view.commit.hash.as_str(), author, committer));
+	}
+
+	let is_trusted =
+		|id: &str| recent_commits.get(id).copied().unwrap_or_default() >= value_threshold;
+
+	let contributors = identities
+		.iter()
+		.map(|i| ContributorTrust {
+			id: i.id.clone(),
+			recent_commits: recent_commits.get(i.id.as_str()).copied().unwrap_or_default(),
+			trusted: is_trusted(&i.id),
+		})
+		.collect();
+
+	let commits = commit_ids
+		.into_iter()
+		.map(|(hash, author, committer)| CommitTrust {
+			hash: hash.to_owned(),
+			author: author.to_owned(),
+			committer: committer.to_owned(),
+			trusted: is_trusted(author) && is_trusted(committer),
+		})
+		.collect();
+
+	Ok(TrustScores {
+		contributors,
+		commits,
+	})
+}
+
+/// Get the trust scores for a repo from the data in `mitre/git`.
+async fn get_trust_scores(engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<TrustScores> {
+	let config = get_config()?;
+
+	let window_start = Zoned::now()
+		.checked_sub(Span::new().months(i64::from(config.trust_months())))
+		.map_err(|e| {
+			log::error!("failed to compute start of trust window: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
+
+	let detailed_repo = DetailedGitRepo {
+		local: repo.clone(),
+		details: Some(window_start.timestamp().to_string()),
+	};
+	let commits_value = engine
+		.query("mitre/git/commits_from_date", detailed_repo)
+		.await
+		.map_err(|e| {
+			log::error!("failed to get commits in trust window: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
+	let commits: Vec<Commit> = serde_json::from_value(commits_value)
+		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
+
+	let hashes: Vec<String> = commits.into_iter().map(|c| c.hash).collect();
+	let mut recent: Vec<CommitContributorView> = Vec::with_capacity(hashes.len());
+	for chunk in hashes.chunks(HASH_CHUNK_LEN) {
+		let batch_repo = BatchGitRepo {
+			local: repo.clone(),
+			details: chunk.to_vec(),
+		};
+		let views_value = engine
+			.query("mitre/git/batch_contributors_for_commit", batch_repo)
+			.await
+			.map_err(|e| {
+				log::error!("failed to get contributors for commits: {}", e);
+				Error::UnspecifiedQueryState
+			})?;
+		let views: Vec<CommitContributorView> = serde_json::from_value(views_value)
+			.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
+		recent.extend(views);
+	}
+
+	let identities_value = engine
+		.query("mitre/git/contributor_identities", repo)
+		.await
+		.map_err(|e| {
+			log::error!("failed to get contributor identities: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
+	let identities: Vec<ContributorIdentity> = serde_json::from_value(identities_value)
+		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
+
+	score(&recent, &identities, config.value_threshold())
+}
+
+/// Returns whether each contributor to the repo is trusted
+#[query]
+async fn contributor_trust(
+	engine: &mut PluginEngine,
+	repo: LocalGitRepo,
+) -> Result<Vec<ContributorTrust>> {
+	Ok(get_trust_scores(engine, repo).await?.contributors)
+}
+
+/// Returns whether each commit in the trust window was made by trusted contributors
+#[query]
+async fn commit_trust(engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<CommitTrust>> {
+	Ok(get_trust_scores(engine, repo).await?.commits)
+}
+
+/// Returns whether each commit in the trust window was made by trusted contributors, as booleans
+#[query(default)]
+async fn trust(engine: &mut PluginEngine, target: Target) -> Result<Vec<bool>> {
+	log::debug!("running trust query");
+
+	let scores = get_trust_scores(engine, target.local).await?;
+
+	for contributor in scores.contributors.iter().filter(|c| !c.trusted) {
+		let commits = scores
+			.commits
+			.iter()
+			.filter(|c| c.author == contributor.id || c.committer == contributor.id)
+			.count();
+		if commits > 0 {
+			engine.record_concern(format!(
+				"Contributor {} is not trusted but made {} recent commit(s)",
+				contributor.id, commits
+			));
+		}
+	}
+
+	log::info!("completed trust query");
+
+	Ok(scores.commits.into_iter().map(|c| c.trusted).collect())
+}
+
+#[derive(Clone, Debug)]
+struct TrustPlugin;
+
+impl Plugin for TrustPlugin {
+	const PUBLISHER: &'static str = "mitre";
+
+	const NAME: &'static str = "trust";
+
+	fn set_config(&self, config: Value) -> StdResult<(), ConfigError> {
+		let conf =
+			serde_json::from_value::<Config>(config).map_err(|e| ConfigError::Unspecified {
+				message: e.to_string(),
+			})?;
+
+		CONFIG.set(conf).map_err(|_e| ConfigError::Unspecified {
+			message: "config was already set".to_owned(),
+		})
+	}
+
+	fn default_policy_expr(&self) -> Result<String> {
+		let conf = get_config()?;
+
+		let threshold = conf.percent_threshold.unwrap_or(0.3);
+
+		Ok(format!(
+			"(lte (divz (count (filter (eq #f) $)) (count $)) {})",
+			threshold
+		))
+	}
+
+	fn explain_default_query(&self) -> Result<Option<String>> {
+		Ok(Some(
+			"Whether each recent commit was made by trusted contributors".to_string(),
+		))
+	}
+
+	queries! { contributor_trust, commit_trust, trust }
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+	#[arg(long)]
+	port: u16,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+	let args = Args::try_parse().unwrap();
+	PluginServer::register(TrustPlugin).listen(args.port).await
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn contributor(name: &str, email: &str) -> Contributor {
+		Contributor {
+			name: name.to_owned(),
+			email: email.to_owned(),
+		}
+	}
+
+	fn view(hash: &str, author: &Contributor, committer: &Contributor) -> CommitContributorView {
+		CommitContributorView {
+			commit: Commit {
+				hash: hash.to_owned(),
+			},
+			author: author.clone(),
+			committer: committer.clone(),
+		}
+	}
+
+	#[test]
+	fn test_score() {
+		let alice_work = contributor("Alice", "alice@work.example");
+		let alice_home = contributor("Alice", "alice@home.example");
+		let bob = contributor("Bob", "bob@example.com");
+		let carol = contributor("Carol", "carol@example.com");
+
+		let identities = vec![
+			ContributorIdentity {
+				id: "alice@home.example".to_owned(),
+				contributors: vec![alice_home.clone(), alice_work.clone()],
+			},
+			ContributorIdentity {
+				id: "bob@example.com".to_owned(),
+				contributors: vec![bob.clone()],
+			},
+			ContributorIdentity {
+				id: "carol@example.com".to_owned(),
+				contributors: vec![carol.clone()],
+			},
+		];
+
+		// Alice's commits under both addresses count towards the same identity.
+		let recent = vec![
+			view("1", &alice_work, &alice_work),
+			view("2", &alice_home, &alice_home),
+			view("3", &bob, &alice_work),
+		];
+
+		let scores = score(&recent, &identities, 2).unwrap();
+
+		let trusted: Vec<(&str, u64, bool)> = scores
+			.contributors
+			.iter()
+			.map(|c| (c.id.as_str(), c.recent_commits, c.trusted))
+			.collect();
+		assert_eq!(
+			trusted,
+			vec![
+				("alice@home.example", 3, true),
+				("bob@example.com", 1, false),
+				("carol@example.com", 0, false),
+			]
+		);
+
+		let commits: Vec<bool> = scores.commits.iter().map(|c| c.trusted).collect();
+		assert_eq!(commits, vec![true, true, false]);
+	}
+
+	#[test]
+	fn test_score_unknown_contributor() {
+		let alice = contributor("Alice", "alice@example.com");
+		let recent = vec![view("1", &alice, &alice)];
+		assert!(score(&recent, &[], 1).is_err());
+	}
+}
@@ -1,7 +1,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-	types::{Homoglyphs, KeyboardLayout, NpmDependencies, Typo},
+	manifests::{Dependencies, Ecosystem},
+	types::{Homoglyphs, KeyboardLayout, Typo},
 	util::fs as file,
 };
 use anyhow::{Context as _, Result};
@@ -13,9 +14,19 @@ pub struct TypoFile {
 	languages: Languages,
 }
 
+/// The popular package names of each ecosystem, keyed by its language.
+///
+/// Only `javascript` is required, so typo files from before the other
+/// ecosystems were supported still load.
 #[derive(Debug, Deserialize)]
 struct Languages {
 	javascript: Vec<String>,
+	#[serde(default)]
+	python: Vec<String>,
+	#[serde(default)]
+	rust: Vec<String>,
+	#[serde(default)]
+	java: Vec<String>,
 }
 
 impl TypoFile {
@@ -25,6 +36,16 @@ impl TypoFile {
 
 		Ok(typo_file)
 	}
+
+	/// Get the popular package names of an ecosystem.
+	fn names(&self, ecosystem: Ecosystem) -> &[String] {
+		match ecosystem {
+			Ecosystem::Npm => &self.languages.javascript,
+			Ecosystem::PyPI => &self.languages.python,
+			Ecosystem::Crates => &self.languages.rust,
+			Ecosystem::Maven => &self.languages.java,
+		}
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -78,14 +99,14 @@ impl<'t> NameFuzzer<'t> {
 	}
 }
 
-pub(crate) fn typos_for_javascript(
-	typo_file: &TypoFile,
-	dependencies: NpmDependencies,
-) -> Result<Vec<String>> {
+/// Get the dependencies whose names may be typos of popular package names in
+/// their ecosystem.
+pub(crate) fn typos_for(typo_file: &TypoFile, dependencies: &Dependencies) -> Vec<String> {
 	let mut typos = Vec::new();
 
-	for legit_name in &typo_file.languages.javascript {
-		let fuzzer = NameFuzzer::new(legit_name);
+	for legit_name in typo_file.names(dependencies.ecosystem) {
+		let legit_name = dependencies.ecosystem.normalize(legit_name);
+		let fuzzer = NameFuzzer::new(&legit_name);
 
 		// Add a dependency name to the list of typos if the list of possible typos for that name is non-empty
 		for dependency in &dependencies.deps {
@@ -95,7 +116,7 @@ pub(crate) fn typos_for_javascript(
 		}
 	}
 
-	Ok(typos)
+	typos
 }
 
 #[inline]
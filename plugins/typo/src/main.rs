@@ -1,21 +1,27 @@
 // SPDX-License-Identifier: Apache-2.0
 
 //! Plugin for querying typos were found in the repository's package dependencies
-//! Currently only NPM dependencies for JavaScript repositories are supported
+//! NPM, PyPI, crates.io, and Maven dependencies are supported
 
 mod languages;
+mod manifests;
 mod types;
 mod util;
 
 use crate::{
 	languages::TypoFile,
+	manifests::{Dependencies, Ecosystem},
 	types::{Lang, NpmDependencies},
 };
 use anyhow::{anyhow, Context as _};
 use clap::Parser;
 use hipcheck_sdk::{prelude::*, types::Target};
 use serde::Deserialize;
-use std::{path::PathBuf, result::Result as StdResult, sync::OnceLock};
+use std::{
+	path::{Path, PathBuf},
+	result::Result as StdResult,
+	sync::OnceLock,
+};
 
 pub static TYPOFILE: OnceLock<TypoFile> = OnceLock::new();
 
@@ -72,31 +78,48 @@ async fn typo(engine: &mut PluginEngine, value: Target) -> Result<Vec<bool>> {
 		.get()
 		.ok_or_else(|| anyhow!("could not find typo file"))?;
 
-	// Get the repo's dependencies
-	let value = engine
-		.query("mitre/npm/dependencies", value.local)
-		.await
-		.context("failed to get dependencies")?;
-
-	let dependencies: NpmDependencies =
-		serde_json::from_value(value).map_err(Error::InvalidJsonInQueryOutput)?;
-
-	// Get the dependencies with identified typos
-	let typo_deps = match dependencies.language {
-		Lang::JavaScript => languages::typos_for_javascript(typo_file, dependencies.clone())?,
-		Lang::Unknown => Err(anyhow!("failed to identify a known language"))?,
-	};
-
-	// Generate a boolean list of depedencies with and without typos
-	let typos = dependencies
-		.deps
-		.iter()
-		.map(|d| typo_deps.contains(d))
-		.collect();
-
-	// Report each dependency typo as a concern
-	for concern in typo_deps {
-		engine.record_concern(concern);
+	// Get the repo's dependencies in each ecosystem it has a manifest for
+	let repo = Path::new(&value.local.path);
+	let mut ecosystems = manifests::detect_dependencies(repo).map_err(|e| {
+		log::error!("failed to get dependencies: {}", e);
+		Error::UnspecifiedQueryState
+	})?;
+
+	// npm dependencies come from the `mitre/npm` plugin, which also reports
+	// when no known language could be found
+	if manifests::is_javascript(repo) || ecosystems.is_empty() {
+		let value = engine
+			.query("mitre/npm/dependencies", value.local)
+			.await
+			.context("failed to get dependencies")?;
+
+		let dependencies: NpmDependencies =
+			serde_json::from_value(value).map_err(Error::InvalidJsonInQueryOutput)?;
+
+		match dependencies.language {
+			Lang::JavaScript => ecosystems.insert(
+				0,
+				Dependencies {
+					ecosystem: Ecosystem::Npm,
+					deps: dependencies.deps,
+				},
+			),
+			Lang::Unknown => Err(anyhow!("failed to identify a known language"))?,
+		}
+	}
+
+	let mut typos = Vec::new();
+	for dependencies in &ecosystems {
+		// Get the dependencies with identified typos
+		let typo_deps = languages::typos_for(typo_file, dependencies);
+
+		// Generate a boolean list of depedencies with and without typos
+		typos.extend(dependencies.deps.iter().map(|d| typo_deps.contains(d)));
+
+		// Report each dependency typo as a concern
+		for concern in typo_deps {
+			engine.record_concern(concern);
+		}
 	}
 
 	log::info!("completed typo query");
@@ -144,12 +167,12 @@ impl Plugin for TypoPlugin {
 
 	fn explain_default_query(&self) -> Result<Option<String>> {
 		Ok(Some(
-			"Returns whether each of the repository's package dependencies has a typo in its name"
+			"Returns whether each of the repository's npm, PyPI, crates.io, and Maven dependencies has a typo in its name"
 				.to_string(),
 		))
 	}
 
-	queries! {}
+	queries! { typo }
 }
 
 #[derive(Parser, Debug)]
@@ -178,6 +201,8 @@ mod test {
 		LocalGitRepo {
 			path: "/home/users/me/.cache/hipcheck/clones/github/foo/bar/".to_string(),
 			git_ref: "main".to_string(),
+			subdir: None,
+			base: None,
 		}
 	}
 
@@ -223,7 +248,7 @@ mod test {
 		assert_eq!(num_typos, 2);
 
 		let concerns = engine.get_concerns();
-		assert!(concerns.contains(&"chakl".to_string()));
-		assert!(concerns.contains(&"reacct".to_string()));
+		assert!(concerns.contains(&Concern::new("chakl")));
+		assert!(concerns.contains(&Concern::new("reacct")));
 	}
 }
@@ -0,0 +1,338 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Parsers for the dependency manifests of the ecosystems besides npm, whose
+//! dependencies come from the `mitre/npm` plugin instead.
+
+use crate::util::fs as file;
+use anyhow::{Context as _, Result};
+use pathbuf::pathbuf;
+use std::{collections::BTreeSet, path::Path};
+use toml::{Table, Value};
+
+/// A package ecosystem whose dependency names can be checked for typos.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Ecosystem {
+	Npm,
+	PyPI,
+	Crates,
+	Maven,
+}
+
+impl Ecosystem {
+	/// Put a package name in the form the ecosystem's registry compares names in.
+	pub fn normalize(&self, name: &str) -> String {
+		match self {
+			Ecosystem::Npm | Ecosystem::Maven => name.to_owned(),
+			// PyPI treats runs of '-', '_', and '.' alike, ignoring case
+			Ecosystem::PyPI => name
+				.to_lowercase()
+				.split(['-', '_', '.'])
+				.filter(|part| !part.is_empty())
+				.collect::<Vec<_>>()
+				.join("-"),
+			// crates.io treats '-' and '_' alike, ignoring case
+			Ecosystem::Crates => name.to_lowercase().replace('_', "-"),
+		}
+	}
+}
+
+/// The direct dependencies of a repo in one ecosystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependencies {
+	pub ecosystem: Ecosystem,
+	pub deps: Vec<String>,
+}
+
+impl Dependencies {
+	fn new(ecosystem: Ecosystem, names: BTreeSet<String>) -> Option<Dependencies> {
+		if names.is_empty() {
+			return None;
+		}
+
+		let deps = names
+			.iter()
+			.map(|name| ecosystem.normalize(name))
+			.collect::<BTreeSet<_>>()
+			.into_iter()
+			.collect();
+
+		Some(Dependencies { ecosystem, deps })
+	}
+}
+
+/// Check if the repo is a JavaScript project, with a `package.json` file.
+pub fn is_javascript(repo: &Path) -> bool {
+	pathbuf![repo, "package.json"].exists()
+}
+
+/// Get the dependencies of the repo in each ecosystem besides npm it has a
+/// manifest for at its root.
+pub fn detect_dependencies(repo: &Path) -> Result<Vec<Dependencies>> {
+	let python = {
+		let mut names = BTreeSet::new();
+		if let Some(requirements) = read_manifest(repo, "requirements.txt")? {
+			names.extend(requirements_txt(&requirements));
+		}
+		if let Some(pyproject) = read_manifest(repo, "pyproject.toml")? {
+			names.extend(pyproject_toml(&pyproject).context("failed to parse pyproject.toml")?);
+		}
+		Dependencies::new(Ecosystem::PyPI, names)
+	};
+
+	let rust = match read_manifest(repo, "Cargo.toml")? {
+		Some(manifest) => Dependencies::new(
+			Ecosystem::Crates,
+			cargo_toml(&manifest).context("failed to parse Cargo.toml")?,
+		),
+		None => None,
+	};
+
+	let java = match read_manifest(repo, "pom.xml")? {
+		Some(pom) => Dependencies::new(Ecosystem::Maven, pom_xml(&pom)),
+		None => None,
+	};
+
+	Ok([python, rust, java].into_iter().flatten().collect())
+}
+
+/// Read a manifest at the root of the repo, if it has one.
+fn read_manifest(repo: &Path, name: &str) -> Result<Option<String>> {
+	let path = pathbuf![repo, name];
+	if !path.exists() {
+		return Ok(None);
+	}
+	file::read_string(&path).map(Some)
+}
+
+/// Get the package names from a pip requirements file.
+fn requirements_txt(contents: &str) -> BTreeSet<String> {
+	contents.lines().filter_map(requirement_name).collect()
+}
+
+/// Get the package name from a requirement like `requests>=2.0; python_version > "3.8"`.
+///
+/// Options like `-r other.txt`, comments, and bare URLs have no name.
+fn requirement_name(requirement: &str) -> Option<String> {
+	let requirement = requirement.split('#').next()?.trim();
+	if requirement.starts_with('-') {
+		return None;
+	}
+
+	let end = requirement
+		.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+		.unwrap_or(requirement.len());
+	let (name, rest) = requirement.split_at(end);
+	if name.is_empty() || rest.starts_with(['+', ':']) {
+		return None;
+	}
+
+	Some(name.to_owned())
+}
+
+/// Get the package names from the PEP 621 and Poetry dependencies of a `pyproject.toml`.
+fn pyproject_toml(contents: &str) -> Result<BTreeSet<String>> {
+	let manifest: Table = toml::from_str(contents)?;
+	let mut names = BTreeSet::new();
+
+	if let Some(project) = manifest.get("project") {
+		let optional = project
+			.get("optional-dependencies")
+			.and_then(Value::as_table)
+			.into_iter()
+			.flat_map(|groups| groups.values());
+		let requirements = project
+			.get("dependencies")
+			.into_iter()
+			.chain(optional)
+			.filter_map(Value::as_array)
+			.flatten()
+			.filter_map(Value::as_str);
+		names.extend(requirements.filter_map(requirement_name));
+	}
+
+	if let Some(poetry) = manifest.get("tool").and_then(|tool| tool.get("poetry")) {
+		let groups = poetry
+			.get("group")
+			.and_then(Value::as_table)
+			.into_iter()
+			.flat_map(|groups| groups.values())
+			.filter_map(|group| group.get("dependencies"));
+		let tables = poetry
+			.get("dependencies")
+			.into_iter()
+			.chain(poetry.get("dev-dependencies"))
+			.chain(groups)
+			.filter_map(Value::as_table);
+		// Poetry lists the Python version it needs alongside the packages
+		names.extend(
+			tables
+				.flat_map(|table| table.keys())
+				.filter(|name| *name != "python")
+				.cloned(),
+		);
+	}
+
+	Ok(names)
+}
+
+/// Get the crate names from the dependency tables of a `Cargo.toml`.
+fn cargo_toml(contents: &str) -> Result<BTreeSet<String>> {
+	let manifest: Table = toml::from_str(contents)?;
+	let kinds = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+	let platforms = manifest
+		.get("target")
+		.and_then(Value::as_table)
+		.into_iter()
+		.flat_map(|targets| targets.values());
+	let workspace = manifest.get("workspace");
+	let tables = kinds
+		.iter()
+		.flat_map(|kind| {
+			let platform_tables = platforms.clone().filter_map(move |target| target.get(kind));
+			manifest
+				.get(*kind)
+				.into_iter()
+				.chain(platform_tables)
+		})
+		.chain(workspace.and_then(|workspace| workspace.get("dependencies")))
+		.filter_map(Value::as_table);
+
+	// A dependency may be renamed, with the crate's own name in `package`
+	let names = tables
+		.flat_map(|table| table.iter())
+		.map(|(name, dependency)| {
+			dependency
+				.get("package")
+				.and_then(Value::as_str)
+				.unwrap_or(name)
+				.to_owned()
+		})
+		.collect();
+
+	Ok(names)
+}
+
+/// Get the artifact IDs of the dependencies in a Maven POM.
+fn pom_xml(contents: &str) -> BTreeSet<String> {
+	contents
+		.split("<dependency>")
+		.skip(1)
+		.filter_map(|dependency| dependency.split("</dependency>").next())
+		.filter_map(|dependency| element_text(dependency, "artifactId"))
+		// Artifact IDs set from properties can't be checked
+		.filter(|artifact| !artifact.contains("${"))
+		.map(ToOwned::to_owned)
+		.collect()
+}
+
+/// Get the text inside the first element with a name in some XML.
+fn element_text<'xml>(xml: &'xml str, name: &str) -> Option<&'xml str> {
+	let open = format!("<{}>", name);
+	let start = xml.find(&open)? + open.len();
+	let end = xml[start..].find("</")?;
+	Some(xml[start..start + end].trim())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn set(names: &[&str]) -> BTreeSet<String> {
+		names.iter().map(|name| name.to_string()).collect()
+	}
+
+	#[test]
+	fn test_requirements_txt() {
+		let requirements = r#"
+			# Web
+			requests>=2.31.0
+			Flask[async]==3.0.0
+			-r dev-requirements.txt
+			numpy ; python_version >= "3.9"
+			django @ https://example.com/django.tar.gz
+			git+https://github.com/foo/bar.git
+		"#;
+		assert_eq!(
+			requirements_txt(requirements),
+			set(&["requests", "Flask", "numpy", "django"])
+		);
+	}
+
+	#[test]
+	fn test_pyproject_toml() {
+		let pyproject = r#"
+			[project]
+			dependencies = ["httpx>=0.27", "rich"]
+
+			[project.optional-dependencies]
+			test = ["pytest"]
+
+			[tool.poetry.dependencies]
+			python = "^3.10"
+			pandas = "^2.0"
+
+			[tool.poetry.group.dev.dependencies]
+			black = "*"
+		"#;
+		assert_eq!(
+			pyproject_toml(pyproject).unwrap(),
+			set(&["httpx", "rich", "pytest", "pandas", "black"])
+		);
+	}
+
+	#[test]
+	fn test_cargo_toml() {
+		let manifest = r#"
+			[dependencies]
+			serde = { version = "1", features = ["derive"] }
+			json = { package = "serde_json", version = "1" }
+
+			[dev-dependencies]
+			tokio = "1"
+
+			[target.'cfg(windows)'.dependencies]
+			winapi = "0.3"
+
+			[workspace.dependencies]
+			anyhow = "1"
+		"#;
+		assert_eq!(
+			cargo_toml(manifest).unwrap(),
+			set(&["serde", "serde_json", "tokio", "winapi", "anyhow"])
+		);
+	}
+
+	#[test]
+	fn test_pom_xml() {
+		let pom = r#"
+			<project>
+				<artifactId>my-app</artifactId>
+				<dependencies>
+					<dependency>
+						<groupId>com.google.guava</groupId>
+						<artifactId>guava</artifactId>
+					</dependency>
+					<dependency>
+						<groupId>junit</groupId>
+						<artifactId> junit </artifactId>
+						<scope>test</scope>
+					</dependency>
+					<dependency>
+						<groupId>org.example</groupId>
+						<artifactId>${example.artifact}</artifactId>
+					</dependency>
+				</dependencies>
+			</project>
+		"#;
+		assert_eq!(pom_xml(pom), set(&["guava", "junit"]));
+	}
+
+	#[test]
+	fn test_normalize() {
+		assert_eq!(Ecosystem::PyPI.normalize("Zope.Interface"), "zope-interface");
+		assert_eq!(Ecosystem::PyPI.normalize("typing__extensions"), "typing-extensions");
+		assert_eq!(Ecosystem::Crates.normalize("serde_json"), "serde-json");
+		assert_eq!(Ecosystem::Npm.normalize("@types/node"), "@types/node");
+	}
+}
@@ -0,0 +1,318 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Fingerprints of known open source projects, and matching them against the
+//! directories of a source tree.
+
+use anyhow::{anyhow, Context as _, Result};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::HashSet,
+	fs,
+	path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+/// The fraction of a project's fingerprint files a directory must contain to
+/// be considered a copy of that project, if the corpus doesn't say otherwise.
+const DEFAULT_MIN_MATCH: f64 = 0.8;
+
+/// The corpus of known projects, as read from the `Vendored.toml` file.
+#[derive(Debug)]
+pub struct Corpus {
+	projects: Vec<Project>,
+}
+
+/// A known project which may be vendored into other projects.
+#[derive(Debug)]
+struct Project {
+	name: String,
+	/// The names of files which together identify a copy of the project.
+	files: HashSet<String>,
+	/// The fraction of `files` a directory must contain to match.
+	min_match: f64,
+	/// How to find the version of a copy of the project.
+	version: Option<VersionMarker>,
+	/// The newest release of the project.
+	latest: Option<String>,
+}
+
+/// A file in a copy of a project which states the project's version.
+#[derive(Debug)]
+struct VersionMarker {
+	file: String,
+	/// Matches the version in the file, as its first capture group.
+	pattern: Regex,
+}
+
+/// A copy of a known project found in the target tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct VendoredCopy {
+	/// The name of the project the copy is of.
+	pub project: String,
+	/// The directory holding the copy, relative to the root of the tree.
+	pub path: PathBuf,
+	/// The version of the copy, if it could be found.
+	pub version: Option<String>,
+	/// Whether the copy is older than the newest release of the project, or its
+	/// version couldn't be found.
+	pub stale: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct CorpusFile {
+	projects: Vec<RawProject>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawProject {
+	name: String,
+	files: Vec<String>,
+	min_match: Option<f64>,
+	version: Option<RawVersionMarker>,
+	latest: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawVersionMarker {
+	file: String,
+	pattern: String,
+}
+
+impl TryFrom<RawProject> for Project {
+	type Error = anyhow::Error;
+
+	fn try_from(raw: RawProject) -> Result<Project> {
+		if raw.files.is_empty() {
+			return Err(anyhow!("project '{}' has no fingerprint files", raw.name));
+		}
+
+		let min_match = raw.min_match.unwrap_or(DEFAULT_MIN_MATCH);
+		if min_match <= 0.0 || min_match > 1.0 {
+			return Err(anyhow!(
+				"project '{}' has min-match {}, which must be greater than 0 and at most 1",
+				raw.name,
+				min_match
+			));
+		}
+
+		let version = raw
+			.version
+			.map(|v| {
+				let pattern = Regex::new(&v.pattern).with_context(|| {
+					format!("invalid version pattern for project '{}'", raw.name)
+				})?;
+				Ok::<_, anyhow::Error>(VersionMarker {
+					file: v.file,
+					pattern,
+				})
+			})
+			.transpose()?;
+
+		Ok(Project {
+			files: raw.files.into_iter().map(|f| f.to_lowercase()).collect(),
+			name: raw.name,
+			min_match,
+			version,
+			latest: raw.latest,
+		})
+	}
+}
+
+impl Corpus {
+	/// Constructs a new `Corpus` from the `Vendored.toml` file.
+	pub fn load(path: &Path) -> Result<Corpus> {
+		let contents = fs::read_to_string(path)
+			.with_context(|| format!("failed to read corpus file '{}'", path.display()))?;
+		Corpus::parse(&contents)
+			.with_context(|| format!("failed to parse corpus file '{}'", path.display()))
+	}
+
+	fn parse(contents: &str) -> Result<Corpus> {
+		let file: CorpusFile = toml::from_str(contents)?;
+		let projects = file
+			.projects
+			.into_iter()
+			.map(Project::try_from)
+			.collect::<Result<Vec<_>>>()?;
+		Ok(Corpus { projects })
+	}
+
+	/// Find copies of known projects in the tree rooted at `root`.
+	///
+	/// Once a directory matches a project, its subdirectories aren't checked
+	/// for that project again.
+	pub fn find_copies(&self, root: &Path) -> Result<Vec<VendoredCopy>> {
+		let mut copies: Vec<VendoredCopy> = Vec::new();
+
+		let dirs = WalkDir::new(root)
+			.sort_by_file_name()
+			.into_iter()
+			.filter_entry(|e| e.file_name() != ".git")
+			.filter(|e| e.as_ref().map_or(true, |e| e.file_type().is_dir()));
+
+		for entry in dirs {
+			let dir = entry?.into_path();
+			let rel_path = dir.strip_prefix(root).unwrap_or(&dir).to_path_buf();
+			let file_names = file_names_in(&dir)?;
+
+			for project in &self.projects {
+				let already_found = copies
+					.iter()
+					.any(|c| c.project == project.name && rel_path.starts_with(&c.path));
+
+				if !already_found && project.matches(&file_names) {
+					let version = project.find_version(&dir);
+					copies.push(VendoredCopy {
+						project: project.name.clone(),
+						stale: project.is_stale(version.as_deref()),
+						path: rel_path.clone(),
+						version,
+					});
+				}
+			}
+		}
+
+		Ok(copies)
+	}
+}
+
+impl Project {
+	/// Check if a directory's files are enough of this project's fingerprint.
+	fn matches(&self, file_names: &HashSet<String>) -> bool {
+		let found = self.files.intersection(file_names).count();
+		found as f64 / self.files.len() as f64 >= self.min_match
+	}
+
+	/// Find the version of the copy of this project in `dir`.
+	fn find_version(&self, dir: &Path) -> Option<String> {
+		let marker = self.version.as_ref()?;
+		let contents = fs::read_to_string(dir.join(&marker.file)).ok()?;
+		let captures = marker.pattern.captures(&contents)?;
+		Some(captures.get(1)?.as_str().to_owned())
+	}
+
+	/// Check if a copy with the given version is older than the newest release.
+	///
+	/// Copies whose version is unknown are considered stale, since they can't
+	/// be shown to be up to date.
+	fn is_stale(&self, version: Option<&str>) -> bool {
+		let (Some(version), Some(latest)) = (version, self.latest.as_deref()) else {
+			return self.latest.is_some();
+		};
+
+		match (parse_version(version), parse_version(latest)) {
+			(Some(version), Some(latest)) => version < latest,
+			_ => version != latest,
+		}
+	}
+}
+
+/// Parse a version, allowing the `MAJOR.MINOR` form many C projects use.
+fn parse_version(version: &str) -> Option<semver::Version> {
+	semver::Version::parse(version)
+		.or_else(|_| semver::Version::parse(&format!("{}.0", version)))
+		.ok()
+}
+
+/// Get the lowercased names of the files directly inside a directory.
+fn file_names_in(dir: &Path) -> Result<HashSet<String>> {
+	let mut names = HashSet::new();
+
+	for entry in fs::read_dir(dir)? {
+		let entry = entry?;
+		if entry.file_type()?.is_file() {
+			names.insert(entry.file_name().to_string_lossy().to_lowercase());
+		}
+	}
+
+	Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const CORPUS: &str = r#"
+		[[projects]]
+		name = "zlib"
+		files = ["zlib.h", "zconf.h", "deflate.c", "inflate.c", "adler32.c"]
+		latest = "1.3.1"
+
+		[projects.version]
+		file = "zlib.h"
+		pattern = '#define ZLIB_VERSION "([^"]+)"'
+	"#;
+
+	fn write_zlib(dir: &Path, version: &str) {
+		fs::create_dir_all(dir).unwrap();
+		for file in ["zconf.h", "deflate.c", "inflate.c", "adler32.c"] {
+			fs::write(dir.join(file), "").unwrap();
+		}
+		fs::write(
+			dir.join("zlib.h"),
+			format!("#define ZLIB_VERSION \"{}\"\n", version),
+		)
+		.unwrap();
+	}
+
+	#[test]
+	fn finds_stale_copy() {
+		let root = tempfile::tempdir().unwrap();
+		write_zlib(&root.path().join("third_party/zlib"), "1.2.11");
+
+		let copies = Corpus::parse(CORPUS)
+			.unwrap()
+			.find_copies(root.path())
+			.unwrap();
+		assert_eq!(
+			copies,
+			vec![VendoredCopy {
+				project: "zlib".to_owned(),
+				path: PathBuf::from("third_party/zlib"),
+				version: Some("1.2.11".to_owned()),
+				stale: true,
+			}]
+		);
+	}
+
+	#[test]
+	fn current_copy_is_not_stale() {
+		let root = tempfile::tempdir().unwrap();
+		write_zlib(&root.path().join("zlib"), "1.3.1");
+
+		let copies = Corpus::parse(CORPUS)
+			.unwrap()
+			.find_copies(root.path())
+			.unwrap();
+		assert_eq!(copies.len(), 1);
+		assert!(!copies[0].stale);
+	}
+
+	#[test]
+	fn partial_fingerprint_does_not_match() {
+		let root = tempfile::tempdir().unwrap();
+		let dir = root.path().join("src");
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join("zlib.h"), "").unwrap();
+		fs::write(dir.join("deflate.c"), "").unwrap();
+
+		let copies = Corpus::parse(CORPUS)
+			.unwrap()
+			.find_copies(root.path())
+			.unwrap();
+		assert!(copies.is_empty());
+	}
+
+	#[test]
+	fn version_comparison() {
+		let project = Corpus::parse(CORPUS).unwrap().projects.remove(0);
+		assert!(project.is_stale(Some("1.3")));
+		assert!(!project.is_stale(Some("1.3.1")));
+		// Not semver, so only an exact match is current.
+		assert!(project.is_stale(Some("1.3.1.1")));
+		assert!(project.is_stale(None));
+	}
+}
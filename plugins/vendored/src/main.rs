@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Plugin for detecting vendored copies of known open source projects
+
+mod corpus;
+
+use crate::corpus::{Corpus, VendoredCopy};
+use clap::Parser;
+use hipcheck_sdk::{
+	prelude::*,
+	types::{LocalGitRepo, Target},
+};
+use serde::Deserialize;
+use std::{path::PathBuf, result::Result as StdResult, sync::OnceLock};
+
+pub static CORPUS: OnceLock<Corpus> = OnceLock::new();
+
+#[derive(Deserialize)]
+struct RawConfig {
+	#[serde(rename = "vendored-file")]
+	vendored_file: Option<PathBuf>,
+	#[serde(rename = "stale-threshold")]
+	stale_threshold: Option<u64>,
+}
+
+struct Config {
+	vendored_file: PathBuf,
+	opt_threshold: Option<u64>,
+}
+
+impl TryFrom<RawConfig> for Config {
+	type Error = hipcheck_sdk::error::ConfigError;
+	fn try_from(value: RawConfig) -> StdResult<Config, Self::Error> {
+		let Some(vendored_file) = value.vendored_file else {
+			return Err(ConfigError::MissingRequiredConfig {
+				field_name: "vendored-file".to_owned(),
+				field_type: "string".to_owned(),
+				possible_values: vec![],
+			});
+		};
+		Ok(Config {
+			vendored_file,
+			opt_threshold: value.stale_threshold,
+		})
+	}
+}
+
+/// Returns every vendored copy of a known project found in the repository
+#[query]
+async fn copies(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<VendoredCopy>> {
+	let corpus = CORPUS.get().ok_or(Error::UnspecifiedQueryState)?;
+	corpus.find_copies(&repo.path).map_err(|e| {
+		log::error!("failed to search for vendored copies: {}", e);
+		Error::UnspecifiedQueryState
+	})
+}
+
+/// Returns the number of vendored copies which are out of date
+#[query(default)]
+async fn vendored(engine: &mut PluginEngine, value: Target) -> Result<usize> {
+	let copies = copies(engine, value.local).await?;
+	copies.iter().for_each(|c| {
+		let version = c.version.as_deref().unwrap_or("unknown version");
+		let state = if c.stale { "out of date" } else { "up to date" };
+		let message = format!(
+			"Found vendored copy of {} ({}, {}) at '{}'",
+			c.project,
+			version,
+			state,
+			c.path.display()
+		);
+		let severity = if c.stale {
+			Severity::Medium
+		} else {
+			Severity::Low
+		};
+		engine.record_concern(
+			Concern::new(message)
+				.with_severity(severity)
+				.with_file(c.path.to_string_lossy())
+				.with_identifier(&c.project),
+		)
+	});
+	Ok(copies.iter().filter(|c| c.stale).count())
+}
+
+#[derive(Clone, Debug, Default)]
+struct VendoredPlugin {
+	policy_conf: OnceLock<Option<u64>>,
+}
+
+impl Plugin for VendoredPlugin {
+	const PUBLISHER: &'static str = "mitre";
+	const NAME: &'static str = "vendored";
+
+	fn set_config(&self, config: Value) -> StdResult<(), ConfigError> {
+		// Deserialize and validate the config struct
+		let conf: Config = serde_json::from_value::<RawConfig>(config)
+			.map_err(|e| ConfigError::Unspecified {
+				message: e.to_string(),
+			})?
+			.try_into()?;
+
+		// Store the policy conf to be accessed only in the `default_policy_expr()` impl
+		self.policy_conf
+			.set(conf.opt_threshold)
+			.map_err(|_| ConfigError::Unspecified {
+				message: "plugin was already configured".to_string(),
+			})?;
+
+		let corpus = Corpus::load(&conf.vendored_file).map_err(|e| ConfigError::Unspecified {
+			message: format!("{:#}", e),
+		})?;
+
+		CORPUS.set(corpus).map_err(|_e| ConfigError::Unspecified {
+			message: "config was already set".to_owned(),
+		})
+	}
+
+	fn default_policy_expr(&self) -> Result<String> {
+		match self.policy_conf.get() {
+			None => Err(Error::UnspecifiedQueryState),
+			Some(policy_conf) => Ok(format!("(lte $ {})", policy_conf.unwrap_or(0))),
+		}
+	}
+
+	fn explain_default_query(&self) -> Result<Option<String>> {
+		Ok(Some(
+			"Returns number of out-of-date vendored copies of known projects in a repo".to_owned(),
+		))
+	}
+
+	queries! { copies, vendored }
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+	#[arg(long)]
+	port: u16,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+	let args = Args::try_parse().unwrap();
+	PluginServer::register(VendoredPlugin::default())
+		.listen(args.port)
+		.await
+}
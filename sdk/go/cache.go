@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: Apache-2.0
+
+package hipcheck
+
+import (
+	"bytes"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io/fs"
+	"log/slog"
+	"os"
+	"path/filepath"
+	"regexp"
+)
+
+var validHead = regexp.MustCompile(`^[A-Za-z0-9_-]+$`)
+
+// PluginCache is a persistent key-value cache for a plugin, scoped by target HEAD commit.
+//
+// Hipcheck core gives each version of a plugin its own cache directory, so a new version never
+// reads what an older one stored. Within it, entries are scoped by the HEAD commit of the target
+// they were computed for, since what's true of one commit may not be of the next. Each entry is
+// kept in its own file, along with its key, at <dir>/<head>/<hash of key>.json, as in the Rust
+// SDK.
+//
+// Keys and values may be anything encoding/json can write. A cache Hipcheck core didn't give a
+// directory for, as when the plugin is run on its own or in unit tests, keeps nothing.
+type PluginCache struct {
+	dir string
+}
+
+// NewPluginCache makes a cache keeping its entries in a directory. An empty directory makes a
+// cache which keeps nothing.
+func NewPluginCache(dir string) *PluginCache {
+	return &PluginCache{dir: dir}
+}
+
+// IsEnabled is whether the cache keeps entries.
+func (c *PluginCache) IsEnabled() bool {
+	return c.dir != ""
+}
+
+// Get reads the value cached for a key when analyzing the target at commit head into value,
+// which must be a pointer, returning whether there was one.
+//
+// Entries which can't be read into value, as when a plugin changes the type it caches for a key,
+// are treated as missing.
+func (c *PluginCache) Get(head string, key any, value any) (bool, error) {
+	canonicalKey, err := canonicalJSON(key)
+	if err != nil {
+		return false, err
+	}
+	path, err := c.entryPath(head, canonicalKey)
+	if err != nil || path == "" {
+		return false, err
+	}
+
+	contents, err := os.ReadFile(path)
+	if errors.Is(err, fs.ErrNotExist) {
+		return false, nil
+	} else if err != nil {
+		return false, fmt.Errorf("failed to access plugin cache entry '%s': %w", path, err)
+	}
+	var entry struct {
+		Key   json.RawMessage `json:"key"`
+		Value json.RawMessage `json:"value"`
+	}
+	if err := json.Unmarshal(contents, &entry); err != nil {
+		slog.Debug(fmt.Sprintf("ignoring unreadable cache entry '%s'", path))
+		return false, nil
+	}
+	// Keys whose hashes collide share a file, so only the key last stored is found
+	if storedKey, err := canonicalJSON(entry.Key); err != nil || !bytes.Equal(storedKey, canonicalKey) {
+		return false, nil
+	}
+
+	return json.Unmarshal(entry.Value, value) == nil, nil
+}
+
+// Insert caches a value for a key when analyzing the target at commit head, replacing any value
+// cached before.
+func (c *PluginCache) Insert(head string, key any, value any) error {
+	canonicalKey, err := canonicalJSON(key)
+	if err != nil {
+		return err
+	}
+	path, err := c.entryPath(head, canonicalKey)
+	if err != nil || path == "" {
+		return err
+	}
+	canonicalValue, err := canonicalJSON(value)
+	if err != nil {
+		return err
+	}
+	entry := fmt.Sprintf(`{"key":%s,"value":%s}`, canonicalKey, canonicalValue)
+
+	if err := os.MkdirAll(filepath.Dir(path), 0o755); err != nil {
+		return fmt.Errorf("failed to access plugin cache entry '%s': %w", path, err)
+	}
+	// Write to a temporary file first, so other sessions never read a partial entry
+	temp := fmt.Sprintf("%s.%d.tmp", path[:len(path)-len(".json")], os.Getpid())
+	if err := os.WriteFile(temp, []byte(entry), 0o644); err != nil {
+		return fmt.Errorf("failed to access plugin cache entry '%s': %w", path, err)
+	}
+	if err := os.Rename(temp, path); err != nil {
+		return fmt.Errorf("failed to access plugin cache entry '%s': %w", path, err)
+	}
+	return nil
+}
+
+// entryPath gets the file an entry is kept in, or "" if the cache keeps nothing.
+func (c *PluginCache) entryPath(head string, canonicalKey []byte) (string, error) {
+	if c.dir == "" {
+		return "", nil
+	}
+	// The HEAD is a directory name, so it mustn't be able to name anything outside the cache
+	if !validHead.MatchString(head) {
+		return "", fmt.Errorf("%w '%s'; expected a commit hash", ErrInvalidCacheScope, head)
+	}
+	return entryFile(c.dir, head, canonicalKey), nil
+}
+
+// entryFile gets the file an entry with a key is kept in, named after the FNV-1a hash of the
+// key's JSON.
+func entryFile(dir, head string, canonicalKey []byte) string {
+	const fnvOffsetBasis uint64 = 0xcbf29ce484222325
+	const fnvPrime uint64 = 0x100000001b3
+
+	hash := fnvOffsetBasis
+	for _, b := range canonicalKey {
+		hash = (hash ^ uint64(b)) * fnvPrime
+	}
+	return filepath.Join(dir, head, fmt.Sprintf("%016x.json", hash))
+}
+
+// canonicalJSON writes a value's JSON the way serde_json writes a Value, with sorted object keys
+// and no whitespace or HTML escaping, so keys hash the same as in the other SDKs.
+func canonicalJSON(value any) ([]byte, error) {
+	raw, err := json.Marshal(value)
+	if err != nil {
+		return nil, fmt.Errorf("invalid JSON in plugin cache entry: %w", err)
+	}
+	decoder := json.NewDecoder(bytes.NewReader(raw))
+	decoder.UseNumber()
+	var generic any
+	if err := decoder.Decode(&generic); err != nil {
+		return nil, fmt.Errorf("invalid JSON in plugin cache entry: %w", err)
+	}
+
+	var out bytes.Buffer
+	encoder := json.NewEncoder(&out)
+	encoder.SetEscapeHTML(false)
+	if err := encoder.Encode(generic); err != nil {
+		return nil, fmt.Errorf("invalid JSON in plugin cache entry: %w", err)
+	}
+	return bytes.TrimSuffix(out.Bytes(), []byte("\n")), nil
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: Apache-2.0
+
+package hipcheck
+
+import (
+	"errors"
+	"path/filepath"
+	"testing"
+)
+
+func TestEntriesAreScopedByHead(t *testing.T) {
+	cache := NewPluginCache(t.TempDir())
+	var value int
+
+	if found, err := cache.Get("abc123", "commits", &value); err != nil || found {
+		t.Fatalf("expected no entry, got %v, %v", found, err)
+	}
+	if err := cache.Insert("abc123", "commits", 42); err != nil {
+		t.Fatal(err)
+	}
+	if found, err := cache.Get("abc123", "commits", &value); err != nil || !found || value != 42 {
+		t.Fatalf("expected 42, got %v, %v, %v", value, found, err)
+	}
+	if found, err := cache.Get("def456", "commits", &value); err != nil || found {
+		t.Fatalf("expected no entry for another commit, got %v, %v", found, err)
+	}
+
+	if err := cache.Insert("abc123", "commits", 43); err != nil {
+		t.Fatal(err)
+	}
+	if found, err := cache.Get("abc123", "commits", &value); err != nil || !found || value != 43 {
+		t.Fatalf("expected 43, got %v, %v, %v", value, found, err)
+	}
+}
+
+func TestHeadsMustNotLeaveTheCache(t *testing.T) {
+	cache := NewPluginCache(t.TempDir())
+	for _, head := range []string{"", "..", "../other", "a/b"} {
+		if err := cache.Insert(head, "key", 1); !errors.Is(err, ErrInvalidCacheScope) {
+			t.Errorf("expected head %q to be rejected, got %v", head, err)
+		}
+	}
+}
+
+func TestDisabledCachesKeepNothing(t *testing.T) {
+	cache := NewPluginCache("")
+	if err := cache.Insert("abc123", "key", 1); err != nil {
+		t.Fatal(err)
+	}
+	var value int
+	if found, err := cache.Get("abc123", "key", &value); err != nil || found {
+		t.Fatalf("expected no entry, got %v, %v", found, err)
+	}
+}
+
+func TestEntryFilesMatchTheRustSDK(t *testing.T) {
+	canonicalKey, err := canonicalJSON("key")
+	if err != nil {
+		t.Fatal(err)
+	}
+	expected := filepath.Join("/cache", "abc123", "1f6b6d08a47db94a.json")
+	if actual := entryFile("/cache", "abc123", canonicalKey); actual != expected {
+		t.Fatalf("expected %s, got %s", expected, actual)
+	}
+}
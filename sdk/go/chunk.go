@@ -0,0 +1,279 @@
+// SPDX-License-Identifier: Apache-2.0
+
+package hipcheck
+
+import (
+	"unicode/utf8"
+
+	"github.com/mitre/hipcheck/sdk/go/internal/pb"
+)
+
+// Splitting query protocol messages into chunks, and putting them back together, exactly as
+// hipcheck-common's chunk module does for Hipcheck core and the Rust SDK.
+
+const (
+	// GRPCMaxSizeBytes is the max size of a single gRPC message (4 MiB), used unless a larger
+	// size is negotiated.
+	GRPCMaxSizeBytes = 1024 * 1024 * 4
+
+	// GRPCMinSizeBytes is the smallest max message size either side may negotiate (64 KiB).
+	GRPCMinSizeBytes = 1024 * 64
+
+	// grpcMessageOverhead is the room left in each message for the fields which aren't chunked.
+	grpcMessageOverhead = 1024
+)
+
+// negotiateMaxSize agrees on the max size of a query protocol message, given our own max size
+// and the max size Hipcheck core sent while configuring the plugin. Core sends zero if it
+// predates negotiation, in which case it only accepts messages of the default size.
+func negotiateMaxSize(ours int, theirs uint64) int {
+	if theirs == 0 {
+		theirs = GRPCMaxSizeBytes
+	}
+	size := ours
+	if theirs < uint64(ours) {
+		size = int(theirs)
+	}
+	return max(size, GRPCMinSizeBytes)
+}
+
+// chunkedFields gets the fields of a message which are chunked, in the order they're chunked.
+func chunkedFields(msg *pb.Query) [4]*[]string {
+	return [4]*[]string{&msg.Key, &msg.Output, &msg.Concern, &msg.Fragment}
+}
+
+func hasChunkedData(msg *pb.Query) bool {
+	for _, field := range chunkedFields(msg) {
+		if len(*field) > 0 {
+			return true
+		}
+	}
+	return false
+}
+
+// queryChunks splits a message into chunks, one chunk at a time, so a sender which sends each
+// chunk before taking the next never holds a second copy of the whole message.
+type queryChunks struct {
+	base       *pb.Query
+	maxEstSize int
+
+	// The state of every chunk but the last
+	inProgress pb.QueryState
+
+	// The state of the last chunk
+	complete pb.QueryState
+}
+
+// newQueryChunks prepares to split a message into chunks holding at most maxEstSize bytes of
+// chunked fields. The message itself is left alone.
+func newQueryChunks(msg *pb.Query, maxEstSize int) (*queryChunks, error) {
+	chunks := &queryChunks{
+		base: &pb.Query{
+			Id:            msg.Id,
+			PublisherName: msg.PublisherName,
+			PluginName:    msg.PluginName,
+			QueryName:     msg.QueryName,
+			Key:           append([]string(nil), msg.Key...),
+			Output:        append([]string(nil), msg.Output...),
+			Concern:       append([]string(nil), msg.Concern...),
+			Fragment:      append([]string(nil), msg.Fragment...),
+		},
+		maxEstSize: maxEstSize,
+	}
+	switch msg.State {
+	case pb.QueryState_QUERY_STATE_SUBMIT_IN_PROGRESS, pb.QueryState_QUERY_STATE_SUBMIT_COMPLETE:
+		chunks.inProgress = pb.QueryState_QUERY_STATE_SUBMIT_IN_PROGRESS
+		chunks.complete = pb.QueryState_QUERY_STATE_SUBMIT_COMPLETE
+	case pb.QueryState_QUERY_STATE_REPLY_IN_PROGRESS, pb.QueryState_QUERY_STATE_REPLY_COMPLETE:
+		chunks.inProgress = pb.QueryState_QUERY_STATE_REPLY_IN_PROGRESS
+		chunks.complete = pb.QueryState_QUERY_STATE_REPLY_COMPLETE
+	default:
+		return nil, ErrUnspecifiedQueryState
+	}
+	return chunks, nil
+}
+
+// prepareChunks prepares to split a message into chunks which fit a negotiated max message size.
+func prepareChunks(msg *pb.Query, maxMessageSize int) (*queryChunks, error) {
+	return newQueryChunks(msg, max(maxMessageSize-grpcMessageOverhead, 1))
+}
+
+// next takes the next chunk, or returns nil once the whole message has been taken.
+func (c *queryChunks) next() (*pb.Query, error) {
+	if !hasChunkedData(c.base) {
+		return nil, nil
+	}
+
+	remaining := c.maxEstSize
+	madeProgress := false
+	chunk := &pb.Query{
+		Id:            c.base.Id,
+		State:         c.inProgress,
+		PublisherName: c.base.PublisherName,
+		PluginName:    c.base.PluginName,
+		QueryName:     c.base.QueryName,
+	}
+
+	sources, sinks := chunkedFields(c.base), chunkedFields(chunk)
+	for i := range sources {
+		if drain(sources[i], sinks[i], &remaining, &madeProgress) {
+			chunk.Split = true
+			break
+		}
+		if remaining == 0 {
+			break
+		}
+	}
+
+	// Check whether we did anything, to avoid looping forever
+	if !madeProgress {
+		return nil, errChunking
+	}
+
+	if !hasChunkedData(c.base) {
+		chunk.State = c.complete
+	}
+	return chunk, nil
+}
+
+// chunkWithSize splits a message into all of its chunks at once.
+func chunkWithSize(msg *pb.Query, maxEstSize int) ([]*pb.Query, error) {
+	chunks, err := newQueryChunks(msg, maxEstSize)
+	if err != nil {
+		return nil, err
+	}
+	var out []*pb.Query
+	for {
+		chunk, err := chunks.next()
+		if err != nil {
+			return nil, err
+		}
+		if chunk == nil {
+			return out, nil
+		}
+		out = append(out, chunk)
+	}
+}
+
+// drain moves as many strings as fit in the remaining bytes from source to sink, splitting the
+// first which doesn't fit at the start of a character. Returns whether a string was split.
+func drain(source, sink *[]string, remaining *int, madeProgress *bool) bool {
+	for len(*source) > 0 {
+		s := (*source)[0]
+		if len(s) <= *remaining {
+			*madeProgress = true
+			*remaining -= len(s)
+			*sink = append(*sink, s)
+			*source = (*source)[1:]
+			continue
+		}
+
+		// Walk back to the start of the character the limit falls in
+		end := *remaining
+		for end > 0 && !utf8.RuneStart(s[end]) {
+			end--
+		}
+		if end == 0 {
+			return false
+		}
+		*madeProgress = true
+		*remaining -= end
+		*sink = append(*sink, s[:end])
+		(*source)[0] = s[end:]
+		return true
+	}
+	return false
+}
+
+// lastFieldWithContent finds the index of the last chunked field of a message with data in it,
+// which is the field a split string continues in.
+func lastFieldWithContent(msg *pb.Query) int {
+	fields := chunkedFields(msg)
+	for i := len(fields) - 1; i > 0; i-- {
+		if len(*fields[i]) > 0 {
+			return i
+		}
+	}
+	return 0
+}
+
+func inProgress(state pb.QueryState) bool {
+	return state == pb.QueryState_QUERY_STATE_SUBMIT_IN_PROGRESS ||
+		state == pb.QueryState_QUERY_STATE_REPLY_IN_PROGRESS
+}
+
+func isSubmit(state pb.QueryState) bool {
+	return state == pb.QueryState_QUERY_STATE_SUBMIT_IN_PROGRESS ||
+		state == pb.QueryState_QUERY_STATE_SUBMIT_COMPLETE
+}
+
+// querySynthesizer puts the chunks of a message back together, one chunk at a time.
+type querySynthesizer struct {
+	raw *pb.Query
+
+	// The field the last chunk split a string in, or -1 if it didn't
+	splitField int
+}
+
+func newQuerySynthesizer() *querySynthesizer {
+	return &querySynthesizer{splitField: -1}
+}
+
+// add adds the next chunk of a message, returning the whole message once it's complete.
+func (s *querySynthesizer) add(chunk *pb.Query) (*pb.Query, error) {
+	if chunk.State == pb.QueryState_QUERY_STATE_UNSPECIFIED {
+		// A query which failed is answered in the unspecified state, with why it failed
+		s.raw = nil
+		if chunk.Error != "" {
+			return nil, &QueryFailedError{Message: chunk.Error}
+		}
+		return nil, ErrUnspecifiedQueryState
+	}
+
+	nextSplitField := -1
+	if chunk.Split {
+		nextSplitField = lastFieldWithContent(chunk)
+	}
+
+	if s.raw == nil {
+		s.raw = &pb.Query{
+			Id:            chunk.Id,
+			State:         chunk.State,
+			PublisherName: chunk.PublisherName,
+			PluginName:    chunk.PluginName,
+			QueryName:     chunk.QueryName,
+			Key:           append([]string(nil), chunk.Key...),
+			Output:        append([]string(nil), chunk.Output...),
+			Concern:       append([]string(nil), chunk.Concern...),
+			Fragment:      append([]string(nil), chunk.Fragment...),
+		}
+	} else {
+		if isSubmit(s.raw.State) && !isSubmit(chunk.State) {
+			return nil, ErrReplyWhenExpectingRequest
+		}
+		if !isSubmit(s.raw.State) && isSubmit(chunk.State) {
+			return nil, ErrSubmitWhenExpectingReply
+		}
+
+		sources, sinks := chunkedFields(chunk), chunkedFields(s.raw)
+		for i := range sources {
+			source := *sources[i]
+			// The first string of this chunk continues the string the last chunk split
+			if i == s.splitField && len(source) > 0 {
+				sink := *sinks[i]
+				sink[len(sink)-1] += source[0]
+				source = source[1:]
+			}
+			*sinks[i] = append(*sinks[i], source...)
+		}
+		s.raw.State = chunk.State
+	}
+	s.splitField = nextSplitField
+
+	if inProgress(s.raw.State) {
+		return nil, nil
+	}
+	raw := s.raw
+	s.raw = nil
+	return raw, nil
+}
@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: Apache-2.0
+
+package hipcheck
+
+import (
+	"encoding/json"
+	"errors"
+	"os"
+	"reflect"
+	"strings"
+	"testing"
+
+	"github.com/mitre/hipcheck/sdk/go/internal/pb"
+)
+
+// The vectors Hipcheck core's own chunking is tested against
+const conformanceVectors = "../../hipcheck-common/tests/conformance/chunks.json"
+
+type vectorMessage struct {
+	State    string   `json:"state"`
+	Key      []string `json:"key"`
+	Output   []string `json:"output"`
+	Concern  []string `json:"concern"`
+	Fragment []string `json:"fragment"`
+	Split    bool     `json:"split"`
+}
+
+type vectorCase struct {
+	Name    string          `json:"name"`
+	MaxSize int             `json:"max_size"`
+	Query   vectorMessage   `json:"query"`
+	Chunks  []vectorMessage `json:"chunks"`
+	Error   bool            `json:"error"`
+}
+
+func loadVectors(t *testing.T) []vectorCase {
+	t.Helper()
+	contents, err := os.ReadFile(conformanceVectors)
+	if err != nil {
+		t.Fatal(err)
+	}
+	var vectors struct {
+		Cases []vectorCase `json:"cases"`
+	}
+	if err := json.Unmarshal(contents, &vectors); err != nil {
+		t.Fatal(err)
+	}
+	return vectors.Cases
+}
+
+func (v vectorMessage) toQuery() *pb.Query {
+	return &pb.Query{
+		Id:            1,
+		State:         pb.QueryState(pb.QueryState_value[v.State]),
+		PublisherName: "pub",
+		PluginName:    "plugin",
+		QueryName:     "q",
+		Key:           v.Key,
+		Output:        v.Output,
+		Concern:       v.Concern,
+		Fragment:      v.Fragment,
+		Split:         v.Split,
+	}
+}
+
+func fromQuery(q *pb.Query) vectorMessage {
+	// The vectors write empty fields as empty lists
+	nonNil := func(s []string) []string {
+		if s == nil {
+			return []string{}
+		}
+		return s
+	}
+	return vectorMessage{
+		State:    q.State.String(),
+		Key:      nonNil(q.Key),
+		Output:   nonNil(q.Output),
+		Concern:  nonNil(q.Concern),
+		Fragment: nonNil(q.Fragment),
+		Split:    q.Split,
+	}
+}
+
+func TestChunkingMatchesHipcheckCore(t *testing.T) {
+	for _, c := range loadVectors(t) {
+		t.Run(c.Name, func(t *testing.T) {
+			chunks, err := chunkWithSize(c.Query.toQuery(), c.MaxSize)
+			if c.Error {
+				if !errors.Is(err, errChunking) {
+					t.Fatalf("expected a chunking error, got %v", err)
+				}
+				return
+			}
+			if err != nil {
+				t.Fatal(err)
+			}
+
+			actual := make([]vectorMessage, 0, len(chunks))
+			for _, chunk := range chunks {
+				actual = append(actual, fromQuery(chunk))
+			}
+			if !reflect.DeepEqual(actual, c.Chunks) {
+				t.Fatalf("expected %+v, got %+v", c.Chunks, actual)
+			}
+		})
+	}
+}
+
+func TestChunksArePutBackTogether(t *testing.T) {
+	for _, c := range loadVectors(t) {
+		if c.Error {
+			continue
+		}
+		t.Run(c.Name, func(t *testing.T) {
+			original := c.Query.toQuery()
+			chunks, err := chunkWithSize(original, c.MaxSize)
+			if err != nil {
+				t.Fatal(err)
+			}
+
+			synth := newQuerySynthesizer()
+			var result *pb.Query
+			for i, chunk := range chunks {
+				result, err = synth.add(chunk)
+				if err != nil {
+					t.Fatal(err)
+				}
+				if (result != nil) != (i == len(chunks)-1) {
+					t.Fatalf("message completed at chunk %d of %d", i+1, len(chunks))
+				}
+			}
+			if !reflect.DeepEqual(fromQuery(result), fromQuery(original)) {
+				t.Fatalf("expected %+v, got %+v", fromQuery(original), fromQuery(result))
+			}
+		})
+	}
+}
+
+func TestChunkingLeavesTheMessageAlone(t *testing.T) {
+	original := &pb.Query{
+		State:  pb.QueryState_QUERY_STATE_REPLY_COMPLETE,
+		Output: []string{strings.Repeat("x", 100)},
+	}
+	if _, err := chunkWithSize(original, 10); err != nil {
+		t.Fatal(err)
+	}
+	if original.Output[0] != strings.Repeat("x", 100) {
+		t.Fatal("chunking changed the message")
+	}
+}
+
+func TestFailedQueriesAreReported(t *testing.T) {
+	synth := newQuerySynthesizer()
+	_, err := synth.add(&pb.Query{State: pb.QueryState_QUERY_STATE_UNSPECIFIED, Error: "no such plugin"})
+
+	var failed *QueryFailedError
+	if !errors.As(err, &failed) || failed.Message != "no such plugin" {
+		t.Fatalf("expected the query to fail with its reason, got %v", err)
+	}
+}
+
+func TestNegotiatedSizeIsTheSmallerOfTheTwo(t *testing.T) {
+	cases := []struct {
+		ours     int
+		theirs   uint64
+		expected int
+	}{
+		{GRPCMaxSizeBytes, 0, GRPCMaxSizeBytes},
+		{GRPCMaxSizeBytes * 4, 0, GRPCMaxSizeBytes},
+		{GRPCMaxSizeBytes * 4, GRPCMaxSizeBytes * 2, GRPCMaxSizeBytes * 2},
+		{GRPCMaxSizeBytes, GRPCMaxSizeBytes * 2, GRPCMaxSizeBytes},
+		{GRPCMaxSizeBytes, 10, GRPCMinSizeBytes},
+	}
+	for _, c := range cases {
+		if actual := negotiateMaxSize(c.ours, c.theirs); actual != c.expected {
+			t.Errorf("negotiateMaxSize(%d, %d) = %d, expected %d", c.ours, c.theirs, actual, c.expected)
+		}
+	}
+}
@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Package hipcheck is the SDK for writing Hipcheck plugins in Go.
+//
+// It mirrors the Rust SDK: a plugin implements [Plugin], defines its query endpoints with
+// [NewQuery] and [NewDefaultQuery], and runs with [Main], which serves the Hipcheck plugin gRPC
+// protocol on the port Hipcheck core passes with --port:
+//
+//	type sha256Plugin struct{}
+//
+//	func (sha256Plugin) Publisher() string { return "dummy" }
+//	func (sha256Plugin) Name() string      { return "sha256" }
+//	...
+//	func (sha256Plugin) Queries() []hipcheck.NamedQuery {
+//		return []hipcheck.NamedQuery{hipcheck.NewDefaultQuery(sha256)}
+//	}
+//
+//	func main() { hipcheck.Main(sha256Plugin{}) }
+//
+// Query keys and outputs are JSON. Typed queries decode keys and encode outputs with
+// encoding/json, and describe them to Hipcheck core with JSON schemas reflected from their types.
+package hipcheck
+
+// The protocol bindings are generated from hipcheck-common's hipcheck.proto, so the SDK always
+// speaks the protocol Hipcheck core is built with.
+//go:generate protoc -I ../../hipcheck-common/proto/hipcheck/v1 --go_out=internal/pb --go_opt=paths=source_relative --go_opt=Mhipcheck.proto=github.com/mitre/hipcheck/sdk/go/internal/pb --go-grpc_out=internal/pb --go-grpc_opt=paths=source_relative --go-grpc_opt=Mhipcheck.proto=github.com/mitre/hipcheck/sdk/go/internal/pb hipcheck.proto
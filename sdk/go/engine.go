@@ -0,0 +1,506 @@
+// SPDX-License-Identifier: Apache-2.0
+
+package hipcheck
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"log/slog"
+	"sync"
+
+	"github.com/mitre/hipcheck/sdk/go/internal/pb"
+)
+
+// sendFunc sends a message of the query protocol to Hipcheck core.
+type sendFunc func(*pb.Query) error
+
+// PluginEngine manages a single query session.
+//
+// An engine is passed to each query a plugin runs, letting the query ask other Hipcheck plugins
+// for information, and record concerns and report fragments for Hipcheck's final report. An
+// engine is only used by the query it's passed to, so it isn't safe for concurrent use.
+type PluginEngine struct {
+	id   int32
+	send sendFunc
+	rx   chan *pb.Query
+
+	// The max message size negotiated with Hipcheck core, which queries are chunked to fit
+	maxMessageSize int
+
+	// When unit testing, the answers to the queries the plugin makes
+	mockResponses *MockResponses
+
+	concerns  []Concern
+	fragments []json.RawMessage
+
+	// The progress last reported to Hipcheck core, if any
+	progress *[2]uint64
+
+	// Closed once Hipcheck core no longer needs the answer to the query
+	cancelled  chan struct{}
+	cancelOnce sync.Once
+}
+
+func newPluginEngine(id int32, send sendFunc, maxMessageSize int) *PluginEngine {
+	return &PluginEngine{
+		id:             id,
+		send:           send,
+		rx:             make(chan *pb.Query, 10),
+		maxMessageSize: maxMessageSize,
+		cancelled:      make(chan struct{}),
+	}
+}
+
+// MockEngine makes an engine for unit tests, which answers the queries the plugin makes from
+// mockResponses instead of asking Hipcheck core.
+func MockEngine(mockResponses *MockResponses) *PluginEngine {
+	if mockResponses == nil {
+		mockResponses = NewMockResponses()
+	}
+	return &PluginEngine{
+		maxMessageSize: GRPCMaxSizeBytes,
+		mockResponses:  mockResponses,
+		cancelled:      make(chan struct{}),
+	}
+}
+
+// Query asks another Hipcheck plugin about a key, returning its answer as JSON. The target is
+// usually of the form "publisher/plugin[/query]", where the query is left out for the plugin's
+// default query. The key may be anything encoding/json can write.
+func (e *PluginEngine) Query(ctx context.Context, target string, key any) (json.RawMessage, error) {
+	outputs, err := e.BatchQuery(ctx, target, []any{key})
+	if err != nil {
+		return nil, err
+	}
+	return outputs[0], nil
+}
+
+// BatchQuery asks another Hipcheck plugin about several keys in one go, returning its answers in
+// the same order.
+func (e *PluginEngine) BatchQuery(ctx context.Context, target string, keys []any) ([]json.RawMessage, error) {
+	if e.IsCancelled() {
+		return nil, ErrQueryCancelled
+	}
+	queryTarget, err := ParseQueryTarget(target)
+	if err != nil {
+		return nil, err
+	}
+	encodedKeys := make([]string, 0, len(keys))
+	for _, key := range keys {
+		encoded, err := json.Marshal(key)
+		if err != nil {
+			return nil, fmt.Errorf("invalid JSON in query key: %w", err)
+		}
+		encodedKeys = append(encodedKeys, string(encoded))
+	}
+
+	// When unit testing, the answers come from the mock responses
+	if e.mockResponses != nil {
+		outputs := make([]json.RawMessage, 0, len(keys))
+		for _, key := range encodedKeys {
+			output, err := e.mockResponses.get(queryTarget, key)
+			if err != nil {
+				return nil, err
+			}
+			outputs = append(outputs, output)
+		}
+		return outputs, nil
+	}
+
+	err = e.sendQuery(&pb.Query{
+		Id:            e.id,
+		State:         pb.QueryState_QUERY_STATE_SUBMIT_COMPLETE,
+		PublisherName: queryTarget.Publisher,
+		PluginName:    queryTarget.Plugin,
+		QueryName:     queryTarget.Query,
+		Key:           encodedKeys,
+	})
+	if err != nil {
+		return nil, err
+	}
+	response, err := e.recv(ctx)
+	if err != nil {
+		return nil, err
+	}
+	if isSubmit(response.State) {
+		return nil, ErrSubmitWhenExpectingReply
+	}
+	outputs := make([]json.RawMessage, 0, len(response.Output))
+	for _, output := range response.Output {
+		if !json.Valid([]byte(output)) {
+			return nil, errors.New("invalid JSON in query output")
+		}
+		outputs = append(outputs, json.RawMessage(output))
+	}
+	return outputs, nil
+}
+
+// Cache gets the cache the plugin keeps data in between runs of Hipcheck, for memoizing
+// expensive work like API calls. Entries are scoped by the version of the plugin and the HEAD
+// commit of the target. When the plugin wasn't started by Hipcheck core, as in unit tests, the
+// cache keeps nothing.
+func (e *PluginEngine) Cache() *PluginCache {
+	return pluginCache()
+}
+
+var pluginCache = sync.OnceValue(func() *PluginCache {
+	dir, _ := CacheDir()
+	return NewPluginCache(dir)
+})
+
+// RecordConcern records a concern to emit in the final Hipcheck report.
+func (e *PluginEngine) RecordConcern(concern Concern) {
+	e.concerns = append(e.concerns, concern)
+}
+
+// Concerns gets the concerns recorded so far.
+func (e *PluginEngine) Concerns() []Concern {
+	return e.concerns
+}
+
+// RecordReportFragment records a section to add to the final Hipcheck report, for results which
+// aren't a pass or fail.
+func (e *PluginEngine) RecordReportFragment(fragment ReportFragment) error {
+	encoded, err := json.Marshal(fragment)
+	if err != nil {
+		return fmt.Errorf("invalid JSON in query report fragment: %w", err)
+	}
+	e.fragments = append(e.fragments, encoded)
+	return nil
+}
+
+// ReportFragments gets the report fragments recorded so far, as JSON.
+func (e *PluginEngine) ReportFragments() []json.RawMessage {
+	return e.fragments
+}
+
+// ReportProgress reports how far along the current query is, as completed out of total units of
+// work (e.g. commits processed), so Hipcheck can show the user how far along the analysis is.
+//
+// Progress is only sent to Hipcheck core when the whole percentage done changes, so this is
+// cheap to call for every unit of work.
+func (e *PluginEngine) ReportProgress(completed, total uint64) error {
+	if total == 0 {
+		return nil
+	}
+	completed = min(completed, total)
+	if e.progress != nil {
+		last := *e.progress
+		if last[1] == total && last[0]*100/total == completed*100/total {
+			return nil
+		}
+	}
+	e.progress = &[2]uint64{completed, total}
+
+	// There's no Hipcheck core to report to when unit testing
+	if e.send == nil {
+		return nil
+	}
+	return e.send(&pb.Query{
+		Id:       e.id,
+		State:    pb.QueryState_QUERY_STATE_UNSPECIFIED,
+		Progress: &pb.Progress{Completed: completed, Total: total},
+	})
+}
+
+// Progress gets the progress last reported, if any.
+func (e *PluginEngine) Progress() (completed, total uint64, ok bool) {
+	if e.progress == nil {
+		return 0, 0, false
+	}
+	return e.progress[0], e.progress[1], true
+}
+
+// IsCancelled reports whether Hipcheck core has cancelled the current query, as when it timed out
+// or a query it depends on failed. Once cancelled, queries to other plugins fail with
+// ErrQueryCancelled, and the query's answer is ignored.
+func (e *PluginEngine) IsCancelled() bool {
+	select {
+	case <-e.cancelled:
+		return true
+	default:
+		return false
+	}
+}
+
+// Cancelled gets a channel which is closed once Hipcheck core cancels the current query. The
+// context passed to the query is cancelled along with it.
+func (e *PluginEngine) Cancelled() <-chan struct{} {
+	return e.cancelled
+}
+
+// Cancel cancels the current query, as Hipcheck core does when it no longer needs the answer. It's
+// for unit testing how a query handles being cancelled.
+func (e *PluginEngine) Cancel() {
+	e.cancelOnce.Do(func() { close(e.cancelled) })
+}
+
+func (e *PluginEngine) takeConcerns() []string {
+	concerns := make([]string, 0, len(e.concerns))
+	for _, concern := range e.concerns {
+		// Concerns are plain data, so they always encode
+		encoded, _ := json.Marshal(concern)
+		concerns = append(concerns, string(encoded))
+	}
+	e.concerns = nil
+	return concerns
+}
+
+func (e *PluginEngine) takeFragments() []string {
+	fragments := make([]string, 0, len(e.fragments))
+	for _, fragment := range e.fragments {
+		fragments = append(fragments, string(fragment))
+	}
+	e.fragments = nil
+	return fragments
+}
+
+// sendQuery sends a whole query to Hipcheck core, one chunk at a time.
+func (e *PluginEngine) sendQuery(query *pb.Query) error {
+	if e.send == nil {
+		return ErrSessionChannelClosed
+	}
+	chunks, err := prepareChunks(query, e.maxMessageSize)
+	if err != nil {
+		return err
+	}
+	for {
+		chunk, err := chunks.next()
+		if err != nil {
+			return err
+		}
+		if chunk == nil {
+			return nil
+		}
+		if err := e.send(chunk); err != nil {
+			return fmt.Errorf("%w: %w", errFailedToSend, err)
+		}
+	}
+}
+
+// recv receives a whole query from Hipcheck core.
+func (e *PluginEngine) recv(ctx context.Context) (*pb.Query, error) {
+	synth := newQuerySynthesizer()
+	for {
+		select {
+		case <-ctx.Done():
+			if e.IsCancelled() {
+				return nil, ErrQueryCancelled
+			}
+			return nil, ctx.Err()
+		case <-e.cancelled:
+			return nil, ErrQueryCancelled
+		case msg, ok := <-e.rx:
+			if !ok {
+				return nil, ErrSessionChannelClosed
+			}
+			query, err := synth.add(msg)
+			if err != nil {
+				return nil, err
+			}
+			if query != nil {
+				return query, nil
+			}
+		}
+	}
+}
+
+func (e *PluginEngine) handleSession(ctx context.Context, plugin Plugin) {
+	// The query's context is cancelled along with the query
+	ctx, stop := context.WithCancel(ctx)
+	defer stop()
+	go func() {
+		select {
+		case <-e.cancelled:
+			stop()
+		case <-ctx.Done():
+		}
+	}()
+
+	err := e.handleSessionFallible(ctx, plugin)
+	if err == nil {
+		return
+	}
+	// Hipcheck core ignores anything sent about a query after cancelling it
+	if e.IsCancelled() {
+		slog.Debug(fmt.Sprintf("SDK: session %d was cancelled: %s", e.id, err))
+		return
+	}
+	if errors.Is(err, errFailedToSend) {
+		slog.Error("Failed to send message to Hipcheck core, analysis will hang.")
+		return
+	}
+	slog.Error(err.Error())
+	// A query which failed is answered in the unspecified state, with why it failed
+	err = e.send(&pb.Query{
+		Id:            e.id,
+		State:         pb.QueryState_QUERY_STATE_UNSPECIFIED,
+		PublisherName: plugin.Publisher(),
+		PluginName:    plugin.Name(),
+		Concern:       e.takeConcerns(),
+		Error:         err.Error(),
+	})
+	if err != nil {
+		slog.Error("Failed to send message to Hipcheck core, analysis will hang.")
+	}
+}
+
+func (e *PluginEngine) handleSessionFallible(ctx context.Context, plugin Plugin) error {
+	query, err := e.recv(ctx)
+	if err != nil {
+		return err
+	}
+	if !isSubmit(query.State) {
+		return ErrReplyWhenExpectingRequest
+	}
+	// Per RFD 0009, there should only be one query key per query
+	if len(query.Key) != 1 {
+		return ErrUnspecifiedQueryState
+	}
+	key := json.RawMessage(query.Key[0])
+	if !json.Valid(key) {
+		return errors.New("invalid JSON in query key")
+	}
+
+	inner, err := findQuery(plugin, query.QueryName)
+	if err != nil {
+		return err
+	}
+	output, err := inner.Run(ctx, e, key)
+	if err != nil {
+		return err
+	}
+
+	return e.sendQuery(&pb.Query{
+		Id:            e.id,
+		State:         pb.QueryState_QUERY_STATE_REPLY_COMPLETE,
+		PublisherName: plugin.Publisher(),
+		PluginName:    plugin.Name(),
+		QueryName:     query.QueryName,
+		Output:        []string{string(output)},
+		Concern:       e.takeConcerns(),
+		Fragment:      e.takeFragments(),
+	})
+}
+
+// sessionSocket routes the messages Hipcheck core sends to the sessions they belong to, starting
+// a new session for each new query.
+type sessionSocket struct {
+	ctx            context.Context
+	plugin         Plugin
+	send           sendFunc
+	maxMessageSize int
+
+	mu       sync.Mutex
+	sessions map[int32]*PluginEngine
+	running  sync.WaitGroup
+}
+
+func newSessionSocket(ctx context.Context, plugin Plugin, send sendFunc, maxMessageSize int) *sessionSocket {
+	return &sessionSocket{
+		ctx:            ctx,
+		plugin:         plugin,
+		send:           send,
+		maxMessageSize: maxMessageSize,
+		sessions:       make(map[int32]*PluginEngine),
+	}
+}
+
+// dispatch hands a message to the session it belongs to, starting a new session if it's the
+// first message of a query.
+func (s *sessionSocket) dispatch(msg *pb.Query) {
+	s.mu.Lock()
+	engine, ok := s.sessions[msg.Id]
+	if msg.Cancel {
+		s.mu.Unlock()
+		if !ok {
+			slog.Log(s.ctx, LevelTrace, fmt.Sprintf("SDK: ignoring cancellation of finished session %d", msg.Id))
+			return
+		}
+		slog.Debug(fmt.Sprintf("SDK: cancelling session %d", msg.Id))
+		engine.Cancel()
+		return
+	}
+	if !ok {
+		if !isSubmit(msg.State) {
+			s.mu.Unlock()
+			slog.Error(ErrReplyWhenExpectingRequest.Error())
+			return
+		}
+		slog.Log(s.ctx, LevelTrace, fmt.Sprintf("SDK: creating new session %d", msg.Id))
+		engine = newPluginEngine(msg.Id, s.send, s.maxMessageSize)
+		s.sessions[msg.Id] = engine
+		s.running.Add(1)
+		go func() {
+			defer s.running.Done()
+			engine.handleSession(s.ctx, s.plugin)
+			s.mu.Lock()
+			delete(s.sessions, engine.id)
+			s.mu.Unlock()
+		}()
+	}
+	s.mu.Unlock()
+
+	engine.rx <- msg
+}
+
+// close tells every open session Hipcheck core has gone away, and waits for them to finish.
+func (s *sessionSocket) close() {
+	s.mu.Lock()
+	for id, engine := range s.sessions {
+		engine.Cancel()
+		close(engine.rx)
+		delete(s.sessions, id)
+	}
+	s.mu.Unlock()
+	s.running.Wait()
+}
+
+// MockResponses holds the answers to the queries a plugin makes of other plugins, for unit
+// testing a plugin's queries without Hipcheck core.
+type MockResponses struct {
+	responses map[mockKey]mockResponse
+}
+
+type mockKey struct {
+	target QueryTarget
+	key    string
+}
+
+type mockResponse struct {
+	output json.RawMessage
+	err    error
+}
+
+// NewMockResponses makes an empty set of mock responses.
+func NewMockResponses() *MockResponses {
+	return &MockResponses{responses: make(map[mockKey]mockResponse)}
+}
+
+// Insert answers queries to target about key with output, or with err if it isn't nil.
+func (m *MockResponses) Insert(target string, key any, output any, err error) error {
+	queryTarget, parseErr := ParseQueryTarget(target)
+	if parseErr != nil {
+		return parseErr
+	}
+	encodedKey, jsonErr := json.Marshal(key)
+	if jsonErr != nil {
+		return fmt.Errorf("invalid JSON in query key: %w", jsonErr)
+	}
+	encodedOutput, jsonErr := json.Marshal(output)
+	if jsonErr != nil {
+		return fmt.Errorf("invalid JSON in query output: %w", jsonErr)
+	}
+	m.responses[mockKey{queryTarget, string(encodedKey)}] = mockResponse{encodedOutput, err}
+	return nil
+}
+
+func (m *MockResponses) get(target QueryTarget, key string) (json.RawMessage, error) {
+	response, ok := m.responses[mockKey{target, key}]
+	if !ok {
+		return nil, fmt.Errorf("%w '%s'", ErrUnknownQuery, target)
+	}
+	return response.output, response.err
+}
@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: Apache-2.0
+
+package hipcheck
+
+import (
+	"os"
+	"strings"
+)
+
+// What Hipcheck core tells a plugin through its environment when it starts the plugin, as in
+// hipcheck-common's plugin_scratch, plugin_secret, and plugin_cache modules.
+const (
+	scratchDirEnvVar   = "HC_PLUGIN_SCRATCH_DIR"
+	cacheDirEnvVar     = "HC_PLUGIN_CACHE_DIR"
+	secretEnvVarPrefix = "HC_SECRET_"
+	logLevelEnvVar     = "HC_PLUGIN_LOG_LEVEL"
+)
+
+// ScratchDir gets the directory Hipcheck core set aside for the plugin's temporary files, if it
+// set one aside. Core removes it when the run ends.
+func ScratchDir() (string, bool) {
+	return nonEmptyEnv(scratchDirEnvVar)
+}
+
+// CacheDir gets the directory Hipcheck core set aside for the plugin to keep data in between
+// runs, if it set one aside. Most plugins should use [PluginEngine.Cache] instead.
+func CacheDir() (string, bool) {
+	return nonEmptyEnv(cacheDirEnvVar)
+}
+
+// Secret gets a secret the user configured for the plugin, like an API token, if they
+// configured it.
+func Secret(name string) (string, bool) {
+	return nonEmptyEnv(secretEnvVar(name))
+}
+
+// secretEnvVar gets the environment variable a secret is passed in, e.g. HC_SECRET_GITHUB_TOKEN
+// for github-token.
+func secretEnvVar(name string) string {
+	var b strings.Builder
+	b.WriteString(secretEnvVarPrefix)
+	for _, c := range name {
+		switch {
+		case c >= 'a' && c <= 'z':
+			b.WriteRune(c - 'a' + 'A')
+		case c >= 'A' && c <= 'Z', c >= '0' && c <= '9':
+			b.WriteRune(c)
+		default:
+			b.WriteByte('_')
+		}
+	}
+	return b.String()
+}
+
+func nonEmptyEnv(name string) (string, bool) {
+	value := os.Getenv(name)
+	return value, value != ""
+}
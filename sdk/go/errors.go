@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: Apache-2.0
+
+package hipcheck
+
+import (
+	"errors"
+	"fmt"
+	"strings"
+
+	"github.com/mitre/hipcheck/sdk/go/internal/pb"
+)
+
+var (
+	// ErrUnspecifiedQueryState is returned when a query message is in the unspecified state.
+	ErrUnspecifiedQueryState = errors.New("unknown error; query is in an unspecified state")
+
+	// ErrReplyWhenExpectingRequest is returned when Hipcheck core sends a reply to a query the
+	// plugin didn't make.
+	ErrReplyWhenExpectingRequest = errors.New("plugin sent QueryReply when server was expecting a request")
+
+	// ErrSubmitWhenExpectingReply is returned when Hipcheck core sends a query while the plugin
+	// is waiting for a reply.
+	ErrSubmitWhenExpectingReply = errors.New("plugin sent QuerySubmit when server was expecting a reply chunk")
+
+	// ErrSessionChannelClosed is returned when Hipcheck core closes the query protocol stream
+	// while a query is waiting on it.
+	ErrSessionChannelClosed = errors.New("session channel closed unexpectedly")
+
+	// ErrUnexpectedQueryInputFormat is returned when a query's key doesn't match its type.
+	ErrUnexpectedQueryInputFormat = errors.New("unexpected JSON value from plugin")
+
+	// ErrUnexpectedQueryOutputFormat is returned when a query's output can't be written as JSON.
+	ErrUnexpectedQueryOutputFormat = errors.New("plugin output could not be serialized to JSON")
+
+	// ErrUnknownQuery is returned when a plugin has no query by the name asked for, nor a
+	// default query.
+	ErrUnknownQuery = errors.New("could not determine which plugin query to run")
+
+	// ErrInvalidQueryTarget is returned when a query target isn't of the form
+	// "publisher/plugin[/query]".
+	ErrInvalidQueryTarget = errors.New("invalid format for QueryTarget")
+
+	// ErrQueryCancelled is returned when a query makes a query of another plugin after Hipcheck
+	// core has cancelled it.
+	ErrQueryCancelled = errors.New("query was cancelled by Hipcheck core")
+
+	// ErrInvalidCacheScope is returned when a cache entry is scoped by something which isn't a
+	// commit hash.
+	ErrInvalidCacheScope = errors.New("invalid plugin cache scope")
+
+	// errFailedToSend is returned when a message can't be sent to Hipcheck core.
+	errFailedToSend = errors.New("failed to send query from session to server")
+
+	// errChunking is returned when a message can't be split into chunks.
+	errChunking = errors.New("message could not be chunked")
+)
+
+// QueryFailedError is returned when a query the plugin made to another plugin failed, with the
+// reason Hipcheck core gave.
+type QueryFailedError struct {
+	Message string
+}
+
+func (e *QueryFailedError) Error() string {
+	return e.Message
+}
+
+// ConfigError is returned by [Plugin.SetConfig] when the configuration the user gave the plugin
+// in their policy file is wrong.
+type ConfigError struct {
+	status  pb.ConfigurationStatus
+	Message string
+}
+
+func (e *ConfigError) Error() string {
+	return e.Message
+}
+
+// InvalidConfigValue makes the error for a config key whose value is invalid.
+func InvalidConfigValue(fieldName, value, reason string) *ConfigError {
+	return &ConfigError{
+		status:  pb.ConfigurationStatus_CONFIGURATION_STATUS_INVALID_CONFIGURATION_VALUE,
+		Message: fmt.Sprintf("invalid value '%s' for '%s', reason: '%s'", value, fieldName, reason),
+	}
+}
+
+// MissingRequiredConfig makes the error for a config missing an expected field.
+func MissingRequiredConfig(fieldName, fieldType string, possibleValues []string) *ConfigError {
+	message := fmt.Sprintf("missing required config item '%s' of type '%s'", fieldName, fieldType)
+	if len(possibleValues) > 0 {
+		message += "; possible values: " + strings.Join(possibleValues, ", ")
+	}
+	return &ConfigError{
+		status:  pb.ConfigurationStatus_CONFIGURATION_STATUS_MISSING_REQUIRED_CONFIGURATION,
+		Message: message,
+	}
+}
+
+// UnrecognizedConfig makes the error for a config including an unrecognized field.
+func UnrecognizedConfig(fieldName, fieldValue string, possibleConfusables []string) *ConfigError {
+	message := fmt.Sprintf("unrecognized field '%s' with value '%s'", fieldName, fieldValue)
+	if len(possibleConfusables) > 0 {
+		message += "; possible field names: " + strings.Join(possibleConfusables, ", ")
+	}
+	return &ConfigError{
+		status:  pb.ConfigurationStatus_CONFIGURATION_STATUS_UNRECOGNIZED_CONFIGURATION,
+		Message: message,
+	}
+}
+
+// toConfigResponse describes a failure to configure the plugin to Hipcheck core.
+func toConfigResponse(err error, maxMessageSize int) *pb.SetConfigurationResponse {
+	var configErr *ConfigError
+	if !errors.As(err, &configErr) {
+		configErr = &ConfigError{
+			status:  pb.ConfigurationStatus_CONFIGURATION_STATUS_UNSPECIFIED,
+			Message: "unknown error; " + err.Error(),
+		}
+	}
+	return &pb.SetConfigurationResponse{
+		Status:         configErr.status,
+		Message:        configErr.Message,
+		MaxMessageSize: uint64(maxMessageSize),
+	}
+}
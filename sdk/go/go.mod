@@ -0,0 +1,9 @@
+module github.com/mitre/hipcheck/sdk/go
+
+go 1.22
+
+require (
+	github.com/invopop/jsonschema v0.12.0
+	google.golang.org/grpc v1.64.0
+	google.golang.org/protobuf v1.34.2
+)
@@ -0,0 +1,5 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Package pb holds the Hipcheck plugin protocol bindings, generated from hipcheck-common's
+// hipcheck.proto by running go generate in the SDK's root.
+package pb
@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: Apache-2.0
+
+package hipcheck
+
+import (
+	"context"
+	"fmt"
+	"io"
+	"log/slog"
+	"os"
+	"strings"
+	"sync"
+)
+
+// LevelTrace is the level of log records finer than debug, which Hipcheck core logs as trace.
+const LevelTrace = slog.LevelDebug - 4
+
+// levelOff is above every level records are logged at.
+const levelOff = slog.LevelError + 4
+
+// logLevelFromEnv gets the level Hipcheck core asked the plugin to log at, defaulting to errors
+// only like core does.
+func logLevelFromEnv() slog.Level {
+	switch strings.ToUpper(os.Getenv(logLevelEnvVar)) {
+	case "OFF":
+		return levelOff
+	case "WARN":
+		return slog.LevelWarn
+	case "INFO":
+		return slog.LevelInfo
+	case "DEBUG":
+		return slog.LevelDebug
+	case "TRACE":
+		return LevelTrace
+	default:
+		return slog.LevelError
+	}
+}
+
+// logHandler writes log records to the plugin's standard error as lines of the form
+// "hc-log<TAB>LEVEL<TAB>target<TAB>message", for Hipcheck core to forward, as described in
+// hipcheck-common's plugin_log module.
+type logHandler struct {
+	mu     *sync.Mutex
+	w      io.Writer
+	level  slog.Level
+	target string
+	attrs  string
+}
+
+func newLogHandler(w io.Writer, level slog.Level, target string) *logHandler {
+	return &logHandler{mu: &sync.Mutex{}, w: w, level: level, target: target}
+}
+
+func (h *logHandler) Enabled(_ context.Context, level slog.Level) bool {
+	return level >= h.level
+}
+
+func (h *logHandler) Handle(_ context.Context, record slog.Record) error {
+	var message strings.Builder
+	message.WriteString(record.Message)
+	message.WriteString(h.attrs)
+	record.Attrs(func(attr slog.Attr) bool {
+		message.WriteString(formatAttr(attr))
+		return true
+	})
+
+	h.mu.Lock()
+	defer h.mu.Unlock()
+	_, err := fmt.Fprintln(h.w, formatLogLine(record.Level, h.target, message.String()))
+	return err
+}
+
+func (h *logHandler) WithAttrs(attrs []slog.Attr) slog.Handler {
+	handler := *h
+	for _, attr := range attrs {
+		handler.attrs += formatAttr(attr)
+	}
+	return &handler
+}
+
+func (h *logHandler) WithGroup(name string) slog.Handler {
+	// Groups only qualify attribute names, which plugins rarely use, so they're left out
+	return h
+}
+
+func formatAttr(attr slog.Attr) string {
+	return fmt.Sprintf(" %s=%v", attr.Key, attr.Value)
+}
+
+// formatLogLine formats a log record as a single line to be parsed by core.
+func formatLogLine(level slog.Level, target, message string) string {
+	var name string
+	switch {
+	case level >= slog.LevelError:
+		name = "ERROR"
+	case level >= slog.LevelWarn:
+		name = "WARN"
+	case level >= slog.LevelInfo:
+		name = "INFO"
+	case level >= slog.LevelDebug:
+		name = "DEBUG"
+	default:
+		name = "TRACE"
+	}
+	return fmt.Sprintf("hc-log\t%s\t%s\t%s", name, target, strings.ReplaceAll(message, "\n", "\\n"))
+}
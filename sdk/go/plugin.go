@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: Apache-2.0
+
+package hipcheck
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+
+	"github.com/invopop/jsonschema"
+)
+
+// Plugin is a Hipcheck plugin.
+type Plugin interface {
+	// Publisher is the name of the plugin publisher.
+	Publisher() string
+
+	// Name is the name of the plugin.
+	Name() string
+
+	// SetConfig handles the configuration the user gave the plugin in their policy file, as a
+	// JSON object. It returns a [*ConfigError] if the configuration is wrong.
+	SetConfig(config json.RawMessage) error
+
+	// DefaultPolicyExpr gets the plugin's default policy expression. It's only called after
+	// SetConfig. For more information on policy expression syntax, see the Hipcheck website.
+	DefaultPolicyExpr() (string, error)
+
+	// ExplainDefaultQuery gets an unstructured description of what is returned by the plugin's
+	// default query, or "" if there's none.
+	ExplainDefaultQuery() (string, error)
+
+	// Queries gets all the query endpoints of the plugin.
+	Queries() []NamedQuery
+}
+
+// Query is a query endpoint of a plugin. Most plugins make their queries with [NewQuery] or
+// [NewDefaultQuery] rather than implementing it themselves.
+type Query interface {
+	// InputSchema describes the JSON of the query's keys.
+	InputSchema() *jsonschema.Schema
+
+	// OutputSchema describes the JSON of the query's outputs.
+	OutputSchema() *jsonschema.Schema
+
+	// Run runs the query on a key, returning its output as JSON.
+	Run(ctx context.Context, engine *PluginEngine, key json.RawMessage) (json.RawMessage, error)
+}
+
+// NamedQuery is a query endpoint with its name, which is empty for the default query.
+type NamedQuery struct {
+	Name  string
+	Inner Query
+}
+
+// IsDefault is whether the query is the plugin's default query.
+func (q NamedQuery) IsDefault() bool {
+	return q.Name == ""
+}
+
+// QueryFunc is the function run for a typed query, taking a key of type K and giving an output of
+// type O.
+type QueryFunc[K, O any] func(ctx context.Context, engine *PluginEngine, key K) (O, error)
+
+// NewQuery makes a query endpoint named name from a function. Keys are decoded into K, and
+// outputs encoded from O, with encoding/json, and the query's schemas are reflected from K and O.
+func NewQuery[K, O any](name string, run QueryFunc[K, O]) NamedQuery {
+	return NamedQuery{Name: name, Inner: typedQuery[K, O]{run: run}}
+}
+
+// NewDefaultQuery makes the plugin's default query endpoint from a function, as [NewQuery] does.
+func NewDefaultQuery[K, O any](run QueryFunc[K, O]) NamedQuery {
+	return NewQuery("", run)
+}
+
+type typedQuery[K, O any] struct {
+	run QueryFunc[K, O]
+}
+
+func (q typedQuery[K, O]) InputSchema() *jsonschema.Schema {
+	return reflectSchema[K]()
+}
+
+func (q typedQuery[K, O]) OutputSchema() *jsonschema.Schema {
+	return reflectSchema[O]()
+}
+
+func (q typedQuery[K, O]) Run(ctx context.Context, engine *PluginEngine, key json.RawMessage) (json.RawMessage, error) {
+	var input K
+	if err := json.Unmarshal(key, &input); err != nil {
+		return nil, fmt.Errorf("%w: %w", ErrUnexpectedQueryInputFormat, err)
+	}
+	output, err := q.run(ctx, engine, input)
+	if err != nil {
+		return nil, err
+	}
+	encoded, err := json.Marshal(output)
+	if err != nil {
+		return nil, fmt.Errorf("%w: %w", ErrUnexpectedQueryOutputFormat, err)
+	}
+	return encoded, nil
+}
+
+func reflectSchema[T any]() *jsonschema.Schema {
+	reflector := jsonschema.Reflector{DoNotReference: true, Anonymous: true}
+	return reflector.Reflect(new(T))
+}
+
+// QuerySchema is the schemas of the key and output of a query endpoint, as sent to Hipcheck core.
+type QuerySchema struct {
+	QueryName    string
+	InputSchema  *jsonschema.Schema
+	OutputSchema *jsonschema.Schema
+}
+
+// Schemas gets the schemas of all of a plugin's query endpoints.
+func Schemas(plugin Plugin) []QuerySchema {
+	var schemas []QuerySchema
+	for _, query := range plugin.Queries() {
+		schemas = append(schemas, QuerySchema{
+			QueryName:    query.Name,
+			InputSchema:  query.Inner.InputSchema(),
+			OutputSchema: query.Inner.OutputSchema(),
+		})
+	}
+	return schemas
+}
+
+// findQuery finds the query endpoint of a plugin with a name, falling back to the plugin's default
+// query as the Rust SDK does.
+func findQuery(plugin Plugin, name string) (Query, error) {
+	var fallback Query
+	for _, query := range plugin.Queries() {
+		if query.Name == name {
+			return query.Inner, nil
+		}
+		if query.IsDefault() {
+			fallback = query.Inner
+		}
+	}
+	if fallback == nil {
+		return nil, fmt.Errorf("%w '%s'", ErrUnknownQuery, name)
+	}
+	return fallback, nil
+}
@@ -0,0 +1,309 @@
+// SPDX-License-Identifier: Apache-2.0
+
+package hipcheck
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"reflect"
+	"strings"
+	"testing"
+	"time"
+
+	"github.com/mitre/hipcheck/sdk/go/internal/pb"
+)
+
+type examplePlugin struct{}
+
+func (examplePlugin) Publisher() string { return "example" }
+
+func (examplePlugin) Name() string { return "example" }
+
+func (examplePlugin) SetConfig(config json.RawMessage) error {
+	var fields map[string]string
+	if err := json.Unmarshal(config, &fields); err != nil {
+		return err
+	}
+	for name, value := range fields {
+		if name != "volume" {
+			return UnrecognizedConfig(name, value, []string{"volume"})
+		}
+	}
+	return nil
+}
+
+func (examplePlugin) DefaultPolicyExpr() (string, error) { return "(gt 10 $)", nil }
+
+func (examplePlugin) ExplainDefaultQuery() (string, error) { return "", nil }
+
+func (examplePlugin) Queries() []NamedQuery {
+	return []NamedQuery{
+		NewDefaultQuery(func(ctx context.Context, engine *PluginEngine, key []int) (int, error) {
+			sum := 0
+			for _, n := range key {
+				sum += n
+			}
+			return sum, nil
+		}),
+		NewQuery("loud", func(ctx context.Context, engine *PluginEngine, key string) (string, error) {
+			engine.RecordConcern(Concern{Message: "too loud", Severity: SeverityHigh, Line: 3})
+			if err := engine.RecordReportFragment(MarkdownFragment("Volume", "**loud**")); err != nil {
+				return "", err
+			}
+			return strings.ToUpper(key), nil
+		}),
+		NewQuery("ask", func(ctx context.Context, engine *PluginEngine, key string) (json.RawMessage, error) {
+			return engine.Query(ctx, "mitre/git/last_commit", key)
+		}),
+	}
+}
+
+func mustJSON(value any) []byte {
+	encoded, err := json.Marshal(value)
+	if err != nil {
+		panic(err)
+	}
+	return encoded
+}
+
+func TestSchemasAreReflectedFromQueryTypes(t *testing.T) {
+	schemas := Schemas(examplePlugin{})
+	if len(schemas) != 3 || schemas[0].QueryName != "" {
+		t.Fatalf("expected the three queries, got %+v", schemas)
+	}
+	if input := string(mustJSON(schemas[0].InputSchema)); !strings.Contains(input, `"type":"array"`) {
+		t.Errorf("expected an array input schema, got %s", input)
+	}
+	if output := string(mustJSON(schemas[0].OutputSchema)); !strings.Contains(output, `"type":"integer"`) {
+		t.Errorf("expected an integer output schema, got %s", output)
+	}
+}
+
+func TestConfigErrorsAreSentWithTheirStatus(t *testing.T) {
+	err := examplePlugin{}.SetConfig(json.RawMessage(`{"color":"red"}`))
+	response := toConfigResponse(err, GRPCMaxSizeBytes)
+	if response.Status != pb.ConfigurationStatus_CONFIGURATION_STATUS_UNRECOGNIZED_CONFIGURATION {
+		t.Errorf("unexpected status %v", response.Status)
+	}
+	if response.Message != "unrecognized field 'color' with value 'red'; possible field names: volume" {
+		t.Errorf("unexpected message %q", response.Message)
+	}
+
+	response = toConfigResponse(errors.New("oops"), GRPCMaxSizeBytes)
+	if response.Status != pb.ConfigurationStatus_CONFIGURATION_STATUS_UNSPECIFIED || response.Message != "unknown error; oops" {
+		t.Errorf("unexpected response %+v", response)
+	}
+}
+
+func runQuery(t *testing.T, engine *PluginEngine, name string, key any) string {
+	t.Helper()
+	query, err := findQuery(examplePlugin{}, name)
+	if err != nil {
+		t.Fatal(err)
+	}
+	output, err := query.Run(context.Background(), engine, mustJSON(key))
+	if err != nil {
+		t.Fatal(err)
+	}
+	return string(output)
+}
+
+func TestQueriesRunWithMockEngine(t *testing.T) {
+	responses := NewMockResponses()
+	if err := responses.Insert("mitre/git/last_commit", "repo", "abc123", nil); err != nil {
+		t.Fatal(err)
+	}
+	engine := MockEngine(responses)
+
+	if output := runQuery(t, engine, "", []int{1, 2, 3}); output != "6" {
+		t.Errorf("unexpected output %s", output)
+	}
+	if output := runQuery(t, engine, "ask", "repo"); output != `"abc123"` {
+		t.Errorf("unexpected output %s", output)
+	}
+	if output := runQuery(t, engine, "loud", "hi"); output != `"HI"` {
+		t.Errorf("unexpected output %s", output)
+	}
+	// Unknown queries fall back to the default query
+	if output := runQuery(t, engine, "nope", []int{4}); output != "4" {
+		t.Errorf("unexpected output %s", output)
+	}
+
+	expectedConcerns := []Concern{{Message: "too loud", Severity: SeverityHigh, Line: 3}}
+	if !reflect.DeepEqual(engine.Concerns(), expectedConcerns) {
+		t.Errorf("unexpected concerns %+v", engine.Concerns())
+	}
+	fragments := engine.ReportFragments()
+	if len(fragments) != 1 || string(fragments[0]) != `{"title":"Volume","format":"markdown","body":"**loud**"}` {
+		t.Errorf("unexpected report fragments %s", fragments)
+	}
+}
+
+func TestMockEngineReportsProgressOncePerPercent(t *testing.T) {
+	engine := MockEngine(nil)
+	for _, completed := range []uint64{1, 5} {
+		if err := engine.ReportProgress(completed, 1000); err != nil {
+			t.Fatal(err)
+		}
+	}
+	if completed, total, _ := engine.Progress(); completed != 1 || total != 1000 {
+		t.Errorf("unexpected progress %d/%d", completed, total)
+	}
+	if err := engine.ReportProgress(10, 1000); err != nil {
+		t.Fatal(err)
+	}
+	if completed, total, _ := engine.Progress(); completed != 10 || total != 1000 {
+		t.Errorf("unexpected progress %d/%d", completed, total)
+	}
+}
+
+func TestCancelledMockEnginesStopQuerying(t *testing.T) {
+	responses := NewMockResponses()
+	if err := responses.Insert("mitre/git/last_commit", "repo", "abc123", nil); err != nil {
+		t.Fatal(err)
+	}
+	engine := MockEngine(responses)
+	engine.Cancel()
+	if !engine.IsCancelled() {
+		t.Fatal("expected the engine to be cancelled")
+	}
+
+	query, err := findQuery(examplePlugin{}, "ask")
+	if err != nil {
+		t.Fatal(err)
+	}
+	if _, err := query.Run(context.Background(), engine, mustJSON("repo")); !errors.Is(err, ErrQueryCancelled) {
+		t.Errorf("expected the query to be cancelled, got %v", err)
+	}
+}
+
+// fakeCore plays Hipcheck core's side of the query protocol.
+type fakeCore struct {
+	sent   chan *pb.Query
+	socket *sessionSocket
+}
+
+func newFakeCore() *fakeCore {
+	core := &fakeCore{sent: make(chan *pb.Query, 100)}
+	send := func(query *pb.Query) error {
+		core.sent <- query
+		return nil
+	}
+	core.socket = newSessionSocket(context.Background(), examplePlugin{}, send, GRPCMinSizeBytes)
+	return core
+}
+
+func (c *fakeCore) submit(t *testing.T, id int32, queryName string, key any) {
+	t.Helper()
+	chunks, err := chunkWithSize(&pb.Query{
+		Id:            id,
+		State:         pb.QueryState_QUERY_STATE_SUBMIT_COMPLETE,
+		PublisherName: "example",
+		PluginName:    "example",
+		QueryName:     queryName,
+		Key:           []string{string(mustJSON(key))},
+	}, 8)
+	if err != nil {
+		t.Fatal(err)
+	}
+	for _, chunk := range chunks {
+		c.socket.dispatch(chunk)
+	}
+}
+
+func (c *fakeCore) receive(t *testing.T) (*pb.Query, error) {
+	t.Helper()
+	synth := newQuerySynthesizer()
+	for {
+		select {
+		case chunk := <-c.sent:
+			query, err := synth.add(chunk)
+			if err != nil || query != nil {
+				return query, err
+			}
+		case <-time.After(5 * time.Second):
+			t.Fatal("timed out waiting for the plugin")
+		}
+	}
+}
+
+func TestSessionsAnswerQueries(t *testing.T) {
+	core := newFakeCore()
+	defer core.socket.close()
+	core.submit(t, 1, "loud", "a chunked key")
+
+	reply, err := core.receive(t)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if reply.Id != 1 || reply.State != pb.QueryState_QUERY_STATE_REPLY_COMPLETE {
+		t.Errorf("unexpected reply %+v", reply)
+	}
+	if !reflect.DeepEqual(reply.Output, []string{`"A CHUNKED KEY"`}) {
+		t.Errorf("unexpected output %v", reply.Output)
+	}
+	if !reflect.DeepEqual(reply.Concern, []string{`{"message":"too loud","severity":"high","line":3}`}) {
+		t.Errorf("unexpected concerns %v", reply.Concern)
+	}
+}
+
+func TestSessionsQueryOtherPlugins(t *testing.T) {
+	core := newFakeCore()
+	defer core.socket.close()
+	core.submit(t, 7, "ask", "repo")
+
+	request, err := core.receive(t)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if request.Id != 7 || request.State != pb.QueryState_QUERY_STATE_SUBMIT_COMPLETE {
+		t.Errorf("unexpected request %+v", request)
+	}
+	target := QueryTarget{request.PublisherName, request.PluginName, request.QueryName}
+	if target.String() != "mitre/git/last_commit" || !reflect.DeepEqual(request.Key, []string{`"repo"`}) {
+		t.Errorf("unexpected request %+v", request)
+	}
+
+	core.socket.dispatch(&pb.Query{
+		Id:     7,
+		State:  pb.QueryState_QUERY_STATE_REPLY_COMPLETE,
+		Output: []string{`"abc123"`},
+	})
+	reply, err := core.receive(t)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if !reflect.DeepEqual(reply.Output, []string{`"abc123"`}) {
+		t.Errorf("unexpected output %v", reply.Output)
+	}
+}
+
+func TestFailedSessionsReportTheirError(t *testing.T) {
+	core := newFakeCore()
+	defer core.socket.close()
+	core.submit(t, 2, "", "not a list")
+
+	_, err := core.receive(t)
+	var failed *QueryFailedError
+	if !errors.As(err, &failed) || !strings.HasPrefix(failed.Message, ErrUnexpectedQueryInputFormat.Error()) {
+		t.Fatalf("expected the query to fail with its reason, got %v", err)
+	}
+}
+
+func TestCancelledSessionsStopWaitingForAnswers(t *testing.T) {
+	core := newFakeCore()
+	defer core.socket.close()
+	core.submit(t, 5, "ask", "repo")
+	if _, err := core.receive(t); err != nil {
+		t.Fatal(err)
+	}
+
+	core.socket.dispatch(&pb.Query{Id: 5, State: pb.QueryState_QUERY_STATE_UNSPECIFIED, Cancel: true})
+	core.socket.running.Wait()
+	select {
+	case query := <-core.sent:
+		t.Errorf("expected nothing to be sent after cancelling, got %+v", query)
+	default:
+	}
+}
@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: Apache-2.0
+
+package hipcheck
+
+// Severity is how serious a concern is.
+type Severity string
+
+const (
+	SeverityLow      Severity = "low"
+	SeverityMedium   Severity = "medium"
+	SeverityHigh     Severity = "high"
+	SeverityCritical Severity = "critical"
+)
+
+// Concern is a concern a plugin raises about the target, shown in the final Hipcheck report when
+// its analysis fails.
+//
+// Only the message is required. The rest let tools reading the report sort and deduplicate
+// concerns without parsing the message, so plugins should fill in whatever they know.
+type Concern struct {
+	// What the concern is, for people reading the report.
+	Message string `json:"message"`
+
+	// How serious the concern is.
+	Severity Severity `json:"severity,omitempty"`
+
+	// The file the concern is about, relative to the root of the repository.
+	File string `json:"file,omitempty"`
+
+	// The line of File the concern is about, counting from 1.
+	Line uint64 `json:"line,omitempty"`
+
+	// The hash of the commit the concern is about.
+	Commit string `json:"commit,omitempty"`
+
+	// Anything else identifying what the concern is about, like e-mail addresses or package
+	// names.
+	Identifiers []string `json:"identifiers,omitempty"`
+}
+
+// FragmentFormat is how the body of a report fragment is written.
+type FragmentFormat string
+
+const (
+	// FragmentMarkdown is Markdown text.
+	FragmentMarkdown FragmentFormat = "markdown"
+
+	// FragmentJSON is arbitrary JSON data.
+	FragmentJSON FragmentFormat = "json"
+)
+
+// ReportFragment is a section a plugin adds to the final Hipcheck report, for results which
+// aren't a pass or fail, like an inventory of licenses.
+type ReportFragment struct {
+	// The heading of the section.
+	Title string `json:"title"`
+
+	// How the body of the section is written.
+	Format FragmentFormat `json:"format"`
+
+	// The contents of the section, which must be a string for FragmentMarkdown.
+	Body any `json:"body"`
+}
+
+// MarkdownFragment makes a report fragment from Markdown text.
+func MarkdownFragment(title, text string) ReportFragment {
+	return ReportFragment{Title: title, Format: FragmentMarkdown, Body: text}
+}
+
+// JSONFragment makes a report fragment from JSON data.
+func JSONFragment(title string, data any) ReportFragment {
+	return ReportFragment{Title: title, Format: FragmentJSON, Body: data}
+}
@@ -0,0 +1,195 @@
+// SPDX-License-Identifier: Apache-2.0
+
+package hipcheck
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"flag"
+	"fmt"
+	"io"
+	"log/slog"
+	"net"
+	"os"
+	"sync"
+	"sync/atomic"
+
+	"google.golang.org/grpc"
+	"google.golang.org/grpc/codes"
+	"google.golang.org/grpc/metadata"
+	"google.golang.org/grpc/status"
+
+	"github.com/mitre/hipcheck/sdk/go/internal/pb"
+)
+
+// PluginServer runs the Hipcheck plugin protocol for a [Plugin].
+//
+// It implements the underlying gRPC protocol, which isn't exposed to the plugin author.
+type PluginServer struct {
+	plugin Plugin
+
+	// The largest query protocol message the plugin sends or accepts
+	maxMessageSize int
+
+	// The max message size agreed with Hipcheck core during configuration
+	negotiatedMaxMessageSize atomic.Int64
+}
+
+// Register makes a plugin server for a plugin.
+//
+// This also installs a default logger which forwards the plugin's log records to Hipcheck core,
+// at the level core asked for.
+func Register(plugin Plugin) *PluginServer {
+	target := plugin.Publisher() + "/" + plugin.Name()
+	slog.SetDefault(slog.New(newLogHandler(os.Stderr, logLevelFromEnv(), target)))
+
+	server := &PluginServer{plugin: plugin, maxMessageSize: GRPCMaxSizeBytes}
+	server.negotiatedMaxMessageSize.Store(GRPCMaxSizeBytes)
+	return server
+}
+
+// MaxMessageSize sets the largest gRPC message, in bytes, the plugin sends or accepts when
+// answering queries. Defaults to 4 MiB.
+//
+// Hipcheck core and the plugin agree on the smaller of their two sizes when the plugin is
+// configured, and large queries and results are split into chunks of that size. Raising it means
+// fewer chunks for plugins with very large results, at the cost of more memory per message on
+// both sides.
+func (s *PluginServer) MaxMessageSize(bytes int) *PluginServer {
+	s.maxMessageSize = max(bytes, GRPCMinSizeBytes)
+	return s
+}
+
+// Listen runs the plugin server on a port, until Hipcheck core stops the plugin.
+func (s *PluginServer) Listen(port uint16) error {
+	listener, err := net.Listen("tcp", fmt.Sprintf("127.0.0.1:%d", port))
+	if err != nil {
+		return fmt.Errorf("failed to start server: %w", err)
+	}
+	server := grpc.NewServer(
+		grpc.MaxRecvMsgSize(s.maxMessageSize),
+		grpc.MaxSendMsgSize(s.maxMessageSize),
+	)
+	pb.RegisterPluginServiceServer(server, &pluginService{server: s})
+	if err := server.Serve(listener); err != nil {
+		return fmt.Errorf("failed to start server: %w", err)
+	}
+	return nil
+}
+
+// Main runs a plugin on the port Hipcheck core gives it with the --port argument, exiting if the
+// plugin server fails.
+func Main(plugin Plugin) {
+	port := flag.Uint("port", 0, "the port to listen on")
+	flag.Parse()
+	if *port > 0xffff {
+		fmt.Fprintf(os.Stderr, "invalid port %d\n", *port)
+		os.Exit(2)
+	}
+	if err := Register(plugin).Listen(uint16(*port)); err != nil {
+		slog.Error(err.Error())
+		os.Exit(1)
+	}
+}
+
+type pluginService struct {
+	pb.UnimplementedPluginServiceServer
+	server *PluginServer
+}
+
+func (p *pluginService) SetConfiguration(_ context.Context, req *pb.SetConfigurationRequest) (*pb.SetConfigurationResponse, error) {
+	if !json.Valid([]byte(req.Configuration)) {
+		return nil, status.Error(codes.Unknown, "invalid JSON in plugin configuration")
+	}
+	s := p.server
+	s.negotiatedMaxMessageSize.Store(int64(negotiateMaxSize(s.maxMessageSize, req.MaxMessageSize)))
+
+	if err := s.plugin.SetConfig(json.RawMessage(req.Configuration)); err != nil {
+		return toConfigResponse(err, s.maxMessageSize), nil
+	}
+	return &pb.SetConfigurationResponse{
+		Status:         pb.ConfigurationStatus_CONFIGURATION_STATUS_NONE,
+		MaxMessageSize: uint64(s.maxMessageSize),
+	}, nil
+}
+
+func (p *pluginService) GetDefaultPolicyExpression(_ context.Context, _ *pb.GetDefaultPolicyExpressionRequest) (*pb.GetDefaultPolicyExpressionResponse, error) {
+	plugin := p.server.plugin
+	policyExpression, err := plugin.DefaultPolicyExpr()
+	if err != nil {
+		return nil, status.Errorf(codes.NotFound, "Error determining default policy expr for %s/%s: %s",
+			plugin.Publisher(), plugin.Name(), err)
+	}
+	return &pb.GetDefaultPolicyExpressionResponse{PolicyExpression: policyExpression}, nil
+}
+
+func (p *pluginService) ExplainDefaultQuery(_ context.Context, _ *pb.ExplainDefaultQueryRequest) (*pb.ExplainDefaultQueryResponse, error) {
+	plugin := p.server.plugin
+	explanation, err := plugin.ExplainDefaultQuery()
+	if err != nil {
+		return nil, status.Errorf(codes.NotFound, "Error explaining default query expr for %s/%s: %s",
+			plugin.Publisher(), plugin.Name(), err)
+	}
+	if explanation == "" {
+		explanation = "No default query explanation provided"
+	}
+	return &pb.ExplainDefaultQueryResponse{Explanation: explanation}, nil
+}
+
+func (p *pluginService) GetQuerySchemas(_ *pb.GetQuerySchemasRequest, stream pb.PluginService_GetQuerySchemasServer) error {
+	for _, schema := range Schemas(p.server.plugin) {
+		inputSchema, err := json.Marshal(schema.InputSchema)
+		if err != nil {
+			return status.Errorf(codes.FailedPrecondition, "Error converting input schema to String: %s", err)
+		}
+		outputSchema, err := json.Marshal(schema.OutputSchema)
+		if err != nil {
+			return status.Errorf(codes.FailedPrecondition, "Error converting output schema to String: %s", err)
+		}
+		err = stream.Send(&pb.GetQuerySchemasResponse{
+			QueryName:    schema.QueryName,
+			KeySchema:    string(inputSchema),
+			OutputSchema: string(outputSchema),
+		})
+		if err != nil {
+			return err
+		}
+	}
+	return nil
+}
+
+func (p *pluginService) InitiateQueryProtocol(stream pb.PluginService_InitiateQueryProtocolServer) error {
+	// Hipcheck core waits for the response headers before sending any queries, and gRPC only
+	// sends them with the first message unless told to
+	if err := stream.SendHeader(metadata.MD{}); err != nil {
+		return err
+	}
+
+	// Sessions answer queries concurrently, but a gRPC stream takes one message at a time
+	var sendMu sync.Mutex
+	send := func(query *pb.Query) error {
+		sendMu.Lock()
+		defer sendMu.Unlock()
+		return stream.Send(&pb.InitiateQueryProtocolResponse{Query: query})
+	}
+
+	maxMessageSize := int(p.server.negotiatedMaxMessageSize.Load())
+	socket := newSessionSocket(stream.Context(), p.server.plugin, send, maxMessageSize)
+	defer socket.close()
+
+	for {
+		req, err := stream.Recv()
+		if errors.Is(err, io.EOF) {
+			return nil
+		}
+		if err != nil {
+			return err
+		}
+		if req.Query == nil {
+			slog.Error("received an empty message from Hipcheck core")
+			continue
+		}
+		socket.dispatch(req.Query)
+	}
+}
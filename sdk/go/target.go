@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: Apache-2.0
+
+package hipcheck
+
+import (
+	"fmt"
+	"strings"
+)
+
+// QueryTarget identifies the plugin and query endpoint a query is sent to. The query is empty
+// for a plugin's default query.
+type QueryTarget struct {
+	Publisher string
+	Plugin    string
+	Query     string
+}
+
+// ParseQueryTarget parses a query target of the form "publisher/plugin[/query]".
+func ParseQueryTarget(target string) (QueryTarget, error) {
+	parts := strings.Split(target, "/")
+	switch len(parts) {
+	case 2:
+		return QueryTarget{Publisher: parts[0], Plugin: parts[1]}, nil
+	case 3:
+		return QueryTarget{Publisher: parts[0], Plugin: parts[1], Query: parts[2]}, nil
+	default:
+		return QueryTarget{}, fmt.Errorf("%w: '%s'", ErrInvalidQueryTarget, target)
+	}
+}
+
+func (t QueryTarget) String() string {
+	if t.Query == "" {
+		return t.Publisher + "/" + t.Plugin
+	}
+	return t.Publisher + "/" + t.Plugin + "/" + t.Query
+}
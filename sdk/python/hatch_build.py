@@ -0,0 +1,48 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""Generates the plugin protocol bindings from `hipcheck-common`'s `hipcheck.proto`, so the SDK
+always speaks the protocol Hipcheck core is built with.
+"""
+
+from __future__ import annotations
+
+import shutil
+from pathlib import Path
+
+from hatchling.builders.hooks.plugin.interface import BuildHookInterface
+
+ROOT = Path(__file__).parent
+# In a checkout the proto comes from `hipcheck-common`; an sdist carries its own copy
+WORKSPACE_PROTO = ROOT.parent.parent / "hipcheck-common" / "proto" / "hipcheck" / "v1"
+SDIST_PROTO = ROOT / "proto"
+OUT = ROOT / "hipcheck_sdk" / "proto"
+
+
+class ProtoBuildHook(BuildHookInterface):
+    def initialize(self, version, build_data):
+        from grpc_tools import protoc
+
+        if (WORKSPACE_PROTO / "hipcheck.proto").exists():
+            SDIST_PROTO.mkdir(exist_ok=True)
+            shutil.copy(WORKSPACE_PROTO / "hipcheck.proto", SDIST_PROTO / "hipcheck.proto")
+
+        status = protoc.main(
+            [
+                "grpc_tools.protoc",
+                f"-I{SDIST_PROTO}",
+                f"--python_out={OUT}",
+                f"--pyi_out={OUT}",
+                f"--grpc_python_out={OUT}",
+                str(SDIST_PROTO / "hipcheck.proto"),
+            ]
+        )
+        if status != 0:
+            raise RuntimeError("failed to generate the plugin protocol bindings")
+
+        # protoc writes an absolute import, which only works with the output on `sys.path`
+        grpc_file = OUT / "hipcheck_pb2_grpc.py"
+        grpc_file.write_text(
+            grpc_file.read_text().replace(
+                "import hipcheck_pb2 as", "from . import hipcheck_pb2 as"
+            )
+        )
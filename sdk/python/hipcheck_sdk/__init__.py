@@ -0,0 +1,70 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""The Hipcheck plugin SDK for Python.
+
+A plugin subclasses `Plugin`, marks its query endpoints with `@query`, and runs with `main()`:
+
+    from hipcheck_sdk import Plugin, PluginEngine, main, query
+
+    class ExamplePlugin(Plugin):
+        publisher = "example"
+        name = "example"
+
+        @query(default=True)
+        async def count(self, engine: PluginEngine, key: str) -> int:
+            return len(key)
+
+    if __name__ == "__main__":
+        main(ExamplePlugin())
+"""
+
+from .cache import InvalidCacheScope, PluginCache
+from .engine import MockResponses, PluginEngine
+from .env import cache_dir, scratch_dir, secret
+from .error import (
+    ConfigError,
+    ConfigurationStatus,
+    HipcheckError,
+    InvalidConfigValue,
+    InvalidQueryTarget,
+    MissingRequiredConfig,
+    ProtocolError,
+    QueryCancelled,
+    QueryFailed,
+    UnknownQuery,
+    UnrecognizedConfig,
+)
+from .plugin import Plugin, QueryInfo, QuerySchema, main, query
+from .query import QueryTarget
+from .report import Concern, FragmentFormat, ReportFragment, Severity
+
+__all__ = [
+    "Concern",
+    "ConfigError",
+    "ConfigurationStatus",
+    "FragmentFormat",
+    "HipcheckError",
+    "InvalidCacheScope",
+    "InvalidConfigValue",
+    "InvalidQueryTarget",
+    "MissingRequiredConfig",
+    "MockResponses",
+    "Plugin",
+    "PluginCache",
+    "PluginEngine",
+    "ProtocolError",
+    "QueryCancelled",
+    "QueryFailed",
+    "QueryInfo",
+    "QuerySchema",
+    "QueryTarget",
+    "ReportFragment",
+    "Severity",
+    "UnknownQuery",
+    "UnrecognizedConfig",
+    "cache_dir",
+    "main",
+    "query",
+    "scratch_dir",
+    "secret",
+]
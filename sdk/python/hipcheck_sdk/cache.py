@@ -0,0 +1,128 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""A persistent key-value cache for a plugin, scoped by target HEAD commit.
+
+This mirrors the Rust SDK's `cache` module, and keeps entries in the same layout.
+"""
+
+from __future__ import annotations
+
+import json
+import logging
+import os
+import re
+from pathlib import Path
+from typing import Any
+
+from pydantic_core import to_jsonable_python
+
+from .env import cache_dir
+from .error import HipcheckError
+
+logger = logging.getLogger(__name__)
+
+_VALID_HEAD = re.compile(r"[A-Za-z0-9_-]+")
+
+_FNV_OFFSET_BASIS = 0xCBF29CE484222325
+_FNV_PRIME = 0x100000001B3
+
+
+class InvalidCacheScope(HipcheckError):
+    """The HEAD commit a cache entry was scoped by can't be used as a directory name."""
+
+    def __init__(self, head: str):
+        super().__init__(f"invalid plugin cache scope '{head}'; expected a commit hash")
+        self.head = head
+
+
+class PluginCache:
+    """A persistent key-value cache for a plugin, scoped by target HEAD commit.
+
+    Hipcheck core gives each version of a plugin its own cache directory, so a new version never
+    reads what an older one stored. Within it, entries are scoped by the HEAD commit of the target
+    they were computed for, since what's true of one commit may not be of the next. Each entry is
+    kept in its own file, along with its key, at `<dir>/<head>/<hash of key>.json`.
+
+    Keys and values may be anything pydantic can turn into JSON. A cache Hipcheck core didn't give
+    a directory for, as when the plugin is run on its own or in unit tests, keeps nothing.
+    """
+
+    def __init__(self, dir: str | os.PathLike[str] | None):
+        self._dir = Path(dir) if dir is not None else None
+
+    @classmethod
+    def disabled(cls) -> PluginCache:
+        """Make a cache which keeps nothing."""
+        return cls(None)
+
+    @classmethod
+    def from_env(cls) -> PluginCache:
+        """Make the cache in the directory Hipcheck core gave this plugin, if it gave one."""
+        return cls(cache_dir())
+
+    @property
+    def is_enabled(self) -> bool:
+        """Whether the cache keeps entries."""
+        return self._dir is not None
+
+    def get(self, head: str, key: Any) -> Any | None:
+        """Get the value cached for a key when analyzing the target at commit `head`, or `None` if
+        there isn't one.
+        """
+        key = to_jsonable_python(key)
+        path = self._entry_path(head, key)
+        if path is None:
+            return None
+
+        try:
+            contents = path.read_text(encoding="utf-8")
+        except FileNotFoundError:
+            return None
+        try:
+            entry = json.loads(contents)
+        except ValueError:
+            logger.debug("ignoring unreadable cache entry '%s'", path)
+            return None
+        # Keys whose hashes collide share a file, so only the key last stored is found
+        if not isinstance(entry, dict) or entry.get("key") != key:
+            return None
+        return entry.get("value")
+
+    def insert(self, head: str, key: Any, value: Any) -> None:
+        """Cache a value for a key when analyzing the target at commit `head`, replacing any value
+        cached before.
+        """
+        key = to_jsonable_python(key)
+        path = self._entry_path(head, key)
+        if path is None:
+            return
+        entry = _to_json({"key": key, "value": to_jsonable_python(value)})
+
+        path.parent.mkdir(parents=True, exist_ok=True)
+        # Write to a temporary file first, so other sessions never read a partial entry
+        temp = path.with_suffix(f".{os.getpid()}.tmp")
+        temp.write_text(entry, encoding="utf-8")
+        os.replace(temp, path)
+
+    def _entry_path(self, head: str, key: Any) -> Path | None:
+        if self._dir is None:
+            return None
+        # The HEAD is a directory name, so it mustn't be able to name anything outside the cache
+        if not _VALID_HEAD.fullmatch(head):
+            raise InvalidCacheScope(head)
+        return entry_file(self._dir, head, key)
+
+
+def entry_file(dir: Path, head: str, key: Any) -> Path:
+    """Get the file an entry with a key is kept in, named after the FNV-1a hash of the key's JSON,
+    as the Rust SDK names it.
+    """
+    hash = _FNV_OFFSET_BASIS
+    for byte in _to_json(key).encode():
+        hash = ((hash ^ byte) * _FNV_PRIME) & 0xFFFFFFFFFFFFFFFF
+    return dir / head / f"{hash:016x}.json"
+
+
+def _to_json(value: Any) -> str:
+    # Matches how serde_json writes a `Value`, with sorted object keys and no whitespace
+    return json.dumps(value, separators=(",", ":"), ensure_ascii=False, sort_keys=True)
@@ -0,0 +1,229 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""Splitting query protocol messages into chunks, and putting them back together.
+
+This mirrors `hipcheck_common::chunk`, which Hipcheck core and the Rust SDK use, following RFD
+0009. Messages are limited in size, so the `key`, `output`, `concern`, and `fragment` fields of a
+large query are spread over several messages, in that order. Every chunk but the last is in the
+"in progress" state. When a string has to be split between two chunks, the first chunk sets
+`split`, and the start of the next chunk's field is appended to it.
+
+Sizes are counted in bytes of UTF-8, and strings are never split within a character.
+"""
+
+from __future__ import annotations
+
+from dataclasses import dataclass, field, replace
+from enum import IntEnum
+from typing import Iterator
+
+from .error import ProtocolError, QueryFailed
+
+#: Max size of a single gRPC message (4 MiB), used unless a larger size is negotiated.
+GRPC_MAX_SIZE_BYTES = 1024 * 1024 * 4
+
+#: The smallest max message size either side may negotiate (64 KiB).
+GRPC_MIN_SIZE_BYTES = 1024 * 64
+
+#: Room left in each message for the fields which aren't chunked (one KiB).
+GRPC_MESSAGE_OVERHEAD = 1024
+
+#: The fields of a query message which are chunked, in the order they're chunked.
+CHUNKED_FIELDS = ("key", "output", "concern", "fragment")
+
+
+class QueryState(IntEnum):
+    """The state of a query message, matching the protocol's `QueryState`."""
+
+    UNSPECIFIED = 0
+    SUBMIT_COMPLETE = 1
+    REPLY_IN_PROGRESS = 2
+    REPLY_COMPLETE = 3
+    SUBMIT_IN_PROGRESS = 4
+
+    @property
+    def in_progress(self) -> bool:
+        return self in (QueryState.SUBMIT_IN_PROGRESS, QueryState.REPLY_IN_PROGRESS)
+
+    @property
+    def is_submit(self) -> bool:
+        return self in (QueryState.SUBMIT_IN_PROGRESS, QueryState.SUBMIT_COMPLETE)
+
+    @property
+    def is_reply(self) -> bool:
+        return self in (QueryState.REPLY_IN_PROGRESS, QueryState.REPLY_COMPLETE)
+
+
+@dataclass
+class QueryMessage:
+    """A single message of the query protocol, with its JSON fields still as text."""
+
+    id: int
+    state: QueryState
+    publisher_name: str = ""
+    plugin_name: str = ""
+    query_name: str = ""
+    key: list[str] = field(default_factory=list)
+    output: list[str] = field(default_factory=list)
+    concern: list[str] = field(default_factory=list)
+    fragment: list[str] = field(default_factory=list)
+    split: bool = False
+    error: str = ""
+    #: How far along the query is, as `(completed, total)`.
+    progress: tuple[int, int] | None = None
+    #: Whether Hipcheck core cancelled the query.
+    cancel: bool = False
+
+    def copy(self) -> QueryMessage:
+        return replace(self, **{name: list(getattr(self, name)) for name in CHUNKED_FIELDS})
+
+    def has_chunked_data(self) -> bool:
+        return any(getattr(self, name) for name in CHUNKED_FIELDS)
+
+
+def negotiate_max_size(ours: int, theirs: int) -> int:
+    """Agree on the max size of a query protocol message, given our own max size and the max
+    size the other side sent while configuring the plugin.
+
+    The other side sends zero if it predates negotiation, in which case it can only be assumed
+    to accept messages of the default size.
+    """
+    if theirs == 0:
+        theirs = GRPC_MAX_SIZE_BYTES
+    return max(min(ours, theirs), GRPC_MIN_SIZE_BYTES)
+
+
+def chunk(msg: QueryMessage, max_message_size: int) -> Iterator[QueryMessage]:
+    """Split a message into chunks which fit a negotiated max message size."""
+    return chunk_with_size(msg, max(max_message_size - GRPC_MESSAGE_OVERHEAD, 1))
+
+
+def chunk_with_size(msg: QueryMessage, max_est_size: int) -> Iterator[QueryMessage]:
+    """Split a message into chunks holding at most `max_est_size` bytes of chunked fields.
+
+    Chunks are produced as they're taken, so a sender which sends each chunk before taking the
+    next never holds a second copy of the whole message.
+    """
+    if msg.state == QueryState.UNSPECIFIED:
+        raise ProtocolError("msg in Unspecified query state")
+    if msg.state.is_submit:
+        in_progress, complete = QueryState.SUBMIT_IN_PROGRESS, QueryState.SUBMIT_COMPLETE
+    else:
+        in_progress, complete = QueryState.REPLY_IN_PROGRESS, QueryState.REPLY_COMPLETE
+
+    source = msg.copy()
+    while source.has_chunked_data():
+        remaining = max_est_size
+        made_progress = False
+        out = QueryMessage(
+            id=msg.id,
+            state=in_progress,
+            publisher_name=msg.publisher_name,
+            plugin_name=msg.plugin_name,
+            query_name=msg.query_name,
+        )
+
+        for name in CHUNKED_FIELDS:
+            split, remaining, progressed = _drain(
+                getattr(source, name), getattr(out, name), remaining
+            )
+            made_progress = made_progress or progressed
+            if split:
+                out.split = True
+                break
+            if remaining == 0:
+                break
+
+        # Check whether we did anything, to avoid looping forever
+        if not made_progress:
+            raise ProtocolError("Message could not be chunked")
+
+        if not source.has_chunked_data():
+            out.state = complete
+        yield out
+
+
+def _drain(source: list[str], sink: list[str], remaining: int) -> tuple[bool, int, bool]:
+    """Move as many strings as fit in `remaining` bytes from `source` to `sink`.
+
+    Returns whether a string had to be split, the bytes still remaining, and whether anything
+    was moved.
+    """
+    made_progress = False
+    while source:
+        data = source.pop(0).encode()
+        if len(data) <= remaining:
+            made_progress = True
+            remaining -= len(data)
+            sink.append(data.decode())
+            continue
+
+        # Walk back to the start of the character the limit falls in
+        end = remaining
+        while end > 0 and (data[end] & 0xC0) == 0x80:
+            end -= 1
+        source.insert(0, data[end:].decode())
+        if end == 0:
+            return False, remaining, made_progress
+        sink.append(data[:end].decode())
+        return True, remaining - end, True
+    return False, remaining, made_progress
+
+
+def _last_field_with_content(msg: QueryMessage) -> str:
+    for name in reversed(CHUNKED_FIELDS):
+        if getattr(msg, name):
+            return name
+    return "key"
+
+
+class QuerySynthesizer:
+    """Puts the chunks of a message back together, one chunk at a time."""
+
+    def __init__(self) -> None:
+        self._raw: QueryMessage | None = None
+        # The field the last chunk split a string in, if it did
+        self._split_field: str | None = None
+
+    def add(self, chunk: QueryMessage) -> QueryMessage | None:
+        """Add the next chunk of a message, returning the whole message once it's complete."""
+        if chunk.state == QueryState.UNSPECIFIED:
+            # A query which failed is answered in the unspecified state, with why it failed
+            self._raw = None
+            if chunk.error:
+                raise QueryFailed(chunk.error)
+            raise ProtocolError("unknown error; query is in an unspecified state")
+
+        raw = self._raw
+        chunk = chunk.copy()
+        # Found before this chunk's continuation is taken from it
+        next_split_field = _last_field_with_content(chunk) if chunk.split else None
+        if raw is None:
+            raw = chunk
+        else:
+            if raw.state.is_submit and chunk.state.is_reply:
+                raise ProtocolError(
+                    "plugin sent QueryReply when server was expecting a submit chunk"
+                )
+            if raw.state.is_reply and chunk.state.is_submit:
+                raise ProtocolError(
+                    "plugin sent QuerySubmit when server was expecting a reply chunk"
+                )
+
+            # The first string of this chunk continues the string the last chunk split
+            if self._split_field is not None:
+                continued = getattr(chunk, self._split_field)
+                getattr(raw, self._split_field)[-1] += continued.pop(0)
+            for name in CHUNKED_FIELDS:
+                getattr(raw, name).extend(getattr(chunk, name))
+            raw.state = chunk.state
+
+        self._split_field = next_split_field
+
+        if raw.state.in_progress:
+            self._raw = raw
+            return None
+
+        self._raw = None
+        raw.split = False
+        return raw
@@ -0,0 +1,344 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""Query sessions, through which a plugin answers a query and queries other plugins.
+
+This mirrors the Rust SDK's `engine` module.
+"""
+
+from __future__ import annotations
+
+import asyncio
+import logging
+from typing import TYPE_CHECKING, Any, Awaitable, Callable, Iterable
+
+from pydantic_core import to_jsonable_python
+
+from .cache import PluginCache
+from .chunk import GRPC_MAX_SIZE_BYTES, QueryMessage, QueryState, QuerySynthesizer, chunk
+from .error import HipcheckError, ProtocolError, QueryCancelled, UnknownQuery, error_message
+from .query import Query, QueryDirection, QueryTarget, to_json
+from .report import Concern, ReportFragment
+
+if TYPE_CHECKING:
+    from .plugin import Plugin
+
+logger = logging.getLogger(__name__)
+
+#: Sends a message of the query protocol to Hipcheck core.
+Send = Callable[[QueryMessage], Awaitable[None]]
+
+
+class MockResponses:
+    """Answers to the queries a plugin makes of other plugins, for unit testing a plugin's queries
+    without Hipcheck core.
+    """
+
+    def __init__(self) -> None:
+        self._responses: dict[tuple[QueryTarget, str], Any] = {}
+
+    def insert(self, target: str | QueryTarget, key: Any, response: Any) -> MockResponses:
+        """Answer queries to `target` with `key` with `response`. If `response` is an exception,
+        the query raises it instead.
+        """
+        self._responses[(QueryTarget.parse(target), to_json(to_jsonable_python(key)))] = response
+        return self
+
+    def get(self, target: QueryTarget, key: Any) -> Any:
+        try:
+            response = self._responses[(target, to_json(key))]
+        except KeyError:
+            raise UnknownQuery(str(target)) from None
+        if isinstance(response, BaseException):
+            raise response
+        return response
+
+
+class PluginEngine:
+    """Manages a single query session.
+
+    An engine is passed to each query a plugin runs, letting the query ask other Hipcheck plugins
+    for information, and record concerns and report fragments for Hipcheck's final report.
+    """
+
+    def __init__(
+        self,
+        session_id: int = 0,
+        send: Send | None = None,
+        max_message_size: int = GRPC_MAX_SIZE_BYTES,
+        mock_responses: MockResponses | None = None,
+    ):
+        self._id = session_id
+        self._send_raw = send
+        self._rx: asyncio.Queue[QueryMessage | None] = asyncio.Queue()
+        self._max_message_size = max_message_size
+        self._mock_responses = mock_responses
+        self._concerns: list[Any] = []
+        self._fragments: list[Any] = []
+        # The progress last reported to Hipcheck core, as (completed, total)
+        self._progress: tuple[int, int] | None = None
+        # Set when Hipcheck core cancels the query this session is answering
+        self._cancelled = asyncio.Event()
+
+    @classmethod
+    def mock(cls, mock_responses: MockResponses | None = None) -> PluginEngine:
+        """Make an engine for unit tests, which answers queries from `mock_responses` instead of
+        asking Hipcheck core.
+        """
+        return cls(mock_responses=mock_responses or MockResponses())
+
+    @property
+    def id(self) -> int:
+        return self._id
+
+    @property
+    def cache(self) -> PluginCache:
+        """The cache this plugin keeps data in between runs of Hipcheck, for memoizing expensive
+        work like API calls. When the plugin wasn't started by Hipcheck core, as in unit tests, the
+        cache keeps nothing.
+        """
+        return PluginCache.from_env()
+
+    async def query(self, target: str | QueryTarget, key: Any) -> Any:
+        """Query another Hipcheck plugin, returning its answer as JSON data.
+
+        `target` is usually a string of the form `"publisher/plugin[/query]"`, where the query is
+        left out for the plugin's default query. `key` may be anything pydantic can turn into
+        JSON.
+        """
+        (output,) = await self.batch_query(target, [key])
+        return output
+
+    async def batch_query(self, target: str | QueryTarget, keys: Iterable[Any]) -> list[Any]:
+        """Query another Hipcheck plugin with several keys in one go, returning its answers in the
+        same order.
+        """
+        # Nothing needs the answer to a cancelled query, so there's no point asking for more
+        if self.is_cancelled:
+            raise QueryCancelled()
+        target = QueryTarget.parse(target)
+        keys = [to_jsonable_python(key) for key in keys]
+
+        # When unit testing, the answers come from the mock responses
+        if self._mock_responses is not None:
+            return [self._mock_responses.get(target, key) for key in keys]
+
+        await self._send(
+            Query(
+                id=self._id,
+                direction=QueryDirection.REQUEST,
+                publisher=target.publisher,
+                plugin=target.plugin,
+                query=target.query,
+                key=keys,
+            )
+        )
+        response = await self._recv()
+        if response.direction != QueryDirection.RESPONSE:
+            raise ProtocolError("received a query submission when expecting a reply")
+        return response.output
+
+    @property
+    def is_cancelled(self) -> bool:
+        """Whether Hipcheck core cancelled the query being answered, as when it timed out, so its
+        answer is no longer needed. Queries which do a lot of work can check this to stop early.
+
+        Queries made to other plugins after the query is cancelled raise `QueryCancelled`.
+        """
+        return self._cancelled.is_set()
+
+    async def cancelled(self) -> None:
+        """Wait until Hipcheck core cancels the query being answered. Queries waiting on something
+        slow, like a subprocess or an API call, can race it against this to give up when the answer
+        is no longer needed.
+        """
+        await self._cancelled.wait()
+
+    def cancel(self) -> None:
+        """Cancel the query being answered, as Hipcheck core does when it no longer needs the
+        answer.
+        """
+        self._cancelled.set()
+
+    def record_concern(self, concern: str | Concern) -> None:
+        """Record a concern to emit in the final Hipcheck report, either a `Concern` or just its
+        message.
+        """
+        self._concerns.append(concern if isinstance(concern, Concern) else Concern(concern))
+
+    @property
+    def concerns(self) -> list[Concern]:
+        """The concerns recorded so far."""
+        return self._concerns
+
+    def record_report_fragment(self, fragment: ReportFragment) -> None:
+        """Record a section to add to the final Hipcheck report, for results which aren't a pass
+        or fail.
+        """
+        self._fragments.append(fragment.to_json())
+
+    @property
+    def report_fragments(self) -> list[Any]:
+        """The report fragments recorded so far, as JSON data."""
+        return self._fragments
+
+    async def report_progress(self, completed: int, total: int) -> None:
+        """Report how far along the current query is, as `completed` out of `total` units of work
+        (e.g. commits processed), so Hipcheck can show the user how far along the analysis is.
+
+        Progress is only sent to Hipcheck core when the whole percentage done changes, so this is
+        cheap to call for every unit of work.
+        """
+        if total <= 0:
+            return
+        completed = max(0, min(completed, total))
+        if self._progress is not None:
+            last_completed, last_total = self._progress
+            if last_total == total and last_completed * 100 // total == completed * 100 // total:
+                return
+        self._progress = (completed, total)
+
+        # There's no Hipcheck core to report to when unit testing
+        if self._send_raw is None:
+            return
+        await self._send_raw(
+            QueryMessage(id=self._id, state=QueryState.UNSPECIFIED, progress=(completed, total))
+        )
+
+    @property
+    def progress(self) -> tuple[int, int] | None:
+        """The progress last reported, as `(completed, total)`."""
+        return self._progress
+
+    def _take_concerns(self) -> list[Any]:
+        concerns = [concern.to_json() for concern in self._concerns]
+        self._concerns = []
+        return concerns
+
+    def _take_fragments(self) -> list[Any]:
+        fragments = self._fragments
+        self._fragments = []
+        return fragments
+
+    async def _send(self, query: Query) -> None:
+        if self._send_raw is None:
+            raise ProtocolError("the engine isn't connected to Hipcheck core")
+        query.id = self._id
+        # Chunks are taken as they're sent, so the whole message is never copied at once
+        for msg in chunk(query.to_message(), self._max_message_size):
+            await self._send_raw(msg)
+
+    async def _recv(self) -> Query:
+        synth = QuerySynthesizer()
+        while True:
+            msg = await self._next_message()
+            if msg is None:
+                raise ProtocolError("the session was closed by Hipcheck core")
+            whole = synth.add(msg)
+            if whole is not None:
+                return Query.from_message(whole)
+
+    async def _next_message(self) -> QueryMessage | None:
+        # Hipcheck core won't send the rest of a cancelled query, or answer the plugin's queries
+        get = asyncio.ensure_future(self._rx.get())
+        cancelled = asyncio.ensure_future(self._cancelled.wait())
+        try:
+            done, _ = await asyncio.wait({get, cancelled}, return_when=asyncio.FIRST_COMPLETED)
+        finally:
+            get.cancel()
+            cancelled.cancel()
+        if get not in done:
+            raise QueryCancelled()
+        return get.result()
+
+    async def _handle_session(self, plugin: Plugin) -> None:
+        try:
+            await self._handle_session_fallible(plugin)
+        except QueryCancelled:
+            # Hipcheck core isn't waiting for an answer anymore
+            logger.debug("query %d was cancelled by Hipcheck core", self._id)
+        except Exception as e:
+            logger.error("%s", error_message(e))
+            # A query which failed is answered in the unspecified state, with why it failed
+            msg = QueryMessage(
+                id=self._id,
+                state=QueryState.UNSPECIFIED,
+                publisher_name=plugin.publisher,
+                plugin_name=plugin.name,
+                concern=[to_json(c) for c in self._take_concerns()],
+                error=error_message(e),
+            )
+            try:
+                await self._send_raw(msg)
+            except Exception:
+                logger.error("Failed to send message to Hipcheck core, analysis will hang.")
+
+    async def _handle_session_fallible(self, plugin: Plugin) -> None:
+        query = await self._recv()
+        if query.direction == QueryDirection.RESPONSE:
+            raise ProtocolError("received a query reply when expecting a submission")
+        # Per RFD 0009, there should only be one query key per query
+        if len(query.key) != 1:
+            raise ProtocolError(f"expected one query key, got {len(query.key)}")
+
+        output = await plugin._run_query(self, query.query, query.key[0])
+
+        await self._send(
+            Query(
+                id=self._id,
+                direction=QueryDirection.RESPONSE,
+                publisher=plugin.publisher,
+                plugin=plugin.name,
+                query=query.query,
+                output=[to_jsonable_python(output)],
+                concerns=self._take_concerns(),
+                fragments=self._take_fragments(),
+            )
+        )
+
+
+class SessionSocket:
+    """Routes the messages Hipcheck core sends to the sessions they belong to, starting a new
+    session for each new query.
+    """
+
+    def __init__(self, plugin: Plugin, send: Send, max_message_size: int):
+        self._plugin = plugin
+        self._send = send
+        self._max_message_size = max_message_size
+        self._sessions: dict[int, PluginEngine] = {}
+        self._tasks: set[asyncio.Task[None]] = set()
+
+    def dispatch(self, msg: QueryMessage) -> None:
+        engine = self._sessions.get(msg.id)
+        if msg.cancel:
+            # Hipcheck core may cancel a query the plugin has already answered
+            if engine is not None:
+                logger.debug("cancelling session %d", msg.id)
+                engine.cancel()
+            return
+        if engine is not None:
+            engine._rx.put_nowait(msg)
+            return
+        if not msg.state.is_submit:
+            logger.error(
+                "%s", HipcheckError(f"received a reply for session {msg.id}, which isn't open")
+            )
+            return
+
+        logger.debug("creating new session %d", msg.id)
+        engine = PluginEngine(msg.id, self._send, self._max_message_size)
+        engine._rx.put_nowait(msg)
+        self._sessions[msg.id] = engine
+        task = asyncio.create_task(engine._handle_session(self._plugin))
+        self._tasks.add(task)
+        task.add_done_callback(lambda t, id=msg.id: self._finish(id, t))
+
+    def _finish(self, session_id: int, task: asyncio.Task[None]) -> None:
+        self._tasks.discard(task)
+        self._sessions.pop(session_id, None)
+
+    def close(self) -> None:
+        """Tell every open session Hipcheck core has gone away, and won't need their answers."""
+        for engine in self._sessions.values():
+            engine.cancel()
+            engine._rx.put_nowait(None)
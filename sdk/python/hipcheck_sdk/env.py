@@ -0,0 +1,52 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""What Hipcheck core tells a plugin through its environment when it starts the plugin.
+
+This mirrors `hipcheck_common`'s `plugin_scratch`, `plugin_secret`, and `plugin_cache` modules.
+"""
+
+from __future__ import annotations
+
+import os
+from pathlib import Path
+
+#: The environment variable core uses to give a plugin its scratch directory.
+SCRATCH_DIR_ENV_VAR = "HC_PLUGIN_SCRATCH_DIR"
+
+#: The environment variable core uses to give a plugin its cache directory.
+CACHE_DIR_ENV_VAR = "HC_PLUGIN_CACHE_DIR"
+
+#: The prefix of the environment variables core passes secrets to a plugin in.
+SECRET_ENV_VAR_PREFIX = "HC_SECRET_"
+
+
+def scratch_dir() -> Path | None:
+    """Get the directory Hipcheck core set aside for this plugin's temporary files, if it set one
+    aside. Core removes it when the run ends.
+    """
+    return _dir_from_env(SCRATCH_DIR_ENV_VAR)
+
+
+def cache_dir() -> Path | None:
+    """Get the directory Hipcheck core set aside for this plugin to keep data in between runs, if
+    it set one aside.
+    """
+    return _dir_from_env(CACHE_DIR_ENV_VAR)
+
+
+def secret_env_var(name: str) -> str:
+    """Get the environment variable a secret is passed in, e.g. `HC_SECRET_GITHUB_TOKEN` for
+    `github-token`.
+    """
+    name = "".join(c.upper() if c.isascii() and c.isalnum() else "_" for c in name)
+    return f"{SECRET_ENV_VAR_PREFIX}{name}"
+
+
+def secret(name: str) -> str | None:
+    """Get a secret the user configured for this plugin, like an API token, if they configured it."""
+    return os.environ.get(secret_env_var(name)) or None
+
+
+def _dir_from_env(var: str) -> Path | None:
+    dir = os.environ.get(var)
+    return Path(dir) if dir else None
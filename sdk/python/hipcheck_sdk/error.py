@@ -0,0 +1,117 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""Errors that can occur in a Hipcheck plugin."""
+
+from __future__ import annotations
+
+from enum import IntEnum
+from typing import Sequence
+
+
+class HipcheckError(Exception):
+    """An error in a Hipcheck plugin, reported to Hipcheck core as the query's error."""
+
+
+class ProtocolError(HipcheckError):
+    """Hipcheck core or the plugin sent a message the query protocol doesn't allow."""
+
+
+class QueryFailed(HipcheckError):
+    """A query the plugin made to another plugin failed, for the reason Hipcheck core gave."""
+
+
+class QueryCancelled(HipcheckError):
+    """Hipcheck core cancelled the query, and no longer needs its answer."""
+
+    def __init__(self) -> None:
+        super().__init__("query was cancelled by Hipcheck core")
+
+
+class UnknownQuery(HipcheckError):
+    """The plugin received a query for an endpoint it doesn't have."""
+
+    def __init__(self, name: str):
+        super().__init__(f"could not determine which plugin query to run for '{name}'")
+        self.name = name
+
+
+class InvalidQueryTarget(HipcheckError):
+    """A query target wasn't of the form `publisher/plugin[/query]`."""
+
+    def __init__(self, target: str):
+        super().__init__(f"invalid format for QueryTarget '{target}'")
+        self.target = target
+
+
+class ConfigurationStatus(IntEnum):
+    """The status of configuring a plugin, as sent to Hipcheck core."""
+
+    UNSPECIFIED = 0
+    NONE = 1
+    MISSING_REQUIRED_CONFIGURATION = 2
+    UNRECOGNIZED_CONFIGURATION = 3
+    INVALID_CONFIGURATION_VALUE = 4
+
+
+class ConfigError(HipcheckError):
+    """The configuration the user gave the plugin in their policy file is wrong."""
+
+    status = ConfigurationStatus.UNSPECIFIED
+
+    def __init__(self, message: str):
+        super().__init__(message)
+        self.message = message
+
+
+class InvalidConfigValue(ConfigError):
+    """The config key was valid, but the associated value was invalid."""
+
+    status = ConfigurationStatus.INVALID_CONFIGURATION_VALUE
+
+    def __init__(self, field_name: str, value: str, reason: str):
+        super().__init__(f"invalid value '{value}' for '{field_name}', reason: '{reason}'")
+        self.field_name = field_name
+        self.value = value
+        self.reason = reason
+
+
+class MissingRequiredConfig(ConfigError):
+    """The config was missing an expected field."""
+
+    status = ConfigurationStatus.MISSING_REQUIRED_CONFIGURATION
+
+    def __init__(self, field_name: str, field_type: str, possible_values: Sequence[str] = ()):
+        message = f"missing required config item '{field_name}' of type '{field_type}'"
+        if possible_values:
+            message += "; possible values: " + ", ".join(possible_values)
+        super().__init__(message)
+        self.field_name = field_name
+        self.field_type = field_type
+        self.possible_values = list(possible_values)
+
+
+class UnrecognizedConfig(ConfigError):
+    """The config included an unrecognized field."""
+
+    status = ConfigurationStatus.UNRECOGNIZED_CONFIGURATION
+
+    def __init__(
+        self, field_name: str, field_value: str, possible_confusables: Sequence[str] = ()
+    ):
+        message = f"unrecognized field '{field_name}' with value '{field_value}'"
+        if possible_confusables:
+            message += "; possible field names: " + ", ".join(possible_confusables)
+        super().__init__(message)
+        self.field_name = field_name
+        self.field_value = field_value
+        self.possible_confusables = list(possible_confusables)
+
+
+def error_message(error: BaseException) -> str:
+    """Describe an error for Hipcheck core to show the user, including the errors which caused it."""
+    message = str(error) or type(error).__name__
+    cause = error.__cause__ or error.__context__
+    while cause is not None:
+        message += ": " + (str(cause) or type(cause).__name__)
+        cause = cause.__cause__ or cause.__context__
+    return message
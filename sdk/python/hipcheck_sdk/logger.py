@@ -0,0 +1,69 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""Forwarding a plugin's log records to Hipcheck core.
+
+Hipcheck core sets `HC_PLUGIN_LOG_LEVEL` to the level it's logging at, and reads lines of the form
+`hc-log<TAB>LEVEL<TAB>target<TAB>message` from the plugin's standard error, as described in
+`hipcheck_common::plugin_log`.
+"""
+
+from __future__ import annotations
+
+import logging
+import os
+import sys
+
+#: The environment variable core uses to tell a plugin what level to log at.
+LOG_LEVEL_ENV_VAR = "HC_PLUGIN_LOG_LEVEL"
+
+# Python has no trace level, so anything finer than debug is sent as trace
+_TRACE = logging.DEBUG - 5
+
+_LEVELS = {
+    "off": logging.CRITICAL + 10,
+    "error": logging.ERROR,
+    "warn": logging.WARNING,
+    "info": logging.INFO,
+    "debug": logging.DEBUG,
+    "trace": _TRACE,
+}
+
+
+def level_from_env() -> int:
+    """Get the level core asked the plugin to log at, defaulting to errors only like core does."""
+    return _LEVELS.get(os.environ.get(LOG_LEVEL_ENV_VAR, "").lower(), logging.ERROR)
+
+
+def format_line(level: int, target: str, message: str) -> str:
+    """Format a log record as a single line to be parsed by core."""
+    if level >= logging.ERROR:
+        name = "ERROR"
+    elif level >= logging.WARNING:
+        name = "WARN"
+    elif level >= logging.INFO:
+        name = "INFO"
+    elif level >= logging.DEBUG:
+        name = "DEBUG"
+    else:
+        name = "TRACE"
+    message = message.replace("\n", "\\n")
+    return f"hc-log\t{name}\t{target}\t{message}"
+
+
+class HipcheckLogHandler(logging.Handler):
+    """A log handler writing records to standard error for Hipcheck core to forward."""
+
+    def emit(self, record: logging.LogRecord) -> None:
+        try:
+            line = format_line(record.levelno, record.name, self.format(record))
+            sys.stderr.write(line + "\n")
+            sys.stderr.flush()
+        except Exception:
+            self.handleError(record)
+
+
+def init() -> None:
+    """Send the plugin's log records at or above the level core asked for to Hipcheck core."""
+    root = logging.getLogger()
+    root.addHandler(HipcheckLogHandler())
+    root.setLevel(level_from_env())
@@ -0,0 +1,177 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""Defining a plugin and its query endpoints.
+
+This mirrors the Rust SDK's `Plugin` trait and `#[query]` macro.
+"""
+
+from __future__ import annotations
+
+import argparse
+import asyncio
+import inspect
+import typing
+from dataclasses import dataclass
+from typing import Any, Callable, Sequence
+
+from pydantic import TypeAdapter, ValidationError
+
+from .engine import PluginEngine
+from .error import HipcheckError, UnknownQuery, UnrecognizedConfig
+
+# Marks a function as a query endpoint, holding its `QueryInfo`
+_QUERY_ATTR = "__hipcheck_query__"
+
+
+@dataclass
+class QueryInfo:
+    """A query endpoint of a plugin, with the types of its key and output."""
+
+    #: The name of the endpoint, which is empty for the default query.
+    name: str
+    func: Callable[..., Any]
+    key_type: Any = Any
+    output_type: Any = Any
+
+    @property
+    def is_default(self) -> bool:
+        return self.name == ""
+
+    def input_schema(self) -> dict[str, Any]:
+        return TypeAdapter(self.key_type).json_schema()
+
+    def output_schema(self) -> dict[str, Any]:
+        return TypeAdapter(self.output_type).json_schema()
+
+
+@dataclass
+class QuerySchema:
+    """The schemas of the key and output of a query endpoint, as sent to Hipcheck core."""
+
+    query_name: str
+    input_schema: dict[str, Any]
+    output_schema: dict[str, Any]
+
+
+def query(
+    func: Callable[..., Any] | None = None, *, name: str | None = None, default: bool = False
+) -> Any:
+    """Mark a method of a `Plugin` as a query endpoint.
+
+    The method is called with the `PluginEngine` for the query session and the query's key, and
+    may be `async`. The key is validated against the type the key parameter is annotated with,
+    and the output is turned into JSON following the return annotation, both using pydantic. The
+    endpoint is named after the method, unless it's given a `name`, or is the plugin's `default`
+    query, which has an empty name.
+    """
+
+    def mark(func: Callable[..., Any]) -> Callable[..., Any]:
+        endpoint = "" if default else (name if name is not None else func.__name__)
+        setattr(func, _QUERY_ATTR, endpoint)
+        return func
+
+    if func is not None:
+        return mark(func)
+    return mark
+
+
+class Plugin:
+    """A Hipcheck plugin.
+
+    Subclasses set `publisher` and `name`, and mark their query endpoints with `@query`.
+    """
+
+    #: The name of the plugin publisher.
+    publisher: str = ""
+    #: The name of the plugin.
+    name: str = ""
+
+    _queries: dict[str, QueryInfo] = {}
+
+    def __init_subclass__(cls, **kwargs: Any) -> None:
+        super().__init_subclass__(**kwargs)
+        queries = dict(cls._queries)
+        for attr, func in vars(cls).items():
+            endpoint = getattr(func, _QUERY_ATTR, None)
+            if endpoint is None:
+                continue
+            if endpoint in queries and queries[endpoint].func.__name__ != attr:
+                raise TypeError(f"{cls.__name__} has two queries named '{endpoint}'")
+            queries[endpoint] = _query_info(endpoint, func)
+        cls._queries = queries
+
+    def set_config(self, config: dict[str, Any]) -> None:
+        """Handle the configuration the user gave the plugin in their policy file, raising a
+        `ConfigError` if it's wrong. By default, the plugin takes no configuration.
+        """
+        for field, value in config.items():
+            raise UnrecognizedConfig(field, str(value))
+
+    def default_policy_expr(self) -> str:
+        """Get the plugin's default policy expression. This is only ever called after
+        `set_config()`. For more information on policy expression syntax, see the Hipcheck
+        website.
+        """
+        return ""
+
+    def explain_default_query(self) -> str | None:
+        """Get an unstructured description of what is returned by the plugin's default query."""
+        return None
+
+    def queries(self) -> list[QueryInfo]:
+        """Get all the query endpoints of the plugin."""
+        return list(self._queries.values())
+
+    def default_query(self) -> QueryInfo | None:
+        """Get the plugin's default query, if it has one."""
+        return self._queries.get("")
+
+    def schemas(self) -> list[QuerySchema]:
+        """Get the schemas of all the plugin's query endpoints."""
+        return [
+            QuerySchema(query.name, query.input_schema(), query.output_schema())
+            for query in self.queries()
+        ]
+
+    async def _run_query(self, engine: PluginEngine, name: str, key: Any) -> Any:
+        # Unknown names fall back to the default query, as in the Rust SDK
+        query = self._queries.get(name) or self.default_query()
+        if query is None:
+            raise UnknownQuery(name)
+
+        try:
+            key = TypeAdapter(query.key_type).validate_python(key)
+        except ValidationError as e:
+            raise HipcheckError("unexpected plugin query input format") from e
+
+        output = query.func(self, engine, key)
+        if inspect.isawaitable(output):
+            output = await output
+
+        try:
+            return TypeAdapter(query.output_type).dump_python(output, mode="json")
+        except Exception as e:
+            raise HipcheckError("unexpected plugin query output format") from e
+
+
+def _query_info(name: str, func: Callable[..., Any]) -> QueryInfo:
+    params = list(inspect.signature(func).parameters)
+    if len(params) != 3:
+        raise TypeError(f"query '{func.__name__}' must take (self, engine, key)")
+    hints = typing.get_type_hints(func)
+    return QueryInfo(
+        name=name,
+        func=func,
+        key_type=hints.get(params[2], Any),
+        output_type=hints.get("return", Any),
+    )
+
+
+def main(plugin: Plugin, argv: Sequence[str] | None = None) -> None:
+    """Run a plugin, listening on the port Hipcheck core passes with `--port`."""
+    from .server import PluginServer
+
+    parser = argparse.ArgumentParser(description=f"The {plugin.publisher}/{plugin.name} plugin")
+    parser.add_argument("--port", type=int, required=True)
+    args = parser.parse_args(argv)
+    asyncio.run(PluginServer(plugin).listen(args.port))
@@ -0,0 +1,5 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""Bindings for the Hipcheck plugin protocol, generated from `hipcheck-common`'s
+`hipcheck.proto` when the package is built.
+"""
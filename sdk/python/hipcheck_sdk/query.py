@@ -0,0 +1,131 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""Whole queries of the query protocol, with their JSON fields decoded.
+
+This mirrors `hipcheck_common::types`.
+"""
+
+from __future__ import annotations
+
+import json
+from dataclasses import dataclass, field
+from enum import Enum
+from typing import Any
+
+from .chunk import QueryMessage, QueryState
+from .error import InvalidQueryTarget, ProtocolError
+
+
+class QueryDirection(Enum):
+    REQUEST = "request"
+    RESPONSE = "response"
+
+
+@dataclass
+class Query:
+    """A query to a plugin, or a plugin's reply to one."""
+
+    id: int
+    direction: QueryDirection
+    publisher: str
+    plugin: str
+    query: str
+    key: list[Any] = field(default_factory=list)
+    output: list[Any] = field(default_factory=list)
+    #: Concerns raised by the query, each a JSON object with at least a "message".
+    concerns: list[Any] = field(default_factory=list)
+    fragments: list[Any] = field(default_factory=list)
+
+    @classmethod
+    def from_message(cls, msg: QueryMessage) -> Query:
+        if msg.state == QueryState.SUBMIT_COMPLETE:
+            direction = QueryDirection.REQUEST
+        elif msg.state == QueryState.REPLY_COMPLETE:
+            direction = QueryDirection.RESPONSE
+        else:
+            raise ProtocolError(f"unexpected query state {msg.state.name}")
+
+        try:
+            key = [json.loads(k) for k in msg.key]
+        except ValueError as e:
+            raise ProtocolError("invalid JSON in query key") from e
+        try:
+            output = [json.loads(o) for o in msg.output]
+        except ValueError as e:
+            raise ProtocolError("invalid JSON in query output") from e
+        try:
+            fragments = [json.loads(f) for f in msg.fragment]
+        except ValueError as e:
+            raise ProtocolError("invalid JSON in query report fragment") from e
+
+        return cls(
+            id=msg.id,
+            direction=direction,
+            publisher=msg.publisher_name,
+            plugin=msg.plugin_name,
+            query=msg.query_name,
+            key=key,
+            output=output,
+            concerns=[_read_concern(c) for c in msg.concern],
+            fragments=fragments,
+        )
+
+    def to_message(self) -> QueryMessage:
+        state = (
+            QueryState.SUBMIT_COMPLETE
+            if self.direction == QueryDirection.REQUEST
+            else QueryState.REPLY_COMPLETE
+        )
+        return QueryMessage(
+            id=self.id,
+            state=state,
+            publisher_name=self.publisher,
+            plugin_name=self.plugin,
+            query_name=self.query,
+            key=[to_json(k) for k in self.key],
+            output=[to_json(o) for o in self.output],
+            concern=[c if isinstance(c, str) else to_json(c) for c in self.concerns],
+            fragment=[to_json(f) for f in self.fragments],
+        )
+
+
+def _read_concern(text: str) -> Any:
+    # Plugins built with older SDKs send concerns as plain text instead of JSON
+    try:
+        concern = json.loads(text)
+    except ValueError:
+        return text
+    return concern if isinstance(concern, dict) else text
+
+
+def to_json(value: Any) -> str:
+    """Write JSON the way the Rust side does, without whitespace."""
+    return json.dumps(value, separators=(",", ":"), ensure_ascii=False)
+
+
+@dataclass(frozen=True)
+class QueryTarget:
+    """Identifies the plugin and endpoint of a query, parsed from `publisher/plugin[/query]`.
+
+    The query is empty for a plugin's default endpoint.
+    """
+
+    publisher: str
+    plugin: str
+    query: str = ""
+
+    @classmethod
+    def parse(cls, target: str | QueryTarget) -> QueryTarget:
+        if isinstance(target, QueryTarget):
+            return target
+        parts = target.split("/")
+        if len(parts) == 2:
+            return cls(parts[0], parts[1])
+        if len(parts) == 3:
+            return cls(parts[0], parts[1], parts[2])
+        raise InvalidQueryTarget(target)
+
+    def __str__(self) -> str:
+        if self.query:
+            return f"{self.publisher}/{self.plugin}/{self.query}"
+        return f"{self.publisher}/{self.plugin}"
@@ -0,0 +1,90 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""Concerns and sections plugins can add to Hipcheck's final report.
+
+These mirror the Rust SDK's `report` module, and are sent to Hipcheck core in the same form.
+"""
+
+from __future__ import annotations
+
+from dataclasses import dataclass, field
+from enum import Enum
+from typing import Any
+
+
+class Severity(str, Enum):
+    """How serious a concern is."""
+
+    LOW = "low"
+    MEDIUM = "medium"
+    HIGH = "high"
+    CRITICAL = "critical"
+
+    def __str__(self) -> str:
+        return self.value
+
+
+@dataclass
+class Concern:
+    """A concern a plugin raises about the target, shown in the final Hipcheck report when its
+    analysis fails.
+
+    Only the message is required. The other fields let Hipcheck show how serious the concern is
+    and where it is, and track it across runs by what it's about rather than by its wording.
+    """
+
+    message: str
+    severity: Severity | None = None
+    #: The file the concern is about, relative to the root of the repository.
+    file: str | None = None
+    #: The line of `file` the concern is about, counting from 1.
+    line: int | None = None
+    #: The hash of the commit the concern is about.
+    commit: str | None = None
+    #: Anything else identifying what the concern is about, like e-mail addresses or package
+    #: names.
+    identifiers: list[str] = field(default_factory=list)
+
+    def to_json(self) -> dict[str, Any]:
+        concern: dict[str, Any] = {"message": self.message}
+        if self.severity is not None:
+            concern["severity"] = Severity(self.severity).value
+        for name in ("file", "line", "commit"):
+            if getattr(self, name) is not None:
+                concern[name] = getattr(self, name)
+        if self.identifiers:
+            concern["identifiers"] = list(self.identifiers)
+        return concern
+
+
+class FragmentFormat(str, Enum):
+    """How the body of a report fragment is written."""
+
+    MARKDOWN = "markdown"
+    JSON = "json"
+
+
+@dataclass
+class ReportFragment:
+    """A section a plugin adds to the final Hipcheck report, for results which aren't a pass or
+    fail, like an inventory of licenses.
+    """
+
+    #: The heading of the section.
+    title: str
+    format: FragmentFormat
+    #: The contents of the section. For `FragmentFormat.MARKDOWN` this must be a string.
+    body: Any
+
+    @classmethod
+    def markdown(cls, title: str, text: str) -> ReportFragment:
+        """Make a fragment from Markdown text."""
+        return cls(title, FragmentFormat.MARKDOWN, text)
+
+    @classmethod
+    def json(cls, title: str, data: Any) -> ReportFragment:
+        """Make a fragment from JSON data."""
+        return cls(title, FragmentFormat.JSON, data)
+
+    def to_json(self) -> dict[str, Any]:
+        return {"title": self.title, "format": FragmentFormat(self.format).value, "body": self.body}
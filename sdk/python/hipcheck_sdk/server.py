@@ -0,0 +1,189 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""The gRPC server through which Hipcheck core talks to a plugin.
+
+This mirrors the Rust SDK's `server` module.
+"""
+
+from __future__ import annotations
+
+import asyncio
+import json
+import logging
+from typing import AsyncIterator
+
+import grpc
+
+from . import logger as hc_logger
+from .chunk import (
+    GRPC_MAX_SIZE_BYTES,
+    GRPC_MIN_SIZE_BYTES,
+    QueryMessage,
+    QueryState,
+    negotiate_max_size,
+)
+from .engine import SessionSocket
+from .error import ConfigError, ConfigurationStatus
+from .plugin import Plugin
+from .proto import hipcheck_pb2 as pb
+from .proto import hipcheck_pb2_grpc as pb_grpc
+from .query import to_json
+
+logger = logging.getLogger(__name__)
+
+
+class PluginServer:
+    """Runs the Hipcheck plugin protocol for a `Plugin`.
+
+    Making a server also installs a logger which forwards the plugin's log records to Hipcheck
+    core, at the log level core requested.
+    """
+
+    def __init__(self, plugin: Plugin, max_message_size: int = GRPC_MAX_SIZE_BYTES):
+        hc_logger.init()
+        self.plugin = plugin
+        #: The largest query protocol message the plugin sends or accepts. Hipcheck core and the
+        #: plugin agree on the smaller of their two sizes when the plugin is configured.
+        self.max_message_size = max(max_message_size, GRPC_MIN_SIZE_BYTES)
+
+    async def listen(self, port: int) -> None:
+        """Run the plugin server on the provided port, until Hipcheck core stops the plugin."""
+        server = grpc.aio.server(
+            options=[
+                ("grpc.max_send_message_length", self.max_message_size),
+                ("grpc.max_receive_message_length", self.max_message_size),
+            ]
+        )
+        pb_grpc.add_PluginServiceServicer_to_server(_PluginServicer(self), server)
+        server.add_insecure_port(f"127.0.0.1:{port}")
+        await server.start()
+        await server.wait_for_termination()
+
+
+class _PluginServicer(pb_grpc.PluginServiceServicer):
+    def __init__(self, server: PluginServer):
+        self.plugin = server.plugin
+        self.max_message_size = server.max_message_size
+        self.negotiated_max_message_size = server.max_message_size
+
+    async def SetConfiguration(self, request, context):
+        try:
+            config = json.loads(request.configuration)
+        except ValueError as e:
+            await context.abort(grpc.StatusCode.UNKNOWN, str(e))
+        self.negotiated_max_message_size = negotiate_max_size(
+            self.max_message_size, request.max_message_size
+        )
+        try:
+            self.plugin.set_config(config)
+        except ConfigError as e:
+            return pb.SetConfigurationResponse(
+                status=int(e.status), message=e.message, max_message_size=self.max_message_size
+            )
+        return pb.SetConfigurationResponse(
+            status=int(ConfigurationStatus.NONE),
+            message="",
+            max_message_size=self.max_message_size,
+        )
+
+    async def GetDefaultPolicyExpression(self, request, context):
+        try:
+            policy_expression = self.plugin.default_policy_expr()
+        except Exception as e:
+            await context.abort(
+                grpc.StatusCode.NOT_FOUND,
+                f"Error determining default policy expr for {self._name()}: {e}",
+            )
+        return pb.GetDefaultPolicyExpressionResponse(policy_expression=policy_expression)
+
+    async def ExplainDefaultQuery(self, request, context):
+        try:
+            explanation = self.plugin.explain_default_query()
+        except Exception as e:
+            await context.abort(
+                grpc.StatusCode.NOT_FOUND,
+                f"Error explaining default query expr for {self._name()}: {e}",
+            )
+        return pb.ExplainDefaultQueryResponse(
+            explanation=explanation or "No default query explanation provided"
+        )
+
+    async def GetQuerySchemas(self, request, context):
+        for schema in self.plugin.schemas():
+            yield pb.GetQuerySchemasResponse(
+                query_name=schema.query_name,
+                key_schema=to_json(schema.input_schema),
+                output_schema=to_json(schema.output_schema),
+            )
+
+    async def InitiateQueryProtocol(
+        self, request_iterator: AsyncIterator[pb.InitiateQueryProtocolRequest], context
+    ) -> AsyncIterator[pb.InitiateQueryProtocolResponse]:
+        # Hipcheck core waits for the response headers before sending any queries, and grpc only
+        # sends them with the first message unless told to
+        await context.send_initial_metadata(())
+
+        outgoing: asyncio.Queue[QueryMessage] = asyncio.Queue(maxsize=10)
+        socket = SessionSocket(self.plugin, outgoing.put, self.negotiated_max_message_size)
+
+        async def receive() -> None:
+            try:
+                async for request in request_iterator:
+                    socket.dispatch(_from_proto(request.query))
+            finally:
+                logger.debug("channel closed by remote")
+                socket.close()
+
+        receiver = asyncio.create_task(receive())
+        try:
+            while True:
+                msg = await outgoing.get()
+                yield pb.InitiateQueryProtocolResponse(query=_to_proto(msg))
+        finally:
+            receiver.cancel()
+
+    def _name(self) -> str:
+        return f"{self.plugin.publisher}/{self.plugin.name}"
+
+
+def _from_proto(query: pb.Query) -> QueryMessage:
+    return QueryMessage(
+        id=query.id,
+        state=QueryState(query.state),
+        publisher_name=query.publisher_name,
+        plugin_name=query.plugin_name,
+        query_name=query.query_name,
+        key=list(query.key),
+        output=list(query.output),
+        concern=list(query.concern),
+        fragment=list(query.fragment),
+        split=query.split,
+        error=query.error,
+        progress=(
+            (query.progress.completed, query.progress.total)
+            if query.HasField("progress")
+            else None
+        ),
+        cancel=query.cancel,
+    )
+
+
+def _to_proto(msg: QueryMessage) -> pb.Query:
+    query = pb.Query(
+        id=msg.id,
+        state=int(msg.state),
+        publisher_name=msg.publisher_name,
+        plugin_name=msg.plugin_name,
+        query_name=msg.query_name,
+        key=msg.key,
+        output=msg.output,
+        concern=msg.concern,
+        fragment=msg.fragment,
+        split=msg.split,
+        error=msg.error,
+        cancel=msg.cancel,
+    )
+    if msg.progress is not None:
+        completed, total = msg.progress
+        query.progress.CopyFrom(pb.Progress(completed=completed, total=total))
+    return query
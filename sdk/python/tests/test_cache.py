@@ -0,0 +1,38 @@
+# SPDX-License-Identifier: Apache-2.0
+
+from pathlib import Path
+
+import pytest
+
+from hipcheck_sdk import InvalidCacheScope, PluginCache
+from hipcheck_sdk.cache import entry_file
+
+
+def test_entries_are_scoped_by_head(tmp_path):
+    cache = PluginCache(tmp_path)
+
+    assert cache.get("abc123", "commits") is None
+    cache.insert("abc123", "commits", 42)
+    assert cache.get("abc123", "commits") == 42
+    assert cache.get("def456", "commits") is None
+
+    cache.insert("abc123", "commits", 43)
+    assert cache.get("abc123", "commits") == 43
+
+
+@pytest.mark.parametrize("head", ["", "..", "../other", "a/b"])
+def test_heads_must_not_leave_the_cache(tmp_path, head):
+    with pytest.raises(InvalidCacheScope):
+        PluginCache(tmp_path).insert(head, "key", 1)
+
+
+def test_disabled_caches_keep_nothing():
+    cache = PluginCache.disabled()
+    cache.insert("abc123", "key", 1)
+    assert cache.get("abc123", "key") is None
+
+
+def test_entry_files_match_the_rust_sdk():
+    assert entry_file(Path("/cache"), "abc123", "key") == Path(
+        "/cache/abc123/1f6b6d08a47db94a.json"
+    )
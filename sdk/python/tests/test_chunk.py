@@ -0,0 +1,121 @@
+# SPDX-License-Identifier: Apache-2.0
+
+import json
+from pathlib import Path
+
+import pytest
+
+from hipcheck_sdk.chunk import (
+    GRPC_MAX_SIZE_BYTES,
+    GRPC_MIN_SIZE_BYTES,
+    QueryMessage,
+    QueryState,
+    QuerySynthesizer,
+    chunk,
+    chunk_with_size,
+    negotiate_max_size,
+)
+from hipcheck_sdk.error import ProtocolError, QueryFailed
+
+# The vectors Hipcheck core's own chunking is tested against
+VECTORS = Path(__file__).parents[3] / "hipcheck-common" / "tests" / "conformance" / "chunks.json"
+CASES = json.loads(VECTORS.read_text(encoding="utf-8"))["cases"]
+
+
+def state(name: str) -> QueryState:
+    return QueryState[name.removeprefix("QUERY_STATE_")]
+
+
+def message(case: dict) -> QueryMessage:
+    query = case["query"]
+    return QueryMessage(
+        id=1,
+        state=state(query["state"]),
+        publisher_name="pub",
+        plugin_name="plugin",
+        query_name="q",
+        key=query["key"],
+        output=query["output"],
+        concern=query["concern"],
+        fragment=query["fragment"],
+    )
+
+
+@pytest.mark.parametrize("case", CASES, ids=[case["name"] for case in CASES])
+def test_chunking_matches_hipcheck_core(case):
+    chunks = chunk_with_size(message(case), case["max_size"])
+    if case.get("error"):
+        with pytest.raises(ProtocolError):
+            list(chunks)
+        return
+
+    actual = [
+        {
+            "state": f"QUERY_STATE_{c.state.name}",
+            "key": c.key,
+            "output": c.output,
+            "concern": c.concern,
+            "fragment": c.fragment,
+            "split": c.split,
+        }
+        for c in chunks
+    ]
+    assert actual == case["chunks"]
+
+
+@pytest.mark.parametrize(
+    "case", [c for c in CASES if not c.get("error")], ids=lambda case: case["name"]
+)
+def test_chunks_are_put_back_together(case):
+    original = message(case)
+    synth = QuerySynthesizer()
+    results = [synth.add(c) for c in chunk_with_size(original.copy(), case["max_size"])]
+
+    assert all(r is None for r in results[:-1])
+    assert results[-1] == original
+
+
+def test_chunking_leaves_the_message_alone():
+    original = QueryMessage(id=1, state=QueryState.REPLY_COMPLETE, output=["x" * 100])
+    list(chunk_with_size(original, 10))
+    assert original.output == ["x" * 100]
+
+
+def test_chunks_fit_the_negotiated_size():
+    msg = QueryMessage(id=3, state=QueryState.REPLY_COMPLETE, output=["x" * 10 * 1024])
+    chunks = list(chunk(msg, 1024 + 4096))
+
+    assert [len(c.output[0]) for c in chunks] == [4096, 4096, 2048]
+    assert [c.state for c in chunks] == [
+        QueryState.REPLY_IN_PROGRESS,
+        QueryState.REPLY_IN_PROGRESS,
+        QueryState.REPLY_COMPLETE,
+    ]
+
+
+def test_negotiate_max_size():
+    # A peer which doesn't negotiate only accepts the default size
+    assert negotiate_max_size(GRPC_MAX_SIZE_BYTES * 4, 0) == GRPC_MAX_SIZE_BYTES
+    assert negotiate_max_size(GRPC_MAX_SIZE_BYTES * 4, GRPC_MAX_SIZE_BYTES * 2) == (
+        GRPC_MAX_SIZE_BYTES * 2
+    )
+    assert negotiate_max_size(1024, 1024) == GRPC_MIN_SIZE_BYTES
+
+
+def test_unspecified_messages_cannot_be_chunked():
+    msg = QueryMessage(id=1, state=QueryState.UNSPECIFIED, key=["1"])
+    with pytest.raises(ProtocolError):
+        list(chunk_with_size(msg, 10))
+
+
+def test_failed_query_reports_error():
+    failed = QueryMessage(id=0, state=QueryState.UNSPECIFIED, error="provider schema drift")
+    with pytest.raises(QueryFailed, match="provider schema drift"):
+        QuerySynthesizer().add(failed)
+
+
+def test_replies_cannot_continue_submissions():
+    synth = QuerySynthesizer()
+    synth.add(QueryMessage(id=1, state=QueryState.SUBMIT_IN_PROGRESS, key=["1"]))
+    with pytest.raises(ProtocolError):
+        synth.add(QueryMessage(id=1, state=QueryState.REPLY_COMPLETE, output=["2"]))
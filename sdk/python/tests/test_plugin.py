@@ -0,0 +1,174 @@
+# SPDX-License-Identifier: Apache-2.0
+
+import asyncio
+import json
+
+import pytest
+
+from hipcheck_sdk import (
+    Concern,
+    MockResponses,
+    Plugin,
+    PluginEngine,
+    QueryCancelled,
+    QueryFailed,
+    ReportFragment,
+    Severity,
+    UnrecognizedConfig,
+    query,
+)
+from hipcheck_sdk.chunk import QueryMessage, QueryState, QuerySynthesizer, chunk_with_size
+from hipcheck_sdk.engine import SessionSocket
+
+
+class ExamplePlugin(Plugin):
+    publisher = "example"
+    name = "example"
+
+    @query(default=True)
+    async def count(self, engine: PluginEngine, key: list[int]) -> int:
+        return sum(key)
+
+    @query
+    def loud(self, engine: PluginEngine, key: str) -> str:
+        engine.record_concern(Concern("too loud", severity=Severity.HIGH, line=3))
+        engine.record_report_fragment(ReportFragment.markdown("Volume", "**loud**"))
+        return key.upper()
+
+    @query(name="ask")
+    async def ask_other(self, engine: PluginEngine, key: str) -> object:
+        return await engine.query("mitre/git/last_commit", key)
+
+
+def test_queries_are_collected():
+    plugin = ExamplePlugin()
+    assert sorted(q.name for q in plugin.queries()) == ["", "ask", "loud"]
+    assert plugin.default_query().func.__name__ == "count"
+
+    schemas = {s.query_name: s for s in plugin.schemas()}
+    assert schemas[""].input_schema == {"items": {"type": "integer"}, "type": "array"}
+    assert schemas[""].output_schema == {"type": "integer"}
+
+
+def test_plugins_take_no_config_by_default():
+    ExamplePlugin().set_config({})
+    with pytest.raises(UnrecognizedConfig, match="unrecognized field 'color'"):
+        ExamplePlugin().set_config({"color": "red"})
+
+
+async def test_queries_run_with_mock_engine():
+    engine = PluginEngine.mock(MockResponses().insert("mitre/git/last_commit", "repo", "abc123"))
+    plugin = ExamplePlugin()
+
+    assert await plugin._run_query(engine, "", [1, 2, 3]) == 6
+    assert await plugin._run_query(engine, "ask", "repo") == "abc123"
+    assert await plugin._run_query(engine, "loud", "hi") == "HI"
+    assert engine.concerns == [Concern("too loud", severity=Severity.HIGH, line=3)]
+    assert engine.report_fragments == [
+        {"title": "Volume", "format": "markdown", "body": "**loud**"}
+    ]
+
+
+async def test_mock_engine_reports_progress_once_per_percent():
+    engine = PluginEngine.mock()
+    await engine.report_progress(1, 1000)
+    await engine.report_progress(5, 1000)
+    assert engine.progress == (1, 1000)
+    await engine.report_progress(10, 1000)
+    assert engine.progress == (10, 1000)
+
+
+async def test_cancelled_mock_engines_stop_querying():
+    engine = PluginEngine.mock(MockResponses().insert("mitre/git/last_commit", "repo", "abc123"))
+    assert not engine.is_cancelled
+    assert await engine.query("mitre/git/last_commit", "repo") == "abc123"
+
+    engine.cancel()
+    assert engine.is_cancelled
+    await asyncio.wait_for(engine.cancelled(), 5)
+    with pytest.raises(QueryCancelled):
+        await engine.query("mitre/git/last_commit", "repo")
+
+
+class Core:
+    """Plays Hipcheck core's side of the query protocol."""
+
+    def __init__(self, plugin):
+        self.sent = asyncio.Queue()
+        self.socket = SessionSocket(plugin, self.sent.put, max_message_size=64 * 1024)
+
+    def submit(self, id, query_name, key, max_size=8):
+        msg = QueryMessage(
+            id=id,
+            state=QueryState.SUBMIT_COMPLETE,
+            publisher_name="example",
+            plugin_name="example",
+            query_name=query_name,
+            key=[json.dumps(key)],
+        )
+        for c in chunk_with_size(msg, max_size):
+            self.socket.dispatch(c)
+
+    async def receive(self):
+        synth = QuerySynthesizer()
+        while True:
+            whole = synth.add(await asyncio.wait_for(self.sent.get(), 5))
+            if whole is not None:
+                return whole
+
+
+async def test_sessions_answer_queries():
+    core = Core(ExamplePlugin())
+    core.submit(1, "loud", "a chunked key")
+
+    reply = await core.receive()
+    assert reply.id == 1
+    assert reply.state == QueryState.REPLY_COMPLETE
+    assert reply.output == ['"A CHUNKED KEY"']
+    assert [json.loads(c) for c in reply.concern] == [
+        {"message": "too loud", "severity": "high", "line": 3}
+    ]
+
+
+async def test_sessions_query_other_plugins():
+    core = Core(ExamplePlugin())
+    core.submit(7, "ask", "repo")
+
+    request = await core.receive()
+    assert (request.id, request.state) == (7, QueryState.SUBMIT_COMPLETE)
+    assert (request.publisher_name, request.plugin_name, request.query_name) == (
+        "mitre",
+        "git",
+        "last_commit",
+    )
+    assert request.key == ['"repo"']
+
+    core.socket.dispatch(
+        QueryMessage(id=7, state=QueryState.REPLY_COMPLETE, output=['"abc123"'])
+    )
+    reply = await core.receive()
+    assert reply.output == ['"abc123"']
+
+
+async def test_failed_sessions_report_their_error():
+    core = Core(ExamplePlugin())
+    core.submit(2, "", "not a list")
+
+    with pytest.raises(QueryFailed, match="unexpected plugin query input format"):
+        await core.receive()
+
+
+async def test_cancelled_sessions_stop_waiting_for_answers():
+    core = Core(ExamplePlugin())
+    core.submit(7, "ask", "repo")
+    await core.receive()
+
+    core.socket.dispatch(QueryMessage(id=7, state=QueryState.UNSPECIFIED, cancel=True))
+    await asyncio.wait_for(asyncio.gather(*core.socket._tasks), 5)
+    # The session ends without answering
+    assert core.sent.empty()
+
+    # Queries which were already answered can be cancelled too
+    core.socket.dispatch(QueryMessage(id=7, state=QueryState.UNSPECIFIED, cancel=True))
+    core.submit(9, "", [1, 2])
+    assert (await core.receive()).output == ["3"]
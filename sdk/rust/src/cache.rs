@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::{Error, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::{
+	fs,
+	io::ErrorKind,
+	path::{Path, PathBuf},
+	process,
+};
+
+/// A persistent key-value cache for a plugin, scoped by target HEAD commit.
+///
+/// Hipcheck core gives each version of a plugin its own cache directory, so a new version never
+/// reads what an older one stored. Within it, entries are scoped by the HEAD commit of the target
+/// they were computed for, since what's true of one commit may not be of the next. Each entry is
+/// kept in its own file, along with its key, at `<dir>/<head>/<hash of key>.json`.
+///
+/// Keys and values may be anything which serializes to JSON. A cache Hipcheck core didn't give a
+/// directory for, as when the plugin is run on its own or in unit tests, keeps nothing.
+#[derive(Debug, Clone)]
+pub struct PluginCache {
+	dir: Option<PathBuf>,
+}
+
+impl PluginCache {
+	/// Make a cache keeping its entries in a directory.
+	pub fn new(dir: impl Into<PathBuf>) -> PluginCache {
+		PluginCache {
+			dir: Some(dir.into()),
+		}
+	}
+
+	/// Make a cache which keeps nothing.
+	pub fn disabled() -> PluginCache {
+		PluginCache { dir: None }
+	}
+
+	/// Make the cache in the directory Hipcheck core gave this plugin, if it gave one.
+	pub(crate) fn from_env() -> PluginCache {
+		PluginCache {
+			dir: hipcheck_common::plugin_cache::cache_dir_from_env(),
+		}
+	}
+
+	/// Whether the cache keeps entries.
+	pub fn is_enabled(&self) -> bool {
+		self.dir.is_some()
+	}
+
+	/// Get the value cached for a key when analyzing the target at commit `head`.
+	///
+	/// Entries which can't be read as the requested type, as when a plugin changes the type it
+	/// caches for a key, are treated as missing.
+	pub fn get<K, V>(&self, head: &str, key: &K) -> Result<Option<V>>
+	where
+		K: Serialize + ?Sized,
+		V: DeserializeOwned,
+	{
+		let key = serde_json::to_value(key).map_err(Error::InvalidJsonInCacheEntry)?;
+		let Some(path) = self.entry_path(head, &key)? else {
+			return Ok(None);
+		};
+
+		let contents = match fs::read_to_string(&path) {
+			Ok(contents) => contents,
+			Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+			Err(source) => return Err(Error::PluginCacheIo { path, source }),
+		};
+		let Ok(mut entry) = serde_json::from_str::<Value>(&contents) else {
+			log::debug!("ignoring unreadable cache entry '{}'", path.display());
+			return Ok(None);
+		};
+		// Keys whose hashes collide share a file, so only the key last stored is found
+		if entry.get("key") != Some(&key) {
+			return Ok(None);
+		}
+
+		Ok(serde_json::from_value(entry["value"].take()).ok())
+	}
+
+	/// Cache a value for a key when analyzing the target at commit `head`, replacing any value
+	/// cached before.
+	pub fn insert<K, V>(&self, head: &str, key: &K, value: &V) -> Result<()>
+	where
+		K: Serialize + ?Sized,
+		V: Serialize + ?Sized,
+	{
+		let key = serde_json::to_value(key).map_err(Error::InvalidJsonInCacheEntry)?;
+		let Some(path) = self.entry_path(head, &key)? else {
+			return Ok(());
+		};
+		let entry = json!({
+			"key": key,
+			"value": serde_json::to_value(value).map_err(Error::InvalidJsonInCacheEntry)?,
+		});
+
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent).map_err(|source| Error::PluginCacheIo {
+				path: parent.to_owned(),
+				source,
+			})?;
+		}
+		// Write to a temporary file first, so other sessions never read a partial entry
+		let temp = path.with_extension(format!("{}.tmp", process::id()));
+		fs::write(&temp, entry.to_string())
+			.and_then(|_| fs::rename(&temp, &path))
+			.map_err(|source| Error::PluginCacheIo { path, source })
+	}
+
+	/// Get the file an entry is kept in, if the cache keeps entries.
+	fn entry_path(&self, head: &str, key: &Value) -> Result<Option<PathBuf>> {
+		let Some(dir) = &self.dir else {
+			return Ok(None);
+		};
+		// The HEAD is a directory name, so it mustn't be able to name anything outside the cache
+		let valid = !head.is_empty()
+			&& head
+				.chars()
+				.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+		if !valid {
+			return Err(Error::InvalidCacheScope(head.to_owned()));
+		}
+
+		Ok(Some(entry_file(dir, head, key)))
+	}
+}
+
+/// Get the file an entry with a key is kept in, named after the FNV-1a hash of the key's JSON,
+/// which unlike the standard library's hashers is the same across Rust versions.
+fn entry_file(dir: &Path, head: &str, key: &Value) -> PathBuf {
+	const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+	const FNV_PRIME: u64 = 0x100000001b3;
+
+	let hash = key
+		.to_string()
+		.bytes()
+		.fold(FNV_OFFSET_BASIS, |hash, byte| {
+			(hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+		});
+	dir.join(head).join(format!("{hash:016x}.json"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Make a cache in a fresh directory, which the caller removes.
+	fn test_cache(name: &str) -> (PluginCache, PathBuf) {
+		let dir = std::env::temp_dir().join(format!("hc-sdk-cache-{}-{}", name, process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		(PluginCache::new(&dir), dir)
+	}
+
+	#[test]
+	fn entries_are_scoped_by_head() {
+		let (cache, dir) = test_cache("scoped");
+
+		assert_eq!(cache.get::<_, u64>("abc123", "commits").unwrap(), None);
+		cache.insert("abc123", "commits", &42).unwrap();
+		assert_eq!(cache.get("abc123", "commits").unwrap(), Some(42));
+		assert_eq!(cache.get::<_, u64>("def456", "commits").unwrap(), None);
+
+		// Values of another type are missing, rather than errors
+		assert_eq!(cache.get::<_, String>("abc123", "commits").unwrap(), None);
+
+		cache.insert("abc123", "commits", &43).unwrap();
+		assert_eq!(cache.get("abc123", "commits").unwrap(), Some(43));
+
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn heads_must_not_leave_the_cache() {
+		let (cache, _) = test_cache("invalid");
+		for head in ["", "..", "../other", "a/b"] {
+			assert!(cache.insert(head, "key", &1).is_err());
+		}
+	}
+
+	#[test]
+	fn disabled_caches_keep_nothing() {
+		let cache = PluginCache::disabled();
+		cache.insert("abc123", "key", &1).unwrap();
+		assert_eq!(cache.get::<_, u64>("abc123", "key").unwrap(), None);
+	}
+
+	#[test]
+	fn entry_files_are_stable() {
+		// Plugins must find what earlier builds of them stored
+		assert_eq!(
+			entry_file(Path::new("/cache"), "abc123", &json!("key")),
+			Path::new("/cache/abc123/1f6b6d08a47db94a.json")
+		);
+	}
+}
@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+	cache::PluginCache,
 	error::{Error, Result},
+	fixture::QueryRecorder,
+	report::{Concern, ReportFragment},
 	JsonValue, Plugin, QueryTarget,
 };
 use futures::Stream;
@@ -10,18 +13,22 @@ use hipcheck_common::proto::{
 	QueryState,
 };
 use hipcheck_common::{
-	chunk::QuerySynthesizer,
+	chunk::{prepare_with_max_size, QuerySynthesizer, GRPC_MAX_SIZE_BYTES},
 	types::{Query, QueryDirection},
 };
 use serde::Serialize;
 use std::{
 	collections::{HashMap, VecDeque},
-	future::poll_fn,
+	error::Error as _,
+	future::{poll_fn, Future},
 	pin::Pin,
 	result::Result as StdResult,
-	sync::Arc,
+	sync::{Arc, OnceLock},
+};
+use tokio::sync::{
+	mpsc::{self, error::TrySendError},
+	watch,
 };
-use tokio::sync::mpsc::{self, error::TrySendError};
 use tonic::Status;
 
 impl From<Status> for Error {
@@ -31,7 +38,16 @@ impl From<Status> for Error {
 	}
 }
 
-type SessionTracker = HashMap<i32, mpsc::Sender<Option<PluginQuery>>>;
+type SessionTracker = HashMap<i32, SessionHandle>;
+
+/// How the session socket reaches a session.
+#[derive(Debug)]
+struct SessionHandle {
+	/// Forwards the messages for the session's query to it
+	tx: mpsc::Sender<Option<PluginQuery>>,
+	/// Tells the session that Hipcheck core cancelled its query
+	cancel: Arc<watch::Sender<bool>>,
+}
 
 /// Used for building a up a `Vec` of keys to send to specific hipcheck plugin
 pub struct QueryBuilder<'engine> {
@@ -77,11 +93,20 @@ pub struct PluginEngine {
 	id: usize,
 	tx: mpsc::Sender<StdResult<InitiateQueryProtocolResponse, Status>>,
 	rx: mpsc::Receiver<Option<PluginQuery>>,
-	concerns: Vec<String>,
+	concerns: Vec<Concern>,
+	fragments: Vec<JsonValue>,
+	// The progress last reported to Hipcheck core, as (completed, total)
+	progress: Option<(u64, u64)>,
+	// The max message size negotiated with Hipcheck core, which queries are chunked to fit
+	max_message_size: usize,
 	// So that we can remove ourselves when we get dropped
 	drop_tx: mpsc::Sender<i32>,
+	// Set when Hipcheck core cancels the query this session is answering
+	cancel: Arc<watch::Sender<bool>>,
 	// When unit testing, this enables the user to mock plugin responses to various inputs
 	mock_responses: MockResponses,
+	// When recording, the answers to queries this session makes are written here
+	recorder: Option<Arc<QueryRecorder>>,
 }
 
 impl PluginEngine {
@@ -108,6 +133,11 @@ impl PluginEngine {
 		target: QueryTarget,
 		input: Vec<JsonValue>,
 	) -> Result<Vec<JsonValue>> {
+		// Nothing needs the answer to a cancelled query, so there's no point asking for more
+		if self.is_cancelled() {
+			return Err(Error::QueryCancelled);
+		}
+
 		// If doing a mock engine, look to the `mock_responses` field for the query answer
 		if cfg!(feature = "mock_engine") {
 			let mut results = Vec::with_capacity(input.len());
@@ -130,17 +160,25 @@ impl PluginEngine {
 			let query = Query {
 				id: 0,
 				direction: QueryDirection::Request,
-				publisher: target.publisher,
-				plugin: target.plugin,
-				query: target.query.unwrap_or_else(|| "".to_owned()),
-				key: input,
+				publisher: target.publisher.clone(),
+				plugin: target.plugin.clone(),
+				query: target.query.clone().unwrap_or_else(|| "".to_owned()),
+				key: input.clone(),
 				output: vec![],
 				concerns: vec![],
+				fragments: vec![],
 			};
 			self.send(query).await?;
 			let response = self.recv().await?;
 			match response {
-				Some(response) => Ok(response.output),
+				Some(response) => {
+					if let Some(recorder) = &self.recorder {
+						if let Err(e) = recorder.record(&target, &input, &response.output) {
+							log::warn!("failed to record query: {}", error_message(&e));
+						}
+					}
+					Ok(response.output)
+				}
 				None => Err(Error::SessionChannelClosed),
 			}
 		}
@@ -220,9 +258,12 @@ impl PluginEngine {
 	// Send a gRPC query from plugin to the hipcheck server
 	async fn send(&self, mut query: Query) -> Result<()> {
 		query.id = self.id(); // incoming id value is just a placeholder
-		let queries = hipcheck_common::chunk::prepare(query)?;
+
+		// Chunks are produced as they're sent, so a full channel holds off chunking the rest of a
+		// large result until Hipcheck core catches up
+		let queries = prepare_with_max_size(query, self.max_message_size)?;
 		for pq in queries {
-			let query = InitiateQueryProtocolResponse { query: Some(pq) };
+			let query = InitiateQueryProtocolResponse { query: Some(pq?) };
 			self.tx
 				.send(Ok(query))
 				.await
@@ -231,7 +272,8 @@ impl PluginEngine {
 		Ok(())
 	}
 
-	async fn send_session_err<P>(&mut self) -> crate::error::Result<()>
+	/// Tell Hipcheck core the query failed, and why.
+	async fn send_session_err<P>(&mut self, error: &Error) -> crate::error::Result<()>
 	where
 		P: Plugin,
 	{
@@ -243,8 +285,16 @@ impl PluginEngine {
 			query_name: "".to_owned(),
 			key: vec![],
 			output: vec![],
-			concern: self.take_concerns(),
+			concern: self
+				.take_concerns()
+				.into_iter()
+				.map(|concern| JsonValue::from(concern).to_string())
+				.collect(),
 			split: false,
+			fragment: vec![],
+			error: error_message(error),
+			progress: None,
+			cancel: false,
 		};
 		self.tx
 			.send(Ok(InitiateQueryProtocolResponse { query: Some(query) }))
@@ -253,10 +303,18 @@ impl PluginEngine {
 	}
 
 	async fn recv(&mut self) -> Result<Option<Query>> {
-		let mut synth = QuerySynthesizer::default();
+		// Hipcheck core won't send the rest of a cancelled query, or answer the plugin's queries
+		let cancelled = self.cancelled();
+		tokio::pin!(cancelled);
+
+		let mut synth = QuerySynthesizer::with_max_message_size(self.max_message_size);
 		let mut res: Option<Query> = None;
 		while res.is_none() {
-			let Some(msg_chunks) = self.recv_raw().await? else {
+			let msg_chunks = tokio::select! {
+				msg_chunks = self.recv_raw() => msg_chunks?,
+				_ = &mut cancelled => return Err(Error::QueryCancelled),
+			};
+			let Some(msg_chunks) = msg_chunks else {
 				return Ok(None);
 			};
 			res = synth.add(msg_chunks.into_iter())?;
@@ -310,7 +368,12 @@ impl PluginEngine {
 			query: name.to_owned(),
 			key: vec![],
 			output: vec![value],
-			concerns: self.take_concerns(),
+			concerns: self
+				.take_concerns()
+				.into_iter()
+				.map(JsonValue::from)
+				.collect(),
+			fragments: self.take_fragments(),
 		};
 
 		self.send(query).await
@@ -327,9 +390,14 @@ impl PluginEngine {
 					log::error!("Failed to send message to Hipcheck core, analysis will hang.");
 					return;
 				}
+				// Hipcheck core isn't waiting for an answer anymore
+				QueryCancelled => {
+					log::debug!("Query {} was cancelled by Hipcheck core", self.id());
+					return;
+				}
 				other => {
 					log::error!("{}", other);
-					self.send_session_err::<P>().await
+					self.send_session_err::<P>(&other).await
 				}
 			};
 			if res_err_send.is_err() {
@@ -338,25 +406,135 @@ impl PluginEngine {
 		}
 	}
 
-	/// Records a string-like concern that will be emitted in the final Hipcheck report. Intended
-	/// for use within a `Query` trait impl.
-	pub fn record_concern<S: AsRef<str>>(&mut self, concern: S) {
-		fn inner(engine: &mut PluginEngine, concern: &str) {
-			engine.concerns.push(concern.to_owned());
+	/// Gets the cache this plugin keeps data in between runs of Hipcheck, for memoizing
+	/// expensive work like API calls. Entries are scoped by the version of the plugin and the
+	/// HEAD commit of the target. When the plugin wasn't started by Hipcheck core, as in unit
+	/// tests, the cache keeps nothing.
+	pub fn cache(&self) -> &'static PluginCache {
+		static CACHE: OnceLock<PluginCache> = OnceLock::new();
+		CACHE.get_or_init(PluginCache::from_env)
+	}
+
+	/// Checks whether Hipcheck core cancelled the query being answered, as when it timed out, so
+	/// its answer is no longer needed. Queries which do a lot of work can check this to stop
+	/// early.
+	///
+	/// Queries made to other plugins after the query is cancelled fail with
+	/// `Error::QueryCancelled`.
+	pub fn is_cancelled(&self) -> bool {
+		*self.cancel.borrow()
+	}
+
+	/// Returns a future which completes once Hipcheck core cancels the query being answered.
+	/// Queries waiting on something slow, like a subprocess or an API call, can race it against
+	/// this to give up when the answer is no longer needed:
+	///
+	/// ```ignore
+	/// tokio::select! {
+	/// 	output = child.wait_with_output() => { /* ... */ }
+	/// 	_ = engine.cancelled() => return Err(Error::QueryCancelled),
+	/// }
+	/// ```
+	pub fn cancelled(&self) -> impl Future<Output = ()> + Send + 'static {
+		let mut rx = self.cancel.subscribe();
+		async move {
+			// The engine holds the sender, so this only fails once nothing could cancel the query
+			let _ = rx.wait_for(|cancelled| *cancelled).await;
 		}
-		inner(self, concern.as_ref())
+	}
+
+	#[cfg(feature = "mock_engine")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "mock_engine")))]
+	/// Cancels the query being answered, as Hipcheck core does when it no longer needs the answer
+	pub fn cancel(&self) {
+		self.cancel.send_replace(true);
+	}
+
+	/// Records a concern that will be emitted in the final Hipcheck report, either a `Concern` or
+	/// a string-like message. Intended for use within a `Query` trait impl.
+	pub fn record_concern<C: Into<Concern>>(&mut self, concern: C) {
+		self.concerns.push(concern.into());
 	}
 
 	#[cfg(feature = "mock_engine")]
 	#[cfg_attr(docsrs, doc(cfg(feature = "mock_engine")))]
 	/// Exposes the current set of concerns recorded by `PluginEngine`
-	pub fn get_concerns(&self) -> &[String] {
+	pub fn get_concerns(&self) -> &[Concern] {
 		&self.concerns
 	}
 
-	fn take_concerns(&mut self) -> Vec<String> {
+	fn take_concerns(&mut self) -> Vec<Concern> {
 		self.concerns.drain(..).collect()
 	}
+
+	/// Records a section to add to the final Hipcheck report, for results which aren't a pass or
+	/// fail. Intended for use within a `Query` trait impl.
+	pub fn record_report_fragment(&mut self, fragment: ReportFragment) {
+		self.fragments.push(fragment.into());
+	}
+
+	#[cfg(feature = "mock_engine")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "mock_engine")))]
+	/// Exposes the current set of report fragments recorded by `PluginEngine`, as JSON
+	pub fn get_report_fragments(&self) -> &[JsonValue] {
+		&self.fragments
+	}
+
+	fn take_fragments(&mut self) -> Vec<JsonValue> {
+		self.fragments.drain(..).collect()
+	}
+
+	/// Reports how far along the current query is, as `completed` out of `total` units of work
+	/// (e.g. commits processed), so Hipcheck can show the user how far along the analysis is.
+	/// Intended for use within a `Query` trait impl which may take a while.
+	///
+	/// Progress is only sent to Hipcheck core when the whole percentage done changes, so this is
+	/// cheap to call for every unit of work.
+	pub async fn report_progress(&mut self, completed: u64, total: u64) -> Result<()> {
+		if total == 0 {
+			return Ok(());
+		}
+		let completed = completed.min(total);
+		let percent = |(completed, total): (u64, u64)| completed.saturating_mul(100) / total;
+		if let Some(last) = self.progress {
+			if last.1 == total && percent(last) == percent((completed, total)) {
+				return Ok(());
+			}
+		}
+		self.progress = Some((completed, total));
+
+		// There's no Hipcheck core to report to when unit testing
+		if cfg!(feature = "mock_engine") {
+			return Ok(());
+		}
+
+		let query = proto::Query {
+			id: self.id() as i32,
+			state: QueryState::Unspecified as i32,
+			publisher_name: "".to_owned(),
+			plugin_name: "".to_owned(),
+			query_name: "".to_owned(),
+			key: vec![],
+			output: vec![],
+			concern: vec![],
+			split: false,
+			fragment: vec![],
+			error: String::new(),
+			progress: Some(proto::Progress { completed, total }),
+			cancel: false,
+		};
+		self.tx
+			.send(Ok(InitiateQueryProtocolResponse { query: Some(query) }))
+			.await
+			.map_err(Error::FailedToSendQueryFromSessionToServer)
+	}
+
+	#[cfg(feature = "mock_engine")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "mock_engine")))]
+	/// Exposes the progress last reported by `PluginEngine`, as `(completed, total)`
+	pub fn get_progress(&self) -> Option<(u64, u64)> {
+		self.progress
+	}
 }
 
 #[cfg(feature = "mock_engine")]
@@ -370,14 +548,32 @@ impl From<MockResponses> for PluginEngine {
 		Self {
 			id: 0,
 			concerns: vec![],
+			fragments: vec![],
+			progress: None,
+			max_message_size: GRPC_MAX_SIZE_BYTES,
 			tx,
 			rx,
 			drop_tx,
+			cancel: Arc::new(watch::channel(false).0),
 			mock_responses: value,
+			recorder: None,
 		}
 	}
 }
 
+/// Describe an error for Hipcheck core to show the user, including the errors
+/// which caused it.
+fn error_message(error: &Error) -> String {
+	let mut message = error.to_string();
+	let mut source = error.source();
+	while let Some(cause) = source {
+		message.push_str(": ");
+		message.push_str(&cause.to_string());
+		source = cause.source();
+	}
+	message
+}
+
 impl Drop for PluginEngine {
 	// Notify to have self removed from session tracker
 	fn drop(&mut self) {
@@ -408,6 +604,8 @@ pub(crate) struct HcSessionSocket {
 	drop_tx: mpsc::Sender<i32>,
 	drop_rx: mpsc::Receiver<i32>,
 	sessions: SessionTracker,
+	max_message_size: usize,
+	recorder: Option<Arc<QueryRecorder>>,
 }
 
 // This is implemented manually since the stream trait object
@@ -420,6 +618,8 @@ impl std::fmt::Debug for HcSessionSocket {
 			.field("drop_tx", &self.drop_tx)
 			.field("drop_rx", &self.drop_rx)
 			.field("sessions", &self.sessions)
+			.field("max_message_size", &self.max_message_size)
+			.field("recorder", &self.recorder)
 			.finish()
 	}
 }
@@ -428,6 +628,8 @@ impl HcSessionSocket {
 	pub(crate) fn new(
 		tx: mpsc::Sender<StdResult<InitiateQueryProtocolResponse, Status>>,
 		rx: impl Stream<Item = StdResult<InitiateQueryProtocolRequest, Status>> + Send + Unpin + 'static,
+		max_message_size: usize,
+		recorder: Option<Arc<QueryRecorder>>,
 	) -> Self {
 		// channel for QuerySession objects to notify us they dropped
 		// TODO: make this configurable
@@ -438,6 +640,8 @@ impl HcSessionSocket {
 			drop_tx,
 			drop_rx,
 			sessions: HashMap::new(),
+			max_message_size,
+			recorder,
 		}
 	}
 
@@ -490,14 +694,20 @@ impl HcSessionSocket {
 
 					let (in_tx, rx) = mpsc::channel::<Option<PluginQuery>>(10);
 					let tx = self.tx.clone();
+					let cancel = Arc::new(watch::channel(false).0);
 
 					let session = PluginEngine {
 						id: id as usize,
 						concerns: vec![],
+						fragments: vec![],
+						progress: None,
+						max_message_size: self.max_message_size,
 						tx,
 						rx,
 						drop_tx: self.drop_tx.clone(),
+						cancel: Arc::clone(&cancel),
 						mock_responses: MockResponses::new(),
+						recorder: self.recorder.clone(),
 					};
 
 					in_tx.send(Some(raw)).await.expect(
@@ -505,18 +715,31 @@ impl HcSessionSocket {
 					);
 
 					log::trace!("SDK: adding new session {id} to tracker");
-					self.sessions.insert(id, in_tx);
+					self.sessions
+						.insert(id, SessionHandle { tx: in_tx, cancel });
 
 					return Ok(Some(session));
 				}
+				Ok(HandleAction::CancelSession(Some(session))) => {
+					log::trace!("SDK: cancelling session {id}");
+					session.cancel.send_replace(true);
+				}
+				Ok(HandleAction::CancelSession(None)) => {
+					log::trace!("SDK: ignoring cancellation of finished session {id}");
+				}
 				Err(e) => log::error!("{}", e),
 			}
 		}
 	}
 
 	fn decide_action(&mut self, query: &PluginQuery) -> Result<HandleAction<'_>> {
-		if let Some(tx) = self.sessions.get_mut(&query.id) {
-			return Ok(HandleAction::ForwardMsgToExistingSession(tx));
+		// Hipcheck core may cancel a query the plugin has already answered
+		if query.cancel {
+			return Ok(HandleAction::CancelSession(self.sessions.get(&query.id)));
+		}
+
+		if let Some(session) = self.sessions.get_mut(&query.id) {
+			return Ok(HandleAction::ForwardMsgToExistingSession(&mut session.tx));
 		}
 
 		if [QueryState::SubmitInProgress, QueryState::SubmitComplete].contains(&query.state()) {
@@ -550,16 +773,27 @@ impl HcSessionSocket {
 	}
 }
 
+impl Drop for HcSessionSocket {
+	// Once Hipcheck core closes the query protocol, nothing is waiting on the sessions' answers
+	fn drop(&mut self) {
+		for session in self.sessions.values() {
+			session.cancel.send_replace(true);
+		}
+	}
+}
+
 enum HandleAction<'s> {
 	ForwardMsgToExistingSession(&'s mut mpsc::Sender<Option<PluginQuery>>),
 	CreateSession,
+	CancelSession(Option<&'s SessionHandle>),
 }
 
 /// A map of query endpoints to mock return values.
 ///
 /// When using the `mock_engine` feature, calling `PluginEngine::query()` will cause this
 /// structure to be referenced instead of trying to communicate with Hipcheck core. Allows
-/// constructing a `PluginEngine` with which to write unit tests.
+/// constructing a `PluginEngine` with which to write unit tests. Responses can be inserted one at
+/// a time, or loaded from a fixture file recorded during a real run of Hipcheck.
 #[derive(Default, Debug)]
 pub struct MockResponses(pub(crate) HashMap<(QueryTarget, JsonValue), Result<JsonValue>>);
 
@@ -592,6 +826,18 @@ impl MockResponses {
 		self.0.insert((query_target, query_value), query_response);
 		Ok(())
 	}
+
+	/// Load the queries recorded in a fixture file by a plugin server set to
+	/// `PluginServer::record_queries`, so tests can use the answers other plugins gave in a real
+	/// run of Hipcheck rather than building them by hand.
+	#[cfg(feature = "mock_engine")]
+	pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<MockResponses> {
+		let mut mock_responses = MockResponses::new();
+		for (target, key, output) in crate::fixture::read_fixture(path.as_ref())? {
+			mock_responses.0.insert((target, key), Ok(output));
+		}
+		Ok(mock_responses)
+	}
 }
 
 #[cfg(test)]
@@ -624,4 +870,76 @@ mod test {
 			&<i32 as Into<JsonValue>>::into(5678)
 		);
 	}
+
+	#[cfg(feature = "mock_engine")]
+	#[tokio::test]
+	async fn test_replay_recorded_queries() {
+		let path =
+			std::env::temp_dir().join(format!("hc-sdk-fixture-{}.jsonl", std::process::id()));
+		let recorder = QueryRecorder::create(path.clone()).unwrap();
+		let target: QueryTarget = "mitre/git/commits".parse().unwrap();
+		recorder
+			.record(
+				&target,
+				&["abcd".into(), "efgh".into()],
+				&[1234.into(), 5678.into()],
+			)
+			.unwrap();
+		// Queries already recorded aren't recorded again
+		recorder
+			.record(&target, &["abcd".into()], &[1234.into()])
+			.unwrap();
+		assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+		let mock_responses = MockResponses::load(&path).unwrap();
+		let _ = std::fs::remove_file(&path);
+		let mut engine = PluginEngine::mock(mock_responses);
+		assert_eq!(
+			engine.query("mitre/git/commits", "efgh").await.unwrap(),
+			JsonValue::from(5678)
+		);
+		assert!(engine.query("mitre/git", "efgh").await.is_err());
+	}
+
+	#[cfg(feature = "mock_engine")]
+	#[tokio::test]
+	async fn test_cancelled_queries_stop_querying() {
+		let mut mock_responses = MockResponses::new();
+		mock_responses
+			.insert("mitre/foo", "abcd", Ok(1234))
+			.unwrap();
+		let mut engine = PluginEngine::mock(mock_responses);
+		let cancelled = engine.cancelled();
+		assert!(!engine.is_cancelled());
+		assert!(engine.query("mitre/foo", "abcd").await.is_ok());
+
+		engine.cancel();
+		assert!(engine.is_cancelled());
+		cancelled.await;
+		assert!(matches!(
+			engine.query("mitre/foo", "abcd").await,
+			Err(Error::QueryCancelled)
+		));
+	}
+
+	#[cfg(feature = "mock_engine")]
+	#[tokio::test]
+	async fn test_report_progress() {
+		let mut engine = PluginEngine::mock(MockResponses::new());
+		assert_eq!(engine.get_progress(), None);
+
+		engine.report_progress(1, 200).await.unwrap();
+		assert_eq!(engine.get_progress(), Some((1, 200)));
+
+		// Still 0%, so not reported again
+		engine.report_progress(1, 200).await.unwrap();
+		engine.report_progress(2, 1000).await.unwrap();
+		assert_eq!(engine.get_progress(), Some((2, 1000)));
+		engine.report_progress(3, 1000).await.unwrap();
+		assert_eq!(engine.get_progress(), Some((2, 1000)));
+
+		// Progress past the total is capped
+		engine.report_progress(2000, 1000).await.unwrap();
+		assert_eq!(engine.get_progress(), Some((1000, 1000)));
+	}
 }
@@ -3,7 +3,10 @@
 use hipcheck_common::proto::{
 	ConfigurationStatus, InitiateQueryProtocolResponse, SetConfigurationResponse,
 };
-use std::{convert::Infallible, error::Error as StdError, ops::Not, result::Result as StdResult};
+use std::{
+	convert::Infallible, error::Error as StdError, ops::Not, path::PathBuf,
+	result::Result as StdResult,
+};
 use tokio::sync::mpsc::error::SendError as TokioMpscSendError;
 use tonic::Status as TonicStatus;
 
@@ -24,6 +27,44 @@ pub enum Error {
 	#[error("invalid JSON in query output")]
 	InvalidJsonInQueryOutput(#[source] serde_json::Error),
 
+	#[error("invalid JSON in query report fragment")]
+	InvalidJsonInReportFragment(#[source] serde_json::Error),
+
+	#[error("invalid JSON in query concern")]
+	InvalidJsonInConcern(#[source] serde_json::Error),
+
+	#[error("invalid JSON in plugin cache entry")]
+	InvalidJsonInCacheEntry(#[source] serde_json::Error),
+
+	/// A plugin cache entry couldn't be read or written
+	#[error("failed to access plugin cache entry '{}'", path.display())]
+	PluginCacheIo {
+		path: PathBuf,
+		#[source]
+		source: std::io::Error,
+	},
+
+	/// Plugin cache entries were scoped by something other than a commit hash
+	#[error("invalid plugin cache scope '{0}'; expected a commit hash")]
+	InvalidCacheScope(String),
+
+	/// A file of recorded queries couldn't be read or written
+	#[error("failed to access query fixture '{}'", path.display())]
+	QueryFixtureIo {
+		path: PathBuf,
+		#[source]
+		source: std::io::Error,
+	},
+
+	/// A line of a file of recorded queries wasn't a recorded query
+	#[error("invalid recorded query on line {line} of query fixture '{}'", path.display())]
+	InvalidQueryFixture {
+		path: PathBuf,
+		line: usize,
+		#[source]
+		source: serde_json::Error,
+	},
+
 	#[error("session channel closed unexpectedly")]
 	SessionChannelClosed,
 
@@ -44,6 +85,10 @@ pub enum Error {
 	#[error("received additional message for ID '{id}' after query completion")]
 	MoreAfterQueryComplete { id: usize },
 
+	/// The `PluginEngine` received a query larger than the max payload size
+	#[error("query '{id}' is larger than the max of {max} bytes")]
+	QueryTooLarge { id: usize, max: usize },
+
 	#[error("failed to start server")]
 	FailedToStartServer(#[source] tonic::transport::Error),
 
@@ -62,6 +107,10 @@ pub enum Error {
 	#[error("invalid format for QueryTarget")]
 	InvalidQueryTargetFormat,
 
+	/// Hipcheck core cancelled the query, and no longer needs its answer
+	#[error("query was cancelled by Hipcheck core")]
+	QueryCancelled,
+
 	#[error(transparent)]
 	Unspecified { source: DynError },
 }
@@ -76,8 +125,14 @@ impl From<hipcheck_common::error::Error> for Error {
 			ReceivedSubmitWhenExpectingReplyChunk => Error::ReceivedSubmitWhenExpectingReplyChunk,
 			ReceivedReplyWhenExpectingSubmitChunk => Error::ReceivedReplyWhenExpectingRequest,
 			MoreAfterQueryComplete { id } => Error::MoreAfterQueryComplete { id },
+			QueryTooLarge { id, max } => Error::QueryTooLarge { id, max },
 			InvalidJsonInQueryKey(s) => Error::InvalidJsonInQueryKey(s),
 			InvalidJsonInQueryOutput(s) => Error::InvalidJsonInQueryOutput(s),
+			InvalidJsonInReportFragment(s) => Error::InvalidJsonInReportFragment(s),
+			InvalidJsonInConcern(s) => Error::InvalidJsonInConcern(s),
+			QueryFailed(message) => Error::Unspecified {
+				source: message.into(),
+			},
 		}
 	}
 }
@@ -150,6 +205,7 @@ impl From<ConfigError> for SetConfigurationResponse {
 			} => SetConfigurationResponse {
 				status: ConfigurationStatus::InvalidConfigurationValue as i32,
 				message: format!("invalid value '{value}' for '{field_name}', reason: '{reason}'"),
+				max_message_size: 0,
 			},
 			ConfigError::MissingRequiredConfig {
 				field_name,
@@ -169,6 +225,7 @@ impl From<ConfigError> for SetConfigurationResponse {
 
 					message
 				},
+				max_message_size: 0,
 			},
 			ConfigError::UnrecognizedConfig {
 				field_name,
@@ -187,10 +244,12 @@ impl From<ConfigError> for SetConfigurationResponse {
 
 					message
 				},
+				max_message_size: 0,
 			},
 			ConfigError::Unspecified { message } => SetConfigurationResponse {
 				status: ConfigurationStatus::Unspecified as i32,
 				message: format!("unknown error; {message}"),
+				max_message_size: 0,
 			},
 		}
 	}
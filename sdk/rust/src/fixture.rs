@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Recording the queries a plugin makes to other plugins during a real run of Hipcheck, so unit
+//! tests can replay them with `MockResponses`.
+//!
+//! A fixture file holds one recorded query per line, as a JSON object like
+//! `{"target":"mitre/git/commits","key":"...","output":[...]}`. Each query is written as soon as
+//! it's answered, since Hipcheck core kills plugins rather than letting them shut down when a run
+//! ends.
+
+use crate::{
+	error::{Error, Result},
+	JsonValue, QueryTarget,
+};
+use serde_json::json;
+use std::{collections::HashSet, fs::File, io::Write as _, path::PathBuf, sync::Mutex};
+
+/// Writes the answers to the queries a plugin makes into a fixture file.
+#[derive(Debug)]
+pub(crate) struct QueryRecorder {
+	path: PathBuf,
+	state: Mutex<RecorderState>,
+}
+
+#[derive(Debug)]
+struct RecorderState {
+	file: File,
+	/// The target and key of each query recorded so far
+	recorded: HashSet<(String, JsonValue)>,
+}
+
+impl QueryRecorder {
+	/// Start recording into a fixture file, replacing anything recorded there before.
+	pub(crate) fn create(path: PathBuf) -> Result<QueryRecorder> {
+		let file = File::create(&path).map_err(|source| Error::QueryFixtureIo {
+			path: path.clone(),
+			source,
+		})?;
+		Ok(QueryRecorder {
+			path,
+			state: Mutex::new(RecorderState {
+				file,
+				recorded: HashSet::new(),
+			}),
+		})
+	}
+
+	/// Record the outputs a target answered a batch of keys with. Queries which were already
+	/// recorded, as when several sessions ask the same thing, are only recorded once.
+	pub(crate) fn record(
+		&self,
+		target: &QueryTarget,
+		keys: &[JsonValue],
+		outputs: &[JsonValue],
+	) -> Result<()> {
+		let target = target_name(target);
+		let mut state = self.state.lock().unwrap();
+		for (key, output) in keys.iter().zip(outputs) {
+			if !state.recorded.insert((target.clone(), key.clone())) {
+				continue;
+			}
+			let line = json!({ "target": target, "key": key, "output": output });
+			writeln!(state.file, "{line}").map_err(|source| Error::QueryFixtureIo {
+				path: self.path.clone(),
+				source,
+			})?;
+		}
+		Ok(())
+	}
+}
+
+/// Name a target the way `QueryTarget` is parsed, as `"publisher/plugin[/query]"`.
+fn target_name(target: &QueryTarget) -> String {
+	match &target.query {
+		Some(query) => format!("{}/{}/{}", target.publisher, target.plugin, query),
+		None => format!("{}/{}", target.publisher, target.plugin),
+	}
+}
+
+/// Read the queries recorded in a fixture file, as their target, key, and output.
+#[cfg(feature = "mock_engine")]
+pub(crate) fn read_fixture(
+	path: &std::path::Path,
+) -> Result<Vec<(QueryTarget, JsonValue, JsonValue)>> {
+	#[derive(serde::Deserialize)]
+	struct RecordedQuery {
+		target: String,
+		key: JsonValue,
+		output: JsonValue,
+	}
+
+	let contents = std::fs::read_to_string(path).map_err(|source| Error::QueryFixtureIo {
+		path: path.to_owned(),
+		source,
+	})?;
+
+	let mut queries = Vec::new();
+	for (i, line) in contents.lines().enumerate() {
+		if line.trim().is_empty() {
+			continue;
+		}
+		let recorded: RecordedQuery =
+			serde_json::from_str(line).map_err(|source| Error::InvalidQueryFixture {
+				path: path.to_owned(),
+				line: i + 1,
+				source,
+			})?;
+		let target: QueryTarget = recorded.target.parse()?;
+		queries.push((target, recorded.key, recorded.output));
+	}
+	Ok(queries)
+}
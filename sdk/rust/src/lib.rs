@@ -34,6 +34,7 @@ pub use engine::PluginEngine;
 use schemars::schema::SchemaObject as JsonSchema;
 use serde_json::Value as JsonValue;
 pub use server::PluginServer;
+use std::path::PathBuf;
 use std::result::Result as StdResult;
 use std::str::FromStr;
 
@@ -47,8 +48,14 @@ pub mod macros {
 #[cfg(feature = "print-timings")]
 mod benchmarking;
 
+/// A cache plugins can keep what's expensive to compute in, like the responses of API calls or
+/// parsed manifests, so later runs of Hipcheck don't compute it again.
+pub mod cache;
+
 mod engine;
 pub mod error;
+mod fixture;
+mod logger;
 mod server;
 
 #[cfg(feature = "mock_engine")]
@@ -58,6 +65,13 @@ pub mod mock {
 	pub use crate::engine::MockResponses;
 }
 
+/// Concerns and sections plugins can add to Hipcheck's final report.
+pub mod report;
+
+/// The key and output types for plugins which resolve package specifiers into targets for
+/// Hipcheck core.
+pub mod resolver;
+
 /// The definitions of Hipcheck's analysis `Target` object and its sub-types for use in writing
 /// query endpoints.
 pub mod types;
@@ -65,9 +79,11 @@ pub mod types;
 /// A utility module containing everything needed to write a plugin, just write `use
 /// hipcheck_sdk::prelude::*`.
 pub mod prelude {
+	pub use crate::cache::PluginCache;
 	pub use crate::deps::*;
 	pub use crate::engine::PluginEngine;
 	pub use crate::error::{ConfigError, Error, Result};
+	pub use crate::report::{Concern, FragmentFormat, ReportFragment, Severity};
 	pub use crate::server::{PluginServer, QueryResult};
 	pub use crate::{DynQuery, NamedQuery, Plugin, Query, QuerySchema, QueryTarget};
 	// Re-export macros
@@ -88,6 +104,24 @@ pub mod deps {
 	pub use tonic::async_trait;
 }
 
+/// Get the directory Hipcheck core set aside for this plugin's temporary files.
+///
+/// Core creates a fresh directory for each plugin on every run and deletes it when the run ends,
+/// so plugins should write temporary files here instead of elsewhere on the system. Returns
+/// `None` if the plugin was not started by Hipcheck core.
+pub fn scratch_dir() -> Option<PathBuf> {
+	hipcheck_common::plugin_scratch::scratch_dir_from_env()
+}
+
+/// Get a secret, like an API token, which the user gave this plugin in their exec config.
+///
+/// Plugins should look up tokens here rather than reading their own environment variables, so
+/// users configure every plugin's secrets in one place. Returns `None` if the user didn't give
+/// this plugin a secret with that name.
+pub fn secret(name: &str) -> Option<String> {
+	hipcheck_common::plugin_secret::secret_from_env(name)
+}
+
 /// Identifies the target plugin and endpoint of a Hipcheck query.
 ///
 /// The `publisher` and `plugin` fields are necessary from Hipcheck core's perspective to identify
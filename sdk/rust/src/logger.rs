@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! A logger which forwards a plugin's log records to Hipcheck core.
+//!
+//! Records are written to standard error in the format Hipcheck core expects,
+//! so core can re-emit them tagged with the plugin's name. The level to log at
+//! is set by core when it spawns the plugin.
+
+use hipcheck_common::plugin_log::{format_line, level_from_env};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::io::Write;
+
+/// Logger writing records to standard error for Hipcheck core to collect.
+struct PluginLogger {
+	level: LevelFilter,
+}
+
+impl Log for PluginLogger {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		metadata.level() <= self.level
+	}
+
+	fn log(&self, record: &Record) {
+		if self.enabled(record.metadata()) {
+			let line = format_line(record.level(), record.target(), &record.args().to_string());
+			// If standard error is gone, there's nowhere left to report that.
+			let _ = writeln!(std::io::stderr().lock(), "{line}");
+		}
+	}
+
+	fn flush(&self) {
+		let _ = std::io::stderr().flush();
+	}
+}
+
+/// Install the plugin logger, unless the plugin has already installed its own.
+pub(crate) fn init() {
+	let level = level_from_env();
+
+	if log::set_boxed_logger(Box::new(PluginLogger { level })).is_ok() {
+		log::set_max_level(level);
+	}
+}
@@ -0,0 +1,181 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt::{self, Display, Formatter};
+
+/// A concern a plugin raises about the target, shown in the final Hipcheck report when its
+/// analysis fails.
+///
+/// Only the message is required. The rest let tools reading the report sort and deduplicate
+/// concerns without parsing the message, so plugins should fill in whatever they know. Record
+/// concerns from a query with `PluginEngine::record_concern`, which also takes a plain string
+/// as a concern with only a message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Concern {
+	/// What the concern is, for people reading the report.
+	pub message: String,
+	/// How serious the concern is.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub severity: Option<Severity>,
+	/// The file the concern is about, relative to the root of the repository.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub file: Option<String>,
+	/// The line of `file` the concern is about, counting from 1.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub line: Option<u64>,
+	/// The hash of the commit the concern is about.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub commit: Option<String>,
+	/// Anything else identifying what the concern is about, like e-mail addresses or package
+	/// names.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub identifiers: Vec<String>,
+}
+
+impl Concern {
+	/// Make a concern with only a message.
+	pub fn new(message: impl Into<String>) -> Self {
+		Concern {
+			message: message.into(),
+			severity: None,
+			file: None,
+			line: None,
+			commit: None,
+			identifiers: Vec::new(),
+		}
+	}
+
+	/// Set how serious the concern is.
+	pub fn with_severity(mut self, severity: Severity) -> Self {
+		self.severity = Some(severity);
+		self
+	}
+
+	/// Set the file the concern is about, relative to the root of the repository.
+	pub fn with_file(mut self, file: impl Into<String>) -> Self {
+		self.file = Some(file.into());
+		self
+	}
+
+	/// Set the line of the file the concern is about, counting from 1.
+	pub fn with_line(mut self, line: u64) -> Self {
+		self.line = Some(line);
+		self
+	}
+
+	/// Set the hash of the commit the concern is about.
+	pub fn with_commit(mut self, commit: impl Into<String>) -> Self {
+		self.commit = Some(commit.into());
+		self
+	}
+
+	/// Add something else identifying what the concern is about.
+	pub fn with_identifier(mut self, identifier: impl Into<String>) -> Self {
+		self.identifiers.push(identifier.into());
+		self
+	}
+}
+
+impl From<&str> for Concern {
+	fn from(message: &str) -> Self {
+		Concern::new(message)
+	}
+}
+
+impl From<String> for Concern {
+	fn from(message: String) -> Self {
+		Concern::new(message)
+	}
+}
+
+impl From<&String> for Concern {
+	fn from(message: &String) -> Self {
+		Concern::new(message.as_str())
+	}
+}
+
+impl From<Concern> for Value {
+	fn from(concern: Concern) -> Value {
+		// A struct of strings and numbers always serializes
+		json!(concern)
+	}
+}
+
+/// How serious a `Concern` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+	Low,
+	Medium,
+	High,
+	Critical,
+}
+
+impl Display for Severity {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let severity = match self {
+			Severity::Low => "low",
+			Severity::Medium => "medium",
+			Severity::High => "high",
+			Severity::Critical => "critical",
+		};
+		f.write_str(severity)
+	}
+}
+
+/// A section a plugin adds to the final Hipcheck report.
+///
+/// Fragments let a plugin present results which aren't a pass or fail, like an inventory of the
+/// licenses or a count of the dependencies in a package. Record them from a query with
+/// `PluginEngine::record_report_fragment`. Hipcheck shows every fragment from a plugin's default
+/// query, whether its analysis passed or failed, in the report's "Plugin Sections".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportFragment {
+	/// The heading of the section.
+	pub title: String,
+	/// How the body is written.
+	pub format: FragmentFormat,
+	/// The contents of the section. For `FragmentFormat::Markdown` this must be a string.
+	pub body: Value,
+}
+
+impl ReportFragment {
+	/// Make a fragment from Markdown text.
+	pub fn markdown(title: impl Into<String>, text: impl Into<String>) -> Self {
+		ReportFragment {
+			title: title.into(),
+			format: FragmentFormat::Markdown,
+			body: Value::String(text.into()),
+		}
+	}
+
+	/// Make a fragment from JSON data.
+	pub fn json(title: impl Into<String>, data: Value) -> Self {
+		ReportFragment {
+			title: title.into(),
+			format: FragmentFormat::Json,
+			body: data,
+		}
+	}
+}
+
+impl From<ReportFragment> for Value {
+	fn from(fragment: ReportFragment) -> Value {
+		json!({
+			"title": fragment.title,
+			"format": fragment.format,
+			"body": fragment.body,
+		})
+	}
+}
+
+/// How the body of a `ReportFragment` is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FragmentFormat {
+	/// Markdown text.
+	Markdown,
+	/// Arbitrary JSON data.
+	Json,
+}
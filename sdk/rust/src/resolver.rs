@@ -0,0 +1,27 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// The name of the query endpoint Hipcheck core calls on a target resolver plugin.
+pub const RESOLVE_QUERY: &str = "resolve";
+
+/// The key Hipcheck core passes to a target resolver plugin's `resolve` query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ResolverKey {
+	/// The package as the user wrote it on the command line, e.g. `numpy@1.26.4`.
+	pub specifier: String,
+	/// The ref the user asked to analyze with the `--ref` flag, if any.
+	pub refspec: Option<String>,
+}
+
+/// The output a target resolver plugin's `resolve` query must return.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ResolvedTarget {
+	/// The URL of the git repository containing the package's source.
+	pub url: Url,
+	/// The ref in that repository corresponding to the requested package version, if known.
+	#[serde(rename = "ref")]
+	pub refspec: Option<String>,
+}
@@ -3,8 +3,10 @@
 use crate::{
 	engine::HcSessionSocket,
 	error::{Error, Result},
+	fixture::QueryRecorder,
 	Plugin, QuerySchema,
 };
+use hipcheck_common::chunk::{negotiate_max_size, GRPC_MAX_SIZE_BYTES, GRPC_MIN_SIZE_BYTES};
 use hipcheck_common::proto::{
 	plugin_service_server::{PluginService, PluginServiceServer},
 	ConfigurationStatus, ExplainDefaultQueryRequest as ExplainDefaultQueryReq,
@@ -17,7 +19,14 @@ use hipcheck_common::proto::{
 	SetConfigurationRequest as SetConfigurationReq,
 	SetConfigurationResponse as SetConfigurationResp,
 };
-use std::{result::Result as StdResult, sync::Arc};
+use std::{
+	path::PathBuf,
+	result::Result as StdResult,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+};
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream as RecvStream;
 use tonic::{transport::Server, Code, Request as Req, Response as Resp, Status, Streaming};
@@ -27,19 +36,68 @@ use tonic::{transport::Server, Code, Request as Req, Response as Resp, Status, S
 /// This struct implements the underlying gRPC protocol that is not exposed to the plugin author.
 pub struct PluginServer<P> {
 	plugin: Arc<P>,
+	/// The largest query protocol message the plugin sends or accepts.
+	max_message_size: usize,
+	/// The max message size agreed with Hipcheck core during configuration.
+	negotiated_max_message_size: Arc<AtomicUsize>,
+	/// The fixture file to record the queries the plugin makes into, if any.
+	record_queries: Option<PathBuf>,
+	/// Records the queries the plugin makes, once the server is listening.
+	recorder: Option<Arc<QueryRecorder>>,
 }
 
 impl<P: Plugin> PluginServer<P> {
 	/// Create a new plugin server for the provided plugin.
+	///
+	/// This also installs a logger which forwards the plugin's log messages to
+	/// Hipcheck core, at the log level core requested, unless the plugin has
+	/// already installed a logger of its own.
 	pub fn register(plugin: P) -> PluginServer<P> {
+		crate::logger::init();
+
 		PluginServer {
 			plugin: Arc::new(plugin),
+			max_message_size: GRPC_MAX_SIZE_BYTES,
+			negotiated_max_message_size: Arc::new(AtomicUsize::new(GRPC_MAX_SIZE_BYTES)),
+			record_queries: None,
+			recorder: None,
 		}
 	}
 
+	/// Set the largest gRPC message, in bytes, the plugin sends or accepts when answering
+	/// queries. Defaults to 4 MB.
+	///
+	/// Hipcheck core and the plugin agree on the smaller of their two sizes when the plugin is
+	/// configured, and large queries and results are split into chunks of that size. Raising it
+	/// means fewer chunks for plugins with very large results, at the cost of more memory per
+	/// message on both sides.
+	pub fn max_message_size(mut self, bytes: usize) -> PluginServer<P> {
+		self.max_message_size = bytes.max(GRPC_MIN_SIZE_BYTES);
+		self
+	}
+
+	/// Record the queries the plugin makes to other plugins, and their answers, into a fixture
+	/// file, replacing anything recorded there before.
+	///
+	/// Run Hipcheck with a plugin set to record to capture what other plugins, like `mitre/git`,
+	/// answer for real targets. Unit tests can then replay those answers by loading the file with
+	/// `MockResponses::load`. Only queries which succeed are recorded. Since Hipcheck core starts
+	/// the plugin itself, this is usually only set when the plugin finds an environment variable
+	/// of its own choosing.
+	pub fn record_queries(mut self, path: impl Into<PathBuf>) -> PluginServer<P> {
+		self.record_queries = Some(path.into());
+		self
+	}
+
 	/// Run the plugin server on the provided port.
-	pub async fn listen(self, port: u16) -> Result<()> {
-		let service = PluginServiceServer::new(self);
+	pub async fn listen(mut self, port: u16) -> Result<()> {
+		if let Some(path) = self.record_queries.take() {
+			self.recorder = Some(Arc::new(QueryRecorder::create(path)?));
+		}
+		let max_message_size = self.max_message_size;
+		let service = PluginServiceServer::new(self)
+			.max_decoding_message_size(max_message_size)
+			.max_encoding_message_size(max_message_size);
 		let host = format!("127.0.0.1:{}", port).parse().unwrap();
 
 		Server::builder()
@@ -64,12 +122,18 @@ impl<P: Plugin> PluginService for PluginServer<P> {
 		&self,
 		req: Req<SetConfigurationReq>,
 	) -> QueryResult<Resp<SetConfigurationResp>> {
-		let config = serde_json::from_str(&req.into_inner().configuration)
+		let req = req.into_inner();
+		let config = serde_json::from_str(&req.configuration)
 			.map_err(|e| Status::from_error(Box::new(e)))?;
+		self.negotiated_max_message_size.store(
+			negotiate_max_size(self.max_message_size, req.max_message_size),
+			Ordering::Relaxed,
+		);
 		match self.plugin.set_config(config) {
 			Ok(_) => Ok(Resp::new(SetConfigurationResp {
 				status: ConfigurationStatus::None as i32,
 				message: "".to_owned(),
+				max_message_size: self.max_message_size as u64,
 			})),
 			Err(e) => Ok(Resp::new(e.into())),
 		}
@@ -171,9 +235,11 @@ impl<P: Plugin> PluginService for PluginServer<P> {
 		let (tx, out_rx) = mpsc::channel::<QueryResult<InitiateQueryProtocolResp>>(10);
 
 		let cloned_plugin = self.plugin.clone();
+		let max_message_size = self.negotiated_max_message_size.load(Ordering::Relaxed);
+		let recorder = self.recorder.clone();
 
 		tokio::spawn(async move {
-			let mut channel = HcSessionSocket::new(tx, rx);
+			let mut channel = HcSessionSocket::new(tx, rx, max_message_size, recorder);
 			if let Err(e) = channel.run(cloned_plugin).await {
 				panic!("Error: {e}");
 			}
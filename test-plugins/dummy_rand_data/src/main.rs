@@ -63,7 +63,7 @@ impl Plugin for RandDataPlugin {
 		Ok(Some("generate random data".to_owned()))
 	}
 
-	queries! {}
+	queries! { rand_data }
 }
 
 #[derive(Parser, Debug)]
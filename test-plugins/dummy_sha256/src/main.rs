@@ -32,7 +32,7 @@ impl Plugin for Sha256Plugin {
 		Ok(Some("calculate sha256 of provided array".to_owned()))
 	}
 
-	queries! {}
+	queries! { query_sha256 }
 }
 
 #[derive(Parser, Debug)]
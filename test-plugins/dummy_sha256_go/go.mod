@@ -0,0 +1,7 @@
+module github.com/mitre/hipcheck/test-plugins/dummy_sha256_go
+
+go 1.22
+
+require github.com/mitre/hipcheck/sdk/go v0.0.0
+
+replace github.com/mitre/hipcheck/sdk/go => ../../sdk/go
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// A Go counterpart of the dummy_sha256 test plugin, which takes an array of bytes and calculates
+// its sha256.
+package main
+
+import (
+	"context"
+	"crypto/sha256"
+	"encoding/json"
+
+	hipcheck "github.com/mitre/hipcheck/sdk/go"
+)
+
+// Bytes are taken and given as arrays of numbers, since encoding/json writes []byte as base64.
+func querySha256(_ context.Context, _ *hipcheck.PluginEngine, content []int) ([]int, error) {
+	data := make([]byte, len(content))
+	for i, b := range content {
+		data[i] = byte(b)
+	}
+	sum := sha256.Sum256(data)
+
+	digest := make([]int, len(sum))
+	for i, b := range sum {
+		digest[i] = int(b)
+	}
+	return digest, nil
+}
+
+type sha256Plugin struct{}
+
+func (sha256Plugin) Publisher() string { return "dummy" }
+
+func (sha256Plugin) Name() string { return "sha256" }
+
+func (sha256Plugin) SetConfig(json.RawMessage) error { return nil }
+
+func (sha256Plugin) DefaultPolicyExpr() (string, error) { return "", nil }
+
+func (sha256Plugin) ExplainDefaultQuery() (string, error) {
+	return "calculate sha256 of provided array", nil
+}
+
+func (sha256Plugin) Queries() []hipcheck.NamedQuery {
+	return []hipcheck.NamedQuery{hipcheck.NewDefaultQuery(querySha256)}
+}
+
+func main() {
+	hipcheck.Main(sha256Plugin{})
+}
@@ -0,0 +1,28 @@
+# SPDX-License-Identifier: Apache-2.0
+
+"""A Python counterpart of the `dummy_sha256` test plugin, which takes an array of bytes and
+calculates its sha256.
+"""
+
+import hashlib
+
+from hipcheck_sdk import Plugin, PluginEngine, main, query
+
+
+class Sha256Plugin(Plugin):
+    publisher = "dummy"
+    name = "sha256"
+
+    def set_config(self, config: dict) -> None:
+        pass
+
+    def explain_default_query(self) -> str:
+        return "calculate sha256 of provided array"
+
+    @query(default=True)
+    async def query_sha256(self, engine: PluginEngine, content: list[int]) -> list[int]:
+        return list(hashlib.sha256(bytes(content)).digest())
+
+
+if __name__ == "__main__":
+    main(Sha256Plugin())